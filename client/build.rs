@@ -1,4 +1,10 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("../proto/smart_memory.proto")?;
+    // Exported memories are written to files by the export and import commands
+    tonic_build::configure()
+        .type_attribute(
+            "smart_memory.ExportedMemory",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
+        .compile(&["../proto/smart_memory.proto"], &["../proto"])?;
     Ok(())
 }
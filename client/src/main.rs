@@ -1,12 +1,172 @@
-use anyhow::Result;
-use tonic::Request;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use tonic::transport::Channel;
+use tonic::{Code, Request, Response, Status};
+use tower::retry::{Policy, Retry};
+use tower::ServiceExt;
 
 mod proto {
     tonic::include_proto!("smart_memory");
 }
 
 use proto::smart_memory_mcp_client::SmartMemoryMcpClient;
-use proto::{ContextRequest, StoreRequest, SwitchModeRequest};
+use proto::{
+    ContextRequest, ContextResponse, ExportMemoriesRequest, ExportedMemory, ImportChunk,
+    StoreRequest, StoreResponse, SwitchModeRequest, SwitchModeResponse,
+};
+
+/// Memories sent in each chunk of an import
+const IMPORT_CHUNK_SIZE: usize = 100;
+
+/// Retries of a failed call when `--max-retries` is not given
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest delay between two retries
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Whether a call failing with `code` may succeed when sent again
+fn is_retryable(code: Code) -> bool {
+    matches!(code, Code::Unavailable | Code::DeadlineExceeded)
+}
+
+/// Retry policy doubling the delay after each failed attempt
+#[derive(Debug, Clone)]
+struct ExponentialBackoff {
+    /// Retries allowed after the first attempt
+    max_retries: u32,
+    /// Retries made so far
+    attempt: u32,
+}
+
+impl ExponentialBackoff {
+    fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            attempt: 0,
+        }
+    }
+
+    /// Delay before the next retry
+    fn delay(&self) -> Duration {
+        INITIAL_RETRY_DELAY
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(MAX_RETRY_DELAY)
+    }
+}
+
+impl<Req: Clone, Res> Policy<Req, Res, Status> for ExponentialBackoff {
+    type Future = Pin<Box<dyn Future<Output = Self> + Send>>;
+
+    fn retry(&self, _request: &Req, result: Result<&Res, &Status>) -> Option<Self::Future> {
+        let status = result.err()?;
+        if !is_retryable(status.code()) || self.attempt >= self.max_retries {
+            return None;
+        }
+
+        let delay = self.delay();
+        let next = Self {
+            attempt: self.attempt + 1,
+            ..self.clone()
+        };
+        println!(
+            "Retry {}/{} in {:?} after: {}",
+            next.attempt,
+            self.max_retries,
+            delay,
+            status.message()
+        );
+        Some(Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            next
+        }))
+    }
+
+    fn clone_request(&self, request: &Req) -> Option<Req> {
+        Some(request.clone())
+    }
+}
+
+/// Client retrying unary calls that fail with `UNAVAILABLE` or `DEADLINE_EXCEEDED`
+#[derive(Debug, Clone)]
+struct RetryingClient {
+    client: SmartMemoryMcpClient<Channel>,
+    policy: ExponentialBackoff,
+    /// Deadline of each call, set with `--timeout-ms`
+    timeout: Option<Duration>,
+}
+
+impl RetryingClient {
+    fn new(
+        client: SmartMemoryMcpClient<Channel>,
+        max_retries: u32,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            client,
+            policy: ExponentialBackoff::new(max_retries),
+            timeout,
+        }
+    }
+
+    /// Wrap `message` in a request carrying the per-call deadline
+    fn request<T>(&self, message: T) -> Request<T> {
+        with_timeout(message, self.timeout)
+    }
+
+    /// Send `message` through `call`, retrying transient failures with backoff
+    async fn call<Req, Res, F, Fut>(&self, message: Req, call: F) -> Result<Res, Status>
+    where
+        Req: Clone,
+        F: Fn(SmartMemoryMcpClient<Channel>, Request<Req>) -> Fut + Clone,
+        Fut: Future<Output = Result<Response<Res>, Status>>,
+    {
+        let client = self.client.clone();
+        let timeout = self.timeout;
+        let service = tower::service_fn(move |message: Req| {
+            call(client.clone(), with_timeout(message, timeout))
+        });
+        Retry::new(self.policy.clone(), service)
+            .oneshot(message)
+            .await
+            .map(Response::into_inner)
+    }
+
+    async fn store_memory(&self, request: StoreRequest) -> Result<StoreResponse, Status> {
+        self.call(request, |mut client, request| async move {
+            client.store_memory(request).await
+        })
+        .await
+    }
+
+    async fn get_context(&self, request: ContextRequest) -> Result<ContextResponse, Status> {
+        self.call(request, |mut client, request| async move {
+            client.get_context(request).await
+        })
+        .await
+    }
+
+    async fn switch_mode(&self, request: SwitchModeRequest) -> Result<SwitchModeResponse, Status> {
+        self.call(request, |mut client, request| async move {
+            client.switch_mode(request).await
+        })
+        .await
+    }
+}
+
+/// Wrap `message` in a request that fails with `DEADLINE_EXCEEDED` after `timeout`
+fn with_timeout<T>(message: T, timeout: Option<Duration>) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Some(timeout) = timeout {
+        request.set_timeout(timeout);
+    }
+    request
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -15,11 +175,30 @@ async fn main() -> Result<()> {
         .connect()
         .await?;
 
+    // `--max-retries <n>` and `--timeout-ms <ms>` apply to every command
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let max_retries = take_option(&mut args, "--max-retries")
+        .map(|retries| retries.parse())
+        .transpose()
+        .context("--max-retries must be a number")?
+        .unwrap_or(DEFAULT_MAX_RETRIES);
+    let timeout = take_option(&mut args, "--timeout-ms")
+        .map(|ms| ms.parse().map(Duration::from_millis))
+        .transpose()
+        .context("--timeout-ms must be a number")?;
+
     // Create a client
-    let mut client = SmartMemoryMcpClient::new(channel);
+    let mut client = RetryingClient::new(SmartMemoryMcpClient::new(channel), max_retries, timeout);
 
     println!("Connected to Smart Memory MCP server");
 
+    // `export <file>` and `import <file>` move memories between servers
+    match args.first().map(String::as_str) {
+        Some("export") => return export(&mut client, &args[1..]).await,
+        Some("import") => return import(&mut client, &args[1..]).await,
+        _ => {}
+    }
+
     // Store multiple memories with different content types
     let memories = vec![
         ("Example Rust code:\nfn main() {\n    println!(\"Hello, World!\");\n}", "text/rust", "code"),
@@ -34,15 +213,19 @@ async fn main() -> Result<()> {
         metadata.insert("mode".to_string(), mode.to_string());
         metadata.insert("project".to_string(), "smart-memory-mcp".to_string());
 
-        let store_request = Request::new(StoreRequest {
+        let store_request = StoreRequest {
             content: content.to_string(),
             content_type: content_type.to_string(),
             metadata,
             compress: true,
-        });
+            ..Default::default()
+        };
 
         let response = client.store_memory(store_request).await?;
-        println!("Stored memory ({}) with ID: {}", content_type, response.get_ref().memory_id);
+        println!(
+            "Stored memory ({}) with ID: {}",
+            content_type, response.memory_id
+        );
     }
 
     // Test GetContext with different modes
@@ -50,15 +233,15 @@ async fn main() -> Result<()> {
     
     println!("\nTesting context retrieval for different modes...");
     for mode in modes {
-        let context_request = Request::new(ContextRequest {
+        let context_request = ContextRequest {
             mode: mode.to_string(),
             max_tokens: 1000,
             relevance_threshold: 0.5,
-        });
+            ..Default::default()
+        };
 
         println!("\nRetrieving context for '{}' mode...", mode);
-        let response = client.get_context(context_request).await?;
-        let context = response.get_ref();
+        let context = client.get_context(context_request).await?;
         println!("Context for '{}' mode:", mode);
         println!("- Content length: {}", context.context.len());
         println!("- Token count: {}", context.token_count);
@@ -66,27 +249,49 @@ async fn main() -> Result<()> {
         println!("- Number of sources: {}", context.sources.len());
     }
 
+    // Stream a large context chunk by chunk instead of as one message
+    println!("\nStreaming context for 'code' mode...");
+    let stream_request = client.request(ContextRequest {
+        mode: "code".to_string(),
+        max_tokens: 100_000,
+        ..Default::default()
+    });
+    let mut stream = client
+        .client
+        .get_context_stream(stream_request)
+        .await?
+        .into_inner();
+    while let Some(chunk) = stream.message().await? {
+        println!(
+            "- Chunk {}: {} sources, {} bytes{}",
+            chunk.chunk_index,
+            chunk.sources.len(),
+            chunk.content.len(),
+            if chunk.is_last { " (last)" } else { "" }
+        );
+    }
+
     // Test mode switching
     println!("\nTesting mode switching...");
-    let switch_request = Request::new(SwitchModeRequest {
+    let switch_request = SwitchModeRequest {
         target_mode: "debug".to_string(),
         preserve_context: true,
-    });
+    };
 
     println!("Switching to debug mode...");
     let response = client.switch_mode(switch_request).await?;
-    println!("Mode switch response: {:?}", response.get_ref());
+    println!("Mode switch response: {:?}", response);
 
     // Verify the mode switch by getting context in the new mode
-    let context_request = Request::new(ContextRequest {
+    let context_request = ContextRequest {
         mode: "debug".to_string(),
         max_tokens: 1000,
         relevance_threshold: 0.5,
-    });
+        ..Default::default()
+    };
 
     println!("\nVerifying context after mode switch...");
-    let response = client.get_context(context_request).await?;
-    let context = response.get_ref();
+    let context = client.get_context(context_request).await?;
     println!("Context in debug mode after switch:");
     println!("- Content length: {}", context.context.len());
     println!("- Token count: {}", context.token_count);
@@ -96,3 +301,263 @@ async fn main() -> Result<()> {
     println!("\nAll tests completed successfully!");
     Ok(())
 }
+
+/// Encoding of an export file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Json,
+    Msgpack,
+}
+
+impl ExportFormat {
+    /// Use the `--format` option, or the file extension when it is not given
+    fn detect(option: Option<&str>, path: &str) -> Result<Self> {
+        match option {
+            Some("json") => Ok(Self::Json),
+            Some("msgpack") => Ok(Self::Msgpack),
+            Some(other) => bail!("Unknown format: {} (expected json or msgpack)", other),
+            None if path.ends_with(".msgpack") => Ok(Self::Msgpack),
+            None => Ok(Self::Json),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Msgpack => "msgpack",
+        }
+    }
+
+    fn encode(&self, memories: &[ExportedMemory]) -> Result<Vec<u8>> {
+        match self {
+            Self::Json => Ok(serde_json::to_vec_pretty(memories)?),
+            Self::Msgpack => Ok(rmp_serde::to_vec_named(memories)?),
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<ExportedMemory>> {
+        match self {
+            Self::Json => Ok(serde_json::from_slice(bytes)?),
+            Self::Msgpack => Ok(rmp_serde::from_slice(bytes)?),
+        }
+    }
+}
+
+/// Get the value following `--name` in the command arguments
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+/// Remove `--name <value>` from the command arguments, returning the value
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == name)?;
+    let value = args.get(index + 1).cloned();
+    args.drain(index..(index + 2).min(args.len()));
+    value
+}
+
+/// `export <file> [--categories a,b] [--mode mode] [--format json|msgpack]`
+async fn export(client: &mut RetryingClient, args: &[String]) -> Result<()> {
+    let path = args.first().ok_or_else(|| {
+        anyhow!("Usage: export <file> [--categories a,b] [--mode mode] [--format json|msgpack]")
+    })?;
+    let format = ExportFormat::detect(option(args, "--format"), path)?;
+    let categories = option(args, "--categories")
+        .map(|categories| categories.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+
+    let request = client.request(ExportMemoriesRequest {
+        categories,
+        mode_filter: option(args, "--mode").unwrap_or_default().to_string(),
+        format: format.as_str().to_string(),
+    });
+    let mut stream = client.client.export_memories(request).await?.into_inner();
+    let mut memories = Vec::new();
+    while let Some(chunk) = stream.message().await? {
+        memories.extend(chunk.memories);
+    }
+
+    std::fs::write(path, format.encode(&memories)?)
+        .with_context(|| format!("Failed to write {}", path))?;
+    println!("Exported {} memories to {}", memories.len(), path);
+    Ok(())
+}
+
+/// `import <file> [--overwrite] [--format json|msgpack]`
+async fn import(client: &mut RetryingClient, args: &[String]) -> Result<()> {
+    let path = args
+        .first()
+        .ok_or_else(|| anyhow!("Usage: import <file> [--overwrite] [--format json|msgpack]"))?;
+    let format = ExportFormat::detect(option(args, "--format"), path)?;
+    let overwrite = args.iter().any(|arg| arg == "--overwrite");
+
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path))?;
+    let memories = format.decode(&bytes)?;
+    let chunks: Vec<ImportChunk> = memories
+        .chunks(IMPORT_CHUNK_SIZE)
+        .map(|memories| ImportChunk {
+            memories: memories.to_vec(),
+            overwrite,
+        })
+        .collect();
+
+    let request = client.request(tokio_stream::iter(chunks));
+    let response = client.client.import_memories(request).await?.into_inner();
+    println!(
+        "Imported {} memories, skipped {}",
+        response.imported_count, response.skipped_count
+    );
+    for error in &response.errors {
+        println!("- {}", error);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context as TaskContext, Poll};
+    use tonic::codec::ProstCodec;
+    use tonic::codegen::{http, BoxFuture, Service, StdError};
+    use tonic::server::{Grpc, NamedService, UnaryService};
+
+    /// Server whose `StoreMemory` fails with `code` for the first `failures` calls
+    #[derive(Clone)]
+    struct FlakyServer {
+        code: Code,
+        failures: u32,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl NamedService for FlakyServer {
+        const NAME: &'static str = "smart_memory.SmartMemoryMcp";
+    }
+
+    impl UnaryService<StoreRequest> for FlakyServer {
+        type Response = StoreResponse;
+        type Future = BoxFuture<Response<StoreResponse>, Status>;
+
+        fn call(&mut self, _request: Request<StoreRequest>) -> Self::Future {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            let result = if call <= self.failures {
+                Err(Status::new(self.code, format!("failure {}", call)))
+            } else {
+                Ok(Response::new(StoreResponse {
+                    memory_id: format!("memory-{}", call),
+                    ..Default::default()
+                }))
+            };
+            Box::pin(async move { result })
+        }
+    }
+
+    impl<B> Service<http::Request<B>> for FlakyServer
+    where
+        B: tonic::codegen::Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<B>) -> Self::Future {
+            let server = self.clone();
+            Box::pin(async move {
+                let mut grpc = Grpc::new(ProstCodec::<StoreResponse, StoreRequest>::default());
+                Ok(grpc.unary(server, request).await)
+            })
+        }
+    }
+
+    /// Serve a [`FlakyServer`] on a free port and connect a client allowing `max_retries`
+    async fn flaky_client(
+        code: Code,
+        failures: u32,
+        max_retries: u32,
+    ) -> (RetryingClient, Arc<AtomicU32>) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let server = FlakyServer {
+            code,
+            failures,
+            calls: calls.clone(),
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(server)
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        let client = SmartMemoryMcpClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        (RetryingClient::new(client, max_retries, None), calls)
+    }
+
+    #[tokio::test]
+    async fn test_unavailable_is_retried_until_success() {
+        let (client, calls) = flaky_client(Code::Unavailable, 2, 3).await;
+
+        let response = client.store_memory(StoreRequest::default()).await.unwrap();
+        assert_eq!(response.memory_id, "memory-3");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_max_retries() {
+        let (client, calls) = flaky_client(Code::Unavailable, 2, 1).await;
+
+        let status = client
+            .store_memory(StoreRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_argument_is_not_retried() {
+        let (client, calls) = flaky_client(Code::InvalidArgument, 2, 3).await;
+
+        let status = client
+            .store_memory(StoreRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_up_to_max() {
+        let delays: Vec<Duration> = [0, 1, 2, 10]
+            .into_iter()
+            .map(|attempt| {
+                ExponentialBackoff {
+                    max_retries: 20,
+                    attempt,
+                }
+                .delay()
+            })
+            .collect();
+        assert_eq!(
+            delays,
+            [
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(400),
+                MAX_RETRY_DELAY,
+            ]
+        );
+    }
+}
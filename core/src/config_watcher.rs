@@ -0,0 +1,192 @@
+//! Hot reload of the memory bank config
+//!
+//! [`ConfigWatcher`] watches the config file and replaces the shared
+//! [`MemoryBankConfig`] whenever it changes, so edits apply without a restart.
+//! A file that fails to parse or to validate is logged and the previous config
+//! stays in place.
+
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::storage::MemoryBankConfig;
+
+/// Config shared between the service and the watcher
+pub type SharedConfig = Arc<RwLock<MemoryBankConfig>>;
+
+/// How long to wait for a burst of file events to settle before reloading
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Load the config at `path`, falling back to the defaults when it is missing or invalid
+pub fn load_or_default(path: &Path) -> MemoryBankConfig {
+    if !path.exists() {
+        crate::log_info!(
+            "config",
+            &format!("No config file at {}, using defaults", path.display())
+        );
+        return MemoryBankConfig::default();
+    }
+
+    match MemoryBankConfig::load(path) {
+        Ok(config) => {
+            crate::log_info!("config", &format!("Loaded config from {}", path.display()));
+            config
+        }
+        Err(e) => {
+            crate::log_warning!(
+                "config",
+                &format!("Failed to load config, using defaults: {:#}", e)
+            );
+            MemoryBankConfig::default()
+        }
+    }
+}
+
+/// Reloads a shared config whenever its file changes
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    /// Config file to watch
+    path: PathBuf,
+    /// Config replaced on every successful reload
+    config: SharedConfig,
+}
+
+impl ConfigWatcher {
+    /// Create a watcher that reloads `config` from the file at `path`
+    pub fn new(path: &Path, config: SharedConfig) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            config,
+        }
+    }
+
+    /// Re-read the config file, returning whether the config changed
+    ///
+    /// The shared config is left untouched when the file cannot be loaded or is invalid.
+    pub fn reload(&self) -> Result<bool> {
+        let config = MemoryBankConfig::load(&self.path)?;
+        crate::service::check_config(&config)?;
+
+        let mut current = self.config.write().unwrap();
+        if *current == config {
+            return Ok(false);
+        }
+        *current = config;
+        Ok(true)
+    }
+
+    /// Watch the config file in a background task
+    pub fn start(self) -> Result<JoinHandle<()>> {
+        // Watch the directory so editors that replace the file are still seen
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                let _ = sender.send(event);
+            }
+        })
+        .context("Failed to create config file watcher")?;
+        watcher
+            .watch(&directory, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", directory.display()))?;
+
+        Ok(tokio::spawn(async move {
+            // The watcher stops when dropped, so it lives as long as the task
+            let _watcher = watcher;
+
+            while let Some(event) = receiver.recv().await {
+                if !self.is_config_change(&event) {
+                    continue;
+                }
+
+                // Writes arrive as several events; reload once they settle
+                tokio::time::sleep(DEBOUNCE).await;
+                while receiver.try_recv().is_ok() {}
+
+                match self.reload() {
+                    Ok(true) => crate::log_info!(
+                        "config",
+                        &format!("Reloaded config from {}", self.path.display())
+                    ),
+                    Ok(false) => {}
+                    Err(e) => crate::log_warning!(
+                        "config",
+                        &format!("Keeping previous config, failed to reload: {:#}", e)
+                    ),
+                }
+            }
+        }))
+    }
+
+    /// Whether `event` modified or replaced the config file
+    fn is_config_change(&self, event: &Event) -> bool {
+        matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+            && event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == self.path.file_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Priority;
+
+    fn watcher_with_file() -> (tempfile::TempDir, ConfigWatcher) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        MemoryBankConfig::default().save(&path).unwrap();
+        let config = Arc::new(RwLock::new(load_or_default(&path)));
+        (dir, ConfigWatcher::new(&path, config))
+    }
+
+    #[test]
+    fn test_reload_replaces_config() {
+        let (_dir, watcher) = watcher_with_file();
+        assert!(!watcher.reload().unwrap());
+
+        let mut changed = MemoryBankConfig::default();
+        changed.categories.get_mut("context").unwrap().priority = Some(Priority::Low);
+        changed.save(&watcher.path).unwrap();
+
+        assert!(watcher.reload().unwrap());
+        assert_eq!(*watcher.config.read().unwrap(), changed);
+    }
+
+    #[test]
+    fn test_invalid_file_keeps_previous_config() {
+        let (_dir, watcher) = watcher_with_file();
+        std::fs::write(&watcher.path, "{ not json").unwrap();
+
+        assert!(watcher.reload().is_err());
+        assert_eq!(*watcher.config.read().unwrap(), MemoryBankConfig::default());
+    }
+
+    #[test]
+    fn test_config_with_errors_keeps_previous_config() {
+        let (_dir, watcher) = watcher_with_file();
+        let mut invalid = MemoryBankConfig::default();
+        invalid.relevance.threshold = 1.5;
+        invalid.save(&watcher.path).unwrap();
+
+        let error = watcher.reload().unwrap_err();
+        assert!(error.to_string().contains("Invalid memory bank config"));
+        assert_eq!(*watcher.config.read().unwrap(), MemoryBankConfig::default());
+    }
+
+    #[test]
+    fn test_missing_file_loads_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = load_or_default(&dir.path().join("config.toml"));
+        assert_eq!(config, MemoryBankConfig::default());
+    }
+}
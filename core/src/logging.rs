@@ -1,11 +1,95 @@
+//! Logging through `tracing`
+//!
+//! Events go to the console, to daily rotated text log files and optionally to
+//! JSON Lines files, each with its own level. Once the files of a log take more
+//! than its size cap, the oldest ones are deleted. Every event is also kept in
+//! memory for log queries and sent to log subscribers.
+
 use chrono::{DateTime, Local, Utc};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use std::fs::{self, File};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
 use std::io::Write;
-use std::path::Path;
-use std::sync::Mutex;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
 use std::time::SystemTime;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{Filtered, LevelFilter, Targets};
+use tracing_subscriber::fmt::format::{DefaultFields, Format, Json, JsonFields, Writer};
+use tracing_subscriber::fmt::writer::{BoxMakeWriter, MakeWriter};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{reload, Layer, Registry};
+use uuid::Uuid;
+
+/// Number of recent log entries kept in memory for log queries
+const LOG_BUFFER_CAPACITY: usize = 10_000;
+
+/// Number of entries a slow log subscriber may fall behind before dropping some
+const LOG_CHANNEL_CAPACITY: usize = 1024;
+
+/// Environment variable holding the path of the JSON Lines log
+pub const LOG_JSON_PATH_VAR: &str = "LOG_JSON_PATH";
+
+/// Environment variable holding the directory of the text log
+pub const LOG_DIR_VAR: &str = "LOG_DIR";
+
+/// Environment variable holding the console log level
+pub const CONSOLE_LOG_LEVEL_VAR: &str = "RUST_LOG";
+
+/// Environment variable holding the file log level
+pub const FILE_LOG_LEVEL_VAR: &str = "FILE_LOG_LEVEL";
+
+/// Environment variable enabling the JSON Lines log in the log directory
+pub const LOG_JSON_VAR: &str = "LOG_JSON";
+
+/// Environment variable holding the number of log files kept
+pub const LOG_MAX_FILES_VAR: &str = "LOG_MAX_FILES";
+
+/// Default number of log files kept
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// Environment variable holding the total size of the text log files, in MB
+pub const LOG_MAX_SIZE_MB_VAR: &str = "LOG_MAX_SIZE_MB";
+
+/// Environment variable holding the total size of the JSON Lines log files, in MB
+pub const LOG_JSON_MAX_SIZE_MB_VAR: &str = "LOG_JSON_MAX_SIZE_MB";
+
+/// Default total size of the text log files, in MB
+const DEFAULT_MAX_SIZE_MB: u64 = 50;
+
+/// Default total size of the JSON Lines log files, in MB
+const DEFAULT_JSON_MAX_SIZE_MB: u64 = 250;
+
+/// Fraction of a log's size cap written between two checks of its files
+const PRUNE_CHECKS_PER_CAP: u64 = 16;
+
+/// Name of the log files in the log directory, before the date
+const LOG_FILE_PREFIX: &str = "smart-memory-mcp";
+
+/// Extension of the text log files
+const TEXT_LOG_SUFFIX: &str = "log";
+
+/// Extension of the JSON Lines log files
+const JSON_LOG_SUFFIX: &str = "jsonl";
+
+/// Name of the log control socket in the data directory
+#[cfg(unix)]
+const LOG_CONTROL_SOCKET: &str = "log-control.sock";
+
+/// Name of the log control named pipe
+#[cfg(windows)]
+const LOG_CONTROL_PIPE: &str = r"\\.\pipe\smart-memory-log-control";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum LogLevel {
@@ -40,6 +124,105 @@ impl LogLevel {
             _ => None,
         }
     }
+
+    /// Read the level from the environment variable `var`
+    ///
+    /// Returns `default` when the variable is unset and `Info` when it is not a level.
+    pub fn from_env(var: &str, default: LogLevel) -> Self {
+        match std::env::var(var) {
+            Ok(level) => LogLevel::from_str(&level).unwrap_or(LogLevel::Info),
+            Err(_) => default,
+        }
+    }
+
+    /// Get the `tracing` level the level is logged at; `Critical` shares `ERROR`
+    pub fn as_tracing_level(&self) -> Level {
+        match self {
+            LogLevel::Trace => Level::TRACE,
+            LogLevel::Debug => Level::DEBUG,
+            LogLevel::Info => Level::INFO,
+            LogLevel::Warning => Level::WARN,
+            LogLevel::Error | LogLevel::Critical => Level::ERROR,
+        }
+    }
+
+    /// Get the level of an event at `level`, marked by a `critical` field or not
+    fn from_tracing_level(level: &Level, critical: bool) -> Self {
+        match *level {
+            Level::TRACE => LogLevel::Trace,
+            Level::DEBUG => LogLevel::Debug,
+            Level::INFO => LogLevel::Info,
+            Level::WARN => LogLevel::Warning,
+            _ if critical => LogLevel::Critical,
+            _ => LogLevel::Error,
+        }
+    }
+}
+
+/// Let through the service's own events from `level` up, and other crates' warnings and errors
+fn level_targets(level: LogLevel) -> Targets {
+    let level = LevelFilter::from_level(level.as_tracing_level());
+    Targets::new()
+        .with_target(env!("CARGO_CRATE_NAME"), level)
+        .with_default(level.min(LevelFilter::WARN))
+}
+
+/// Where and how verbosely the logger writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogConfig {
+    /// Directory of the log files, from `LOG_DIR` (default `$DATA_DIR/logs`)
+    pub log_dir: PathBuf,
+    /// Minimum level written to stderr, from `RUST_LOG` (default `Info`)
+    pub console_level: LogLevel,
+    /// Minimum level written to the log files, from `FILE_LOG_LEVEL` (default `Debug`)
+    pub file_level: LogLevel,
+    /// Also write a JSON Lines log to the log directory, from `LOG_JSON` (default off)
+    pub json_output: bool,
+    /// Number of daily files kept of each log, from `LOG_MAX_FILES` (default 5)
+    pub max_files: usize,
+    /// Total size in MB of the text log files, from `LOG_MAX_SIZE_MB` (default 50)
+    pub max_size_mb: u64,
+    /// Total size in MB of the JSON Lines log files, from `LOG_JSON_MAX_SIZE_MB` (default 250)
+    pub json_max_size_mb: u64,
+}
+
+impl LogConfig {
+    /// Read the config from the environment, using the default of every unset variable
+    pub fn from_env() -> Self {
+        Self {
+            log_dir: std::env::var_os(LOG_DIR_VAR)
+                .map(PathBuf::from)
+                .unwrap_or_else(default_log_dir),
+            console_level: LogLevel::from_env(CONSOLE_LOG_LEVEL_VAR, LogLevel::Info),
+            file_level: LogLevel::from_env(FILE_LOG_LEVEL_VAR, LogLevel::Debug),
+            json_output: std::env::var(LOG_JSON_VAR)
+                .is_ok_and(|json| matches!(json.to_lowercase().as_str(), "true" | "1")),
+            max_files: parse_env(LOG_MAX_FILES_VAR).unwrap_or(DEFAULT_MAX_FILES),
+            max_size_mb: parse_env(LOG_MAX_SIZE_MB_VAR).unwrap_or(DEFAULT_MAX_SIZE_MB),
+            json_max_size_mb: parse_env(LOG_JSON_MAX_SIZE_MB_VAR)
+                .unwrap_or(DEFAULT_JSON_MAX_SIZE_MB),
+        }
+    }
+}
+
+/// Parse the environment variable `var`, ignoring zero and unparsable values
+fn parse_env<T: std::str::FromStr + PartialEq + Default>(var: &str) -> Option<T> {
+    std::env::var(var)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|value| *value != T::default())
+}
+
+/// Get the log directory under `DATA_DIR`, defaulting to `~/.smart-memory/logs`
+pub fn default_log_dir() -> PathBuf {
+    let data_dir = std::env::var("DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".smart-memory")
+        });
+    data_dir.join("logs")
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +234,99 @@ pub struct LogEntry {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// Identifies the RPC call a log entry was written for
+///
+/// Passed to the `log_*!` macros in place of metadata, it adds `request_id`,
+/// `rpc` and `peer` fields so the entries of concurrent calls can be told apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    /// Unique ID of the call
+    pub request_id: String,
+    /// Name of the RPC
+    pub rpc: String,
+    /// Address of the client, when the transport knows it
+    pub peer: Option<SocketAddr>,
+}
+
+impl RequestContext {
+    /// Start the context of a new call to `rpc` from `peer`
+    pub fn new(rpc: &str, peer: Option<SocketAddr>) -> Self {
+        Self {
+            request_id: Uuid::new_v4().to_string(),
+            rpc: rpc.to_string(),
+            peer,
+        }
+    }
+
+    /// Metadata identifying the call, with the fields of `extra` added
+    pub fn with(&self, extra: serde_json::Value) -> serde_json::Value {
+        let mut metadata = self.into_log_metadata();
+        if let (Some(fields), serde_json::Value::Object(extra)) = (metadata.as_object_mut(), extra)
+        {
+            fields.extend(extra);
+        }
+        metadata
+    }
+}
+
+/// Values the `log_*!` macros accept as an entry's metadata
+pub trait LogMetadata {
+    fn into_log_metadata(self) -> serde_json::Value;
+}
+
+impl LogMetadata for serde_json::Value {
+    fn into_log_metadata(self) -> serde_json::Value {
+        self
+    }
+}
+
+impl LogMetadata for &RequestContext {
+    fn into_log_metadata(self) -> serde_json::Value {
+        serde_json::json!({
+            "request_id": self.request_id,
+            "rpc": self.rpc,
+            "peer": self.peer.map(|peer| peer.to_string()),
+        })
+    }
+}
+
+/// Filter applied to log entries by log queries and subscriptions
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Minimum level to include
+    pub min_level: Option<LogLevel>,
+    /// Only include entries from this module
+    pub module: Option<String>,
+    /// Only include entries logged at or after this time
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl LogFilter {
+    /// Check whether a log entry passes the filter
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if self.min_level.is_some_and(|level| entry.level < level) {
+            return false;
+        }
+
+        if self
+            .module
+            .as_ref()
+            .is_some_and(|module| &entry.module != module)
+        {
+            return false;
+        }
+
+        if let Some(since) = self.since {
+            match DateTime::parse_from_rfc3339(&entry.timestamp) {
+                Ok(timestamp) if timestamp >= since => {}
+                _ => return false,
+            }
+        }
+
+        true
+    }
+}
+
 impl LogEntry {
     pub fn new(
         level: LogLevel,
@@ -68,8 +344,22 @@ impl LogEntry {
         }
     }
 
-    pub fn to_json(&self) -> String {
-        serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string())
+    /// Build an entry from a `tracing` event, using its target when it has no `module` field
+    fn from_event(event: &Event<'_>) -> Self {
+        let mut fields = EntryFields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        Self::new(
+            LogLevel::from_tracing_level(metadata.level(), fields.critical),
+            fields.module.as_deref().unwrap_or(metadata.target()),
+            &fields.message,
+            fields.metadata,
+        )
+    }
+
+    /// Get the ID of the RPC call the entry was written for, if any
+    pub fn request_id(&self) -> Option<&str> {
+        self.metadata.as_ref()?.get("request_id")?.as_str()
     }
 
     pub fn to_formatted_string(&self) -> String {
@@ -78,249 +368,1070 @@ impl LogEntry {
             .unwrap_or_else(|_| Local::now());
 
         let time_str = local_time.format("%Y-%m-%d %H:%M:%S%.3f").to_string();
+        let request_str = self
+            .request_id()
+            .map(|request_id| format!(" [{}]", request_id))
+            .unwrap_or_default();
         let metadata_str = match &self.metadata {
             Some(data) => format!(" | {}", serde_json::to_string(data).unwrap_or_default()),
             None => String::new(),
         };
 
         format!(
-            "[{}] [{}] [{}] {}{}",
+            "[{}] [{}] [{}]{} {}{}",
             time_str,
             self.level.as_str(),
             self.module,
+            request_str,
             self.message,
             metadata_str
         )
     }
 }
 
-pub struct Logger {
-    log_file: Option<Mutex<File>>,
-    console_level: LogLevel,
-    file_level: LogLevel,
-    max_file_size: u64,
-    max_files: usize,
+/// Fields of an event that make up a log entry
+#[derive(Default)]
+struct EntryFields {
+    message: String,
+    module: Option<String>,
+    metadata: Option<serde_json::Value>,
+    critical: bool,
+}
+
+impl Visit for EntryFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            "module" => self.module = Some(value.to_string()),
+            "metadata" => self.metadata = serde_json::from_str(value).ok(),
+            _ => {}
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        if field.name() == "critical" {
+            self.critical = value;
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+/// Formats events like [`LogEntry::to_formatted_string`]
+struct EntryFormat;
+
+impl<S, N> FormatEvent<S, N> for EntryFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        writeln!(
+            writer,
+            "{}",
+            LogEntry::from_event(event).to_formatted_string()
+        )
+    }
+}
+
+/// Keeps every event for log queries and sends it to log subscribers
+struct CaptureLayer;
+
+impl<S: Subscriber> Layer<S> for CaptureLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let entry = LogEntry::from_event(event);
+        if let Ok(mut buffer) = LOG_BUFFER.lock() {
+            if buffer.len() == LOG_BUFFER_CAPACITY {
+                buffer.pop_front();
+            }
+            buffer.push_back(entry.clone());
+        }
+        let _ = LOG_SENDER.send(entry);
+    }
+}
+
+/// Output layer whose writer and level can be changed after the subscriber is installed
+type OutputLayer<N, E, W> =
+    Filtered<tracing_subscriber::fmt::Layer<Registry, N, E, W>, Targets, Registry>;
+
+/// Handle changing an [`OutputLayer`]
+type OutputHandle<N, E, W> = reload::Handle<OutputLayer<N, E, W>, Registry>;
+
+/// Handles to the outputs of the global subscriber
+struct LogOutputs {
+    console: OutputHandle<DefaultFields, EntryFormat, fn() -> std::io::Stderr>,
+    text: OutputHandle<DefaultFields, EntryFormat, BoxMakeWriter>,
+    json: OutputHandle<JsonFields, Format<Json>, BoxMakeWriter>,
+    /// Whether the JSON Lines log follows the file level rather than staying off
+    json_enabled: AtomicBool,
 }
 
 lazy_static! {
-    static ref LOGGER: Mutex<Logger> = Mutex::new(Logger::new());
+    static ref LOG_BUFFER: Mutex<VecDeque<LogEntry>> =
+        Mutex::new(VecDeque::with_capacity(LOG_BUFFER_CAPACITY));
+    static ref LOG_SENDER: broadcast::Sender<LogEntry> = broadcast::channel(LOG_CHANNEL_CAPACITY).0;
 }
 
-impl Logger {
-    pub fn new() -> Self {
-        Self {
-            log_file: None,
-            console_level: LogLevel::Info,
-            file_level: LogLevel::Debug,
-            max_file_size: 10 * 1024 * 1024, // 10 MB
-            max_files: 5,
+static LOG_OUTPUTS: OnceLock<LogOutputs> = OnceLock::new();
+
+impl LogOutputs {
+    /// Get the outputs, installing the global subscriber on first use
+    ///
+    /// Until logging is configured only the console writes, at `Info`.
+    fn get() -> &'static Self {
+        LOG_OUTPUTS.get_or_init(|| {
+            let off = || Targets::new().with_default(LevelFilter::OFF);
+            let (console, console_handle) = reload::Layer::new(
+                tracing_subscriber::fmt::layer()
+                    .event_format(EntryFormat)
+                    .with_writer(std::io::stderr as fn() -> std::io::Stderr)
+                    .with_filter(level_targets(LogLevel::Info)),
+            );
+            let (text, text_handle) = reload::Layer::new(
+                tracing_subscriber::fmt::layer()
+                    .event_format(EntryFormat)
+                    .with_ansi(false)
+                    .with_writer(BoxMakeWriter::new(std::io::sink))
+                    .with_filter(off()),
+            );
+            let (json, json_handle) = reload::Layer::new(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_ansi(false)
+                    .with_writer(BoxMakeWriter::new(std::io::sink))
+                    .with_filter(off()),
+            );
+            let capture = CaptureLayer.with_filter(level_targets(LogLevel::Trace));
+
+            let layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> =
+                vec![console.boxed(), text.boxed(), json.boxed(), capture.boxed()];
+            // Only the first subscriber installed in a process takes effect
+            let _ = tracing::subscriber::set_global_default(Registry::default().with(layers));
+
+            Self {
+                console: console_handle,
+                text: text_handle,
+                json: json_handle,
+                json_enabled: AtomicBool::new(false),
+            }
+        })
+    }
+
+    fn set_console_level(&self, level: LogLevel) -> std::io::Result<()> {
+        modify(&self.console, |layer| {
+            *layer.filter_mut() = level_targets(level)
+        })
+    }
+
+    /// Set the level of the text log, and of the JSON Lines log when it is written
+    fn set_file_level(&self, level: LogLevel) -> std::io::Result<()> {
+        modify(&self.text, |layer| {
+            *layer.filter_mut() = level_targets(level)
+        })?;
+        if self.json_enabled.load(Ordering::Acquire) {
+            modify(&self.json, |layer| {
+                *layer.filter_mut() = level_targets(level)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Change an output layer, rebuilding which events are enabled
+fn modify<N, E, W>(
+    handle: &OutputHandle<N, E, W>,
+    f: impl FnOnce(&mut OutputLayer<N, E, W>),
+) -> std::io::Result<()> {
+    handle.modify(f).map_err(std::io::Error::other)
+}
+
+/// Create an appender writing `directory/prefix.YYYY-MM-DD.suffix`
+///
+/// At most `max_files` files taking at most `max_size_mb` MB in total are
+/// kept. On Unix `prefix.latest.suffix` links to the current file.
+fn daily_appender(
+    directory: &Path,
+    prefix: &str,
+    suffix: &str,
+    max_files: usize,
+    max_size_mb: u64,
+) -> std::io::Result<SizeCappedAppender> {
+    let builder = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(prefix)
+        .filename_suffix(suffix)
+        .max_log_files(max_files);
+    #[cfg(unix)]
+    let builder = builder.latest_symlink(format!("{}.latest.{}", prefix, suffix));
+    let appender = SizeCappedAppender {
+        inner: builder.build(directory).map_err(std::io::Error::other)?,
+        directory: directory.to_path_buf(),
+        prefix: prefix.to_string(),
+        suffix: suffix.to_string(),
+        max_bytes: max_size_mb * 1024 * 1024,
+        unchecked_bytes: AtomicU64::new(0),
+    };
+    appender.prune();
+    Ok(appender)
+}
+
+/// Daily appender deleting the oldest files of its log once they exceed a total size
+///
+/// The file being written is never deleted, so a single day's file may still
+/// outgrow the cap.
+struct SizeCappedAppender {
+    inner: RollingFileAppender,
+    directory: PathBuf,
+    prefix: String,
+    suffix: String,
+    /// Total size the log's files may take
+    max_bytes: u64,
+    /// Bytes written since the files were last checked
+    unchecked_bytes: AtomicU64,
+}
+
+impl SizeCappedAppender {
+    /// Count `written` bytes, checking the files' size every so often
+    fn record(&self, written: usize) {
+        let unchecked = self
+            .unchecked_bytes
+            .fetch_add(written as u64, Ordering::Relaxed)
+            + written as u64;
+        if unchecked >= self.max_bytes / PRUNE_CHECKS_PER_CAP {
+            self.unchecked_bytes.store(0, Ordering::Relaxed);
+            self.prune();
         }
     }
 
+    /// Delete the oldest files of the log until they fit in `max_bytes`
+    fn prune(&self) {
+        // Logging a failure here would write to this very log, so it is ignored
+        let _ = prune_log_files(&self.directory, &self.prefix, &self.suffix, self.max_bytes);
+    }
+}
+
+impl<'a> MakeWriter<'a> for SizeCappedAppender {
+    type Writer = SizeCappedWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        SizeCappedWriter {
+            inner: self.inner.make_writer(),
+            appender: self,
+        }
+    }
+}
+
+/// Writer of one event to a [`SizeCappedAppender`]
+struct SizeCappedWriter<'a> {
+    inner: <RollingFileAppender as MakeWriter<'a>>::Writer,
+    appender: &'a SizeCappedAppender,
+}
+
+impl Write for SizeCappedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.appender.record(written);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Delete the oldest `prefix.*.suffix` files in `directory` until the rest take at most `max_bytes`
+///
+/// The newest file is always kept. Returns the paths deleted.
+fn prune_log_files(
+    directory: &Path,
+    prefix: &str,
+    suffix: &str,
+    max_bytes: u64,
+) -> std::io::Result<Vec<PathBuf>> {
+    let name_prefix = format!("{}.", prefix);
+    let name_suffix = format!(".{}", suffix);
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        // The latest-file link is a symlink, so it is skipped as well
+        if !name.starts_with(&name_prefix) || !name.ends_with(&name_suffix) {
+            continue;
+        }
+        let metadata = entry.path().symlink_metadata()?;
+        if metadata.is_file() {
+            files.push((name, entry.path(), metadata.len()));
+        }
+    }
+
+    // Dated names sort oldest first, so walk them newest first
+    files.sort();
+    let mut total = 0;
+    let mut deleted = Vec::new();
+    for (newer_files, (_, path, size)) in files.into_iter().rev().enumerate() {
+        total += size;
+        if newer_files > 0 && total > max_bytes {
+            fs::remove_file(&path)?;
+            deleted.push(path);
+        }
+    }
+    Ok(deleted)
+}
+
+/// Configures the global `tracing` subscriber
+pub struct Logger;
+
+impl Logger {
+    #[deprecated(note = "use `Logger::init_from_config`")]
     pub fn init(
         log_dir: &str,
         console_level: LogLevel,
         file_level: LogLevel,
     ) -> std::io::Result<()> {
-        let log_path = Path::new(log_dir);
+        Self::init_from_config(&LogConfig {
+            log_dir: PathBuf::from(log_dir),
+            console_level,
+            file_level,
+            json_output: false,
+            max_files: DEFAULT_MAX_FILES,
+            max_size_mb: DEFAULT_MAX_SIZE_MB,
+            json_max_size_mb: DEFAULT_JSON_MAX_SIZE_MB,
+        })
+    }
 
-        // Create log directory if it doesn't exist
-        if !log_path.exists() {
-            fs::create_dir_all(log_path)?;
-        }
+    /// Start writing the text log, and the JSON Lines log if configured
+    ///
+    /// `LOG_JSON_PATH` takes precedence over the JSON log in the log directory;
+    /// its file name supplies the prefix and extension of the daily files.
+    /// Calling this again replaces the previous configuration.
+    pub fn init_from_config(config: &LogConfig) -> std::io::Result<()> {
+        let outputs = LogOutputs::get();
+        fs::create_dir_all(&config.log_dir)?;
 
-        let log_file_path = log_path.join("smart-memory-mcp.log");
-        let file = File::options()
-            .create(true)
-            .append(true)
-            .open(&log_file_path)?;
+        let text = daily_appender(
+            &config.log_dir,
+            LOG_FILE_PREFIX,
+            TEXT_LOG_SUFFIX,
+            config.max_files,
+            config.max_size_mb,
+        )?;
+        modify(&outputs.text, |layer| {
+            *layer.inner_mut().writer_mut() = BoxMakeWriter::new(text);
+        })?;
 
-        let mut logger = LOGGER.lock().unwrap();
-        logger.log_file = Some(Mutex::new(file));
-        logger.console_level = console_level;
-        logger.file_level = file_level;
+        let json = match std::env::var_os(LOG_JSON_PATH_VAR) {
+            Some(path) => Some(json_path_appender(Path::new(&path), config)?),
+            None if config.json_output => Some(daily_appender(
+                &config.log_dir,
+                LOG_FILE_PREFIX,
+                JSON_LOG_SUFFIX,
+                config.max_files,
+                config.json_max_size_mb,
+            )?),
+            None => None,
+        };
+        outputs
+            .json_enabled
+            .store(json.is_some(), Ordering::Release);
+        modify(&outputs.json, |layer| match json {
+            Some(json) => *layer.inner_mut().writer_mut() = BoxMakeWriter::new(json),
+            None => *layer.filter_mut() = Targets::new().with_default(LevelFilter::OFF),
+        })?;
+
+        outputs.set_console_level(config.console_level)?;
+        outputs.set_file_level(config.file_level)?;
 
-        // Log initialization
         log(
             LogLevel::Info,
             "logging",
             &format!(
                 "Logging initialized. Console level: {}, File level: {}",
-                console_level.as_str(),
-                file_level.as_str()
+                config.console_level.as_str(),
+                config.file_level.as_str()
             ),
             None,
         );
 
         Ok(())
     }
+}
 
-    fn rotate_logs(&self, log_dir: &str) -> std::io::Result<()> {
-        let log_path = Path::new(log_dir);
-        let log_file_path = log_path.join("smart-memory-mcp.log");
-
-        // Check if log file exists and needs rotation
-        if let Ok(metadata) = fs::metadata(&log_file_path) {
-            if metadata.len() > self.max_file_size {
-                // Rotate existing log files
-                for i in (1..self.max_files).rev() {
-                    let old_path = log_path.join(format!("smart-memory-mcp.{}.log", i));
-                    let new_path = log_path.join(format!("smart-memory-mcp.{}.log", i + 1));
-
-                    if old_path.exists() {
-                        if i + 1 >= self.max_files {
-                            // Remove the oldest log file if we've reached the maximum
-                            fs::remove_file(&old_path)?;
-                        } else {
-                            // Rename the file to the next number
-                            fs::rename(&old_path, &new_path)?;
-                        }
-                    }
-                }
+/// Create the daily JSON Lines appender for the `LOG_JSON_PATH` file `path`
+fn json_path_appender(path: &Path, config: &LogConfig) -> std::io::Result<SizeCappedAppender> {
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let prefix = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(LOG_FILE_PREFIX);
+    let suffix = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or(JSON_LOG_SUFFIX);
+    fs::create_dir_all(directory)?;
+    daily_appender(
+        directory,
+        prefix,
+        suffix,
+        config.max_files,
+        config.json_max_size_mb,
+    )
+}
 
-                // Rename the current log file to .1
-                let backup_path = log_path.join("smart-memory-mcp.1.log");
-                fs::rename(&log_file_path, &backup_path)?;
-
-                // Create a new log file
-                let file = File::options()
-                    .create(true)
-                    .append(true)
-                    .open(&log_file_path)?;
-
-                let mut logger = LOGGER.lock().unwrap();
-                logger.log_file = Some(Mutex::new(file));
-
-                // Log rotation
-                log(
-                    LogLevel::Info,
-                    "logging",
-                    &format!(
-                        "Log file rotated. Previous log saved to {}",
-                        backup_path.display()
-                    ),
-                    None,
-                );
-            }
-        }
+/// Log a message at a level chosen at runtime
+///
+/// The `log_*!` macros are preferred when the level is known.
+pub fn log(level: LogLevel, module: &str, message: &str, metadata: Option<serde_json::Value>) {
+    let metadata = metadata.map(|metadata| metadata.to_string());
+    let metadata = metadata.as_deref();
+    match level {
+        LogLevel::Trace => tracing::trace!(module, metadata, "{}", message),
+        LogLevel::Debug => tracing::debug!(module, metadata, "{}", message),
+        LogLevel::Info => tracing::info!(module, metadata, "{}", message),
+        LogLevel::Warning => tracing::warn!(module, metadata, "{}", message),
+        LogLevel::Error => tracing::error!(module, metadata, "{}", message),
+        LogLevel::Critical => tracing::error!(module, metadata, critical = true, "{}", message),
+    }
+}
 
-        Ok(())
+/// Path the log level controller listens on for the data directory `data_dir`
+///
+/// This is a Unix domain socket in the data directory, or a named pipe on Windows.
+pub fn log_control_path(data_dir: &Path) -> PathBuf {
+    #[cfg(unix)]
+    {
+        data_dir.join(LOG_CONTROL_SOCKET)
+    }
+    #[cfg(windows)]
+    {
+        let _ = data_dir;
+        PathBuf::from(LOG_CONTROL_PIPE)
     }
+}
 
-    fn write_to_log(&self, entry: &LogEntry) -> std::io::Result<()> {
-        if let Some(file_mutex) = &self.log_file {
-            if entry.level >= self.file_level {
-                let mut file = file_mutex.lock().unwrap();
-                writeln!(file, "{}", entry.to_formatted_string())?;
-                file.flush()?;
-            }
+/// Apply a log control command such as `set_level console DEBUG`
+pub fn apply_control_command(command: &str) -> Result<(), String> {
+    let parts: Vec<&str> = command.split_whitespace().collect();
+    let (target, level) = match parts.as_slice() {
+        ["set_level", target, level] => (*target, *level),
+        _ => return Err(format!("Unknown command: {}", command.trim())),
+    };
+    let level = LogLevel::from_str(level).ok_or_else(|| format!("Unknown log level: {}", level))?;
+
+    let outputs = LogOutputs::get();
+    match target {
+        "console" => outputs.set_console_level(level),
+        "file" => outputs.set_file_level(level),
+        _ => return Err(format!("Unknown log target: {}", target)),
+    }
+    .map_err(|e| e.to_string())?;
+
+    log(
+        LogLevel::Info,
+        "logging",
+        &format!("{} log level set to {}", target, level.as_str()),
+        None,
+    );
+    Ok(())
+}
+
+/// Changes log levels on request from a local socket or named pipe
+///
+/// Each line received is a command for [`apply_control_command`] and is
+/// answered with `OK` or `ERROR <reason>`.
+#[derive(Debug)]
+pub struct LogLevelController {
+    /// Socket or pipe path to listen on
+    path: PathBuf,
+}
+
+impl LogLevelController {
+    /// Create a controller listening on `path`, usually from [`log_control_path`]
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
         }
+    }
 
-        if entry.level >= self.console_level {
-            eprintln!("{}", entry.to_formatted_string());
+    /// Listen for commands in a background task
+    #[cfg(unix)]
+    pub fn start(self) -> std::io::Result<JoinHandle<()>> {
+        // A socket left behind by a previous run would make binding fail
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
         }
+        let listener = tokio::net::UnixListener::bind(&self.path)?;
 
-        Ok(())
+        Ok(tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(handle_control_connection(stream));
+                    }
+                    Err(e) => log(
+                        LogLevel::Warning,
+                        "logging",
+                        &format!("Failed to accept log control connection: {}", e),
+                        None,
+                    ),
+                }
+            }
+        }))
+    }
+
+    /// Listen for commands in a background task
+    #[cfg(windows)]
+    pub fn start(self) -> std::io::Result<JoinHandle<()>> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        let pipe_name = self.path.into_os_string();
+        let mut server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(&pipe_name)?;
+
+        Ok(tokio::spawn(async move {
+            loop {
+                if let Err(e) = server.connect().await {
+                    log(
+                        LogLevel::Warning,
+                        "logging",
+                        &format!("Failed to accept log control connection: {}", e),
+                        None,
+                    );
+                    continue;
+                }
+
+                // Create the next instance before serving this client
+                let connected = server;
+                server = match ServerOptions::new().create(&pipe_name) {
+                    Ok(server) => server,
+                    Err(e) => {
+                        log(
+                            LogLevel::Error,
+                            "logging",
+                            &format!("Failed to create log control pipe: {}", e),
+                            None,
+                        );
+                        return;
+                    }
+                };
+                tokio::spawn(handle_control_connection(connected));
+            }
+        }))
     }
 }
 
-pub fn log(level: LogLevel, module: &str, message: &str, metadata: Option<serde_json::Value>) {
-    let entry = LogEntry::new(level, module, message, metadata);
+/// Answer each command line received on a log control connection
+async fn handle_control_connection<S: AsyncRead + AsyncWrite>(stream: S) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
 
-    if let Ok(logger) = LOGGER.lock() {
-        if let Err(e) = logger.write_to_log(&entry) {
-            eprintln!("Failed to write to log: {}", e);
+    while let Ok(Some(line)) = lines.next_line().await {
+        let reply = match apply_control_command(&line) {
+            Ok(()) => "OK\n".to_string(),
+            Err(e) => format!("ERROR {}\n", e),
+        };
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            break;
         }
     }
 }
 
-// Convenience macros for logging
+/// Get the most recent `limit` buffered log entries that pass the filter, oldest first
+pub fn recent_logs(filter: &LogFilter, limit: usize) -> Vec<LogEntry> {
+    let buffer = match LOG_BUFFER.lock() {
+        Ok(buffer) => buffer,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut entries: Vec<LogEntry> = buffer
+        .iter()
+        .rev()
+        .filter(|entry| filter.matches(entry))
+        .take(limit)
+        .cloned()
+        .collect();
+    entries.reverse();
+    entries
+}
+
+/// Subscribe to log entries as they are written
+pub fn subscribe() -> broadcast::Receiver<LogEntry> {
+    LOG_SENDER.subscribe()
+}
+
+// Convenience macros for logging; each emits a `tracing` event with `module`
+// and, when given, JSON `metadata` fields taken from a `serde_json::Value` or
+// a `&RequestContext`
 #[macro_export]
 macro_rules! log_trace {
     ($module:expr, $message:expr) => {
-        $crate::logging::log($crate::logging::LogLevel::Trace, $module, $message, None)
+        ::tracing::trace!(module = $module, "{}", $message)
     };
     ($module:expr, $message:expr, $metadata:expr) => {
-        $crate::logging::log(
-            $crate::logging::LogLevel::Trace,
-            $module,
-            $message,
-            Some($metadata),
-        )
+        ::tracing::trace!(module = $module, metadata = %$crate::logging::LogMetadata::into_log_metadata($metadata), "{}", $message)
     };
 }
 
 #[macro_export]
 macro_rules! log_debug {
     ($module:expr, $message:expr) => {
-        $crate::logging::log($crate::logging::LogLevel::Debug, $module, $message, None)
+        ::tracing::debug!(module = $module, "{}", $message)
     };
     ($module:expr, $message:expr, $metadata:expr) => {
-        $crate::logging::log(
-            $crate::logging::LogLevel::Debug,
-            $module,
-            $message,
-            Some($metadata),
-        )
+        ::tracing::debug!(module = $module, metadata = %$crate::logging::LogMetadata::into_log_metadata($metadata), "{}", $message)
     };
 }
 
 #[macro_export]
 macro_rules! log_info {
     ($module:expr, $message:expr) => {
-        $crate::logging::log($crate::logging::LogLevel::Info, $module, $message, None)
+        ::tracing::info!(module = $module, "{}", $message)
     };
     ($module:expr, $message:expr, $metadata:expr) => {
-        $crate::logging::log(
-            $crate::logging::LogLevel::Info,
-            $module,
-            $message,
-            Some($metadata),
-        )
+        ::tracing::info!(module = $module, metadata = %$crate::logging::LogMetadata::into_log_metadata($metadata), "{}", $message)
     };
 }
 
 #[macro_export]
 macro_rules! log_warning {
     ($module:expr, $message:expr) => {
-        $crate::logging::log($crate::logging::LogLevel::Warning, $module, $message, None)
+        ::tracing::warn!(module = $module, "{}", $message)
     };
     ($module:expr, $message:expr, $metadata:expr) => {
-        $crate::logging::log(
-            $crate::logging::LogLevel::Warning,
-            $module,
-            $message,
-            Some($metadata),
-        )
+        ::tracing::warn!(module = $module, metadata = %$crate::logging::LogMetadata::into_log_metadata($metadata), "{}", $message)
     };
 }
 
 #[macro_export]
 macro_rules! log_error {
     ($module:expr, $message:expr) => {
-        $crate::logging::log($crate::logging::LogLevel::Error, $module, $message, None)
+        ::tracing::error!(module = $module, "{}", $message)
     };
     ($module:expr, $message:expr, $metadata:expr) => {
-        $crate::logging::log(
-            $crate::logging::LogLevel::Error,
-            $module,
-            $message,
-            Some($metadata),
-        )
+        ::tracing::error!(module = $module, metadata = %$crate::logging::LogMetadata::into_log_metadata($metadata), "{}", $message)
     };
 }
 
 #[macro_export]
 macro_rules! log_critical {
     ($module:expr, $message:expr) => {
-        $crate::logging::log($crate::logging::LogLevel::Critical, $module, $message, None)
+        ::tracing::error!(module = $module, critical = true, "{}", $message)
     };
     ($module:expr, $message:expr, $metadata:expr) => {
-        $crate::logging::log(
-            $crate::logging::LogLevel::Critical,
-            $module,
-            $message,
-            Some($metadata),
-        )
+        ::tracing::error!(module = $module, metadata = %$crate::logging::LogMetadata::into_log_metadata($metadata), critical = true, "{}", $message)
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serializes the tests that reconfigure the global logger or read its environment
+    static LOGGER_CONFIG: Mutex<()> = Mutex::new(());
+
+    /// Variables read by [`LogConfig::from_env`]
+    const LOG_CONFIG_VARS: [&str; 7] = [
+        LOG_DIR_VAR,
+        CONSOLE_LOG_LEVEL_VAR,
+        FILE_LOG_LEVEL_VAR,
+        LOG_JSON_VAR,
+        LOG_MAX_FILES_VAR,
+        LOG_MAX_SIZE_MB_VAR,
+        LOG_JSON_MAX_SIZE_MB_VAR,
+    ];
+
+    /// Config writing to `dir` with the default number of files and no JSON log
+    fn config(dir: &Path, console_level: LogLevel, file_level: LogLevel) -> LogConfig {
+        LogConfig {
+            log_dir: dir.to_path_buf(),
+            console_level,
+            file_level,
+            json_output: false,
+            max_files: DEFAULT_MAX_FILES,
+            max_size_mb: DEFAULT_MAX_SIZE_MB,
+            json_max_size_mb: DEFAULT_JSON_MAX_SIZE_MB,
+        }
+    }
+
+    fn clear_log_config_vars() {
+        for var in LOG_CONFIG_VARS {
+            std::env::remove_var(var);
+        }
+    }
+
+    /// Read every log file in `dir` with the extension `suffix`, skipping the latest-file links
+    fn read_logs(dir: &Path, suffix: &str) -> String {
+        let mut contents = String::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if entry.file_type().unwrap().is_file() && name.ends_with(&format!(".{}", suffix)) {
+                contents.push_str(&fs::read_to_string(entry.path()).unwrap());
+            }
+        }
+        contents
+    }
+
+    #[test]
+    fn test_log_config_defaults() {
+        let _guard = LOGGER_CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+        clear_log_config_vars();
+
+        assert_eq!(
+            LogConfig::from_env(),
+            LogConfig {
+                log_dir: default_log_dir(),
+                console_level: LogLevel::Info,
+                file_level: LogLevel::Debug,
+                json_output: false,
+                max_files: 5,
+                max_size_mb: 50,
+                json_max_size_mb: 250,
+            }
+        );
+    }
+
+    #[test]
+    fn test_log_config_invalid_values() {
+        let _guard = LOGGER_CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+        clear_log_config_vars();
+        std::env::set_var(CONSOLE_LOG_LEVEL_VAR, "verbose");
+        std::env::set_var(FILE_LOG_LEVEL_VAR, "smart_memory=debug");
+        std::env::set_var(LOG_JSON_VAR, "yes please");
+        std::env::set_var(LOG_MAX_FILES_VAR, "0");
+        std::env::set_var(LOG_MAX_SIZE_MB_VAR, "large");
+        std::env::set_var(LOG_JSON_MAX_SIZE_MB_VAR, "0");
+
+        let config = LogConfig::from_env();
+        clear_log_config_vars();
+        assert_eq!(config.console_level, LogLevel::Info);
+        assert_eq!(config.file_level, LogLevel::Info);
+        assert!(!config.json_output);
+        assert_eq!(config.max_files, DEFAULT_MAX_FILES);
+        assert_eq!(config.max_size_mb, DEFAULT_MAX_SIZE_MB);
+        assert_eq!(config.json_max_size_mb, DEFAULT_JSON_MAX_SIZE_MB);
+    }
+
+    #[test]
+    fn test_log_config_from_present_vars() {
+        let _guard = LOGGER_CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+        let values = [
+            "/tmp/smart-memory-logs",
+            "trace",
+            "error",
+            "true",
+            "9",
+            "20",
+            "80",
+        ];
+
+        // Every subset of the variables is set, the rest keep their defaults
+        for mask in 0..1u32 << LOG_CONFIG_VARS.len() {
+            let present = |index: usize| mask & (1 << index) != 0;
+            clear_log_config_vars();
+            for (index, (var, value)) in LOG_CONFIG_VARS.iter().zip(values).enumerate() {
+                if present(index) {
+                    std::env::set_var(var, value);
+                }
+            }
+
+            let config = LogConfig::from_env();
+            let expected = LogConfig {
+                log_dir: if present(0) {
+                    PathBuf::from(values[0])
+                } else {
+                    default_log_dir()
+                },
+                console_level: if present(1) {
+                    LogLevel::Trace
+                } else {
+                    LogLevel::Info
+                },
+                file_level: if present(2) {
+                    LogLevel::Error
+                } else {
+                    LogLevel::Debug
+                },
+                json_output: present(3),
+                max_files: if present(4) { 9 } else { 5 },
+                max_size_mb: if present(5) { 20 } else { 50 },
+                json_max_size_mb: if present(6) { 80 } else { 250 },
+            };
+            assert_eq!(config, expected, "variables set: {:07b}", mask);
+        }
+        clear_log_config_vars();
+    }
+
+    #[test]
+    fn test_prune_log_files_keeps_newest_within_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, size: usize| fs::write(dir.path().join(name), vec![b'x'; size]);
+        write("smart-memory-mcp.2026-01-01.log", 400).unwrap();
+        write("smart-memory-mcp.2026-01-02.log", 400).unwrap();
+        write("smart-memory-mcp.2026-01-03.log", 400).unwrap();
+        write("smart-memory-mcp.2026-01-01.jsonl", 4000).unwrap();
+        write("other.2026-01-01.log", 4000).unwrap();
+
+        let deleted = prune_log_files(dir.path(), "smart-memory-mcp", "log", 1000).unwrap();
+        assert_eq!(
+            deleted,
+            vec![dir.path().join("smart-memory-mcp.2026-01-01.log")]
+        );
+        assert!(dir.path().join("smart-memory-mcp.2026-01-02.log").exists());
+        assert!(dir
+            .path()
+            .join("smart-memory-mcp.2026-01-01.jsonl")
+            .exists());
+        assert!(dir.path().join("other.2026-01-01.log").exists());
+
+        // The file being written survives even when it alone exceeds the cap
+        let deleted = prune_log_files(dir.path(), "smart-memory-mcp", "log", 100).unwrap();
+        assert_eq!(
+            deleted,
+            vec![dir.path().join("smart-memory-mcp.2026-01-02.log")]
+        );
+        assert!(dir.path().join("smart-memory-mcp.2026-01-03.log").exists());
+    }
+
+    #[test]
+    fn test_json_output_in_log_dir() {
+        let _guard = LOGGER_CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        std::env::remove_var(LOG_JSON_PATH_VAR);
+        Logger::init_from_config(&LogConfig {
+            json_output: true,
+            ..config(dir.path(), LogLevel::Critical, LogLevel::Info)
+        })
+        .unwrap();
+
+        log(
+            LogLevel::Warning,
+            "json_output_test",
+            "in the log dir",
+            None,
+        );
+        assert!(read_logs(dir.path(), JSON_LOG_SUFFIX).contains("in the log dir"));
+        #[cfg(unix)]
+        assert!(dir.path().join("smart-memory-mcp.latest.jsonl").exists());
+    }
+
+    #[test]
+    fn test_recent_logs_filters_entries() {
+        LogOutputs::get();
+        log(LogLevel::Info, "logging_test", "first", None);
+        log(LogLevel::Error, "logging_test", "second", None);
+        log(LogLevel::Error, "logging_test", "third", None);
+        log(LogLevel::Error, "other_module", "ignored", None);
+
+        let filter = LogFilter {
+            min_level: Some(LogLevel::Error),
+            module: Some("logging_test".to_string()),
+            since: None,
+        };
+        let messages: Vec<_> = recent_logs(&filter, 10)
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect();
+        assert_eq!(messages, vec!["second", "third"]);
+
+        let latest = recent_logs(&filter, 1);
+        assert_eq!(latest[0].message, "third");
+
+        let future = LogFilter {
+            since: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..LogFilter::default()
+        };
+        assert!(recent_logs(&future, 10).is_empty());
+    }
+
+    #[test]
+    fn test_macros_emit_structured_fields() {
+        LogOutputs::get();
+        log_warning!(
+            "macro_test",
+            &format!("retry {}", 2),
+            serde_json::json!({ "attempt": 2 })
+        );
+        log_critical!("macro_test", "out of disk");
+
+        let filter = LogFilter {
+            module: Some("macro_test".to_string()),
+            ..LogFilter::default()
+        };
+        let entries = recent_logs(&filter, 10);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].level, LogLevel::Warning);
+        assert_eq!(entries[0].message, "retry 2");
+        assert_eq!(
+            entries[0].metadata,
+            Some(serde_json::json!({ "attempt": 2 }))
+        );
+        assert_eq!(entries[1].level, LogLevel::Critical);
+        assert_eq!(entries[1].metadata, None);
+    }
+
+    #[tokio::test]
+    async fn test_request_ids_separate_concurrent_calls() {
+        use crate::proto::smart_memory_mcp_server::SmartMemoryMcp;
+        use crate::proto::StoreRequest;
+        use tonic::transport::server::TcpConnectInfo;
+
+        LogOutputs::get();
+        let service = crate::service::SmartMemoryService::new().unwrap();
+        // Each call comes from its own peer, so its entries can be found among other tests'
+        let peers: [SocketAddr; 2] = [
+            "127.0.0.1:40001".parse().unwrap(),
+            "127.0.0.1:40002".parse().unwrap(),
+        ];
+        let store = |peer: SocketAddr| {
+            let mut request = tonic::Request::new(StoreRequest {
+                content: format!("Stored from {}", peer),
+                ..Default::default()
+            });
+            request.extensions_mut().insert(TcpConnectInfo {
+                local_addr: None,
+                remote_addr: Some(peer),
+            });
+            service.store_memory(request)
+        };
+        let (first, second) = tokio::join!(store(peers[0]), store(peers[1]));
+        first.unwrap();
+        second.unwrap();
+
+        let entries = recent_logs(&LogFilter::default(), LOG_BUFFER_CAPACITY);
+        let request_ids: Vec<String> = peers
+            .iter()
+            .map(|peer| {
+                let calls: Vec<&LogEntry> = entries
+                    .iter()
+                    .filter(|entry| {
+                        entry.metadata.as_ref().and_then(|m| m.get("peer"))
+                            == Some(&serde_json::json!(peer.to_string()))
+                    })
+                    .collect();
+                let messages: Vec<&str> =
+                    calls.iter().map(|entry| entry.message.as_str()).collect();
+                assert_eq!(messages.first(), Some(&"store_memory started"));
+                assert!(messages
+                    .last()
+                    .unwrap()
+                    .starts_with("store_memory finished in "));
+
+                // Every entry of the call carries its request ID, in the formatted line too
+                let request_id = calls[0].request_id().unwrap().to_string();
+                for entry in calls {
+                    assert_eq!(entry.request_id(), Some(request_id.as_str()));
+                    assert_eq!(entry.metadata.as_ref().unwrap()["rpc"], "store_memory");
+                    assert!(entry
+                        .to_formatted_string()
+                        .contains(&format!("[memory_service] [{}] ", request_id)));
+                }
+                request_id
+            })
+            .collect();
+        assert_ne!(request_ids[0], request_ids[1]);
+    }
+
+    #[test]
+    fn test_request_context_metadata() {
+        let peer = "10.0.0.7:5123".parse().unwrap();
+        let context = RequestContext::new("get_context", Some(peer));
+        assert_eq!(
+            context.with(serde_json::json!({ "mode": "code" })),
+            serde_json::json!({
+                "request_id": context.request_id,
+                "rpc": "get_context",
+                "peer": "10.0.0.7:5123",
+                "mode": "code",
+            })
+        );
+        assert_ne!(
+            RequestContext::new("get_context", None).request_id,
+            context.request_id
+        );
+    }
+
+    #[test]
+    fn test_json_lines_log() {
+        let _guard = LOGGER_CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let json_dir = dir.path().join("json");
+        std::env::set_var(LOG_JSON_PATH_VAR, json_dir.join("events.jsonl"));
+        let initialized =
+            Logger::init_from_config(&config(dir.path(), LogLevel::Critical, LogLevel::Debug));
+        std::env::remove_var(LOG_JSON_PATH_VAR);
+        initialized.unwrap();
+
+        log(LogLevel::Trace, "json_log_test", "too verbose", None);
+        log(LogLevel::Debug, "json_log_test", "debug entry", None);
+        log(
+            LogLevel::Warning,
+            "json_log_test",
+            "warning entry",
+            Some(serde_json::json!({ "attempt": 2 })),
+        );
+        log(LogLevel::Error, "json_log_test", "error entry", None);
+
+        // Other tests may log concurrently, so only look at this test's entries
+        let contents = read_logs(&json_dir, JSON_LOG_SUFFIX);
+        let entries: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("every line is a JSON object"))
+            .filter(|entry: &serde_json::Value| entry["module"] == "json_log_test")
+            .collect();
+
+        let levels: Vec<_> = entries.iter().map(|entry| &entry["level"]).collect();
+        assert_eq!(levels, vec!["DEBUG", "WARN", "ERROR"]);
+        assert_eq!(entries[1]["message"], "warning entry");
+        assert_eq!(entries[1]["metadata"], r#"{"attempt":2}"#);
+        let timestamp = entries[0]["timestamp"].as_str().unwrap();
+        assert!(DateTime::parse_from_rfc3339(timestamp).is_ok());
+
+        // The text log is still written
+        let text = read_logs(dir.path(), TEXT_LOG_SUFFIX);
+        assert!(text.contains("[ERROR] [json_log_test] error entry"));
+        assert!(!text.contains("too verbose"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_log_level_controller_changes_file_level() {
+        let _guard = LOGGER_CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        std::env::remove_var(LOG_JSON_PATH_VAR);
+        Logger::init_from_config(&config(dir.path(), LogLevel::Critical, LogLevel::Info)).unwrap();
+
+        // The controller runs on the runtime's workers while the test blocks on the client
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let path = log_control_path(dir.path());
+        {
+            let _enter = runtime.enter();
+            LogLevelController::new(&path).start().unwrap();
+        }
+        let set_file_level =
+            |level: &str| crate::server_manager::set_log_level_at(&path, level, "file");
+
+        log(LogLevel::Trace, "log_control_test", "hidden before", None);
+        set_file_level("TRACE").unwrap();
+        log(LogLevel::Trace, "log_control_test", "visible", None);
+        set_file_level("info").unwrap();
+        log(LogLevel::Trace, "log_control_test", "hidden after", None);
+
+        let text = read_logs(dir.path(), TEXT_LOG_SUFFIX);
+        assert!(text.contains("[TRACE] [log_control_test] visible"));
+        assert!(!text.contains("hidden before"));
+        assert!(!text.contains("hidden after"));
+        assert!(text.contains("file log level set to TRACE"));
+    }
+}
@@ -0,0 +1,241 @@
+//! `logs` CLI subcommand for querying and streaming server logs
+//!
+//! Usage: `smart-memory logs [--level <level>] [--module <module>] [--last <n>]
+//! [--since <duration>] [--grep <pattern>] [--format text|json] [--follow]`
+
+use regex::Regex;
+use std::io::{self, IsTerminal};
+use std::time::Duration;
+
+use crate::proto::smart_memory_mcp_client::SmartMemoryMcpClient;
+use crate::proto::{GetLogsRequest, LogRecord, StreamLogsRequest};
+use crate::service::authorized_request;
+
+/// Output format for log entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Options accepted by the `logs` subcommand
+#[derive(Debug)]
+struct LogsOptions {
+    level: String,
+    module: String,
+    last: u32,
+    since: Option<Duration>,
+    grep: Option<Regex>,
+    format: OutputFormat,
+    follow: bool,
+}
+
+impl LogsOptions {
+    /// Parse options from the arguments following `logs`
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self {
+            level: String::new(),
+            module: String::new(),
+            last: 100,
+            since: None,
+            grep: None,
+            format: OutputFormat::Text,
+            follow: false,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format!("Missing value for {}", arg))
+            };
+
+            match arg.as_str() {
+                "--level" => options.level = value()?,
+                "--module" => options.module = value()?,
+                "--last" => {
+                    options.last = value()?
+                        .parse()
+                        .map_err(|e| format!("Invalid --last: {}", e))?
+                }
+                "--since" => {
+                    options.since = Some(
+                        humantime::parse_duration(&value()?)
+                            .map_err(|e| format!("Invalid --since: {}", e))?,
+                    )
+                }
+                "--grep" => {
+                    options.grep =
+                        Some(Regex::new(&value()?).map_err(|e| format!("Invalid --grep: {}", e))?)
+                }
+                "--format" => {
+                    options.format = match value()?.as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        other => return Err(format!("Unknown format: {}", other)),
+                    }
+                }
+                "--follow" | "-f" => options.follow = true,
+                other => return Err(format!("Unknown option: {}", other)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Check whether a record passes the client-side `--grep` filter
+    fn matches(&self, record: &LogRecord) -> bool {
+        self.grep
+            .as_ref()
+            .is_none_or(|grep| grep.is_match(&record.message))
+    }
+}
+
+/// Run the `logs` subcommand against the server at `host:port`
+pub fn run(host: &str, port: u16, args: &[String]) -> io::Result<()> {
+    let options =
+        LogsOptions::parse(args).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let endpoint = format!("http://{}:{}", host, port);
+
+    // The CLI may be invoked from within the server's runtime, so use a dedicated one
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new()?;
+        runtime
+            .block_on(fetch_logs(endpoint, options))
+            .map_err(|e| io::Error::other(e.to_string()))
+    })
+    .join()
+    .map_err(|_| io::Error::other("logs command panicked"))?
+}
+
+/// Fetch buffered logs and, with `--follow`, stream new ones
+async fn fetch_logs(endpoint: String, options: LogsOptions) -> anyhow::Result<()> {
+    let mut client = SmartMemoryMcpClient::connect(endpoint).await?;
+    let colored = io::stdout().is_terminal();
+
+    let since = match options.since {
+        Some(duration) => (chrono::Utc::now() - chrono::Duration::from_std(duration)?).to_rfc3339(),
+        None => String::new(),
+    };
+
+    let response = client
+        .get_logs(authorized_request(GetLogsRequest {
+            level: options.level.clone(),
+            module: options.module.clone(),
+            last: options.last,
+            since,
+        }))
+        .await?
+        .into_inner();
+
+    for record in response.entries.iter().filter(|r| options.matches(r)) {
+        print_record(record, options.format, colored);
+    }
+
+    if options.follow {
+        let mut stream = client
+            .stream_logs(authorized_request(StreamLogsRequest {
+                level: options.level.clone(),
+                module: options.module.clone(),
+            }))
+            .await?
+            .into_inner();
+
+        while let Some(record) = stream.message().await? {
+            if options.matches(&record) {
+                print_record(&record, options.format, colored);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a log record in the requested format
+fn print_record(record: &LogRecord, format: OutputFormat, colored: bool) {
+    match format {
+        OutputFormat::Json => {
+            let metadata = serde_json::from_str::<serde_json::Value>(&record.metadata_json).ok();
+            let entry = serde_json::json!({
+                "timestamp": record.timestamp,
+                "level": record.level,
+                "module": record.module,
+                "message": record.message,
+                "metadata": metadata,
+            });
+            println!("{}", entry);
+        }
+        OutputFormat::Text => {
+            let level = if colored {
+                format!("{}{}\x1b[0m", level_color(&record.level), record.level)
+            } else {
+                record.level.clone()
+            };
+            let metadata = if record.metadata_json.is_empty() {
+                String::new()
+            } else {
+                format!(" | {}", record.metadata_json)
+            };
+
+            println!(
+                "[{}] [{}] [{}] {}{}",
+                record.timestamp, level, record.module, record.message, metadata
+            );
+        }
+    }
+}
+
+/// ANSI color escape for a log level
+fn level_color(level: &str) -> &'static str {
+    match level {
+        "CRITICAL" | "ERROR" => "\x1b[31m",
+        "WARNING" => "\x1b[33m",
+        "INFO" => "\x1b[32m",
+        _ => "\x1b[2m",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = LogsOptions::parse(&args(&[
+            "--level", "error", "--module", "storage", "--last", "20", "--since", "1h", "--format",
+            "json", "--follow",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.level, "error");
+        assert_eq!(options.module, "storage");
+        assert_eq!(options.last, 20);
+        assert_eq!(options.since, Some(Duration::from_secs(3600)));
+        assert_eq!(options.format, OutputFormat::Json);
+        assert!(options.follow);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_options() {
+        assert!(LogsOptions::parse(&args(&["--since", "soon"])).is_err());
+        assert!(LogsOptions::parse(&args(&["--last"])).is_err());
+        assert!(LogsOptions::parse(&args(&["--verbose"])).is_err());
+    }
+
+    #[test]
+    fn test_grep_filters_messages() {
+        let options = LogsOptions::parse(&args(&["--grep", "backup"])).unwrap();
+        let record = |message: &str| LogRecord {
+            message: message.to_string(),
+            ..LogRecord::default()
+        };
+
+        assert!(options.matches(&record("Created automatic backup")));
+        assert!(!options.matches(&record("Server started")));
+    }
+}
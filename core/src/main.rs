@@ -3,15 +3,24 @@ use std::env;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::signal;
+use tokio_util::sync::CancellationToken;
 use tonic::transport::Server;
 
+mod config_watcher;
 mod crash_recovery;
 mod logging;
+mod logs_cli;
+mod metrics_server;
 mod parent_process_monitor;
+mod rest_bridge;
 mod server_manager;
 mod service;
+mod stats_cli;
 mod storage;
+mod telemetry;
+mod tls;
 mod version;
 mod proto {
     tonic::include_proto!("smart_memory");
@@ -20,13 +29,30 @@ mod proto {
     pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!("proto/smart_memory.bin");
 }
 
-use crate::logging::LogLevel;
+use config_watcher::ConfigWatcher;
 use crash_recovery::CrashRecoveryManager;
 use parent_process_monitor::{
     is_shutdown_requested, start_parent_process_monitor, wait_for_shutdown_request,
 };
+use proto::health_check_server::HealthCheckServer;
+use proto::smart_memory_mcp_server::SmartMemoryMcpServer;
 use version::VersionManager;
 
+/// Interval between diagnostic log entries
+const DIAGNOSTICS_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Interval between cache hit rate samples
+const CACHE_MONITOR_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Consecutive low hit rate samples that trigger a cache rebuild
+const CACHE_REBUILD_SAMPLES: u32 = 5;
+
+/// Default interval between scheduled backups, in seconds
+const DEFAULT_BACKUP_INTERVAL_SECS: u64 = 3600;
+
+/// Default interval between purges of expired memories, in seconds
+const DEFAULT_TTL_PURGE_INTERVAL_SECS: u64 = 300;
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Get data directory
@@ -44,21 +70,9 @@ async fn main() -> Result<()> {
         }
     }
 
-    // Initialize logging system
-    let log_dir = env::var("LOG_DIR")
-        .unwrap_or_else(|_| data_path.join("logs").to_string_lossy().to_string());
-
-    let console_level = env::var("RUST_LOG")
-        .map(|level| LogLevel::from_str(&level).unwrap_or(LogLevel::Info))
-        .unwrap_or(LogLevel::Info);
-
-    let file_level = env::var("FILE_LOG_LEVEL")
-        .map(|level| LogLevel::from_str(&level).unwrap_or(LogLevel::Debug))
-        .unwrap_or(LogLevel::Debug);
-
-    if let Err(e) = logging::Logger::init(&log_dir, console_level, file_level) {
+    // Initialize logging system, falling back to standard error if it fails
+    if let Err(e) = logging::Logger::init_from_config(&logging::LogConfig::from_env()) {
         eprintln!("Failed to initialize logging system: {}", e);
-        // Continue anyway, we'll use standard output
     }
 
     // Initialize crash recovery system
@@ -79,7 +93,7 @@ async fn main() -> Result<()> {
     };
 
     // Initialize version manager
-    let version_manager = match version::init() {
+    let mut version_manager = match version::init() {
         Ok(manager) => {
             log_info!(
                 "main",
@@ -88,14 +102,6 @@ async fn main() -> Result<()> {
                     manager.get_current_version()
                 )
             );
-
-            // Check for updates
-            if manager.is_update_available() {
-                if let Some(latest_version) = manager.get_latest_version() {
-                    log_info!("main", &format!("Update available: {}", latest_version));
-                }
-            }
-
             manager
         }
         Err(e) => {
@@ -109,6 +115,16 @@ async fn main() -> Result<()> {
         }
     };
 
+    // Check for updates
+    if let Err(e) = version_manager.check_for_updates_async().await {
+        log_warning!("main", &format!("Failed to check for updates: {}", e));
+    }
+    if version_manager.is_update_available() {
+        if let Some(latest_version) = version_manager.get_latest_version() {
+            log_info!("main", &format!("Update available: {}", latest_version));
+        }
+    }
+
     // Check for previous crashes
     if let Some(crash_state) = recovery_manager.check_previous_crash() {
         log_warning!(
@@ -146,6 +162,22 @@ async fn main() -> Result<()> {
     // Check if this is a server manager command
     server_manager::integrate_server_manager();
 
+    // Accept log level changes while running
+    let log_control_path = logging::log_control_path(&data_path);
+    match logging::LogLevelController::new(&log_control_path).start() {
+        Ok(_) => log_info!(
+            "main",
+            &format!(
+                "Listening for log level changes on {}",
+                log_control_path.display()
+            )
+        ),
+        Err(e) => log_warning!(
+            "main",
+            &format!("Log level changes at runtime disabled: {}", e)
+        ),
+    }
+
     log_info!("main", "Starting Smart Memory MCP server...");
 
     let start_time = std::time::Instant::now();
@@ -169,22 +201,40 @@ async fn main() -> Result<()> {
     })?;
 
     // Update recovery state with port
-    let db_path = data_path.join("memories.db").to_string_lossy().to_string();
-    let config_path = data_path.join("config.json").to_string_lossy().to_string();
-    if let Err(e) = recovery_manager.set_paths(&db_path, &config_path, port) {
+    let db_path_buf = env::var("DB_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| data_path.join("memories.db"));
+    let db_path = db_path_buf.to_string_lossy().to_string();
+    let config_path = env::var("CONFIG_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| data_path.join("config.json"));
+    if let Err(e) = recovery_manager.set_paths(&db_path, &config_path.to_string_lossy(), port) {
         log_error!(
             "main",
             &format!("Failed to update crash recovery paths: {}", e)
         );
     }
 
-    // Initialize backup manager and create automatic backup
-    let db_path_buf = data_path.join("memories.db");
-    if db_path_buf.exists() {
-        match storage::BackupManager::new(&backup_dir) {
-            Ok(backup_manager) => {
-                log_info!("main", "Backup manager initialized");
+    // Load the memory bank config, refusing to start on one with errors
+    let memory_bank_config = config_watcher::load_or_default(&config_path);
+    service::check_config(&memory_bank_config)?;
 
+    // Initialize backup manager, create a startup backup and schedule periodic ones
+    let backup_on_startup = env::var("BACKUP_ON_STARTUP")
+        .map(|value| value != "false" && value != "0")
+        .unwrap_or(true);
+    let backup_interval = env::var("BACKUP_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .unwrap_or(DEFAULT_BACKUP_INTERVAL_SECS);
+    let backup_cancellation = match storage::BackupManager::new(&backup_dir) {
+        Ok(backup_manager) => {
+            log_info!("main", "Backup manager initialized");
+
+            if !backup_on_startup {
+                log_info!("main", "Startup backup disabled");
+            } else if db_path_buf.exists() {
                 // Create automatic backup
                 match backup_manager.create_auto_backup(&db_path_buf) {
                     Ok(backup_path) => {
@@ -197,17 +247,29 @@ async fn main() -> Result<()> {
                         log_warning!("main", &format!("Failed to create automatic backup: {}", e));
                     }
                 }
+            } else {
+                log_info!("main", "No database file found, skipping automatic backup");
             }
-            Err(e) => {
-                log_warning!(
-                    "main",
-                    &format!("Failed to initialize backup manager: {}", e)
-                );
-            }
+
+            let scheduler = storage::BackupScheduler::new(
+                backup_manager,
+                &db_path_buf,
+                Duration::from_secs(backup_interval),
+            )
+            .with_auto_compact_threshold(memory_bank_config.auto_compact_threshold_mb * 1024 * 1024)
+            .with_s3_export_from_env();
+            let cancellation = scheduler.cancellation_token();
+            scheduler.start();
+            Some(cancellation)
         }
-    } else {
-        log_info!("main", "No database file found, skipping automatic backup");
-    }
+        Err(e) => {
+            log_warning!(
+                "main",
+                &format!("Failed to initialize backup manager: {}", e)
+            );
+            None
+        }
+    };
 
     log_debug!(
         "main",
@@ -217,8 +279,17 @@ async fn main() -> Result<()> {
         )
     );
 
-    // Create the memory store first
-    let memory_store = service::create_memory_store();
+    // Create the memory service first, in the database the backups are taken of
+    let memory_service = service::SmartMemoryService::new_with_pool(
+        &db_path_buf,
+        &config_path,
+        storage::SqliteMemoryRepository::default_pool_size(),
+    )
+    .map_err(|e| {
+        log_error!("main", &format!("Failed to create memory service: {:#}", e));
+        e
+    })?;
+    let memory_store = memory_service.memory_store();
     log_info!(
         "main",
         &format!(
@@ -227,8 +298,61 @@ async fn main() -> Result<()> {
         )
     );
 
-    // Create the main service with the shared memory store
-    let memory_service = service::create_service_with_store(memory_store.clone());
+    // Reload the memory bank config whenever the file changes
+    let cache_rebuild_threshold = memory_bank_config.cache_rebuild_threshold;
+    match ConfigWatcher::new(&config_path, memory_service.shared_config()).start() {
+        Ok(_) => log_info!(
+            "main",
+            &format!("Watching {} for changes", config_path.display())
+        ),
+        Err(e) => log_warning!("main", &format!("Config hot reload disabled: {:#}", e)),
+    }
+
+    // Serve Prometheus metrics and the REST bridge until the gRPC server shuts down
+    let prometheus = Arc::new(metrics_server::PrometheusMetrics::new());
+    let http_shutdown = CancellationToken::new();
+    match metrics_server::metrics_addr() {
+        Ok(Some(metrics_addr)) => match metrics_server::start(
+            metrics_addr,
+            prometheus.clone(),
+            memory_store.clone(),
+            http_shutdown.clone(),
+        ) {
+            Ok(addr) => log_info!(
+                "main",
+                &format!("Serving metrics on http://{}/metrics", addr)
+            ),
+            Err(e) => log_warning!(
+                "main",
+                &format!("Failed to start metrics server on {}: {}", metrics_addr, e)
+            ),
+        },
+        Ok(None) => {}
+        Err(e) => log_warning!("main", &format!("Not starting metrics server: {}", e)),
+    }
+
+    // Trace calls when an OTLP collector is configured
+    let tracer = match telemetry::init_tracer() {
+        Ok(tracer) => {
+            if let Ok(endpoint) = env::var(telemetry::OTEL_ENDPOINT_VAR) {
+                log_info!("main", &format!("Exporting traces to {}", endpoint));
+            }
+            tracer
+        }
+        Err(e) => {
+            log_warning!("main", &format!("Tracing disabled: {}", e));
+            telemetry::noop_tracer()
+        }
+    };
+
+    // Report the service's calls to Prometheus and the tracer
+    let memory_service = Arc::new(
+        memory_service
+            .with_prometheus(prometheus)
+            .with_tracer(tracer),
+    );
+    // Writes through gRPC and the REST bridge draw on the same buckets
+    let rate_limiter = service::RateLimiter::from_env();
     log_info!(
         "main",
         &format!(
@@ -237,8 +361,31 @@ async fn main() -> Result<()> {
         )
     );
 
+    // Start with the most retrieved memories cached
+    match memory_service.warm_caches() {
+        Ok(loaded) => log_info!(
+            "main",
+            &format!("Preloaded {} frequently retrieved memories", loaded)
+        ),
+        Err(e) => log_warning!("main", &format!("Failed to preload hot memories: {}", e)),
+    }
+
+    // Log memory diagnostics at startup and once a day
+    spawn_daily_diagnostics(memory_store.clone());
+
+    // Rebuild the cache if its hit rate stays low
+    spawn_cache_monitor(memory_store.clone(), cache_rebuild_threshold);
+
+    // Delete expired memories in the background
+    let purge_interval = env::var("TTL_PURGE_INTERVAL_SECS")
+        .ok()
+        .and_then(|secs| secs.parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .unwrap_or(DEFAULT_TTL_PURGE_INTERVAL_SECS);
+    spawn_ttl_purge(memory_store.clone(), Duration::from_secs(purge_interval));
+
     // Create the health check service with the shared memory store
-    let health_service = service::create_health_service(Some(memory_store));
+    let health_service = Arc::new(service::HealthCheckService::new(Some(memory_store)));
     log_info!(
         "main",
         &format!(
@@ -247,6 +394,36 @@ async fn main() -> Result<()> {
         )
     );
 
+    // Serve the REST bridge alongside gRPC, when enabled, for clients without a gRPC stack
+    match rest_bridge::rest_addr() {
+        Ok(Some(rest_addr)) => {
+            let rest_tls = match tls::TlsPaths::from_env()? {
+                Some(tls_paths) => Some(tls_paths.load_rustls().await?),
+                None => None,
+            };
+            let scheme = if rest_tls.is_some() { "https" } else { "http" };
+            match rest_bridge::start(
+                rest_addr,
+                rest_tls,
+                memory_service.clone(),
+                health_service.clone(),
+                rate_limiter.clone(),
+                http_shutdown.clone(),
+            ) {
+                Ok(addr) => log_info!(
+                    "main",
+                    &format!("Serving REST API on {}://{}", scheme, addr)
+                ),
+                Err(e) => log_warning!(
+                    "main",
+                    &format!("Failed to start REST server on {}: {}", rest_addr, e)
+                ),
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log_warning!("main", &format!("Not starting REST server: {}", e)),
+    }
+
     log_debug!(
         "main",
         &format!(
@@ -260,12 +437,33 @@ async fn main() -> Result<()> {
         .build()
         .unwrap();
 
-    let server = Server::builder()
+    // Serve over TLS when a certificate is configured
+    let mut builder = Server::builder();
+    if let Some(tls_paths) = tls::TlsPaths::from_env()? {
+        builder = builder.tls_config(tls_paths.load().await?)?;
+        log_info!(
+            "main",
+            &format!(
+                "TLS enabled with certificate {}{}",
+                tls_paths.cert_path.display(),
+                if tls_paths.is_mutual() {
+                    ", client certificates required"
+                } else {
+                    ""
+                }
+            )
+        );
+    }
+
+    // End calls past their deadline and limit how fast clients store memories
+    let server = builder
+        .layer(service::Deadline::from_env())
+        .layer(rate_limiter)
         .accept_http1(true)
         .tcp_keepalive(Some(std::time::Duration::from_secs(60)))
         .tcp_nodelay(true)
-        .add_service(memory_service)
-        .add_service(health_service)
+        .add_service(SmartMemoryMcpServer::from_arc(memory_service))
+        .add_service(HealthCheckServer::from_arc(health_service))
         .add_service(reflection_service);
 
     log_info!(
@@ -286,8 +484,8 @@ async fn main() -> Result<()> {
 
     // Start parent process monitor
     log_info!("main", "Starting parent process monitor...");
-    let parent_monitor_handle = start_parent_process_monitor();
-    if parent_monitor_handle.is_some() {
+    let parent_monitor_handles = start_parent_process_monitor();
+    if !parent_monitor_handles.is_empty() {
         log_info!("main", "Parent process monitor started successfully");
     } else {
         log_warning!(
@@ -315,7 +513,10 @@ async fn main() -> Result<()> {
                     log_error!("main", &format!("[{}ms] Error details: {:?}", start_time.elapsed().as_millis(), e));
 
                     // Record crash
-                    if let Err(re) = recovery_manager.record_crash(&format!("Server error: {}", e)) {
+                    if let Err(re) = recovery_manager
+                        .record_crash(&format!("Server error: {}", e))
+                        .and_then(|_| recovery_manager.flush_sync())
+                    {
                         log_error!("main", &format!("Failed to record crash: {}", re));
                     }
 
@@ -334,10 +535,11 @@ async fn main() -> Result<()> {
             // Set shutdown flag
             shutdown_flag.store(true, Ordering::SeqCst);
         }
-        _ = async {
-            // Wait for parent process monitor to request shutdown
-            wait_for_shutdown_request(shutdown_flag.clone());
-            Ok::<_, anyhow::Error>(())
+        _ = {
+            // Wait for parent process monitor to request shutdown. The wait blocks,
+            // so keep it off the task that drives the server.
+            let shutdown_flag = shutdown_flag.clone();
+            tokio::task::spawn_blocking(move || wait_for_shutdown_request(shutdown_flag))
         } => {
             log_info!("main", &format!("[{}ms] Parent process (VSCode) terminated, shutting down...", start_time.elapsed().as_millis()));
 
@@ -348,8 +550,16 @@ async fn main() -> Result<()> {
         }
     }
 
-    // Wait for parent monitor thread to finish if it was started
-    if let Some(handle) = parent_monitor_handle {
+    http_shutdown.cancel();
+    telemetry::shutdown();
+
+    // Stop scheduled backups, letting one that is in progress finish
+    if let Some(cancellation) = backup_cancellation {
+        cancellation.cancel();
+    }
+
+    // Wait for the parent monitor threads to finish if they were started
+    for handle in parent_monitor_handles {
         if let Err(e) = handle.join() {
             log_error!(
                 "main",
@@ -358,5 +568,113 @@ async fn main() -> Result<()> {
         }
     }
 
+    // Persist the final recovery state before exiting
+    if let Err(e) = recovery_manager.flush_sync() {
+        log_error!(
+            "main",
+            &format!("Failed to save crash recovery state: {}", e)
+        );
+    }
+
     Ok(())
 }
+
+/// Periodically log statistics about the stored memories
+fn spawn_daily_diagnostics(memory_store: Arc<storage::MemoryStore>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DIAGNOSTICS_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            match memory_store.get_memory_size_distribution(None, None) {
+                Ok(distribution) => {
+                    log_info!(
+                        "diagnostics",
+                        &format!(
+                            "Memory size distribution: {} memories, p50 {} tokens, p99 {} tokens, max {} tokens",
+                            distribution.total_count,
+                            distribution.p50_tokens,
+                            distribution.p99_tokens,
+                            distribution.max_tokens
+                        ),
+                        serde_json::to_value(&distribution).unwrap_or_default()
+                    );
+                }
+                Err(e) => {
+                    log_warning!(
+                        "diagnostics",
+                        &format!("Failed to compute memory size distribution: {}", e)
+                    );
+                }
+            }
+        }
+    });
+}
+
+/// Periodically delete memories whose time-to-live has elapsed and roll back abandoned snapshots
+fn spawn_ttl_purge(memory_store: Arc<storage::MemoryStore>, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+
+            match memory_store.purge_expired() {
+                Ok(0) => {}
+                Ok(deleted) => {
+                    log_info!("ttl", &format!("Purged {} expired memories", deleted));
+                }
+                Err(e) => {
+                    log_warning!("ttl", &format!("Failed to purge expired memories: {}", e));
+                }
+            }
+
+            match memory_store.expire_snapshots() {
+                Ok(0) => {}
+                Ok(expired) => {
+                    log_info!(
+                        "ttl",
+                        &format!("Rolled back {} abandoned snapshots", expired)
+                    );
+                }
+                Err(e) => {
+                    log_warning!("ttl", &format!("Failed to expire snapshots: {}", e));
+                }
+            }
+        }
+    });
+}
+
+/// Rebuild the memory cache when its hit rate stays below the threshold percentage
+fn spawn_cache_monitor(memory_store: Arc<storage::MemoryStore>, threshold_percent: u32) {
+    tokio::spawn(async move {
+        let threshold = threshold_percent as f64 / 100.0;
+        let mut interval = tokio::time::interval(CACHE_MONITOR_INTERVAL);
+        let mut low_samples = 0;
+
+        loop {
+            interval.tick().await;
+
+            // Minutes without lookups neither count towards nor reset the streak
+            match memory_store.take_cache_hit_rate() {
+                Some(hit_rate) if hit_rate < threshold => low_samples += 1,
+                Some(_) => low_samples = 0,
+                None => continue,
+            }
+
+            if low_samples >= CACHE_REBUILD_SAMPLES {
+                low_samples = 0;
+                match memory_store.rebuild_cache_from_db() {
+                    Ok(loaded) => {
+                        log_info!(
+                            "cache",
+                            &format!("Rebuilt memory cache with {} entries", loaded)
+                        );
+                    }
+                    Err(e) => {
+                        log_warning!("cache", &format!("Failed to rebuild memory cache: {}", e));
+                    }
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,292 @@
+//! Prometheus metrics served over HTTP
+//!
+//! [`PrometheusMetrics`] collects RPC counters and latencies as calls finish;
+//! the gauges derived from the memory store are refreshed on every scrape of
+//! `/metrics`. The server listens on `METRICS_HOST` (default 127.0.0.1) and
+//! `METRICS_PORT` (default 9091) unless `METRICS_ENABLED` is `false`.
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use prometheus::{
+    Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::convert::Infallible;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::storage::MemoryStore;
+
+/// Environment variable holding the port of the metrics server
+pub const METRICS_PORT_VAR: &str = "METRICS_PORT";
+
+/// Environment variable holding the address the metrics server listens on
+pub const METRICS_HOST_VAR: &str = "METRICS_HOST";
+
+/// Environment variable that turns the metrics server off when `false`
+pub const METRICS_ENABLED_VAR: &str = "METRICS_ENABLED";
+
+/// Default port of the metrics server
+const DEFAULT_METRICS_PORT: u16 = 9091;
+
+/// Default address of the metrics server, reachable from this machine only
+const DEFAULT_METRICS_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Get the metrics server address from the environment, or `None` when it is disabled
+pub fn metrics_addr() -> io::Result<Option<SocketAddr>> {
+    metrics_addr_from(|name| std::env::var(name).ok())
+}
+
+/// Get the metrics server address from the variables `var` looks up
+fn metrics_addr_from(var: impl Fn(&str) -> Option<String>) -> io::Result<Option<SocketAddr>> {
+    if var(METRICS_ENABLED_VAR)
+        .is_some_and(|enabled| matches!(enabled.to_lowercase().as_str(), "false" | "0"))
+    {
+        return Ok(None);
+    }
+
+    let invalid = |name: &str, value: &str, e: &dyn std::fmt::Display| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid {} {:?}: {}", name, value, e),
+        )
+    };
+    let port = match var(METRICS_PORT_VAR) {
+        Some(port) => port
+            .parse()
+            .map_err(|e| invalid(METRICS_PORT_VAR, &port, &e))?,
+        None => DEFAULT_METRICS_PORT,
+    };
+    let host = match var(METRICS_HOST_VAR) {
+        Some(host) => host
+            .parse()
+            .map_err(|e| invalid(METRICS_HOST_VAR, &host, &e))?,
+        None => DEFAULT_METRICS_HOST,
+    };
+
+    Ok(Some(SocketAddr::new(host, port)))
+}
+
+/// Prometheus metrics of the memory service
+#[derive(Debug)]
+pub struct PrometheusMetrics {
+    registry: Registry,
+    /// Calls to the store, retrieve and delete RPCs
+    store_total: IntCounterVec,
+    /// Total tokens of all stored memories
+    token_count: IntGauge,
+    /// Duration of every RPC
+    rpc_duration: HistogramVec,
+    /// Share of memory lookups served from the cache
+    cache_hit_ratio: Gauge,
+}
+
+impl Default for PrometheusMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrometheusMetrics {
+    /// Create the metrics in a fresh registry
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let store_total = IntCounterVec::new(
+            Opts::new(
+                "smart_memory_store_total",
+                "Memory store operations by operation",
+            ),
+            &["operation"],
+        )
+        .unwrap();
+        let token_count = IntGauge::new(
+            "smart_memory_token_count",
+            "Total tokens of stored memories",
+        )
+        .unwrap();
+        let rpc_duration = HistogramVec::new(
+            HistogramOpts::new("smart_memory_rpc_duration_seconds", "RPC duration by RPC"),
+            &["rpc"],
+        )
+        .unwrap();
+        let cache_hit_ratio = Gauge::new(
+            "smart_memory_cache_hit_ratio",
+            "Share of memory lookups served from the cache",
+        )
+        .unwrap();
+
+        // Names are distinct, so registration cannot fail
+        registry.register(Box::new(store_total.clone())).unwrap();
+        registry.register(Box::new(token_count.clone())).unwrap();
+        registry.register(Box::new(rpc_duration.clone())).unwrap();
+        registry
+            .register(Box::new(cache_hit_ratio.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            store_total,
+            token_count,
+            rpc_duration,
+            cache_hit_ratio,
+        }
+    }
+
+    /// Record a finished RPC
+    pub fn observe_call(&self, rpc: &str, duration: Duration) {
+        self.rpc_duration
+            .with_label_values(&[rpc])
+            .observe(duration.as_secs_f64());
+
+        let operation = match rpc {
+            "store_memory" => "store",
+            "retrieve_memory" => "retrieve",
+            "delete_memory" => "delete",
+            _ => return,
+        };
+        self.store_total.with_label_values(&[operation]).inc();
+    }
+
+    /// Refresh the gauges derived from the memory store
+    pub fn update_store_gauges(&self, memory_store: &MemoryStore) {
+        match memory_store.get_total_tokens() {
+            Ok(tokens) => self.token_count.set(tokens.as_usize() as i64),
+            Err(e) => crate::log_warning!(
+                "metrics_server",
+                &format!("Failed to count tokens for metrics: {}", e)
+            ),
+        }
+
+        let cache = memory_store.cache_stats();
+        let lookups = cache.hits + cache.misses;
+        self.cache_hit_ratio.set(if lookups == 0 {
+            0.0
+        } else {
+            cache.hits as f64 / lookups as f64
+        });
+    }
+
+    /// Encode every metric in the Prometheus text format
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            crate::log_warning!(
+                "metrics_server",
+                &format!("Failed to encode metrics: {}", e)
+            );
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Serve `/metrics` on `addr` until `shutdown` is cancelled, returning the bound address
+pub fn start(
+    addr: SocketAddr,
+    metrics: Arc<PrometheusMetrics>,
+    memory_store: Arc<MemoryStore>,
+    shutdown: CancellationToken,
+) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+    let server = Server::from_tcp(listener).map_err(io::Error::other)?;
+
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        let memory_store = memory_store.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = respond(&request, &metrics, &memory_store);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+
+    tokio::spawn(async move {
+        let result = server
+            .serve(make_service)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await;
+        if let Err(e) = result {
+            crate::log_error!("metrics_server", &format!("Metrics server error: {}", e));
+        }
+    });
+
+    Ok(local_addr)
+}
+
+/// Answer a request to the metrics server
+fn respond(
+    request: &Request<Body>,
+    metrics: &PrometheusMetrics,
+    memory_store: &MemoryStore,
+) -> Response<Body> {
+    if request.method() != Method::GET || request.uri().path() != "/metrics" {
+        let mut response = Response::new(Body::from("Not Found\n"));
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    }
+
+    metrics.update_store_gauges(memory_store);
+    let mut response = Response::new(Body::from(metrics.encode()));
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Tokenizer;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_only_store_operations_are_counted() {
+        let metrics = PrometheusMetrics::new();
+        metrics.observe_call("retrieve_memory", Duration::from_millis(5));
+        metrics.observe_call("retrieve_memory", Duration::from_millis(7));
+        metrics.observe_call("get_context", Duration::from_millis(9));
+
+        let text = metrics.encode();
+        assert!(text.contains("smart_memory_store_total{operation=\"retrieve\"} 2\n"));
+        assert!(!text.contains("operation=\"get_context\""));
+        assert!(text.contains("smart_memory_rpc_duration_seconds_count{rpc=\"get_context\"} 1\n"));
+    }
+
+    #[test]
+    fn test_metrics_addr_defaults_to_loopback() {
+        let addr = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            metrics_addr_from(|name| vars.get(name).cloned())
+        };
+
+        assert_eq!(
+            addr(&[]).unwrap(),
+            Some(SocketAddr::from(([127, 0, 0, 1], 9091)))
+        );
+        assert_eq!(
+            addr(&[(METRICS_HOST_VAR, "0.0.0.0"), (METRICS_PORT_VAR, "9100")]).unwrap(),
+            Some(SocketAddr::from(([0, 0, 0, 0], 9100)))
+        );
+        assert_eq!(addr(&[(METRICS_ENABLED_VAR, "false")]).unwrap(), None);
+        assert!(addr(&[(METRICS_PORT_VAR, "ninety")]).is_err());
+        assert!(addr(&[(METRICS_HOST_VAR, "example.com")]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_paths_are_not_found() {
+        let metrics = PrometheusMetrics::new();
+        let memory_store = MemoryStore::new_in_memory(Tokenizer::default());
+        let request = Request::get("/other").body(Body::empty()).unwrap();
+
+        let response = respond(&request, &metrics, &memory_store);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
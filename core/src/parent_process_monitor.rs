@@ -1,7 +1,8 @@
 use std::env;
 use std::fs;
-use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -11,57 +12,86 @@ use nix::sys::signal::{kill, Signal};
 #[cfg(unix)]
 use nix::unistd::Pid;
 
-/// Flag to indicate if shutdown has been requested
+/// Flag set once every monitored parent process has terminated
 pub static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
 
-/// Monitor the parent process (VSCode) and initiate shutdown if it terminates
-pub fn start_parent_process_monitor() -> Option<thread::JoinHandle<()>> {
-    // Check if the VSCODE_PID environment variable is set
-    let parent_pid = match env::var("VSCODE_PID") {
-        Ok(pid_str) => match pid_str.parse::<u32>() {
-            Ok(pid) => pid,
+/// Interval between checks of the monitored processes
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Monitor the parent processes and initiate shutdown once all of them have terminated
+///
+/// The PIDs come from `MONITOR_PIDS` (comma-separated), falling back to
+/// `VSCODE_PID` and then the VSCode PID file. One thread watches each PID.
+pub fn start_parent_process_monitor() -> Vec<thread::JoinHandle<()>> {
+    let pids = monitored_pids();
+    if pids.is_empty() {
+        eprintln!("No parent process ID found, parent process monitoring disabled");
+        return Vec::new();
+    }
+
+    println!("Starting parent process monitor for PIDs: {:?}", pids);
+
+    // Shutdown is requested by whichever thread sees the last process terminate
+    let alive = Arc::new(AtomicUsize::new(pids.len()));
+    pids.into_iter()
+        .map(|pid| {
+            let alive = alive.clone();
+            thread::spawn(move || loop {
+                thread::sleep(CHECK_INTERVAL);
+
+                if !is_process_running(pid) {
+                    println!("Parent process {} has terminated", pid);
+                    if alive.fetch_sub(1, Ordering::SeqCst) == 1 {
+                        println!("All parent processes have terminated, initiating shutdown");
+                        SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+                    }
+                    break;
+                }
+            })
+        })
+        .collect()
+}
+
+/// Get the PIDs to monitor from the environment or the VSCode PID file
+fn monitored_pids() -> Vec<u32> {
+    if let Ok(list) = env::var("MONITOR_PIDS") {
+        let pids = parse_pid_list(&list);
+        if !pids.is_empty() {
+            return pids;
+        }
+    }
+
+    match env::var("VSCODE_PID") {
+        Ok(pid_str) => match pid_str.trim().parse::<u32>() {
+            Ok(pid) => vec![pid],
             Err(e) => {
                 eprintln!("Failed to parse VSCODE_PID: {}", e);
-                return None;
+                Vec::new()
             }
         },
-        Err(_) => {
-            // Check if there's a PID file
-            match read_vscode_pid_file() {
-                Some(pid) => pid,
-                None => {
-                    eprintln!("No parent process ID found, parent process monitoring disabled");
-                    return None;
-                }
-            }
-        }
-    };
+        Err(_) => read_vscode_pid_file().into_iter().collect(),
+    }
+}
 
-    println!(
-        "Starting parent process monitor for VSCode PID: {}",
-        parent_pid
-    );
-
-    // Create a thread to monitor the parent process
-    let handle = thread::spawn(move || {
-        let check_interval = Duration::from_secs(5);
-        loop {
-            thread::sleep(check_interval);
-
-            // Check if the parent process is still running
-            if !is_process_running(parent_pid) {
-                println!("Parent process (VSCode) has terminated, initiating shutdown");
-                SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
-                break;
-            }
+/// Parse a comma-separated list of PIDs, skipping invalid and duplicate entries
+fn parse_pid_list(list: &str) -> Vec<u32> {
+    let mut pids = Vec::new();
+    for entry in list
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        match entry.parse::<u32>() {
+            Ok(pid) if !pids.contains(&pid) => pids.push(pid),
+            Ok(_) => {}
+            Err(e) => eprintln!("Ignoring invalid PID {:?} in MONITOR_PIDS: {}", entry, e),
         }
-    });
-
-    Some(handle)
+    }
+    pids
 }
 
-/// Read the VSCode PID from the PID file
-fn read_vscode_pid_file() -> Option<u32> {
+/// Path of the VSCode PID file, `~/.smart-memory/vscode.pid`
+fn vscode_pid_file_path() -> Option<PathBuf> {
     let home_dir = match env::var("HOME") {
         Ok(dir) => dir,
         Err(_) => match env::var("USERPROFILE") {
@@ -71,15 +101,25 @@ fn read_vscode_pid_file() -> Option<u32> {
         },
     };
 
-    let pid_file_path = Path::new(&home_dir)
-        .join(".smart-memory")
-        .join("vscode.pid");
+    Some(
+        Path::new(&home_dir)
+            .join(".smart-memory")
+            .join("vscode.pid"),
+    )
+}
+
+/// Read the VSCode PID from the PID file
+fn read_vscode_pid_file() -> Option<u32> {
+    read_pid_file(&vscode_pid_file_path()?)
+}
 
-    if !pid_file_path.exists() {
+/// Read a PID from `path`
+fn read_pid_file(path: &Path) -> Option<u32> {
+    if !path.exists() {
         return None;
     }
 
-    match fs::read_to_string(&pid_file_path) {
+    match fs::read_to_string(path) {
         Ok(content) => match content.trim().parse::<u32>() {
             Ok(pid) => Some(pid),
             Err(e) => {
@@ -94,6 +134,25 @@ fn read_vscode_pid_file() -> Option<u32> {
     }
 }
 
+/// Write `pid` to the VSCode PID file, creating its directory if needed
+pub fn write_vscode_pid_file(pid: u32) -> io::Result<()> {
+    let path = vscode_pid_file_path().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "No home directory to write the PID file to",
+        )
+    })?;
+    write_pid_file(&path, pid)
+}
+
+/// Write `pid` to `path`, creating its directory if needed
+fn write_pid_file(path: &Path, pid: u32) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, pid.to_string())
+}
+
 /// Check if a process is running
 #[cfg(unix)]
 fn is_process_running(pid: u32) -> bool {
@@ -138,3 +197,29 @@ pub fn wait_for_shutdown_request(shutdown_requested: Arc<AtomicBool>) {
         thread::sleep(Duration::from_millis(100));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pid_list_skips_invalid_and_duplicate_pids() {
+        assert_eq!(parse_pid_list("12, 34,abc,,12, 56 "), vec![12, 34, 56]);
+        assert!(parse_pid_list(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_pid_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("vscode.pid");
+        assert_eq!(read_pid_file(&path), None);
+
+        write_pid_file(&path, 4242).unwrap();
+        assert_eq!(read_pid_file(&path), Some(4242));
+    }
+
+    #[test]
+    fn test_current_process_is_running() {
+        assert!(is_process_running(std::process::id()));
+    }
+}
@@ -14,6 +14,17 @@ pub struct StoreRequest {
     >,
     #[prost(bool, tag = "4")]
     pub compress: bool,
+    /// Retries with the same key return the original memory
+    #[prost(string, tag = "5")]
+    pub idempotency_key: ::prost::alloc::string::String,
+    /// Expire the memory this long after creation; 0 keeps it indefinitely
+    #[prost(uint64, tag = "6")]
+    pub ttl_seconds: u64,
+    #[prost(string, repeated, tag = "7")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Slug to use as the ID; when taken, the ID is the slug plus a random suffix
+    #[prost(string, tag = "8")]
+    pub preferred_id: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -25,6 +36,19 @@ pub struct StoreResponse {
     #[prost(float, tag = "3")]
     pub compression_ratio: f32,
 }
+/// Stores every item in one transaction; idempotency keys and TTLs are not supported
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BulkStoreRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub items: ::prost::alloc::vec::Vec<StoreRequest>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BulkStoreResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<StoreResponse>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RetrieveRequest {
@@ -45,6 +69,9 @@ pub struct RetrieveResponse {
     >,
     #[prost(uint32, tag = "3")]
     pub token_count: u32,
+    /// Times the memory has been retrieved, this retrieval included
+    #[prost(uint64, tag = "4")]
+    pub access_count: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -66,6 +93,170 @@ pub struct OptimizeResponse {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CopyMemoryRequest {
+    #[prost(string, tag = "1")]
+    pub source_memory_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub target_category: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub target_mode: ::prost::alloc::string::String,
+    #[prost(bool, tag = "4")]
+    pub copy_metadata: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CopyMemoryResponse {
+    #[prost(string, tag = "1")]
+    pub new_memory_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub token_count: u32,
+}
+/// Combines memories into one new memory and deletes them; nothing changes if any is missing
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MergeMemoriesRequest {
+    /// At least two, merged in this order
+    #[prost(string, repeated, tag = "1")]
+    pub memory_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Placed between contents; empty uses a blank line
+    #[prost(string, tag = "2")]
+    pub separator: ::prost::alloc::string::String,
+    /// Empty keeps the first memory's category
+    #[prost(string, tag = "3")]
+    pub target_category: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MergeMemoriesResponse {
+    #[prost(string, tag = "1")]
+    pub merged_memory_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub total_tokens: u32,
+}
+/// Groups of memories whose whitespace-separated terms largely overlap
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FindDuplicatesRequest {
+    /// Jaccard similarity in (0, 1]; 0 uses the bank's dedup_threshold
+    #[prost(float, tag = "1")]
+    pub threshold: f32,
+    /// When true the groups are only reported, not merged
+    #[prost(bool, tag = "2")]
+    pub dry_run: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DuplicateGroupResult {
+    /// Longest memory of the group, first in a merge
+    #[prost(string, tag = "1")]
+    pub primary_id: ::prost::alloc::string::String,
+    /// Most similar first
+    #[prost(string, repeated, tag = "2")]
+    pub duplicate_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Lowest similarity to the primary
+    #[prost(double, tag = "3")]
+    pub similarity: f64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FindDuplicatesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub groups: ::prost::alloc::vec::Vec<DuplicateGroupResult>,
+    /// One per group, in order, unless dry_run
+    #[prost(string, repeated, tag = "2")]
+    pub merged_memory_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteMemoryRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteMemoryResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    #[prost(uint32, tag = "2")]
+    pub freed_tokens: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateMemoryRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub content: ::prost::alloc::string::String,
+    #[prost(map = "string, string", tag = "3")]
+    pub metadata: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    /// When false the existing metadata is kept
+    #[prost(bool, tag = "4")]
+    pub replace_metadata: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateMemoryResponse {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub token_count: u32,
+}
+/// Earlier versions of a memory, kept by each update in the "history" category
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetHistoryRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetHistoryResponse {
+    /// Oldest first; metadata\["version"\] numbers them from 1
+    #[prost(message, repeated, tag = "1")]
+    pub versions: ::prost::alloc::vec::Vec<MemoryResult>,
+}
+/// Restores the content a memory had at a version; the replaced content becomes a new version
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RollbackRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub version: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RollbackResponse {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub token_count: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PinMemoryRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UnpinMemoryRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PinMemoryResponse {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub pinned: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ContextRequest {
     #[prost(string, tag = "1")]
     pub mode: ::prost::alloc::string::String,
@@ -73,6 +264,34 @@ pub struct ContextRequest {
     pub max_tokens: u32,
     #[prost(float, tag = "3")]
     pub relevance_threshold: f32,
+    #[prost(string, repeated, tag = "4")]
+    pub exclude_memory_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Only draw context from this page of memories
+    #[prost(uint32, tag = "5")]
+    pub page: u32,
+    /// 0 draws context from every memory
+    #[prost(uint32, tag = "6")]
+    pub page_size: u32,
+    /// Free text the context should be relevant to
+    #[prost(string, tag = "7")]
+    pub query: ::prost::alloc::string::String,
+    /// Also add memories linked from the selected ones, budget permitting
+    #[prost(bool, tag = "8")]
+    pub include_linked: bool,
+    /// Price the context for the model named in the x-model metadata
+    #[prost(bool, tag = "9")]
+    pub include_cost_estimate: bool,
+    #[prost(enumeration = "IsolationMode", tag = "10")]
+    pub isolation_mode: i32,
+    /// Memories with any of these tags bypass strict isolation
+    #[prost(string, repeated, tag = "11")]
+    pub cross_mode_tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Count tokens returned by earlier calls of this session against max_tokens
+    #[prost(string, tag = "12")]
+    pub session_id: ::prost::alloc::string::String,
+    /// Say why each scored memory was included or left out
+    #[prost(bool, tag = "13")]
+    pub explain: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -85,6 +304,53 @@ pub struct ContextResponse {
     pub relevance_score: f32,
     #[prost(message, repeated, tag = "4")]
     pub sources: ::prost::alloc::vec::Vec<ContextSource>,
+    #[prost(uint32, tag = "5")]
+    pub excluded_count: u32,
+    /// Set when include_cost_estimate is requested
+    #[prost(double, tag = "6")]
+    pub estimated_cost_usd: f64,
+    /// Set when explain is requested, most relevant first
+    #[prost(message, repeated, tag = "7")]
+    pub explanations: ::prost::alloc::vec::Vec<ContextExplanation>,
+}
+/// Why GetContext included a scored memory or left it out
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContextExplanation {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub included: bool,
+    /// e.g. "relevance 0.120 is below the threshold"
+    #[prost(string, tag = "3")]
+    pub reason: ::prost::alloc::string::String,
+}
+/// Forget the tokens a session's earlier GetContext calls returned
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResetSessionRequest {
+    #[prost(string, tag = "1")]
+    pub session_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResetSessionResponse {
+    /// False if the session was unknown or had expired
+    #[prost(bool, tag = "1")]
+    pub existed: bool,
+}
+/// A piece of a streamed context; chunks arrive in relevance order
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ContextChunk {
+    #[prost(uint32, tag = "1")]
+    pub chunk_index: u32,
+    #[prost(string, tag = "2")]
+    pub content: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "3")]
+    pub sources: ::prost::alloc::vec::Vec<ContextSource>,
+    #[prost(bool, tag = "4")]
+    pub is_last: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -295,6 +561,11 @@ pub struct MemoryBankContextRequest {
     pub relevance_threshold: f32,
     #[prost(string, tag = "5")]
     pub date: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "6")]
+    pub exclude_memory_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Free text the context should be relevant to
+    #[prost(string, tag = "7")]
+    pub query: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -307,6 +578,8 @@ pub struct MemoryBankContextResponse {
     pub relevance_score: f32,
     #[prost(message, repeated, tag = "4")]
     pub sources: ::prost::alloc::vec::Vec<MemoryBankSource>,
+    #[prost(uint32, tag = "5")]
+    pub excluded_count: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -367,6 +640,8 @@ pub struct MemoryBankStatsResponse {
     >,
     #[prost(message, repeated, tag = "5")]
     pub category_stats: ::prost::alloc::vec::Vec<MemoryBankCategoryStats>,
+    #[prost(uint32, tag = "6")]
+    pub pinned_count: u32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -382,6 +657,29 @@ pub struct MemoryBankCategoryStats {
     #[prost(string, tag = "5")]
     pub last_updated: ::prost::alloc::string::String,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MergeCategoriesRequest {
+    #[prost(string, tag = "1")]
+    pub source_category: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub target_category: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RenameCategoryRequest {
+    #[prost(string, tag = "1")]
+    pub old_category: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub new_category: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MergeCategoriesResponse {
+    /// Memories moved to the target category
+    #[prost(uint64, tag = "1")]
+    pub updated_count: u64,
+}
 /// UMB command messages
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -410,369 +708,2523 @@ pub struct UmbCommandResponse {
     #[prost(string, tag = "5")]
     pub message: ::prost::alloc::string::String,
 }
-/// Health check messages
-///
-/// Empty request
-#[allow(clippy::derive_partial_eq_without_eq)]
-#[derive(Clone, PartialEq, ::prost::Message)]
-pub struct HealthCheckRequest {}
-#[allow(clippy::derive_partial_eq_without_eq)]
-#[derive(Clone, PartialEq, ::prost::Message)]
-pub struct HealthCheckResponse {
-    #[prost(enumeration = "health_check_response::ServingStatus", tag = "1")]
-    pub status: i32,
-    #[prost(string, tag = "2")]
-    pub message: ::prost::alloc::string::String,
-}
-/// Nested message and enum types in `HealthCheckResponse`.
-pub mod health_check_response {
-    #[derive(
-        Clone,
-        Copy,
-        Debug,
-        PartialEq,
-        Eq,
-        Hash,
-        PartialOrd,
-        Ord,
-        ::prost::Enumeration
-    )]
-    #[repr(i32)]
-    pub enum ServingStatus {
-        Unknown = 0,
-        Serving = 1,
-        NotServing = 2,
-        ServiceUnknown = 3,
-    }
-    impl ServingStatus {
-        /// String value of the enum field names used in the ProtoBuf definition.
-        ///
-        /// The values are not transformed in any way and thus are considered stable
-        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
-        pub fn as_str_name(&self) -> &'static str {
-            match self {
-                ServingStatus::Unknown => "UNKNOWN",
-                ServingStatus::Serving => "SERVING",
-                ServingStatus::NotServing => "NOT_SERVING",
-                ServingStatus::ServiceUnknown => "SERVICE_UNKNOWN",
-            }
-        }
-        /// Creates an enum from field names used in the ProtoBuf definition.
-        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
-            match value {
-                "UNKNOWN" => Some(Self::Unknown),
-                "SERVING" => Some(Self::Serving),
-                "NOT_SERVING" => Some(Self::NotServing),
-                "SERVICE_UNKNOWN" => Some(Self::ServiceUnknown),
-                _ => None,
-            }
-        }
-    }
-}
-/// Empty request
-#[allow(clippy::derive_partial_eq_without_eq)]
-#[derive(Clone, PartialEq, ::prost::Message)]
-pub struct StatusRequest {}
+/// Search messages
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct StatusResponse {
+pub struct MemoryResult {
     #[prost(string, tag = "1")]
-    pub version: ::prost::alloc::string::String,
-    #[prost(uint64, tag = "2")]
-    pub uptime_seconds: u64,
-    #[prost(uint32, tag = "3")]
-    pub memory_usage_mb: u32,
-    #[prost(uint32, tag = "4")]
-    pub total_memories: u32,
-    #[prost(uint32, tag = "5")]
-    pub total_tokens: u32,
+    pub memory_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub content: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub content_type: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub category: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub mode: ::prost::alloc::string::String,
     #[prost(map = "string, string", tag = "6")]
-    pub system_info: ::std::collections::HashMap<
+    pub metadata: ::std::collections::HashMap<
         ::prost::alloc::string::String,
         ::prost::alloc::string::String,
     >,
-    #[prost(message, repeated, tag = "7")]
-    pub components: ::prost::alloc::vec::Vec<ComponentStatus>,
+    #[prost(uint32, tag = "7")]
+    pub token_count: u32,
+    #[prost(string, repeated, tag = "8")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint64, tag = "9")]
+    pub access_count: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
-pub struct ComponentStatus {
+pub struct RegexSearchRequest {
     #[prost(string, tag = "1")]
-    pub name: ::prost::alloc::string::String,
-    #[prost(string, tag = "2")]
-    pub status: ::prost::alloc::string::String,
-    #[prost(string, tag = "3")]
-    pub version: ::prost::alloc::string::String,
-    #[prost(uint64, tag = "4")]
-    pub last_updated: u64,
+    pub pattern: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub limit: u32,
 }
-/// Enums
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
-#[repr(i32)]
-pub enum OptimizationStrategy {
-    Balanced = 0,
-    Aggressive = 1,
-    Conservative = 2,
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RegexSearchResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<MemoryResult>,
 }
-impl OptimizationStrategy {
-    /// String value of the enum field names used in the ProtoBuf definition.
-    ///
-    /// The values are not transformed in any way and thus are considered stable
-    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
-    pub fn as_str_name(&self) -> &'static str {
-        match self {
-            OptimizationStrategy::Balanced => "BALANCED",
-            OptimizationStrategy::Aggressive => "AGGRESSIVE",
-            OptimizationStrategy::Conservative => "CONSERVATIVE",
-        }
-    }
-    /// Creates an enum from field names used in the ProtoBuf definition.
-    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
-        match value {
-            "BALANCED" => Some(Self::Balanced),
-            "AGGRESSIVE" => Some(Self::Aggressive),
-            "CONSERVATIVE" => Some(Self::Conservative),
-            _ => None,
-        }
-    }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchMemoriesRequest {
+    #[prost(string, tag = "1")]
+    pub query: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub limit: u32,
+    #[prost(string, tag = "3")]
+    pub mode_filter: ::prost::alloc::string::String,
 }
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
-#[repr(i32)]
-pub enum Priority {
-    Low = 0,
-    Medium = 1,
-    High = 2,
-    Critical = 3,
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchMemoriesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<MemoryResult>,
+    #[prost(uint32, tag = "2")]
+    pub total_count: u32,
 }
-impl Priority {
-    /// String value of the enum field names used in the ProtoBuf definition.
-    ///
-    /// The values are not transformed in any way and thus are considered stable
-    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
-    pub fn as_str_name(&self) -> &'static str {
-        match self {
-            Priority::Low => "LOW",
-            Priority::Medium => "MEDIUM",
-            Priority::High => "HIGH",
-            Priority::Critical => "CRITICAL",
-        }
-    }
-    /// Creates an enum from field names used in the ProtoBuf definition.
-    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
-        match value {
-            "LOW" => Some(Self::Low),
-            "MEDIUM" => Some(Self::Medium),
-            "HIGH" => Some(Self::High),
-            "CRITICAL" => Some(Self::Critical),
-            _ => None,
-        }
-    }
+/// Lists the most often retrieved memories, however long ago they were last used
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetHotMemoriesRequest {
+    /// 0 uses the default of 10
+    #[prost(uint32, tag = "1")]
+    pub limit: u32,
+    /// Empty matches every mode
+    #[prost(string, tag = "2")]
+    pub mode_filter: ::prost::alloc::string::String,
 }
-/// Generated client implementations.
-pub mod health_check_client {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    use tonic::codegen::http::Uri;
-    /// Health check service definition
-    #[derive(Debug, Clone)]
-    pub struct HealthCheckClient<T> {
-        inner: tonic::client::Grpc<T>,
-    }
-    impl HealthCheckClient<tonic::transport::Channel> {
-        /// Attempt to create a new client by connecting to a given endpoint.
-        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
-        where
-            D: TryInto<tonic::transport::Endpoint>,
-            D::Error: Into<StdError>,
-        {
-            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
-            Ok(Self::new(conn))
-        }
-    }
-    impl<T> HealthCheckClient<T>
-    where
-        T: tonic::client::GrpcService<tonic::body::BoxBody>,
-        T::Error: Into<StdError>,
-        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
-        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
-    {
-        pub fn new(inner: T) -> Self {
-            let inner = tonic::client::Grpc::new(inner);
-            Self { inner }
-        }
-        pub fn with_origin(inner: T, origin: Uri) -> Self {
-            let inner = tonic::client::Grpc::with_origin(inner, origin);
-            Self { inner }
-        }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
-        ) -> HealthCheckClient<InterceptedService<T, F>>
-        where
-            F: tonic::service::Interceptor,
-            T::ResponseBody: Default,
-            T: tonic::codegen::Service<
-                http::Request<tonic::body::BoxBody>,
-                Response = http::Response<
-                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
-                >,
-            >,
-            <T as tonic::codegen::Service<
-                http::Request<tonic::body::BoxBody>,
-            >>::Error: Into<StdError> + Send + Sync,
-        {
-            HealthCheckClient::new(InterceptedService::new(inner, interceptor))
-        }
-        /// Compress requests with the given encoding.
-        ///
-        /// This requires the server to support it otherwise it might respond with an
-        /// error.
-        #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.inner = self.inner.send_compressed(encoding);
-            self
-        }
-        /// Enable decompressing responses.
-        #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.inner = self.inner.accept_compressed(encoding);
-            self
-        }
-        /// Limits the maximum size of a decoded message.
-        ///
-        /// Default: `4MB`
-        #[must_use]
-        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
-            self.inner = self.inner.max_decoding_message_size(limit);
-            self
-        }
-        /// Limits the maximum size of an encoded message.
-        ///
-        /// Default: `usize::MAX`
-        #[must_use]
-        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
-            self.inner = self.inner.max_encoding_message_size(limit);
-            self
-        }
-        /// Check if the server is running
-        pub async fn check(
-            &mut self,
-            request: impl tonic::IntoRequest<super::HealthCheckRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::HealthCheckResponse>,
-            tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.HealthCheck/Check",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.HealthCheck", "Check"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Get server version and status
-        pub async fn get_status(
-            &mut self,
-            request: impl tonic::IntoRequest<super::StatusRequest>,
-        ) -> std::result::Result<tonic::Response<super::StatusResponse>, tonic::Status> {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.HealthCheck/GetStatus",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.HealthCheck", "GetStatus"));
-            self.inner.unary(req, path, codec).await
-        }
-    }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetHotMemoriesResponse {
+    /// Most retrieved first
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<MemoryResult>,
 }
-/// Generated client implementations.
-pub mod smart_memory_mcp_client {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    use tonic::codegen::http::Uri;
-    /// Main MCP service definition
-    #[derive(Debug, Clone)]
-    pub struct SmartMemoryMcpClient<T> {
-        inner: tonic::client::Grpc<T>,
-    }
-    impl SmartMemoryMcpClient<tonic::transport::Channel> {
-        /// Attempt to create a new client by connecting to a given endpoint.
-        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
-        where
-            D: TryInto<tonic::transport::Endpoint>,
-            D::Error: Into<StdError>,
-        {
-            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
-            Ok(Self::new(conn))
-        }
-    }
-    impl<T> SmartMemoryMcpClient<T>
-    where
-        T: tonic::client::GrpcService<tonic::body::BoxBody>,
-        T::Error: Into<StdError>,
-        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
-        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
-    {
-        pub fn new(inner: T) -> Self {
-            let inner = tonic::client::Grpc::new(inner);
-            Self { inner }
-        }
-        pub fn with_origin(inner: T, origin: Uri) -> Self {
-            let inner = tonic::client::Grpc::with_origin(inner, origin);
-            Self { inner }
-        }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
-        ) -> SmartMemoryMcpClient<InterceptedService<T, F>>
-        where
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FilterByTagsRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Require every tag instead of any of them
+    #[prost(bool, tag = "2")]
+    pub match_all: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FilterByTagsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<MemoryResult>,
+    #[prost(uint32, tag = "2")]
+    pub total_count: u32,
+}
+/// Lists the memories matching every given filter, one sorted page at a time
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMemoriesRequest {
+    /// Empty matches every category
+    #[prost(string, tag = "1")]
+    pub category: ::prost::alloc::string::String,
+    /// Empty matches every mode
+    #[prost(string, tag = "2")]
+    pub mode: ::prost::alloc::string::String,
+    /// RFC 3339; only memories created at or after it
+    #[prost(string, tag = "3")]
+    pub from_date: ::prost::alloc::string::String,
+    /// RFC 3339; only memories created at or before it
+    #[prost(string, tag = "4")]
+    pub to_date: ::prost::alloc::string::String,
+    /// Case-sensitive text the content must contain
+    #[prost(string, tag = "5")]
+    pub content_contains: ::prost::alloc::string::String,
+    /// created_at (default), last_accessed or token_count
+    #[prost(string, tag = "6")]
+    pub sort_by: ::prost::alloc::string::String,
+    /// asc (default) or desc
+    #[prost(string, tag = "7")]
+    pub order: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "8")]
+    pub page: u32,
+    /// 0 uses the default page size
+    #[prost(uint32, tag = "9")]
+    pub page_size: u32,
+    /// Filter expression, e.g. category:context AND (tag:rust OR tokens>500)
+    #[prost(string, tag = "10")]
+    pub query: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMemoriesResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<MemoryResult>,
+    /// Memories matching the filters across all pages
+    #[prost(uint64, tag = "2")]
+    pub total_count: u64,
+    #[prost(uint32, tag = "3")]
+    pub page: u32,
+    #[prost(uint32, tag = "4")]
+    pub page_size: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRelatedRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+    /// 0 uses the default search limit
+    #[prost(uint32, tag = "2")]
+    pub limit: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RelatedMemory {
+    #[prost(message, optional, tag = "1")]
+    pub memory: ::core::option::Option<MemoryResult>,
+    #[prost(float, tag = "2")]
+    pub relevance: f32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetRelatedResponse {
+    /// Most relevant first
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<RelatedMemory>,
+}
+/// Line-level changes from memory A to memory B
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffMemoriesRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id_a: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub memory_id_b: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DiffResponse {
+    /// Lines only in memory B
+    #[prost(string, repeated, tag = "1")]
+    pub added_lines: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Lines only in memory A
+    #[prost(string, repeated, tag = "2")]
+    pub removed_lines: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Tokens of memory B minus those of memory A
+    #[prost(int32, tag = "3")]
+    pub token_delta: i32,
+}
+/// Links the source memory to the target; links of one relation type may not form a cycle
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LinkMemoriesRequest {
+    #[prost(string, tag = "1")]
+    pub source_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub target_id: ::prost::alloc::string::String,
+    /// e.g. "references"
+    #[prost(string, tag = "3")]
+    pub relation_type: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LinkMemoriesResponse {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetLinkedRequest {
+    #[prost(string, tag = "1")]
+    pub memory_id: ::prost::alloc::string::String,
+    /// Empty follows links of every relation type
+    #[prost(string, tag = "2")]
+    pub relation_type: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetLinkedResponse {
+    /// Oldest first
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<MemoryResult>,
+}
+/// Configuration messages
+///
+/// Empty request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetConfigRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CategorySummary {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub max_tokens: u32,
+    #[prost(string, tag = "3")]
+    pub priority: ::prost::alloc::string::String,
+    /// Template the category extends, empty if none
+    #[prost(string, tag = "4")]
+    pub parent: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetConfigResponse {
+    #[prost(string, tag = "1")]
+    pub config_json: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub schema_version: u32,
+    #[prost(bool, tag = "3")]
+    pub read_only: bool,
+    #[prost(string, repeated, tag = "4")]
+    pub custom_modes: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(message, repeated, tag = "5")]
+    pub categories: ::prost::alloc::vec::Vec<CategorySummary>,
+}
+/// Diagnostics messages
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SizeDistribution {
+    #[prost(uint32, tag = "1")]
+    pub p25_tokens: u32,
+    #[prost(uint32, tag = "2")]
+    pub p50_tokens: u32,
+    #[prost(uint32, tag = "3")]
+    pub p75_tokens: u32,
+    #[prost(uint32, tag = "4")]
+    pub p90_tokens: u32,
+    #[prost(uint32, tag = "5")]
+    pub p99_tokens: u32,
+    #[prost(double, tag = "6")]
+    pub mean_tokens: f64,
+    #[prost(double, tag = "7")]
+    pub std_dev_tokens: f64,
+    #[prost(uint32, tag = "8")]
+    pub max_tokens: u32,
+    #[prost(uint32, tag = "9")]
+    pub min_tokens: u32,
+    #[prost(uint32, tag = "10")]
+    pub total_count: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSizeDistributionRequest {
+    #[prost(string, tag = "1")]
+    pub category: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub mode: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetSizeDistributionResponse {
+    #[prost(message, optional, tag = "1")]
+    pub distribution: ::core::option::Option<SizeDistribution>,
+}
+/// Log messages
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LogRecord {
+    #[prost(string, tag = "1")]
+    pub timestamp: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub level: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub module: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub message: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub metadata_json: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetLogsRequest {
+    #[prost(string, tag = "1")]
+    pub level: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub module: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub last: u32,
+    #[prost(string, tag = "4")]
+    pub since: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetLogsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub entries: ::prost::alloc::vec::Vec<LogRecord>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StreamLogsRequest {
+    #[prost(string, tag = "1")]
+    pub level: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub module: ::prost::alloc::string::String,
+}
+/// Backup messages
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyBackupRequest {
+    /// File name within the backup directory, e.g. "backup_1700000000.db"
+    #[prost(string, tag = "1")]
+    pub backup_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyBackupResponse {
+    #[prost(bool, tag = "1")]
+    pub valid: bool,
+    /// False for backups made before checksums were recorded
+    #[prost(bool, tag = "2")]
+    pub checksum_present: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompactRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompactResponse {
+    #[prost(uint64, tag = "1")]
+    pub bytes_saved: u64,
+}
+/// Return free pages to the file system without rewriting the database
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VacuumRequest {
+    /// Most pages to free; 0 frees all of them
+    #[prost(uint32, tag = "1")]
+    pub pages: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VacuumResponse {
+    #[prost(uint32, tag = "1")]
+    pub pages_freed: u32,
+}
+/// Recount every memory's tokens with the server's current tokenizer
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecalculateTokensRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecalculateTokensResponse {
+    #[prost(uint64, tag = "1")]
+    pub updated_count: u64,
+    #[prost(uint64, tag = "2")]
+    pub elapsed_ms: u64,
+}
+/// Name the backup RestoreLatest would restore and issue the token confirming it
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PrepareRestoreRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PrepareRestoreResponse {
+    /// Pass to RestoreLatest within five minutes; usable once
+    #[prost(string, tag = "1")]
+    pub confirmation_token: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub backup_name: ::prost::alloc::string::String,
+    /// Milliseconds since the UNIX epoch
+    #[prost(uint64, tag = "3")]
+    pub backup_timestamp: u64,
+}
+/// Replace every memory with the newest backup, backing up the current contents first
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RestoreLatestRequest {
+    /// From the latest PrepareRestore
+    #[prost(string, tag = "1")]
+    pub confirmation_token: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RestoreLatestResponse {
+    /// File name of the restored backup
+    #[prost(string, tag = "1")]
+    pub restored_from: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub backup_timestamp: u64,
+    #[prost(uint64, tag = "3")]
+    pub bytes_restored: u64,
+}
+/// Snapshot messages
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateSnapshotRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateSnapshotResponse {
+    #[prost(string, tag = "1")]
+    pub snapshot_id: ::prost::alloc::string::String,
+}
+/// Undo every write since the snapshot, closing it and any snapshot taken after it
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RollbackSnapshotRequest {
+    #[prost(string, tag = "1")]
+    pub snapshot_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RollbackSnapshotResponse {}
+/// Keep every write since the snapshot, closing it and any snapshot taken after it
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CommitSnapshotRequest {
+    #[prost(string, tag = "1")]
+    pub snapshot_id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CommitSnapshotResponse {}
+/// Sync messages
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SyncRequest {
+    #[prost(string, tag = "1")]
+    pub peer_address: ::prost::alloc::string::String,
+    /// "push", "pull" or "bidirectional"
+    #[prost(string, tag = "2")]
+    pub sync_direction: ::prost::alloc::string::String,
+    /// Empty syncs all categories
+    #[prost(string, repeated, tag = "3")]
+    pub categories: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// "newer_wins", "local_wins" or "remote_wins"
+    #[prost(string, tag = "4")]
+    pub conflict_resolution: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SyncResponse {
+    #[prost(uint32, tag = "1")]
+    pub sent: u32,
+    #[prost(uint32, tag = "2")]
+    pub received: u32,
+    #[prost(uint32, tag = "3")]
+    pub conflicts_resolved: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SyncExportRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub categories: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SyncPayload {
+    /// zstd-compressed JSON array of memories
+    #[prost(bytes = "vec", tag = "1")]
+    pub memories_zstd: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub memory_count: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SyncImportRequest {
+    #[prost(bytes = "vec", tag = "1")]
+    pub memories_zstd: ::prost::alloc::vec::Vec<u8>,
+    /// "newer_wins", "keep_existing", "prefer_incoming" or "fail"
+    #[prost(string, tag = "2")]
+    pub conflict_resolution: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SyncImportResponse {
+    #[prost(uint32, tag = "1")]
+    pub imported: u32,
+    #[prost(uint32, tag = "2")]
+    pub conflicts_resolved: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExportMemoriesRequest {
+    /// Empty exports all categories
+    #[prost(string, repeated, tag = "1")]
+    pub categories: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Only export memories of this mode; empty exports every mode
+    #[prost(string, tag = "2")]
+    pub mode_filter: ::prost::alloc::string::String,
+    /// Encoding of the export file: "json" (default) or "msgpack"
+    #[prost(string, tag = "3")]
+    pub format: ::prost::alloc::string::String,
+}
+/// A memory with every stored field, for moving it to another server
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExportedMemory {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub content: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub content_type: ::prost::alloc::string::String,
+    /// Empty when uncategorized
+    #[prost(string, tag = "4")]
+    pub category: ::prost::alloc::string::String,
+    /// Empty when the memory has no mode
+    #[prost(string, tag = "5")]
+    pub mode: ::prost::alloc::string::String,
+    #[prost(map = "string, string", tag = "6")]
+    pub metadata: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    #[prost(uint32, tag = "7")]
+    pub token_count: u32,
+    /// RFC 3339
+    #[prost(string, tag = "8")]
+    pub created_at: ::prost::alloc::string::String,
+    /// RFC 3339
+    #[prost(string, tag = "9")]
+    pub last_accessed: ::prost::alloc::string::String,
+    /// 0 keeps the memory indefinitely
+    #[prost(uint64, tag = "10")]
+    pub ttl_seconds: u64,
+    #[prost(bool, tag = "11")]
+    pub pinned: bool,
+    #[prost(string, repeated, tag = "12")]
+    pub tags: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExportChunk {
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<ExportedMemory>,
+    #[prost(uint32, tag = "2")]
+    pub chunk_index: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImportChunk {
+    #[prost(message, repeated, tag = "1")]
+    pub memories: ::prost::alloc::vec::Vec<ExportedMemory>,
+    /// Replace stored memories with the same ID instead of skipping them; identical ones are always skipped
+    #[prost(bool, tag = "2")]
+    pub overwrite: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImportResponse {
+    #[prost(uint32, tag = "1")]
+    pub imported_count: u32,
+    #[prost(uint32, tag = "2")]
+    pub skipped_count: u32,
+    /// One entry per memory that could not be imported
+    #[prost(string, repeated, tag = "3")]
+    pub errors: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+/// Health check messages
+///
+/// Empty request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HealthCheckRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HealthCheckResponse {
+    #[prost(enumeration = "health_check_response::ServingStatus", tag = "1")]
+    pub status: i32,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `HealthCheckResponse`.
+pub mod health_check_response {
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum ServingStatus {
+        Unknown = 0,
+        Serving = 1,
+        NotServing = 2,
+        ServiceUnknown = 3,
+    }
+    impl ServingStatus {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                ServingStatus::Unknown => "UNKNOWN",
+                ServingStatus::Serving => "SERVING",
+                ServingStatus::NotServing => "NOT_SERVING",
+                ServingStatus::ServiceUnknown => "SERVICE_UNKNOWN",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "UNKNOWN" => Some(Self::Unknown),
+                "SERVING" => Some(Self::Serving),
+                "NOT_SERVING" => Some(Self::NotServing),
+                "SERVICE_UNKNOWN" => Some(Self::ServiceUnknown),
+                _ => None,
+            }
+        }
+    }
+}
+/// Empty request
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StatusRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct StatusResponse {
+    #[prost(string, tag = "1")]
+    pub version: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub uptime_seconds: u64,
+    #[prost(uint32, tag = "3")]
+    pub memory_usage_mb: u32,
+    #[prost(uint32, tag = "4")]
+    pub total_memories: u32,
+    #[prost(uint32, tag = "5")]
+    pub total_tokens: u32,
+    #[prost(map = "string, string", tag = "6")]
+    pub system_info: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+    #[prost(message, repeated, tag = "7")]
+    pub components: ::prost::alloc::vec::Vec<ComponentStatus>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ComponentStatus {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub status: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub version: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "4")]
+    pub last_updated: u64,
+}
+/// Enums
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum OptimizationStrategy {
+    Balanced = 0,
+    Aggressive = 1,
+    Conservative = 2,
+    Deduplicate = 3,
+}
+impl OptimizationStrategy {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            OptimizationStrategy::Balanced => "BALANCED",
+            OptimizationStrategy::Aggressive => "AGGRESSIVE",
+            OptimizationStrategy::Conservative => "CONSERVATIVE",
+            OptimizationStrategy::Deduplicate => "DEDUPLICATE",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "BALANCED" => Some(Self::Balanced),
+            "AGGRESSIVE" => Some(Self::Aggressive),
+            "CONSERVATIVE" => Some(Self::Conservative),
+            "DEDUPLICATE" => Some(Self::Deduplicate),
+            _ => None,
+        }
+    }
+}
+/// Which modes' memories a context may draw from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum IsolationMode {
+    /// Use the memory bank config's default_isolation
+    Configured = 0,
+    /// Only memories of the requested mode or of no mode
+    Strict = 1,
+    /// Memories of every mode
+    Shared = 2,
+}
+impl IsolationMode {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            IsolationMode::Configured => "CONFIGURED",
+            IsolationMode::Strict => "STRICT",
+            IsolationMode::Shared => "SHARED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CONFIGURED" => Some(Self::Configured),
+            "STRICT" => Some(Self::Strict),
+            "SHARED" => Some(Self::Shared),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Priority {
+    Low = 0,
+    Medium = 1,
+    High = 2,
+    Critical = 3,
+}
+impl Priority {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Priority::Low => "LOW",
+            Priority::Medium => "MEDIUM",
+            Priority::High => "HIGH",
+            Priority::Critical => "CRITICAL",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "LOW" => Some(Self::Low),
+            "MEDIUM" => Some(Self::Medium),
+            "HIGH" => Some(Self::High),
+            "CRITICAL" => Some(Self::Critical),
+            _ => None,
+        }
+    }
+}
+/// Generated client implementations.
+pub mod health_check_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Health check service definition
+    #[derive(Debug, Clone)]
+    pub struct HealthCheckClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl HealthCheckClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> HealthCheckClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> HealthCheckClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            HealthCheckClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// Check if the server is running
+        pub async fn check(
+            &mut self,
+            request: impl tonic::IntoRequest<super::HealthCheckRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::HealthCheckResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.HealthCheck/Check",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.HealthCheck", "Check"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Get server version and status
+        pub async fn get_status(
+            &mut self,
+            request: impl tonic::IntoRequest<super::StatusRequest>,
+        ) -> std::result::Result<tonic::Response<super::StatusResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.HealthCheck/GetStatus",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.HealthCheck", "GetStatus"));
+            self.inner.unary(req, path, codec).await
+        }
+    }
+}
+/// Generated client implementations.
+pub mod smart_memory_mcp_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
+    /// Main MCP service definition
+    #[derive(Debug, Clone)]
+    pub struct SmartMemoryMcpClient<T> {
+        inner: tonic::client::Grpc<T>,
+    }
+    impl SmartMemoryMcpClient<tonic::transport::Channel> {
+        /// Attempt to create a new client by connecting to a given endpoint.
+        pub async fn connect<D>(dst: D) -> Result<Self, tonic::transport::Error>
+        where
+            D: TryInto<tonic::transport::Endpoint>,
+            D::Error: Into<StdError>,
+        {
+            let conn = tonic::transport::Endpoint::new(dst)?.connect().await?;
+            Ok(Self::new(conn))
+        }
+    }
+    impl<T> SmartMemoryMcpClient<T>
+    where
+        T: tonic::client::GrpcService<tonic::body::BoxBody>,
+        T::Error: Into<StdError>,
+        T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+        <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+    {
+        pub fn new(inner: T) -> Self {
+            let inner = tonic::client::Grpc::new(inner);
+            Self { inner }
+        }
+        pub fn with_origin(inner: T, origin: Uri) -> Self {
+            let inner = tonic::client::Grpc::with_origin(inner, origin);
+            Self { inner }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> SmartMemoryMcpClient<InterceptedService<T, F>>
+        where
+            F: tonic::service::Interceptor,
+            T::ResponseBody: Default,
+            T: tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+                Response = http::Response<
+                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
+                >,
+            >,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + Send + Sync,
+        {
+            SmartMemoryMcpClient::new(InterceptedService::new(inner, interceptor))
+        }
+        /// Compress requests with the given encoding.
+        ///
+        /// This requires the server to support it otherwise it might respond with an
+        /// error.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.send_compressed(encoding);
+            self
+        }
+        /// Enable decompressing responses.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.inner = self.inner.accept_compressed(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_decoding_message_size(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.inner = self.inner.max_encoding_message_size(limit);
+            self
+        }
+        /// Memory management
+        pub async fn store_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::StoreRequest>,
+        ) -> std::result::Result<tonic::Response<super::StoreResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/StoreMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "StoreMemory"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn bulk_store(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BulkStoreRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BulkStoreResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/BulkStore",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "BulkStore"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn retrieve_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RetrieveRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RetrieveResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/RetrieveMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "RetrieveMemory"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn optimize_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::OptimizeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::OptimizeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/OptimizeMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "OptimizeMemory"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn copy_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CopyMemoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CopyMemoryResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/CopyMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "CopyMemory"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn merge_memories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MergeMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MergeMemoriesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/MergeMemories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "MergeMemories"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn find_duplicates(
+            &mut self,
+            request: impl tonic::IntoRequest<super::FindDuplicatesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::FindDuplicatesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/FindDuplicates",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "FindDuplicates"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn delete_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteMemoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteMemoryResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/DeleteMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "DeleteMemory"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn update_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateMemoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdateMemoryResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/UpdateMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "UpdateMemory"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_history(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetHistoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetHistoryResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetHistory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetHistory"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn rollback_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RollbackRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RollbackResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/RollbackMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "RollbackMemory"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn pin_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PinMemoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PinMemoryResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/PinMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "PinMemory"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn unpin_memory(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UnpinMemoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PinMemoryResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/UnpinMemory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "UnpinMemory"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Context operations
+        pub async fn get_context(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ContextRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ContextResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetContext",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetContext"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_context_stream(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ContextRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::ContextChunk>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetContextStream",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetContextStream"),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn update_context(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateContextRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdateContextResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/UpdateContext",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "UpdateContext"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn predict_context(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PredictRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PredictResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/PredictContext",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "PredictContext"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn reset_session(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ResetSessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ResetSessionResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/ResetSession",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "ResetSession"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Mode management
+        pub async fn switch_mode(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SwitchModeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SwitchModeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/SwitchMode",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "SwitchMode"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn analyze_mode(
+            &mut self,
+            request: impl tonic::IntoRequest<super::AnalyzeModeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::AnalyzeModeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/AnalyzeMode",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "AnalyzeMode"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Analytics
+        pub async fn get_metrics(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MetricsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MetricsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetMetrics",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetMetrics"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn track_usage(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UsageRequest>,
+        ) -> std::result::Result<tonic::Response<super::UsageResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/TrackUsage",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "TrackUsage"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Memory Bank operations
+        pub async fn store_memory_bank(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MemoryBankStoreRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MemoryBankStoreResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/StoreMemoryBank",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "StoreMemoryBank"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_memory_bank_context(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MemoryBankContextRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MemoryBankContextResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetMemoryBankContext",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new(
+                        "smart_memory.SmartMemoryMcp",
+                        "GetMemoryBankContext",
+                    ),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn optimize_memory_bank(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MemoryBankOptimizeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MemoryBankOptimizeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/OptimizeMemoryBank",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "OptimizeMemoryBank"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_memory_bank_stats(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MemoryBankStatsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MemoryBankStatsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetMemoryBankStats",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetMemoryBankStats"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn merge_categories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MergeCategoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MergeCategoriesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/MergeCategories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "MergeCategories"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn rename_category(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RenameCategoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MergeCategoriesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/RenameCategory",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "RenameCategory"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// UMB command handler
+        pub async fn handle_umb_command(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UmbCommandRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UmbCommandResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/HandleUmbCommand",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "HandleUmbCommand"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Search operations
+        pub async fn search_content_regex(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RegexSearchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RegexSearchResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/SearchContentRegex",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "SearchContentRegex"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn search_memories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SearchMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SearchMemoriesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/SearchMemories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "SearchMemories"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn filter_by_tags(
+            &mut self,
+            request: impl tonic::IntoRequest<super::FilterByTagsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::FilterByTagsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/FilterByTags",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "FilterByTags"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_memories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListMemoriesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/ListMemories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "ListMemories"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_hot_memories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetHotMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetHotMemoriesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetHotMemories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetHotMemories"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_related(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetRelatedRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetRelatedResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetRelated",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetRelated"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn diff_memories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DiffMemoriesRequest>,
+        ) -> std::result::Result<tonic::Response<super::DiffResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/DiffMemories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "DiffMemories"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Links between memories
+        pub async fn link_memories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::LinkMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::LinkMemoriesResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/LinkMemories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "LinkMemories"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_linked(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetLinkedRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetLinkedResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetLinked",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetLinked"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Configuration
+        pub async fn get_config(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetConfigRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetConfigResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetConfig",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetConfig"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Diagnostics
+        pub async fn get_size_distribution(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetSizeDistributionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetSizeDistributionResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetSizeDistribution",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetSizeDistribution"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Server logs
+        pub async fn get_logs(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetLogsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetLogsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/GetLogs",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetLogs"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn stream_logs(
+            &mut self,
+            request: impl tonic::IntoRequest<super::StreamLogsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::LogRecord>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/StreamLogs",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "StreamLogs"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        /// Backups
+        pub async fn verify_backup(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VerifyBackupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::VerifyBackupResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/VerifyBackup",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "VerifyBackup"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn compact(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CompactRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CompactResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/Compact",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "Compact"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn vacuum(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VacuumRequest>,
+        ) -> std::result::Result<tonic::Response<super::VacuumResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/Vacuum",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "Vacuum"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Maintenance; requires the admin key when REQUIRE_ADMIN_KEY is set
+        pub async fn recalculate_tokens(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RecalculateTokensRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RecalculateTokensResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/RecalculateTokens",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "RecalculateTokens"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn prepare_restore(
+            &mut self,
+            request: impl tonic::IntoRequest<super::PrepareRestoreRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PrepareRestoreResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/PrepareRestore",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "PrepareRestore"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn restore_latest(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RestoreLatestRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RestoreLatestResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/RestoreLatest",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "RestoreLatest"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Checkpoints that speculative writes can be rolled back to
+        pub async fn create_snapshot(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateSnapshotRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CreateSnapshotResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/CreateSnapshot",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "CreateSnapshot"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn rollback_snapshot(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RollbackSnapshotRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RollbackSnapshotResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/RollbackSnapshot",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "RollbackSnapshot"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn commit_snapshot(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CommitSnapshotRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CommitSnapshotResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/CommitSnapshot",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "CommitSnapshot"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        /// Sync between server instances
+        pub async fn memory_bank_sync(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SyncRequest>,
+        ) -> std::result::Result<tonic::Response<super::SyncResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/MemoryBankSync",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "MemoryBankSync"),
+                );
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn sync_export(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SyncExportRequest>,
+        ) -> std::result::Result<tonic::Response<super::SyncPayload>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/SyncExport",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "SyncExport"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn sync_import(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SyncImportRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SyncImportResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/SyncImport",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "SyncImport"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// Migration between server instances
+        pub async fn export_memories(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ExportMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::ExportChunk>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/ExportMemories",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "ExportMemories"),
+                );
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn import_memories(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::ImportChunk>,
+        ) -> std::result::Result<tonic::Response<super::ImportResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/smart_memory.SmartMemoryMcp/ImportMemories",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(
+                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "ImportMemories"),
+                );
+            self.inner.client_streaming(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod health_check_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with HealthCheckServer.
+    #[async_trait]
+    pub trait HealthCheck: Send + Sync + 'static {
+        /// Check if the server is running
+        async fn check(
+            &self,
+            request: tonic::Request<super::HealthCheckRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::HealthCheckResponse>,
+            tonic::Status,
+        >;
+        /// Get server version and status
+        async fn get_status(
+            &self,
+            request: tonic::Request<super::StatusRequest>,
+        ) -> std::result::Result<tonic::Response<super::StatusResponse>, tonic::Status>;
+    }
+    /// Health check service definition
+    #[derive(Debug)]
+    pub struct HealthCheckServer<T: HealthCheck> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: HealthCheck> HealthCheckServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
             F: tonic::service::Interceptor,
-            T::ResponseBody: Default,
-            T: tonic::codegen::Service<
-                http::Request<tonic::body::BoxBody>,
-                Response = http::Response<
-                    <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
-                >,
-            >,
-            <T as tonic::codegen::Service<
-                http::Request<tonic::body::BoxBody>,
-            >>::Error: Into<StdError> + Send + Sync,
         {
-            SmartMemoryMcpClient::new(InterceptedService::new(inner, interceptor))
+            InterceptedService::new(Self::new(inner), interceptor)
         }
-        /// Compress requests with the given encoding.
-        ///
-        /// This requires the server to support it otherwise it might respond with an
-        /// error.
+        /// Enable decompressing requests with the given encoding.
         #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.inner = self.inner.send_compressed(encoding);
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
             self
         }
-        /// Enable decompressing responses.
+        /// Compress responses with the given encoding, if the client supports it.
         #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.inner = self.inner.accept_compressed(encoding);
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
             self
         }
         /// Limits the maximum size of a decoded message.
@@ -780,7 +3232,7 @@ pub mod smart_memory_mcp_client {
         /// Default: `4MB`
         #[must_use]
         pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
-            self.inner = self.inner.max_decoding_message_size(limit);
+            self.max_decoding_message_size = Some(limit);
             self
         }
         /// Limits the maximum size of an encoded message.
@@ -788,429 +3240,559 @@ pub mod smart_memory_mcp_client {
         /// Default: `usize::MAX`
         #[must_use]
         pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
-            self.inner = self.inner.max_encoding_message_size(limit);
+            self.max_encoding_message_size = Some(limit);
             self
         }
-        /// Memory management
-        pub async fn store_memory(
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for HealthCheckServer<T>
+    where
+        T: HealthCheck,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
             &mut self,
-            request: impl tonic::IntoRequest<super::StoreRequest>,
-        ) -> std::result::Result<tonic::Response<super::StoreResponse>, tonic::Status> {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/StoreMemory",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "StoreMemory"));
-            self.inner.unary(req, path, codec).await
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
         }
-        pub async fn retrieve_memory(
-            &mut self,
-            request: impl tonic::IntoRequest<super::RetrieveRequest>,
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/smart_memory.HealthCheck/Check" => {
+                    #[allow(non_camel_case_types)]
+                    struct CheckSvc<T: HealthCheck>(pub Arc<T>);
+                    impl<
+                        T: HealthCheck,
+                    > tonic::server::UnaryService<super::HealthCheckRequest>
+                    for CheckSvc<T> {
+                        type Response = super::HealthCheckResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::HealthCheckRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as HealthCheck>::check(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CheckSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.HealthCheck/GetStatus" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetStatusSvc<T: HealthCheck>(pub Arc<T>);
+                    impl<
+                        T: HealthCheck,
+                    > tonic::server::UnaryService<super::StatusRequest>
+                    for GetStatusSvc<T> {
+                        type Response = super::StatusResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StatusRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as HealthCheck>::get_status(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetStatusSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", "12")
+                                .header("content-type", "application/grpc")
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T: HealthCheck> Clone for HealthCheckServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    impl<T: HealthCheck> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(Arc::clone(&self.0))
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    impl<T: HealthCheck> tonic::server::NamedService for HealthCheckServer<T> {
+        const NAME: &'static str = "smart_memory.HealthCheck";
+    }
+}
+/// Generated server implementations.
+pub mod smart_memory_mcp_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with SmartMemoryMcpServer.
+    #[async_trait]
+    pub trait SmartMemoryMcp: Send + Sync + 'static {
+        /// Memory management
+        async fn store_memory(
+            &self,
+            request: tonic::Request<super::StoreRequest>,
+        ) -> std::result::Result<tonic::Response<super::StoreResponse>, tonic::Status>;
+        async fn bulk_store(
+            &self,
+            request: tonic::Request<super::BulkStoreRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BulkStoreResponse>,
+            tonic::Status,
+        >;
+        async fn retrieve_memory(
+            &self,
+            request: tonic::Request<super::RetrieveRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RetrieveResponse>,
+            tonic::Status,
+        >;
+        async fn optimize_memory(
+            &self,
+            request: tonic::Request<super::OptimizeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::OptimizeResponse>,
+            tonic::Status,
+        >;
+        async fn copy_memory(
+            &self,
+            request: tonic::Request<super::CopyMemoryRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::RetrieveResponse>,
+            tonic::Response<super::CopyMemoryResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/RetrieveMemory",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "RetrieveMemory"),
-                );
-            self.inner.unary(req, path, codec).await
-        }
-        pub async fn optimize_memory(
-            &mut self,
-            request: impl tonic::IntoRequest<super::OptimizeRequest>,
+        >;
+        async fn merge_memories(
+            &self,
+            request: tonic::Request<super::MergeMemoriesRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::OptimizeResponse>,
+            tonic::Response<super::MergeMemoriesResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/OptimizeMemory",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "OptimizeMemory"),
-                );
-            self.inner.unary(req, path, codec).await
-        }
-        /// Context operations
-        pub async fn get_context(
-            &mut self,
-            request: impl tonic::IntoRequest<super::ContextRequest>,
+        >;
+        async fn find_duplicates(
+            &self,
+            request: tonic::Request<super::FindDuplicatesRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::ContextResponse>,
+            tonic::Response<super::FindDuplicatesResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/GetContext",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetContext"));
-            self.inner.unary(req, path, codec).await
-        }
-        pub async fn update_context(
-            &mut self,
-            request: impl tonic::IntoRequest<super::UpdateContextRequest>,
+        >;
+        async fn delete_memory(
+            &self,
+            request: tonic::Request<super::DeleteMemoryRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::UpdateContextResponse>,
+            tonic::Response<super::DeleteMemoryResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/UpdateContext",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "UpdateContext"));
-            self.inner.unary(req, path, codec).await
-        }
-        pub async fn predict_context(
-            &mut self,
-            request: impl tonic::IntoRequest<super::PredictRequest>,
+        >;
+        async fn update_memory(
+            &self,
+            request: tonic::Request<super::UpdateMemoryRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::PredictResponse>,
+            tonic::Response<super::UpdateMemoryResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/PredictContext",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "PredictContext"),
-                );
-            self.inner.unary(req, path, codec).await
-        }
-        /// Mode management
-        pub async fn switch_mode(
-            &mut self,
-            request: impl tonic::IntoRequest<super::SwitchModeRequest>,
+        >;
+        async fn get_history(
+            &self,
+            request: tonic::Request<super::GetHistoryRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::SwitchModeResponse>,
+            tonic::Response<super::GetHistoryResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/SwitchMode",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "SwitchMode"));
-            self.inner.unary(req, path, codec).await
-        }
-        pub async fn analyze_mode(
-            &mut self,
-            request: impl tonic::IntoRequest<super::AnalyzeModeRequest>,
+        >;
+        async fn rollback_memory(
+            &self,
+            request: tonic::Request<super::RollbackRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::AnalyzeModeResponse>,
+            tonic::Response<super::RollbackResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/AnalyzeMode",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "AnalyzeMode"));
-            self.inner.unary(req, path, codec).await
-        }
-        /// Analytics
-        pub async fn get_metrics(
-            &mut self,
-            request: impl tonic::IntoRequest<super::MetricsRequest>,
+        >;
+        async fn pin_memory(
+            &self,
+            request: tonic::Request<super::PinMemoryRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::MetricsResponse>,
+            tonic::Response<super::PinMemoryResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/GetMetrics",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetMetrics"));
-            self.inner.unary(req, path, codec).await
-        }
-        pub async fn track_usage(
-            &mut self,
-            request: impl tonic::IntoRequest<super::UsageRequest>,
-        ) -> std::result::Result<tonic::Response<super::UsageResponse>, tonic::Status> {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/TrackUsage",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("smart_memory.SmartMemoryMcp", "TrackUsage"));
-            self.inner.unary(req, path, codec).await
-        }
+        >;
+        async fn unpin_memory(
+            &self,
+            request: tonic::Request<super::UnpinMemoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PinMemoryResponse>,
+            tonic::Status,
+        >;
+        /// Context operations
+        async fn get_context(
+            &self,
+            request: tonic::Request<super::ContextRequest>,
+        ) -> std::result::Result<tonic::Response<super::ContextResponse>, tonic::Status>;
+        /// Server streaming response type for the GetContextStream method.
+        type GetContextStreamStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::ContextChunk, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn get_context_stream(
+            &self,
+            request: tonic::Request<super::ContextRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::GetContextStreamStream>,
+            tonic::Status,
+        >;
+        async fn update_context(
+            &self,
+            request: tonic::Request<super::UpdateContextRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdateContextResponse>,
+            tonic::Status,
+        >;
+        async fn predict_context(
+            &self,
+            request: tonic::Request<super::PredictRequest>,
+        ) -> std::result::Result<tonic::Response<super::PredictResponse>, tonic::Status>;
+        async fn reset_session(
+            &self,
+            request: tonic::Request<super::ResetSessionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ResetSessionResponse>,
+            tonic::Status,
+        >;
+        /// Mode management
+        async fn switch_mode(
+            &self,
+            request: tonic::Request<super::SwitchModeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SwitchModeResponse>,
+            tonic::Status,
+        >;
+        async fn analyze_mode(
+            &self,
+            request: tonic::Request<super::AnalyzeModeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::AnalyzeModeResponse>,
+            tonic::Status,
+        >;
+        /// Analytics
+        async fn get_metrics(
+            &self,
+            request: tonic::Request<super::MetricsRequest>,
+        ) -> std::result::Result<tonic::Response<super::MetricsResponse>, tonic::Status>;
+        async fn track_usage(
+            &self,
+            request: tonic::Request<super::UsageRequest>,
+        ) -> std::result::Result<tonic::Response<super::UsageResponse>, tonic::Status>;
         /// Memory Bank operations
-        pub async fn store_memory_bank(
-            &mut self,
-            request: impl tonic::IntoRequest<super::MemoryBankStoreRequest>,
+        async fn store_memory_bank(
+            &self,
+            request: tonic::Request<super::MemoryBankStoreRequest>,
         ) -> std::result::Result<
             tonic::Response<super::MemoryBankStoreResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/StoreMemoryBank",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "StoreMemoryBank"),
-                );
-            self.inner.unary(req, path, codec).await
-        }
-        pub async fn get_memory_bank_context(
-            &mut self,
-            request: impl tonic::IntoRequest<super::MemoryBankContextRequest>,
+        >;
+        async fn get_memory_bank_context(
+            &self,
+            request: tonic::Request<super::MemoryBankContextRequest>,
         ) -> std::result::Result<
             tonic::Response<super::MemoryBankContextResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/GetMemoryBankContext",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new(
-                        "smart_memory.SmartMemoryMcp",
-                        "GetMemoryBankContext",
-                    ),
-                );
-            self.inner.unary(req, path, codec).await
-        }
-        pub async fn optimize_memory_bank(
-            &mut self,
-            request: impl tonic::IntoRequest<super::MemoryBankOptimizeRequest>,
+        >;
+        async fn optimize_memory_bank(
+            &self,
+            request: tonic::Request<super::MemoryBankOptimizeRequest>,
         ) -> std::result::Result<
             tonic::Response<super::MemoryBankOptimizeResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/OptimizeMemoryBank",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "OptimizeMemoryBank"),
-                );
-            self.inner.unary(req, path, codec).await
-        }
-        pub async fn get_memory_bank_stats(
-            &mut self,
-            request: impl tonic::IntoRequest<super::MemoryBankStatsRequest>,
+        >;
+        async fn get_memory_bank_stats(
+            &self,
+            request: tonic::Request<super::MemoryBankStatsRequest>,
         ) -> std::result::Result<
             tonic::Response<super::MemoryBankStatsResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/GetMemoryBankStats",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "GetMemoryBankStats"),
-                );
-            self.inner.unary(req, path, codec).await
-        }
+        >;
+        async fn merge_categories(
+            &self,
+            request: tonic::Request<super::MergeCategoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MergeCategoriesResponse>,
+            tonic::Status,
+        >;
+        async fn rename_category(
+            &self,
+            request: tonic::Request<super::RenameCategoryRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MergeCategoriesResponse>,
+            tonic::Status,
+        >;
         /// UMB command handler
-        pub async fn handle_umb_command(
-            &mut self,
-            request: impl tonic::IntoRequest<super::UmbCommandRequest>,
+        async fn handle_umb_command(
+            &self,
+            request: tonic::Request<super::UmbCommandRequest>,
         ) -> std::result::Result<
             tonic::Response<super::UmbCommandResponse>,
             tonic::Status,
-        > {
-            self.inner
-                .ready()
-                .await
-                .map_err(|e| {
-                    tonic::Status::new(
-                        tonic::Code::Unknown,
-                        format!("Service was not ready: {}", e.into()),
-                    )
-                })?;
-            let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static(
-                "/smart_memory.SmartMemoryMcp/HandleUmbCommand",
-            );
-            let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(
-                    GrpcMethod::new("smart_memory.SmartMemoryMcp", "HandleUmbCommand"),
-                );
-            self.inner.unary(req, path, codec).await
-        }
-    }
-}
-/// Generated server implementations.
-pub mod health_check_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with HealthCheckServer.
-    #[async_trait]
-    pub trait HealthCheck: Send + Sync + 'static {
-        /// Check if the server is running
-        async fn check(
+        >;
+        /// Search operations
+        async fn search_content_regex(
+            &self,
+            request: tonic::Request<super::RegexSearchRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RegexSearchResponse>,
+            tonic::Status,
+        >;
+        async fn search_memories(
+            &self,
+            request: tonic::Request<super::SearchMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SearchMemoriesResponse>,
+            tonic::Status,
+        >;
+        async fn filter_by_tags(
+            &self,
+            request: tonic::Request<super::FilterByTagsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::FilterByTagsResponse>,
+            tonic::Status,
+        >;
+        async fn list_memories(
+            &self,
+            request: tonic::Request<super::ListMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListMemoriesResponse>,
+            tonic::Status,
+        >;
+        async fn get_hot_memories(
+            &self,
+            request: tonic::Request<super::GetHotMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetHotMemoriesResponse>,
+            tonic::Status,
+        >;
+        async fn get_related(
+            &self,
+            request: tonic::Request<super::GetRelatedRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetRelatedResponse>,
+            tonic::Status,
+        >;
+        async fn diff_memories(
+            &self,
+            request: tonic::Request<super::DiffMemoriesRequest>,
+        ) -> std::result::Result<tonic::Response<super::DiffResponse>, tonic::Status>;
+        /// Links between memories
+        async fn link_memories(
+            &self,
+            request: tonic::Request<super::LinkMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::LinkMemoriesResponse>,
+            tonic::Status,
+        >;
+        async fn get_linked(
+            &self,
+            request: tonic::Request<super::GetLinkedRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetLinkedResponse>,
+            tonic::Status,
+        >;
+        /// Configuration
+        async fn get_config(
+            &self,
+            request: tonic::Request<super::GetConfigRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetConfigResponse>,
+            tonic::Status,
+        >;
+        /// Diagnostics
+        async fn get_size_distribution(
+            &self,
+            request: tonic::Request<super::GetSizeDistributionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetSizeDistributionResponse>,
+            tonic::Status,
+        >;
+        /// Server logs
+        async fn get_logs(
+            &self,
+            request: tonic::Request<super::GetLogsRequest>,
+        ) -> std::result::Result<tonic::Response<super::GetLogsResponse>, tonic::Status>;
+        /// Server streaming response type for the StreamLogs method.
+        type StreamLogsStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::LogRecord, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn stream_logs(
+            &self,
+            request: tonic::Request<super::StreamLogsRequest>,
+        ) -> std::result::Result<tonic::Response<Self::StreamLogsStream>, tonic::Status>;
+        /// Backups
+        async fn verify_backup(
+            &self,
+            request: tonic::Request<super::VerifyBackupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::VerifyBackupResponse>,
+            tonic::Status,
+        >;
+        async fn compact(
+            &self,
+            request: tonic::Request<super::CompactRequest>,
+        ) -> std::result::Result<tonic::Response<super::CompactResponse>, tonic::Status>;
+        async fn vacuum(
+            &self,
+            request: tonic::Request<super::VacuumRequest>,
+        ) -> std::result::Result<tonic::Response<super::VacuumResponse>, tonic::Status>;
+        /// Maintenance; requires the admin key when REQUIRE_ADMIN_KEY is set
+        async fn recalculate_tokens(
+            &self,
+            request: tonic::Request<super::RecalculateTokensRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RecalculateTokensResponse>,
+            tonic::Status,
+        >;
+        async fn prepare_restore(
+            &self,
+            request: tonic::Request<super::PrepareRestoreRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PrepareRestoreResponse>,
+            tonic::Status,
+        >;
+        async fn restore_latest(
+            &self,
+            request: tonic::Request<super::RestoreLatestRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RestoreLatestResponse>,
+            tonic::Status,
+        >;
+        /// Checkpoints that speculative writes can be rolled back to
+        async fn create_snapshot(
+            &self,
+            request: tonic::Request<super::CreateSnapshotRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CreateSnapshotResponse>,
+            tonic::Status,
+        >;
+        async fn rollback_snapshot(
+            &self,
+            request: tonic::Request<super::RollbackSnapshotRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RollbackSnapshotResponse>,
+            tonic::Status,
+        >;
+        async fn commit_snapshot(
             &self,
-            request: tonic::Request<super::HealthCheckRequest>,
+            request: tonic::Request<super::CommitSnapshotRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::HealthCheckResponse>,
+            tonic::Response<super::CommitSnapshotResponse>,
             tonic::Status,
         >;
-        /// Get server version and status
-        async fn get_status(
+        /// Sync between server instances
+        async fn memory_bank_sync(
             &self,
-            request: tonic::Request<super::StatusRequest>,
-        ) -> std::result::Result<tonic::Response<super::StatusResponse>, tonic::Status>;
+            request: tonic::Request<super::SyncRequest>,
+        ) -> std::result::Result<tonic::Response<super::SyncResponse>, tonic::Status>;
+        async fn sync_export(
+            &self,
+            request: tonic::Request<super::SyncExportRequest>,
+        ) -> std::result::Result<tonic::Response<super::SyncPayload>, tonic::Status>;
+        async fn sync_import(
+            &self,
+            request: tonic::Request<super::SyncImportRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SyncImportResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the ExportMemories method.
+        type ExportMemoriesStream: tonic::codegen::tokio_stream::Stream<
+                Item = std::result::Result<super::ExportChunk, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        /// Migration between server instances
+        async fn export_memories(
+            &self,
+            request: tonic::Request<super::ExportMemoriesRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::ExportMemoriesStream>,
+            tonic::Status,
+        >;
+        async fn import_memories(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::ImportChunk>>,
+        ) -> std::result::Result<tonic::Response<super::ImportResponse>, tonic::Status>;
     }
-    /// Health check service definition
+    /// Main MCP service definition
     #[derive(Debug)]
-    pub struct HealthCheckServer<T: HealthCheck> {
+    pub struct SmartMemoryMcpServer<T: SmartMemoryMcp> {
         inner: _Inner<T>,
         accept_compression_encodings: EnabledCompressionEncodings,
         send_compression_encodings: EnabledCompressionEncodings,
@@ -1218,7 +3800,7 @@ pub mod health_check_server {
         max_encoding_message_size: Option<usize>,
     }
     struct _Inner<T>(Arc<T>);
-    impl<T: HealthCheck> HealthCheckServer<T> {
+    impl<T: SmartMemoryMcp> SmartMemoryMcpServer<T> {
         pub fn new(inner: T) -> Self {
             Self::from_arc(Arc::new(inner))
         }
@@ -1270,9 +3852,9 @@ pub mod health_check_server {
             self
         }
     }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for HealthCheckServer<T>
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for SmartMemoryMcpServer<T>
     where
-        T: HealthCheck,
+        T: SmartMemoryMcp,
         B: Body + Send + 'static,
         B::Error: Into<StdError> + Send + 'static,
     {
@@ -1288,25 +3870,1704 @@ pub mod health_check_server {
         fn call(&mut self, req: http::Request<B>) -> Self::Future {
             let inner = self.inner.clone();
             match req.uri().path() {
-                "/smart_memory.HealthCheck/Check" => {
+                "/smart_memory.SmartMemoryMcp/StoreMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct StoreMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::StoreRequest>
+                    for StoreMemorySvc<T> {
+                        type Response = super::StoreResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StoreRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::store_memory(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StoreMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/BulkStore" => {
+                    #[allow(non_camel_case_types)]
+                    struct BulkStoreSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::BulkStoreRequest>
+                    for BulkStoreSvc<T> {
+                        type Response = super::BulkStoreResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BulkStoreRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::bulk_store(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = BulkStoreSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/RetrieveMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct RetrieveMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::RetrieveRequest>
+                    for RetrieveMemorySvc<T> {
+                        type Response = super::RetrieveResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RetrieveRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::retrieve_memory(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RetrieveMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/OptimizeMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct OptimizeMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::OptimizeRequest>
+                    for OptimizeMemorySvc<T> {
+                        type Response = super::OptimizeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::OptimizeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::optimize_memory(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = OptimizeMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/CopyMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct CopyMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::CopyMemoryRequest>
+                    for CopyMemorySvc<T> {
+                        type Response = super::CopyMemoryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CopyMemoryRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::copy_memory(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = CopyMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/MergeMemories" => {
+                    #[allow(non_camel_case_types)]
+                    struct MergeMemoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::MergeMemoriesRequest>
+                    for MergeMemoriesSvc<T> {
+                        type Response = super::MergeMemoriesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MergeMemoriesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::merge_memories(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = MergeMemoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/FindDuplicates" => {
+                    #[allow(non_camel_case_types)]
+                    struct FindDuplicatesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::FindDuplicatesRequest>
+                    for FindDuplicatesSvc<T> {
+                        type Response = super::FindDuplicatesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::FindDuplicatesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::find_duplicates(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = FindDuplicatesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/DeleteMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::DeleteMemoryRequest>
+                    for DeleteMemorySvc<T> {
+                        type Response = super::DeleteMemoryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteMemoryRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::delete_memory(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DeleteMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/UpdateMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdateMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::UpdateMemoryRequest>
+                    for UpdateMemorySvc<T> {
+                        type Response = super::UpdateMemoryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateMemoryRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::update_memory(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UpdateMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetHistory" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetHistorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::GetHistoryRequest>
+                    for GetHistorySvc<T> {
+                        type Response = super::GetHistoryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetHistoryRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::get_history(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetHistorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/RollbackMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct RollbackMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::RollbackRequest>
+                    for RollbackMemorySvc<T> {
+                        type Response = super::RollbackResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RollbackRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::rollback_memory(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RollbackMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/PinMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct PinMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::PinMemoryRequest>
+                    for PinMemorySvc<T> {
+                        type Response = super::PinMemoryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PinMemoryRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::pin_memory(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PinMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/UnpinMemory" => {
+                    #[allow(non_camel_case_types)]
+                    struct UnpinMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::UnpinMemoryRequest>
+                    for UnpinMemorySvc<T> {
+                        type Response = super::PinMemoryResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UnpinMemoryRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::unpin_memory(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UnpinMemorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetContext" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetContextSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::ContextRequest>
+                    for GetContextSvc<T> {
+                        type Response = super::ContextResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ContextRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::get_context(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetContextSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetContextStream" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetContextStreamSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::ServerStreamingService<super::ContextRequest>
+                    for GetContextStreamSvc<T> {
+                        type Response = super::ContextChunk;
+                        type ResponseStream = T::GetContextStreamStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ContextRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::get_context_stream(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetContextStreamSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/UpdateContext" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdateContextSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::UpdateContextRequest>
+                    for UpdateContextSvc<T> {
+                        type Response = super::UpdateContextResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateContextRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::update_context(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UpdateContextSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/PredictContext" => {
+                    #[allow(non_camel_case_types)]
+                    struct PredictContextSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::PredictRequest>
+                    for PredictContextSvc<T> {
+                        type Response = super::PredictResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PredictRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::predict_context(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PredictContextSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/ResetSession" => {
+                    #[allow(non_camel_case_types)]
+                    struct ResetSessionSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::ResetSessionRequest>
+                    for ResetSessionSvc<T> {
+                        type Response = super::ResetSessionResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ResetSessionRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::reset_session(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ResetSessionSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/SwitchMode" => {
+                    #[allow(non_camel_case_types)]
+                    struct SwitchModeSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::SwitchModeRequest>
+                    for SwitchModeSvc<T> {
+                        type Response = super::SwitchModeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SwitchModeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::switch_mode(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SwitchModeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/AnalyzeMode" => {
+                    #[allow(non_camel_case_types)]
+                    struct AnalyzeModeSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::AnalyzeModeRequest>
+                    for AnalyzeModeSvc<T> {
+                        type Response = super::AnalyzeModeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AnalyzeModeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::analyze_mode(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = AnalyzeModeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetMetrics" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetMetricsSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::MetricsRequest>
+                    for GetMetricsSvc<T> {
+                        type Response = super::MetricsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MetricsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::get_metrics(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetMetricsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/TrackUsage" => {
+                    #[allow(non_camel_case_types)]
+                    struct TrackUsageSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::UsageRequest>
+                    for TrackUsageSvc<T> {
+                        type Response = super::UsageResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UsageRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::track_usage(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = TrackUsageSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/StoreMemoryBank" => {
+                    #[allow(non_camel_case_types)]
+                    struct StoreMemoryBankSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::MemoryBankStoreRequest>
+                    for StoreMemoryBankSvc<T> {
+                        type Response = super::MemoryBankStoreResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MemoryBankStoreRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::store_memory_bank(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StoreMemoryBankSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetMemoryBankContext" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetMemoryBankContextSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::MemoryBankContextRequest>
+                    for GetMemoryBankContextSvc<T> {
+                        type Response = super::MemoryBankContextResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MemoryBankContextRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::get_memory_bank_context(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetMemoryBankContextSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/OptimizeMemoryBank" => {
+                    #[allow(non_camel_case_types)]
+                    struct OptimizeMemoryBankSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::MemoryBankOptimizeRequest>
+                    for OptimizeMemoryBankSvc<T> {
+                        type Response = super::MemoryBankOptimizeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MemoryBankOptimizeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::optimize_memory_bank(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = OptimizeMemoryBankSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetMemoryBankStats" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetMemoryBankStatsSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::MemoryBankStatsRequest>
+                    for GetMemoryBankStatsSvc<T> {
+                        type Response = super::MemoryBankStatsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MemoryBankStatsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::get_memory_bank_stats(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetMemoryBankStatsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/MergeCategories" => {
+                    #[allow(non_camel_case_types)]
+                    struct MergeCategoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::MergeCategoriesRequest>
+                    for MergeCategoriesSvc<T> {
+                        type Response = super::MergeCategoriesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MergeCategoriesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::merge_categories(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = MergeCategoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/RenameCategory" => {
+                    #[allow(non_camel_case_types)]
+                    struct RenameCategorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::RenameCategoryRequest>
+                    for RenameCategorySvc<T> {
+                        type Response = super::MergeCategoriesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RenameCategoryRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::rename_category(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = RenameCategorySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/HandleUmbCommand" => {
+                    #[allow(non_camel_case_types)]
+                    struct HandleUmbCommandSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::UmbCommandRequest>
+                    for HandleUmbCommandSvc<T> {
+                        type Response = super::UmbCommandResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UmbCommandRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::handle_umb_command(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = HandleUmbCommandSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/SearchContentRegex" => {
+                    #[allow(non_camel_case_types)]
+                    struct SearchContentRegexSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::RegexSearchRequest>
+                    for SearchContentRegexSvc<T> {
+                        type Response = super::RegexSearchResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RegexSearchRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::search_content_regex(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SearchContentRegexSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/SearchMemories" => {
+                    #[allow(non_camel_case_types)]
+                    struct SearchMemoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::SearchMemoriesRequest>
+                    for SearchMemoriesSvc<T> {
+                        type Response = super::SearchMemoriesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SearchMemoriesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::search_memories(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SearchMemoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/FilterByTags" => {
+                    #[allow(non_camel_case_types)]
+                    struct FilterByTagsSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::FilterByTagsRequest>
+                    for FilterByTagsSvc<T> {
+                        type Response = super::FilterByTagsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::FilterByTagsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::filter_by_tags(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = FilterByTagsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/ListMemories" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListMemoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::ListMemoriesRequest>
+                    for ListMemoriesSvc<T> {
+                        type Response = super::ListMemoriesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListMemoriesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::list_memories(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListMemoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetHotMemories" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetHotMemoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::GetHotMemoriesRequest>
+                    for GetHotMemoriesSvc<T> {
+                        type Response = super::GetHotMemoriesResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetHotMemoriesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::get_hot_memories(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetHotMemoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetRelated" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetRelatedSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::GetRelatedRequest>
+                    for GetRelatedSvc<T> {
+                        type Response = super::GetRelatedResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetRelatedRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::get_related(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetRelatedSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/DiffMemories" => {
+                    #[allow(non_camel_case_types)]
+                    struct DiffMemoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::DiffMemoriesRequest>
+                    for DiffMemoriesSvc<T> {
+                        type Response = super::DiffResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DiffMemoriesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::diff_memories(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DiffMemoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/LinkMemories" => {
                     #[allow(non_camel_case_types)]
-                    struct CheckSvc<T: HealthCheck>(pub Arc<T>);
+                    struct LinkMemoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
-                        T: HealthCheck,
-                    > tonic::server::UnaryService<super::HealthCheckRequest>
-                    for CheckSvc<T> {
-                        type Response = super::HealthCheckResponse;
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::LinkMemoriesRequest>
+                    for LinkMemoriesSvc<T> {
+                        type Response = super::LinkMemoriesResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::HealthCheckRequest>,
+                            request: tonic::Request<super::LinkMemoriesRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as HealthCheck>::check(&inner, request).await
+                                <T as SmartMemoryMcp>::link_memories(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1318,7 +5579,7 @@ pub mod health_check_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = CheckSvc(inner);
+                        let method = LinkMemoriesSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1334,25 +5595,25 @@ pub mod health_check_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.HealthCheck/GetStatus" => {
+                "/smart_memory.SmartMemoryMcp/GetLinked" => {
                     #[allow(non_camel_case_types)]
-                    struct GetStatusSvc<T: HealthCheck>(pub Arc<T>);
+                    struct GetLinkedSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
-                        T: HealthCheck,
-                    > tonic::server::UnaryService<super::StatusRequest>
-                    for GetStatusSvc<T> {
-                        type Response = super::StatusResponse;
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::GetLinkedRequest>
+                    for GetLinkedSvc<T> {
+                        type Response = super::GetLinkedResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::StatusRequest>,
+                            request: tonic::Request<super::GetLinkedRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as HealthCheck>::get_status(&inner, request).await
+                                <T as SmartMemoryMcp>::get_linked(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1364,266 +5625,41 @@ pub mod health_check_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetStatusSvc(inner);
+                        let method = GetLinkedSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
                                 accept_compression_encodings,
                                 send_compression_encodings,
-                            )
-                            .apply_max_message_size_config(
-                                max_decoding_message_size,
-                                max_encoding_message_size,
-                            );
-                        let res = grpc.unary(method, req).await;
-                        Ok(res)
-                    };
-                    Box::pin(fut)
-                }
-                _ => {
-                    Box::pin(async move {
-                        Ok(
-                            http::Response::builder()
-                                .status(200)
-                                .header("grpc-status", "12")
-                                .header("content-type", "application/grpc")
-                                .body(empty_body())
-                                .unwrap(),
-                        )
-                    })
-                }
-            }
-        }
-    }
-    impl<T: HealthCheck> Clone for HealthCheckServer<T> {
-        fn clone(&self) -> Self {
-            let inner = self.inner.clone();
-            Self {
-                inner,
-                accept_compression_encodings: self.accept_compression_encodings,
-                send_compression_encodings: self.send_compression_encodings,
-                max_decoding_message_size: self.max_decoding_message_size,
-                max_encoding_message_size: self.max_encoding_message_size,
-            }
-        }
-    }
-    impl<T: HealthCheck> Clone for _Inner<T> {
-        fn clone(&self) -> Self {
-            Self(Arc::clone(&self.0))
-        }
-    }
-    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            write!(f, "{:?}", self.0)
-        }
-    }
-    impl<T: HealthCheck> tonic::server::NamedService for HealthCheckServer<T> {
-        const NAME: &'static str = "smart_memory.HealthCheck";
-    }
-}
-/// Generated server implementations.
-pub mod smart_memory_mcp_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with SmartMemoryMcpServer.
-    #[async_trait]
-    pub trait SmartMemoryMcp: Send + Sync + 'static {
-        /// Memory management
-        async fn store_memory(
-            &self,
-            request: tonic::Request<super::StoreRequest>,
-        ) -> std::result::Result<tonic::Response<super::StoreResponse>, tonic::Status>;
-        async fn retrieve_memory(
-            &self,
-            request: tonic::Request<super::RetrieveRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::RetrieveResponse>,
-            tonic::Status,
-        >;
-        async fn optimize_memory(
-            &self,
-            request: tonic::Request<super::OptimizeRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::OptimizeResponse>,
-            tonic::Status,
-        >;
-        /// Context operations
-        async fn get_context(
-            &self,
-            request: tonic::Request<super::ContextRequest>,
-        ) -> std::result::Result<tonic::Response<super::ContextResponse>, tonic::Status>;
-        async fn update_context(
-            &self,
-            request: tonic::Request<super::UpdateContextRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::UpdateContextResponse>,
-            tonic::Status,
-        >;
-        async fn predict_context(
-            &self,
-            request: tonic::Request<super::PredictRequest>,
-        ) -> std::result::Result<tonic::Response<super::PredictResponse>, tonic::Status>;
-        /// Mode management
-        async fn switch_mode(
-            &self,
-            request: tonic::Request<super::SwitchModeRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::SwitchModeResponse>,
-            tonic::Status,
-        >;
-        async fn analyze_mode(
-            &self,
-            request: tonic::Request<super::AnalyzeModeRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::AnalyzeModeResponse>,
-            tonic::Status,
-        >;
-        /// Analytics
-        async fn get_metrics(
-            &self,
-            request: tonic::Request<super::MetricsRequest>,
-        ) -> std::result::Result<tonic::Response<super::MetricsResponse>, tonic::Status>;
-        async fn track_usage(
-            &self,
-            request: tonic::Request<super::UsageRequest>,
-        ) -> std::result::Result<tonic::Response<super::UsageResponse>, tonic::Status>;
-        /// Memory Bank operations
-        async fn store_memory_bank(
-            &self,
-            request: tonic::Request<super::MemoryBankStoreRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::MemoryBankStoreResponse>,
-            tonic::Status,
-        >;
-        async fn get_memory_bank_context(
-            &self,
-            request: tonic::Request<super::MemoryBankContextRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::MemoryBankContextResponse>,
-            tonic::Status,
-        >;
-        async fn optimize_memory_bank(
-            &self,
-            request: tonic::Request<super::MemoryBankOptimizeRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::MemoryBankOptimizeResponse>,
-            tonic::Status,
-        >;
-        async fn get_memory_bank_stats(
-            &self,
-            request: tonic::Request<super::MemoryBankStatsRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::MemoryBankStatsResponse>,
-            tonic::Status,
-        >;
-        /// UMB command handler
-        async fn handle_umb_command(
-            &self,
-            request: tonic::Request<super::UmbCommandRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::UmbCommandResponse>,
-            tonic::Status,
-        >;
-    }
-    /// Main MCP service definition
-    #[derive(Debug)]
-    pub struct SmartMemoryMcpServer<T: SmartMemoryMcp> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-        max_decoding_message_size: Option<usize>,
-        max_encoding_message_size: Option<usize>,
-    }
-    struct _Inner<T>(Arc<T>);
-    impl<T: SmartMemoryMcp> SmartMemoryMcpServer<T> {
-        pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
-        }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            let inner = _Inner(inner);
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-                max_decoding_message_size: None,
-                max_encoding_message_size: None,
-            }
-        }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
-        ) -> InterceptedService<Self, F>
-        where
-            F: tonic::service::Interceptor,
-        {
-            InterceptedService::new(Self::new(inner), interceptor)
-        }
-        /// Enable decompressing requests with the given encoding.
-        #[must_use]
-        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.accept_compression_encodings.enable(encoding);
-            self
-        }
-        /// Compress responses with the given encoding, if the client supports it.
-        #[must_use]
-        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
-            self.send_compression_encodings.enable(encoding);
-            self
-        }
-        /// Limits the maximum size of a decoded message.
-        ///
-        /// Default: `4MB`
-        #[must_use]
-        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
-            self.max_decoding_message_size = Some(limit);
-            self
-        }
-        /// Limits the maximum size of an encoded message.
-        ///
-        /// Default: `usize::MAX`
-        #[must_use]
-        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
-            self.max_encoding_message_size = Some(limit);
-            self
-        }
-    }
-    impl<T, B> tonic::codegen::Service<http::Request<B>> for SmartMemoryMcpServer<T>
-    where
-        T: SmartMemoryMcp,
-        B: Body + Send + 'static,
-        B::Error: Into<StdError> + Send + 'static,
-    {
-        type Response = http::Response<tonic::body::BoxBody>;
-        type Error = std::convert::Infallible;
-        type Future = BoxFuture<Self::Response, Self::Error>;
-        fn poll_ready(
-            &mut self,
-            _cx: &mut Context<'_>,
-        ) -> Poll<std::result::Result<(), Self::Error>> {
-            Poll::Ready(Ok(()))
-        }
-        fn call(&mut self, req: http::Request<B>) -> Self::Future {
-            let inner = self.inner.clone();
-            match req.uri().path() {
-                "/smart_memory.SmartMemoryMcp/StoreMemory" => {
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/GetConfig" => {
                     #[allow(non_camel_case_types)]
-                    struct StoreMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct GetConfigSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::StoreRequest>
-                    for StoreMemorySvc<T> {
-                        type Response = super::StoreResponse;
+                    > tonic::server::UnaryService<super::GetConfigRequest>
+                    for GetConfigSvc<T> {
+                        type Response = super::GetConfigResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::StoreRequest>,
+                            request: tonic::Request<super::GetConfigRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::store_memory(&inner, request).await
+                                <T as SmartMemoryMcp>::get_config(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1635,7 +5671,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = StoreMemorySvc(inner);
+                        let method = GetConfigSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1651,25 +5687,28 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/RetrieveMemory" => {
+                "/smart_memory.SmartMemoryMcp/GetSizeDistribution" => {
                     #[allow(non_camel_case_types)]
-                    struct RetrieveMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct GetSizeDistributionSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::RetrieveRequest>
-                    for RetrieveMemorySvc<T> {
-                        type Response = super::RetrieveResponse;
+                    > tonic::server::UnaryService<super::GetSizeDistributionRequest>
+                    for GetSizeDistributionSvc<T> {
+                        type Response = super::GetSizeDistributionResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::RetrieveRequest>,
+                            request: tonic::Request<super::GetSizeDistributionRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::retrieve_memory(&inner, request)
+                                <T as SmartMemoryMcp>::get_size_distribution(
+                                        &inner,
+                                        request,
+                                    )
                                     .await
                             };
                             Box::pin(fut)
@@ -1682,7 +5721,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = RetrieveMemorySvc(inner);
+                        let method = GetSizeDistributionSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1698,26 +5737,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/OptimizeMemory" => {
+                "/smart_memory.SmartMemoryMcp/GetLogs" => {
                     #[allow(non_camel_case_types)]
-                    struct OptimizeMemorySvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct GetLogsSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::OptimizeRequest>
-                    for OptimizeMemorySvc<T> {
-                        type Response = super::OptimizeResponse;
+                    > tonic::server::UnaryService<super::GetLogsRequest>
+                    for GetLogsSvc<T> {
+                        type Response = super::GetLogsResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::OptimizeRequest>,
+                            request: tonic::Request<super::GetLogsRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::optimize_memory(&inner, request)
-                                    .await
+                                <T as SmartMemoryMcp>::get_logs(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1729,7 +5767,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = OptimizeMemorySvc(inner);
+                        let method = GetLogsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1745,25 +5783,72 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/GetContext" => {
+                "/smart_memory.SmartMemoryMcp/StreamLogs" => {
                     #[allow(non_camel_case_types)]
-                    struct GetContextSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct StreamLogsSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::ContextRequest>
-                    for GetContextSvc<T> {
-                        type Response = super::ContextResponse;
+                    > tonic::server::ServerStreamingService<super::StreamLogsRequest>
+                    for StreamLogsSvc<T> {
+                        type Response = super::LogRecord;
+                        type ResponseStream = T::StreamLogsStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::StreamLogsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::stream_logs(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = StreamLogsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/VerifyBackup" => {
+                    #[allow(non_camel_case_types)]
+                    struct VerifyBackupSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::UnaryService<super::VerifyBackupRequest>
+                    for VerifyBackupSvc<T> {
+                        type Response = super::VerifyBackupResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ContextRequest>,
+                            request: tonic::Request<super::VerifyBackupRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::get_context(&inner, request).await
+                                <T as SmartMemoryMcp>::verify_backup(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1775,7 +5860,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetContextSvc(inner);
+                        let method = VerifyBackupSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1791,25 +5876,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/UpdateContext" => {
+                "/smart_memory.SmartMemoryMcp/Compact" => {
                     #[allow(non_camel_case_types)]
-                    struct UpdateContextSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct CompactSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::UpdateContextRequest>
-                    for UpdateContextSvc<T> {
-                        type Response = super::UpdateContextResponse;
+                    > tonic::server::UnaryService<super::CompactRequest>
+                    for CompactSvc<T> {
+                        type Response = super::CompactResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::UpdateContextRequest>,
+                            request: tonic::Request<super::CompactRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::update_context(&inner, request).await
+                                <T as SmartMemoryMcp>::compact(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1821,7 +5906,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = UpdateContextSvc(inner);
+                        let method = CompactSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1837,26 +5922,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/PredictContext" => {
+                "/smart_memory.SmartMemoryMcp/Vacuum" => {
                     #[allow(non_camel_case_types)]
-                    struct PredictContextSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct VacuumSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::PredictRequest>
-                    for PredictContextSvc<T> {
-                        type Response = super::PredictResponse;
+                    > tonic::server::UnaryService<super::VacuumRequest>
+                    for VacuumSvc<T> {
+                        type Response = super::VacuumResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::PredictRequest>,
+                            request: tonic::Request<super::VacuumRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::predict_context(&inner, request)
-                                    .await
+                                <T as SmartMemoryMcp>::vacuum(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -1868,7 +5952,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = PredictContextSvc(inner);
+                        let method = VacuumSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1884,25 +5968,26 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/SwitchMode" => {
+                "/smart_memory.SmartMemoryMcp/RecalculateTokens" => {
                     #[allow(non_camel_case_types)]
-                    struct SwitchModeSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct RecalculateTokensSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::SwitchModeRequest>
-                    for SwitchModeSvc<T> {
-                        type Response = super::SwitchModeResponse;
+                    > tonic::server::UnaryService<super::RecalculateTokensRequest>
+                    for RecalculateTokensSvc<T> {
+                        type Response = super::RecalculateTokensResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::SwitchModeRequest>,
+                            request: tonic::Request<super::RecalculateTokensRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::switch_mode(&inner, request).await
+                                <T as SmartMemoryMcp>::recalculate_tokens(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -1914,7 +5999,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = SwitchModeSvc(inner);
+                        let method = RecalculateTokensSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1930,25 +6015,26 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/AnalyzeMode" => {
+                "/smart_memory.SmartMemoryMcp/PrepareRestore" => {
                     #[allow(non_camel_case_types)]
-                    struct AnalyzeModeSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct PrepareRestoreSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::AnalyzeModeRequest>
-                    for AnalyzeModeSvc<T> {
-                        type Response = super::AnalyzeModeResponse;
+                    > tonic::server::UnaryService<super::PrepareRestoreRequest>
+                    for PrepareRestoreSvc<T> {
+                        type Response = super::PrepareRestoreResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::AnalyzeModeRequest>,
+                            request: tonic::Request<super::PrepareRestoreRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::analyze_mode(&inner, request).await
+                                <T as SmartMemoryMcp>::prepare_restore(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -1960,7 +6046,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = AnalyzeModeSvc(inner);
+                        let method = PrepareRestoreSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1976,25 +6062,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/GetMetrics" => {
+                "/smart_memory.SmartMemoryMcp/RestoreLatest" => {
                     #[allow(non_camel_case_types)]
-                    struct GetMetricsSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct RestoreLatestSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::MetricsRequest>
-                    for GetMetricsSvc<T> {
-                        type Response = super::MetricsResponse;
+                    > tonic::server::UnaryService<super::RestoreLatestRequest>
+                    for RestoreLatestSvc<T> {
+                        type Response = super::RestoreLatestResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::MetricsRequest>,
+                            request: tonic::Request<super::RestoreLatestRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::get_metrics(&inner, request).await
+                                <T as SmartMemoryMcp>::restore_latest(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -2006,7 +6092,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetMetricsSvc(inner);
+                        let method = RestoreLatestSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2022,25 +6108,26 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/TrackUsage" => {
+                "/smart_memory.SmartMemoryMcp/CreateSnapshot" => {
                     #[allow(non_camel_case_types)]
-                    struct TrackUsageSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct CreateSnapshotSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::UsageRequest>
-                    for TrackUsageSvc<T> {
-                        type Response = super::UsageResponse;
+                    > tonic::server::UnaryService<super::CreateSnapshotRequest>
+                    for CreateSnapshotSvc<T> {
+                        type Response = super::CreateSnapshotResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::UsageRequest>,
+                            request: tonic::Request<super::CreateSnapshotRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::track_usage(&inner, request).await
+                                <T as SmartMemoryMcp>::create_snapshot(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -2052,7 +6139,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = TrackUsageSvc(inner);
+                        let method = CreateSnapshotSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2068,25 +6155,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/StoreMemoryBank" => {
+                "/smart_memory.SmartMemoryMcp/RollbackSnapshot" => {
                     #[allow(non_camel_case_types)]
-                    struct StoreMemoryBankSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct RollbackSnapshotSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::MemoryBankStoreRequest>
-                    for StoreMemoryBankSvc<T> {
-                        type Response = super::MemoryBankStoreResponse;
+                    > tonic::server::UnaryService<super::RollbackSnapshotRequest>
+                    for RollbackSnapshotSvc<T> {
+                        type Response = super::RollbackSnapshotResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::MemoryBankStoreRequest>,
+                            request: tonic::Request<super::RollbackSnapshotRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::store_memory_bank(&inner, request)
+                                <T as SmartMemoryMcp>::rollback_snapshot(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -2099,7 +6186,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = StoreMemoryBankSvc(inner);
+                        let method = RollbackSnapshotSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2115,28 +6202,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/GetMemoryBankContext" => {
+                "/smart_memory.SmartMemoryMcp/CommitSnapshot" => {
                     #[allow(non_camel_case_types)]
-                    struct GetMemoryBankContextSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct CommitSnapshotSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::MemoryBankContextRequest>
-                    for GetMemoryBankContextSvc<T> {
-                        type Response = super::MemoryBankContextResponse;
+                    > tonic::server::UnaryService<super::CommitSnapshotRequest>
+                    for CommitSnapshotSvc<T> {
+                        type Response = super::CommitSnapshotResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::MemoryBankContextRequest>,
+                            request: tonic::Request<super::CommitSnapshotRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::get_memory_bank_context(
-                                        &inner,
-                                        request,
-                                    )
+                                <T as SmartMemoryMcp>::commit_snapshot(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -2149,7 +6233,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetMemoryBankContextSvc(inner);
+                        let method = CommitSnapshotSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2165,25 +6249,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/OptimizeMemoryBank" => {
+                "/smart_memory.SmartMemoryMcp/MemoryBankSync" => {
                     #[allow(non_camel_case_types)]
-                    struct OptimizeMemoryBankSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct MemoryBankSyncSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::MemoryBankOptimizeRequest>
-                    for OptimizeMemoryBankSvc<T> {
-                        type Response = super::MemoryBankOptimizeResponse;
+                    > tonic::server::UnaryService<super::SyncRequest>
+                    for MemoryBankSyncSvc<T> {
+                        type Response = super::SyncResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::MemoryBankOptimizeRequest>,
+                            request: tonic::Request<super::SyncRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::optimize_memory_bank(&inner, request)
+                                <T as SmartMemoryMcp>::memory_bank_sync(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -2196,7 +6280,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = OptimizeMemoryBankSvc(inner);
+                        let method = MemoryBankSyncSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2212,29 +6296,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/GetMemoryBankStats" => {
+                "/smart_memory.SmartMemoryMcp/SyncExport" => {
                     #[allow(non_camel_case_types)]
-                    struct GetMemoryBankStatsSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct SyncExportSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::MemoryBankStatsRequest>
-                    for GetMemoryBankStatsSvc<T> {
-                        type Response = super::MemoryBankStatsResponse;
+                    > tonic::server::UnaryService<super::SyncExportRequest>
+                    for SyncExportSvc<T> {
+                        type Response = super::SyncPayload;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::MemoryBankStatsRequest>,
+                            request: tonic::Request<super::SyncExportRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::get_memory_bank_stats(
-                                        &inner,
-                                        request,
-                                    )
-                                    .await
+                                <T as SmartMemoryMcp>::sync_export(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -2246,7 +6326,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = GetMemoryBankStatsSvc(inner);
+                        let method = SyncExportSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2262,26 +6342,25 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
-                "/smart_memory.SmartMemoryMcp/HandleUmbCommand" => {
+                "/smart_memory.SmartMemoryMcp/SyncImport" => {
                     #[allow(non_camel_case_types)]
-                    struct HandleUmbCommandSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    struct SyncImportSvc<T: SmartMemoryMcp>(pub Arc<T>);
                     impl<
                         T: SmartMemoryMcp,
-                    > tonic::server::UnaryService<super::UmbCommandRequest>
-                    for HandleUmbCommandSvc<T> {
-                        type Response = super::UmbCommandResponse;
+                    > tonic::server::UnaryService<super::SyncImportRequest>
+                    for SyncImportSvc<T> {
+                        type Response = super::SyncImportResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::UmbCommandRequest>,
+                            request: tonic::Request<super::SyncImportRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as SmartMemoryMcp>::handle_umb_command(&inner, request)
-                                    .await
+                                <T as SmartMemoryMcp>::sync_import(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -2293,7 +6372,7 @@ pub mod smart_memory_mcp_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = HandleUmbCommandSvc(inner);
+                        let method = SyncImportSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -2309,6 +6388,101 @@ pub mod smart_memory_mcp_server {
                     };
                     Box::pin(fut)
                 }
+                "/smart_memory.SmartMemoryMcp/ExportMemories" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExportMemoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::ServerStreamingService<super::ExportMemoriesRequest>
+                    for ExportMemoriesSvc<T> {
+                        type Response = super::ExportChunk;
+                        type ResponseStream = T::ExportMemoriesStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ExportMemoriesRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::export_memories(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ExportMemoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/smart_memory.SmartMemoryMcp/ImportMemories" => {
+                    #[allow(non_camel_case_types)]
+                    struct ImportMemoriesSvc<T: SmartMemoryMcp>(pub Arc<T>);
+                    impl<
+                        T: SmartMemoryMcp,
+                    > tonic::server::ClientStreamingService<super::ImportChunk>
+                    for ImportMemoriesSvc<T> {
+                        type Response = super::ImportResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<tonic::Streaming<super::ImportChunk>>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as SmartMemoryMcp>::import_memories(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ImportMemoriesSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
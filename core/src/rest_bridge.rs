@@ -0,0 +1,1017 @@
+//! HTTP REST bridge to the gRPC API
+//!
+//! Exposes the most common RPCs as JSON endpoints for clients that cannot
+//! speak gRPC. Handlers call the service implementations directly, so the
+//! bridge shares the server's store, config and metrics. `/ws/events`
+//! streams memory changes to WebSocket clients as JSON.
+//!
+//! The bridge only starts when `REST_PORT` is set and listens on `REST_HOST`
+//! (default 127.0.0.1). It holds calls to the same API key, rate limits and
+//! deadlines as the gRPC server, and serves HTTPS whenever the gRPC server
+//! uses TLS. Only `/health` is open without the API key.
+
+use axum::extract::connect_info::{ConnectInfo, Connected};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{FromRequestParts, Path, Query, State};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router, Server};
+use hyper::server::conn::AddrStream;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tonic::metadata::MetadataMap;
+use tonic::transport::server::TcpConnectInfo;
+use tonic::{Code, Extensions, Status};
+
+use crate::proto::health_check_server::HealthCheck;
+use crate::proto::smart_memory_mcp_server::SmartMemoryMcp;
+use crate::proto::{
+    ContextRequest, DeleteMemoryRequest, HealthCheckRequest, RetrieveRequest, StoreRequest,
+};
+use crate::service::{
+    self, Deadline, HealthCheckService, MemoryEvent, RateLimiter, SmartMemoryService,
+    StatusResponse,
+};
+
+/// Environment variable holding the port of the REST server, enabling it
+pub const REST_PORT_VAR: &str = "REST_PORT";
+
+/// Environment variable holding the address the REST server listens on
+pub const REST_HOST_VAR: &str = "REST_HOST";
+
+/// Default address of the REST server, reachable from this machine only
+const DEFAULT_REST_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Token budget of `GET /context` when the query sets none, as in the editor extension
+const DEFAULT_CONTEXT_MAX_TOKENS: u32 = 2000;
+
+/// Longest a client may take to complete the TLS handshake
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Get the REST server address from the environment, or `None` when the bridge is disabled
+pub fn rest_addr() -> io::Result<Option<SocketAddr>> {
+    let port = match std::env::var(REST_PORT_VAR) {
+        Ok(port) if !port.is_empty() => port,
+        _ => return Ok(None),
+    };
+    let port = port.parse().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid {} {:?}: {}", REST_PORT_VAR, port, e),
+        )
+    })?;
+    let host = match std::env::var(REST_HOST_VAR) {
+        Ok(host) => host.parse().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid {} {:?}: {}", REST_HOST_VAR, host, e),
+            )
+        })?,
+        Err(_) => DEFAULT_REST_HOST,
+    };
+
+    Ok(Some(SocketAddr::new(host, port)))
+}
+
+/// Services the REST handlers call into
+#[derive(Clone)]
+struct BridgeState {
+    memory: Arc<SmartMemoryService>,
+    health: Arc<HealthCheckService>,
+    /// Key callers must present, or `None` when authentication is disabled
+    api_key: Option<Arc<str>>,
+}
+
+/// Address of the client on the other end of a connection
+#[derive(Debug, Clone, Copy)]
+struct PeerAddr(Option<SocketAddr>);
+
+impl Connected<&AddrStream> for PeerAddr {
+    fn connect_info(stream: &AddrStream) -> Self {
+        Self(Some(stream.remote_addr()))
+    }
+}
+
+impl Connected<&TlsStream<TcpStream>> for PeerAddr {
+    fn connect_info(stream: &TlsStream<TcpStream>) -> Self {
+        Self(stream.get_ref().0.peer_addr().ok())
+    }
+}
+
+/// Headers and address of the client making a REST call
+struct Caller {
+    headers: HeaderMap,
+    peer: Option<SocketAddr>,
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Caller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<PeerAddr>>()
+            .and_then(|ConnectInfo(PeerAddr(peer))| *peer);
+        Ok(Self {
+            headers: parts.headers.clone(),
+            peer,
+        })
+    }
+}
+
+impl Caller {
+    /// Wrap a message in a gRPC request from this caller
+    ///
+    /// The headers become metadata so API keys pass through, and the address
+    /// lets the service log which client called.
+    fn request<T>(self, message: T) -> tonic::Request<T> {
+        let mut extensions = Extensions::default();
+        extensions.insert(TcpConnectInfo {
+            local_addr: None,
+            remote_addr: self.peer,
+        });
+        tonic::Request::from_parts(MetadataMap::from_headers(self.headers), extensions, message)
+    }
+}
+
+/// Body of `POST /memories`
+#[derive(Debug, Deserialize)]
+struct StoreBody {
+    content: String,
+    #[serde(default)]
+    content_type: String,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+    #[serde(default)]
+    compress: bool,
+    #[serde(default)]
+    idempotency_key: String,
+    #[serde(default)]
+    ttl_seconds: u64,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    preferred_id: String,
+}
+
+#[derive(Debug, Serialize)]
+struct StoreReply {
+    memory_id: String,
+    token_count: u32,
+    compression_ratio: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct RetrieveReply {
+    memory_id: String,
+    content: String,
+    metadata: HashMap<String, String>,
+    token_count: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct DeleteReply {
+    success: bool,
+    freed_tokens: u32,
+}
+
+/// Query of `GET /context`
+#[derive(Debug, Deserialize)]
+struct ContextQuery {
+    mode: String,
+    /// Token budget, `DEFAULT_CONTEXT_MAX_TOKENS` when unset
+    max_tokens: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct ContextSourceReply {
+    source_id: String,
+    source_type: String,
+    relevance: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct ContextReply {
+    context: String,
+    token_count: u32,
+    relevance_score: f32,
+    sources: Vec<ContextSourceReply>,
+    excluded_count: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct HealthReply {
+    status: String,
+    message: String,
+}
+
+/// A failed RPC, answered with the matching HTTP status and a JSON error
+struct BridgeError(Status);
+
+impl From<Status> for BridgeError {
+    fn from(status: Status) -> Self {
+        Self(status)
+    }
+}
+
+impl IntoResponse for BridgeError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({
+            "code": format!("{:?}", self.0.code()),
+            "error": self.0.message(),
+        });
+        (http_status(self.0.code()), Json(body)).into_response()
+    }
+}
+
+impl StatusResponse for Response {
+    fn from_status(status: Status) -> Self {
+        BridgeError(status).into_response()
+    }
+}
+
+/// Map a gRPC status code to the closest HTTP status
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::InvalidArgument | Code::OutOfRange => StatusCode::BAD_REQUEST,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::FailedPrecondition => StatusCode::PRECONDITION_FAILED,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Cancelled => StatusCode::REQUEST_TIMEOUT,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Reject calls without the configured API key
+async fn require_api_key<B>(
+    State(state): State<BridgeState>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if let Some(expected) = &state.api_key {
+        let metadata = tonic::Request::from_parts(
+            MetadataMap::from_headers(request.headers().clone()),
+            Extensions::default(),
+            (),
+        );
+        if let Err(status) = service::verify_api_key(&metadata, expected) {
+            return BridgeError(status).into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// Record the caller's address where the rate limiter looks for it, as the gRPC server does
+async fn attach_peer<B>(mut request: Request<B>) -> Request<B> {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<PeerAddr>>()
+        .and_then(|ConnectInfo(PeerAddr(peer))| *peer);
+    request.extensions_mut().insert(TcpConnectInfo {
+        local_addr: None,
+        remote_addr: peer,
+    });
+    request
+}
+
+/// Build the REST routes over the given services, requiring `api_key` when set
+/// and limiting writes with `rate_limiter`
+fn router(
+    memory: Arc<SmartMemoryService>,
+    health: Arc<HealthCheckService>,
+    api_key: Option<String>,
+    rate_limiter: RateLimiter,
+) -> Router {
+    let state = BridgeState {
+        memory,
+        health,
+        api_key: api_key.map(Arc::from),
+    };
+    Router::new()
+        .route("/memories", post(store_memory))
+        .route("/memories/:id", get(retrieve_memory).delete(delete_memory))
+        .route("/context", get(get_context))
+        .route("/ws/events", get(subscribe_events))
+        .route_layer(rate_limiter)
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            require_api_key,
+        ))
+        .route("/health", get(check_health))
+        .layer(Deadline::from_env())
+        .layer(middleware::map_request(attach_peer))
+        .with_state(state)
+}
+
+async fn store_memory(
+    State(state): State<BridgeState>,
+    caller: Caller,
+    Json(body): Json<StoreBody>,
+) -> Result<(StatusCode, Json<StoreReply>), BridgeError> {
+    let request = StoreRequest {
+        content: body.content,
+        content_type: body.content_type,
+        metadata: body.metadata,
+        compress: body.compress,
+        idempotency_key: body.idempotency_key,
+        ttl_seconds: body.ttl_seconds,
+        tags: body.tags,
+        preferred_id: body.preferred_id,
+    };
+    let response = state
+        .memory
+        .store_memory(caller.request(request))
+        .await?
+        .into_inner();
+
+    Ok((
+        StatusCode::CREATED,
+        Json(StoreReply {
+            memory_id: response.memory_id,
+            token_count: response.token_count,
+            compression_ratio: response.compression_ratio,
+        }),
+    ))
+}
+
+async fn retrieve_memory(
+    State(state): State<BridgeState>,
+    Path(id): Path<String>,
+    caller: Caller,
+) -> Result<Json<RetrieveReply>, BridgeError> {
+    let request = RetrieveRequest {
+        memory_id: id.clone(),
+        include_metadata: true,
+    };
+    let response = state
+        .memory
+        .retrieve_memory(caller.request(request))
+        .await?
+        .into_inner();
+
+    Ok(Json(RetrieveReply {
+        memory_id: id,
+        content: response.content,
+        metadata: response.metadata,
+        token_count: response.token_count,
+    }))
+}
+
+async fn delete_memory(
+    State(state): State<BridgeState>,
+    Path(id): Path<String>,
+    caller: Caller,
+) -> Result<Json<DeleteReply>, BridgeError> {
+    let request = DeleteMemoryRequest { memory_id: id };
+    let response = state
+        .memory
+        .delete_memory(caller.request(request))
+        .await?
+        .into_inner();
+
+    Ok(Json(DeleteReply {
+        success: response.success,
+        freed_tokens: response.freed_tokens,
+    }))
+}
+
+async fn get_context(
+    State(state): State<BridgeState>,
+    Query(query): Query<ContextQuery>,
+    caller: Caller,
+) -> Result<Json<ContextReply>, BridgeError> {
+    let request = ContextRequest {
+        mode: query.mode,
+        max_tokens: query.max_tokens.unwrap_or(DEFAULT_CONTEXT_MAX_TOKENS),
+        ..Default::default()
+    };
+    let response = state
+        .memory
+        .get_context(caller.request(request))
+        .await?
+        .into_inner();
+
+    Ok(Json(ContextReply {
+        context: response.context,
+        token_count: response.token_count,
+        relevance_score: response.relevance_score,
+        sources: response
+            .sources
+            .into_iter()
+            .map(|source| ContextSourceReply {
+                source_id: source.source_id,
+                source_type: source.source_type,
+                relevance: source.relevance,
+            })
+            .collect(),
+        excluded_count: response.excluded_count,
+    }))
+}
+
+async fn check_health(
+    State(state): State<BridgeState>,
+    caller: Caller,
+) -> Result<Json<HealthReply>, BridgeError> {
+    let response = state
+        .health
+        .check(caller.request(HealthCheckRequest {}))
+        .await?
+        .into_inner();
+
+    Ok(Json(HealthReply {
+        status: response.status().as_str_name().to_string(),
+        message: response.message,
+    }))
+}
+
+async fn subscribe_events(State(state): State<BridgeState>, upgrade: WebSocketUpgrade) -> Response {
+    // Subscribe before upgrading so no event is missed during the handshake
+    let events = state.memory.events.subscribe();
+    upgrade.on_upgrade(move |socket| forward_events(socket, events))
+}
+
+/// Send every event to the socket as JSON until either side goes away
+///
+/// A client that falls further behind than the channel holds is disconnected
+/// rather than silently missing events.
+async fn forward_events(mut socket: WebSocket, mut events: broadcast::Receiver<MemoryEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let text = match serde_json::to_string(&event) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            crate::log_warning!(
+                                "rest_bridge",
+                                &format!("Failed to serialize memory event: {}", e)
+                            );
+                            continue;
+                        }
+                    };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    crate::log_warning!(
+                        "rest_bridge",
+                        &format!("Dropping event subscriber {} events behind", missed)
+                    );
+                    let _ = socket
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::POLICY,
+                            reason: "Too far behind the event stream".into(),
+                        })))
+                        .await;
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+/// Accept connections on `listener`, yielding those completing the TLS handshake
+///
+/// Each handshake runs in its own task, so slow clients hold up no one else.
+fn accept_tls(
+    listener: tokio::net::TcpListener,
+    acceptor: TlsAcceptor,
+    shutdown: CancellationToken,
+) -> ReceiverStream<io::Result<TlsStream<TcpStream>>> {
+    let (sender, receiver) = mpsc::channel(32);
+    tokio::spawn(async move {
+        loop {
+            let stream = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        crate::log_warning!(
+                            "rest_bridge",
+                            &format!("Failed to accept REST connection: {}", e)
+                        );
+                        // Usually out of file descriptors, which takes a while to recover
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                },
+            };
+
+            let acceptor = acceptor.clone();
+            let sender = sender.clone();
+            tokio::spawn(async move {
+                match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = sender.send(Ok(stream)).await;
+                    }
+                    Ok(Err(e)) => crate::log_debug!(
+                        "rest_bridge",
+                        &format!("REST TLS handshake failed: {}", e)
+                    ),
+                    Err(_) => crate::log_debug!("rest_bridge", "REST TLS handshake timed out"),
+                }
+            });
+        }
+    });
+    ReceiverStream::new(receiver)
+}
+
+/// Serve the REST bridge on `addr` until `shutdown` is cancelled, returning the bound address
+///
+/// Connections are served over TLS when `tls` is given. Writes draw on the
+/// same buckets as the gRPC server's when given its `rate_limiter`.
+pub fn start(
+    addr: SocketAddr,
+    tls: Option<Arc<ServerConfig>>,
+    memory: Arc<SmartMemoryService>,
+    health: Arc<HealthCheckService>,
+    rate_limiter: RateLimiter,
+    shutdown: CancellationToken,
+) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+    let app = router(memory, health, service::api_key(), rate_limiter)
+        .into_make_service_with_connect_info::<PeerAddr>();
+
+    let server = match tls {
+        Some(tls) => {
+            listener.set_nonblocking(true)?;
+            let listener = tokio::net::TcpListener::from_std(listener)?;
+            let incoming = accept_tls(listener, TlsAcceptor::from(tls), shutdown.clone());
+            let server = Server::builder(hyper::server::accept::from_stream(incoming))
+                .serve(app)
+                .with_graceful_shutdown(shutdown.cancelled_owned());
+            tokio::spawn(server)
+        }
+        None => {
+            let server = Server::from_tcp(listener)
+                .map_err(io::Error::other)?
+                .serve(app)
+                .with_graceful_shutdown(shutdown.cancelled_owned());
+            tokio::spawn(server)
+        }
+    };
+
+    tokio::spawn(async move {
+        match server.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => crate::log_error!("rest_bridge", &format!("REST server error: {}", e)),
+            Err(e) => crate::log_error!("rest_bridge", &format!("REST server failed: {}", e)),
+        }
+    });
+
+    Ok(local_addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::{EventBroadcaster, EventType};
+    use crate::tls::TlsPaths;
+    use axum::body::Body;
+    use axum::http::Method;
+    use serde_json::Value;
+    use tokio_stream::StreamExt;
+    use tokio_tungstenite::tungstenite;
+    use tower::ServiceExt;
+
+    fn test_router() -> Router {
+        router(
+            Arc::new(SmartMemoryService::new().unwrap()),
+            Arc::new(HealthCheckService::new(None)),
+            None,
+            RateLimiter::new(1000, 0.0),
+        )
+    }
+
+    /// Send a request to the router, returning the status and JSON body
+    async fn send(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        send_with_headers(app, method, uri, body, &[]).await
+    }
+
+    /// Send a request with extra headers to the router, returning the status and JSON body
+    async fn send_with_headers(
+        app: &Router,
+        method: Method,
+        uri: &str,
+        body: Option<Value>,
+        headers: &[(&str, &str)],
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let body = match body {
+            Some(json) => {
+                request = request.header("content-type", "application/json");
+                Body::from(json.to_string())
+            }
+            None => Body::empty(),
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    async fn store(app: &Router, content: &str, mode: &str) -> String {
+        let (status, body) = send(
+            app,
+            Method::POST,
+            "/memories",
+            Some(serde_json::json!({
+                "content": content,
+                "content_type": "text/plain",
+                "metadata": { "mode": mode },
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        body["memory_id"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_store_memory() {
+        let app = test_router();
+
+        let (status, body) = send(
+            &app,
+            Method::POST,
+            "/memories",
+            Some(serde_json::json!({ "content": "The bridge stores memories" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert!(!body["memory_id"].as_str().unwrap().is_empty());
+        assert!(body["token_count"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_memory() {
+        let app = test_router();
+        let id = store(&app, "Retrieved over HTTP", "code").await;
+
+        let (status, body) = send(&app, Method::GET, &format!("/memories/{}", id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["memory_id"], id.as_str());
+        assert_eq!(body["content"], "Retrieved over HTTP");
+        assert_eq!(body["metadata"]["mode"], "code");
+
+        let (status, body) = send(&app, Method::GET, "/memories/missing", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "NotFound");
+    }
+
+    #[tokio::test]
+    async fn test_delete_memory() {
+        let app = test_router();
+        let id = store(&app, "Deleted over HTTP", "code").await;
+
+        let uri = format!("/memories/{}", id);
+        let (status, body) = send(&app, Method::DELETE, &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["success"], true);
+
+        let (status, _) = send(&app, Method::GET, &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(&app, Method::DELETE, &uri, None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_get_context() {
+        let app = test_router();
+        let id = store(&app, "Context served over HTTP", "code").await;
+
+        let (status, body) = send(
+            &app,
+            Method::GET,
+            "/context?mode=code&max_tokens=1000",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["context"]
+            .as_str()
+            .unwrap()
+            .contains("Context served over HTTP"));
+        assert!(body["sources"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|source| source["source_id"] == id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_get_context_defaults_max_tokens() {
+        let app = test_router();
+        store(&app, "Context without a token budget", "code").await;
+
+        let (status, body) = send(&app, Method::GET, "/context?mode=code", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["context"]
+            .as_str()
+            .unwrap()
+            .contains("Context without a token budget"));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_is_required() {
+        let app = router(
+            Arc::new(SmartMemoryService::new().unwrap()),
+            Arc::new(HealthCheckService::new(None)),
+            Some("secret".to_string()),
+            RateLimiter::new(1000, 0.0),
+        );
+        let note = || Some(serde_json::json!({ "content": "Stored with a key" }));
+
+        let (status, body) = send(&app, Method::POST, "/memories", note()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "Missing API key");
+        let (status, _) = send_with_headers(
+            &app,
+            Method::POST,
+            "/memories",
+            note(),
+            &[("x-api-key", "wrong")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = send(&app, Method::GET, "/ws/events", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = send_with_headers(
+            &app,
+            Method::POST,
+            "/memories",
+            note(),
+            &[("x-api-key", "secret")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = send_with_headers(
+            &app,
+            Method::GET,
+            "/context?mode=code",
+            None,
+            &[("authorization", "Bearer secret")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        // Load balancers probe health without credentials
+        let (status, _) = send(&app, Method::GET, "/health", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_rate_limited() {
+        let app = router(
+            Arc::new(SmartMemoryService::new().unwrap()),
+            Arc::new(HealthCheckService::new(None)),
+            None,
+            RateLimiter::new(1, 0.0),
+        );
+
+        store(&app, "Within the limit", "code").await;
+        let (status, body) = send(
+            &app,
+            Method::POST,
+            "/memories",
+            Some(serde_json::json!({ "content": "Over the limit" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body["code"], "ResourceExhausted");
+
+        // Calls without a peer address are limited per forwarded address
+        let (status, _) = send_with_headers(
+            &app,
+            Method::POST,
+            "/memories",
+            Some(serde_json::json!({ "content": "From behind a proxy" })),
+            &[("x-forwarded-for", "10.0.0.7")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        // Reads are not limited
+        let (status, _) = send(&app, Method::GET, "/context?mode=code", None).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_calls_past_their_deadline_time_out() {
+        let app = test_router();
+
+        let (status, body) = send_with_headers(
+            &app,
+            Method::GET,
+            "/context?mode=code",
+            None,
+            &[("grpc-timeout", "1n")],
+        )
+        .await;
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(body["code"], "DeadlineExceeded");
+    }
+
+    #[tokio::test]
+    async fn test_check_health() {
+        let app = test_router();
+
+        let (status, body) = send(&app, Method::GET, "/health", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "UNKNOWN");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Smart Memory MCP v"));
+    }
+
+    /// Serve the bridge on a free port, returning its address and the memory service
+    async fn serve(events: EventBroadcaster) -> (SocketAddr, Arc<SmartMemoryService>) {
+        let mut service = SmartMemoryService::new().unwrap();
+        service.events = Arc::new(events);
+        let service = Arc::new(service);
+
+        let addr = start(
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            None,
+            service.clone(),
+            Arc::new(HealthCheckService::new(None)),
+            RateLimiter::new(1000, 0.0),
+            CancellationToken::new(),
+        )
+        .unwrap();
+        (addr, service)
+    }
+
+    async fn next_json<S>(socket: &mut S) -> Value
+    where
+        S: tokio_stream::Stream<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        match socket.next().await.unwrap().unwrap() {
+            tungstenite::Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("Expected a text message, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_are_forwarded_over_websocket() {
+        let (addr, service) = serve(EventBroadcaster::new(16)).await;
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/events", addr))
+            .await
+            .unwrap();
+
+        let stored = service
+            .store_memory(tonic::Request::new(StoreRequest {
+                content: "Pushed to subscribers".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        service
+            .delete_memory(tonic::Request::new(DeleteMemoryRequest {
+                memory_id: stored.memory_id.clone(),
+            }))
+            .await
+            .unwrap();
+
+        let event = next_json(&mut socket).await;
+        assert_eq!(event["event_type"], "stored");
+        assert_eq!(event["memory_id"], stored.memory_id.as_str());
+        assert!(event["timestamp"].as_u64().unwrap() > 0);
+
+        let event = next_json(&mut socket).await;
+        assert_eq!(event["event_type"], "deleted");
+        assert_eq!(event["memory_id"], stored.memory_id.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_lagging_websocket_subscribers_are_dropped() {
+        let (addr, service) = serve(EventBroadcaster::new(2)).await;
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/events", addr))
+            .await
+            .unwrap();
+
+        // Publishing never yields, so the forwarding task falls behind
+        for i in 0..5 {
+            let memory = service
+                .memory_store
+                .store(
+                    format!("Event {}", i),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    HashMap::new(),
+                )
+                .unwrap();
+            service
+                .events
+                .publish(MemoryEvent::new(EventType::Stored, &memory));
+        }
+
+        match socket.next().await.unwrap().unwrap() {
+            tungstenite::Message::Close(Some(frame)) => {
+                assert_eq!(u16::from(frame.code), close_code::POLICY);
+            }
+            other => panic!("Expected a close frame, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_when_tls_is_configured() {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("server.pem");
+        let key_path = dir.path().join("server.key");
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+        let tls = TlsPaths {
+            cert_path,
+            key_path,
+            client_ca_path: None,
+        }
+        .load_rustls()
+        .await
+        .unwrap();
+
+        let addr = start(
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+            Some(tls),
+            Arc::new(SmartMemoryService::new().unwrap()),
+            Arc::new(HealthCheckService::new(None)),
+            RateLimiter::new(1000, 0.0),
+            CancellationToken::new(),
+        )
+        .unwrap();
+        let client = reqwest::Client::builder()
+            .add_root_certificate(
+                reqwest::Certificate::from_pem(certified.cert.pem().as_bytes()).unwrap(),
+            )
+            .resolve("localhost", addr)
+            .build()
+            .unwrap();
+
+        let url = format!("https://localhost:{}/health", addr.port());
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        // Plaintext is not served
+        let plain = format!("http://localhost:{}/health", addr.port());
+        assert!(client.get(&plain).send().await.is_err());
+    }
+
+    #[test]
+    fn test_status_codes_map_to_http() {
+        assert_eq!(http_status(Code::InvalidArgument), StatusCode::BAD_REQUEST);
+        assert_eq!(http_status(Code::NotFound), StatusCode::NOT_FOUND);
+        assert_eq!(http_status(Code::Unauthenticated), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            http_status(Code::Internal),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
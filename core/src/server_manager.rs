@@ -7,7 +7,34 @@ use std::process::{Command, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+use tonic::codegen::http::Uri;
+use tonic::transport::{Channel, Endpoint};
+use tonic::Status;
+
+use crate::proto::health_check_client::HealthCheckClient;
+use crate::proto::health_check_response::ServingStatus;
+use crate::proto::HealthCheckRequest;
+use crate::service::authorized_request;
+use crate::tls::{TlsPaths, TLS_CERT_PATH_VAR, TLS_CLIENT_CA_PATH_VAR, TLS_KEY_PATH_VAR};
+
+/// Name of the systemd unit installed by `install`
+const SYSTEMD_UNIT_NAME: &str = "smart-memory";
+
+/// Environment variable holding how many seconds `start` waits for the server to serve
+const HEALTH_PROBE_TIMEOUT_VAR: &str = "HEALTH_PROBE_TIMEOUT_SECS";
+
+/// Default time `start` waits for the server to serve
+const DEFAULT_HEALTH_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause between two readiness probes
+const HEALTH_PROBE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long `upgrade` waits for the stopped server to release its port
+const PORT_RELEASE_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Server manager for Smart Memory MCP
 ///
@@ -24,6 +51,8 @@ pub struct ServerManager {
     binary_path: PathBuf,
     db_path: PathBuf,
     config_path: PathBuf,
+    /// TLS certificates passed on to spawned servers
+    tls: Option<TlsPaths>,
 }
 
 impl ServerManager {
@@ -56,6 +85,10 @@ impl ServerManager {
             50051
         };
 
+        // Validate the TLS settings before they are handed to a daemon
+        let tls = TlsPaths::from_env()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+
         Ok(Self {
             port,
             host: "127.0.0.1".to_string(),
@@ -64,6 +97,7 @@ impl ServerManager {
             binary_path,
             db_path: smart_memory_dir.join("memories.db"),
             config_path,
+            tls,
         })
     }
 
@@ -188,6 +222,48 @@ impl ServerManager {
         false
     }
 
+    /// Ask the server's health service whether it is serving
+    ///
+    /// Servers using TLS are probed over TLS, trusting only their own
+    /// certificate. Returns an `UNAVAILABLE` status when nothing answers on the port.
+    pub fn health_probe(&self) -> Result<bool, Box<Status>> {
+        let endpoint = format!("http://{}:{}", self.host, self.port);
+        let tls = self.tls.clone();
+
+        // The manager may run inside the server's runtime, so use a dedicated one
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| Status::internal(format!("Failed to start runtime: {}", e)))?;
+            runtime.block_on(probe_health(endpoint, tls))
+        })
+        .join()
+        .map_err(|_| Box::new(Status::internal("Health probe panicked")))?
+    }
+
+    /// Wait until the server is serving, for at most `timeout`
+    pub fn wait_until_ready(&self, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if matches!(self.health_probe(), Ok(true)) {
+                return Ok(());
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "Server on port {} was not serving after {:?}",
+                        self.port, timeout
+                    ),
+                ));
+            }
+            thread::sleep(HEALTH_PROBE_INTERVAL.min(deadline - now));
+        }
+    }
+
     /// Start the server
     pub fn start_server(&self) -> io::Result<u32> {
         // Check if server is already running
@@ -244,6 +320,10 @@ impl ServerManager {
             .stdout(Stdio::from(log_file.try_clone()?))
             .stderr(Stdio::from(log_file));
 
+        if let Some(tls) = &self.tls {
+            tls.apply_to(&mut command);
+        }
+
         // Add --daemon flag to indicate this is a daemon process
         command.arg("--daemon");
 
@@ -267,25 +347,16 @@ impl ServerManager {
             ));
         }
 
-        // Verify the server is listening on the port
-        let mut retries = 5;
-        while retries > 0 {
-            if self.test_server_connection() {
-                return Ok(pid);
+        // Verify the server is serving requests
+        if let Err(e) = self.wait_until_ready(health_probe_timeout()) {
+            // The server is running but not serving
+            if Self::kill_process(pid) {
+                let _ = self.cleanup_pid_file();
             }
-            thread::sleep(Duration::from_millis(500));
-            retries -= 1;
-        }
-
-        // If we get here, the server is running but not listening on the port
-        if Self::kill_process(pid) {
-            let _ = self.cleanup_pid_file();
+            return Err(e);
         }
 
-        Err(io::Error::new(
-            io::ErrorKind::Other,
-            "Server started but failed to listen on the port",
-        ))
+        Ok(pid)
     }
 
     /// Stop the server
@@ -317,6 +388,138 @@ impl ServerManager {
         }
     }
 
+    /// Wait until nothing accepts connections on the server's port, for at most `timeout`
+    fn wait_until_port_free(&self, timeout: Duration) -> io::Result<()> {
+        let addr = format!("{}:{}", self.host, self.port)
+            .parse::<SocketAddr>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            match TcpStream::connect_timeout(&addr, Duration::from_millis(100)) {
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => return Ok(()),
+                _ if Instant::now() >= deadline => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("Port {} was still in use after {:?}", self.port, timeout),
+                    ));
+                }
+                _ => thread::sleep(HEALTH_PROBE_INTERVAL),
+            }
+        }
+    }
+
+    /// Replace the server binary with `new_binary_path` and restart the server on it
+    ///
+    /// The new binary is copied next to the current one before the server is
+    /// stopped, so the final rename over the old binary is atomic on POSIX
+    /// filesystems. The old binary is kept at `<binary>.bak`; should the new
+    /// server fail its health probe, the old binary is restored and restarted.
+    pub fn upgrade(&self, new_binary_path: &Path) -> io::Result<()> {
+        println!("Verifying {} is executable", new_binary_path.display());
+        verify_executable(new_binary_path)?;
+
+        let staged_path = staged_binary_path(&self.binary_path);
+        let backup_path = backup_binary_path(&self.binary_path);
+        if let Err(e) = self.swap_binary(new_binary_path, &staged_path, &backup_path) {
+            let _ = fs::remove_file(&staged_path);
+            return Err(e);
+        }
+
+        println!("Starting the upgraded server");
+        match self.start_server().and_then(|pid| self.verify_serving(pid)) {
+            Ok(pid) => {
+                println!("Upgraded server is running with PID {}", pid);
+                println!("Previous binary kept at {}", backup_path.display());
+                Ok(())
+            }
+            Err(e) => {
+                eprintln!("Upgraded server failed: {}", e);
+                match self.roll_back(&backup_path, &staged_path) {
+                    Ok(pid) => println!("Previous server restored with PID {}", pid),
+                    Err(rollback_error) => {
+                        let _ = fs::remove_file(&staged_path);
+                        return Err(io::Error::other(format!(
+                            "{}; restoring {} also failed: {}",
+                            e,
+                            backup_path.display(),
+                            rollback_error
+                        )));
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Probe the health of the server with `pid`, returning the PID once it is serving
+    fn verify_serving(&self, pid: u32) -> io::Result<u32> {
+        println!("Probing the health of server with PID {}", pid);
+        match self.health_probe() {
+            Ok(true) => Ok(pid),
+            Ok(false) => Err(io::Error::other(format!(
+                "Upgraded server with PID {} is not serving",
+                pid
+            ))),
+            Err(status) => Err(io::Error::other(format!(
+                "Health probe failed: {}",
+                status.message()
+            ))),
+        }
+    }
+
+    /// Stop the upgraded server, put the binary at `backup_path` back and start it
+    fn roll_back(&self, backup_path: &Path, staged_path: &Path) -> io::Result<u32> {
+        if let Some(pid) = self.is_server_running() {
+            println!("Stopping upgraded server with PID {}", pid);
+            self.stop_server(pid);
+        }
+        self.wait_until_port_free(PORT_RELEASE_TIMEOUT)?;
+
+        println!("Restoring {}", backup_path.display());
+        fs::copy(backup_path, staged_path)?;
+        fs::rename(staged_path, &self.binary_path)?;
+        self.start_server()
+    }
+
+    /// Stage `new_binary_path` at `staged_path`, back up the current binary to
+    /// `backup_path`, stop the server and rename the staged binary into place
+    fn swap_binary(
+        &self,
+        new_binary_path: &Path,
+        staged_path: &Path,
+        backup_path: &Path,
+    ) -> io::Result<()> {
+        println!(
+            "Copying {} to {}",
+            new_binary_path.display(),
+            staged_path.display()
+        );
+        fs::copy(new_binary_path, staged_path)?;
+
+        println!(
+            "Backing up {} to {}",
+            self.binary_path.display(),
+            backup_path.display()
+        );
+        fs::copy(&self.binary_path, backup_path)?;
+
+        if let Some(pid) = self.is_server_running() {
+            println!("Stopping server with PID {}", pid);
+            if !self.stop_server(pid) {
+                return Err(io::Error::other(format!(
+                    "Failed to stop server with PID {}",
+                    pid
+                )));
+            }
+        }
+
+        println!("Waiting for port {} to be released", self.port);
+        self.wait_until_port_free(PORT_RELEASE_TIMEOUT)?;
+
+        println!("Replacing {}", self.binary_path.display());
+        fs::rename(staged_path, &self.binary_path)
+    }
+
     /// Find VS Code process ID
     #[cfg(windows)]
     fn find_vscode_process(&self) -> Option<u32> {
@@ -489,6 +692,356 @@ impl ServerManager {
 
         None
     }
+    /// Check whether systemd is available on this system
+    pub fn is_systemd_available() -> bool {
+        Command::new("which")
+            .arg("systemctl")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    /// Whether the unit is installed system-wide (root) or for the current user
+    fn systemd_system_wide() -> bool {
+        #[cfg(unix)]
+        {
+            nix::unistd::geteuid().is_root()
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
+
+    /// Get the path of the systemd unit file
+    fn systemd_unit_path(system_wide: bool) -> io::Result<PathBuf> {
+        let unit_dir = if system_wide {
+            PathBuf::from("/etc/systemd/system")
+        } else {
+            dirs::home_dir()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Home directory not found"))?
+                .join(".config")
+                .join("systemd")
+                .join("user")
+        };
+
+        Ok(unit_dir.join(format!("{}.service", SYSTEMD_UNIT_NAME)))
+    }
+
+    /// Get the path of the environment file the systemd unit reads
+    ///
+    /// The server's environment, API key included, lives here rather than in
+    /// the world-readable unit file.
+    fn systemd_environment_path(system_wide: bool) -> io::Result<PathBuf> {
+        let config_dir = if system_wide {
+            PathBuf::from("/etc/smart-memory")
+        } else {
+            dirs::home_dir()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Home directory not found"))?
+                .join(".config")
+                .join("smart-memory")
+        };
+
+        Ok(config_dir.join(format!("{}.env", SYSTEMD_UNIT_NAME)))
+    }
+
+    /// Run a systemctl command, failing if it exits unsuccessfully
+    fn systemctl(system_wide: bool, args: &[&str]) -> io::Result<()> {
+        let mut command = Command::new("systemctl");
+        if !system_wide {
+            command.arg("--user");
+        }
+
+        let status = command.args(args).status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "systemctl {} failed with {}",
+                args.join(" "),
+                status
+            )))
+        }
+    }
+
+    /// Install and enable a systemd unit that starts the server on boot
+    pub fn setup_systemd_unit(&self) -> io::Result<PathBuf> {
+        if !Self::is_systemd_available() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "systemctl not found; systemd does not appear to be available",
+            ));
+        }
+
+        let system_wide = Self::systemd_system_wide();
+        let unit_path = Self::systemd_unit_path(system_wide)?;
+        let environment_path = Self::systemd_environment_path(system_wide)?;
+
+        // Capture the environment the server should run with
+        let port = env::var("PORT").unwrap_or_else(|_| self.port.to_string());
+        let mut environment = vec![("PORT", port)];
+        for key in [
+            "DATA_DIR",
+            "LOG_DIR",
+            "API_KEY",
+            TLS_CERT_PATH_VAR,
+            TLS_KEY_PATH_VAR,
+            TLS_CLIENT_CA_PATH_VAR,
+        ] {
+            if let Ok(value) = env::var(key) {
+                environment.push((key, value));
+            }
+        }
+
+        if let Some(parent) = environment_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_private_file(&environment_path, &render_environment_file(&environment))?;
+
+        let unit = render_systemd_unit(&self.binary_path, &environment_path, system_wide);
+        if let Some(parent) = unit_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&unit_path, unit)?;
+
+        Self::systemctl(system_wide, &["daemon-reload"])?;
+        Self::systemctl(system_wide, &["enable", SYSTEMD_UNIT_NAME])?;
+
+        Ok(unit_path)
+    }
+
+    /// Disable and remove the systemd unit, returning its path if it existed
+    pub fn remove_systemd_unit(&self) -> io::Result<Option<PathBuf>> {
+        let system_wide = Self::systemd_system_wide();
+        let unit_path = Self::systemd_unit_path(system_wide)?;
+
+        if !unit_path.exists() {
+            return Ok(None);
+        }
+
+        if Self::is_systemd_available() {
+            // The unit may already be disabled, so only warn on failure
+            if let Err(e) = Self::systemctl(system_wide, &["disable", SYSTEMD_UNIT_NAME]) {
+                eprintln!("Warning: {}", e);
+            }
+        }
+
+        fs::remove_file(&unit_path)?;
+        let environment_path = Self::systemd_environment_path(system_wide)?;
+        if environment_path.exists() {
+            fs::remove_file(&environment_path)?;
+        }
+
+        if Self::is_systemd_available() {
+            Self::systemctl(system_wide, &["daemon-reload"])?;
+        }
+
+        Ok(Some(unit_path))
+    }
+}
+
+/// Call the health service at `endpoint` and check that it is serving
+///
+/// With `tls` the connection uses TLS and accepts only the server's own certificate.
+async fn probe_health(endpoint: String, tls: Option<TlsPaths>) -> Result<bool, Box<Status>> {
+    let endpoint = Channel::from_shared(endpoint)
+        .map_err(|e| Status::invalid_argument(format!("Invalid endpoint: {}", e)))?
+        .connect_timeout(Duration::from_secs(1));
+    let connected = match tls {
+        Some(tls) => {
+            let config = tls.load_probe_config().await.map_err(|e| {
+                Status::failed_precondition(format!("Failed to load TLS certificates: {:#}", e))
+            })?;
+            connect_tls(endpoint, TlsConnector::from(config)).await
+        }
+        None => endpoint.connect().await,
+    };
+    let channel = connected
+        .map_err(|e| Status::unavailable(format!("Failed to connect to server: {}", e)))?;
+
+    let response = HealthCheckClient::new(channel)
+        .check(authorized_request(HealthCheckRequest {}))
+        .await?
+        .into_inner();
+    Ok(response.status() == ServingStatus::Serving)
+}
+
+/// Connect to `endpoint` over TLS, handshaking within a second
+async fn connect_tls(
+    endpoint: Endpoint,
+    connector: TlsConnector,
+) -> Result<Channel, tonic::transport::Error> {
+    endpoint
+        .connect_with_connector(tower::service_fn(move |uri: Uri| {
+            let connector = connector.clone();
+            async move {
+                let host = uri
+                    .host()
+                    .unwrap_or("localhost")
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .to_string();
+                let port = uri.port_u16().unwrap_or(443);
+                // The certificate is pinned, so the name only has to be well-formed
+                let server_name = ServerName::try_from(host.clone())
+                    .unwrap_or_else(|_| ServerName::try_from("localhost").unwrap());
+
+                tokio::time::timeout(Duration::from_secs(1), async {
+                    let stream = tokio::net::TcpStream::connect((host.as_str(), port)).await?;
+                    connector.connect(server_name, stream).await
+                })
+                .await
+                .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out"))?
+            }
+        }))
+        .await
+}
+
+/// Fail unless `path` is a file that can be executed
+fn verify_executable(path: &Path) -> io::Result<()> {
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file", path.display()),
+        ));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not executable", path.display()),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Path a new binary is copied to before it is renamed over `binary_path`
+///
+/// It is in the same directory, so the rename stays within one filesystem.
+fn staged_binary_path(binary_path: &Path) -> PathBuf {
+    let file_name = binary_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "smart-memory-mcp".to_string());
+    binary_path.with_file_name(format!(".{}.upgrade", file_name))
+}
+
+/// Path the current binary is kept at while `upgrade` replaces it
+fn backup_binary_path(binary_path: &Path) -> PathBuf {
+    let mut path = binary_path.as_os_str().to_owned();
+    path.push(".bak");
+    PathBuf::from(path)
+}
+
+/// Time `start` waits for the server to serve, from `HEALTH_PROBE_TIMEOUT_SECS`
+fn health_probe_timeout() -> Duration {
+    env::var(HEALTH_PROBE_TIMEOUT_VAR)
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_HEALTH_PROBE_TIMEOUT)
+}
+
+/// Write `contents` to a file only its owner can read, tightening an existing file's mode first
+fn write_private_file(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_bytes())
+}
+
+/// Quote a value for a systemd environment file
+///
+/// Inside double quotes a backslash escapes `"`, `\`, `$` and `` ` ``.
+fn quote_environment_value(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quote a command-line argument for `ExecStart`
+///
+/// systemd expands `%` specifiers and `$` variables in command lines, so both
+/// are doubled as well as quotes and backslashes escaped.
+fn quote_exec_argument(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            '$' => quoted.push_str("$$"),
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Render the environment file read by the systemd unit
+fn render_environment_file(environment: &[(&str, String)]) -> String {
+    environment
+        .iter()
+        .map(|(key, value)| format!("{}={}\n", key, quote_environment_value(value)))
+        .collect()
+}
+
+/// Render a systemd unit file for the server, reading its environment from `environment_path`
+fn render_systemd_unit(binary_path: &Path, environment_path: &Path, system_wide: bool) -> String {
+    let mut unit = String::new();
+    unit.push_str("[Unit]\n");
+    unit.push_str("Description=Smart Memory MCP server\n");
+    unit.push_str("After=network.target\n\n");
+
+    unit.push_str("[Service]\n");
+    unit.push_str("Type=simple\n");
+    unit.push_str(&format!(
+        "ExecStart={}\n",
+        quote_exec_argument(&binary_path.to_string_lossy())
+    ));
+    unit.push_str(&format!(
+        "EnvironmentFile={}\n",
+        environment_path.to_string_lossy().replace('%', "%%")
+    ));
+    unit.push_str("Restart=on-failure\n");
+    unit.push_str("RestartSec=5\n\n");
+
+    unit.push_str("[Install]\n");
+    unit.push_str(if system_wide {
+        "WantedBy=multi-user.target\n"
+    } else {
+        "WantedBy=default.target\n"
+    });
+    unit
 }
 
 /// Global shutdown flag
@@ -697,8 +1250,7 @@ pub fn main() -> io::Result<()> {
                     // Get backup ID from args
                     if let Some(backup_id) = args.get(2) {
                         // Find backup with this ID
-                        let backup_path = backup_dir.join(format!("backup_{}.db", backup_id));
-                        if backup_path.exists() {
+                        if let Some(backup_path) = backup_manager.find_backup(backup_id) {
                             // Restore backup
                             match backup_manager.restore_backup(&backup_path, &manager.db_path) {
                                 Ok(()) => {
@@ -709,7 +1261,7 @@ pub fn main() -> io::Result<()> {
                                 }
                             }
                         } else {
-                            println!("Backup not found: {}", backup_path.display());
+                            println!("Backup not found: {}", backup_id);
                         }
                     } else {
                         // List available backups
@@ -720,18 +1272,12 @@ pub fn main() -> io::Result<()> {
                                 } else {
                                     println!("Available backups:");
                                     for (path, metadata) in backups {
-                                        let timestamp = chrono::DateTime::<chrono::Utc>::from(
-                                            std::time::UNIX_EPOCH
-                                                + std::time::Duration::from_secs(
-                                                    metadata.timestamp,
-                                                ),
-                                        );
+                                        let timestamp = metadata.created_at();
                                         println!(
                                             "  ID: {} - {} - {}",
-                                            path.file_stem()
-                                                .unwrap_or_default()
-                                                .to_string_lossy()
-                                                .strip_prefix("backup_")
+                                            path.file_name()
+                                                .and_then(|name| name.to_str())
+                                                .and_then(crate::storage::backup_id)
                                                 .unwrap_or(""),
                                             timestamp.format("%Y-%m-%d %H:%M:%S"),
                                             metadata.description
@@ -755,6 +1301,30 @@ pub fn main() -> io::Result<()> {
 
             Ok(())
         }
+        "restore-latest" => {
+            // Restore the newest backup without looking up its ID
+            if let Some(pid) = manager.is_server_running() {
+                println!("Server is running with PID {}. Please stop the server before restoring a backup.", pid);
+                return Ok(());
+            }
+
+            let backup_manager = crate::storage::BackupManager::new(&manager.get_backup_dir())?;
+            match backup_manager.restore_latest(&manager.db_path) {
+                Ok((backup_path, metadata)) => {
+                    println!(
+                        "Restored backup: {} ({} - {})",
+                        backup_path.display(),
+                        metadata.created_at().format("%Y-%m-%d %H:%M:%S"),
+                        metadata.description
+                    );
+                }
+                Err(e) => {
+                    println!("Failed to restore backup: {}", e);
+                }
+            }
+
+            Ok(())
+        }
         "start" => {
             // Check if port is in use by another application
             let addr = format!("{}:{}", manager.host, manager.port);
@@ -843,6 +1413,89 @@ pub fn main() -> io::Result<()> {
             println!("Started server with PID {}", pid);
             Ok(())
         }
+        "install" => {
+            // Only systemd is supported for now
+            let service_manager = args
+                .iter()
+                .position(|arg| arg == "--service-manager")
+                .and_then(|i| args.get(i + 1))
+                .map(|s| s.as_str())
+                .unwrap_or("systemd");
+
+            if service_manager != "systemd" {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unsupported service manager: {}", service_manager),
+                ));
+            }
+
+            let unit_path = manager.setup_systemd_unit()?;
+            println!("Installed systemd unit: {}", unit_path.display());
+            println!("Start it now with: systemctl start {}", SYSTEMD_UNIT_NAME);
+            Ok(())
+        }
+        "config" => {
+            let option = |name: &str| {
+                args.iter()
+                    .position(|arg| arg == name)
+                    .and_then(|i| args.get(i + 1))
+                    .map(PathBuf::from)
+            };
+
+            match (
+                args.get(2).map(|s| s.as_str()),
+                option("--from"),
+                option("--to"),
+            ) {
+                (Some("convert"), Some(from), Some(to)) => {
+                    convert_config(&from, &to)?;
+                    println!("Converted {} to {}", from.display(), to.display());
+                    Ok(())
+                }
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Usage: smart-memory-mcp config convert --from <path> --to <path>",
+                )),
+            }
+        }
+        "--upgrade-from" => match args.get(2) {
+            Some(new_binary_path) => manager.upgrade(Path::new(new_binary_path)),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Usage: smart-memory-mcp --upgrade-from <path>",
+            )),
+        },
+        "--write-pid-file" => match args.get(2).map(|pid| pid.parse::<u32>()) {
+            Some(Ok(pid)) => {
+                crate::parent_process_monitor::write_vscode_pid_file(pid)?;
+                println!("Wrote VSCode PID {} to the PID file", pid);
+                Ok(())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Usage: smart-memory-mcp --write-pid-file <PID>",
+            )),
+        },
+        "logs" => crate::logs_cli::run(&manager.host, manager.port, &args[2..]),
+        "stats" => crate::stats_cli::run(&manager.host, manager.port, &args[2..]),
+        "log-level" => match (args.get(2), args.get(3)) {
+            (Some(target), Some(level)) => {
+                set_log_level(level, target)?;
+                println!("Set {} log level to {}", target, level.to_uppercase());
+                Ok(())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Usage: smart-memory-mcp log-level <console|file> <LEVEL>",
+            )),
+        },
+        "uninstall" => {
+            match manager.remove_systemd_unit()? {
+                Some(unit_path) => println!("Removed systemd unit: {}", unit_path.display()),
+                None => println!("No systemd unit installed"),
+            }
+            Ok(())
+        }
         "status" | _ => {
             if let Some(pid) = manager.is_server_running() {
                 let responsive = manager.test_server_connection();
@@ -863,6 +1516,50 @@ pub fn main() -> io::Result<()> {
     }
 }
 
+/// Get the data directory, from `DATA_DIR` or `~/.smart-memory`
+fn data_dir() -> PathBuf {
+    env::var_os("DATA_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".smart-memory")))
+        .unwrap_or_else(|| PathBuf::from(".smart-memory"))
+}
+
+/// Rewrite a memory bank config in the format implied by the extension of `to`
+pub fn convert_config(from: &Path, to: &Path) -> io::Result<()> {
+    crate::storage::MemoryBankConfig::load(from)
+        .and_then(|config| config.save(to))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:#}", e)))
+}
+
+/// Change the running server's log level for `target` (`console` or `file`)
+pub fn set_log_level(level: &str, target: &str) -> io::Result<()> {
+    set_log_level_at(
+        &crate::logging::log_control_path(&data_dir()),
+        level,
+        target,
+    )
+}
+
+/// Change the log level of the server whose log control socket or pipe is at `path`
+pub fn set_log_level_at(path: &Path, level: &str, target: &str) -> io::Result<()> {
+    #[cfg(unix)]
+    let stream = std::os::unix::net::UnixStream::connect(path)?;
+    #[cfg(windows)]
+    let stream = fs::OpenOptions::new().read(true).write(true).open(path)?;
+
+    (&stream).write_all(format!("set_level {} {}\n", target, level).as_bytes())?;
+
+    let mut reply = String::new();
+    io::BufRead::read_line(&mut io::BufReader::new(&stream), &mut reply)?;
+    match reply.trim_end() {
+        "OK" => Ok(()),
+        reply => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            reply.strip_prefix("ERROR ").unwrap_or(reply).to_string(),
+        )),
+    }
+}
+
 // Add this to your main.rs to integrate the server manager
 pub fn integrate_server_manager() {
     let args: Vec<String> = env::args().collect();
@@ -870,7 +1567,25 @@ pub fn integrate_server_manager() {
     // Check if this is a server manager command
     if args.len() > 1 {
         let command = &args[1];
-        if ["start", "stop", "restart", "status", "backup", "restore"].contains(&command.as_str()) {
+        if [
+            "start",
+            "stop",
+            "restart",
+            "status",
+            "backup",
+            "restore",
+            "restore-latest",
+            "install",
+            "uninstall",
+            "config",
+            "logs",
+            "stats",
+            "log-level",
+            "--write-pid-file",
+            "--upgrade-from",
+        ]
+        .contains(&command.as_str())
+        {
             if let Err(err) = main() {
                 eprintln!("Server manager error: {}", err);
                 std::process::exit(1);
@@ -881,3 +1596,348 @@ pub fn integrate_server_manager() {
 
     // If we're here, it's not a server manager command, so continue with normal execution
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create an empty in-memory store for a test server
+    fn memory_store() -> Arc<crate::storage::MemoryStore> {
+        Arc::new(crate::storage::MemoryStore::new_in_memory(
+            crate::storage::Tokenizer::default(),
+        ))
+    }
+
+    /// Create a manager for a server on `port` keeping its files in `dir`
+    fn manager_for_port(dir: &Path, port: u16) -> ServerManager {
+        ServerManager {
+            port,
+            host: "127.0.0.1".to_string(),
+            pid_file: dir.join("server.pid"),
+            log_file: dir.join("server.log"),
+            binary_path: PathBuf::from("smart-memory-mcp"),
+            db_path: dir.join("memories.db"),
+            config_path: dir.join("config.json"),
+            tls: None,
+        }
+    }
+
+    /// Serve the health service on a free port, returning the port
+    async fn serve_health(
+        memory_store: Option<std::sync::Arc<crate::storage::MemoryStore>>,
+    ) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(crate::service::create_health_service(memory_store))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        port
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_wait_until_ready_with_running_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let port = serve_health(Some(memory_store())).await;
+        let manager = manager_for_port(dir.path(), port);
+
+        let started = Instant::now();
+        tokio::task::spawn_blocking(move || {
+            assert!(manager.health_probe().unwrap());
+            manager.wait_until_ready(Duration::from_secs(5))
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_health_probe_requires_a_serving_server() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Without a memory store the health service reports an unknown status
+        let port = serve_health(None).await;
+        let manager = manager_for_port(dir.path(), port);
+        let result = tokio::task::spawn_blocking(move || {
+            let probe = manager.health_probe();
+            (probe, manager.wait_until_ready(Duration::from_millis(300)))
+        })
+        .await
+        .unwrap();
+        assert!(!result.0.unwrap());
+        assert_eq!(result.1.unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        // Nothing listens on a port whose listener was dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let manager = manager_for_port(dir.path(), listener.local_addr().unwrap().port());
+        drop(listener);
+        let status = tokio::task::spawn_blocking(move || manager.health_probe().unwrap_err())
+            .await
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_set_log_level_at_reports_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = crate::logging::log_control_path(dir.path());
+        crate::logging::LogLevelController::new(&path)
+            .start()
+            .unwrap();
+
+        let result =
+            tokio::task::spawn_blocking(move || set_log_level_at(&path, "LOUD", "console"))
+                .await
+                .unwrap();
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains("Unknown log level: LOUD"));
+    }
+
+    #[test]
+    fn test_render_systemd_unit() {
+        let unit = render_systemd_unit(
+            Path::new("/usr/local/bin/smart-memory-mcp-core"),
+            Path::new("/etc/smart-memory/smart-memory.env"),
+            true,
+        );
+
+        assert!(unit.contains("ExecStart=\"/usr/local/bin/smart-memory-mcp-core\"\n"));
+        assert!(unit.contains("EnvironmentFile=/etc/smart-memory/smart-memory.env\n"));
+        assert!(!unit.contains("Environment=\""));
+        assert!(unit.contains("WantedBy=multi-user.target"));
+
+        let user_unit = render_systemd_unit(
+            Path::new("/opt/my \"tools\"/50%/$HOME\\bin"),
+            Path::new("/home/me/100%/smart-memory.env"),
+            false,
+        );
+        assert!(user_unit.contains("WantedBy=default.target"));
+        assert!(user_unit.contains("ExecStart=\"/opt/my \\\"tools\\\"/50%%/$$HOME\\\\bin\"\n"));
+        assert!(user_unit.contains("EnvironmentFile=/home/me/100%%/smart-memory.env\n"));
+    }
+
+    #[test]
+    fn test_render_environment_file() {
+        let environment = vec![
+            ("PORT", "50051".to_string()),
+            ("API_KEY", "se\"cr$et`\\%".to_string()),
+        ];
+        assert_eq!(
+            render_environment_file(&environment),
+            "PORT=\"50051\"\nAPI_KEY=\"se\\\"cr\\$et\\`\\\\%\"\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_environment_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("smart-memory.env");
+        fs::write(&path, "stale").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private_file(&path, "API_KEY=\"secret\"\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "API_KEY=\"secret\"\n");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+    }
+
+    #[test]
+    fn test_convert_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("config.json");
+        let yaml = dir.path().join("config.yaml");
+        let config = crate::storage::MemoryBankConfig {
+            max_pinned: 7,
+            ..Default::default()
+        };
+        config.save(&json).unwrap();
+
+        convert_config(&json, &yaml).unwrap();
+        assert_eq!(
+            crate::storage::MemoryBankConfig::from_yaml_file(&yaml).unwrap(),
+            config
+        );
+
+        let error = convert_config(&json, &dir.path().join("config.ini")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    /// Port the stand-in server of `test_upgrade_replaces_running_server` listens on
+    #[cfg(target_os = "linux")]
+    const FAKE_SERVER_PORT_VAR: &str = "SMART_MEMORY_FAKE_SERVER_PORT";
+
+    /// Stand-in server binary: serves the health service until it is killed
+    ///
+    /// Run by the scripts `test_upgrade_replaces_running_server` installs, and
+    /// a no-op without `SMART_MEMORY_FAKE_SERVER_PORT`.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[ignore]
+    async fn fake_server_for_upgrade() {
+        let Some(port) = env::var(FAKE_SERVER_PORT_VAR).ok() else {
+            return;
+        };
+        let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port))
+            .await
+            .unwrap();
+        tonic::transport::Server::builder()
+            .add_service(crate::service::create_health_service(Some(memory_store())))
+            .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
+            .await
+            .unwrap();
+    }
+
+    /// Write a server binary at `path` that runs `fake_server_for_upgrade` from this test binary
+    #[cfg(target_os = "linux")]
+    fn write_fake_server(path: &Path, port: u16, version: u32) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = format!(
+            "#!/bin/sh\n# version {}\n{}={} exec {} --ignored --exact server_manager::tests::fake_server_for_upgrade\n",
+            version,
+            FAKE_SERVER_PORT_VAR,
+            port,
+            env::current_exe().unwrap().display()
+        );
+        fs::write(path, script).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_upgrade_replaces_running_server() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut manager = manager_for_port(dir.path(), port);
+        manager.binary_path = dir.path().join("smart-memory-mcp");
+        write_fake_server(&manager.binary_path, port, 1);
+        let old_pid = manager.start_server().unwrap();
+
+        // A binary without the executable bit is refused before the server is touched
+        let new_binary = dir.path().join("smart-memory-mcp-new");
+        write_fake_server(&new_binary, port, 2);
+        fs::set_permissions(&new_binary, fs::Permissions::from_mode(0o644)).unwrap();
+        let error = manager.upgrade(&new_binary).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(manager.is_server_running(), Some(old_pid));
+
+        fs::set_permissions(&new_binary, fs::Permissions::from_mode(0o755)).unwrap();
+        let upgraded = manager.upgrade(&new_binary);
+        let new_pid = manager.is_server_running();
+        if let Some(pid) = new_pid {
+            manager.stop_server(pid);
+        }
+        upgraded.unwrap();
+
+        assert!(new_pid.is_some_and(|pid| pid != old_pid));
+        assert!(fs::read_to_string(&manager.binary_path)
+            .unwrap()
+            .contains("# version 2"));
+        assert!(fs::read_to_string(backup_binary_path(&manager.binary_path))
+            .unwrap()
+            .contains("# version 1"));
+        assert!(!staged_binary_path(&manager.binary_path).exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_failed_upgrade_restores_previous_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let mut manager = manager_for_port(dir.path(), port);
+        manager.binary_path = dir.path().join("smart-memory-mcp");
+        write_fake_server(&manager.binary_path, port, 1);
+        let old_pid = manager.start_server().unwrap();
+
+        // A binary that exits at once never passes the health probe
+        let new_binary = dir.path().join("smart-memory-mcp-new");
+        fs::write(&new_binary, "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(&new_binary, fs::Permissions::from_mode(0o755)).unwrap();
+        let upgraded = manager.upgrade(&new_binary);
+        let restored_pid = manager.is_server_running();
+        let serving = manager.health_probe();
+        if let Some(pid) = restored_pid {
+            manager.stop_server(pid);
+        }
+
+        assert!(upgraded.is_err());
+        assert!(restored_pid.is_some_and(|pid| pid != old_pid));
+        assert!(serving.unwrap());
+        assert!(fs::read_to_string(&manager.binary_path)
+            .unwrap()
+            .contains("# version 1"));
+        assert!(!staged_binary_path(&manager.binary_path).exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_health_probe_over_tls() {
+        let dir = tempfile::tempdir().unwrap();
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let tls = TlsPaths {
+            cert_path: dir.path().join("server.pem"),
+            key_path: dir.path().join("server.key"),
+            client_ca_path: None,
+        };
+        fs::write(&tls.cert_path, certified.cert.pem()).unwrap();
+        fs::write(&tls.key_path, certified.key_pair.serialize_pem()).unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .tls_config(tls.load().await.unwrap())
+                .unwrap()
+                .add_service(crate::service::create_health_service(Some(memory_store())))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        // The certificate names localhost, but the manager talks to 127.0.0.1
+        let mut manager = manager_for_port(dir.path(), port);
+        let plain_probe = tokio::task::spawn_blocking(move || {
+            let plain = manager.health_probe();
+            manager.tls = Some(tls);
+            (plain, manager.health_probe())
+        })
+        .await
+        .unwrap();
+        assert!(plain_probe.0.is_err());
+        assert!(plain_probe.1.unwrap());
+
+        // Another certificate is not trusted
+        let other = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let other_tls = TlsPaths {
+            cert_path: dir.path().join("other.pem"),
+            key_path: dir.path().join("other.key"),
+            client_ca_path: None,
+        };
+        fs::write(&other_tls.cert_path, other.cert.pem()).unwrap();
+        fs::write(&other_tls.key_path, other.key_pair.serialize_pem()).unwrap();
+        let mut manager = manager_for_port(dir.path(), port);
+        manager.tls = Some(other_tls);
+        let status = tokio::task::spawn_blocking(move || manager.health_probe().unwrap_err())
+            .await
+            .unwrap();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+    }
+}
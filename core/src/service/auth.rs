@@ -0,0 +1,132 @@
+//! API key authentication for gRPC requests
+//!
+//! When the `API_KEY` environment variable is set, protected RPCs require the
+//! caller to present it either as `authorization: Bearer <key>` or as an
+//! `x-api-key` metadata entry. Without `API_KEY` authentication is disabled.
+//!
+//! Admin RPCs additionally require the `REQUIRE_ADMIN_KEY` value, when set, as
+//! an `x-admin-key` metadata entry.
+
+use tonic::{Request, Status};
+
+/// Environment variable holding the server's API key
+const API_KEY_ENV: &str = "API_KEY";
+
+/// Get the configured API key, or `None` when authentication is disabled
+pub fn api_key() -> Option<String> {
+    std::env::var(API_KEY_ENV)
+        .ok()
+        .filter(|expected| !expected.is_empty())
+}
+
+/// Check that a request carries the configured API key
+pub fn check_api_key<T>(request: &Request<T>) -> Result<(), Status> {
+    match api_key() {
+        Some(expected) => verify_api_key(request, &expected),
+        None => Ok(()),
+    }
+}
+
+/// Environment variable holding the key required by admin RPCs
+const ADMIN_KEY_ENV: &str = "REQUIRE_ADMIN_KEY";
+
+/// Check that a request to an admin RPC carries the configured admin key
+pub fn check_admin_key<T>(request: &Request<T>) -> Result<(), Status> {
+    check_api_key(request)?;
+    match std::env::var(ADMIN_KEY_ENV) {
+        Ok(expected) if !expected.is_empty() => verify_admin_key(request, &expected),
+        _ => Ok(()),
+    }
+}
+
+/// Wrap a message in a request carrying the `API_KEY` from the environment, if set
+pub fn authorized_request<T>(message: T) -> Request<T> {
+    let mut request = Request::new(message);
+    if let Ok(api_key) = std::env::var(API_KEY_ENV) {
+        if let Ok(value) = api_key.parse() {
+            request.metadata_mut().insert("x-api-key", value);
+        }
+    }
+    request
+}
+
+/// Verify the request's API key against the expected key
+pub fn verify_api_key<T>(request: &Request<T>, expected: &str) -> Result<(), Status> {
+    let metadata = request.metadata();
+
+    let bearer = metadata
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let header = metadata
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok());
+
+    match bearer.or(header) {
+        Some(provided) if constant_time_eq(provided.as_bytes(), expected.as_bytes()) => Ok(()),
+        Some(_) => Err(Status::unauthenticated("Invalid API key")),
+        None => Err(Status::unauthenticated("Missing API key")),
+    }
+}
+
+/// Verify the request's admin key against the expected key
+fn verify_admin_key<T>(request: &Request<T>, expected: &str) -> Result<(), Status> {
+    match request
+        .metadata()
+        .get("x-admin-key")
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(provided) if constant_time_eq(provided.as_bytes(), expected.as_bytes()) => Ok(()),
+        Some(_) => Err(Status::permission_denied("Invalid admin key")),
+        None => Err(Status::permission_denied("Missing admin key")),
+    }
+}
+
+/// Compare two byte strings without short-circuiting on the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_api_key() {
+        let mut request = Request::new(());
+        assert_eq!(
+            verify_api_key(&request, "secret").unwrap_err().code(),
+            tonic::Code::Unauthenticated
+        );
+
+        request
+            .metadata_mut()
+            .insert("x-api-key", "wrong".parse().unwrap());
+        assert!(verify_api_key(&request, "secret").is_err());
+
+        request
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        assert!(verify_api_key(&request, "secret").is_ok());
+    }
+
+    #[test]
+    fn test_verify_admin_key() {
+        let mut request = Request::new(());
+        assert_eq!(
+            verify_admin_key(&request, "admin").unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+
+        // The API key does not stand in for the admin key
+        request
+            .metadata_mut()
+            .insert("x-api-key", "admin".parse().unwrap());
+        assert!(verify_admin_key(&request, "admin").is_err());
+
+        request
+            .metadata_mut()
+            .insert("x-admin-key", "admin".parse().unwrap());
+        assert!(verify_admin_key(&request, "admin").is_ok());
+    }
+}
@@ -0,0 +1,136 @@
+//! Token usage of the LLM context window across `GetContext` calls
+//!
+//! A client passing a `session_id` gets one [`ContextWindow`] per session.
+//! Every call's budget is its `max_tokens` less the tokens earlier calls of
+//! the session already returned, so context from several calls never adds
+//! up to more than the window holds. Sessions idle for longer than
+//! [`SESSION_IDLE_TIMEOUT`] are forgotten.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a session may go without a call before its window is dropped
+pub const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Tokens one session has already placed in its LLM's context window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextWindow {
+    pub session_id: String,
+    /// Size of the window, from the `max_tokens` of the latest call
+    pub total_capacity: u32,
+    /// Tokens returned to the session so far
+    pub used_tokens: u32,
+    /// When the session last made a call
+    last_active: Instant,
+}
+
+impl ContextWindow {
+    /// Tokens still free in the window
+    pub fn remaining(&self) -> u32 {
+        self.total_capacity.saturating_sub(self.used_tokens)
+    }
+}
+
+/// Context windows of every active session
+#[derive(Debug, Clone)]
+pub struct ContextWindows {
+    /// Window of each session, by session ID
+    windows: Arc<Mutex<HashMap<String, ContextWindow>>>,
+    /// Inactivity after which a session is dropped
+    idle_timeout: Duration,
+}
+
+impl Default for ContextWindows {
+    fn default() -> Self {
+        Self::new(SESSION_IDLE_TIMEOUT)
+    }
+}
+
+impl ContextWindows {
+    /// Track sessions, dropping those idle for longer than `idle_timeout`
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            windows: Arc::new(Mutex::new(HashMap::new())),
+            idle_timeout,
+        }
+    }
+
+    /// Open or refresh the session's window with a capacity of `max_tokens`,
+    /// returning the tokens still free in it
+    pub fn budget(&self, session_id: &str, max_tokens: u32) -> u32 {
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        self.expire(&mut windows, now);
+
+        let window = windows
+            .entry(session_id.to_string())
+            .or_insert_with(|| ContextWindow {
+                session_id: session_id.to_string(),
+                total_capacity: max_tokens,
+                used_tokens: 0,
+                last_active: now,
+            });
+        window.total_capacity = max_tokens;
+        window.last_active = now;
+        window.remaining()
+    }
+
+    /// Add `tokens` returned to the session to its used tokens
+    pub fn record(&self, session_id: &str, tokens: u32) {
+        if let Some(window) = self.windows.lock().unwrap().get_mut(session_id) {
+            window.used_tokens = window.used_tokens.saturating_add(tokens);
+            window.last_active = Instant::now();
+        }
+    }
+
+    /// Get the session's window, if it is still active
+    pub fn get(&self, session_id: &str) -> Option<ContextWindow> {
+        let mut windows = self.windows.lock().unwrap();
+        self.expire(&mut windows, Instant::now());
+        windows.get(session_id).cloned()
+    }
+
+    /// Forget the session's window, returning whether it was active
+    pub fn reset(&self, session_id: &str) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        self.expire(&mut windows, Instant::now());
+        windows.remove(session_id).is_some()
+    }
+
+    /// Drop the windows of sessions idle for longer than the timeout
+    fn expire(&self, windows: &mut HashMap<String, ContextWindow>, now: Instant) {
+        windows.retain(|_, window| now.duration_since(window.last_active) <= self.idle_timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_shrinks_by_recorded_tokens() {
+        let windows = ContextWindows::default();
+
+        assert_eq!(windows.budget("session", 1000), 1000);
+        windows.record("session", 300);
+        assert_eq!(windows.budget("session", 1000), 700);
+        windows.record("session", 800);
+        assert_eq!(windows.budget("session", 1000), 0);
+
+        // Other sessions have windows of their own
+        assert_eq!(windows.budget("other", 500), 500);
+    }
+
+    #[test]
+    fn test_idle_sessions_expire() {
+        let windows = ContextWindows::new(Duration::from_millis(20));
+        windows.budget("session", 1000);
+        windows.record("session", 400);
+        assert_eq!(windows.get("session").unwrap().used_tokens, 400);
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(windows.get("session"), None);
+        assert_eq!(windows.budget("session", 1000), 1000);
+    }
+}
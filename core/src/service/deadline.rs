@@ -0,0 +1,282 @@
+//! Per-call deadlines
+//!
+//! Every call must finish within the timeout the client sent in its
+//! `grpc-timeout` header and within the server-wide `RPC_TIMEOUT_MS`, whichever
+//! is shorter. Calls still running at their deadline are answered with
+//! `DEADLINE_EXCEEDED`. Streaming, sync and maintenance calls may legitimately
+//! run for minutes, so only the client's own timeout applies to them.
+//!
+//! Handlers do their store work on the blocking pool, which a deadline does
+//! not interrupt. A write that committed before the deadline therefore still
+//! completes, announcing its change, even though the client is told it timed
+//! out. Calls that arrive with no time left are refused before they start.
+
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture};
+use tonic::Status;
+use tower::{Layer, Service};
+
+/// Environment variable holding the longest a call may run, in milliseconds
+const RPC_TIMEOUT_VAR: &str = "RPC_TIMEOUT_MS";
+
+/// Default longest a call may run, in milliseconds
+const DEFAULT_RPC_TIMEOUT_MS: u64 = 5000;
+
+/// Header carrying the client's timeout
+const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// Calls held only to the client's timeout, never to `RPC_TIMEOUT_MS`
+///
+/// Imports commit chunk by chunk, so cutting one off would leave it half done.
+const EXEMPT_PATHS: &[&str] = &[
+    "/smart_memory.SmartMemoryMcp/GetContextStream",
+    "/smart_memory.SmartMemoryMcp/StreamLogs",
+    "/smart_memory.SmartMemoryMcp/ExportMemories",
+    "/smart_memory.SmartMemoryMcp/ImportMemories",
+    "/smart_memory.SmartMemoryMcp/MemoryBankSync",
+    "/smart_memory.SmartMemoryMcp/SyncExport",
+    "/smart_memory.SmartMemoryMcp/SyncImport",
+    "/smart_memory.SmartMemoryMcp/VerifyBackup",
+    "/smart_memory.SmartMemoryMcp/Compact",
+    "/smart_memory.SmartMemoryMcp/Vacuum",
+    "/smart_memory.SmartMemoryMcp/RecalculateTokens",
+    "/smart_memory.SmartMemoryMcp/PrepareRestore",
+    "/smart_memory.SmartMemoryMcp/RestoreLatest",
+];
+
+/// Time by which a call is answered ahead of the client's timeout
+///
+/// gRPC clients give up locally once their own timeout passes, so the reply
+/// has to leave early enough to reach them as `DEADLINE_EXCEEDED` rather than
+/// being dropped in favour of a client-side `CANCELLED`.
+const CLIENT_DEADLINE_MARGIN: Duration = Duration::from_millis(5);
+
+/// Parse a `grpc-timeout` header value, e.g. `100m` or `5S`
+///
+/// The value is up to eight digits followed by a unit: `H`ours, `M`inutes,
+/// `S`econds, `m`illiseconds, `u` microseconds or `n`anoseconds.
+pub fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let amount: u64 = digits.parse().ok()?;
+    match unit {
+        "H" => Some(Duration::from_secs(amount * 60 * 60)),
+        "M" => Some(Duration::from_secs(amount * 60)),
+        "S" => Some(Duration::from_secs(amount)),
+        "m" => Some(Duration::from_millis(amount)),
+        "u" => Some(Duration::from_micros(amount)),
+        "n" => Some(Duration::from_nanos(amount)),
+        _ => None,
+    }
+}
+
+/// Response a failed call can be answered with
+pub trait StatusResponse {
+    /// Build the response reporting `status`
+    fn from_status(status: Status) -> Self;
+}
+
+impl StatusResponse for http::Response<BoxBody> {
+    fn from_status(status: Status) -> Self {
+        status.to_http()
+    }
+}
+
+/// Layer answering calls that outlive their deadline with `DEADLINE_EXCEEDED`
+#[derive(Debug, Clone)]
+pub struct Deadline {
+    /// Longest any call may run, whatever the client asks for
+    server_timeout: Duration,
+}
+
+impl Deadline {
+    /// Create a layer letting no call run longer than `server_timeout`
+    pub fn new(server_timeout: Duration) -> Self {
+        Self { server_timeout }
+    }
+
+    /// Create a layer from `RPC_TIMEOUT_MS`
+    pub fn from_env() -> Self {
+        let timeout_ms = std::env::var(RPC_TIMEOUT_VAR)
+            .ok()
+            .and_then(|timeout| timeout.parse().ok())
+            .unwrap_or(DEFAULT_RPC_TIMEOUT_MS);
+        Self::new(Duration::from_millis(timeout_ms))
+    }
+
+    /// Time a call to `path` with the given headers may run, if it is limited at all
+    fn timeout_for(&self, path: &str, headers: &http::HeaderMap) -> Option<Duration> {
+        let client_timeout = headers
+            .get(GRPC_TIMEOUT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_grpc_timeout)
+            .map(|timeout| timeout.saturating_sub(CLIENT_DEADLINE_MARGIN));
+        if EXEMPT_PATHS.contains(&path) {
+            return client_timeout;
+        }
+        Some(client_timeout.map_or(self.server_timeout, |timeout| {
+            timeout.min(self.server_timeout)
+        }))
+    }
+}
+
+impl<S> Layer<S> for Deadline {
+    type Service = WithDeadline<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithDeadline {
+            inner,
+            deadline: self.clone(),
+        }
+    }
+}
+
+/// Service running every call under a [`Deadline`]
+#[derive(Debug, Clone)]
+pub struct WithDeadline<S> {
+    inner: S,
+    deadline: Deadline,
+}
+
+impl<S, B> Service<http::Request<B>> for WithDeadline<S>
+where
+    S: Service<http::Request<B>>,
+    S::Response: StatusResponse,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let path = request.uri().path().to_string();
+        let Some(timeout) = self.deadline.timeout_for(&path, request.headers()) else {
+            return Box::pin(self.inner.call(request));
+        };
+        if timeout.is_zero() {
+            let status = Status::deadline_exceeded(format!("{} arrived past its deadline", path));
+            return Box::pin(async move { Ok(S::Response::from_status(status)) });
+        }
+        // Start the clock now rather than when the response is first polled
+        let call = tokio::time::timeout(timeout, self.inner.call(request));
+        Box::pin(async move {
+            match call.await {
+                Ok(result) => result,
+                Err(_) => Ok(S::Response::from_status(Status::deadline_exceeded(
+                    format!("{} did not finish within {}ms", path, timeout.as_millis()),
+                ))),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::smart_memory_mcp_client::SmartMemoryMcpClient;
+    use crate::proto::smart_memory_mcp_server::SmartMemoryMcpServer;
+    use crate::proto::ContextRequest;
+    use crate::service::memory_service::SmartMemoryService;
+
+    #[test]
+    fn test_parse_grpc_timeout() {
+        assert_eq!(parse_grpc_timeout("10m"), Some(Duration::from_millis(10)));
+        assert_eq!(parse_grpc_timeout("5S"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_grpc_timeout("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_grpc_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_grpc_timeout("250u"), Some(Duration::from_micros(250)));
+        assert_eq!(
+            parse_grpc_timeout("99999999n"),
+            Some(Duration::from_nanos(99_999_999))
+        );
+
+        assert_eq!(parse_grpc_timeout(""), None);
+        assert_eq!(parse_grpc_timeout("m"), None);
+        assert_eq!(parse_grpc_timeout("10"), None);
+        assert_eq!(parse_grpc_timeout("10x"), None);
+        assert_eq!(parse_grpc_timeout("-10m"), None);
+        assert_eq!(parse_grpc_timeout("123456789m"), None);
+    }
+
+    #[test]
+    fn test_client_timeout_is_capped_by_server_timeout() {
+        let deadline = Deadline::new(Duration::from_secs(5));
+        let path = "/smart_memory.SmartMemoryMcp/GetContext";
+        let mut headers = http::HeaderMap::new();
+        assert_eq!(
+            deadline.timeout_for(path, &headers),
+            Some(Duration::from_secs(5))
+        );
+
+        headers.insert(GRPC_TIMEOUT_HEADER, "100m".parse().unwrap());
+        assert_eq!(
+            deadline.timeout_for(path, &headers),
+            Some(Duration::from_millis(100) - CLIENT_DEADLINE_MARGIN)
+        );
+
+        headers.insert(GRPC_TIMEOUT_HEADER, "1M".parse().unwrap());
+        assert_eq!(
+            deadline.timeout_for(path, &headers),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_long_running_calls_only_honour_client_timeout() {
+        let deadline = Deadline::new(Duration::from_secs(5));
+        let path = "/smart_memory.SmartMemoryMcp/ImportMemories";
+        let mut headers = http::HeaderMap::new();
+        assert_eq!(deadline.timeout_for(path, &headers), None);
+
+        headers.insert(GRPC_TIMEOUT_HEADER, "1M".parse().unwrap());
+        assert_eq!(
+            deadline.timeout_for(path, &headers),
+            Some(Duration::from_secs(60) - CLIENT_DEADLINE_MARGIN)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_large_get_context_exceeds_client_deadline() {
+        let service = SmartMemoryService::new().unwrap();
+        let memories = (0..2000)
+            .map(|i| {
+                (
+                    format!("note {} about deadlines and slow context requests", i),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    Default::default(),
+                )
+            })
+            .collect();
+        service.memory_store.bulk_store(memories).unwrap();
+
+        // tonic's transport would give up on its own at the client's timeout and race
+        // the server's reply, so the client talks to the layered service directly
+        let mut client = SmartMemoryMcpClient::new(
+            Deadline::new(Duration::from_secs(5)).layer(SmartMemoryMcpServer::new(service)),
+        );
+
+        let mut request = tonic::Request::new(ContextRequest {
+            query: "deadlines".to_string(),
+            mode: "code".to_string(),
+            max_tokens: 200_000,
+            ..Default::default()
+        });
+        request.set_timeout(Duration::from_millis(10));
+        let status = client.get_context(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+    }
+}
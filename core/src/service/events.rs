@@ -0,0 +1,151 @@
+//! Live notifications of memory store changes
+//!
+//! Every successful store, update and delete publishes a [`MemoryEvent`] to
+//! all current subscribers. Each subscriber may fall at most `EVENTS_MAX_LAG`
+//! events behind; beyond that it misses events and is expected to disconnect.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::storage::Memory;
+
+/// Environment variable holding the number of events a subscriber may fall behind
+const EVENTS_MAX_LAG_VAR: &str = "EVENTS_MAX_LAG";
+
+/// Default number of events a subscriber may fall behind
+const DEFAULT_MAX_LAG: usize = 256;
+
+/// Kind of change to a memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    Stored,
+    Updated,
+    Deleted,
+}
+
+/// A change to one memory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryEvent {
+    pub event_type: EventType,
+    pub memory_id: String,
+    pub category: Option<String>,
+    pub mode: Option<String>,
+    /// Milliseconds since the Unix epoch when the change was published
+    pub timestamp: u64,
+}
+
+impl MemoryEvent {
+    /// Describe a change to `memory` happening now
+    pub fn new(event_type: EventType, memory: &Memory) -> Self {
+        Self {
+            event_type,
+            memory_id: memory.id.as_str().to_string(),
+            category: memory.category.clone(),
+            mode: memory.mode.clone(),
+            timestamp: chrono::Utc::now().timestamp_millis().max(0) as u64,
+        }
+    }
+}
+
+/// Fans memory events out to every subscriber
+#[derive(Debug)]
+pub struct EventBroadcaster {
+    sender: broadcast::Sender<MemoryEvent>,
+}
+
+impl EventBroadcaster {
+    /// Create a broadcaster whose subscribers may fall `max_lag` events behind
+    ///
+    /// The channel holds exactly `max_lag` events, so a subscriber further
+    /// behind gets [`broadcast::error::RecvError::Lagged`] on its next receive.
+    pub fn new(max_lag: usize) -> Self {
+        let (sender, _) = broadcast::channel(max_lag.max(1));
+        Self { sender }
+    }
+
+    /// Create a broadcaster from `EVENTS_MAX_LAG`
+    pub fn from_env() -> Self {
+        let max_lag = std::env::var(EVENTS_MAX_LAG_VAR)
+            .ok()
+            .and_then(|lag| lag.parse().ok())
+            .unwrap_or(DEFAULT_MAX_LAG);
+        Self::new(max_lag)
+    }
+
+    /// Publish an event to the current subscribers, if any
+    pub fn publish(&self, event: MemoryEvent) {
+        // Sending only fails when nobody is listening
+        let _ = self.sender.send(event);
+    }
+
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<MemoryEvent> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryId, Tokenizer};
+    use broadcast::error::RecvError;
+
+    fn event(id: &str) -> MemoryEvent {
+        MemoryEvent {
+            event_type: EventType::Stored,
+            memory_id: id.to_string(),
+            category: None,
+            mode: Some("code".to_string()),
+            timestamp: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_published_events() {
+        let events = EventBroadcaster::new(4);
+        events.publish(event("before"));
+
+        let mut first = events.subscribe();
+        let mut second = events.subscribe();
+        events.publish(event("after"));
+
+        assert_eq!(first.recv().await.unwrap().memory_id, "after");
+        assert_eq!(second.recv().await.unwrap().memory_id, "after");
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_lag_past_the_threshold() {
+        let events = EventBroadcaster::new(2);
+        let mut receiver = events.subscribe();
+
+        events.publish(event("1"));
+        events.publish(event("2"));
+        assert_eq!(receiver.recv().await.unwrap().memory_id, "1");
+
+        events.publish(event("3"));
+        events.publish(event("4"));
+        events.publish(event("5"));
+        assert!(matches!(receiver.recv().await, Err(RecvError::Lagged(_))));
+    }
+
+    #[test]
+    fn test_event_serializes_as_json() {
+        let mut memory = Memory::new(
+            "content".to_string(),
+            "text/plain".to_string(),
+            Some("decision".to_string()),
+            None,
+            Default::default(),
+            &Tokenizer::default(),
+        );
+        memory.id = MemoryId::from("m1");
+
+        let json = serde_json::to_value(MemoryEvent::new(EventType::Deleted, &memory)).unwrap();
+        assert_eq!(json["event_type"], "deleted");
+        assert_eq!(json["memory_id"], "m1");
+        assert_eq!(json["category"], "decision");
+        assert!(json["mode"].is_null());
+        assert!(json["timestamp"].as_u64().unwrap() > 0);
+    }
+}
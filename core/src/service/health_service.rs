@@ -10,7 +10,7 @@ use crate::proto::health_check_server::{HealthCheck, HealthCheckServer};
 use crate::proto::{
     ComponentStatus, HealthCheckRequest, HealthCheckResponse, StatusRequest, StatusResponse,
 };
-use crate::storage::MemoryStore;
+use crate::storage::{MemoryStore, UNCATEGORIZED};
 
 /// Health check service implementation
 pub struct HealthCheckService {
@@ -50,8 +50,8 @@ impl HealthCheckService {
     /// Get the total number of memories
     fn total_memories(&self) -> u32 {
         if let Some(store) = &self.memory_store {
-            match store.get_all_ids() {
-                Ok(ids) => ids.len() as u32,
+            match store.count() {
+                Ok(count) => count as u32,
                 Err(_) => 0,
             }
         } else {
@@ -103,6 +103,31 @@ impl HealthCheckService {
             info.insert("port".to_string(), port);
         }
 
+        // Add memory cache statistics
+        if let Some(store) = &self.memory_store {
+            let stats = store.cache_stats();
+            info.insert("cache_entries".to_string(), stats.entries.to_string());
+            info.insert("cache_capacity".to_string(), stats.capacity.to_string());
+            info.insert("cache_hits".to_string(), stats.hits.to_string());
+            info.insert("cache_misses".to_string(), stats.misses.to_string());
+            info.insert(
+                "cache_hit_rate".to_string(),
+                format!("{:.3}", store.cache_hit_rate()),
+            );
+
+            // Cheap per-category counts, so monitoring need not call the stats RPC
+            if let Ok(mut counts) = store.count_by_category() {
+                if let Ok(uncategorized) = store.count_uncategorized() {
+                    if uncategorized > 0 {
+                        counts.insert(UNCATEGORIZED.to_string(), uncategorized);
+                    }
+                }
+                if let Ok(json) = serde_json::to_string(&counts) {
+                    info.insert("memories_by_category".to_string(), json);
+                }
+            }
+        }
+
         info
     }
 
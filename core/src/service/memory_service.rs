@@ -1,17 +1,82 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::path::Path;
-use std::sync::Arc;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::time::{Duration, Instant};
 
 use anyhow::{Context as AnyhowContext, Result};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tokio_util::sync::CancellationToken;
 use tonic::{Request, Response, Status};
 
+use super::auth;
+use super::context_window::ContextWindows;
+use super::events::{EventBroadcaster, EventType, MemoryEvent};
+use super::prediction::{record_transition, PredictionModel, MODE_TRANSITION_CATEGORY};
+use super::sync_peers::SyncPeers;
+use super::validation::{RequestValidator, Validator};
+use crate::logging::{self, LogEntry, LogFilter, LogLevel, RequestContext};
+use crate::metrics_server::PrometheusMetrics;
+use crate::telemetry;
+use opentelemetry::global::{BoxedSpan, BoxedTracer};
+use opentelemetry::trace::{TraceContextExt, Tracer};
+
+use crate::proto::smart_memory_mcp_client::SmartMemoryMcpClient;
 use crate::proto::smart_memory_mcp_server::{SmartMemoryMcp, SmartMemoryMcpServer};
 use crate::proto::{
     AnalyzeModeRequest,
     AnalyzeModeResponse,
+    BulkStoreRequest,
+    BulkStoreResponse,
+    CategorySummary,
+    CommitSnapshotRequest,
+    CommitSnapshotResponse,
+    CompactRequest,
+    CompactResponse,
+    ContextChunk,
+    ContextExplanation,
     ContextRequest,
     ContextResponse,
     ContextSource,
+    CopyMemoryRequest,
+    CopyMemoryResponse,
+    CreateSnapshotRequest,
+    CreateSnapshotResponse,
+    DeleteMemoryRequest,
+    DeleteMemoryResponse,
+    DiffMemoriesRequest,
+    DiffResponse,
+    DuplicateGroupResult,
+    ExportChunk,
+    ExportMemoriesRequest,
+    ExportedMemory,
+    FilterByTagsRequest,
+    FilterByTagsResponse,
+    FindDuplicatesRequest,
+    FindDuplicatesResponse,
+    GetConfigRequest,
+    GetConfigResponse,
+    GetHistoryRequest,
+    GetHistoryResponse,
+    GetHotMemoriesRequest,
+    GetHotMemoriesResponse,
+    GetLinkedRequest,
+    GetLinkedResponse,
+    GetLogsRequest,
+    GetLogsResponse,
+    GetRelatedRequest,
+    GetRelatedResponse,
+    GetSizeDistributionRequest,
+    GetSizeDistributionResponse,
+    ImportChunk,
+    ImportResponse,
+    IsolationMode as IsolationModeRequest,
+    LinkMemoriesRequest,
+    LinkMemoriesResponse,
+    ListMemoriesRequest,
+    ListMemoriesResponse,
+    LogRecord,
     MemoryBankCategoryStats,
     MemoryBankContextRequest,
     MemoryBankContextResponse,
@@ -23,38 +88,187 @@ use crate::proto::{
     // Memory Bank messages
     MemoryBankStoreRequest,
     MemoryBankStoreResponse,
+    MemoryResult,
+    MergeCategoriesRequest,
+    MergeCategoriesResponse,
+    MergeMemoriesRequest,
+    MergeMemoriesResponse,
+    Metric,
     MetricsRequest,
     MetricsResponse,
+    ModeMetric,
     OptimizationStrategy,
     OptimizeRequest,
     OptimizeResponse,
+    PinMemoryRequest,
+    PinMemoryResponse,
     PredictRequest,
     PredictResponse,
+    PrepareRestoreRequest,
+    PrepareRestoreResponse,
     Priority,
+    RecalculateTokensRequest,
+    RecalculateTokensResponse,
+    RegexSearchRequest,
+    RegexSearchResponse,
+    RelatedMemory,
+    RenameCategoryRequest,
+    ResetSessionRequest,
+    ResetSessionResponse,
+    RestoreLatestRequest,
+    RestoreLatestResponse,
     RetrieveRequest,
     RetrieveResponse,
+    RollbackRequest,
+    RollbackResponse,
+    RollbackSnapshotRequest,
+    RollbackSnapshotResponse,
+    SearchMemoriesRequest,
+    SearchMemoriesResponse,
+    SizeDistribution as SizeDistributionProto,
     StoreRequest,
     StoreResponse,
+    StreamLogsRequest,
     SwitchModeRequest,
     SwitchModeResponse,
+    SyncExportRequest,
+    SyncImportRequest,
+    SyncImportResponse,
+    SyncPayload,
+    SyncRequest,
+    SyncResponse,
+    Trend,
     // UMB command messages
     UmbCommandRequest,
     UmbCommandResponse,
+    UnpinMemoryRequest,
     UpdateContextRequest,
     UpdateContextResponse,
+    UpdateMemoryRequest,
+    UpdateMemoryResponse,
+    Usage,
     UsageRequest,
     UsageResponse,
+    VacuumRequest,
+    VacuumResponse,
+    VerifyBackupRequest,
+    VerifyBackupResponse,
 };
 use crate::storage::{
-    ContextOptimizer, MemoryBankConfig, MemoryId, MemoryStore, RelevanceScorer, TfIdfScorer,
-    TokenBudgetOptimizer, TokenCount, Tokenizer, TokenizerType,
+    decode_memories, default_backup_dir, encode_memories, find_duplicate_groups,
+    AsyncMemoryRepository, AsyncSqliteMemoryRepository, BackupManager, BackupMetadata,
+    CategoryAwareOptimizer, CircularLink, CompactionInProgress, ConflictResolution, ContentCipher,
+    ContextOptimizer, DuplicateGroup, DuplicateMemoryId, EmbeddingScorer, ExclusionReason,
+    HybridScorer, ImportConflict, IsolationMode, LanguageTagger, Memory, MemoryBankConfig,
+    MemoryDiff, MemoryFilter, MemoryId, MemoryQuery, MemorySortField, MemoryStore, MetricsStore,
+    RegexSafetyError, RelevanceScorer, ScoredMemory, SnapshotId, SortOrder, SqliteMemoryRepository,
+    StoreOptions, SummarizingOptimizer, TfIdfScorer, TokenBudgetOptimizer, TokenCount,
+    TokenPricing, Tokenizer, TokenizerType, UnknownMemory, UnknownSnapshot, UnknownVersion,
+    ACCESS_LOG_CATEGORY, CONFIG_SCHEMA_VERSION, DEFAULT_HYBRID_ALPHA, HISTORY_CATEGORY,
 };
 
+/// Default number of results returned by search RPCs
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// Default number of memories in each page of `ListMemories`
+const DEFAULT_LIST_PAGE_SIZE: usize = 50;
+
+/// Default number of memories returned by `GetHotMemories`
+const DEFAULT_HOT_MEMORIES_LIMIT: usize = 10;
+
+/// Separator placed between contents by `MergeMemories` when none is given
+const DEFAULT_MERGE_SEPARATOR: &str = "\n\n";
+
+/// Most retrieved memories loaded into the caches on startup
+const HOT_MEMORIES_PRELOAD: usize = 100;
+
+/// Memories loaded per page when building context from every memory
+const CONTEXT_PAGE_SIZE: usize = 500;
+
+/// How long a `GetConfig` response is served from cache
+const CONFIG_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// How long a `PrepareRestore` confirmation token stays valid
+const RESTORE_CONFIRMATION_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Default number of entries returned by `GetLogs`
+const DEFAULT_LOG_LIMIT: usize = 100;
+
+/// Number of log records buffered per `StreamLogs` subscriber
+const LOG_STREAM_BUFFER: usize = 128;
+
+/// Mode the service starts in before any `SwitchMode` call
+const DEFAULT_MODE: &str = "code";
+
+/// Category of the memories preserving a mode's context across a switch
+const MODE_SNAPSHOT_CATEGORY: &str = "mode_snapshot";
+
+/// Token budget for the context preserved when switching modes
+const SNAPSHOT_MAX_TOKENS: usize = 2000;
+
+/// How long the record of a context retrieval is kept, in seconds
+const ACCESS_LOG_TTL_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Category memories are moved to when they no longer fit their mode's token budget
+const ARCHIVED_CATEGORY: &str = "archived";
+
+/// Default `AnalyzeMode` window, in hours, for counting memories as recently accessed
+const DEFAULT_ANALYSIS_WINDOW_HOURS: u32 = 24;
+
+/// Average memory size, in tokens, at which the token dimension of `AnalyzeMode` scores 0.5
+const COMPACT_MEMORY_TOKENS: f64 = 500.0;
+
+/// Default token limit of a `GetContextStream` chunk, overridden by `STREAM_CHUNK_TOKENS`
+const DEFAULT_STREAM_CHUNK_TOKENS: usize = 1000;
+
+/// Default `GetMetrics` time range, in hours
+const DEFAULT_METRICS_RANGE_HOURS: u32 = 7 * 24;
+
+/// Memories sent in each `ExportMemories` chunk
+const EXPORT_CHUNK_SIZE: usize = 100;
+
+/// Export file formats accepted by `ExportMemories`
+const EXPORT_FORMATS: [&str; 2] = ["json", "msgpack"];
+
+/// Request metadata naming the model a context is priced for
+const MODEL_METADATA_KEY: &str = "x-model";
+
+#[derive(Clone)]
 pub struct SmartMemoryService {
     pub memory_store: Arc<MemoryStore>,
+    /// The memory store, run on the blocking pool for the async handlers
+    repository: AsyncSqliteMemoryRepository,
     relevance_scorer: Arc<dyn RelevanceScorer>,
     context_optimizer: Arc<dyn ContextOptimizer>,
-    memory_bank_config: MemoryBankConfig,
+    /// Memory bank config, replaced in place when the config file changes
+    memory_bank_config: Arc<RwLock<MemoryBankConfig>>,
+    /// Mode the client is currently working in
+    current_mode: Arc<Mutex<String>>,
+    /// Per-call metrics shared by every service instance
+    metrics: Arc<MetricsStore>,
+    /// Metrics exposed to Prometheus
+    prometheus: Arc<PrometheusMetrics>,
+    /// Tracer of the spans recorded for every call
+    tracer: Arc<BoxedTracer>,
+    /// Cached `GetConfig` response, when it was built and the config it was built from
+    config_cache: Arc<Mutex<Option<(Instant, MemoryBankConfig, GetConfigResponse)>>>,
+    /// Changes to memories, published for live subscribers
+    pub events: Arc<EventBroadcaster>,
+    /// Tokens each session's earlier `GetContext` calls returned
+    context_windows: ContextWindows,
+    /// Restore confirmed by the latest `PrepareRestore` call, until used or expired
+    pending_restore: Arc<Mutex<Option<PendingRestore>>>,
+    /// Peers `MemoryBankSync` may connect to
+    sync_peers: Arc<SyncPeers>,
+}
+
+/// A restore of the newest backup awaiting its confirmation token
+#[derive(Debug)]
+struct PendingRestore {
+    token: String,
+    /// File name of the backup that was newest when the token was issued
+    backup_name: String,
+    issued: Instant,
 }
 
 impl std::fmt::Debug for SmartMemoryService {
@@ -64,143 +278,869 @@ impl std::fmt::Debug for SmartMemoryService {
             .field("relevance_scorer", &"<dyn RelevanceScorer>")
             .field("context_optimizer", &"<dyn ContextOptimizer>")
             .field("memory_bank_config", &self.memory_bank_config)
+            .field("current_mode", &self.current_mode)
+            .field("metrics", &self.metrics)
             .finish()
     }
 }
 
-impl SmartMemoryService {
-    pub fn new() -> Result<Self> {
-        println!("Initializing SmartMemoryService...");
-
-        // Create the tokenizer
-        println!("Creating tokenizer...");
-        let tokenizer = Tokenizer::new(TokenizerType::Simple)
-            .map_err(|e| anyhow::anyhow!("Failed to create tokenizer: {}", e))?;
-        println!("Tokenizer created successfully");
+/// Records an RPC call in the metrics store and ends its span when dropped
+struct TrackedCall {
+    metrics: Arc<MetricsStore>,
+    prometheus: Arc<PrometheusMetrics>,
+    tracer: Arc<BoxedTracer>,
+    /// Trace context holding the span of the call
+    context: opentelemetry::Context,
+    operation: &'static str,
+    /// Identifies the call in its log entries
+    request: RequestContext,
+    mode: String,
+    token_count: usize,
+    started: Instant,
+}
 
-        // Create the memory store
-        println!("Creating memory store...");
-        let memory_store = Arc::new(MemoryStore::new_in_memory(tokenizer));
-        println!("Memory store created successfully");
+impl TrackedCall {
+    /// Get the context to pass to the log calls made for this call
+    fn request(&self) -> &RequestContext {
+        &self.request
+    }
 
-        // Create the relevance scorer
-        println!("Creating relevance scorer...");
-        let relevance_scorer = Arc::new(TfIdfScorer::new());
-        println!("Relevance scorer created successfully");
+    /// Set the mode the call was made for
+    fn set_mode(&mut self, mode: &str) {
+        self.mode = mode.to_string();
+    }
 
-        // Create the context optimizer
-        println!("Creating context optimizer...");
-        let context_optimizer = Arc::new(TokenBudgetOptimizer::new());
-        println!("Context optimizer created successfully");
+    /// Set the number of tokens the call stored or returned
+    fn set_tokens(&mut self, token_count: usize) {
+        self.token_count = token_count;
+    }
 
-        // Create the memory bank config
-        println!("Creating memory bank config...");
-        let memory_bank_config = MemoryBankConfig::default();
-        println!("Memory bank config created successfully");
+    /// Start a span for one step of the call, ended when dropped
+    fn child_span(&self, name: &'static str) -> BoxedSpan {
+        self.tracer.start_with_context(name, &self.context)
+    }
+}
 
-        println!("SmartMemoryService initialization complete");
+impl Drop for TrackedCall {
+    fn drop(&mut self) {
+        let latency = self.started.elapsed();
+        self.context.span().end();
+        self.prometheus.observe_call(self.operation, latency);
+        if let Err(e) = self
+            .metrics
+            .record(self.operation, &self.mode, self.token_count, latency)
+        {
+            crate::log_warning!(
+                "memory_service",
+                &format!("Failed to record metrics for {}: {}", self.operation, e),
+                &self.request
+            );
+        }
+        crate::log_debug!(
+            "memory_service",
+            &format!("{} finished in {:?}", self.operation, latency),
+            &self.request
+        );
+    }
+}
 
-        Ok(Self {
-            memory_store,
-            relevance_scorer,
-            context_optimizer,
-            memory_bank_config,
-        })
+impl SmartMemoryService {
+    /// Start timing and tracing an RPC call, recorded in the metrics store and Prometheus when dropped
+    ///
+    /// The span of the call continues the trace in the request's `traceparent` header.
+    fn track_call<T>(&self, operation: &'static str, request: &Request<T>) -> TrackedCall {
+        let parent = telemetry::extract_context(request.metadata());
+        let span = self.tracer.start_with_context(operation, &parent);
+        let request = RequestContext::new(operation, request.remote_addr());
+        crate::log_debug!(
+            "memory_service",
+            &format!("{} started", operation),
+            &request
+        );
+        TrackedCall {
+            metrics: self.metrics.clone(),
+            prometheus: self.prometheus.clone(),
+            tracer: self.tracer.clone(),
+            context: parent.with_span(span),
+            operation,
+            request,
+            mode: String::new(),
+            token_count: 0,
+            started: Instant::now(),
+        }
     }
 
-    pub fn new_with_sqlite(db_path: &Path) -> Result<Self> {
-        // Create the tokenizer
-        let tokenizer = Tokenizer::new(TokenizerType::Simple)?;
+    /// Read the current memory bank config
+    fn config(&self) -> RwLockReadGuard<'_, MemoryBankConfig> {
+        self.memory_bank_config.read().unwrap()
+    }
 
-        // Create the memory store with SQLite storage
-        let memory_store = MemoryStore::new_sqlite(db_path, tokenizer.clone())
-            .context("Failed to create SQLite memory store")?;
+    /// Reject writes when the server is configured as read-only
+    fn ensure_writable(&self) -> Result<(), Status> {
+        if self.config().read_only {
+            return Err(Status::failed_precondition("Server is read-only"));
+        }
+        Ok(())
+    }
 
-        // Create the relevance scorer
-        let relevance_scorer = Arc::new(TfIdfScorer::new());
+    /// Reject a request that breaks the constraints of its type under the current config
+    fn validate_request<T>(&self, request: &Request<T>) -> Result<(), Status>
+    where
+        for<'a> RequestValidator<'a>: Validator<T>,
+    {
+        let config = self.config();
+        RequestValidator::new(&config)
+            .validate(request.get_ref())
+            .map_err(Status::from)
+    }
 
-        // Create the context optimizer
-        let context_optimizer = Arc::new(TokenBudgetOptimizer::new());
+    /// Move the memories and config of one category into another, returning the memories moved
+    fn move_category(&self, source: &str, target: &str) -> Result<u64, Status> {
+        self.ensure_writable()?;
+        if source.is_empty() || target.is_empty() {
+            return Err(Status::invalid_argument(
+                "Source and target categories are required",
+            ));
+        }
 
-        // Create the memory bank config
-        let memory_bank_config = MemoryBankConfig::default();
+        let updated = self
+            .memory_store
+            .merge_categories(source, target)
+            .map_err(|e| Status::internal(format!("Failed to merge categories: {}", e)))?;
+        self.memory_bank_config
+            .write()
+            .unwrap()
+            .merge_categories(source, target);
 
-        Ok(Self {
-            memory_store: Arc::new(memory_store),
-            relevance_scorer,
-            context_optimizer,
-            memory_bank_config,
-        })
+        Ok(updated)
     }
 
-    /// Create a new SmartMemoryService with SQLite storage and a custom memory bank config
-    pub fn new_with_config(db_path: &Path, config_path: &Path) -> Result<Self> {
-        // Create the tokenizer
-        let tokenizer = Tokenizer::new(TokenizerType::Simple)?;
+    /// Store the most relevant context of `mode` as a snapshot memory, returning its tokens
+    fn snapshot_mode(&self, mode: &str) -> Result<usize, Status> {
+        let memories: Vec<Memory> = self
+            .memory_store
+            .get_memories_page(0, usize::MAX)
+            .map_err(|e| Status::internal(format!("Failed to load memories: {}", e)))?
+            .into_iter()
+            .filter(|memory| memory.mode.as_deref() == Some(mode) && !is_internal(memory))
+            .collect();
 
-        // Create the memory store with SQLite storage
-        let memory_store = MemoryStore::new_sqlite(db_path, tokenizer.clone())
-            .context("Failed to create SQLite memory store")?;
+        let scored_memories = self
+            .relevance_scorer
+            .score_memories(&memories, mode, None)
+            .map_err(|e| Status::internal(format!("Failed to score memories: {}", e)))?;
+        let snapshot = self
+            .context_optimizer
+            .optimize(
+                &scored_memories,
+                TokenCount::from(SNAPSHOT_MAX_TOKENS),
+                crate::storage::RelevanceScore::new(0.0),
+            )
+            .map_err(|e| Status::internal(format!("Failed to optimize context: {}", e)))?;
+        if snapshot.is_empty() {
+            return Ok(0);
+        }
 
-        // Create the relevance scorer
-        let relevance_scorer = Arc::new(TfIdfScorer::new());
+        let content = snapshot
+            .iter()
+            .map(|scored| scored.memory.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let mut metadata = HashMap::new();
+        metadata.insert("snapshot_mode".to_string(), mode.to_string());
 
-        // Create the context optimizer
-        let context_optimizer = Arc::new(TokenBudgetOptimizer::new());
+        let memory = self
+            .memory_store
+            .store(
+                content,
+                "text/plain".to_string(),
+                Some(MODE_SNAPSHOT_CATEGORY.to_string()),
+                Some(mode.to_string()),
+                metadata,
+            )
+            .map_err(|e| Status::internal(format!("Failed to store mode snapshot: {}", e)))?;
+        Ok(memory.token_count.as_usize())
+    }
 
-        // Load the memory bank config from file
-        let memory_bank_config = match MemoryBankConfig::from_file(config_path) {
-            Ok(config) => {
-                println!("Loaded memory bank config from {}", config_path.display());
-                config
+    /// Archive the memories of `mode` that do not fit the total token budget, returning the rest
+    ///
+    /// Within budget nothing changes. Otherwise the mode's memories are re-scored,
+    /// newest first on ties, and `CategoryAwareOptimizer` picks the ones to keep;
+    /// the others move to the archived category. Pinned memories are never archived.
+    fn enforce_mode_budget(&self, mode: &str) -> Result<Vec<Memory>, Status> {
+        let mut memories = Vec::new();
+        for page in 0.. {
+            let batch = self
+                .memory_store
+                .get_by_mode(mode, page, CONTEXT_PAGE_SIZE)
+                .map_err(|e| Status::internal(format!("Failed to load memories: {}", e)))?;
+            let last = batch.len() < CONTEXT_PAGE_SIZE;
+            memories.extend(batch);
+            if last {
+                break;
             }
-            Err(e) => {
-                println!("Failed to load memory bank config: {}", e);
-                println!("Using default memory bank config");
-                let default_config = MemoryBankConfig::default();
+        }
+        memories.retain(|memory| {
+            !is_internal(memory) && memory.category.as_deref() != Some(ARCHIVED_CATEGORY)
+        });
 
-                // Try to save the default config to the file
-                if let Err(save_err) = default_config.to_file(config_path) {
-                    println!("Failed to save default config: {}", save_err);
-                } else {
-                    println!("Saved default config to {}", config_path.display());
+        let config = self.config().clone();
+        let budget = config.token_budget.total;
+        let total: usize = memories.iter().map(|m| m.token_count.as_usize()).sum();
+        if total <= budget {
+            return Ok(memories);
+        }
+
+        // Pinned memories stay, so only what they leave of the budget is shared out
+        memories.reverse();
+        let (mut kept, candidates): (Vec<Memory>, Vec<Memory>) =
+            memories.into_iter().partition(|memory| memory.pinned);
+        let pinned_tokens: usize = kept.iter().map(|m| m.token_count.as_usize()).sum();
+        let scored_memories = self
+            .relevance_scorer
+            .score_memories(&candidates, mode, None)
+            .map_err(|e| Status::internal(format!("Failed to score memories: {}", e)))?;
+        let optimized = CategoryAwareOptimizer::new(config)
+            .optimize(
+                &scored_memories,
+                TokenCount::from(budget.saturating_sub(pinned_tokens)),
+                crate::storage::RelevanceScore::new(0.0),
+            )
+            .map_err(|e| Status::internal(format!("Failed to optimize context: {}", e)))?;
+
+        let selected: HashSet<&MemoryId> = optimized.iter().map(|s| &s.memory.id).collect();
+        for memory in candidates {
+            if selected.contains(&memory.id) {
+                kept.push(memory);
+                continue;
+            }
+            self.memory_store
+                .set_category(&memory.id, ARCHIVED_CATEGORY)
+                .map_err(|e| Status::internal(format!("Failed to archive memory: {}", e)))?;
+        }
+
+        Ok(kept)
+    }
+
+    /// List `mode` followed by the other modes of memories linked from `memories`, sorted
+    fn linked_modes(&self, mode: &str, memories: &[Memory]) -> Result<Vec<String>, Status> {
+        let mut modes = BTreeSet::new();
+        for memory in memories {
+            let linked = self
+                .memory_store
+                .get_linked(&memory.id, None)
+                .map_err(|e| Status::internal(format!("Failed to load linked memories: {}", e)))?;
+            modes.extend(linked.into_iter().filter_map(|linked| linked.mode));
+        }
+        modes.remove(mode);
+
+        Ok(std::iter::once(mode.to_string()).chain(modes).collect())
+    }
+
+    /// Pick the memories for a context request, in the order they should be returned
+    ///
+    /// Returns the selected memories, how many were left out because the client
+    /// excluded them, and when `explain` is set why each scored memory was or
+    /// was not selected. Once `cancelled` fires the selection stops before it
+    /// logs access or charges the session's window.
+    fn select_context(
+        &self,
+        req: &ContextRequest,
+        call: &TrackedCall,
+        cancelled: &CancellationToken,
+    ) -> Result<(Vec<ScoredMemory>, usize, Vec<ContextExplanation>), Status> {
+        // Load the requested page, or every page when no page size is given
+        let load_page = |page: usize, page_size: usize| {
+            self.memory_store
+                .get_memories_page(page, page_size)
+                .map_err(|e| Status::internal(format!("Failed to load memories: {}", e)))
+        };
+        let memories = if req.page_size > 0 {
+            load_page(req.page as usize, req.page_size as usize)?
+        } else {
+            let mut memories = Vec::new();
+            for page in 0.. {
+                let batch = load_page(page, CONTEXT_PAGE_SIZE)?;
+                let last = batch.len() < CONTEXT_PAGE_SIZE;
+                memories.extend(batch);
+                if last {
+                    break;
                 }
+            }
+            memories
+        };
 
-                default_config
+        // Only snapshots preserved for the requested mode are context; other bookkeeping is not
+        let is_own_snapshot = |memory: &Memory| {
+            memory.metadata.get("snapshot_mode").map(String::as_str) == Some(req.mode.as_str())
+        };
+        let memories: Vec<Memory> = memories
+            .into_iter()
+            .filter(|memory| !is_internal(memory) || is_own_snapshot(memory))
+            .collect();
+
+        // Under strict isolation other modes' memories are dropped before scoring
+        let strict = match req.isolation_mode() {
+            IsolationModeRequest::Strict => true,
+            IsolationModeRequest::Shared => false,
+            IsolationModeRequest::Configured => {
+                self.config().default_isolation == IsolationMode::Strict
             }
         };
+        let cross_mode_tags: HashSet<&str> = req
+            .cross_mode_tags
+            .iter()
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        let is_visible = |memory: &Memory| {
+            !strict
+                || memory.mode.as_deref().is_none_or(|mode| mode == req.mode)
+                || memory
+                    .tags
+                    .iter()
+                    .any(|tag| cross_mode_tags.contains(tag.as_str()))
+                || is_own_snapshot(memory)
+        };
+        let memories: Vec<Memory> = memories.into_iter().filter(is_visible).collect();
+
+        // Score memories for relevance
+        let scoring = call.child_span("relevance.score");
+        let mut scored_memories = self
+            .relevance_scorer
+            .score_memories(&memories, &req.mode, non_empty(&req.query))
+            .map_err(|e| Status::internal(format!("Failed to score memories: {}", e)))?;
+        drop(scoring);
+        if cancelled.is_cancelled() {
+            return Err(Status::cancelled("Context selection was cancelled"));
+        }
+
+        // Drop memories the client has already seen before optimizing
+        let excluded: HashSet<&str> = req.exclude_memory_ids.iter().map(String::as_str).collect();
+        let scored_count = scored_memories.len();
+        scored_memories.retain(|scored| !excluded.contains(scored.memory.id.as_str()));
+        let excluded_count = scored_count - scored_memories.len();
+
+        // Preserved snapshots go first so they survive the token budget
+        scored_memories.sort_by_key(|scored| !is_own_snapshot(&scored.memory));
+
+        // Optimize context based on token budget and relevance threshold, less
+        // whatever the session's earlier calls already put in its window
+        let budget = if req.session_id.is_empty() {
+            req.max_tokens
+        } else {
+            self.context_windows.budget(&req.session_id, req.max_tokens)
+        };
+        let max_tokens = TokenCount::from(budget as usize);
+        let relevance_threshold =
+            crate::storage::RelevanceScore::new(req.relevance_threshold.into());
+
+        // The optimizer always keeps one memory, even over budget, so a full window is special
+        let mut optimized_memories = if budget == 0 {
+            Vec::new()
+        } else {
+            self.context_optimizer
+                .optimize(&scored_memories, max_tokens, relevance_threshold)
+                .map_err(|e| Status::internal(format!("Failed to optimize context: {}", e)))?
+        };
+        let explanations = if !req.explain {
+            Vec::new()
+        } else if budget == 0 {
+            // Nothing fits a full window; below-threshold memories are still told apart
+            scored_memories
+                .iter()
+                .map(|scored| {
+                    let reason = if scored.score.as_f64() < relevance_threshold.as_f64() {
+                        ExclusionReason::BelowThreshold(scored.score.as_f64())
+                    } else {
+                        ExclusionReason::ExceedsTokenBudget {
+                            available: 0,
+                            needed: scored.memory.token_count.as_usize(),
+                        }
+                    };
+                    context_explanation(&scored.memory.id, false, &reason)
+                })
+                .collect()
+        } else {
+            self.context_optimizer
+                .explain(&scored_memories, max_tokens, relevance_threshold)
+                .map_err(|e| Status::internal(format!("Failed to explain context: {}", e)))?
+                .iter()
+                .map(|explanation| {
+                    context_explanation(
+                        &explanation.memory_id,
+                        explanation.included,
+                        &explanation.reason,
+                    )
+                })
+                .collect()
+        };
+        if req.include_linked {
+            self.add_linked_memories(
+                &mut optimized_memories,
+                &excluded,
+                max_tokens.as_usize(),
+                &is_visible,
+            )?;
+        }
+
+        // A call answered at its deadline must not count against the session
+        if cancelled.is_cancelled() {
+            return Err(Status::cancelled("Context selection was cancelled"));
+        }
+        self.log_access(&req.mode, &optimized_memories, call.request());
+
+        if !req.session_id.is_empty() {
+            let tokens: usize = optimized_memories
+                .iter()
+                .map(|scored| scored.memory.token_count.as_usize())
+                .sum();
+            self.context_windows
+                .record(&req.session_id, u32::try_from(tokens).unwrap_or(u32::MAX));
+        }
+
+        Ok((optimized_memories, excluded_count, explanations))
+    }
+
+    /// Append the memories linked from `selected`, one level deep, while they fit in `max_tokens`
+    ///
+    /// A linked memory gets the score of the first selected memory linking to it,
+    /// and is skipped unless `is_visible` accepts it.
+    fn add_linked_memories(
+        &self,
+        selected: &mut Vec<ScoredMemory>,
+        excluded: &HashSet<&str>,
+        max_tokens: usize,
+        is_visible: &dyn Fn(&Memory) -> bool,
+    ) -> Result<(), Status> {
+        let mut seen: HashSet<MemoryId> = selected
+            .iter()
+            .map(|scored| scored.memory.id.clone())
+            .collect();
+        let mut total_tokens: usize = selected
+            .iter()
+            .map(|scored| scored.memory.token_count.as_usize())
+            .sum();
+
+        let mut linked = Vec::new();
+        for scored in selected.iter() {
+            let memories = self
+                .memory_store
+                .get_linked(&scored.memory.id, None)
+                .map_err(|e| Status::internal(format!("Failed to load linked memories: {}", e)))?;
+            for memory in memories {
+                let tokens = memory.token_count.as_usize();
+                if is_internal(&memory)
+                    || !is_visible(&memory)
+                    || excluded.contains(memory.id.as_str())
+                    || total_tokens + tokens > max_tokens
+                    || !seen.insert(memory.id.clone())
+                {
+                    continue;
+                }
+                total_tokens += tokens;
+                linked.push(ScoredMemory {
+                    memory,
+                    score: scored.score,
+                });
+            }
+        }
+
+        selected.extend(linked);
+        Ok(())
+    }
+
+    /// Load the memories in `categories`, or every memory when none are given
+    fn load_memory_bank(&self, categories: &[String]) -> Result<Vec<Memory>, Status> {
+        if categories.is_empty() {
+            return self
+                .memory_store
+                .get_memories_page(0, usize::MAX)
+                .map_err(|e| Status::internal(format!("Failed to get memories: {}", e)));
+        }
+
+        let mut seen = HashSet::new();
+        let mut memories = Vec::new();
+        for category in categories {
+            if !seen.insert(category.as_str()) {
+                continue;
+            }
+            memories.extend(
+                self.memory_store
+                    .get_by_category(category, 0, usize::MAX)
+                    .map_err(|e| {
+                        Status::internal(format!(
+                            "Failed to get memories in category {}: {}",
+                            category, e
+                        ))
+                    })?,
+            );
+        }
+        Ok(memories)
+    }
+
+    /// Remember which memories a context retrieval for `mode` returned
+    fn log_access(&self, mode: &str, retrieved: &[ScoredMemory], request: &RequestContext) {
+        if self.config().read_only || mode.is_empty() {
+            return;
+        }
+
+        let ids: Vec<&str> = retrieved
+            .iter()
+            .map(|scored| scored.memory.id.as_str())
+            .collect();
+        let mut metadata = HashMap::new();
+        metadata.insert("memory_ids".to_string(), ids.join(","));
+
+        if let Err(e) = self.memory_store.store_with_ttl(
+            format!("Retrieved {} memories for {} context", ids.len(), mode),
+            "text/plain".to_string(),
+            Some(ACCESS_LOG_CATEGORY.to_string()),
+            Some(mode.to_string()),
+            metadata,
+            Some(ACCESS_LOG_TTL_SECONDS),
+        ) {
+            crate::log_warning!(
+                "memory_service",
+                &format!("Failed to record context access for {} mode: {}", mode, e),
+                request
+            );
+        }
+    }
+
+    /// Announce a change to a memory, marking relevance statistics built before it stale
+    fn memory_changed(&self, event_type: EventType, memory: &Memory) {
+        self.relevance_scorer.invalidate_cache();
+        self.events.publish(MemoryEvent::new(event_type, memory));
+    }
+
+    /// Merge each group of duplicates into its primary, announcing the changes
+    ///
+    /// Returns the merged primaries. Both `OptimizeMemory` and `FindDuplicates`
+    /// merge through here.
+    fn merge_duplicate_groups(&self, groups: &[DuplicateGroup]) -> Result<Vec<Memory>, Status> {
+        let mut merged = Vec::with_capacity(groups.len());
+        for group in groups {
+            let (primary, duplicates) = self
+                .memory_store
+                .merge_duplicates(group, DEFAULT_MERGE_SEPARATOR)
+                .map_err(|e| Status::internal(format!("Failed to merge duplicates: {}", e)))?;
+            for memory in &duplicates {
+                self.memory_changed(EventType::Deleted, memory);
+            }
+            self.memory_changed(EventType::Updated, &primary);
+            merged.push(primary);
+        }
+        Ok(merged)
+    }
+
+    /// Run a call's work on the blocking pool
+    ///
+    /// Store calls wait on SQLite, which would hold an async worker and keep the
+    /// call's deadline from ending it. The work runs to completion even if the
+    /// call is dropped at its deadline, so changes it announces after committing
+    /// are never lost.
+    async fn blocking<T, F>(&self, work: F) -> Result<T, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&SmartMemoryService) -> Result<T, Status> + Send + 'static,
+    {
+        let service = self.clone();
+        self.repository
+            .run(move |_| work(&service))
+            .await
+            .map_err(|e| Status::internal(format!("{:#}", e)))?
+    }
+
+    /// Build the `GetConfig` response from the current configuration
+    fn build_config_response(&self) -> Result<GetConfigResponse, Status> {
+        let config = self.config();
+        let config_json = config
+            .to_public_json()
+            .map_err(|e| Status::internal(format!("Failed to serialize config: {}", e)))?;
+
+        let mut categories: Vec<CategorySummary> = config
+            .categories
+            .iter()
+            .map(|(name, category)| CategorySummary {
+                name: name.clone(),
+                max_tokens: config.get_max_tokens(name).as_usize() as u32,
+                priority: config.get_priority(name).as_str().to_string(),
+                parent: category.extends.clone().unwrap_or_default(),
+            })
+            .collect();
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(GetConfigResponse {
+            config_json,
+            schema_version: CONFIG_SCHEMA_VERSION,
+            read_only: config.read_only,
+            custom_modes: config.custom_modes.clone(),
+            categories,
+        })
+    }
+
+    /// Report calls to these Prometheus metrics, e.g. the ones the metrics server exposes
+    pub fn with_prometheus(mut self, prometheus: Arc<PrometheusMetrics>) -> Self {
+        self.prometheus = prometheus;
+        self
+    }
+
+    /// Trace calls with `tracer` instead of discarding their spans
+    pub fn with_tracer(mut self, tracer: BoxedTracer) -> Self {
+        self.tracer = Arc::new(tracer);
+        self
+    }
+
+    /// Get the store the service keeps its memories in
+    pub fn memory_store(&self) -> Arc<MemoryStore> {
+        self.memory_store.clone()
+    }
+
+    /// Get the memory bank config the service reads, to update it in place
+    pub fn shared_config(&self) -> Arc<RwLock<MemoryBankConfig>> {
+        self.memory_bank_config.clone()
+    }
+
+    /// Allow `MemoryBankSync` to connect to these peers instead of those in `SYNC_PEERS`
+    pub fn with_sync_peers(mut self, sync_peers: SyncPeers) -> Self {
+        self.sync_peers = Arc::new(sync_peers);
+        self
+    }
+
+    /// Load the most retrieved memories into the store's cache and the relevance scorer's,
+    /// returning how many were loaded
+    pub fn warm_caches(&self) -> Result<usize> {
+        let memories = self
+            .memory_store
+            .preload_hot_memories(HOT_MEMORIES_PRELOAD)?;
+        self.relevance_scorer.warm_up(&memories)?;
+        Ok(memories.len())
+    }
+
+    /// Create a new SmartMemoryService with in-memory storage and the default config
+    pub fn new() -> Result<Self> {
+        let tokenizer = Tokenizer::new(TokenizerType::Simple)
+            .map_err(|e| anyhow::anyhow!("Failed to create tokenizer: {}", e))?;
+        let memory_store = MemoryStore::new_in_memory(tokenizer);
+
+        Self::from_parts(
+            memory_store,
+            MemoryBankConfig::default(),
+            MetricsStore::new_in_memory()?,
+        )
+    }
+
+    /// Create a new SmartMemoryService with SQLite storage behind a pool of `pool_size` connections
+    ///
+    /// The memory bank config is loaded from `config_path`, falling back to the
+    /// defaults if the file is missing or invalid. The scorer and context
+    /// optimizer are selected by the `SCORER_TYPE` and `CONTEXT_SUMMARIZE` env vars.
+    pub fn new_with_pool(db_path: &Path, config_path: &Path, pool_size: u32) -> Result<Self> {
+        let tokenizer = Tokenizer::new(TokenizerType::Simple)?;
+        let memory_store = MemoryStore::new_sqlite_with_pool_size(db_path, tokenizer, pool_size)
+            .context("Failed to create SQLite memory store")?;
+
+        Self::from_parts(
+            memory_store,
+            crate::config_watcher::load_or_default(config_path),
+            MetricsStore::open(db_path)?,
+        )
+    }
+
+    /// Create a new SmartMemoryService with SQLite storage
+    ///
+    /// The config is read from `config.json` next to the database, if present.
+    #[deprecated(note = "use `SmartMemoryService::new_with_pool`")]
+    pub fn new_with_sqlite(db_path: &Path) -> Result<Self> {
+        Self::new_with_pool(db_path, &db_path.with_file_name("config.json"), 1)
+    }
+
+    /// Create a new SmartMemoryService with SQLite storage and a custom memory bank config
+    #[deprecated(note = "use `SmartMemoryService::new_with_pool`")]
+    pub fn new_with_config(db_path: &Path, config_path: &Path) -> Result<Self> {
+        Self::new_with_pool(db_path, config_path, 1)
+    }
+
+    /// Assemble a service around a store, config and metrics store
+    fn from_parts(
+        memory_store: MemoryStore,
+        memory_bank_config: MemoryBankConfig,
+        metrics: MetricsStore,
+    ) -> Result<Self> {
+        check_config(&memory_bank_config)?;
+        let memory_store = Arc::new(encrypt_from_env(memory_store)?);
+        memory_store.enable_audit_log(&memory_bank_config.audit_log)?;
+        let relevance_scorer = create_relevance_scorer(&memory_bank_config);
+        register_post_store_processors(&memory_store, &memory_bank_config);
 
         Ok(Self {
-            memory_store: Arc::new(memory_store),
+            repository: AsyncSqliteMemoryRepository::new(memory_store.clone()),
+            memory_store,
             relevance_scorer,
-            context_optimizer,
-            memory_bank_config,
+            context_optimizer: create_context_optimizer(),
+            memory_bank_config: Arc::new(RwLock::new(memory_bank_config)),
+            current_mode: Arc::new(Mutex::new(DEFAULT_MODE.to_string())),
+            metrics: Arc::new(metrics),
+            prometheus: Arc::new(PrometheusMetrics::new()),
+            tracer: Arc::new(telemetry::noop_tracer()),
+            config_cache: Arc::new(Mutex::new(None)),
+            events: Arc::new(EventBroadcaster::from_env()),
+            context_windows: ContextWindows::default(),
+            pending_restore: Arc::new(Mutex::new(None)),
+            sync_peers: Arc::new(SyncPeers::from_env()),
         })
     }
 }
 
+/// Replace the memories in `memory_store` with the newest backup, which must be `expected_backup`
+///
+/// The backup is restored to a staging file next to `db_path` and copied over the
+/// live database from there, after the live contents are backed up themselves.
+fn restore_latest_backup(
+    memory_store: &MemoryStore,
+    db_path: &Path,
+    expected_backup: &str,
+) -> Result<(String, BackupMetadata, u64), Status> {
+    let internal = |e: std::io::Error| Status::internal(format!("Failed to restore backup: {}", e));
+    let backup_manager = BackupManager::new(&default_backup_dir()).map_err(internal)?;
+
+    // A leftover staging file would otherwise get a pre-restore backup of its own
+    let staging_path = db_path.with_extension("restore.tmp");
+    if staging_path.exists() {
+        std::fs::remove_file(&staging_path).map_err(internal)?;
+    }
+
+    let result = (|| {
+        let (backup_path, metadata) = backup_manager
+            .restore_latest(&staging_path)
+            .map_err(internal)?;
+        let restored_from = file_name(&backup_path);
+        if restored_from != expected_backup {
+            return Err(Status::aborted(format!(
+                "Backup {} was made after PrepareRestore named {}; prepare the restore again",
+                restored_from, expected_backup
+            )));
+        }
+        let bytes_restored = std::fs::metadata(&staging_path).map_err(internal)?.len();
+
+        backup_manager
+            .create_backup(
+                db_path,
+                &format!("Pre-restore backup of {}", db_path.display()),
+            )
+            .map_err(internal)?;
+        memory_store
+            .restore_from(&staging_path)
+            .map_err(|e| Status::internal(format!("Failed to restore backup: {:#}", e)))?;
+
+        Ok((restored_from, metadata, bytes_restored))
+    })();
+
+    let _ = std::fs::remove_file(&staging_path);
+    result
+}
+
+/// File name of a path, or the empty string if it has none
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Get the model named in a request's `x-model` metadata and its pricing
+fn model_pricing<T>(request: &Request<T>) -> Result<(String, TokenPricing), Status> {
+    let model = request
+        .metadata()
+        .get(MODEL_METADATA_KEY)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if model.is_empty() {
+        return Err(Status::invalid_argument(format!(
+            "Cost estimates need the model in the {} metadata",
+            MODEL_METADATA_KEY
+        )));
+    }
+    let pricing = TokenPricing::for_model(&model)
+        .ok_or_else(|| Status::invalid_argument(format!("No pricing known for model {}", model)))?;
+    Ok((model, pricing))
+}
+
+/// Log the config's warnings and fail on its errors
+pub fn check_config(config: &MemoryBankConfig) -> Result<()> {
+    match config.validate() {
+        Ok(warnings) => {
+            for warning in warnings {
+                crate::log_warning!("config", &format!("Config warning: {}", warning));
+            }
+            Ok(())
+        }
+        Err(errors) => {
+            for error in &errors {
+                crate::log_error!("config", &format!("Config error: {}", error));
+            }
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            anyhow::bail!("Invalid memory bank config: {}", errors.join("; "))
+        }
+    }
+}
+
 #[tonic::async_trait]
 impl SmartMemoryMcp for SmartMemoryService {
+    type StreamLogsStream = Pin<Box<dyn Stream<Item = Result<LogRecord, Status>> + Send>>;
+    type GetContextStreamStream = Pin<Box<dyn Stream<Item = Result<ContextChunk, Status>> + Send>>;
+    type ExportMemoriesStream = Pin<Box<dyn Stream<Item = Result<ExportChunk, Status>> + Send>>;
+
     async fn store_memory(
         &self,
         request: Request<StoreRequest>,
     ) -> Result<Response<StoreResponse>, Status> {
+        let mut call = self.track_call("store_memory", &request);
+        self.ensure_writable()?;
+        self.validate_request(&request)?;
         let req = request.into_inner();
 
-        // Store the memory
+        // Store the memory, deduplicating retries that carry an idempotency key
+        let options = StoreOptions {
+            ttl_seconds: Some(req.ttl_seconds).filter(|&ttl| ttl > 0),
+            tags: req.tags,
+            preferred_id: Some(req.preferred_id).filter(|id| !id.is_empty()),
+        };
+        let compress = req.compress;
+        let repository_span = call.child_span("repository.store");
+        // Storing runs several repository writes, so keep it off the async workers
         let memory = self
-            .memory_store
-            .store(
-                req.content,
-                req.content_type,
-                None, // No category for regular memories
-                None, // No mode for regular memories
-                req.metadata,
-            )
-            .map_err(|e| Status::internal(format!("Failed to store memory: {}", e)))?;
+            .blocking(move |service| {
+                let memory_store = &service.memory_store;
+                let store = || {
+                    memory_store.store_with_options(
+                        req.content,
+                        req.content_type,
+                        None, // No category for regular memories
+                        None, // No mode for regular memories
+                        req.metadata,
+                        options,
+                    )
+                };
+                let memory = if req.idempotency_key.is_empty() {
+                    store()
+                } else {
+                    memory_store.store_idempotent(&req.idempotency_key, store)
+                }
+                .map_err(|e| match e.downcast_ref::<DuplicateMemoryId>() {
+                    Some(duplicate) => Status::already_exists(duplicate.to_string()),
+                    None => Status::internal(format!("Failed to store memory: {}", e)),
+                })?;
+                service.memory_changed(EventType::Stored, &memory);
+                Ok(memory)
+            })
+            .await?;
+        drop(repository_span);
+        call.set_tokens(memory.token_count.as_usize());
 
         // Calculate compression ratio (mock for now)
-        let compression_ratio = if req.compress { 0.8 } else { 1.0 };
+        let compression_ratio = if compress { 0.8 } else { 1.0 };
 
         // Create the response
         let response = StoreResponse {
@@ -212,20 +1152,87 @@ impl SmartMemoryMcp for SmartMemoryService {
         Ok(Response::new(response))
     }
 
+    async fn bulk_store(
+        &self,
+        request: Request<BulkStoreRequest>,
+    ) -> Result<Response<BulkStoreResponse>, Status> {
+        let mut call = self.track_call("bulk_store", &request);
+        self.ensure_writable()?;
+        self.validate_request(&request)?;
+        if request.get_ref().items.iter().any(|item| {
+            !item.idempotency_key.is_empty()
+                || item.ttl_seconds > 0
+                || !item.preferred_id.is_empty()
+        }) {
+            return Err(Status::invalid_argument(
+                "Bulk stores do not support idempotency keys, TTLs or preferred IDs",
+            ));
+        }
+        let req = request.into_inner();
+
+        let compression_ratios: Vec<f32> = req
+            .items
+            .iter()
+            .map(|item| if item.compress { 0.8 } else { 1.0 })
+            .collect();
+        let items = req
+            .items
+            .into_iter()
+            .map(|item| {
+                let store_item = (item.content, item.content_type, None, None, item.metadata);
+                (store_item, item.tags)
+            })
+            .collect();
+
+        let memories = self
+            .blocking(move |service| {
+                let memories = service
+                    .memory_store
+                    .bulk_store_with_tags(items)
+                    .map_err(|e| Status::internal(format!("Failed to store memories: {}", e)))?;
+                for memory in &memories {
+                    service.memory_changed(EventType::Stored, memory);
+                }
+                Ok(memories)
+            })
+            .await?;
+        call.set_tokens(memories.iter().map(|m| m.token_count.as_usize()).sum());
+
+        let response = BulkStoreResponse {
+            memories: memories
+                .iter()
+                .zip(compression_ratios)
+                .map(|(memory, compression_ratio)| StoreResponse {
+                    memory_id: memory.id.as_str().to_string(),
+                    token_count: memory.token_count.as_usize() as u32,
+                    compression_ratio,
+                })
+                .collect(),
+        };
+
+        Ok(Response::new(response))
+    }
+
     async fn retrieve_memory(
         &self,
         request: Request<RetrieveRequest>,
     ) -> Result<Response<RetrieveResponse>, Status> {
+        let mut call = self.track_call("retrieve_memory", &request);
         let req = request.into_inner();
         let memory_id = MemoryId::from(req.memory_id);
 
         // Retrieve the memory
-        match self
-            .memory_store
+        let repository_span = call.child_span("repository.retrieve");
+        let memory = self
+            .repository
             .retrieve(&memory_id)
-            .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
-        {
+            .await
+            .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?;
+        drop(repository_span);
+
+        match memory {
             Some(memory) => {
+                call.set_tokens(memory.token_count.as_usize());
                 // Create the response
                 let response = RetrieveResponse {
                     content: memory.content,
@@ -235,6 +1242,7 @@ impl SmartMemoryMcp for SmartMemoryService {
                         HashMap::new()
                     },
                     token_count: memory.token_count.as_usize() as u32,
+                    access_count: memory.access_count,
                 };
 
                 Ok(Response::new(response))
@@ -250,63 +1258,440 @@ impl SmartMemoryMcp for SmartMemoryService {
         &self,
         request: Request<OptimizeRequest>,
     ) -> Result<Response<OptimizeResponse>, Status> {
+        let _call = self.track_call("optimize_memory", &request);
         let req = request.into_inner();
 
-        // For now, just return a mock response
-        // In a real implementation, we would apply optimization strategies
-        let response = OptimizeResponse {
-            tokens_saved: 100,
-            optimization_ratio: match req.strategy() {
-                OptimizationStrategy::Balanced => 0.3,
-                OptimizationStrategy::Aggressive => 0.5,
-                OptimizationStrategy::Conservative => 0.1,
-            },
-            optimized_ids: req.memory_ids,
-        };
+        // Deduplication is the only strategy that changes memories
+        let strategy = req.strategy();
+        if strategy != OptimizationStrategy::Deduplicate {
+            return Err(Status::unimplemented(format!(
+                "The {} strategy is not implemented; use DEDUPLICATE",
+                strategy.as_str_name()
+            )));
+        }
 
-        Ok(Response::new(response))
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+
+            let mut memories = Vec::new();
+            for id in &req.memory_ids {
+                if let Some(memory) = service
+                    .memory_store
+                    .retrieve(&MemoryId::from(id.as_str()))
+                    .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
+                {
+                    if !is_internal(&memory) && !memories.iter().any(|m: &Memory| m.id == memory.id)
+                    {
+                        memories.push(memory);
+                    }
+                }
+            }
+
+            let tokens_before: usize = memories.iter().map(|m| m.token_count.as_usize()).sum();
+            let groups = find_duplicate_groups(&memories, service.config().dedup_threshold);
+            let merged = service.merge_duplicate_groups(&groups)?;
+
+            let merged_ids: HashSet<_> = groups
+                .iter()
+                .flat_map(|group| group.duplicate_ids.iter())
+                .chain(merged.iter().map(|memory| &memory.id))
+                .collect();
+            let tokens_after: usize = memories
+                .iter()
+                .filter(|memory| !merged_ids.contains(&memory.id))
+                .chain(merged.iter())
+                .map(|m| m.token_count.as_usize())
+                .sum();
+            let tokens_saved = tokens_before.saturating_sub(tokens_after);
+
+            Ok(Response::new(OptimizeResponse {
+                tokens_saved: tokens_saved as u32,
+                optimization_ratio: if tokens_before == 0 {
+                    0.0
+                } else {
+                    tokens_saved as f32 / tokens_before as f32
+                },
+                optimized_ids: merged
+                    .iter()
+                    .map(|memory| memory.id.as_str().to_string())
+                    .collect(),
+            }))
+        })
+        .await
     }
 
-    async fn get_context(
+    async fn copy_memory(
         &self,
-        request: Request<ContextRequest>,
-    ) -> Result<Response<ContextResponse>, Status> {
-        let req = request.into_inner();
+        request: Request<CopyMemoryRequest>,
+    ) -> Result<Response<CopyMemoryResponse>, Status> {
+        let _call = self.track_call("copy_memory", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let req = request.into_inner();
+            let source_id = MemoryId::from(req.source_memory_id);
 
-        // Get all memories
-        let memory_ids = self
-            .memory_store
-            .get_all_ids()
-            .map_err(|e| Status::internal(format!("Failed to get memory IDs: {}", e)))?;
+            // Empty targets keep the source's category and mode
+            let target_category = if req.target_category.is_empty() {
+                None
+            } else {
+                Some(req.target_category)
+            };
+            let target_mode = if req.target_mode.is_empty() {
+                None
+            } else {
+                Some(req.target_mode)
+            };
 
-        let mut memories = Vec::new();
-        for id in memory_ids {
-            if let Some(memory) = self
+            match service
+                .memory_store
+                .copy_memory(&source_id, target_category, target_mode, req.copy_metadata)
+                .map_err(|e| Status::internal(format!("Failed to copy memory: {}", e)))?
+            {
+                Some(copy) => Ok(Response::new(CopyMemoryResponse {
+                    new_memory_id: copy.id.as_str().to_string(),
+                    token_count: copy.token_count.as_usize() as u32,
+                })),
+                None => Err(Status::not_found(format!(
+                    "Memory with ID {} not found",
+                    source_id.as_str()
+                ))),
+            }
+        })
+        .await
+    }
+
+    async fn merge_memories(
+        &self,
+        request: Request<MergeMemoriesRequest>,
+    ) -> Result<Response<MergeMemoriesResponse>, Status> {
+        let _call = self.track_call("merge_memories", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let req = request.into_inner();
+            let ids: Vec<MemoryId> = req.memory_ids.into_iter().map(MemoryId::from).collect();
+            if ids.len() < 2 {
+                return Err(Status::invalid_argument(
+                    "At least two memories are needed for a merge",
+                ));
+            }
+            if ids.iter().collect::<HashSet<_>>().len() < ids.len() {
+                return Err(Status::invalid_argument(
+                    "A memory is listed more than once",
+                ));
+            }
+
+            let separator = if req.separator.is_empty() {
+                DEFAULT_MERGE_SEPARATOR.to_string()
+            } else {
+                req.separator
+            };
+            let target_category = non_empty(&req.target_category).map(str::to_string);
+
+            // Kept to tell subscribers which memories the merge removed
+            let mut originals = Vec::with_capacity(ids.len());
+            for id in &ids {
+                if let Some(memory) = service
+                    .memory_store
+                    .retrieve(id)
+                    .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
+                {
+                    originals.push(memory);
+                }
+            }
+
+            let merged = service
+                .memory_store
+                .merge_memories_into(&ids, &separator, target_category)
+                .map_err(|e| match e.downcast_ref::<UnknownMemory>() {
+                    Some(unknown) => Status::not_found(unknown.to_string()),
+                    None => Status::internal(format!("Failed to merge memories: {}", e)),
+                })?;
+            for memory in &originals {
+                service.memory_changed(EventType::Deleted, memory);
+            }
+            service.memory_changed(EventType::Stored, &merged);
+
+            Ok(Response::new(MergeMemoriesResponse {
+                merged_memory_id: merged.id.as_str().to_string(),
+                total_tokens: merged.token_count.as_usize() as u32,
+            }))
+        })
+        .await
+    }
+
+    async fn find_duplicates(
+        &self,
+        request: Request<FindDuplicatesRequest>,
+    ) -> Result<Response<FindDuplicatesResponse>, Status> {
+        let _call = self.track_call("find_duplicates", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+            if !req.dry_run {
+                service.ensure_writable()?;
+            }
+            let threshold = if req.threshold == 0.0 {
+                service.config().dedup_threshold
+            } else if req.threshold > 0.0 && req.threshold <= 1.0 {
+                f64::from(req.threshold)
+            } else {
+                return Err(Status::invalid_argument(
+                    "Threshold must be between 0 and 1",
+                ));
+            };
+
+            let groups = service
+                .memory_store
+                .find_duplicates_where(threshold, |memory| !is_internal(memory))
+                .map_err(|e| Status::internal(format!("Failed to find duplicates: {}", e)))?;
+
+            let merged_memory_ids = if req.dry_run {
+                Vec::new()
+            } else {
+                service
+                    .merge_duplicate_groups(&groups)?
+                    .iter()
+                    .map(|memory| memory.id.as_str().to_string())
+                    .collect()
+            };
+
+            Ok(Response::new(FindDuplicatesResponse {
+                groups: groups.iter().map(duplicate_group_result).collect(),
+                merged_memory_ids,
+            }))
+        })
+        .await
+    }
+
+    async fn delete_memory(
+        &self,
+        request: Request<DeleteMemoryRequest>,
+    ) -> Result<Response<DeleteMemoryResponse>, Status> {
+        let _call = self.track_call("delete_memory", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let req = request.into_inner();
+            let memory_id = MemoryId::from(req.memory_id);
+
+            match service
+                .memory_store
+                .delete(&memory_id)
+                .map_err(|e| Status::internal(format!("Failed to delete memory: {}", e)))?
+            {
+                Some(memory) => {
+                    service.memory_changed(EventType::Deleted, &memory);
+                    Ok(Response::new(DeleteMemoryResponse {
+                        success: true,
+                        freed_tokens: memory.token_count.as_usize() as u32,
+                    }))
+                }
+                None => Err(Status::not_found(format!(
+                    "Memory with ID {} not found",
+                    memory_id.as_str()
+                ))),
+            }
+        })
+        .await
+    }
+
+    async fn update_memory(
+        &self,
+        request: Request<UpdateMemoryRequest>,
+    ) -> Result<Response<UpdateMemoryResponse>, Status> {
+        let _call = self.track_call("update_memory", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let req = request.into_inner();
+            let memory_id = MemoryId::from(req.memory_id);
+            let metadata = if req.replace_metadata {
+                Some(req.metadata)
+            } else {
+                None
+            };
+
+            match service
+                .memory_store
+                .update(&memory_id, req.content, metadata)
+                .map_err(|e| Status::internal(format!("Failed to update memory: {}", e)))?
+            {
+                Some(memory) => {
+                    service.memory_changed(EventType::Updated, &memory);
+                    Ok(Response::new(UpdateMemoryResponse {
+                        memory_id: memory.id.as_str().to_string(),
+                        token_count: memory.token_count.as_usize() as u32,
+                    }))
+                }
+                None => Err(Status::not_found(format!(
+                    "Memory with ID {} not found",
+                    memory_id.as_str()
+                ))),
+            }
+        })
+        .await
+    }
+
+    async fn get_history(
+        &self,
+        request: Request<GetHistoryRequest>,
+    ) -> Result<Response<GetHistoryResponse>, Status> {
+        let _call = self.track_call("get_history", &request);
+        self.blocking(move |service| {
+            let memory_id = MemoryId::from(request.into_inner().memory_id);
+
+            if service
                 .memory_store
-                .retrieve(&id)
+                .retrieve(&memory_id)
                 .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
+                .is_none()
             {
-                memories.push(memory);
+                return Err(Status::not_found(format!(
+                    "Memory with ID {} not found",
+                    memory_id.as_str()
+                )));
             }
-        }
 
-        // Score memories for relevance
-        let scored_memories = self
-            .relevance_scorer
-            .score_memories(
-                &memories, &req.mode, None, // No query for now
-            )
-            .map_err(|e| Status::internal(format!("Failed to score memories: {}", e)))?;
+            let versions = service
+                .memory_store
+                .get_history(&memory_id)
+                .map_err(|e| Status::internal(format!("Failed to load memory history: {}", e)))?;
 
-        // Optimize context based on token budget and relevance threshold
-        let max_tokens = TokenCount::from(req.max_tokens as usize);
-        let relevance_threshold =
-            crate::storage::RelevanceScore::new(req.relevance_threshold.into());
+            Ok(Response::new(GetHistoryResponse {
+                versions: versions.into_iter().map(memory_to_result).collect(),
+            }))
+        })
+        .await
+    }
 
-        let optimized_memories = self
-            .context_optimizer
-            .optimize(&scored_memories, max_tokens, relevance_threshold)
-            .map_err(|e| Status::internal(format!("Failed to optimize context: {}", e)))?;
+    async fn rollback_memory(
+        &self,
+        request: Request<RollbackRequest>,
+    ) -> Result<Response<RollbackResponse>, Status> {
+        let _call = self.track_call("rollback_memory", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let req = request.into_inner();
+            let memory_id = MemoryId::from(req.memory_id);
+
+            let memory = service
+                .memory_store
+                .rollback_to_version(&memory_id, req.version)
+                .map_err(|e| match e.downcast_ref::<UnknownVersion>() {
+                    Some(unknown) => Status::not_found(unknown.to_string()),
+                    None => Status::internal(format!("Failed to roll back memory: {}", e)),
+                })?;
+            service.memory_changed(EventType::Updated, &memory);
+
+            Ok(Response::new(RollbackResponse {
+                memory_id: memory.id.as_str().to_string(),
+                token_count: memory.token_count.as_usize() as u32,
+            }))
+        })
+        .await
+    }
+
+    async fn pin_memory(
+        &self,
+        request: Request<PinMemoryRequest>,
+    ) -> Result<Response<PinMemoryResponse>, Status> {
+        let _call = self.track_call("pin_memory", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let memory_id = MemoryId::from(request.into_inner().memory_id);
+
+            let memory = service
+                .memory_store
+                .retrieve(&memory_id)
+                .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
+                .ok_or_else(|| {
+                    Status::not_found(format!("Memory with ID {} not found", memory_id.as_str()))
+                })?;
+            if !memory.pinned {
+                let stats = service
+                    .memory_store
+                    .get_stats()
+                    .map_err(|e| Status::internal(format!("Failed to get memory stats: {}", e)))?;
+                let max_pinned = service.config().max_pinned;
+                if stats.pinned_count >= max_pinned {
+                    return Err(Status::resource_exhausted(format!(
+                        "At most {} memories may be pinned",
+                        max_pinned
+                    )));
+                }
+            }
+
+            match service
+                .memory_store
+                .pin(&memory_id)
+                .map_err(|e| Status::internal(format!("Failed to pin memory: {}", e)))?
+            {
+                Some(memory) => Ok(Response::new(PinMemoryResponse {
+                    memory_id: memory.id.as_str().to_string(),
+                    pinned: memory.pinned,
+                })),
+                None => Err(Status::not_found(format!(
+                    "Memory with ID {} not found",
+                    memory_id.as_str()
+                ))),
+            }
+        })
+        .await
+    }
+
+    async fn unpin_memory(
+        &self,
+        request: Request<UnpinMemoryRequest>,
+    ) -> Result<Response<PinMemoryResponse>, Status> {
+        let _call = self.track_call("unpin_memory", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let memory_id = MemoryId::from(request.into_inner().memory_id);
+
+            match service
+                .memory_store
+                .unpin(&memory_id)
+                .map_err(|e| Status::internal(format!("Failed to unpin memory: {}", e)))?
+            {
+                Some(memory) => Ok(Response::new(PinMemoryResponse {
+                    memory_id: memory.id.as_str().to_string(),
+                    pinned: memory.pinned,
+                })),
+                None => Err(Status::not_found(format!(
+                    "Memory with ID {} not found",
+                    memory_id.as_str()
+                ))),
+            }
+        })
+        .await
+    }
+
+    async fn get_context(
+        &self,
+        request: Request<ContextRequest>,
+    ) -> Result<Response<ContextResponse>, Status> {
+        let mut call = self.track_call("get_context", &request);
+        self.validate_request(&request)?;
+        let pricing = if request.get_ref().include_cost_estimate {
+            Some(model_pricing(&request)?)
+        } else {
+            None
+        };
+        let req = request.into_inner();
+        call.set_mode(&req.mode);
+
+        // Scoring thousands of memories runs off the async workers so the call's deadline can
+        // end it. The blocking task cannot be aborted, so dropping this call cancels it instead
+        let service = self.clone();
+        let cancelled = CancellationToken::new();
+        let cancel_on_drop = cancelled.clone().drop_guard();
+        let (mut call, selected) = self
+            .repository
+            .run(move |_| {
+                let selected = service.select_context(&req, &call, &cancelled);
+                (call, selected)
+            })
+            .await
+            .map_err(|e| Status::internal(format!("Context selection failed: {:#}", e)))?;
+        cancel_on_drop.disarm();
+        let (optimized_memories, excluded_count, explanations) = selected?;
 
         // Build the context from the optimized memories
         let mut context = String::new();
@@ -319,16 +1704,23 @@ impl SmartMemoryMcp for SmartMemoryService {
             context.push_str("\n\n");
 
             // Add the memory as a source
-            sources.push(ContextSource {
-                source_id: scored_memory.memory.id.as_str().to_string(),
-                source_type: scored_memory.memory.content_type.clone(),
-                relevance: scored_memory.score.as_f64() as f32,
-            });
+            sources.push(context_source(scored_memory));
 
             // Add the memory tokens to the total
             total_tokens += scored_memory.memory.token_count.as_usize();
         }
 
+        call.set_tokens(total_tokens);
+        let estimated_cost_usd = pricing
+            .map(|(model, pricing)| {
+                self.memory_store.tokenizer().estimate_cost(
+                    TokenCount::from(total_tokens),
+                    &model,
+                    &pricing,
+                )
+            })
+            .unwrap_or_default();
+
         // Create the response
         let response = ContextResponse {
             context,
@@ -338,114 +1730,386 @@ impl SmartMemoryMcp for SmartMemoryService {
                 .map(|m| m.score.as_f64() as f32)
                 .unwrap_or(0.0),
             sources,
+            excluded_count: excluded_count as u32,
+            estimated_cost_usd,
+            explanations,
         };
 
         Ok(Response::new(response))
     }
 
+    async fn get_context_stream(
+        &self,
+        request: Request<ContextRequest>,
+    ) -> Result<Response<Self::GetContextStreamStream>, Status> {
+        let mut call = self.track_call("get_context_stream", &request);
+        self.blocking(move |service| {
+            service.validate_request(&request)?;
+            let req = request.into_inner();
+            call.set_mode(&req.mode);
+
+            let (optimized_memories, _, _) =
+                service.select_context(&req, &call, &CancellationToken::new())?;
+            call.set_tokens(
+                optimized_memories
+                    .iter()
+                    .map(|scored| scored.memory.token_count.as_usize())
+                    .sum(),
+            );
+
+            let chunks = chunk_context(&optimized_memories, stream_chunk_tokens());
+            let stream = tokio_stream::iter(chunks.into_iter().map(Ok));
+            Ok(Response::new(
+                Box::pin(stream) as Self::GetContextStreamStream
+            ))
+        })
+        .await
+    }
+
     async fn update_context(
         &self,
         request: Request<UpdateContextRequest>,
     ) -> Result<Response<UpdateContextResponse>, Status> {
-        let req = request.into_inner();
+        let mut call = self.track_call("update_context", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let req = request.into_inner();
+            call.set_mode(&req.mode);
 
-        // For now, just return a mock response
-        // In a real implementation, we would update the context for the specified mode
-        let response = UpdateContextResponse {
-            success: true,
-            new_token_count: 15,
-            affected_modes: vec![req.mode, "architect".to_string()],
-        };
+            if req.mode.is_empty() || req.content.is_empty() {
+                return Err(Status::invalid_argument("Mode and content are required"));
+            }
 
-        Ok(Response::new(response))
+            let mut metadata = HashMap::new();
+            metadata.insert(
+                "priority".to_string(),
+                req.priority().as_str_name().to_lowercase(),
+            );
+            service
+                .memory_store
+                .store(
+                    req.content,
+                    "text/plain".to_string(),
+                    None,
+                    Some(req.mode.clone()),
+                    metadata,
+                )
+                .map_err(|e| Status::internal(format!("Failed to store context update: {}", e)))?;
+
+            let kept = service.enforce_mode_budget(&req.mode)?;
+            let new_token_count: usize = kept.iter().map(|m| m.token_count.as_usize()).sum();
+            call.set_tokens(new_token_count);
+
+            let response = UpdateContextResponse {
+                success: true,
+                new_token_count: new_token_count as u32,
+                affected_modes: service.linked_modes(&req.mode, &kept)?,
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
     }
 
     async fn predict_context(
         &self,
         request: Request<PredictRequest>,
     ) -> Result<Response<PredictResponse>, Status> {
+        let mut call = self.track_call("predict_context", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+            call.set_mode(&req.current_mode);
+
+            let response = PredictionModel::default()
+                .predict(&req.current_mode, &req.user_activity, &service.memory_store)
+                .map_err(|e| Status::internal(format!("Failed to predict context: {}", e)))?;
+
+            call.set_tokens(response.estimated_tokens as usize);
+
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn reset_session(
+        &self,
+        request: Request<ResetSessionRequest>,
+    ) -> Result<Response<ResetSessionResponse>, Status> {
+        let _call = self.track_call("reset_session", &request);
         let req = request.into_inner();
 
-        // For now, just return a mock response
-        // In a real implementation, we would predict context based on user activity
-        let response = PredictResponse {
-            predicted_context: format!(
-                "This is predicted context for {} mode based on '{}'",
-                req.current_mode, req.user_activity
-            ),
-            confidence: 0.85,
-            estimated_tokens: 12,
-        };
+        if req.session_id.is_empty() {
+            return Err(Status::invalid_argument("Session ID must not be empty"));
+        }
 
-        Ok(Response::new(response))
+        Ok(Response::new(ResetSessionResponse {
+            existed: self.context_windows.reset(&req.session_id),
+        }))
     }
 
     async fn switch_mode(
         &self,
         request: Request<SwitchModeRequest>,
     ) -> Result<Response<SwitchModeResponse>, Status> {
-        let req = request.into_inner();
+        let mut call = self.track_call("switch_mode", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+            call.set_mode(&req.target_mode);
 
-        // For now, just return a mock response
-        // In a real implementation, we would handle mode switching
-        let response = SwitchModeResponse {
-            success: true,
-            preserved_tokens: if req.preserve_context { 50 } else { 0 },
-            previous_mode: "code".to_string(),
-        };
+            if req.target_mode.is_empty() {
+                return Err(Status::invalid_argument("Target mode must not be empty"));
+            }
+            if req.preserve_context {
+                service.ensure_writable()?;
+            }
 
-        Ok(Response::new(response))
+            let previous_mode = std::mem::replace(
+                &mut *service.current_mode.lock().unwrap(),
+                req.target_mode.clone(),
+            );
+
+            let preserved_tokens = if req.preserve_context {
+                service.snapshot_mode(&previous_mode)?
+            } else {
+                0
+            };
+
+            call.set_tokens(preserved_tokens);
+
+            // Remember the switch for context prediction, unless writes are disabled
+            if !service.config().read_only {
+                record_transition(&service.memory_store, &previous_mode, &req.target_mode)
+                    .map_err(|e| {
+                        Status::internal(format!("Failed to record mode switch: {}", e))
+                    })?;
+            }
+
+            let response = SwitchModeResponse {
+                success: true,
+                preserved_tokens: preserved_tokens as u32,
+                previous_mode,
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
     }
 
     async fn analyze_mode(
         &self,
         request: Request<AnalyzeModeRequest>,
     ) -> Result<Response<AnalyzeModeResponse>, Status> {
-        let req = request.into_inner();
+        let mut call = self.track_call("analyze_mode", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+            call.set_mode(&req.mode);
 
-        // For now, just return a mock response
-        // In a real implementation, we would analyze mode effectiveness
-        let response = AnalyzeModeResponse {
-            effectiveness_score: 0.78,
-            average_tokens: 1200,
-            metrics: vec![],
-        };
+            if req.mode.is_empty() {
+                return Err(Status::invalid_argument("Mode must not be empty"));
+            }
 
-        Ok(Response::new(response))
+            let (access_logs, mode_memories): (Vec<Memory>, Vec<Memory>) = service
+                .memory_store
+                .get_memories_page(0, usize::MAX)
+                .map_err(|e| Status::internal(format!("Failed to load memories: {}", e)))?
+                .into_iter()
+                .filter(|memory| memory.mode.as_deref() == Some(req.mode.as_str()))
+                .filter(|memory| {
+                    !is_internal(memory) || memory.category.as_deref() == Some(ACCESS_LOG_CATEGORY)
+                })
+                .partition(|memory| memory.category.as_deref() == Some(ACCESS_LOG_CATEGORY));
+            if mode_memories.is_empty() {
+                return Err(Status::not_found(format!(
+                    "No memories found for mode: {}",
+                    req.mode
+                )));
+            }
+
+            // Average relevance of the mode's memories for the mode itself
+            let scored = TfIdfScorer::new()
+                .score_memories(&mode_memories, &req.mode, None)
+                .map_err(|e| Status::internal(format!("Failed to score memories: {}", e)))?;
+            let average_relevance = (scored.iter().map(|s| s.score.as_f64()).sum::<f64>()
+                / scored.len() as f64)
+                .clamp(0.0, 1.0);
+
+            // Share of the mode's memories read or retrieved as context within the window
+            let window_hours = match req.time_window {
+                0 => DEFAULT_ANALYSIS_WINDOW_HOURS,
+                hours => hours,
+            };
+            let cutoff = chrono::Utc::now() - chrono::Duration::hours(window_hours as i64);
+            let recent_logs: Vec<&Memory> = access_logs
+                .iter()
+                .filter(|log| log.created_at >= cutoff)
+                .collect();
+            let retrieved: HashSet<&str> = recent_logs
+                .iter()
+                .filter_map(|log| log.metadata.get("memory_ids"))
+                .flat_map(|ids| ids.split(','))
+                .collect();
+            let recently_accessed = mode_memories
+                .iter()
+                .filter(|memory| {
+                    memory.last_accessed >= cutoff || retrieved.contains(memory.id.as_str())
+                })
+                .count();
+            let access_ratio = recently_accessed as f64 / mode_memories.len() as f64;
+
+            // Smaller memories leave more room in the context window
+            let average_tokens = mode_memories
+                .iter()
+                .map(|memory| memory.token_count.as_usize())
+                .sum::<usize>() as f64
+                / mode_memories.len() as f64;
+            let compactness = 1.0 / (1.0 + average_tokens / COMPACT_MEMORY_TOKENS);
+
+            let weights = service.config().relevance.effectiveness_weights.clone();
+            let total_weight = weights.relevance + weights.activity + weights.tokens;
+            let effectiveness_score = if total_weight > 0.0 {
+                (weights.relevance * average_relevance
+                    + weights.activity * access_ratio
+                    + weights.tokens * compactness)
+                    / total_weight
+            } else {
+                0.0
+            };
+
+            let metric = |name: &str, value: f64, unit: &str| ModeMetric {
+                name: name.to_string(),
+                value: value as f32,
+                unit: unit.to_string(),
+            };
+            let response = AnalyzeModeResponse {
+                effectiveness_score: effectiveness_score as f32,
+                average_tokens: average_tokens.round() as u32,
+                metrics: vec![
+                    metric("average_relevance", average_relevance, "score"),
+                    metric("recent_access_ratio", access_ratio, "ratio"),
+                    metric("average_tokens", average_tokens, "tokens"),
+                    metric("memory_count", mode_memories.len() as f64, "count"),
+                    metric("context_retrievals", recent_logs.len() as f64, "count"),
+                ],
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
     }
 
     async fn get_metrics(
         &self,
         request: Request<MetricsRequest>,
     ) -> Result<Response<MetricsResponse>, Status> {
-        let _req = request.into_inner();
+        let _call = self.track_call("get_metrics", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
 
-        // For now, just return a mock response
-        // In a real implementation, we would retrieve metrics
-        let response = MetricsResponse {
-            metrics: vec![],
-            usage: None,
-            trends: vec![],
-        };
+            let range_hours = match req.time_range {
+                0 => DEFAULT_METRICS_RANGE_HOURS,
+                hours => hours,
+            };
+            let now = chrono::Utc::now().timestamp();
+            let since = now - range_hours as i64 * 60 * 60;
+            let wants = |metric_type: &str| {
+                req.metric_types.is_empty() || req.metric_types.iter().any(|t| t == metric_type)
+            };
+            let internal =
+                |e: anyhow::Error| Status::internal(format!("Failed to read metrics: {}", e));
+            let metric = |name: String, value: f64| Metric {
+                name,
+                value: value as f32,
+                timestamp: now as u64,
+            };
 
-        Ok(Response::new(response))
+            let mut metrics = Vec::new();
+            if wants("calls") {
+                for (operation, calls) in service
+                    .metrics
+                    .calls_per_operation(since)
+                    .map_err(internal)?
+                {
+                    metrics.push(metric(format!("calls.{}", operation), calls as f64));
+                }
+            }
+            if wants("latency") {
+                let latency = service
+                    .metrics
+                    .latency_percentiles(since)
+                    .map_err(internal)?;
+                metrics.push(metric("latency_p50_ms".to_string(), latency.p50_ms as f64));
+                metrics.push(metric("latency_p95_ms".to_string(), latency.p95_ms as f64));
+                metrics.push(metric("latency_p99_ms".to_string(), latency.p99_ms as f64));
+            }
+
+            let mut trends = Vec::new();
+            if wants("tokens") {
+                for (mode, days) in service
+                    .metrics
+                    .tokens_per_mode_per_day(since)
+                    .map_err(internal)?
+                {
+                    trends.push(Trend {
+                        metric_name: format!("tokens.{}", mode),
+                        values: days.iter().map(|(_, tokens)| *tokens as f32).collect(),
+                        timestamps: days.iter().map(|(day, _)| *day as u64).collect(),
+                    });
+                }
+            }
+
+            let usage = Usage {
+                total_tokens: service.metrics.total_tokens(since).map_err(internal)? as u32,
+                optimized_tokens: 0,
+                cost_saved: 0.0,
+            };
+
+            let response = MetricsResponse {
+                metrics,
+                usage: Some(usage),
+                trends,
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
     }
 
     async fn track_usage(
         &self,
         request: Request<UsageRequest>,
     ) -> Result<Response<UsageResponse>, Status> {
-        let _req = request.into_inner();
+        let _call = self.track_call("track_usage", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
 
-        // For now, just return a mock response
-        // In a real implementation, we would track usage
-        let response = UsageResponse {
-            recorded: true,
-            session_tokens: 2500,
-            daily_tokens: 10000,
-        };
+            let operation = match req.action.as_str() {
+                "" => "usage".to_string(),
+                action => format!("usage.{}", action),
+            };
+            let tokens = match req.metadata.get("tokens") {
+                Some(tokens) => tokens.parse().map_err(|_| {
+                    Status::invalid_argument(format!("Invalid token count: {}", tokens))
+                })?,
+                None => 0,
+            };
 
-        Ok(Response::new(response))
+            let internal =
+                |e: anyhow::Error| Status::internal(format!("Failed to track usage: {}", e));
+            service
+                .metrics
+                .record(&operation, &req.mode, tokens, Duration::ZERO)
+                .map_err(internal)?;
+            let day_ago = chrono::Utc::now().timestamp() - 24 * 60 * 60;
+
+            let response = UsageResponse {
+                recorded: true,
+                session_tokens: service.metrics.session_tokens().map_err(internal)? as u32,
+                daily_tokens: service.metrics.total_tokens(day_ago).map_err(internal)? as u32,
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
     }
 
     // Memory Bank operations
@@ -453,383 +2117,3973 @@ impl SmartMemoryMcp for SmartMemoryService {
         &self,
         request: Request<MemoryBankStoreRequest>,
     ) -> Result<Response<MemoryBankStoreResponse>, Status> {
-        let req = request.into_inner();
+        let mut call = self.track_call("store_memory_bank", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            service.validate_request(&request)?;
+            let req = request.into_inner();
+            call.set_mode(&req.mode);
 
-        // Extract category and mode from request
-        let category = if req.category.is_empty() {
-            None
-        } else {
-            Some(req.category)
-        };
-        let mode = if req.mode.is_empty() {
-            None
-        } else {
-            Some(req.mode)
-        };
+            // Extract category and mode from request
+            let category = if req.category.is_empty() {
+                None
+            } else {
+                Some(req.category)
+            };
+            let mode = if req.mode.is_empty() {
+                None
+            } else {
+                Some(req.mode)
+            };
 
-        // Add date to metadata if provided
-        let mut metadata = req.metadata;
-        if !req.date.is_empty() {
-            metadata.insert("date".to_string(), req.date);
-        }
+            // Add date to metadata if provided
+            let mut metadata = req.metadata;
+            if !req.date.is_empty() {
+                metadata.insert("date".to_string(), req.date);
+            }
 
-        // Store the memory
-        let memory = self
-            .memory_store
-            .store(
-                req.content,
-                "text/markdown".to_string(), // Default content type for memory bank
-                category.clone(),
-                mode,
-                metadata,
-            )
-            .map_err(|e| Status::internal(format!("Failed to store memory bank entry: {}", e)))?;
+            // Store the memory
+            let memory = service
+                .memory_store
+                .store(
+                    req.content,
+                    "text/markdown".to_string(), // Default content type for memory bank
+                    category.clone(),
+                    mode,
+                    metadata,
+                )
+                .map_err(|e| {
+                    Status::internal(format!("Failed to store memory bank entry: {}", e))
+                })?;
+            call.set_tokens(memory.token_count.as_usize());
 
-        // Create the response
-        let response = MemoryBankStoreResponse {
-            memory_id: memory.id.as_str().to_string(),
-            token_count: memory.token_count.as_usize() as u32,
-            category: category.unwrap_or_default(),
-            success: true,
-        };
+            // Create the response
+            let response = MemoryBankStoreResponse {
+                memory_id: memory.id.as_str().to_string(),
+                token_count: memory.token_count.as_usize() as u32,
+                category: category.unwrap_or_default(),
+                success: true,
+            };
 
-        Ok(Response::new(response))
+            Ok(Response::new(response))
+        })
+        .await
     }
 
     async fn get_memory_bank_context(
         &self,
         request: Request<MemoryBankContextRequest>,
     ) -> Result<Response<MemoryBankContextResponse>, Status> {
-        let req = request.into_inner();
+        let mut call = self.track_call("get_memory_bank_context", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+            call.set_mode(&req.mode);
 
-        // Get all memories
-        let memory_ids = self
-            .memory_store
-            .get_all_ids()
-            .map_err(|e| Status::internal(format!("Failed to get memory IDs: {}", e)))?;
+            let mut memories = service.load_memory_bank(&req.categories)?;
 
-        let mut memories = Vec::new();
-        for id in memory_ids {
-            if let Some(memory) = self
-                .memory_store
-                .retrieve(&id)
-                .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
-            {
-                // Filter by category if categories are specified
-                if !req.categories.is_empty() {
-                    if let Some(category) = &memory.category {
-                        if !req.categories.contains(category) {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
-                }
+            // Filter by date if specified
+            if !req.date.is_empty() {
+                memories.retain(|memory| memory.metadata.get("date") == Some(&req.date));
+            }
 
-                // Filter by date if specified
-                if !req.date.is_empty() {
-                    if let Some(date) = memory.metadata.get("date") {
-                        if date != &req.date {
-                            continue;
-                        }
-                    } else {
-                        continue;
-                    }
-                }
+            // Score memories for relevance
+            let scoring = call.child_span("relevance.score");
+            let mut scored_memories = service
+                .relevance_scorer
+                .score_memories(&memories, &req.mode, non_empty(&req.query))
+                .map_err(|e| Status::internal(format!("Failed to score memories: {}", e)))?;
+            drop(scoring);
 
-                memories.push(memory);
-            }
-        }
+            // Drop memories the client has already seen before optimizing
+            let excluded: HashSet<&str> =
+                req.exclude_memory_ids.iter().map(String::as_str).collect();
+            let scored_count = scored_memories.len();
+            scored_memories.retain(|scored| !excluded.contains(scored.memory.id.as_str()));
+            let excluded_count = scored_count - scored_memories.len();
 
-        // Score memories for relevance
-        let scored_memories = self
-            .relevance_scorer
-            .score_memories(
-                &memories, &req.mode, None, // No query for now
-            )
-            .map_err(|e| Status::internal(format!("Failed to score memories: {}", e)))?;
+            // Optimize context based on token budget and relevance threshold
+            let max_tokens = crate::storage::TokenCount::from(req.max_tokens as usize);
+            let relevance_threshold =
+                crate::storage::RelevanceScore::new(req.relevance_threshold.into());
 
-        // Optimize context based on token budget and relevance threshold
-        let max_tokens = crate::storage::TokenCount::from(req.max_tokens as usize);
-        let relevance_threshold =
-            crate::storage::RelevanceScore::new(req.relevance_threshold.into());
+            // Apply the per-category budgets from the current memory bank config
+            let optimized_memories = CategoryAwareOptimizer::new(service.config().clone())
+                .optimize(&scored_memories, max_tokens, relevance_threshold)
+                .map_err(|e| Status::internal(format!("Failed to optimize context: {}", e)))?;
 
-        let optimized_memories = self
-            .context_optimizer
-            .optimize(&scored_memories, max_tokens, relevance_threshold)
-            .map_err(|e| Status::internal(format!("Failed to optimize context: {}", e)))?;
+            // Build the context from the optimized memories
+            let mut context = String::new();
+            let mut sources = Vec::new();
+            let mut total_tokens = 0;
 
-        // Build the context from the optimized memories
-        let mut context = String::new();
-        let mut sources = Vec::new();
-        let mut total_tokens = 0;
+            for scored_memory in &optimized_memories {
+                // Add the memory content to the context
+                context.push_str(&scored_memory.memory.content);
+                context.push_str("\n\n");
 
-        for scored_memory in &optimized_memories {
-            // Add the memory content to the context
-            context.push_str(&scored_memory.memory.content);
-            context.push_str("\n\n");
+                // Add the memory as a source
+                sources.push(MemoryBankSource {
+                    id: scored_memory.memory.id.as_str().to_string(),
+                    category: scored_memory.memory.category.clone().unwrap_or_default(),
+                    relevance: scored_memory.score.as_f64() as f32,
+                });
 
-            // Add the memory as a source
-            sources.push(MemoryBankSource {
-                id: scored_memory.memory.id.as_str().to_string(),
-                category: scored_memory.memory.category.clone().unwrap_or_default(),
-                relevance: scored_memory.score.as_f64() as f32,
-            });
+                // Add the memory tokens to the total
+                total_tokens += scored_memory.memory.token_count.as_usize();
+            }
 
-            // Add the memory tokens to the total
-            total_tokens += scored_memory.memory.token_count.as_usize();
-        }
+            call.set_tokens(total_tokens);
 
-        // Create the response
-        let response = MemoryBankContextResponse {
-            context,
-            token_count: total_tokens as u32,
-            relevance_score: optimized_memories
-                .first()
-                .map(|m| m.score.as_f64() as f32)
-                .unwrap_or(0.0),
-            sources,
-        };
+            // Create the response
+            let response = MemoryBankContextResponse {
+                context,
+                token_count: total_tokens as u32,
+                relevance_score: optimized_memories
+                    .first()
+                    .map(|m| m.score.as_f64() as f32)
+                    .unwrap_or(0.0),
+                sources,
+                excluded_count: excluded_count as u32,
+            };
 
-        Ok(Response::new(response))
+            Ok(Response::new(response))
+        })
+        .await
     }
 
     async fn optimize_memory_bank(
         &self,
         request: Request<MemoryBankOptimizeRequest>,
     ) -> Result<Response<MemoryBankOptimizeResponse>, Status> {
-        let req = request.into_inner();
+        let _call = self.track_call("optimize_memory_bank", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
 
-        // Get all memories
-        let memory_ids = self
-            .memory_store
-            .get_all_ids()
-            .map_err(|e| Status::internal(format!("Failed to get memory IDs: {}", e)))?;
+            let memories = service.load_memory_bank(&req.categories)?;
 
-        let mut memories = Vec::new();
-        for id in memory_ids {
-            if let Some(memory) = self
+            // Calculate total tokens before optimization
+            let tokens_before: usize = memories.iter().map(|m| m.token_count.as_usize()).sum();
+
+            // For now, just return a mock response with realistic values
+            // In a real implementation, we would apply optimization strategies
+            let tokens_after = match req.strategy.as_str() {
+                "aggressive" => (tokens_before as f32 * 0.5) as usize,
+                "conservative" => (tokens_before as f32 * 0.9) as usize,
+                _ => (tokens_before as f32 * 0.7) as usize, // balanced
+            };
+
+            let tokens_saved = tokens_before - tokens_after;
+
+            // Create the response
+            let response = MemoryBankOptimizeResponse {
+                tokens_before: tokens_before as u32,
+                tokens_after: tokens_after as u32,
+                tokens_saved: tokens_saved as u32,
+                optimized_memories: memories.len() as u32,
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn get_memory_bank_stats(
+        &self,
+        request: Request<MemoryBankStatsRequest>,
+    ) -> Result<Response<MemoryBankStatsResponse>, Status> {
+        let _call = self.track_call("get_memory_bank_stats", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+
+            let stats = service
                 .memory_store
-                .retrieve(&id)
-                .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
-            {
-                // Filter by category if categories are specified
-                if !req.categories.is_empty() {
-                    if let Some(category) = &memory.category {
-                        if !req.categories.contains(category) {
-                            continue;
-                        }
-                    } else {
-                        continue;
+                .get_stats()
+                .map_err(|e| Status::internal(format!("Failed to get memory stats: {}", e)))?;
+
+            let mut tokens_by_category = HashMap::new();
+            let mut memories_by_category = HashMap::new();
+            let mut category_stats = Vec::new();
+            for (category, category_totals) in stats.by_category {
+                let memory_count = category_totals.memory_count as u32;
+                let token_count = category_totals.token_count as u32;
+                tokens_by_category.insert(category.clone(), token_count);
+                memories_by_category.insert(category.clone(), memory_count);
+
+                category_stats.push(MemoryBankCategoryStats {
+                    category,
+                    memory_count,
+                    token_count,
+                    // Relevance and update dates are not tracked per category yet
+                    average_relevance: 0.75,
+                    last_updated: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+                });
+            }
+            category_stats.sort_by(|a, b| a.category.cmp(&b.category));
+
+            // Create the response
+            let response = MemoryBankStatsResponse {
+                total_memories: stats.total_count as u32,
+                total_tokens: stats.total_tokens as u32,
+                tokens_by_category,
+                memories_by_category,
+                category_stats,
+                pinned_count: stats.pinned_count as u32,
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn merge_categories(
+        &self,
+        request: Request<MergeCategoriesRequest>,
+    ) -> Result<Response<MergeCategoriesResponse>, Status> {
+        let _call = self.track_call("merge_categories", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+
+            let updated_count =
+                service.move_category(&req.source_category, &req.target_category)?;
+            Ok(Response::new(MergeCategoriesResponse { updated_count }))
+        })
+        .await
+    }
+
+    async fn rename_category(
+        &self,
+        request: Request<RenameCategoryRequest>,
+    ) -> Result<Response<MergeCategoriesResponse>, Status> {
+        let _call = self.track_call("rename_category", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+
+            let updated_count = service.move_category(&req.old_category, &req.new_category)?;
+            Ok(Response::new(MergeCategoriesResponse { updated_count }))
+        })
+        .await
+    }
+
+    async fn handle_umb_command(
+        &self,
+        request: Request<UmbCommandRequest>,
+    ) -> Result<Response<UmbCommandResponse>, Status> {
+        let _call = self.track_call("handle_umb_command", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let req = request.into_inner();
+
+            println!("Received UMB command for mode: {}", req.current_mode);
+
+            // Get the current mode from the request
+            let mode = req.current_mode;
+
+            // Get the current context from the request
+            let context = req.current_context;
+
+            // Get the metadata from the request
+            let metadata = req.metadata;
+
+            // Store the context in different categories based on the memory bank config
+            let mut stored_memories = 0;
+            let mut total_tokens = 0;
+            let mut categories = Vec::new();
+
+            // Get the default categories from the memory bank config
+            let default_categories = vec![
+                "context".to_string(),
+                "decision".to_string(),
+                "progress".to_string(),
+            ];
+
+            // Store the context in each category
+            for category in default_categories {
+                // Store the memory
+                match service.memory_store.store(
+                    context.clone(),
+                    "text/markdown".to_string(),
+                    Some(category.clone()),
+                    Some(mode.clone()),
+                    metadata.clone(),
+                ) {
+                    Ok(memory) => {
+                        stored_memories += 1;
+                        total_tokens += memory.token_count.as_usize();
+                        categories.push(category);
+                    }
+                    Err(e) => {
+                        println!("Failed to store memory in category {}: {}", category, e);
                     }
                 }
-
-                memories.push(memory);
             }
-        }
 
-        // Calculate total tokens before optimization
-        let tokens_before: usize = memories.iter().map(|m| m.token_count.as_usize()).sum();
+            // Create the response
+            let response = UmbCommandResponse {
+                success: stored_memories > 0,
+                stored_memories,
+                total_tokens: total_tokens as u32,
+                categories,
+                message: format!(
+                    "Stored {} memories with {} tokens",
+                    stored_memories, total_tokens
+                ),
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn search_content_regex(
+        &self,
+        request: Request<RegexSearchRequest>,
+    ) -> Result<Response<RegexSearchResponse>, Status> {
+        let _call = self.track_call("search_content_regex", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+
+            let limit = if req.limit == 0 {
+                DEFAULT_SEARCH_LIMIT
+            } else {
+                req.limit as usize
+            };
+
+            // Unsafe or invalid patterns are the caller's fault, not an internal error
+            let memories = service
+                .memory_store
+                .search_content_regex_safe(&req.pattern, limit)
+                .map_err(|e| match e.downcast_ref::<RegexSafetyError>() {
+                    Some(safety_error) => Status::invalid_argument(safety_error.to_string()),
+                    None => Status::internal(format!("Failed to search memories: {}", e)),
+                })?;
+
+            let response = RegexSearchResponse {
+                memories: memories.into_iter().map(memory_to_result).collect(),
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn search_memories(
+        &self,
+        request: Request<SearchMemoriesRequest>,
+    ) -> Result<Response<SearchMemoriesResponse>, Status> {
+        let _call = self.track_call("search_memories", &request);
+        let req = request.into_inner();
 
-        // For now, just return a mock response with realistic values
-        // In a real implementation, we would apply optimization strategies
-        let tokens_after = match req.strategy.as_str() {
-            "aggressive" => (tokens_before as f32 * 0.5) as usize,
-            "conservative" => (tokens_before as f32 * 0.9) as usize,
-            _ => (tokens_before as f32 * 0.7) as usize, // balanced
+        let limit = if req.limit == 0 {
+            DEFAULT_SEARCH_LIMIT
+        } else {
+            req.limit as usize
         };
+        let mode = Some(req.mode_filter.as_str()).filter(|mode| !mode.is_empty());
 
-        let tokens_saved = tokens_before - tokens_after;
+        let (memories, total_count) = self
+            .repository
+            .search(&req.query, mode, limit)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to search memories: {}", e)))?;
 
-        // Create the response
-        let response = MemoryBankOptimizeResponse {
-            tokens_before: tokens_before as u32,
-            tokens_after: tokens_after as u32,
-            tokens_saved: tokens_saved as u32,
-            optimized_memories: memories.len() as u32,
+        let response = SearchMemoriesResponse {
+            total_count: total_count as u32,
+            memories: memories.into_iter().map(memory_to_result).collect(),
         };
 
         Ok(Response::new(response))
     }
 
-    async fn get_memory_bank_stats(
+    async fn get_hot_memories(
         &self,
-        request: Request<MemoryBankStatsRequest>,
-    ) -> Result<Response<MemoryBankStatsResponse>, Status> {
+        request: Request<GetHotMemoriesRequest>,
+    ) -> Result<Response<GetHotMemoriesResponse>, Status> {
+        let _call = self.track_call("get_hot_memories", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+
+            let limit = if req.limit == 0 {
+                DEFAULT_HOT_MEMORIES_LIMIT
+            } else {
+                req.limit as usize
+            };
+            let mode = Some(req.mode_filter.as_str()).filter(|mode| !mode.is_empty());
+
+            let memories = service
+                .memory_store
+                .get_hot_memories(mode, limit)
+                .map_err(|e| Status::internal(format!("Failed to load hot memories: {}", e)))?;
+
+            Ok(Response::new(GetHotMemoriesResponse {
+                memories: memories.into_iter().map(memory_to_result).collect(),
+            }))
+        })
+        .await
+    }
+
+    async fn filter_by_tags(
+        &self,
+        request: Request<FilterByTagsRequest>,
+    ) -> Result<Response<FilterByTagsResponse>, Status> {
+        let _call = self.track_call("filter_by_tags", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+
+            let memories = service
+                .memory_store
+                .filter_by_tags(&req.tags, req.match_all)
+                .map_err(|e| {
+                    Status::internal(format!("Failed to filter memories by tags: {}", e))
+                })?;
+
+            let response = FilterByTagsResponse {
+                total_count: memories.len() as u32,
+                memories: memories.into_iter().map(memory_to_result).collect(),
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn list_memories(
+        &self,
+        request: Request<ListMemoriesRequest>,
+    ) -> Result<Response<ListMemoriesResponse>, Status> {
+        let _call = self.track_call("list_memories", &request);
         let req = request.into_inner();
 
-        // Get all memories
-        let memory_ids = self
-            .memory_store
-            .get_all_ids()
-            .map_err(|e| Status::internal(format!("Failed to get memory IDs: {}", e)))?;
+        let filter = MemoryFilter {
+            category: non_empty(&req.category).map(str::to_string),
+            mode: non_empty(&req.mode).map(str::to_string),
+            from_date: parse_date("from_date", &req.from_date)?,
+            to_date: parse_date("to_date", &req.to_date)?,
+            content_contains: non_empty(&req.content_contains).map(str::to_string),
+            // The query narrows whatever the fields above select
+            query: match non_empty(&req.query) {
+                Some(query) => {
+                    MemoryQuery::parse(query)
+                        .map_err(|e| Status::invalid_argument(format!("Invalid query: {}", e)))?
+                        .query
+                }
+                None => None,
+            },
+        };
+        let sort = match non_empty(&req.sort_by) {
+            Some(field) => MemorySortField::from_str(field).ok_or_else(|| {
+                Status::invalid_argument(format!("Unknown sort field: {}", field))
+            })?,
+            None => MemorySortField::default(),
+        };
+        let order = match non_empty(&req.order) {
+            Some(order) => SortOrder::from_str(order).ok_or_else(|| {
+                Status::invalid_argument(format!("Unknown sort order: {}", order))
+            })?,
+            None => SortOrder::default(),
+        };
+        let page_size = if req.page_size == 0 {
+            DEFAULT_LIST_PAGE_SIZE
+        } else {
+            req.page_size as usize
+        };
 
-        let mut memories = Vec::new();
-        for id in memory_ids {
-            if let Some(memory) = self
+        let (memories, total_count) = self
+            .repository
+            .list(&filter, sort, order, req.page as usize, page_size)
+            .await
+            .map_err(|e| Status::internal(format!("Failed to list memories: {}", e)))?;
+
+        Ok(Response::new(ListMemoriesResponse {
+            memories: memories.into_iter().map(memory_to_result).collect(),
+            total_count,
+            page: req.page,
+            page_size: page_size as u32,
+        }))
+    }
+
+    async fn get_related(
+        &self,
+        request: Request<GetRelatedRequest>,
+    ) -> Result<Response<GetRelatedResponse>, Status> {
+        let call = self.track_call("get_related", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+            let memory_id = MemoryId::from(req.memory_id);
+
+            let limit = if req.limit == 0 {
+                DEFAULT_SEARCH_LIMIT
+            } else {
+                req.limit as usize
+            };
+
+            if service
                 .memory_store
-                .retrieve(&id)
+                .retrieve(&memory_id)
                 .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
+                .is_none()
             {
-                memories.push(memory);
+                return Err(Status::not_found(format!(
+                    "Memory with ID {} not found",
+                    memory_id.as_str()
+                )));
+            }
+
+            let scoring = call.child_span("relevance.score");
+            let related = service
+                .memory_store
+                .get_related(&memory_id, service.relevance_scorer.as_ref(), limit)
+                .map_err(|e| Status::internal(format!("Failed to find related memories: {}", e)))?;
+            drop(scoring);
+
+            let response = GetRelatedResponse {
+                memories: related
+                    .into_iter()
+                    .map(|scored| RelatedMemory {
+                        relevance: scored.score.as_f64() as f32,
+                        memory: Some(memory_to_result(scored.memory)),
+                    })
+                    .collect(),
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn link_memories(
+        &self,
+        request: Request<LinkMemoriesRequest>,
+    ) -> Result<Response<LinkMemoriesResponse>, Status> {
+        let _call = self.track_call("link_memories", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let req = request.into_inner();
+            if req.relation_type.trim().is_empty() {
+                return Err(Status::invalid_argument("Relation type is required"));
+            }
+
+            let source = MemoryId::from(req.source_id);
+            let target = MemoryId::from(req.target_id);
+            let linked = service
+                .memory_store
+                .link(&source, &target, &req.relation_type)
+                .map_err(|e| match e.downcast_ref::<CircularLink>() {
+                    Some(circular) => Status::failed_precondition(circular.to_string()),
+                    None => Status::internal(format!("Failed to link memories: {}", e)),
+                })?;
+            if !linked {
+                return Err(Status::not_found(format!(
+                    "Memories {} and {} must both exist",
+                    source.as_str(),
+                    target.as_str()
+                )));
             }
+
+            Ok(Response::new(LinkMemoriesResponse { success: true }))
+        })
+        .await
+    }
+
+    async fn diff_memories(
+        &self,
+        request: Request<DiffMemoriesRequest>,
+    ) -> Result<Response<DiffResponse>, Status> {
+        let _call = self.track_call("diff_memories", &request);
+        let req = request.into_inner();
+
+        let mut memories = Vec::with_capacity(2);
+        for id in [req.memory_id_a, req.memory_id_b] {
+            let memory = self
+                .repository
+                .retrieve(&MemoryId::from(id.clone()))
+                .await
+                .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
+                .ok_or_else(|| Status::not_found(format!("Memory with ID {} not found", id)))?;
+            memories.push(memory);
         }
 
-        // Calculate statistics
-        let total_memories = memories.len() as u32;
-        let total_tokens: usize = memories.iter().map(|m| m.token_count.as_usize()).sum();
+        let diff = MemoryDiff::diff(&memories[0], &memories[1]);
+        Ok(Response::new(DiffResponse {
+            added_lines: diff.added_lines,
+            removed_lines: diff.removed_lines,
+            token_delta: diff.added_tokens as i32,
+        }))
+    }
+
+    async fn get_linked(
+        &self,
+        request: Request<GetLinkedRequest>,
+    ) -> Result<Response<GetLinkedResponse>, Status> {
+        let _call = self.track_call("get_linked", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
+            let memory_id = MemoryId::from(req.memory_id);
+
+            if service
+                .memory_store
+                .retrieve(&memory_id)
+                .map_err(|e| Status::internal(format!("Failed to retrieve memory: {}", e)))?
+                .is_none()
+            {
+                return Err(Status::not_found(format!(
+                    "Memory with ID {} not found",
+                    memory_id.as_str()
+                )));
+            }
 
-        // Group memories by category
-        let mut tokens_by_category = std::collections::HashMap::new();
-        let mut memories_by_category = std::collections::HashMap::new();
-        let mut category_stats = Vec::new();
+            let memories = service
+                .memory_store
+                .get_linked(&memory_id, non_empty(&req.relation_type))
+                .map_err(|e| Status::internal(format!("Failed to get linked memories: {}", e)))?;
 
-        // Process each memory
-        for memory in &memories {
-            let category = memory
-                .category
-                .clone()
-                .unwrap_or_else(|| "uncategorized".to_string());
+            Ok(Response::new(GetLinkedResponse {
+                memories: memories.into_iter().map(memory_to_result).collect(),
+            }))
+        })
+        .await
+    }
 
-            // Update tokens by category
-            let token_count = memory.token_count.as_usize() as u32;
-            *tokens_by_category.entry(category.clone()).or_insert(0) += token_count;
+    async fn get_config(
+        &self,
+        request: Request<GetConfigRequest>,
+    ) -> Result<Response<GetConfigResponse>, Status> {
+        let _call = self.track_call("get_config", &request);
+        auth::check_api_key(&request)?;
 
-            // Update memories by category
-            *memories_by_category.entry(category.clone()).or_insert(0) += 1;
+        // A reloaded config invalidates the cached response
+        let config = self.config().clone();
+        let mut cache = self.config_cache.lock().unwrap();
+        if let Some((built_at, built_from, response)) = cache.as_ref() {
+            if built_at.elapsed() < CONFIG_CACHE_TTL && *built_from == config {
+                return Ok(Response::new(response.clone()));
+            }
         }
 
-        // Create category stats
-        for (category, memory_count) in &memories_by_category {
-            let token_count = *tokens_by_category.get(category).unwrap_or(&0);
+        let response = self.build_config_response()?;
+        *cache = Some((Instant::now(), config, response.clone()));
+
+        Ok(Response::new(response))
+    }
 
-            // Calculate average relevance (mock for now)
-            let average_relevance = 0.75;
+    async fn get_size_distribution(
+        &self,
+        request: Request<GetSizeDistributionRequest>,
+    ) -> Result<Response<GetSizeDistributionResponse>, Status> {
+        let _call = self.track_call("get_size_distribution", &request);
+        self.blocking(move |service| {
+            let req = request.into_inner();
 
-            // Get last updated date (mock for now)
-            let last_updated = chrono::Utc::now().format("%Y-%m-%d").to_string();
+            // Empty filters match every memory
+            let category = Some(req.category.as_str()).filter(|c| !c.is_empty());
+            let mode = Some(req.mode.as_str()).filter(|m| !m.is_empty());
 
-            category_stats.push(MemoryBankCategoryStats {
-                category: category.clone(),
-                memory_count: *memory_count,
-                token_count,
-                average_relevance,
-                last_updated,
-            });
+            let distribution = service
+                .memory_store
+                .get_memory_size_distribution(category, mode)
+                .map_err(|e| {
+                    Status::internal(format!("Failed to compute size distribution: {}", e))
+                })?;
+
+            let response = GetSizeDistributionResponse {
+                distribution: Some(SizeDistributionProto {
+                    p25_tokens: distribution.p25_tokens as u32,
+                    p50_tokens: distribution.p50_tokens as u32,
+                    p75_tokens: distribution.p75_tokens as u32,
+                    p90_tokens: distribution.p90_tokens as u32,
+                    p99_tokens: distribution.p99_tokens as u32,
+                    mean_tokens: distribution.mean_tokens,
+                    std_dev_tokens: distribution.std_dev_tokens,
+                    max_tokens: distribution.max_tokens as u32,
+                    min_tokens: distribution.min_tokens as u32,
+                    total_count: distribution.total_count as u32,
+                }),
+            };
+
+            Ok(Response::new(response))
+        })
+        .await
+    }
+
+    async fn get_logs(
+        &self,
+        request: Request<GetLogsRequest>,
+    ) -> Result<Response<GetLogsResponse>, Status> {
+        let _call = self.track_call("get_logs", &request);
+        auth::check_api_key(&request)?;
+        let req = request.into_inner();
+
+        let mut filter = log_filter(&req.level, &req.module)?;
+        if !req.since.is_empty() {
+            let since = chrono::DateTime::parse_from_rfc3339(&req.since)
+                .map_err(|e| Status::invalid_argument(format!("Invalid since timestamp: {}", e)))?;
+            filter.since = Some(since.with_timezone(&chrono::Utc));
         }
 
-        // Create the response
-        let response = MemoryBankStatsResponse {
-            total_memories,
-            total_tokens: total_tokens as u32,
-            tokens_by_category,
-            memories_by_category,
-            category_stats,
+        let limit = if req.last == 0 {
+            DEFAULT_LOG_LIMIT
+        } else {
+            req.last as usize
+        };
+
+        let response = GetLogsResponse {
+            entries: logging::recent_logs(&filter, limit)
+                .into_iter()
+                .map(log_entry_to_record)
+                .collect(),
         };
 
         Ok(Response::new(response))
     }
 
-    async fn handle_umb_command(
+    async fn stream_logs(
         &self,
-        request: Request<UmbCommandRequest>,
-    ) -> Result<Response<UmbCommandResponse>, Status> {
+        request: Request<StreamLogsRequest>,
+    ) -> Result<Response<Self::StreamLogsStream>, Status> {
+        let _call = self.track_call("stream_logs", &request);
+        auth::check_api_key(&request)?;
         let req = request.into_inner();
 
-        println!("Received UMB command for mode: {}", req.current_mode);
+        let filter = log_filter(&req.level, &req.module)?;
+        let mut receiver = logging::subscribe();
+        let (sender, stream_receiver) = tokio::sync::mpsc::channel(LOG_STREAM_BUFFER);
 
-        // Get the current mode from the request
-        let mode = req.current_mode;
+        // Forward matching entries until the client disconnects
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(entry) => {
+                        if !filter.matches(&entry) {
+                            continue;
+                        }
+                        if sender.send(Ok(log_entry_to_record(entry))).await.is_err() {
+                            break;
+                        }
+                    }
+                    // Entries dropped for a slow subscriber are skipped
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
 
-        // Get the current context from the request
-        let context = req.current_context;
+        Ok(Response::new(Box::pin(ReceiverStream::new(
+            stream_receiver,
+        ))))
+    }
 
-        // Get the metadata from the request
-        let metadata = req.metadata;
+    async fn verify_backup(
+        &self,
+        request: Request<VerifyBackupRequest>,
+    ) -> Result<Response<VerifyBackupResponse>, Status> {
+        let _call = self.track_call("verify_backup", &request);
+        // Hashing the backup reads the whole file
+        self.blocking(move |_| {
+            auth::check_api_key(&request)?;
+            let req = request.into_inner();
 
-        // Store the context in different categories based on the memory bank config
-        let mut stored_memories = 0;
-        let mut total_tokens = 0;
-        let mut categories = Vec::new();
+            // Only files inside the backup directory may be checked
+            if req.backup_name.is_empty() || req.backup_name.contains(['/', '\\']) {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid backup name: {}",
+                    req.backup_name
+                )));
+            }
 
-        // Get the default categories from the memory bank config
-        let default_categories = vec![
-            "context".to_string(),
-            "decision".to_string(),
-            "progress".to_string(),
-        ];
+            let backup_manager = BackupManager::new(&default_backup_dir())
+                .map_err(|e| Status::internal(format!("Failed to open backup directory: {}", e)))?;
+            let backup_path = backup_manager.backup_path(&req.backup_name);
+            if !backup_path.is_file() {
+                return Err(Status::not_found(format!(
+                    "Backup not found: {}",
+                    req.backup_name
+                )));
+            }
 
-        // Store the context in each category
-        for category in default_categories {
-            // Store the memory
-            match self.memory_store.store(
-                context.clone(),
-                "text/markdown".to_string(),
-                Some(category.clone()),
-                Some(mode.clone()),
-                metadata.clone(),
-            ) {
-                Ok(memory) => {
-                    stored_memories += 1;
-                    total_tokens += memory.token_count.as_usize();
-                    categories.push(category);
+            let checksum_present = backup_manager
+                .backup_metadata(&req.backup_name)
+                .map(|metadata| metadata.sha256.is_some())
+                .unwrap_or(false);
+            let valid = backup_manager
+                .verify_backup(&backup_path)
+                .map_err(|e| Status::internal(format!("Failed to verify backup: {}", e)))?;
+
+            Ok(Response::new(VerifyBackupResponse {
+                valid,
+                checksum_present,
+            }))
+        })
+        .await
+    }
+
+    async fn compact(
+        &self,
+        request: Request<CompactRequest>,
+    ) -> Result<Response<CompactResponse>, Status> {
+        let _call = self.track_call("compact", &request);
+        auth::check_api_key(&request)?;
+
+        // VACUUM rewrites the whole database, so keep it off the async workers
+        let bytes_saved = self
+            .repository
+            .run(|store| store.compact())
+            .await
+            .map_err(|e| Status::internal(format!("{:#}", e)))?
+            .map_err(|e| match e.downcast_ref::<CompactionInProgress>() {
+                Some(in_progress) => Status::aborted(in_progress.to_string()),
+                None => Status::internal(format!("Failed to compact database: {}", e)),
+            })?;
+
+        Ok(Response::new(CompactResponse { bytes_saved }))
+    }
+
+    async fn vacuum(
+        &self,
+        request: Request<VacuumRequest>,
+    ) -> Result<Response<VacuumResponse>, Status> {
+        let _call = self.track_call("vacuum", &request);
+        auth::check_api_key(&request)?;
+
+        let pages = request.into_inner().pages;
+        let pages_freed = self
+            .repository
+            .run(move |store| store.vacuum(pages))
+            .await
+            .map_err(|e| Status::internal(format!("{:#}", e)))?
+            .map_err(|e| Status::internal(format!("Failed to vacuum database: {}", e)))?;
+
+        Ok(Response::new(VacuumResponse {
+            pages_freed: u32::try_from(pages_freed).unwrap_or(u32::MAX),
+        }))
+    }
+
+    async fn recalculate_tokens(
+        &self,
+        request: Request<RecalculateTokensRequest>,
+    ) -> Result<Response<RecalculateTokensResponse>, Status> {
+        let _call = self.track_call("recalculate_tokens", &request);
+        auth::check_admin_key(&request)?;
+        self.ensure_writable()?;
+
+        // Every memory is re-tokenized, so keep it off the async workers
+        let started = Instant::now();
+        let updated_count = self
+            .repository
+            .run(|store| store.recalculate_tokens())
+            .await
+            .map_err(|e| Status::internal(format!("{:#}", e)))?
+            .map_err(|e| Status::internal(format!("Failed to recalculate tokens: {}", e)))?;
+
+        Ok(Response::new(RecalculateTokensResponse {
+            updated_count,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }))
+    }
+
+    async fn prepare_restore(
+        &self,
+        request: Request<PrepareRestoreRequest>,
+    ) -> Result<Response<PrepareRestoreResponse>, Status> {
+        let _call = self.track_call("prepare_restore", &request);
+        self.blocking(move |service| {
+            auth::check_admin_key(&request)?;
+            service.ensure_writable()?;
+
+            let (backup_path, metadata) = BackupManager::new(&default_backup_dir())
+                .and_then(|backup_manager| backup_manager.latest_backup())
+                .map_err(|e| match e.kind() {
+                    std::io::ErrorKind::NotFound => Status::not_found(e.to_string()),
+                    _ => Status::internal(format!("Failed to find the latest backup: {}", e)),
+                })?;
+            let backup_name = file_name(&backup_path);
+
+            // A new token replaces any earlier one
+            let token = uuid::Uuid::new_v4().to_string();
+            *service.pending_restore.lock().unwrap() = Some(PendingRestore {
+                token: token.clone(),
+                backup_name: backup_name.clone(),
+                issued: Instant::now(),
+            });
+
+            Ok(Response::new(PrepareRestoreResponse {
+                confirmation_token: token,
+                backup_name,
+                backup_timestamp: metadata.created_at().timestamp_millis() as u64,
+            }))
+        })
+        .await
+    }
+
+    async fn restore_latest(
+        &self,
+        request: Request<RestoreLatestRequest>,
+    ) -> Result<Response<RestoreLatestResponse>, Status> {
+        let _call = self.track_call("restore_latest", &request);
+        auth::check_admin_key(&request)?;
+        self.ensure_writable()?;
+        let req = request.into_inner();
+
+        let expected_backup = {
+            let mut pending = self.pending_restore.lock().unwrap();
+            match pending.take() {
+                Some(restore)
+                    if restore.token == req.confirmation_token
+                        && restore.issued.elapsed() <= RESTORE_CONFIRMATION_TTL =>
+                {
+                    restore.backup_name
                 }
-                Err(e) => {
-                    println!("Failed to store memory in category {}: {}", category, e);
+                // A wrong token leaves the pending restore for the right one
+                Some(restore) if restore.token != req.confirmation_token => {
+                    *pending = Some(restore);
+                    return Err(Status::failed_precondition(
+                        "Confirmation token does not match the latest PrepareRestore",
+                    ));
+                }
+                _ => {
+                    return Err(Status::failed_precondition(
+                        "No restore is pending; call PrepareRestore first",
+                    ))
                 }
             }
-        }
-
-        // Create the response
-        let response = UmbCommandResponse {
-            success: stored_memories > 0,
-            stored_memories,
-            total_tokens: total_tokens as u32,
-            categories,
-            message: format!(
-                "Stored {} memories with {} tokens",
-                stored_memories, total_tokens
-            ),
         };
+        let db_path = self.memory_store.database_path().ok_or_else(|| {
+            Status::failed_precondition("Restoring a backup needs SQLite storage")
+        })?;
 
-        Ok(Response::new(response))
+        // Restoring copies the whole database, so keep it off the async workers
+        let (restored_from, metadata, bytes_restored) = self
+            .blocking(move |service| {
+                let restored =
+                    restore_latest_backup(&service.memory_store, &db_path, &expected_backup)?;
+                service.relevance_scorer.invalidate_cache();
+                Ok(restored)
+            })
+            .await?;
+
+        Ok(Response::new(RestoreLatestResponse {
+            restored_from,
+            backup_timestamp: metadata.created_at().timestamp_millis() as u64,
+            bytes_restored,
+        }))
     }
-}
 
-/// Create a new memory store instance
-pub fn create_memory_store() -> Arc<MemoryStore> {
-    let tokenizer = Tokenizer::new(TokenizerType::Simple).expect("Failed to create tokenizer");
-    Arc::new(MemoryStore::new_in_memory(tokenizer))
-}
+    async fn create_snapshot(
+        &self,
+        request: Request<CreateSnapshotRequest>,
+    ) -> Result<Response<CreateSnapshotResponse>, Status> {
+        let _call = self.track_call("create_snapshot", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
 
-/// Create a new service with a shared memory store
-pub fn create_service_with_store(
-    memory_store: Arc<MemoryStore>,
-) -> SmartMemoryMcpServer<SmartMemoryService> {
-    let service = SmartMemoryService {
-        memory_store,
-        relevance_scorer: Arc::new(TfIdfScorer::new()),
-        context_optimizer: Arc::new(TokenBudgetOptimizer::new()),
-        memory_bank_config: MemoryBankConfig::default(),
-    };
+            let snapshot_id = service
+                .memory_store
+                .snapshot()
+                .map_err(|e| Status::internal(format!("Failed to create snapshot: {}", e)))?;
 
-    SmartMemoryMcpServer::new(service)
-}
+            Ok(Response::new(CreateSnapshotResponse {
+                snapshot_id: snapshot_id.as_str().to_string(),
+            }))
+        })
+        .await
+    }
 
-pub fn create_service() -> SmartMemoryMcpServer<SmartMemoryService> {
-    // Check if DB_PATH environment variable is set
-    let memory_store = if let Ok(db_path) = std::env::var("DB_PATH") {
-        println!("Using SQLite database at {}", db_path);
+    async fn rollback_snapshot(
+        &self,
+        request: Request<RollbackSnapshotRequest>,
+    ) -> Result<Response<RollbackSnapshotResponse>, Status> {
+        let _call = self.track_call("rollback_snapshot", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let snapshot_id = SnapshotId::from(request.into_inner().snapshot_id);
 
-        let tokenizer = Tokenizer::new(TokenizerType::Simple).expect("Failed to create tokenizer");
+            service
+                .memory_store
+                .rollback_snapshot(&snapshot_id)
+                .map_err(|e| match e.downcast_ref::<UnknownSnapshot>() {
+                    Some(unknown) => Status::not_found(unknown.to_string()),
+                    None => Status::internal(format!("Failed to roll back snapshot: {}", e)),
+                })?;
 
-        Arc::new(
-            MemoryStore::new_sqlite(Path::new(&db_path), tokenizer)
-                .expect("Failed to create SQLite memory store"),
-        )
-    } else {
-        create_memory_store()
-    };
+            Ok(Response::new(RollbackSnapshotResponse {}))
+        })
+        .await
+    }
+
+    async fn commit_snapshot(
+        &self,
+        request: Request<CommitSnapshotRequest>,
+    ) -> Result<Response<CommitSnapshotResponse>, Status> {
+        let _call = self.track_call("commit_snapshot", &request);
+        self.blocking(move |service| {
+            service.ensure_writable()?;
+            let snapshot_id = SnapshotId::from(request.into_inner().snapshot_id);
+
+            service
+                .memory_store
+                .commit_snapshot(&snapshot_id)
+                .map_err(|e| match e.downcast_ref::<UnknownSnapshot>() {
+                    Some(unknown) => Status::not_found(unknown.to_string()),
+                    None => Status::internal(format!("Failed to commit snapshot: {}", e)),
+                })?;
+
+            Ok(Response::new(CommitSnapshotResponse {}))
+        })
+        .await
+    }
+
+    async fn memory_bank_sync(
+        &self,
+        request: Request<SyncRequest>,
+    ) -> Result<Response<SyncResponse>, Status> {
+        let _call = self.track_call("memory_bank_sync", &request);
+        auth::check_api_key(&request)?;
+        let req = request.into_inner();
+
+        let (pull, push) = match req.sync_direction.as_str() {
+            "pull" => (true, false),
+            "push" => (false, true),
+            "" | "bidirectional" => (true, true),
+            other => {
+                return Err(Status::invalid_argument(format!(
+                    "Unknown sync direction: {}",
+                    other
+                )))
+            }
+        };
+        if pull {
+            self.ensure_writable()?;
+        }
+        let pull_resolution = sync_resolution(&req.conflict_resolution, true)?;
+        let push_resolution = sync_resolution(&req.conflict_resolution, false)?;
+
+        // Only listed peers are dialed, each with its own key
+        let peer_config = self.sync_peers.get(&req.peer_address)?;
+        let mut peer = SmartMemoryMcpClient::connect(peer_config.endpoint.clone())
+            .await
+            .map_err(|e| {
+                Status::unavailable(format!(
+                    "Failed to connect to peer {}: {}",
+                    req.peer_address, e
+                ))
+            })?;
+
+        let mut response = SyncResponse::default();
+
+        // Pull first so a bidirectional sync pushes back the merged result
+        if pull {
+            let payload = peer
+                .sync_export(peer_config.request(SyncExportRequest {
+                    categories: req.categories.clone(),
+                })?)
+                .await?
+                .into_inner();
+            let memories = decode_memories(&payload.memories_zstd)
+                .map_err(|e| Status::internal(format!("Invalid sync payload: {}", e)))?;
+
+            response.received = memories.len() as u32;
+            let stats = self
+                .repository
+                .run(move |store| store.import_memories(memories, pull_resolution))
+                .await
+                .map_err(|e| Status::internal(format!("{:#}", e)))?
+                .map_err(|e| Status::internal(format!("Failed to import memories: {}", e)))?;
+            response.conflicts_resolved += stats.conflicts_resolved as u32;
+        }
+
+        if push {
+            let categories = req.categories.clone();
+            let memories = self
+                .repository
+                .run(move |store| store.export_memories(&categories))
+                .await
+                .map_err(|e| Status::internal(format!("{:#}", e)))?
+                .map_err(|e| Status::internal(format!("Failed to export memories: {}", e)))?;
+            let payload = encode_memories(&memories)
+                .map_err(|e| Status::internal(format!("Failed to encode memories: {}", e)))?;
+
+            response.sent = memories.len() as u32;
+            let result = peer
+                .sync_import(peer_config.request(SyncImportRequest {
+                    memories_zstd: payload,
+                    conflict_resolution: push_resolution.as_str().to_string(),
+                })?)
+                .await?
+                .into_inner();
+            response.conflicts_resolved += result.conflicts_resolved;
+        }
+
+        Ok(Response::new(response))
+    }
 
-    create_service_with_store(memory_store)
+    async fn sync_export(
+        &self,
+        request: Request<SyncExportRequest>,
+    ) -> Result<Response<SyncPayload>, Status> {
+        let _call = self.track_call("sync_export", &request);
+        self.blocking(move |service| {
+            auth::check_api_key(&request)?;
+            let req = request.into_inner();
+
+            let memories = service
+                .memory_store
+                .export_memories(&req.categories)
+                .map_err(|e| Status::internal(format!("Failed to export memories: {}", e)))?;
+            let memories_zstd = encode_memories(&memories)
+                .map_err(|e| Status::internal(format!("Failed to encode memories: {}", e)))?;
+
+            Ok(Response::new(SyncPayload {
+                memories_zstd,
+                memory_count: memories.len() as u32,
+            }))
+        })
+        .await
+    }
+
+    async fn sync_import(
+        &self,
+        request: Request<SyncImportRequest>,
+    ) -> Result<Response<SyncImportResponse>, Status> {
+        let _call = self.track_call("sync_import", &request);
+        self.blocking(move |service| {
+            auth::check_api_key(&request)?;
+            service.ensure_writable()?;
+            let req = request.into_inner();
+
+            let resolution =
+                ConflictResolution::parse(&req.conflict_resolution).ok_or_else(|| {
+                    Status::invalid_argument(format!(
+                        "Unknown conflict resolution: {}",
+                        req.conflict_resolution
+                    ))
+                })?;
+            let memories = decode_memories(&req.memories_zstd)
+                .map_err(|e| Status::invalid_argument(format!("Invalid sync payload: {}", e)))?;
+
+            let stats = service
+                .memory_store
+                .import_memories(memories, resolution)
+                .map_err(|e| match e.downcast_ref::<ImportConflict>() {
+                    Some(conflict) => Status::already_exists(conflict.to_string()),
+                    None => Status::internal(format!("Failed to import memories: {}", e)),
+                })?;
+
+            Ok(Response::new(SyncImportResponse {
+                imported: stats.imported as u32,
+                conflicts_resolved: stats.conflicts_resolved as u32,
+            }))
+        })
+        .await
+    }
+
+    async fn export_memories(
+        &self,
+        request: Request<ExportMemoriesRequest>,
+    ) -> Result<Response<Self::ExportMemoriesStream>, Status> {
+        let _call = self.track_call("export_memories", &request);
+        self.blocking(move |service| {
+            auth::check_api_key(&request)?;
+            let req = request.into_inner();
+
+            // The chunks are the same for every format; clients encode the export file
+            if !req.format.is_empty() && !EXPORT_FORMATS.contains(&req.format.as_str()) {
+                return Err(Status::invalid_argument(format!(
+                    "Unknown export format: {}",
+                    req.format
+                )));
+            }
+
+            let mut memories = service
+                .memory_store
+                .export_memories(&req.categories)
+                .map_err(|e| Status::internal(format!("Failed to export memories: {}", e)))?;
+            if !req.mode_filter.is_empty() {
+                memories.retain(|memory| memory.mode.as_deref() == Some(req.mode_filter.as_str()));
+            }
+
+            let chunks: Vec<ExportChunk> = memories
+                .chunks(EXPORT_CHUNK_SIZE)
+                .enumerate()
+                .map(|(index, chunk)| ExportChunk {
+                    memories: chunk.iter().map(exported_memory).collect(),
+                    chunk_index: index as u32,
+                })
+                .collect();
+            let stream = tokio_stream::iter(chunks.into_iter().map(Ok));
+            Ok(Response::new(Box::pin(stream) as Self::ExportMemoriesStream))
+        })
+        .await
+    }
+
+    async fn import_memories(
+        &self,
+        request: Request<tonic::Streaming<ImportChunk>>,
+    ) -> Result<Response<ImportResponse>, Status> {
+        let _call = self.track_call("import_memories", &request);
+        auth::check_api_key(&request)?;
+        self.ensure_writable()?;
+        let mut chunks = request.into_inner();
+
+        let mut response = ImportResponse::default();
+        while let Some(chunk) = chunks.message().await? {
+            let mut memories = Vec::with_capacity(chunk.memories.len());
+            for exported in chunk.memories {
+                match imported_memory(exported) {
+                    Ok(memory) => memories.push(memory),
+                    Err(e) => response.errors.push(e),
+                }
+            }
+
+            // Memories already stored under the same ID are skipped unless overwriting
+            let resolution = if chunk.overwrite {
+                ConflictResolution::PreferIncoming
+            } else {
+                ConflictResolution::KeepExisting
+            };
+            let received = memories.len();
+            let stats = self
+                .repository
+                .run(move |store| store.import_memories(memories, resolution))
+                .await
+                .map_err(|e| Status::internal(format!("{:#}", e)))?
+                .map_err(|e| Status::internal(format!("Failed to import memories: {}", e)))?;
+            response.imported_count += stats.imported as u32;
+            response.skipped_count += (received - stats.imported) as u32;
+        }
+
+        Ok(Response::new(response))
+    }
+}
+
+/// Convert a memory into its export form
+fn exported_memory(memory: &Memory) -> ExportedMemory {
+    ExportedMemory {
+        id: memory.id.as_str().to_string(),
+        content: memory.content.clone(),
+        content_type: memory.content_type.clone(),
+        category: memory.category.clone().unwrap_or_default(),
+        mode: memory.mode.clone().unwrap_or_default(),
+        metadata: memory.metadata.clone(),
+        token_count: memory.token_count.as_usize() as u32,
+        created_at: memory.created_at.to_rfc3339(),
+        last_accessed: memory.last_accessed.to_rfc3339(),
+        ttl_seconds: memory.ttl_seconds.unwrap_or(0),
+        pinned: memory.pinned,
+        tags: memory.tags.clone(),
+    }
+}
+
+/// Convert an exported memory back into a memory, describing why when it is invalid
+fn imported_memory(exported: ExportedMemory) -> Result<Memory, String> {
+    if exported.id.is_empty() {
+        return Err("Memory without an ID".to_string());
+    }
+    let parse_time = |field: &str, value: &str| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .map(|time| time.with_timezone(&chrono::Utc))
+            .map_err(|e| format!("Memory {}: invalid {}: {}", exported.id, field, e))
+    };
+    if i64::try_from(exported.ttl_seconds).is_err() {
+        return Err(format!(
+            "Memory {}: invalid ttl_seconds: {} is more than {} seconds",
+            exported.id,
+            exported.ttl_seconds,
+            i64::MAX
+        ));
+    }
+    let created_at = parse_time("created_at", &exported.created_at)?;
+    let last_accessed = if exported.last_accessed.is_empty() {
+        created_at
+    } else {
+        parse_time("last_accessed", &exported.last_accessed)?
+    };
+
+    Ok(Memory {
+        id: MemoryId::from(exported.id),
+        content: exported.content,
+        content_type: exported.content_type,
+        category: Some(exported.category).filter(|category| !category.is_empty()),
+        mode: Some(exported.mode).filter(|mode| !mode.is_empty()),
+        metadata: exported.metadata,
+        token_count: TokenCount::from(exported.token_count as usize),
+        created_at,
+        last_accessed,
+        ttl_seconds: Some(exported.ttl_seconds).filter(|&ttl| ttl > 0),
+        pinned: exported.pinned,
+        tags: exported.tags,
+        access_count: 0,
+    })
+}
+
+/// Map a sync request's conflict resolution onto the side importing the memories
+///
+/// `local_wins` and `remote_wins` are relative to the server initiating the sync.
+fn sync_resolution(name: &str, import_is_local: bool) -> Result<ConflictResolution, Status> {
+    match (name, import_is_local) {
+        ("" | "newer_wins", _) => Ok(ConflictResolution::NewerWins),
+        ("local_wins", true) | ("remote_wins", false) => Ok(ConflictResolution::KeepExisting),
+        ("local_wins", false) | ("remote_wins", true) => Ok(ConflictResolution::PreferIncoming),
+        (other, _) => Err(Status::invalid_argument(format!(
+            "Unknown conflict resolution: {}",
+            other
+        ))),
+    }
+}
+
+/// Treat an empty string field as unset
+fn non_empty(value: &str) -> Option<&str> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// Parse an optional RFC 3339 timestamp from the request field `field`
+fn parse_date(field: &str, value: &str) -> Result<Option<chrono::DateTime<chrono::Utc>>, Status> {
+    non_empty(value)
+        .map(|value| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .map(|date| date.with_timezone(&chrono::Utc))
+                .map_err(|e| Status::invalid_argument(format!("Invalid {}: {}", field, e)))
+        })
+        .transpose()
+}
+
+/// Create the context optimizer, truncating oversized memories when `CONTEXT_SUMMARIZE=true`
+fn create_context_optimizer() -> Arc<dyn ContextOptimizer> {
+    let optimizer: Arc<dyn ContextOptimizer> = Arc::new(TokenBudgetOptimizer::new());
+    match std::env::var("CONTEXT_SUMMARIZE").as_deref() {
+        Ok("true") | Ok("1") => {
+            Arc::new(SummarizingOptimizer::new(optimizer, Tokenizer::default()))
+        }
+        _ => optimizer,
+    }
+}
+
+/// Create the relevance scorer configured by the memory bank config
+///
+/// `SCORER_TYPE=embedding` selects the embedding scorer and `SCORER_TYPE=hybrid`
+/// blends it with TF-IDF, weighted by `SCORER_ALPHA`. Both fall back to TF-IDF
+/// if the embedding model cannot be loaded.
+fn create_relevance_scorer(config: &MemoryBankConfig) -> Arc<dyn RelevanceScorer> {
+    let tf_idf =
+        || Arc::new(TfIdfScorer::new().with_corpus_window(config.relevance.corpus_window()));
+
+    match std::env::var("SCORER_TYPE").as_deref() {
+        Ok("embedding") => match EmbeddingScorer::new() {
+            Ok(scorer) => Arc::new(scorer),
+            Err(e) => {
+                crate::log_warning!(
+                    "service",
+                    &format!("Embedding scorer unavailable, using TF-IDF: {:#}", e)
+                );
+                tf_idf()
+            }
+        },
+        Ok("hybrid") => {
+            let alpha = std::env::var("SCORER_ALPHA")
+                .ok()
+                .and_then(|alpha| alpha.parse::<f64>().ok())
+                .unwrap_or(DEFAULT_HYBRID_ALPHA);
+            match HybridScorer::new(alpha) {
+                Ok(scorer) => Arc::new(scorer),
+                Err(e) => {
+                    crate::log_warning!(
+                        "service",
+                        &format!("Hybrid scorer unavailable, using TF-IDF: {:#}", e)
+                    );
+                    tf_idf()
+                }
+            }
+        }
+        Ok("") | Ok("tfidf") | Err(_) => tf_idf(),
+        Ok(other) => {
+            crate::log_warning!(
+                "service",
+                &format!("Unknown SCORER_TYPE '{}', using TF-IDF", other)
+            );
+            tf_idf()
+        }
+    }
+}
+
+/// Register the post-store processors enabled by the memory bank config
+fn register_post_store_processors(memory_store: &MemoryStore, config: &MemoryBankConfig) {
+    if config.auto_tag && config.language_detection_enabled {
+        memory_store.add_post_store_processor(Arc::new(LanguageTagger));
+    }
+}
+
+/// Build a log filter from the level and module fields of a log request
+fn log_filter(level: &str, module: &str) -> Result<LogFilter, Status> {
+    let min_level = if level.is_empty() {
+        None
+    } else {
+        Some(
+            LogLevel::from_str(level)
+                .ok_or_else(|| Status::invalid_argument(format!("Unknown log level: {}", level)))?,
+        )
+    };
+
+    Ok(LogFilter {
+        min_level,
+        module: Some(module.to_string()).filter(|m| !m.is_empty()),
+        since: None,
+    })
+}
+
+/// Convert a log entry into its gRPC representation
+fn log_entry_to_record(entry: LogEntry) -> LogRecord {
+    LogRecord {
+        timestamp: entry.timestamp,
+        level: entry.level.as_str().to_string(),
+        module: entry.module,
+        message: entry.message,
+        metadata_json: entry
+            .metadata
+            .map(|metadata| metadata.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Convert a stored memory into its gRPC representation
+fn memory_to_result(memory: Memory) -> MemoryResult {
+    MemoryResult {
+        memory_id: memory.id.as_str().to_string(),
+        content: memory.content,
+        content_type: memory.content_type,
+        category: memory.category.unwrap_or_default(),
+        mode: memory.mode.unwrap_or_default(),
+        metadata: memory.metadata,
+        token_count: memory.token_count.as_usize() as u32,
+        tags: memory.tags,
+        access_count: memory.access_count,
+    }
+}
+
+/// Encrypt the store's memory content when `ENCRYPTION_KEY` is set
+fn encrypt_from_env(memory_store: MemoryStore) -> Result<MemoryStore> {
+    Ok(match ContentCipher::from_env()? {
+        Some(cipher) => memory_store.with_encryption(cipher),
+        None => memory_store,
+    })
+}
+
+/// Create the gRPC server for a service configured from the environment
+///
+/// Memories are kept in the SQLite database at `DB_PATH` when it is set, with
+/// the config at `CONFIG_PATH` or next to the database, and in memory otherwise.
+pub fn create_service() -> Result<SmartMemoryMcpServer<SmartMemoryService>> {
+    let service = match std::env::var("DB_PATH") {
+        Ok(db_path) => {
+            let db_path = Path::new(&db_path);
+            let config_path = std::env::var("CONFIG_PATH")
+                .map(std::path::PathBuf::from)
+                .unwrap_or_else(|_| db_path.with_file_name("config.json"));
+            SmartMemoryService::new_with_pool(
+                db_path,
+                &config_path,
+                SqliteMemoryRepository::default_pool_size(),
+            )?
+        }
+        Err(_) => SmartMemoryService::new()?,
+    };
+
+    Ok(SmartMemoryMcpServer::new(service))
+}
+
+/// Describe a memory included in a context
+fn context_source(scored_memory: &ScoredMemory) -> ContextSource {
+    ContextSource {
+        source_id: scored_memory.memory.id.as_str().to_string(),
+        source_type: scored_memory.memory.content_type.clone(),
+        relevance: scored_memory.score.as_f64() as f32,
+    }
+}
+
+/// Pack memories into context chunks of at most `chunk_tokens` tokens
+///
+/// Memories larger than the limit are sent in a chunk of their own. The last
+/// chunk is marked, and an empty context still produces one empty final chunk.
+fn chunk_context(memories: &[ScoredMemory], chunk_tokens: usize) -> Vec<ContextChunk> {
+    let mut chunks: Vec<ContextChunk> = Vec::new();
+    let mut current_tokens = 0;
+    for scored_memory in memories {
+        let tokens = scored_memory.memory.token_count.as_usize();
+        match chunks.last_mut() {
+            Some(chunk) if current_tokens + tokens <= chunk_tokens => {
+                current_tokens += tokens;
+                chunk.content.push_str(&scored_memory.memory.content);
+                chunk.content.push_str("\n\n");
+                chunk.sources.push(context_source(scored_memory));
+            }
+            _ => {
+                current_tokens = tokens;
+                chunks.push(ContextChunk {
+                    chunk_index: chunks.len() as u32,
+                    content: format!("{}\n\n", scored_memory.memory.content),
+                    sources: vec![context_source(scored_memory)],
+                    is_last: false,
+                });
+            }
+        }
+    }
+
+    match chunks.last_mut() {
+        Some(chunk) => chunk.is_last = true,
+        None => chunks.push(ContextChunk {
+            is_last: true,
+            ..Default::default()
+        }),
+    }
+    chunks
+}
+
+/// Token limit of a streamed context chunk
+fn stream_chunk_tokens() -> usize {
+    std::env::var("STREAM_CHUNK_TOKENS")
+        .ok()
+        .and_then(|tokens| tokens.parse().ok())
+        .filter(|&tokens| tokens > 0)
+        .unwrap_or(DEFAULT_STREAM_CHUNK_TOKENS)
+}
+
+/// Describe an optimizer decision about a memory for a context response
+fn context_explanation(
+    memory_id: &MemoryId,
+    included: bool,
+    reason: &ExclusionReason,
+) -> ContextExplanation {
+    ContextExplanation {
+        memory_id: memory_id.as_str().to_string(),
+        included,
+        reason: reason.to_string(),
+    }
+}
+
+/// Check whether a memory is service bookkeeping rather than user content
+fn is_internal(memory: &Memory) -> bool {
+    matches!(
+        memory.category.as_deref(),
+        Some(MODE_SNAPSHOT_CATEGORY)
+            | Some(MODE_TRANSITION_CATEGORY)
+            | Some(ACCESS_LOG_CATEGORY)
+            | Some(HISTORY_CATEGORY)
+    )
+}
+
+/// Convert a duplicate group to its protobuf message
+fn duplicate_group_result(group: &DuplicateGroup) -> DuplicateGroupResult {
+    DuplicateGroupResult {
+        primary_id: group.primary_id.as_str().to_string(),
+        duplicate_ids: group
+            .duplicate_ids
+            .iter()
+            .map(|id| id.as_str().to_string())
+            .collect(),
+        similarity: group.similarity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_sync_only_dials_listed_peers_with_their_key() {
+        let peer = SmartMemoryService::new().unwrap();
+        peer.memory_store
+            .store(
+                "shared by the peer".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+            .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(SmartMemoryMcpServer::with_interceptor(
+                    peer,
+                    |request: Request<()>| {
+                        auth::verify_api_key(&request, "peer-key")?;
+                        Ok(request)
+                    },
+                ))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        let service = SmartMemoryService::new()
+            .unwrap()
+            .with_sync_peers(SyncPeers::parse(&format!("{}=peer-key", addr)));
+        let sync = |peer_address: String| SyncRequest {
+            peer_address,
+            sync_direction: "pull".to_string(),
+            ..Default::default()
+        };
+
+        let response = service
+            .memory_bank_sync(Request::new(sync(addr.to_string())))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.received, 1);
+
+        let status = service
+            .memory_bank_sync(Request::new(sync("169.254.169.254:80".to_string())))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_get_config_reports_categories() {
+        let service = SmartMemoryService::new().unwrap();
+
+        let response = service
+            .get_config(Request::new(GetConfigRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.schema_version, CONFIG_SCHEMA_VERSION);
+        assert!(!response.read_only);
+        let names: Vec<_> = response
+            .categories
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["context", "decision", "pattern", "product", "progress"]
+        );
+        assert_eq!(response.categories[0].priority, "high");
+    }
+
+    #[tokio::test]
+    async fn test_config_file_changes_are_picked_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.json");
+        MemoryBankConfig::default().save(&config_path).unwrap();
+
+        let service = SmartMemoryService::new().unwrap();
+        crate::config_watcher::ConfigWatcher::new(&config_path, service.memory_bank_config.clone())
+            .start()
+            .unwrap();
+
+        let mut changed = MemoryBankConfig::default();
+        changed.categories.get_mut("decision").unwrap().max_tokens = Some(1234);
+        changed.save(&config_path).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            let response = service
+                .get_config(Request::new(GetConfigRequest {}))
+                .await
+                .unwrap()
+                .into_inner();
+            let decision = response
+                .categories
+                .iter()
+                .find(|c| c.name == "decision")
+                .unwrap();
+            if decision.max_tokens == 1234 {
+                break;
+            }
+            assert!(Instant::now() < deadline, "config was not reloaded");
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_server_counts_stores() {
+        let service = SmartMemoryService::new().unwrap();
+        let shutdown = tokio_util::sync::CancellationToken::new();
+        let addr = crate::metrics_server::start(
+            "127.0.0.1:0".parse().unwrap(),
+            service.prometheus.clone(),
+            service.memory_store.clone(),
+            shutdown.clone(),
+        )
+        .unwrap();
+
+        service
+            .store_memory(Request::new(StoreRequest {
+                content: "scraped".to_string(),
+                content_type: "text/plain".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        shutdown.cancel();
+
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.contains("smart_memory_store_total{operation=\"store\"} 1\n"));
+        assert!(
+            response.contains("smart_memory_rpc_duration_seconds_count{rpc=\"store_memory\"} 1\n")
+        );
+        assert!(response.contains("smart_memory_token_count "));
+        assert!(response.contains("smart_memory_cache_hit_ratio "));
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_traced_from_traceparent() {
+        use opentelemetry::trace::{SpanId, TraceId, TracerProvider};
+        use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+
+        let exporter = InMemorySpanExporter::default();
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let mut service = SmartMemoryService::new().unwrap();
+        service.tracer = Arc::new(BoxedTracer::new(Box::new(provider.tracer("test"))));
+
+        let mut request = Request::new(StoreRequest {
+            content: "traced".to_string(),
+            content_type: "text/plain".to_string(),
+            ..Default::default()
+        });
+        request.metadata_mut().insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        let memory_id = service
+            .store_memory(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .memory_id;
+        service
+            .retrieve_memory(Request::new(RetrieveRequest {
+                memory_id,
+                include_metadata: false,
+            }))
+            .await
+            .unwrap();
+        service
+            .get_context(Request::new(ContextRequest {
+                mode: "code".to_string(),
+                max_tokens: 1000,
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        provider.force_flush();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let span = |name: &str| {
+            spans
+                .iter()
+                .find(|span| span.name == name)
+                .unwrap_or_else(|| panic!("no {} span", name))
+        };
+
+        let store = span("store_memory");
+        assert_eq!(
+            store.span_context.trace_id(),
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap()
+        );
+        assert_eq!(
+            store.parent_span_id,
+            SpanId::from_hex("00f067aa0ba902b7").unwrap()
+        );
+        assert_eq!(
+            span("repository.store").parent_span_id,
+            store.span_context.span_id()
+        );
+
+        let retrieve = span("retrieve_memory");
+        assert_eq!(retrieve.parent_span_id, SpanId::INVALID);
+        assert_eq!(
+            span("repository.retrieve").parent_span_id,
+            retrieve.span_context.span_id()
+        );
+        assert_eq!(
+            span("relevance.score").parent_span_id,
+            span("get_context").span_context.span_id()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_import_moves_memories_between_servers() {
+        let source = SmartMemoryService::new().unwrap();
+        let mut originals = Vec::new();
+        for (content, category, mode) in [
+            ("Decided on gRPC", "decision", "architect"),
+            ("Wrote the parser", "progress", "code"),
+            ("Fixed the parser", "progress", "debug"),
+        ] {
+            originals.push(
+                source
+                    .memory_store
+                    .store(
+                        content.to_string(),
+                        "text/plain".to_string(),
+                        Some(category.to_string()),
+                        Some(mode.to_string()),
+                        HashMap::new(),
+                    )
+                    .unwrap(),
+            );
+        }
+
+        let mut stream = source
+            .export_memories(Request::new(ExportMemoriesRequest {
+                categories: vec!["progress".to_string()],
+                mode_filter: "code".to_string(),
+                format: "json".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            chunks.push(chunk.unwrap());
+        }
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].memories.len(), 1);
+        assert_eq!(chunks[0].memories[0].id, originals[1].id.as_str());
+
+        // Import into a second server over gRPC, once with an invalid memory
+        let target = SmartMemoryService::new().unwrap();
+        let target_store = target.memory_store.clone();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(SmartMemoryMcpServer::new(target))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let mut client = SmartMemoryMcpClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+
+        let mut invalid = chunks[0].memories[0].clone();
+        invalid.id = "broken".to_string();
+        invalid.created_at = "yesterday".to_string();
+        let import = |memories: Vec<ExportedMemory>, overwrite: bool| {
+            tokio_stream::iter(vec![ImportChunk {
+                memories,
+                overwrite,
+            }])
+        };
+
+        let mut memories = chunks[0].memories.clone();
+        memories.push(invalid);
+        let response = client
+            .import_memories(import(memories, false))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.imported_count, 1);
+        assert_eq!(response.skipped_count, 0);
+        assert_eq!(response.errors.len(), 1);
+
+        let imported = target_store.retrieve(&originals[1].id).unwrap().unwrap();
+        assert_eq!(imported.content, "Wrote the parser");
+        assert_eq!(imported.created_at, originals[1].created_at);
+
+        // Importing the same ID again skips it unless overwriting
+        let mut changed = chunks[0].memories.clone();
+        changed[0].content = "Rewrote the parser".to_string();
+        let response = client
+            .import_memories(import(changed.clone(), false))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((response.imported_count, response.skipped_count), (0, 1));
+
+        let response = client
+            .import_memories(import(changed, true))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!((response.imported_count, response.skipped_count), (1, 0));
+        let imported = target_store.retrieve(&originals[1].id).unwrap().unwrap();
+        assert_eq!(imported.content, "Rewrote the parser");
+    }
+
+    #[tokio::test]
+    async fn test_export_rejects_unknown_format() {
+        let service = SmartMemoryService::new().unwrap();
+        let result = service
+            .export_memories(Request::new(ExportMemoriesRequest {
+                format: "xml".to_string(),
+                ..Default::default()
+            }))
+            .await;
+        assert_eq!(result.err().unwrap().code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_memory_bank_stats() {
+        let service = SmartMemoryService::new().unwrap();
+        for (category, content) in [
+            (Some("decision"), "use sqlite"),
+            (Some("decision"), "use grpc"),
+            (None, "loose note"),
+        ] {
+            service
+                .memory_store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    category.map(str::to_string),
+                    None,
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+
+        let response = service
+            .get_memory_bank_stats(Request::new(MemoryBankStatsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.total_memories, 3);
+        assert_eq!(response.memories_by_category["decision"], 2);
+        assert_eq!(response.memories_by_category["uncategorized"], 1);
+        assert_eq!(
+            response.tokens_by_category.values().sum::<u32>(),
+            response.total_tokens
+        );
+        let categories: Vec<_> = response
+            .category_stats
+            .iter()
+            .map(|stats| stats.category.as_str())
+            .collect();
+        assert_eq!(categories, ["decision", "uncategorized"]);
+    }
+
+    #[tokio::test]
+    async fn test_memory_bank_context_respects_category_caps() {
+        let service = SmartMemoryService::new().unwrap();
+        for (name, max_tokens) in [("context", 100), ("decision", 50)] {
+            service
+                .memory_bank_config
+                .write()
+                .unwrap()
+                .categories
+                .get_mut(name)
+                .unwrap()
+                .max_tokens = Some(max_tokens);
+        }
+
+        for i in 0..20 {
+            let category = if i % 2 == 0 { "context" } else { "decision" };
+            service
+                .memory_store
+                .store(
+                    format!("{} entry {} ", category, i).repeat(4),
+                    "text/plain".to_string(),
+                    Some(category.to_string()),
+                    None,
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+
+        let response = service
+            .get_memory_bank_context(Request::new(MemoryBankContextRequest {
+                mode: "code".to_string(),
+                max_tokens: 10_000,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let category_tokens = |category: &str| -> usize {
+            response
+                .sources
+                .iter()
+                .filter(|source| source.category == category)
+                .map(|source| {
+                    let id = MemoryId::from(source.id.clone());
+                    let memory = service.memory_store.retrieve(&id).unwrap().unwrap();
+                    memory.token_count.as_usize()
+                })
+                .sum()
+        };
+        assert!(!response.sources.is_empty());
+        assert!(category_tokens("context") <= 100);
+        assert!(category_tokens("decision") <= 50);
+    }
+
+    /// Store a memory matching the query, one matching the `code` mode and an unrelated one
+    fn store_query_samples(service: &SmartMemoryService) -> [String; 3] {
+        let store = |content: &str, metadata: &[(&str, &str)]| {
+            service
+                .memory_store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    Some("context".to_string()),
+                    Some("code".to_string()),
+                    metadata
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .collect(),
+                )
+                .unwrap()
+                .id
+                .as_str()
+                .to_string()
+        };
+        [
+            store("configure the grpc server port", &[]),
+            store(
+                "refactor the storage layer",
+                &[("language", "rust"), ("file", "repository.rs")],
+            ),
+            store("lunch order for friday", &[]),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_get_context_ranks_query_matches_first() {
+        let service = SmartMemoryService::new().unwrap();
+        let [query_match, mode_match, _] = store_query_samples(&service);
+        let context = |query: &str| {
+            service.get_context(Request::new(ContextRequest {
+                mode: "code".to_string(),
+                max_tokens: 1000,
+                query: query.to_string(),
+                ..Default::default()
+            }))
+        };
+
+        let response = context("grpc port").await.unwrap().into_inner();
+        assert_eq!(response.sources.len(), 3);
+        assert_eq!(response.sources[0].source_id, query_match);
+
+        // Without a query the mode's metadata decides
+        let response = context("").await.unwrap().into_inner();
+        assert_eq!(response.sources[0].source_id, mode_match);
+    }
+
+    #[tokio::test]
+    async fn test_memory_bank_context_ranks_query_matches_first() {
+        let service = SmartMemoryService::new().unwrap();
+        let [query_match, _, _] = store_query_samples(&service);
+
+        let response = service
+            .get_memory_bank_context(Request::new(MemoryBankContextRequest {
+                mode: "code".to_string(),
+                max_tokens: 1000,
+                query: "grpc port".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.sources.len(), 3);
+        assert_eq!(response.sources[0].id, query_match);
+    }
+
+    #[tokio::test]
+    async fn test_predict_context_learns_from_mode_switches() {
+        let service = SmartMemoryService::new().unwrap();
+        service
+            .memory_store
+            .store(
+                "breakpoint in the request handler".to_string(),
+                "text/plain".to_string(),
+                None,
+                Some("debug".to_string()),
+                HashMap::new(),
+            )
+            .unwrap();
+        let predict = || {
+            service.predict_context(Request::new(PredictRequest {
+                current_mode: "code".to_string(),
+                user_activity: "request handler".to_string(),
+            }))
+        };
+
+        let before = predict().await.unwrap().into_inner();
+        assert!(before.predicted_context.is_empty());
+
+        service
+            .switch_mode(Request::new(SwitchModeRequest {
+                target_mode: "debug".to_string(),
+                preserve_context: false,
+            }))
+            .await
+            .unwrap();
+
+        let after = predict().await.unwrap().into_inner();
+        assert_eq!(after.predicted_context, "breakpoint in the request handler");
+        assert_eq!(after.confidence, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_switch_mode_preserves_context_snapshot() {
+        let service = SmartMemoryService::new().unwrap();
+        for (mode, content) in [
+            ("code", "the parser lives in src/parse.rs"),
+            ("code", "run the linter before committing"),
+            ("debug", "enable verbose logging with RUST_LOG"),
+        ] {
+            service
+                .memory_store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    Some(mode.to_string()),
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+        let switch = |target_mode: &str| {
+            service.switch_mode(Request::new(SwitchModeRequest {
+                target_mode: target_mode.to_string(),
+                preserve_context: true,
+            }))
+        };
+
+        let response = switch("debug").await.unwrap().into_inner();
+        assert_eq!(response.previous_mode, "code");
+        assert!(response.preserved_tokens > 0);
+
+        let snapshots: Vec<_> = service
+            .memory_store
+            .get_memories_page(0, usize::MAX)
+            .unwrap()
+            .into_iter()
+            .filter(|memory| memory.category.as_deref() == Some(MODE_SNAPSHOT_CATEGORY))
+            .collect();
+        assert_eq!(snapshots.len(), 1);
+        let snapshot = &snapshots[0];
+        assert_eq!(snapshot.metadata["snapshot_mode"], "code");
+        assert_eq!(
+            snapshot.token_count.as_usize(),
+            response.preserved_tokens as usize
+        );
+        assert!(snapshot.content.contains("src/parse.rs"));
+        assert!(!snapshot.content.contains("RUST_LOG"));
+
+        let context_sources = |mode: &str| {
+            service.get_context(Request::new(ContextRequest {
+                mode: mode.to_string(),
+                max_tokens: 10_000,
+                ..Default::default()
+            }))
+        };
+        let code = context_sources("code").await.unwrap().into_inner();
+        assert_eq!(code.sources[0].source_id, snapshot.id.as_str());
+        let debug = context_sources("debug").await.unwrap().into_inner();
+        assert!(debug
+            .sources
+            .iter()
+            .all(|source| source.source_id != snapshot.id.as_str()));
+
+        let response = switch("code").await.unwrap().into_inner();
+        assert_eq!(response.previous_mode, "debug");
+    }
+
+    #[tokio::test]
+    async fn test_switch_mode_rejects_empty_target() {
+        let service = SmartMemoryService::new().unwrap();
+        let status = service
+            .switch_mode(Request::new(SwitchModeRequest {
+                target_mode: String::new(),
+                preserve_context: false,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_mode_requires_memories() {
+        let service = SmartMemoryService::new().unwrap();
+        let status = service
+            .analyze_mode(Request::new(AnalyzeModeRequest {
+                mode: "architect".to_string(),
+                time_window: 0,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_mode_counts_context_retrievals() {
+        let service = SmartMemoryService::new().unwrap();
+        let tokenizer = Tokenizer::new(TokenizerType::Simple).unwrap();
+        let stale: Vec<Memory> = ["error budget policy", "paging escalation steps"]
+            .iter()
+            .map(|content| {
+                let mut memory = Memory::new(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    Some("ops".to_string()),
+                    HashMap::new(),
+                    &tokenizer,
+                );
+                memory.last_accessed = chrono::Utc::now() - chrono::Duration::days(3);
+                memory
+            })
+            .collect();
+        service
+            .memory_store
+            .import_memories(stale, ConflictResolution::KeepExisting)
+            .unwrap();
+        let analyze = || {
+            service.analyze_mode(Request::new(AnalyzeModeRequest {
+                mode: "ops".to_string(),
+                time_window: 24,
+            }))
+        };
+        let metric = |response: &AnalyzeModeResponse, name: &str| {
+            response
+                .metrics
+                .iter()
+                .find(|metric| metric.name == name)
+                .unwrap()
+                .value
+        };
+
+        let before = analyze().await.unwrap().into_inner();
+        assert_eq!(metric(&before, "recent_access_ratio"), 0.0);
+        assert_eq!(metric(&before, "memory_count"), 2.0);
+        assert_eq!(
+            before.average_tokens as f32,
+            metric(&before, "average_tokens")
+        );
+
+        service
+            .get_context(Request::new(ContextRequest {
+                mode: "ops".to_string(),
+                max_tokens: 10_000,
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        let after = analyze().await.unwrap().into_inner();
+        assert_eq!(metric(&after, "recent_access_ratio"), 1.0);
+        assert_eq!(metric(&after, "context_retrievals"), 1.0);
+        assert_eq!(metric(&after, "memory_count"), 2.0);
+        assert!(after.effectiveness_score > before.effectiveness_score);
+        assert!((0.0..=1.0).contains(&after.effectiveness_score));
+
+        // The retrieval record expires and stays out of listings and token totals
+        let logs: Vec<Memory> = service
+            .memory_store
+            .get_memories_page(0, usize::MAX)
+            .unwrap()
+            .into_iter()
+            .filter(|memory| memory.category.as_deref() == Some(ACCESS_LOG_CATEGORY))
+            .collect();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].ttl_seconds, Some(ACCESS_LOG_TTL_SECONDS));
+        let listed = service
+            .list_memories(Request::new(ListMemoriesRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(listed.total_count, 2);
+        let stats = service
+            .get_memory_bank_stats(Request::new(MemoryBankStatsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!stats.tokens_by_category.contains_key(ACCESS_LOG_CATEGORY));
+        assert_eq!(
+            service.memory_store.get_total_tokens().unwrap().as_usize() as u32,
+            stats.total_tokens
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_record_each_call() {
+        let service = SmartMemoryService::new().unwrap();
+        service
+            .store_memory_bank(Request::new(MemoryBankStoreRequest {
+                content: "use feature flags for rollouts".to_string(),
+                category: "decision".to_string(),
+                mode: "architect".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        service
+            .get_context(Request::new(ContextRequest {
+                mode: "architect".to_string(),
+                max_tokens: 1000,
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        let usage = service
+            .track_usage(Request::new(UsageRequest {
+                mode: "architect".to_string(),
+                action: "completion".to_string(),
+                metadata: HashMap::from([("tokens".to_string(), "40".to_string())]),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(usage.recorded);
+        assert!(usage.daily_tokens >= 40);
+
+        let response = service
+            .get_metrics(Request::new(MetricsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        let value = |name: &str| {
+            response
+                .metrics
+                .iter()
+                .find(|metric| metric.name == name)
+                .map(|metric| metric.value)
+        };
+        assert_eq!(value("calls.store_memory_bank"), Some(1.0));
+        assert_eq!(value("calls.get_context"), Some(1.0));
+        assert_eq!(value("calls.usage.completion"), Some(1.0));
+        assert!(value("latency_p99_ms").is_some());
+
+        let trend = response
+            .trends
+            .iter()
+            .find(|trend| trend.metric_name == "tokens.architect")
+            .unwrap();
+        assert!(trend.values.iter().sum::<f32>() >= 40.0);
+        assert_eq!(trend.values.len(), trend.timestamps.len());
+
+        let latency_only = service
+            .get_metrics(Request::new(MetricsRequest {
+                time_range: 1,
+                metric_types: vec!["latency".to_string()],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(latency_only.metrics.len(), 3);
+        assert!(latency_only.trends.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_context_stream_matches_get_context() {
+        let service = SmartMemoryService::new().unwrap();
+        for i in 0..5 {
+            service
+                .memory_store
+                .store(
+                    format!("streamed note number {}", i),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+        let request = || ContextRequest {
+            mode: "code".to_string(),
+            max_tokens: 10_000,
+            ..Default::default()
+        };
+
+        let whole = service
+            .get_context(Request::new(request()))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut stream = service
+            .get_context_stream(Request::new(request()))
+            .await
+            .unwrap()
+            .into_inner();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            chunks.push(chunk.unwrap());
+        }
+
+        assert!(chunks.last().unwrap().is_last);
+        assert!(chunks.iter().rev().skip(1).all(|chunk| !chunk.is_last));
+        let content: String = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(content, whole.context);
+        let ids: Vec<_> = chunks
+            .iter()
+            .flat_map(|chunk| &chunk.sources)
+            .map(|source| source.source_id.clone())
+            .collect();
+        let expected: Vec<_> = whole.sources.iter().map(|s| s.source_id.clone()).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn test_chunk_context_respects_token_limit() {
+        let tokenizer = Tokenizer::new(TokenizerType::Simple).unwrap();
+        let scored = |content: &str| ScoredMemory {
+            memory: Memory::new(
+                content.to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+                &tokenizer,
+            ),
+            score: crate::storage::RelevanceScore::new(1.0),
+        };
+        let memories = vec![
+            scored("one two three"),
+            scored("four five"),
+            scored(&"large ".repeat(20)),
+            scored("six"),
+        ];
+        let limit =
+            memories[0].memory.token_count.as_usize() + memories[1].memory.token_count.as_usize();
+
+        let chunks = chunk_context(&memories, limit);
+
+        let sizes: Vec<_> = chunks.iter().map(|chunk| chunk.sources.len()).collect();
+        assert_eq!(sizes, vec![2, 1, 1]);
+        let indexes: Vec<_> = chunks.iter().map(|chunk| chunk.chunk_index).collect();
+        assert_eq!(indexes, vec![0, 1, 2]);
+        assert!(chunks[2].is_last && !chunks[1].is_last);
+
+        let empty = chunk_context(&[], limit);
+        assert_eq!(empty.len(), 1);
+        assert!(empty[0].is_last && empty[0].sources.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_memory() {
+        let service = SmartMemoryService::new().unwrap();
+        let memory = service
+            .memory_store
+            .store(
+                "obsolete note".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+            .unwrap();
+        let delete = |memory_id: &str| {
+            service.delete_memory(Request::new(DeleteMemoryRequest {
+                memory_id: memory_id.to_string(),
+            }))
+        };
+
+        let response = delete(memory.id.as_str()).await.unwrap().into_inner();
+        assert!(response.success);
+        assert_eq!(response.freed_tokens, memory.token_count.as_usize() as u32);
+        assert!(service.memory_store.retrieve(&memory.id).unwrap().is_none());
+
+        let status = delete(memory.id.as_str()).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_deletes_dropped_at_their_deadline_still_announce_the_change() {
+        let service = SmartMemoryService::new().unwrap();
+        let memory = service
+            .memory_store
+            .store(
+                "note deleted as the deadline passes".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+            .unwrap();
+        let mut events = service.events.subscribe();
+
+        // The call is dropped while its delete waits on the blocking pool
+        let delete = service.delete_memory(Request::new(DeleteMemoryRequest {
+            memory_id: memory.id.as_str().to_string(),
+        }));
+        let _ = tokio::time::timeout(Duration::ZERO, delete).await;
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.event_type, EventType::Deleted);
+        assert_eq!(event.memory_id, memory.id.as_str());
+        assert!(service.memory_store.retrieve(&memory.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_new_with_pool_uses_sqlite_and_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("memories.db");
+        let config_path = dir.path().join("config.toml");
+        MemoryBankConfig {
+            max_pinned: 3,
+            ..Default::default()
+        }
+        .save(&config_path)
+        .unwrap();
+
+        let service = SmartMemoryService::new_with_pool(&db_path, &config_path, 2).unwrap();
+        assert_eq!(service.config().max_pinned, 3);
+        let ids = store_all(&service, &["persisted note"]);
+        drop(service);
+
+        // A missing config falls back to the defaults
+        let service =
+            SmartMemoryService::new_with_pool(&db_path, &dir.path().join("missing.json"), 1)
+                .unwrap();
+        assert_eq!(
+            service.config().max_pinned,
+            MemoryBankConfig::default().max_pinned
+        );
+        let memory = service
+            .memory_store
+            .retrieve(&MemoryId::from(ids[0].clone()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(memory.content, "persisted note");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_new_with_config_rejects_invalid_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.json");
+        let mut config = MemoryBankConfig::default();
+        config.relevance.threshold = 1.5;
+        config.categories.get_mut("decision").unwrap().max_tokens = Some(0);
+        config.save(&config_path).unwrap();
+
+        let error =
+            SmartMemoryService::new_with_config(&dir.path().join("memories.db"), &config_path)
+                .err()
+                .unwrap()
+                .to_string();
+        assert!(error.contains("relevance.threshold 1.5"));
+        assert!(error.contains("\"decision\" has max_tokens 0"));
+    }
+
+    #[tokio::test]
+    async fn test_context_cost_estimate() {
+        let service = SmartMemoryService::new().unwrap();
+        store_all(&service, &["one two three four", "five six"]);
+        let request = |include_cost_estimate: bool, model: Option<&str>| {
+            let mut request = Request::new(ContextRequest {
+                mode: "code".to_string(),
+                max_tokens: 1000,
+                include_cost_estimate,
+                ..Default::default()
+            });
+            if let Some(model) = model {
+                request
+                    .metadata_mut()
+                    .insert(MODEL_METADATA_KEY, model.parse().unwrap());
+            }
+            request
+        };
+
+        let response = service
+            .get_context(request(true, Some("gpt-4")))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.token_count, 6);
+        assert!((response.estimated_cost_usd - 6.0 / 1000.0 * 0.03).abs() < 1e-12);
+
+        let response = service
+            .get_context(request(false, Some("gpt-4")))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.estimated_cost_usd, 0.0);
+
+        for model in [None, Some("unknown-model")] {
+            let status = service.get_context(request(true, model)).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compact_in_memory_store_saves_nothing() {
+        let service = SmartMemoryService::new().unwrap();
+        store_all(&service, &["first note", "second note"]);
+
+        let response = service
+            .compact(Request::new(CompactRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.bytes_saved, 0);
+    }
+
+    #[tokio::test]
+    async fn test_vacuum_frees_pages_of_deleted_memories() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("memories.db");
+        let service =
+            SmartMemoryService::new_with_pool(&db_path, &dir.path().join("config.toml"), 1)
+                .unwrap();
+        let content = "padding ".repeat(2000);
+        for id in store_all(&service, &[content.as_str(); 20]) {
+            service.memory_store.delete(&MemoryId::from(id)).unwrap();
+        }
+
+        let response = service
+            .vacuum(Request::new(VacuumRequest { pages: 0 }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.pages_freed > 0);
+        assert_eq!(service.memory_store.vacuum(0).unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_restore_latest_requires_confirmation_token() {
+        let service = SmartMemoryService::new().unwrap();
+        let restore = |token: &str| {
+            service.restore_latest(Request::new(RestoreLatestRequest {
+                confirmation_token: token.to_string(),
+            }))
+        };
+
+        let status = restore("").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+
+        *service.pending_restore.lock().unwrap() = Some(PendingRestore {
+            token: "expected-token".to_string(),
+            backup_name: "backup_1700000000000.db".to_string(),
+            issued: Instant::now(),
+        });
+        let status = restore("guessed-token").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        assert!(service.pending_restore.lock().unwrap().is_some());
+
+        // The right token is used up even though in-memory storage cannot be restored
+        let status = restore("expected-token").await.unwrap_err();
+        assert!(status.message().contains("SQLite"), "{}", status.message());
+        assert!(service.pending_restore.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recalculate_tokens_recounts_stale_memories() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("memories.db");
+
+        // Store under a different tokenizer so the service's counts are stale
+        let store =
+            MemoryStore::new_sqlite(&db_path, Tokenizer::approximate(TokenizerType::Cl100k))
+                .unwrap();
+        for content in [
+            "internationalization and localization",
+            "serialization benchmarks",
+        ] {
+            store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+        drop(store);
+
+        let service =
+            SmartMemoryService::new_with_pool(&db_path, &dir.path().join("config.toml"), 1)
+                .unwrap();
+        let recalculate = || service.recalculate_tokens(Request::new(RecalculateTokensRequest {}));
+
+        assert_eq!(recalculate().await.unwrap().into_inner().updated_count, 2);
+        assert_eq!(
+            service.memory_store.get_total_tokens().unwrap().as_usize(),
+            5
+        );
+        assert_eq!(recalculate().await.unwrap().into_inner().updated_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_store_memory_with_preferred_id() {
+        let service = SmartMemoryService::new().unwrap();
+        let store = || {
+            service.store_memory(Request::new(StoreRequest {
+                content: "Use hexagonal architecture".to_string(),
+                content_type: "text/plain".to_string(),
+                preferred_id: "architecture".to_string(),
+                ..Default::default()
+            }))
+        };
+
+        assert_eq!(
+            store().await.unwrap().into_inner().memory_id,
+            "architecture"
+        );
+        let fallback = store().await.unwrap().into_inner().memory_id;
+        assert!(fallback.starts_with("mem_architecture-"));
+    }
+
+    #[tokio::test]
+    async fn test_bulk_store_validates_tags_and_announces_every_memory() {
+        let service = SmartMemoryService::new().unwrap();
+        let mut events = service.events.subscribe();
+        let note = |content: &str, tags: &[&str]| StoreRequest {
+            content: content.to_string(),
+            content_type: "text/plain".to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        };
+
+        let status = service
+            .bulk_store(Request::new(BulkStoreRequest {
+                items: vec![note("first note", &[]), note(" ", &[])],
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(service.memory_store.get_stats().unwrap().total_count, 0);
+
+        let response = service
+            .bulk_store(Request::new(BulkStoreRequest {
+                items: vec![note("first note", &["design"]), note("second note", &[])],
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let first = MemoryId::from(response.memories[0].memory_id.as_str());
+        assert_eq!(
+            service.memory_store.retrieve(&first).unwrap().unwrap().tags,
+            vec!["design"]
+        );
+        for stored in &response.memories {
+            let event = events.try_recv().unwrap();
+            assert_eq!(event.event_type, EventType::Stored);
+            assert_eq!(event.memory_id, stored.memory_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_requests_are_rejected() {
+        let service = SmartMemoryService::new().unwrap();
+
+        let status = service
+            .store_memory(Request::new(StoreRequest {
+                content: String::new(),
+                content_type: "text/plain".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("content"));
+
+        let status = service
+            .get_context(Request::new(ContextRequest {
+                mode: "code".to_string(),
+                max_tokens: 1000,
+                relevance_threshold: 1.5,
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("relevance_threshold"));
+
+        let status = service
+            .store_memory_bank(Request::new(MemoryBankStoreRequest {
+                content: "entry".to_string(),
+                category: "unknown".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(service.memory_store.count().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_snapshot_discards_speculative_stores() {
+        let service = SmartMemoryService::new().unwrap();
+        store_all(&service, &["one", "two", "three", "four", "five"]);
+
+        let snapshot_id = service
+            .create_snapshot(Request::new(CreateSnapshotRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .snapshot_id;
+        store_all(&service, &["six", "seven", "eight"]);
+        assert_eq!(service.memory_store.count().unwrap(), 8);
+
+        service
+            .rollback_snapshot(Request::new(RollbackSnapshotRequest {
+                snapshot_id: snapshot_id.clone(),
+            }))
+            .await
+            .unwrap();
+        assert_eq!(service.memory_store.count().unwrap(), 5);
+
+        let status = service
+            .commit_snapshot(Request::new(CommitSnapshotRequest { snapshot_id }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_get_history_and_rollback_memory() {
+        let service = SmartMemoryService::new().unwrap();
+        let memory_id = service
+            .memory_store
+            .store(
+                "version one".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+            .unwrap()
+            .id;
+        for content in ["version two", "version three"] {
+            service
+                .update_memory(Request::new(UpdateMemoryRequest {
+                    memory_id: memory_id.as_str().to_string(),
+                    content: content.to_string(),
+                    metadata: HashMap::new(),
+                    replace_metadata: false,
+                }))
+                .await
+                .unwrap();
+        }
+
+        let versions = service
+            .get_history(Request::new(GetHistoryRequest {
+                memory_id: memory_id.as_str().to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .versions;
+        let contents: Vec<&str> = versions.iter().map(|v| v.content.as_str()).collect();
+        assert_eq!(contents, vec!["version one", "version two"]);
+        assert_eq!(versions[1].metadata["version"], "2");
+
+        // Replaced content stays out of context
+        let context = service
+            .get_context(Request::new(ContextRequest {
+                mode: "code".to_string(),
+                max_tokens: 1000,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .context;
+        assert!(context.contains("version three"));
+        assert!(!context.contains("version one"));
+
+        service
+            .rollback_memory(Request::new(RollbackRequest {
+                memory_id: memory_id.as_str().to_string(),
+                version: 1,
+            }))
+            .await
+            .unwrap();
+        assert_eq!(
+            service
+                .memory_store
+                .retrieve(&memory_id)
+                .unwrap()
+                .unwrap()
+                .content,
+            "version one"
+        );
+
+        let status = service
+            .rollback_memory(Request::new(RollbackRequest {
+                memory_id: memory_id.as_str().to_string(),
+                version: 7,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        let status = service
+            .get_history(Request::new(GetHistoryRequest {
+                memory_id: "mem_missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_merge_memories_rpc() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(&service, &["alpha", "beta", "gamma"]);
+        let merge = |memory_ids: Vec<String>| {
+            service.merge_memories(Request::new(MergeMemoriesRequest {
+                memory_ids,
+                separator: String::new(),
+                target_category: "notes".to_string(),
+            }))
+        };
+
+        let status = merge(vec![ids[0].clone(), "mem_missing".to_string()])
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+        let status = merge(vec![ids[0].clone()]).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert_eq!(service.memory_store.count().unwrap(), 3);
+
+        let response = merge(ids.clone()).await.unwrap().into_inner();
+        let merged = service
+            .memory_store
+            .retrieve(&MemoryId::from(response.merged_memory_id))
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.content, "alpha\n\nbeta\n\ngamma");
+        assert_eq!(merged.category.as_deref(), Some("notes"));
+        assert_eq!(
+            response.total_tokens as usize,
+            merged.token_count.as_usize()
+        );
+        assert_eq!(service.memory_store.count().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_cancelled_context_selection_leaves_session_untouched() {
+        let service = SmartMemoryService::new().unwrap();
+        store_all(&service, &["a note about cancelled requests"]);
+        let request = Request::new(ContextRequest {
+            query: "cancelled".to_string(),
+            mode: "code".to_string(),
+            max_tokens: 1000,
+            session_id: "late-session".to_string(),
+            ..Default::default()
+        });
+        let call = service.track_call("get_context", &request);
+
+        let cancelled = CancellationToken::new();
+        cancelled.cancel();
+        let status = service
+            .select_context(request.get_ref(), &call, &cancelled)
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Cancelled);
+        assert_eq!(service.context_windows.budget("late-session", 1000), 1000);
+
+        service
+            .select_context(request.get_ref(), &call, &CancellationToken::new())
+            .unwrap();
+        assert!(service.context_windows.budget("late-session", 1000) < 1000);
+    }
+
+    #[tokio::test]
+    async fn test_find_duplicates_rpc() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(
+            &service,
+            &[
+                "deploys run from the main branch after every merge and tag a release",
+                "deploys run from the main branch after every merge and tag a new release",
+                "the parser rejects queries nested more than sixty four levels deep",
+            ],
+        );
+        let find = |threshold: f32, dry_run: bool| {
+            service.find_duplicates(Request::new(FindDuplicatesRequest { threshold, dry_run }))
+        };
+
+        let status = find(1.5, true).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let response = find(0.0, true).await.unwrap().into_inner();
+        assert_eq!(response.groups.len(), 1);
+        assert_eq!(response.groups[0].primary_id, ids[1]);
+        assert_eq!(response.groups[0].duplicate_ids, vec![ids[0].clone()]);
+        assert_eq!(response.groups[0].similarity, 13.0 / 14.0);
+        assert!(response.merged_memory_ids.is_empty());
+        assert_eq!(service.memory_store.count().unwrap(), 3);
+
+        // Nothing reaches a stricter threshold
+        let response = find(0.95, false).await.unwrap().into_inner();
+        assert!(response.groups.is_empty());
+        assert_eq!(service.memory_store.count().unwrap(), 3);
+
+        let response = find(0.9, false).await.unwrap().into_inner();
+        assert_eq!(response.merged_memory_ids, vec![ids[1].clone()]);
+        let merged = service
+            .memory_store
+            .retrieve(&MemoryId::from(response.merged_memory_ids[0].as_str()))
+            .unwrap()
+            .unwrap();
+        assert!(merged.content.starts_with(
+            "deploys run from the main branch after every merge and tag a new release\n\n"
+        ));
+        assert_eq!(service.memory_store.count().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_merge_categories_moves_memories_and_config() {
+        let service = SmartMemoryService::new().unwrap();
+        for category in ["ctx", "ctx", "context"] {
+            service
+                .memory_store
+                .store(
+                    format!("{} note", category),
+                    "text/plain".to_string(),
+                    Some(category.to_string()),
+                    None,
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+        let context = service.config().categories["context"].clone();
+        let context_max_tokens = service.config().get_max_tokens("context").as_usize();
+        service
+            .memory_bank_config
+            .write()
+            .unwrap()
+            .categories
+            .insert("ctx".to_string(), context.clone());
+
+        let response = service
+            .merge_categories(Request::new(MergeCategoriesRequest {
+                source_category: "ctx".to_string(),
+                target_category: "context".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.updated_count, 2);
+
+        let stats = service
+            .get_memory_bank_stats(Request::new(MemoryBankStatsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.memories_by_category.get("context"), Some(&3));
+        assert!(!stats.memories_by_category.contains_key("ctx"));
+
+        let config = service.config().clone();
+        assert!(!config.categories.contains_key("ctx"));
+        assert_eq!(
+            config.get_max_tokens("context").as_usize(),
+            (2 * context_max_tokens).min(config.token_budget.total)
+        );
+
+        let response = service
+            .rename_category(Request::new(RenameCategoryRequest {
+                old_category: "context".to_string(),
+                new_category: "background".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.updated_count, 3);
+        assert!(service.config().categories.contains_key("background"));
+
+        let status = service
+            .rename_category(Request::new(RenameCategoryRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_pin_memory_respects_max_pinned() {
+        let service = SmartMemoryService::new().unwrap();
+        service.memory_bank_config.write().unwrap().max_pinned = 1;
+        let ids = store_all(&service, &["first note", "second note"]);
+        let pin = |memory_id: &str| {
+            service.pin_memory(Request::new(PinMemoryRequest {
+                memory_id: memory_id.to_string(),
+            }))
+        };
+
+        assert!(pin(&ids[0]).await.unwrap().into_inner().pinned);
+        // Pinning the same memory again stays within the limit
+        assert!(pin(&ids[0]).await.is_ok());
+        let status = pin(&ids[1]).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        let stats = service
+            .get_memory_bank_stats(Request::new(MemoryBankStatsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(stats.pinned_count, 1);
+
+        let response = service
+            .unpin_memory(Request::new(UnpinMemoryRequest {
+                memory_id: ids[0].clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!response.pinned);
+        assert!(pin(&ids[1]).await.is_ok());
+
+        let status = pin("mem_missing").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_link_memories_and_linked_context() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(&service, &["decision note", "context note"]);
+        let link = |source: &str, target: &str| {
+            service.link_memories(Request::new(LinkMemoriesRequest {
+                source_id: source.to_string(),
+                target_id: target.to_string(),
+                relation_type: "references".to_string(),
+            }))
+        };
+
+        assert!(link(&ids[0], &ids[1]).await.unwrap().into_inner().success);
+        let status = link(&ids[1], &ids[0]).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+        let status = link(&ids[0], "mem_missing").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let linked = service
+            .get_linked(Request::new(GetLinkedRequest {
+                memory_id: ids[0].clone(),
+                relation_type: String::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let linked_ids: Vec<_> = linked.memories.into_iter().map(|m| m.memory_id).collect();
+        assert_eq!(linked_ids, vec![ids[1].clone()]);
+
+        // Only the decision is on the first page; its link brings in the context note
+        let context_sources = |include_linked| {
+            let service = &service;
+            async move {
+                let response = service
+                    .get_context(Request::new(ContextRequest {
+                        mode: "code".to_string(),
+                        max_tokens: 1000,
+                        page: 0,
+                        page_size: 1,
+                        include_linked,
+                        ..Default::default()
+                    }))
+                    .await
+                    .unwrap()
+                    .into_inner();
+                response
+                    .sources
+                    .into_iter()
+                    .map(|source| source.source_id)
+                    .collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(context_sources(false).await, vec![ids[0].clone()]);
+        assert_eq!(context_sources(true).await, ids);
+    }
+
+    #[tokio::test]
+    async fn test_search_memories_counts_matches_past_the_limit() {
+        let service = SmartMemoryService::new().unwrap();
+        store_all(
+            &service,
+            &[
+                "retry uploads with backoff",
+                "retry downloads once",
+                "retry nothing else",
+                "cache tokens per model",
+            ],
+        );
+
+        let response = service
+            .search_memories(Request::new(SearchMemoriesRequest {
+                query: "retry".to_string(),
+                limit: 2,
+                mode_filter: String::new(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.memories.len(), 2);
+        assert_eq!(response.total_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_list_memories() {
+        let service = SmartMemoryService::new().unwrap();
+        for (content, category) in [
+            ("first context", "context"),
+            ("a decision", "decision"),
+            ("second context", "context"),
+            ("third context", "context"),
+        ] {
+            service
+                .memory_store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    Some(category.to_string()),
+                    None,
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+        let list = |request: ListMemoriesRequest| service.list_memories(Request::new(request));
+
+        let response = list(ListMemoriesRequest {
+            category: "context".to_string(),
+            order: "desc".to_string(),
+            page: 0,
+            page_size: 2,
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+        let contents: Vec<_> = response
+            .memories
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["third context", "second context"]);
+        assert_eq!(response.total_count, 3);
+        assert_eq!((response.page, response.page_size), (0, 2));
+
+        let response = list(ListMemoriesRequest {
+            content_contains: "decision".to_string(),
+            to_date: chrono::Utc::now().to_rfc3339(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+        assert_eq!(response.total_count, 1);
+        assert_eq!(response.page_size, DEFAULT_LIST_PAGE_SIZE as u32);
+
+        // The query narrows the explicit filter fields
+        let response = list(ListMemoriesRequest {
+            category: "context".to_string(),
+            query: r#"content_contains:"first" OR NOT content_contains:"context""#.to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+        let contents: Vec<_> = response
+            .memories
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["first context"]);
+
+        let status = list(ListMemoriesRequest {
+            query: "category:context AND".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("at position 20"));
+
+        for invalid in [
+            ListMemoriesRequest {
+                sort_by: "content".to_string(),
+                ..Default::default()
+            },
+            ListMemoriesRequest {
+                order: "sideways".to_string(),
+                ..Default::default()
+            },
+            ListMemoriesRequest {
+                from_date: "yesterday".to_string(),
+                ..Default::default()
+            },
+        ] {
+            let status = list(invalid).await.unwrap_err();
+            assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_tags() {
+        let service = SmartMemoryService::new().unwrap();
+        let mut ids = Vec::new();
+        for tags in [vec!["rust", "grpc"], vec!["rust"], vec!["python"]] {
+            let response = service
+                .store_memory(Request::new(StoreRequest {
+                    content: "tagged note".to_string(),
+                    content_type: "text/plain".to_string(),
+                    tags: tags.into_iter().map(String::from).collect(),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            ids.push(response.memory_id);
+        }
+        let filter = |tags: &[&str], match_all| {
+            service.filter_by_tags(Request::new(FilterByTagsRequest {
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                match_all,
+            }))
+        };
+        let ids_of = |response: FilterByTagsResponse| -> Vec<String> {
+            response.memories.into_iter().map(|m| m.memory_id).collect()
+        };
+
+        let all = filter(&["rust", "grpc"], true).await.unwrap().into_inner();
+        assert_eq!(all.total_count, 1);
+        assert_eq!(all.memories[0].tags, vec!["grpc", "rust"]);
+        assert_eq!(ids_of(all), ids[..1]);
+
+        let any = filter(&["grpc", "python"], false)
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(ids_of(any), vec![ids[0].clone(), ids[2].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_get_hot_memories() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(
+            &service,
+            &["opened once", "opened five times", "never opened"],
+        );
+        let retrieve = |memory_id: &str| {
+            service.retrieve_memory(Request::new(RetrieveRequest {
+                memory_id: memory_id.to_string(),
+                include_metadata: false,
+            }))
+        };
+
+        retrieve(&ids[0]).await.unwrap();
+        let mut access_counts = Vec::new();
+        for _ in 0..5 {
+            access_counts.push(retrieve(&ids[1]).await.unwrap().into_inner().access_count);
+        }
+        assert_eq!(access_counts, vec![1, 2, 3, 4, 5]);
+
+        let hot = service
+            .get_hot_memories(Request::new(GetHotMemoriesRequest::default()))
+            .await
+            .unwrap()
+            .into_inner();
+        let ranked: Vec<(String, u64)> = hot
+            .memories
+            .into_iter()
+            .map(|memory| (memory.memory_id, memory.access_count))
+            .collect();
+        assert_eq!(ranked, vec![(ids[1].clone(), 5), (ids[0].clone(), 1)]);
+
+        assert_eq!(service.warm_caches().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_related() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(
+            &service,
+            &[
+                "deploy the grpc server behind the proxy",
+                "grpc server proxy deploy checklist",
+                "quarterly budget review",
+            ],
+        );
+        let related = |memory_id: &str| {
+            service.get_related(Request::new(GetRelatedRequest {
+                memory_id: memory_id.to_string(),
+                limit: 1,
+            }))
+        };
+
+        let response = related(&ids[0]).await.unwrap().into_inner();
+        assert_eq!(response.memories.len(), 1);
+        let memory = response.memories[0].memory.as_ref().unwrap();
+        assert_eq!(memory.memory_id, ids[1]);
+        assert!(response.memories[0].relevance > 0.0);
+
+        let status = related("mem_missing").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_diff_memories() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(
+            &service,
+            &[
+                "Wrote the parser\nFixing the lexer",
+                "Wrote the parser\nFixed the lexer\nNext: docs",
+            ],
+        );
+        let diff = |memory_id_a: &str, memory_id_b: &str| {
+            service.diff_memories(Request::new(DiffMemoriesRequest {
+                memory_id_a: memory_id_a.to_string(),
+                memory_id_b: memory_id_b.to_string(),
+            }))
+        };
+
+        let response = diff(&ids[0], &ids[1]).await.unwrap().into_inner();
+        assert_eq!(response.removed_lines, ["Fixing the lexer"]);
+        assert_eq!(response.added_lines, ["Fixed the lexer", "Next: docs"]);
+        assert_eq!(response.token_delta, 2);
+
+        let status = diff(&ids[0], "mem_missing").await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_strict_isolation_keeps_modes_apart() {
+        let service = SmartMemoryService::new().unwrap();
+        let store = |content: &str, mode: Option<&str>, tags: &[&str]| {
+            service
+                .memory_store
+                .store_with_options(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    mode.map(str::to_string),
+                    HashMap::new(),
+                    StoreOptions {
+                        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+                .id
+        };
+        let code = store("refactor the parser module", Some("code"), &[]);
+        let architect = store("split the parser into a service", Some("architect"), &[]);
+        let shared = store("parser conventions for everyone", None, &[]);
+        let glossary = store("parser glossary", Some("ask"), &["glossary"]);
+        service
+            .memory_store
+            .link(&architect, &code, "references")
+            .unwrap();
+
+        let context = |mode: &str, isolation_mode: IsolationModeRequest, tags: &[&str]| {
+            let service = &service;
+            let request = ContextRequest {
+                mode: mode.to_string(),
+                max_tokens: 1000,
+                include_linked: true,
+                isolation_mode: isolation_mode as i32,
+                cross_mode_tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Default::default()
+            };
+            async move {
+                let response = service
+                    .get_context(Request::new(request))
+                    .await
+                    .unwrap()
+                    .into_inner();
+                response
+                    .sources
+                    .into_iter()
+                    .map(|source| MemoryId::from(source.source_id))
+                    .collect::<HashSet<_>>()
+            }
+        };
+
+        // Strict isolation is the default; links do not reach other modes either
+        let ids = context("architect", IsolationModeRequest::Configured, &[]).await;
+        assert_eq!(ids, HashSet::from([architect.clone(), shared.clone()]));
+        let ids = context("code", IsolationModeRequest::Strict, &[]).await;
+        assert_eq!(ids, HashSet::from([code.clone(), shared.clone()]));
+
+        // Cross-mode tags let specific memories through
+        let ids = context("code", IsolationModeRequest::Strict, &[" glossary "]).await;
+        assert!(ids.contains(&glossary));
+        assert!(!ids.contains(&architect));
+
+        // Shared isolation, requested or configured, draws from every mode
+        let ids = context("code", IsolationModeRequest::Shared, &[]).await;
+        assert_eq!(ids.len(), 4);
+        service
+            .memory_bank_config
+            .write()
+            .unwrap()
+            .default_isolation = IsolationMode::Shared;
+        let ids = context("architect", IsolationModeRequest::Configured, &[]).await;
+        assert_eq!(ids.len(), 4);
+    }
+
+    /// Store memories with the given contents, returning their IDs
+    fn store_all(service: &SmartMemoryService, contents: &[&str]) -> Vec<String> {
+        contents
+            .iter()
+            .map(|content| {
+                service
+                    .memory_store
+                    .store(
+                        content.to_string(),
+                        "text/plain".to_string(),
+                        None,
+                        None,
+                        HashMap::new(),
+                    )
+                    .unwrap()
+                    .id
+                    .as_str()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// Run `OptimizeMemory` with the deduplicate strategy
+    async fn deduplicate(
+        service: &SmartMemoryService,
+        memory_ids: Vec<String>,
+    ) -> OptimizeResponse {
+        service
+            .optimize_memory(Request::new(OptimizeRequest {
+                memory_ids,
+                strategy: OptimizationStrategy::Deduplicate as i32,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_optimize_memory_merges_identical_memories() {
+        let service = SmartMemoryService::new().unwrap();
+        let content = "the deploy script lives in tools";
+        let ids = store_all(&service, &[content, content, content]);
+        let tokens = service.memory_store.get_total_tokens().unwrap();
+
+        let response = deduplicate(&service, ids.clone()).await;
+
+        assert_eq!(response.optimized_ids.len(), 1);
+        let kept_id = &response.optimized_ids[0];
+        assert!(ids.contains(kept_id));
+        assert_eq!(
+            response.tokens_saved as usize,
+            tokens.as_usize() - service.memory_store.get_total_tokens().unwrap().as_usize()
+        );
+        assert!(response.tokens_saved > 0);
+
+        let kept = service
+            .memory_store
+            .retrieve(&MemoryId::from(kept_id.as_str()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(kept.content, content);
+        assert_eq!(kept.metadata.get("duplicate_count").unwrap(), "2");
+        for id in ids.iter().filter(|id| *id != kept_id) {
+            assert!(service
+                .memory_store
+                .retrieve(&MemoryId::from(id.as_str()))
+                .unwrap()
+                .is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_other_strategies_are_unimplemented() {
+        let service = SmartMemoryService::new().unwrap();
+        let content = "the deploy script lives in tools";
+        let ids = store_all(&service, &[content, content]);
+
+        for strategy in [
+            OptimizationStrategy::Balanced,
+            OptimizationStrategy::Aggressive,
+            OptimizationStrategy::Conservative,
+        ] {
+            let status = service
+                .optimize_memory(Request::new(OptimizeRequest {
+                    memory_ids: ids.clone(),
+                    strategy: strategy as i32,
+                }))
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), tonic::Code::Unimplemented);
+        }
+        assert_eq!(service.memory_store.count().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_optimize_memory_keeps_different_memories() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(&service, &["alpha beta gamma", "delta epsilon zeta"]);
+
+        let response = deduplicate(&service, ids.clone()).await;
+
+        assert_eq!(response.tokens_saved, 0);
+        assert!(response.optimized_ids.is_empty());
+        for id in &ids {
+            assert!(service
+                .memory_store
+                .retrieve(&MemoryId::from(id.as_str()))
+                .unwrap()
+                .is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_optimize_memory_single_memory() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(&service, &["only memory"]);
+
+        let response = deduplicate(&service, ids).await;
+
+        assert_eq!(response.tokens_saved, 0);
+        assert_eq!(response.optimization_ratio, 0.0);
+        assert!(response.optimized_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_tracks_tokens_across_context_calls() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(
+            &service,
+            &["alpha notes", "bravo notes", "charlie notes", "delta notes"],
+        );
+        let memory_tokens = service
+            .memory_store
+            .retrieve(&MemoryId::from(ids[0].as_str()))
+            .unwrap()
+            .unwrap()
+            .token_count
+            .as_usize() as u32;
+        let request = |session_id: &str, exclude_memory_ids: Vec<String>| {
+            Request::new(ContextRequest {
+                mode: "code".to_string(),
+                max_tokens: memory_tokens * 3,
+                exclude_memory_ids,
+                session_id: session_id.to_string(),
+                ..Default::default()
+            })
+        };
+
+        let first = service
+            .get_context(request("session", Vec::new()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(first.token_count, memory_tokens * 3);
+        let seen: Vec<String> = first.sources.iter().map(|s| s.source_id.clone()).collect();
+
+        // The window is already full, so the remaining memory no longer fits
+        let second = service
+            .get_context(request("session", seen.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(second.token_count, 0);
+        let window = service.context_windows.get("session").unwrap();
+        assert_eq!(window.used_tokens, memory_tokens * 3);
+        assert_eq!(window.remaining(), 0);
+
+        // Without a session, or after a reset, the full budget is available again
+        let unsessioned = service
+            .get_context(request("", seen.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(unsessioned.token_count, memory_tokens);
+
+        let reset = |session_id: &str| {
+            service.reset_session(Request::new(ResetSessionRequest {
+                session_id: session_id.to_string(),
+            }))
+        };
+        assert!(reset("session").await.unwrap().into_inner().existed);
+        assert!(!reset("session").await.unwrap().into_inner().existed);
+        assert_eq!(
+            reset("").await.unwrap_err().code(),
+            tonic::Code::InvalidArgument
+        );
+
+        let after_reset = service
+            .get_context(request("session", seen))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(after_reset.token_count, memory_tokens);
+        assert_eq!(
+            service.context_windows.get("session").unwrap().used_tokens,
+            memory_tokens
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_context_skips_excluded_memories() {
+        let service = SmartMemoryService::new().unwrap();
+        let mut ids = Vec::new();
+        for content in ["first memory", "second memory", "third memory"] {
+            let memory = service
+                .memory_store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    HashMap::new(),
+                )
+                .unwrap();
+            ids.push(memory.id.as_str().to_string());
+        }
+
+        let response = service
+            .get_context(Request::new(ContextRequest {
+                mode: "code".to_string(),
+                max_tokens: 1000,
+                relevance_threshold: 0.0,
+                exclude_memory_ids: vec![ids[0].clone(), "mem_unknown".to_string()],
+                page: 0,
+                page_size: 0,
+                query: String::new(),
+                include_linked: false,
+                include_cost_estimate: false,
+                isolation_mode: IsolationModeRequest::Configured as i32,
+                cross_mode_tags: Vec::new(),
+                session_id: String::new(),
+                explain: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.excluded_count, 1);
+        assert_eq!(response.sources.len(), 2);
+        assert!(response
+            .sources
+            .iter()
+            .all(|source| source.source_id != ids[0]));
+    }
+
+    #[tokio::test]
+    async fn test_get_context_explains_left_out_memories() {
+        let service = SmartMemoryService::new().unwrap();
+        let ids = store_all(&service, &["alpha beta", "gamma delta", "epsilon zeta"]);
+        let request = |explain| ContextRequest {
+            mode: "code".to_string(),
+            max_tokens: 3,
+            explain,
+            ..Default::default()
+        };
+
+        let response = service
+            .get_context(Request::new(request(true)))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.explanations.len(), ids.len());
+        let included: Vec<&str> = response
+            .explanations
+            .iter()
+            .filter(|explanation| explanation.included)
+            .map(|explanation| explanation.memory_id.as_str())
+            .collect();
+        let sources: Vec<&str> = response
+            .sources
+            .iter()
+            .map(|source| source.source_id.as_str())
+            .collect();
+        assert_eq!(included, sources);
+        let left_out: Vec<&ContextExplanation> = response
+            .explanations
+            .iter()
+            .filter(|explanation| !explanation.included)
+            .collect();
+        assert_eq!(left_out.len(), 2);
+        assert!(left_out
+            .iter()
+            .all(|explanation| explanation.reason.starts_with("needs 2 tokens but only 1")));
+
+        let response = service
+            .get_context(Request::new(request(false)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.explanations.is_empty());
+    }
+
+    /// Store memories in `mode`, oldest first, returning them
+    fn store_in_mode(service: &SmartMemoryService, mode: &str, contents: &[&str]) -> Vec<Memory> {
+        contents
+            .iter()
+            .map(|content| {
+                service
+                    .memory_store
+                    .store(
+                        content.to_string(),
+                        "text/plain".to_string(),
+                        None,
+                        Some(mode.to_string()),
+                        HashMap::new(),
+                    )
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn update_request(mode: &str, content: &str) -> Request<UpdateContextRequest> {
+        Request::new(UpdateContextRequest {
+            mode: mode.to_string(),
+            content: content.to_string(),
+            priority: Priority::High as i32,
+        })
+    }
+
+    fn category_of(service: &SmartMemoryService, memory: &Memory) -> Option<String> {
+        service
+            .memory_store
+            .retrieve(&memory.id)
+            .unwrap()
+            .unwrap()
+            .category
+    }
+
+    #[tokio::test]
+    async fn test_update_context_stores_memory_in_mode() {
+        let service = SmartMemoryService::new().unwrap();
+        let existing = store_in_mode(&service, "code", &["first code note"]);
+
+        let response = service
+            .update_context(update_request("code", "second code note"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.success);
+        assert_eq!(response.affected_modes, vec!["code".to_string()]);
+
+        let stored = service.memory_store.get_by_mode("code", 0, 10).unwrap();
+        assert_eq!(stored.len(), 2);
+        let update = stored
+            .iter()
+            .find(|memory| memory.content == "second code note")
+            .unwrap();
+        assert_eq!(update.metadata.get("priority").unwrap(), "high");
+        assert_eq!(
+            response.new_token_count as usize,
+            existing[0].token_count.as_usize() + update.token_count.as_usize()
+        );
+        assert_eq!(category_of(&service, &existing[0]), None);
+
+        let status = service
+            .update_context(update_request("", "note"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        service.memory_bank_config.write().unwrap().read_only = true;
+        let status = service
+            .update_context(update_request("code", "note"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_update_context_archives_memories_over_budget() {
+        let service = SmartMemoryService::new().unwrap();
+        let old = store_in_mode(&service, "code", &["code note one", "code note two"]);
+        let newer = store_in_mode(&service, "code", &["code note six"]);
+        let other = store_in_mode(&service, "architect", &["architect note one"]);
+        let tokens = old[0].token_count.as_usize();
+        service
+            .memory_bank_config
+            .write()
+            .unwrap()
+            .token_budget
+            .total = 2 * tokens;
+
+        let response = service
+            .update_context(update_request("code", "code note ten"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.new_token_count as usize, 2 * tokens);
+
+        // The oldest memories are archived, not deleted
+        for memory in &old {
+            assert_eq!(
+                category_of(&service, memory).as_deref(),
+                Some(ARCHIVED_CATEGORY)
+            );
+        }
+        assert_eq!(category_of(&service, &newer[0]), None);
+        assert_eq!(category_of(&service, &other[0]), None);
+        assert_eq!(service.memory_store.count().unwrap(), 5);
+
+        // Archived memories no longer count against the budget
+        let response = service
+            .update_context(update_request("code", "code note zero"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.new_token_count as usize, 2 * tokens);
+        assert_eq!(
+            category_of(&service, &newer[0]).as_deref(),
+            Some(ARCHIVED_CATEGORY)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_context_keeps_pinned_memories_over_budget() {
+        let service = SmartMemoryService::new().unwrap();
+        let old = store_in_mode(&service, "code", &["code note one", "code note two"]);
+        service.memory_store.pin(&old[0].id).unwrap();
+        let tokens = old[0].token_count.as_usize();
+        service
+            .memory_bank_config
+            .write()
+            .unwrap()
+            .token_budget
+            .total = 2 * tokens;
+
+        let response = service
+            .update_context(update_request("code", "code note ten"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.new_token_count as usize, 2 * tokens);
+        assert_eq!(category_of(&service, &old[0]), None);
+        assert_eq!(
+            category_of(&service, &old[1]).as_deref(),
+            Some(ARCHIVED_CATEGORY)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_context_reports_linked_modes() {
+        let service = SmartMemoryService::new().unwrap();
+        let code = store_in_mode(&service, "code", &["code note"]);
+        let architect = store_in_mode(&service, "architect", &["architect note"]);
+        let debug = store_in_mode(&service, "debug", &["debug note"]);
+        for target in [&architect[0], &debug[0]] {
+            service
+                .memory_store
+                .link(&code[0].id, &target.id, "references")
+                .unwrap();
+        }
+
+        let response = service
+            .update_context(update_request("code", "another code note"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            response.affected_modes,
+            vec![
+                "code".to_string(),
+                "architect".to_string(),
+                "debug".to_string()
+            ]
+        );
+    }
 }
@@ -1,15 +1,23 @@
 //! Service implementation for Smart Memory MCP
 
+// Helpers of the RPC handlers fail with the `Status` the handlers themselves
+// return; boxing it would only move an unboxing step into every handler
+#![allow(clippy::result_large_err)]
+
+mod auth;
+mod context_window;
+mod deadline;
+mod events;
 mod health_service;
 mod memory_service;
+mod prediction;
+mod rate_limiter;
+mod sync_peers;
+mod validation;
 
-use crate::storage::MemoryStore;
-use std::sync::Arc;
-
-pub use health_service::create_health_service;
-pub use memory_service::{create_service, create_service_with_store};
-
-/// Create a new memory store instance
-pub fn create_memory_store() -> Arc<MemoryStore> {
-    memory_service::create_memory_store()
-}
+pub use auth::{api_key, authorized_request, verify_api_key};
+pub use deadline::{Deadline, StatusResponse};
+pub use events::{EventBroadcaster, EventType, MemoryEvent};
+pub use health_service::{create_health_service, HealthCheckService};
+pub use memory_service::{check_config, create_service, SmartMemoryService};
+pub use rate_limiter::RateLimiter;
@@ -0,0 +1,288 @@
+//! Context prediction from mode transition history and recency
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+use crate::proto::PredictResponse;
+use crate::storage::{Memory, MemoryStore};
+
+/// Category of the memories recording mode switches
+pub const MODE_TRANSITION_CATEGORY: &str = "mode_transition";
+
+/// Number of recent mode transitions considered by default
+const DEFAULT_TRANSITION_WINDOW: usize = 20;
+
+/// Number of memories included in a prediction by default
+const DEFAULT_TOP_K: usize = 5;
+
+/// Default half-life of the recency decay, in hours
+const DEFAULT_HALF_LIFE_HOURS: f64 = 24.0;
+
+/// Weight of the modes that usually follow the current one, relative to the current mode
+const TRANSITION_WEIGHT: f64 = 0.5;
+
+/// Record a mode switch so later predictions can learn from it
+pub fn record_transition(memory_store: &MemoryStore, from: &str, to: &str) -> Result<Memory> {
+    let mut metadata = HashMap::new();
+    metadata.insert("from_mode".to_string(), from.to_string());
+    metadata.insert("to_mode".to_string(), to.to_string());
+
+    memory_store.store(
+        format!("Switched from {} mode to {} mode", from, to),
+        "text/plain".to_string(),
+        Some(MODE_TRANSITION_CATEGORY.to_string()),
+        Some(to.to_string()),
+        metadata,
+    )
+}
+
+/// Predicts the context a mode is about to need
+#[derive(Debug, Clone)]
+pub struct PredictionModel {
+    /// Number of most recent transitions to learn from
+    transition_window: usize,
+    /// Number of memories to include in the predicted context
+    top_k: usize,
+    /// Age in hours at which a memory's weight halves
+    half_life_hours: f64,
+}
+
+impl Default for PredictionModel {
+    fn default() -> Self {
+        Self {
+            transition_window: DEFAULT_TRANSITION_WINDOW,
+            top_k: DEFAULT_TOP_K,
+            half_life_hours: DEFAULT_HALF_LIFE_HOURS,
+        }
+    }
+}
+
+impl PredictionModel {
+    /// Predict the context for `current_mode` given what the user is doing
+    pub fn predict(
+        &self,
+        current_mode: &str,
+        user_activity: &str,
+        memory_store: &MemoryStore,
+    ) -> Result<PredictResponse> {
+        let (transitions, memories): (Vec<_>, Vec<_>) = memory_store
+            .get_memories_page(0, usize::MAX)?
+            .into_iter()
+            .partition(|memory| memory.category.as_deref() == Some(MODE_TRANSITION_CATEGORY));
+
+        let mode_weights = self.mode_weights(current_mode, transitions);
+        let activity = activity_terms(user_activity);
+        let now = chrono::Utc::now();
+
+        let mut scored: Vec<(f64, Memory)> = memories
+            .into_iter()
+            .filter_map(|memory| {
+                let mode_weight = *mode_weights.get(memory.mode.as_deref()?)?;
+                let age_hours = (now - memory.created_at).num_seconds().max(0) as f64 / 3600.0;
+                let decay = (-std::f64::consts::LN_2 * age_hours / self.half_life_hours).exp();
+                let score = mode_weight * decay * (1.0 + activity_match(&activity, &memory));
+                Some((score, memory))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(self.top_k);
+
+        let matching = scored
+            .iter()
+            .filter(|(_, memory)| memory.mode.as_deref() == Some(current_mode))
+            .count();
+        let confidence = if scored.is_empty() {
+            0.0
+        } else {
+            matching as f32 / scored.len() as f32
+        };
+
+        let predicted_context = scored
+            .iter()
+            .map(|(_, memory)| memory.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let estimated_tokens = scored
+            .iter()
+            .map(|(_, memory)| memory.token_count.as_usize())
+            .sum::<usize>();
+
+        Ok(PredictResponse {
+            predicted_context,
+            confidence,
+            estimated_tokens: estimated_tokens as u32,
+        })
+    }
+
+    /// Weight the current mode and the modes that recently followed it
+    fn mode_weights(
+        &self,
+        current_mode: &str,
+        mut transitions: Vec<Memory>,
+    ) -> HashMap<String, f64> {
+        transitions.sort_by_key(|memory| std::cmp::Reverse(memory.created_at));
+
+        let next_modes: Vec<&str> = transitions
+            .iter()
+            .take(self.transition_window)
+            .filter(|memory| {
+                memory.metadata.get("from_mode").map(String::as_str) == Some(current_mode)
+            })
+            .filter_map(|memory| memory.metadata.get("to_mode").map(String::as_str))
+            .filter(|mode| *mode != current_mode)
+            .collect();
+
+        let mut weights = HashMap::new();
+        for mode in &next_modes {
+            *weights.entry(mode.to_string()).or_insert(0.0) +=
+                TRANSITION_WEIGHT / next_modes.len() as f64;
+        }
+        weights.insert(current_mode.to_string(), 1.0);
+        weights
+    }
+}
+
+/// Split the user activity into lowercase terms
+fn activity_terms(user_activity: &str) -> HashSet<String> {
+    user_activity
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Fraction of the activity terms that appear in a memory
+fn activity_match(activity: &HashSet<String>, memory: &Memory) -> f64 {
+    if activity.is_empty() {
+        return 0.0;
+    }
+    let content = activity_terms(&memory.content);
+    activity.intersection(&content).count() as f64 / activity.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{ConflictResolution, Tokenizer};
+
+    fn store_with_memories(memories: &[(&str, &str)]) -> MemoryStore {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        for (mode, content) in memories {
+            store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    Some(mode.to_string()),
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn test_predict_prefers_activity_matches_in_current_mode() {
+        let store = store_with_memories(&[
+            ("code", "refactor the parser module"),
+            ("code", "add retries to the http client"),
+            ("debug", "parser crashes on empty input"),
+        ]);
+
+        let response = PredictionModel::default()
+            .predict("code", "working on the parser", &store)
+            .unwrap();
+
+        assert!(response
+            .predicted_context
+            .starts_with("refactor the parser module"));
+        assert!(!response.predicted_context.contains("crashes"));
+        assert_eq!(response.confidence, 1.0);
+        assert!(response.estimated_tokens > 0);
+    }
+
+    #[test]
+    fn test_predict_includes_modes_that_usually_follow() {
+        let store = store_with_memories(&[
+            ("debug", "stack trace from the failing test"),
+            ("architect", "service boundaries overview"),
+        ]);
+        record_transition(&store, "debug", "code").unwrap();
+        record_transition(&store, "code", "debug").unwrap();
+        record_transition(&store, "code", "debug").unwrap();
+
+        let response = PredictionModel::default()
+            .predict("code", "fixing the failing test", &store)
+            .unwrap();
+
+        assert!(response.predicted_context.contains("stack trace"));
+        assert!(!response.predicted_context.contains("service boundaries"));
+        assert!(!response.predicted_context.contains("Switched from"));
+        assert_eq!(response.confidence, 0.0);
+    }
+
+    #[test]
+    fn test_predict_confidence_is_fraction_of_current_mode() {
+        let store = store_with_memories(&[
+            ("architect", "database schema design"),
+            ("review", "database schema review notes"),
+        ]);
+        record_transition(&store, "architect", "review").unwrap();
+
+        let response = PredictionModel::default()
+            .predict("architect", "database schema", &store)
+            .unwrap();
+
+        assert!(response
+            .predicted_context
+            .starts_with("database schema design"));
+        assert_eq!(response.confidence, 0.5);
+    }
+
+    #[test]
+    fn test_predict_without_memories() {
+        let store = store_with_memories(&[]);
+
+        let response = PredictionModel::default()
+            .predict("ask", "anything", &store)
+            .unwrap();
+
+        assert!(response.predicted_context.is_empty());
+        assert_eq!(response.confidence, 0.0);
+        assert_eq!(response.estimated_tokens, 0);
+    }
+
+    #[test]
+    fn test_recent_memories_outrank_old_ones() {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        let mut old = Memory::new(
+            "old deployment notes".to_string(),
+            "text/plain".to_string(),
+            None,
+            Some("code".to_string()),
+            HashMap::new(),
+            &Tokenizer::default(),
+        );
+        old.created_at = chrono::Utc::now() - chrono::Duration::days(7);
+        store
+            .import_memories(vec![old], ConflictResolution::KeepExisting)
+            .unwrap();
+        store
+            .store(
+                "new deployment notes".to_string(),
+                "text/plain".to_string(),
+                None,
+                Some("code".to_string()),
+                HashMap::new(),
+            )
+            .unwrap();
+
+        let response = PredictionModel::default()
+            .predict("code", "deployment", &store)
+            .unwrap();
+
+        assert!(response
+            .predicted_context
+            .starts_with("new deployment notes"));
+    }
+}
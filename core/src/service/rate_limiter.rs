@@ -0,0 +1,606 @@
+//! Per-client rate limiting of memory writes
+//!
+//! [`RateLimiter`] is a layer on the gRPC server and the REST bridge that
+//! charges each write call before it reaches its handler. Each client gets a
+//! token bucket holding up to `RATE_LIMIT_CAPACITY` tokens that refill at
+//! `RATE_LIMIT_REFILL` tokens per second. Every memory a write stores takes
+//! one token, so a bulk store of ten memories takes ten; writes finding too
+//! few tokens are rejected with `RESOURCE_EXHAUSTED`. A batch larger than a
+//! full bucket could never be stored, so it is rejected with
+//! `INVALID_ARGUMENT` and taken from nobody's bucket.
+//!
+//! Clients are told apart by their IP address. Calls without a peer address,
+//! such as those handed over by a proxy in the same process, are told apart
+//! by the first `x-forwarded-for` address, then by their API key, and share
+//! one bucket only when they carry neither. Buckets that have refilled
+//! completely are forgotten, so only clients still being limited are kept.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use hyper::body::HttpBody;
+use prost::Message;
+use tonic::codegen::{http, BoxFuture};
+use tonic::transport::server::{TcpConnectInfo, TlsConnectInfo};
+use tonic::Status;
+use tower::{Layer, Service};
+
+use super::deadline::StatusResponse;
+use crate::proto::BulkStoreRequest;
+
+/// Environment variable holding the number of memories a client may store in a burst
+const RATE_LIMIT_CAPACITY_VAR: &str = "RATE_LIMIT_CAPACITY";
+
+/// Environment variable holding the number of memories a client regains per second
+const RATE_LIMIT_REFILL_VAR: &str = "RATE_LIMIT_REFILL";
+
+/// Default number of memories a client may store in a burst
+const DEFAULT_CAPACITY: u32 = 100;
+
+/// Default number of memories a client regains per second
+const DEFAULT_REFILL_RATE: f64 = 10.0;
+
+/// Time between two sweeps for buckets that have refilled
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Most clients tracked at once; reaching it forces a sweep
+const MAX_BUCKETS: usize = 10_000;
+
+/// Largest bulk store body read to count its memories, tonic's default message limit and frame header
+const MAX_BULK_STORE_BODY: usize = 4 * 1024 * 1024 + 5;
+
+/// Path of the `BulkStore` RPC, charged for each of its memories
+const BULK_STORE_PATH: &str = "/smart_memory.SmartMemoryMcp/BulkStore";
+
+/// Paths of the calls charged one token each
+const WRITE_PATHS: &[&str] = &[
+    "/smart_memory.SmartMemoryMcp/StoreMemory",
+    "/smart_memory.SmartMemoryMcp/StoreMemoryBank",
+    "/smart_memory.SmartMemoryMcp/CopyMemory",
+    // The REST bridge's `POST /memories`
+    "/memories",
+];
+
+/// Source of the time buckets refill by
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// The current instant
+    fn now(&self) -> Instant;
+}
+
+/// Clock reading the system's monotonic time
+#[derive(Debug)]
+struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Who a bucket belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    Addr(IpAddr),
+    ApiKey(String),
+    Unidentified,
+}
+
+impl Client {
+    /// Identify the client making `request`
+    fn of<B>(request: &http::Request<B>) -> Self {
+        let extensions = request.extensions();
+        let peer = extensions
+            .get::<TcpConnectInfo>()
+            .or_else(|| {
+                extensions
+                    .get::<TlsConnectInfo<TcpConnectInfo>>()
+                    .map(TlsConnectInfo::get_ref)
+            })
+            .and_then(TcpConnectInfo::remote_addr);
+        if let Some(peer) = peer {
+            return Self::Addr(peer.ip());
+        }
+
+        let header = |name| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let forwarded = header("x-forwarded-for")
+            .and_then(|addrs| addrs.split(',').next())
+            .and_then(|addr| addr.trim().parse().ok());
+        let api_key = header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .or_else(|| header("x-api-key"));
+        match (forwarded, api_key) {
+            (Some(addr), _) => Self::Addr(addr),
+            (None, Some(api_key)) => Self::ApiKey(api_key.to_string()),
+            (None, None) => Self::Unidentified,
+        }
+    }
+}
+
+impl fmt::Display for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Addr(addr) => write!(f, "{}", addr),
+            Self::ApiKey(_) => write!(f, "this API key"),
+            Self::Unidentified => write!(f, "unidentified clients"),
+        }
+    }
+}
+
+/// Tokens left for one client and when they were last refilled
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Buckets of the clients being limited and when idle ones were last evicted
+#[derive(Debug)]
+struct Buckets {
+    by_client: HashMap<Client, Bucket>,
+    last_eviction: Instant,
+}
+
+/// Layer limiting how fast each client may store memories
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Most tokens a bucket holds
+    capacity: f64,
+    /// Tokens added to a bucket per second
+    refill_rate: f64,
+    clock: Arc<dyn Clock>,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing bursts of `capacity` memories, refilled at `refill_rate` per second
+    pub fn new(capacity: u32, refill_rate: f64) -> Self {
+        Self::with_clock(capacity, refill_rate, Arc::new(SystemClock))
+    }
+
+    /// Create a limiter as [`RateLimiter::new`] does, refilling by `clock`
+    pub fn with_clock(capacity: u32, refill_rate: f64, clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
+        Self {
+            capacity: f64::from(capacity),
+            refill_rate: refill_rate.max(0.0),
+            clock,
+            buckets: Arc::new(Mutex::new(Buckets {
+                by_client: HashMap::new(),
+                last_eviction: now,
+            })),
+        }
+    }
+
+    /// Create a limiter from `RATE_LIMIT_CAPACITY` and `RATE_LIMIT_REFILL`
+    pub fn from_env() -> Self {
+        let capacity = std::env::var(RATE_LIMIT_CAPACITY_VAR)
+            .ok()
+            .and_then(|capacity| capacity.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        let refill_rate = std::env::var(RATE_LIMIT_REFILL_VAR)
+            .ok()
+            .and_then(|rate| rate.parse().ok())
+            .unwrap_or(DEFAULT_REFILL_RATE);
+        Self::new(capacity, refill_rate)
+    }
+
+    /// Charge `client` for storing `items` memories
+    fn check(&self, client: &Client, items: usize) -> Result<(), Status> {
+        if items as f64 > self.capacity {
+            return Err(Status::invalid_argument(format!(
+                "A batch of {} memories is more than the {} a client may store at once; \
+                 split it into smaller batches",
+                items, self.capacity
+            )));
+        }
+
+        if self.try_acquire(client, items) {
+            Ok(())
+        } else {
+            Err(Status::resource_exhausted(format!(
+                "Rate limit exceeded for {}",
+                client
+            )))
+        }
+    }
+
+    /// Take `cost` tokens from the client's bucket, returning false and taking none if it has fewer
+    fn try_acquire(&self, client: &Client, cost: usize) -> bool {
+        let now = self.clock.now();
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.by_client.len() >= MAX_BUCKETS
+            || now.duration_since(buckets.last_eviction) >= EVICTION_INTERVAL
+        {
+            self.evict_idle(&mut buckets, now);
+        }
+
+        let bucket = buckets.by_client.entry(client.clone()).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.last_refill = now;
+
+        let cost = cost as f64;
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Tokens the bucket holds at `now`
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        (bucket.tokens + elapsed * self.refill_rate).min(self.capacity)
+    }
+
+    /// Forget clients whose buckets are full again, as a new bucket starts full anyway
+    ///
+    /// Should `MAX_BUCKETS` clients still be refilling, the least recently seen
+    /// half of them is forgotten as well.
+    fn evict_idle(&self, buckets: &mut Buckets, now: Instant) {
+        buckets.last_eviction = now;
+        buckets
+            .by_client
+            .retain(|_, bucket| self.refilled(bucket, now) < self.capacity);
+
+        if buckets.by_client.len() >= MAX_BUCKETS {
+            let mut last_seen: Vec<Instant> = buckets
+                .by_client
+                .values()
+                .map(|bucket| bucket.last_refill)
+                .collect();
+            let evicted = last_seen.len() - MAX_BUCKETS / 2;
+            let cutoff = *last_seen.select_nth_unstable(evicted).1;
+            buckets
+                .by_client
+                .retain(|_, bucket| bucket.last_refill >= cutoff);
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimiter {
+    type Service = WithRateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        WithRateLimit {
+            inner,
+            limiter: self.clone(),
+        }
+    }
+}
+
+/// Service charging every write call to a [`RateLimiter`]
+#[derive(Debug, Clone)]
+pub struct WithRateLimit<S> {
+    inner: S,
+    limiter: RateLimiter,
+}
+
+impl<S> Service<http::Request<hyper::Body>> for WithRateLimit<S>
+where
+    S: Service<http::Request<hyper::Body>> + Clone + Send + 'static,
+    S::Response: StatusResponse,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<hyper::Body>) -> Self::Future {
+        let path = request.uri().path();
+        let bulk_store = path == BULK_STORE_PATH;
+        if request.method() != http::Method::POST || !(bulk_store || WRITE_PATHS.contains(&path)) {
+            return Box::pin(self.inner.call(request));
+        }
+        let client = Client::of(&request);
+
+        if !bulk_store {
+            return match self.limiter.check(&client, 1) {
+                Ok(()) => Box::pin(self.inner.call(request)),
+                Err(status) => Box::pin(async move { Ok(S::Response::from_status(status)) }),
+            };
+        }
+
+        // Counting the memories means reading the body first, so the call goes
+        // to the inner service that was polled ready and a clone takes its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        Box::pin(async move {
+            let charged = match count_bulk_store_items(request).await {
+                Ok((request, items)) => limiter.check(&client, items).map(|()| request),
+                Err(status) => Err(status),
+            };
+            match charged {
+                Ok(request) => inner.call(request).await,
+                Err(status) => Ok(S::Response::from_status(status)),
+            }
+        })
+    }
+}
+
+/// Read a `BulkStore` request to count its memories, returning it intact with the count
+///
+/// A body that cannot be decoded is charged as one memory and left for the
+/// handler to reject.
+async fn count_bulk_store_items(
+    request: http::Request<hyper::Body>,
+) -> Result<(http::Request<hyper::Body>, usize), Status> {
+    let (parts, mut body) = request.into_parts();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk =
+            chunk.map_err(|e| Status::internal(format!("Failed to read request: {}", e)))?;
+        if bytes.len() + chunk.len() > MAX_BULK_STORE_BODY {
+            return Err(Status::out_of_range(format!(
+                "Bulk store request is larger than {} bytes",
+                MAX_BULK_STORE_BODY
+            )));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let items = bulk_store_items(&bytes).unwrap_or(1);
+    Ok((
+        http::Request::from_parts(parts, hyper::Body::from(bytes)),
+        items,
+    ))
+}
+
+/// Number of memories in an uncompressed gRPC frame holding a `BulkStoreRequest`
+fn bulk_store_items(frame: &[u8]) -> Option<usize> {
+    let (&compressed, rest) = frame.split_first()?;
+    if compressed != 0 || rest.len() < 4 {
+        return None;
+    }
+    let (length, message) = rest.split_at(4);
+    let length = u32::from_be_bytes(length.try_into().ok()?) as usize;
+    let request = BulkStoreRequest::decode(message.get(..length)?).ok()?;
+    Some(request.items.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::smart_memory_mcp_client::SmartMemoryMcpClient;
+    use crate::proto::smart_memory_mcp_server::SmartMemoryMcpServer;
+    use crate::proto::{MemoryBankStatsRequest, StoreRequest};
+    use crate::service::memory_service::SmartMemoryService;
+    use std::net::Ipv4Addr;
+
+    /// Clock that only moves when told to
+    #[derive(Debug)]
+    struct ManualClock(Mutex<Instant>);
+
+    impl ManualClock {
+        fn new() -> Arc<Self> {
+            Arc::new(Self(Mutex::new(Instant::now())))
+        }
+
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn client(last_octet: u8) -> Client {
+        Client::Addr(IpAddr::V4(Ipv4Addr::new(10, 0, 0, last_octet)))
+    }
+
+    #[test]
+    fn test_check_allows_capacity_then_throttles() {
+        let clock = ManualClock::new();
+        let limiter = RateLimiter::with_clock(DEFAULT_CAPACITY, DEFAULT_REFILL_RATE, clock.clone());
+
+        let results: Vec<bool> = (0..200)
+            .map(|_| limiter.check(&client(1), 1).is_ok())
+            .collect();
+        let allowed = results.iter().take_while(|&&ok| ok).count();
+        assert_eq!(allowed, DEFAULT_CAPACITY as usize);
+        assert!(results[allowed..].iter().all(|&ok| !ok));
+
+        let status = limiter.check(&client(1), 1).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        // A second refills as many memories as the refill rate
+        clock.advance(Duration::from_secs(1));
+        let refilled = (0..200)
+            .take_while(|_| limiter.check(&client(1), 1).is_ok())
+            .count();
+        assert_eq!(refilled, DEFAULT_REFILL_RATE as usize);
+    }
+
+    #[test]
+    fn test_clients_have_separate_buckets() {
+        let limiter = RateLimiter::new(1, 0.0);
+
+        assert!(limiter.try_acquire(&client(1), 1));
+        assert!(!limiter.try_acquire(&client(1), 1));
+        assert!(limiter.try_acquire(&client(2), 1));
+    }
+
+    #[test]
+    fn test_writes_are_charged_per_item() {
+        let limiter = RateLimiter::new(10, 0.0);
+
+        assert!(limiter.try_acquire(&client(1), 7));
+        // Too few tokens for the whole batch takes none of them
+        assert!(!limiter.try_acquire(&client(1), 4));
+        assert!(limiter.try_acquire(&client(1), 3));
+        assert!(!limiter.try_acquire(&client(1), 1));
+    }
+
+    #[test]
+    fn test_batches_larger_than_the_capacity_are_rejected() {
+        let limiter = RateLimiter::new(10, 0.0);
+
+        let status = limiter.check(&client(1), 11).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        // The rejected batch took nothing from the bucket
+        assert!(limiter.check(&client(1), 10).is_ok());
+    }
+
+    #[test]
+    fn test_refilled_buckets_are_evicted() {
+        let clock = ManualClock::new();
+        let limiter = RateLimiter::with_clock(2, 1000.0, clock.clone());
+        for i in 0..=255 {
+            assert!(limiter.try_acquire(&client(i), 1));
+        }
+        clock.advance(Duration::from_millis(10));
+
+        let mut buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.by_client.len(), 256);
+        limiter.evict_idle(&mut buckets, clock.now());
+        assert!(buckets.by_client.is_empty());
+    }
+
+    #[test]
+    fn test_bucket_count_is_capped() {
+        let limiter = RateLimiter::new(2, 0.0);
+        for i in 0..=MAX_BUCKETS as u32 {
+            let client = Client::Addr(IpAddr::V4(Ipv4Addr::from(0x0a00_0000 + i)));
+            assert!(limiter.try_acquire(&client, 1));
+        }
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert!(buckets.by_client.len() < MAX_BUCKETS);
+    }
+
+    #[test]
+    fn test_peerless_calls_are_told_apart() {
+        let request = |headers: &[(&str, &str)]| {
+            let mut request = http::Request::builder();
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            Client::of(&request.body(()).unwrap())
+        };
+
+        assert_eq!(
+            request(&[
+                ("x-forwarded-for", "10.0.0.7, 10.0.0.1"),
+                ("x-api-key", "a")
+            ]),
+            client(7)
+        );
+        assert_eq!(
+            request(&[("x-api-key", "a")]),
+            Client::ApiKey("a".to_string())
+        );
+        assert_eq!(
+            request(&[("authorization", "Bearer b")]),
+            Client::ApiKey("b".to_string())
+        );
+        assert_eq!(
+            request(&[("x-forwarded-for", "unknown")]),
+            Client::Unidentified
+        );
+        assert_eq!(request(&[]), Client::Unidentified);
+
+        // A peer address wins over anything the headers claim
+        let mut request = http::Request::builder()
+            .header("x-forwarded-for", "10.0.0.7")
+            .body(())
+            .unwrap();
+        request.extensions_mut().insert(TcpConnectInfo {
+            local_addr: None,
+            remote_addr: Some("10.0.0.3:4000".parse().unwrap()),
+        });
+        assert_eq!(Client::of(&request), client(3));
+    }
+
+    #[test]
+    fn test_bulk_store_items_are_counted_from_the_frame() {
+        let request = BulkStoreRequest {
+            items: vec![StoreRequest::default(); 3],
+        };
+        let message = request.encode_to_vec();
+        let mut frame = vec![0];
+        frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&message);
+        assert_eq!(bulk_store_items(&frame), Some(3));
+
+        // Compressed or cut short frames are not counted
+        frame[0] = 1;
+        assert_eq!(bulk_store_items(&frame), None);
+        frame[0] = 0;
+        assert_eq!(bulk_store_items(&frame[..frame.len() - 1]), None);
+    }
+
+    #[tokio::test]
+    async fn test_service_limits_every_stored_memory() {
+        // Without refills the outcome does not depend on how fast the calls are
+        let capacity = 100;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let service = SmartMemoryService::new().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .layer(RateLimiter::new(capacity, 0.0))
+                .add_service(SmartMemoryMcpServer::new(service))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        let mut client = SmartMemoryMcpClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+        let note = |i: usize| StoreRequest {
+            content: format!("note {}", i),
+            content_type: "text/plain".to_string(),
+            ..Default::default()
+        };
+
+        // A bulk store is charged for each of its memories
+        let bulk = client
+            .bulk_store(BulkStoreRequest {
+                items: (0..60).map(note).collect(),
+            })
+            .await;
+        assert!(bulk.is_ok());
+        let status = client
+            .bulk_store(BulkStoreRequest {
+                items: (0..60).map(note).collect(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        let mut results = Vec::new();
+        for i in 0..60 {
+            let result = client.store_memory(note(i)).await;
+            results.push(result.map(|_| ()).map_err(|status| status.code()));
+        }
+        let allowed = results.iter().take_while(|result| result.is_ok()).count();
+        assert_eq!(allowed, capacity as usize - 60);
+        assert!(results[allowed..]
+            .iter()
+            .all(|result| *result == Err(tonic::Code::ResourceExhausted)));
+
+        // Reads are not limited
+        assert!(client
+            .get_memory_bank_stats(MemoryBankStatsRequest::default())
+            .await
+            .is_ok());
+    }
+}
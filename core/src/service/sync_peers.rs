@@ -0,0 +1,125 @@
+//! Peers a `MemoryBankSync` call may connect to
+//!
+//! The `SYNC_PEERS` environment variable lists them, separated by commas, each
+//! as `<address>` or `<address>=<api key>`. A sync with any other address is
+//! refused, and without `SYNC_PEERS` syncing is disabled. The key of an entry
+//! is presented to that peer only; this server's own `API_KEY` is never sent.
+//! Addresses without a scheme are reached over `http://`.
+
+use std::collections::HashMap;
+
+use tonic::{Request, Status};
+
+/// Environment variable listing the peers and their API keys
+const SYNC_PEERS_VAR: &str = "SYNC_PEERS";
+
+/// Peers a sync may connect to, by endpoint
+#[derive(Debug, Clone, Default)]
+pub struct SyncPeers {
+    /// API key presented to each peer, if it needs one
+    api_keys: HashMap<String, Option<String>>,
+}
+
+/// A peer allowed by [`SyncPeers`] and the key it expects
+#[derive(Debug, Clone)]
+pub struct SyncPeer {
+    /// URL the peer is reached at
+    pub endpoint: String,
+    api_key: Option<String>,
+}
+
+impl SyncPeers {
+    /// Parse a `SYNC_PEERS` list
+    pub fn parse(list: &str) -> Self {
+        let api_keys = list
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once('=') {
+                Some((address, api_key)) => (endpoint(address.trim()), Some(api_key.to_string())),
+                None => (endpoint(entry), None),
+            })
+            .collect();
+        Self { api_keys }
+    }
+
+    /// Read the peers from `SYNC_PEERS`, allowing none when it is unset
+    pub fn from_env() -> Self {
+        std::env::var(SYNC_PEERS_VAR)
+            .map(|list| Self::parse(&list))
+            .unwrap_or_default()
+    }
+
+    /// Look up the peer at `address`, refusing addresses that are not listed
+    pub fn get(&self, address: &str) -> Result<SyncPeer, Status> {
+        let endpoint = endpoint(address.trim());
+        match self.api_keys.get(&endpoint) {
+            Some(api_key) => Ok(SyncPeer {
+                endpoint,
+                api_key: api_key.clone(),
+            }),
+            None if self.api_keys.is_empty() => Err(Status::failed_precondition(format!(
+                "Syncing is disabled; list peers in {}",
+                SYNC_PEERS_VAR
+            ))),
+            None => Err(Status::permission_denied(format!(
+                "Peer {} is not listed in {}",
+                address, SYNC_PEERS_VAR
+            ))),
+        }
+    }
+}
+
+impl SyncPeer {
+    /// Wrap a message in a request carrying this peer's API key, if it has one
+    pub fn request<T>(&self, message: T) -> Result<Request<T>, Status> {
+        let mut request = Request::new(message);
+        if let Some(api_key) = &self.api_key {
+            let value = api_key
+                .parse()
+                .map_err(|_| Status::internal("Invalid API key for sync peer"))?;
+            request.metadata_mut().insert("x-api-key", value);
+        }
+        Ok(request)
+    }
+}
+
+/// Get the URL of a peer address, defaulting to `http://`
+fn endpoint(address: &str) -> String {
+    if address.contains("://") {
+        address.to_string()
+    } else {
+        format!("http://{}", address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_listed_peers_are_allowed() {
+        let peers = SyncPeers::parse("peer-a:50051=key-a, https://peer-b:50051");
+
+        let peer = peers.get("http://peer-a:50051").unwrap();
+        assert_eq!(peer.endpoint, "http://peer-a:50051");
+        let request = peer.request(()).unwrap();
+        assert_eq!(request.metadata().get("x-api-key").unwrap(), "key-a");
+
+        let peer = peers.get("https://peer-b:50051").unwrap();
+        assert!(peer.request(()).unwrap().metadata().is_empty());
+
+        assert_eq!(
+            peers.get("169.254.169.254:80").unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+        assert_eq!(
+            peers.get("peer-b:50051").unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+        assert_eq!(
+            SyncPeers::default().get("peer-a:50051").unwrap_err().code(),
+            tonic::Code::FailedPrecondition
+        );
+    }
+}
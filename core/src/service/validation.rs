@@ -0,0 +1,371 @@
+//! Validation of requests before they reach the service logic
+//!
+//! Each checked request type has a [`Validator`] implementation on
+//! [`RequestValidator`], which reads the categories allowed by the current
+//! memory bank config. Violations become `INVALID_ARGUMENT` statuses.
+
+use std::fmt;
+
+use tonic::Status;
+
+use crate::proto::{BulkStoreRequest, ContextRequest, MemoryBankStoreRequest, StoreRequest};
+use crate::storage::{MemoryBankConfig, MemoryId};
+
+/// Largest token budget a context request may ask for
+pub const MAX_CONTEXT_TOKENS: u32 = 200_000;
+
+/// Top-level MIME types a content type may start with
+const MIME_TOP_LEVEL_TYPES: &[&str] = &[
+    "application",
+    "audio",
+    "font",
+    "image",
+    "message",
+    "model",
+    "multipart",
+    "text",
+    "video",
+];
+
+/// A request field that breaks one of its constraints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl ValidationError {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl From<ValidationError> for Status {
+    fn from(error: ValidationError) -> Self {
+        Status::invalid_argument(error.to_string())
+    }
+}
+
+/// Checks the requests of type `T`
+pub trait Validator<T> {
+    /// Check `req`, returning the first violation found
+    fn validate(&self, req: &T) -> Result<(), ValidationError>;
+}
+
+/// Validates requests against the current memory bank config
+#[derive(Debug, Clone, Copy)]
+pub struct RequestValidator<'a> {
+    config: &'a MemoryBankConfig,
+}
+
+impl<'a> RequestValidator<'a> {
+    /// Create a validator reading allowed values from `config`
+    pub fn new(config: &'a MemoryBankConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl Validator<StoreRequest> for RequestValidator<'_> {
+    fn validate(&self, req: &StoreRequest) -> Result<(), ValidationError> {
+        if req.content.trim().is_empty() {
+            return Err(ValidationError::new("content", "must not be empty"));
+        }
+        // An empty content type leaves it unspecified
+        if !req.content_type.is_empty() && !is_mime_type(&req.content_type) {
+            return Err(ValidationError::new(
+                "content_type",
+                format!(
+                    "{:?} is not a MIME type such as \"text/plain\"",
+                    req.content_type
+                ),
+            ));
+        }
+        if i64::try_from(req.ttl_seconds).is_err() {
+            return Err(ValidationError::new(
+                "ttl_seconds",
+                format!("{} is more than {} seconds", req.ttl_seconds, i64::MAX),
+            ));
+        }
+        // An empty preferred ID lets the server pick one
+        if !req.preferred_id.is_empty() && !MemoryId::is_valid_slug(&req.preferred_id) {
+            return Err(ValidationError::new(
+                "preferred_id",
+                format!(
+                    "{:?} is not a slug of at most 32 lowercase letters, digits and hyphens",
+                    req.preferred_id
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Validator<BulkStoreRequest> for RequestValidator<'_> {
+    fn validate(&self, req: &BulkStoreRequest) -> Result<(), ValidationError> {
+        for (index, item) in req.items.iter().enumerate() {
+            self.validate(item).map_err(|error| {
+                ValidationError::new(
+                    "items",
+                    format!("item {}: {} {}", index, error.field, error.message),
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Validator<ContextRequest> for RequestValidator<'_> {
+    fn validate(&self, req: &ContextRequest) -> Result<(), ValidationError> {
+        if req.mode.trim().is_empty() {
+            return Err(ValidationError::new("mode", "must not be empty"));
+        }
+        if !(1..=MAX_CONTEXT_TOKENS).contains(&req.max_tokens) {
+            return Err(ValidationError::new(
+                "max_tokens",
+                format!("{} is outside [1, {}]", req.max_tokens, MAX_CONTEXT_TOKENS),
+            ));
+        }
+        if !(0.0..=1.0).contains(&req.relevance_threshold) {
+            return Err(ValidationError::new(
+                "relevance_threshold",
+                format!("{} is outside [0.0, 1.0]", req.relevance_threshold),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Validator<MemoryBankStoreRequest> for RequestValidator<'_> {
+    fn validate(&self, req: &MemoryBankStoreRequest) -> Result<(), ValidationError> {
+        if !req.category.is_empty() && !self.config.categories.contains_key(&req.category) {
+            let mut categories: Vec<&str> =
+                self.config.categories.keys().map(String::as_str).collect();
+            categories.sort_unstable();
+            return Err(ValidationError::new(
+                "category",
+                format!(
+                    "{:?} is not one of the configured categories: {}",
+                    req.category,
+                    categories.join(", ")
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Check that `content_type` is `type/subtype` with a registered top-level type
+///
+/// Parameters after a `;`, such as a charset, are allowed.
+fn is_mime_type(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    let Some((top_level, subtype)) = essence.split_once('/') else {
+        return false;
+    };
+
+    let is_token = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$&^_.+-".contains(c))
+    };
+    MIME_TOP_LEVEL_TYPES.contains(&top_level.to_ascii_lowercase().as_str()) && is_token(subtype)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arbitrary::{Arbitrary, Unstructured};
+    use proptest::prelude::*;
+
+    fn store_request(content: &str, content_type: &str) -> StoreRequest {
+        StoreRequest {
+            content: content.to_string(),
+            content_type: content_type.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn context_request(mode: &str, max_tokens: u32, relevance_threshold: f32) -> ContextRequest {
+        ContextRequest {
+            mode: mode.to_string(),
+            max_tokens,
+            relevance_threshold,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bulk_store_requests_check_every_item() {
+        let config = MemoryBankConfig::default();
+        let validator = RequestValidator::new(&config);
+
+        let mut request = BulkStoreRequest {
+            items: vec![store_request("note", "text/plain"), store_request("", "")],
+        };
+        let error = validator.validate(&request).unwrap_err();
+        assert_eq!(error.field, "items");
+        assert_eq!(error.message, "item 1: content must not be empty");
+
+        request.items[1].content = "another note".to_string();
+        assert!(validator.validate(&request).is_ok());
+    }
+
+    #[test]
+    fn test_store_requests() {
+        let config = MemoryBankConfig::default();
+        let validator = RequestValidator::new(&config);
+
+        assert!(validator.validate(&store_request("note", "")).is_ok());
+        assert!(validator
+            .validate(&store_request("note", "text/plain"))
+            .is_ok());
+        assert!(validator
+            .validate(&store_request("note", "text/markdown; charset=utf-8"))
+            .is_ok());
+
+        let error = validator
+            .validate(&store_request(" \n", "text/plain"))
+            .unwrap_err();
+        assert_eq!(error.field, "content");
+        for content_type in ["plain", "text/", "banana/split", "text/pl ain"] {
+            let error = validator
+                .validate(&store_request("note", content_type))
+                .unwrap_err();
+            assert_eq!(error.field, "content_type", "{}", content_type);
+        }
+
+        let request = |preferred_id: &str| StoreRequest {
+            preferred_id: preferred_id.to_string(),
+            ..store_request("note", "text/plain")
+        };
+        assert!(validator.validate(&request("architecture")).is_ok());
+        for preferred_id in [
+            "Architecture",
+            "mem_1234",
+            "-notes",
+            "a".repeat(33).as_str(),
+        ] {
+            let error = validator.validate(&request(preferred_id)).unwrap_err();
+            assert_eq!(error.field, "preferred_id", "{}", preferred_id);
+        }
+
+        let request = |ttl_seconds: u64| StoreRequest {
+            ttl_seconds,
+            ..store_request("note", "text/plain")
+        };
+        assert!(validator.validate(&request(i64::MAX as u64)).is_ok());
+        let error = validator.validate(&request(u64::MAX)).unwrap_err();
+        assert_eq!(error.field, "ttl_seconds");
+    }
+
+    #[test]
+    fn test_context_requests() {
+        let config = MemoryBankConfig::default();
+        let validator = RequestValidator::new(&config);
+
+        assert!(validator.validate(&context_request("code", 1, 0.0)).is_ok());
+        assert!(validator
+            .validate(&context_request("code", MAX_CONTEXT_TOKENS, 1.0))
+            .is_ok());
+
+        let field = |req: ContextRequest| validator.validate(&req).unwrap_err().field;
+        assert_eq!(field(context_request("", 100, 0.5)), "mode");
+        assert_eq!(field(context_request("code", 0, 0.5)), "max_tokens");
+        assert_eq!(
+            field(context_request("code", MAX_CONTEXT_TOKENS + 1, 0.5)),
+            "max_tokens"
+        );
+        assert_eq!(
+            field(context_request("code", 100, -0.1)),
+            "relevance_threshold"
+        );
+        assert_eq!(
+            field(context_request("code", 100, f32::NAN)),
+            "relevance_threshold"
+        );
+    }
+
+    #[test]
+    fn test_memory_bank_store_requests() {
+        let config = MemoryBankConfig::default();
+        let validator = RequestValidator::new(&config);
+        let request = |category: &str| MemoryBankStoreRequest {
+            content: "entry".to_string(),
+            category: category.to_string(),
+            ..Default::default()
+        };
+
+        assert!(validator.validate(&request("")).is_ok());
+        assert!(validator.validate(&request("decision")).is_ok());
+
+        let error = validator.validate(&request("decisions")).unwrap_err();
+        assert_eq!(error.field, "category");
+        assert!(error.message.contains("decision, pattern"));
+        assert_eq!(Status::from(error).code(), tonic::Code::InvalidArgument);
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_store_requests(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+            let mut input = Unstructured::new(&bytes);
+            let request = StoreRequest {
+                content: String::arbitrary(&mut input).unwrap_or_default(),
+                content_type: String::arbitrary(&mut input).unwrap_or_default(),
+                compress: bool::arbitrary(&mut input).unwrap_or_default(),
+                ..Default::default()
+            };
+
+            let config = MemoryBankConfig::default();
+            if RequestValidator::new(&config).validate(&request).is_ok() {
+                prop_assert!(!request.content.trim().is_empty());
+                prop_assert!(request.content_type.is_empty() || request.content_type.contains('/'));
+            }
+        }
+
+        #[test]
+        fn fuzz_context_requests(bytes in prop::collection::vec(any::<u8>(), 0..128)) {
+            let mut input = Unstructured::new(&bytes);
+            let request = ContextRequest {
+                mode: String::arbitrary(&mut input).unwrap_or_default(),
+                max_tokens: u32::arbitrary(&mut input).unwrap_or_default(),
+                relevance_threshold: f32::arbitrary(&mut input).unwrap_or_default(),
+                ..Default::default()
+            };
+
+            let config = MemoryBankConfig::default();
+            if RequestValidator::new(&config).validate(&request).is_ok() {
+                prop_assert!(!request.mode.trim().is_empty());
+                prop_assert!(request.max_tokens >= 1 && request.max_tokens <= MAX_CONTEXT_TOKENS);
+                prop_assert!((0.0..=1.0).contains(&request.relevance_threshold));
+            }
+        }
+
+        #[test]
+        fn fuzz_memory_bank_store_requests(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+            let mut input = Unstructured::new(&bytes);
+            let request = MemoryBankStoreRequest {
+                category: String::arbitrary(&mut input).unwrap_or_default(),
+                ..Default::default()
+            };
+
+            let config = MemoryBankConfig::default();
+            let accepted = RequestValidator::new(&config).validate(&request).is_ok();
+            prop_assert_eq!(
+                accepted,
+                request.category.is_empty() || config.categories.contains_key(&request.category)
+            );
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! `stats` CLI subcommand for printing memory bank statistics
+//!
+//! Usage: `smart-memory stats [--json] [--sort-by tokens|memories|category]`
+
+use comfy_table::{presets::UTF8_FULL, CellAlignment, Table};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io;
+
+use crate::proto::smart_memory_mcp_client::SmartMemoryMcpClient;
+use crate::proto::{
+    GetConfigRequest, MemoryBankCategoryStats, MemoryBankStatsRequest, MemoryBankStatsResponse,
+};
+use crate::service::authorized_request;
+
+/// Column the category rows are ordered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortBy {
+    Tokens,
+    Memories,
+    Category,
+}
+
+/// Options accepted by the `stats` subcommand
+#[derive(Debug)]
+struct StatsOptions {
+    json: bool,
+    sort_by: SortBy,
+}
+
+impl StatsOptions {
+    /// Parse options from the arguments following `stats`
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Self {
+            json: false,
+            sort_by: SortBy::Category,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => options.json = true,
+                "--sort-by" => {
+                    options.sort_by = match args.next().map(String::as_str) {
+                        Some("tokens") => SortBy::Tokens,
+                        Some("memories") => SortBy::Memories,
+                        Some("category") => SortBy::Category,
+                        Some(other) => return Err(format!("Unknown sort column: {}", other)),
+                        None => return Err(format!("Missing value for {}", arg)),
+                    }
+                }
+                other => return Err(format!("Unknown option: {}", other)),
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Order category rows by the `--sort-by` column, largest first for counts
+    fn sort(&self, categories: &mut [MemoryBankCategoryStats]) {
+        match self.sort_by {
+            SortBy::Tokens => categories.sort_by_key(|c| Reverse(c.token_count)),
+            SortBy::Memories => categories.sort_by_key(|c| Reverse(c.memory_count)),
+            SortBy::Category => categories.sort_by(|a, b| a.category.cmp(&b.category)),
+        }
+    }
+}
+
+/// Run the `stats` subcommand against the server at `host:port`
+pub fn run(host: &str, port: u16, args: &[String]) -> io::Result<()> {
+    let options =
+        StatsOptions::parse(args).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let endpoint = format!("http://{}:{}", host, port);
+
+    // The CLI may be invoked from within the server's runtime, so use a dedicated one
+    let output = std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new()?;
+        runtime
+            .block_on(fetch_stats(endpoint, &options))
+            .map_err(|e| io::Error::other(e.to_string()))
+    })
+    .join()
+    .map_err(|_| io::Error::other("stats command panicked"))??;
+
+    println!("{}", output);
+    Ok(())
+}
+
+/// Fetch the memory bank stats and category budgets and render them
+async fn fetch_stats(endpoint: String, options: &StatsOptions) -> anyhow::Result<String> {
+    let mut client = SmartMemoryMcpClient::connect(endpoint.clone())
+        .await
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "Server is not running at {}; start it with `smart-memory-mcp start`",
+                endpoint
+            )
+        })?;
+
+    let stats = client
+        .get_memory_bank_stats(authorized_request(MemoryBankStatsRequest::default()))
+        .await?
+        .into_inner();
+    let budgets = client
+        .get_config(authorized_request(GetConfigRequest {}))
+        .await?
+        .into_inner()
+        .categories
+        .into_iter()
+        .map(|category| (category.name, category.max_tokens))
+        .collect();
+
+    Ok(if options.json {
+        render_json(stats, &budgets, options)
+    } else {
+        render_table(stats, &budgets, options)
+    })
+}
+
+/// Share of a category's token budget in use, if it has one
+fn budget_percent(
+    category: &MemoryBankCategoryStats,
+    budgets: &HashMap<String, u32>,
+) -> Option<f64> {
+    budgets
+        .get(&category.category)
+        .filter(|&&budget| budget > 0)
+        .map(|&budget| category.token_count as f64 * 100.0 / budget as f64)
+}
+
+/// Render the stats as a table followed by a totals line
+fn render_table(
+    mut stats: MemoryBankStatsResponse,
+    budgets: &HashMap<String, u32>,
+    options: &StatsOptions,
+) -> String {
+    options.sort(&mut stats.category_stats);
+
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL).set_header(vec![
+        "Category",
+        "Memories",
+        "Tokens",
+        "% of Budget",
+        "Last Updated",
+    ]);
+    for category in &stats.category_stats {
+        let percent = budget_percent(category, budgets)
+            .map(|percent| format!("{:.1}%", percent))
+            .unwrap_or_else(|| "-".to_string());
+        table.add_row(vec![
+            category.category.clone(),
+            category.memory_count.to_string(),
+            category.token_count.to_string(),
+            percent,
+            category.last_updated.clone(),
+        ]);
+    }
+    for index in 1..=3 {
+        if let Some(column) = table.column_mut(index) {
+            column.set_cell_alignment(CellAlignment::Right);
+        }
+    }
+
+    format!(
+        "{}\nTotal: {} memories, {} tokens in {} categories ({} pinned)",
+        table,
+        stats.total_memories,
+        stats.total_tokens,
+        stats.category_stats.len(),
+        stats.pinned_count
+    )
+}
+
+/// Render the stats as a JSON document for scripts
+fn render_json(
+    mut stats: MemoryBankStatsResponse,
+    budgets: &HashMap<String, u32>,
+    options: &StatsOptions,
+) -> String {
+    options.sort(&mut stats.category_stats);
+
+    let categories: Vec<_> = stats
+        .category_stats
+        .iter()
+        .map(|category| {
+            serde_json::json!({
+                "category": category.category,
+                "memories": category.memory_count,
+                "tokens": category.token_count,
+                "budget_percent": budget_percent(category, budgets),
+                "last_updated": category.last_updated,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "total_memories": stats.total_memories,
+        "total_tokens": stats.total_tokens,
+        "pinned_count": stats.pinned_count,
+        "categories": categories,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::smart_memory_mcp_server::SmartMemoryMcpServer;
+    use crate::service::SmartMemoryService;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn category(name: &str, memory_count: u32, token_count: u32) -> MemoryBankCategoryStats {
+        MemoryBankCategoryStats {
+            category: name.to_string(),
+            memory_count,
+            token_count,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = StatsOptions::parse(&args(&["--json", "--sort-by", "tokens"])).unwrap();
+        assert!(options.json);
+        assert_eq!(options.sort_by, SortBy::Tokens);
+
+        assert!(StatsOptions::parse(&args(&["--sort-by"])).is_err());
+        assert!(StatsOptions::parse(&args(&["--sort-by", "size"])).is_err());
+        assert!(StatsOptions::parse(&args(&["--verbose"])).is_err());
+    }
+
+    #[test]
+    fn test_sort_and_budget() {
+        let stats = MemoryBankStatsResponse {
+            category_stats: vec![
+                category("context", 5, 100),
+                category("decision", 2, 400),
+                category("progress", 9, 50),
+            ],
+            ..Default::default()
+        };
+        let budgets = HashMap::from([("decision".to_string(), 1000)]);
+        let order = |sort_by: &str| {
+            let options = StatsOptions::parse(&args(&["--json", "--sort-by", sort_by])).unwrap();
+            let json: serde_json::Value =
+                serde_json::from_str(&render_json(stats.clone(), &budgets, &options)).unwrap();
+            json["categories"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| c["category"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(order("category"), ["context", "decision", "progress"]);
+        assert_eq!(order("tokens"), ["decision", "context", "progress"]);
+        assert_eq!(order("memories"), ["progress", "context", "decision"]);
+
+        let options = StatsOptions::parse(&[]).unwrap();
+        let table = render_table(stats, &budgets, &options);
+        assert!(table.contains("40.0%"));
+        assert!(table.contains("Total: 0 memories"));
+    }
+
+    #[tokio::test]
+    async fn test_stats_from_running_server() {
+        let service = SmartMemoryService::new().unwrap();
+        for (content, category) in [
+            ("Decided on gRPC", "decision"),
+            ("Wrote the parser", "progress"),
+            ("Fixed the parser", "progress"),
+        ] {
+            service
+                .memory_store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    Some(category.to_string()),
+                    Some("code".to_string()),
+                    HashMap::new(),
+                )
+                .unwrap();
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(SmartMemoryMcpServer::new(service))
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+
+        let options = StatsOptions::parse(&args(&["--sort-by", "memories"])).unwrap();
+        let output = fetch_stats(endpoint.clone(), &options).await.unwrap();
+        assert!(output.contains("decision"));
+        assert!(output.contains("progress"));
+        assert!(output.contains("Total: 3 memories"));
+        assert!(output.find("progress") < output.find("decision"));
+
+        let options = StatsOptions::parse(&args(&["--json"])).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fetch_stats(endpoint, &options).await.unwrap()).unwrap();
+        assert_eq!(json["total_memories"], 3);
+        assert_eq!(json["categories"][1]["category"], "progress");
+        assert_eq!(json["categories"][1]["memories"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_stats_without_server() {
+        // Bind and drop a listener to find a port nothing listens on
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let options = StatsOptions::parse(&[]).unwrap();
+        let error = fetch_stats(endpoint, &options).await.unwrap_err();
+        assert!(error.to_string().contains("Server is not running"));
+    }
+}
@@ -0,0 +1,222 @@
+//! Append-only audit trail of memory mutations
+//!
+//! Each store, update and delete appends one JSON line to the audit file.
+//! The file is only ever opened for appending and is never rotated, so it
+//! holds the full history of the store. Lines carry either a SHA-256 hash of
+//! the content or, with `include_content`, the content itself, which lets
+//! [`MemoryStore::replay_audit_log`](super::MemoryStore::replay_audit_log)
+//! rebuild the memories after a loss. On Unix only the file's owner may read it.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::memory::Memory;
+
+/// Mutation recorded by an audit line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOperation {
+    Store,
+    Update,
+    Delete,
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub operation: AuditOperation,
+    pub memory_id: String,
+    pub category: Option<String>,
+    pub mode: Option<String>,
+    /// SHA-256 of the content, hex encoded, when the content itself is left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Metadata of the memory, recorded along with its content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Audit log file that mutations are appended to
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    file: Mutex<File>,
+    /// Whether lines carry the content rather than its hash
+    include_content: bool,
+}
+
+impl AuditLog {
+    /// Open the audit log at `path` for appending, creating it if needed
+    ///
+    /// An existing file's mode is tightened so only its owner can read it.
+    pub fn open(path: &Path, include_content: bool) -> Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create audit log directory {}", parent.display())
+            })?;
+        }
+        let mut options = OpenOptions::new();
+        options.append(true).create(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options
+            .open(path)
+            .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))
+                .with_context(|| format!("Failed to restrict audit log {}", path.display()))?;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            include_content,
+        })
+    }
+
+    /// Append a line recording `operation` on `memory`
+    pub fn record(&self, operation: AuditOperation, memory: &Memory) -> Result<()> {
+        let (content_hash, content, content_type, metadata) = if self.include_content {
+            (
+                None,
+                Some(memory.content.clone()),
+                Some(memory.content_type.clone()),
+                Some(memory.metadata.clone()),
+            )
+        } else {
+            (
+                Some(hex::encode(Sha256::digest(&memory.content))),
+                None,
+                None,
+                None,
+            )
+        };
+        let record = AuditRecord {
+            timestamp: Utc::now(),
+            operation,
+            memory_id: memory.id.as_str().to_string(),
+            category: memory.category.clone(),
+            mode: memory.mode.clone(),
+            content_hash,
+            content,
+            content_type,
+            metadata,
+        };
+
+        let mut line =
+            serde_json::to_string(&record).context("Failed to serialize audit record")?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap();
+        // One write per line, so concurrent writers never interleave within a line
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .with_context(|| format!("Failed to append to audit log {}", self.path.display()))
+    }
+}
+
+/// Read every record of the audit log at `path`, oldest first
+pub fn read_audit_log(path: &Path) -> Result<Vec<AuditRecord>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open audit log {}", path.display()))?;
+
+    let mut records = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read audit log {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(
+            serde_json::from_str(&line)
+                .with_context(|| format!("Invalid audit record on line {}", index + 1))?,
+        );
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Tokenizer;
+    use tempfile::tempdir;
+
+    fn memory(content: &str) -> Memory {
+        Memory::new(
+            content.to_string(),
+            "text/plain".to_string(),
+            Some("decision".to_string()),
+            Some("code".to_string()),
+            HashMap::new(),
+            &Tokenizer::default(),
+        )
+    }
+
+    #[test]
+    fn test_reopened_log_is_appended_to() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("audit").join("audit.jsonl");
+        let first = memory("first");
+        let second = memory("second");
+
+        AuditLog::open(&path, false)?.record(AuditOperation::Store, &first)?;
+        let before = fs::read_to_string(&path)?;
+        let log = AuditLog::open(&path, true)?;
+        log.record(AuditOperation::Store, &second)?;
+        log.record(AuditOperation::Delete, &first)?;
+
+        // Earlier lines are kept byte for byte
+        assert!(fs::read_to_string(&path)?.starts_with(&before));
+        let records = read_audit_log(&path)?;
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].memory_id, first.id.as_str());
+        assert_eq!(
+            records[0].content_hash.as_deref(),
+            Some(hex::encode(Sha256::digest("first")).as_str())
+        );
+        assert_eq!(records[0].content, None);
+        assert_eq!(records[1].content.as_deref(), Some("second"));
+        assert_eq!(records[1].content_hash, None);
+        assert_eq!(records[1].metadata, Some(HashMap::new()));
+        assert_eq!(records[2].operation, AuditOperation::Delete);
+        assert_eq!(records[2].category.as_deref(), Some("decision"));
+        assert_eq!(records[2].mode.as_deref(), Some("code"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_log_is_private_to_its_owner() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempdir()?;
+        let path = dir.path().join("audit.jsonl");
+        AuditLog::open(&path, true)?;
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+
+        // A file created elsewhere with a wider mode is tightened when opened
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644))?;
+        AuditLog::open(&path, true)?;
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        Ok(())
+    }
+}
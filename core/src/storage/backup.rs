@@ -1,16 +1,128 @@
+use super::db::{compact_database, database_size, incremental_vacuum};
 use crate::logging::LogLevel;
 use crate::{log_error, log_info, log_warning};
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_smithy_http_client::tls::{rustls_provider::CryptoMode, Provider};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
+use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+
+/// Magic bytes at the start of an incremental backup file
+const INCREMENTAL_MAGIC: &[u8; 8] = b"SMINC\0\0\x01";
+
+/// Timestamps below this are in seconds, as written before millisecond timestamps
+const LEGACY_TIMESTAMP_LIMIT: u64 = 100_000_000_000;
+
+/// Pages copied per step when snapshotting a database with the online backup API
+const SNAPSHOT_PAGES_PER_STEP: std::os::raw::c_int = 256;
+
+/// Bytes every SQLite database file starts with
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Free pages returned to the file system after each scheduled backup
+const AUTO_VACUUM_PAGES: u32 = 100;
+
+/// File name endings of backup files, full backups in every compression first
+const BACKUP_EXTENSIONS: &[&str] = &[".db.gz", ".db.zst", ".db", ".inc"];
+
+/// Compression applied to full backup files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionMode {
+    /// Plain copy of the database
+    #[default]
+    None,
+    /// gzip-compressed `.db.gz` file
+    Gzip,
+    /// zstd-compressed `.db.zst` file
+    Zstd,
+}
+
+impl CompressionMode {
+    /// Parse a compression name as used in `BACKUP_COMPRESSION`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Some(Self::None),
+            "gzip" | "gz" => Some(Self::Gzip),
+            "zstd" | "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Read the default compression from `BACKUP_COMPRESSION`
+    pub fn from_env() -> Self {
+        let name = match std::env::var("BACKUP_COMPRESSION") {
+            Ok(name) => name,
+            Err(_) => return Self::None,
+        };
+
+        Self::parse(&name).unwrap_or_else(|| {
+            log_warning!(
+                "backup",
+                &format!(
+                    "Unknown BACKUP_COMPRESSION '{}', backups will not be compressed",
+                    name
+                )
+            );
+            Self::None
+        })
+    }
+
+    /// Detect the compression of a backup file from its extension
+    pub fn from_path(path: &Path) -> Self {
+        let name = path.to_string_lossy();
+        if name.ends_with(".gz") {
+            Self::Gzip
+        } else if name.ends_with(".zst") {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// Get the name recorded in backup metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Get the extension of a full backup file
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::None => "db",
+            Self::Gzip => "db.gz",
+            Self::Zstd => "db.zst",
+        }
+    }
+}
+
+/// Compression recorded for backups made before compression was supported
+fn default_compression() -> String {
+    CompressionMode::None.as_str().to_string()
+}
+
+/// Environment variable naming the S3 bucket scheduled backups are copied to
+const S3_BACKUP_BUCKET_VAR: &str = "S3_BACKUP_BUCKET";
+
+/// Environment variable holding the key prefix of scheduled backups copied to S3
+const S3_BACKUP_PREFIX_VAR: &str = "S3_BACKUP_PREFIX";
+
+/// Key prefix of scheduled backups copied to S3 when `S3_BACKUP_PREFIX` is unset
+const DEFAULT_S3_BACKUP_PREFIX: &str = "smart-memory/backups";
 
 /// Backup metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BackupMetadata {
-    /// Timestamp of the backup (seconds since UNIX epoch)
+    /// Timestamp of the backup (milliseconds since UNIX epoch; older backups recorded seconds)
     pub timestamp: u64,
     /// Description of the backup
     pub description: String,
@@ -20,6 +132,68 @@ pub struct BackupMetadata {
     pub version: String,
     /// Type of backup (auto, manual, pre-update, etc.)
     pub backup_type: String,
+    /// Hex-encoded SHA-256 of the backup file; absent for backups made before checksums
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// File name of the backup an incremental backup was taken against
+    #[serde(default)]
+    pub base_backup: Option<String>,
+    /// Compression of the backup file (`none`, `gzip` or `zstd`)
+    #[serde(default = "default_compression")]
+    pub compression: String,
+    /// Whether the backup is kept by rotation regardless of `max_backups`
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl BackupMetadata {
+    /// Get when the backup was created, accepting both second and millisecond timestamps
+    pub fn created_at(&self) -> DateTime<Utc> {
+        let since_epoch = if self.timestamp < LEGACY_TIMESTAMP_LIMIT {
+            Duration::from_secs(self.timestamp)
+        } else {
+            Duration::from_millis(self.timestamp)
+        };
+        DateTime::<Utc>::from(UNIX_EPOCH + since_epoch)
+    }
+
+    /// Check whether this is an incremental backup that needs its base to restore
+    pub fn is_incremental(&self) -> bool {
+        self.backup_type == "incremental"
+    }
+}
+
+/// Get the backup directory under `DATA_DIR`, defaulting to `~/.smart-memory/backups`
+pub fn default_backup_dir() -> PathBuf {
+    let data_dir = std::env::var("DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".smart-memory")
+        });
+    data_dir.join("backups")
+}
+
+/// Get the current time in milliseconds since the UNIX epoch
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Compute the hex-encoded SHA-256 of a file
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
 }
 
 /// Backup manager
@@ -28,11 +202,20 @@ pub struct BackupManager {
     backup_dir: PathBuf,
     /// Maximum number of backups to keep
     max_backups: usize,
+    /// Compression applied to new full backups
+    compression: CompressionMode,
+    /// Client for S3 exports and imports, loaded from the AWS environment when unset
+    s3_client: Option<aws_sdk_s3::Client>,
 }
 
 impl BackupManager {
-    /// Create a new backup manager
+    /// Create a new backup manager compressing as configured by `BACKUP_COMPRESSION`
     pub fn new(backup_dir: &Path) -> io::Result<Self> {
+        Self::with_compression(backup_dir, CompressionMode::from_env())
+    }
+
+    /// Create a new backup manager with the given compression for new backups
+    pub fn with_compression(backup_dir: &Path, compression: CompressionMode) -> io::Result<Self> {
         // Create backup directory if it doesn't exist
         if !backup_dir.exists() {
             fs::create_dir_all(backup_dir)?;
@@ -41,6 +224,8 @@ impl BackupManager {
         Ok(Self {
             backup_dir: backup_dir.to_path_buf(),
             max_backups: 10, // Default to keeping 10 backups
+            compression,
+            s3_client: None,
         })
     }
 
@@ -49,20 +234,22 @@ impl BackupManager {
         self.max_backups = max_backups;
     }
 
+    /// Use the given client for S3 exports and imports instead of one configured from the environment
+    pub fn set_s3_client(&mut self, client: aws_sdk_s3::Client) {
+        self.s3_client = Some(client);
+    }
+
     /// Create a backup
     pub fn create_backup(&self, source_path: &Path, description: &str) -> io::Result<PathBuf> {
         // Generate a unique backup ID based on timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let timestamp = now_millis();
 
         // Create backup filename
-        let backup_filename = format!("backup_{}.db", timestamp);
+        let backup_filename = self.unique_backup_filename(timestamp, self.compression.extension());
         let backup_path = self.backup_dir.join(&backup_filename);
 
         // Copy the source file to the backup location
-        self.copy_file(source_path, &backup_path)?;
+        self.write_backup_file(source_path, &backup_path)?;
 
         // Create metadata
         let metadata = BackupMetadata {
@@ -71,6 +258,10 @@ impl BackupManager {
             size: fs::metadata(&backup_path)?.len(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             backup_type: "manual".to_string(),
+            sha256: Some(sha256_file(&backup_path)?),
+            base_backup: None,
+            compression: self.compression.as_str().to_string(),
+            pinned: false,
         };
 
         // Save metadata
@@ -90,17 +281,14 @@ impl BackupManager {
     /// Create an automatic backup
     pub fn create_auto_backup(&self, source_path: &Path) -> io::Result<PathBuf> {
         // Generate a unique backup ID based on timestamp
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        let timestamp = now_millis();
 
         // Create backup filename
-        let backup_filename = format!("backup_{}.db", timestamp);
+        let backup_filename = self.unique_backup_filename(timestamp, self.compression.extension());
         let backup_path = self.backup_dir.join(&backup_filename);
 
         // Copy the source file to the backup location
-        self.copy_file(source_path, &backup_path)?;
+        self.write_backup_file(source_path, &backup_path)?;
 
         // Create metadata
         let metadata = BackupMetadata {
@@ -109,6 +297,10 @@ impl BackupManager {
             size: fs::metadata(&backup_path)?.len(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             backup_type: "auto".to_string(),
+            sha256: Some(sha256_file(&backup_path)?),
+            base_backup: None,
+            compression: self.compression.as_str().to_string(),
+            pinned: false,
         };
 
         // Save metadata
@@ -125,6 +317,139 @@ impl BackupManager {
         Ok(backup_path)
     }
 
+    /// Create an incremental backup holding only the pages changed since `base_backup`
+    ///
+    /// The source is snapshotted with SQLite's online backup API and compared page
+    /// by page against the database state `base_backup` represents. The base may
+    /// itself be incremental, which extends its chain.
+    pub fn create_incremental_backup(
+        &self,
+        source_path: &Path,
+        base_backup: &Path,
+    ) -> io::Result<PathBuf> {
+        let base_filename = backup_filename(base_backup)?;
+        let base_image = self.reconstruct_backup(&base_filename)?;
+
+        let timestamp = now_millis();
+        let backup_filename = self.unique_backup_filename(timestamp, "inc");
+        let backup_path = self.backup_dir.join(&backup_filename);
+
+        let snapshot = self.snapshot_database(source_path, &backup_filename)?;
+        fs::write(&backup_path, encode_incremental(&base_image, &snapshot)?)?;
+
+        let metadata = BackupMetadata {
+            timestamp,
+            description: format!("Incremental backup against {}", base_filename),
+            size: fs::metadata(&backup_path)?.len(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            backup_type: "incremental".to_string(),
+            sha256: Some(sha256_file(&backup_path)?),
+            base_backup: Some(base_filename),
+            compression: default_compression(),
+            pinned: false,
+        };
+        self.save_metadata(&backup_filename, &metadata)?;
+
+        self.rotate_backups()?;
+
+        log_info!(
+            "backup",
+            &format!("Created incremental backup: {}", backup_path.display())
+        );
+
+        Ok(backup_path)
+    }
+
+    /// Restore a full backup followed by a chain of incremental backups, in order
+    pub fn restore_incremental(
+        &self,
+        base_path: &Path,
+        incremental_paths: &[&Path],
+        target: &Path,
+    ) -> io::Result<()> {
+        let mut image = read_backup_image(base_path)?;
+        let mut previous = backup_filename(base_path)?;
+
+        for incremental_path in incremental_paths {
+            let filename = backup_filename(incremental_path)?;
+            let metadata = self.read_metadata(&filename)?;
+
+            // Each incremental must have been taken against the previous link
+            if metadata.base_backup.as_deref() != Some(previous.as_str()) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Incremental backup {} is not based on {}",
+                        filename, previous
+                    ),
+                ));
+            }
+
+            apply_incremental(&mut image, &fs::read(incremental_path)?)?;
+            previous = filename;
+        }
+
+        // Write to a temporary file first so a failure leaves the target untouched
+        let temp_path = target.with_extension("restore.tmp");
+        fs::write(&temp_path, &image)?;
+        fs::rename(&temp_path, target)?;
+
+        log_info!(
+            "backup",
+            &format!(
+                "Restored {} with {} incremental backups to {}",
+                base_path.display(),
+                incremental_paths.len(),
+                target.display()
+            )
+        );
+
+        Ok(())
+    }
+
+    /// Rebuild the database image a backup represents, following incremental chains
+    fn reconstruct_backup(&self, backup_filename: &str) -> io::Result<Vec<u8>> {
+        let backup_path = self.backup_dir.join(backup_filename);
+        let metadata = self.read_metadata(backup_filename)?;
+
+        if !metadata.is_incremental() {
+            return read_backup_image(&backup_path);
+        }
+
+        let base = metadata.base_backup.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Incremental backup {} has no base", backup_filename),
+            )
+        })?;
+        let mut image = self.reconstruct_backup(&base)?;
+        apply_incremental(&mut image, &fs::read(backup_path)?)?;
+
+        Ok(image)
+    }
+
+    /// Take a consistent copy of a live database with SQLite's online backup API
+    fn snapshot_database(&self, source_path: &Path, backup_filename: &str) -> io::Result<Vec<u8>> {
+        let snapshot_path = self
+            .backup_dir
+            .join(format!("{}.snapshot", backup_filename));
+        let result =
+            copy_database(source_path, &snapshot_path).and_then(|()| fs::read(&snapshot_path));
+        let _ = fs::remove_file(&snapshot_path);
+        result
+    }
+
+    /// Build a backup filename for a timestamp that does not collide with an existing backup
+    fn unique_backup_filename(&self, timestamp: u64, extension: &str) -> String {
+        let mut filename = format!("backup_{}.{}", timestamp, extension);
+        let mut suffix = 1;
+        while self.backup_dir.join(&filename).exists() {
+            filename = format!("backup_{}_{}.{}", timestamp, suffix, extension);
+            suffix += 1;
+        }
+        filename
+    }
+
     /// Restore a backup
     pub fn restore_backup(&self, backup_path: &Path, target_path: &Path) -> io::Result<()> {
         // Check if backup exists
@@ -162,6 +487,16 @@ impl BackupManager {
             fs::remove_file(target_path)?;
         }
 
+        // A WAL file left by the replaced database would be replayed onto the backup
+        for suffix in ["-wal", "-shm"] {
+            let mut sidecar = target_path.as_os_str().to_owned();
+            sidecar.push(suffix);
+            match fs::remove_file(&sidecar) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+
         // Copy the backup file to the target location
         log_info!(
             "backup",
@@ -171,16 +506,14 @@ impl BackupManager {
                 target_path.display()
             )
         );
-        self.copy_file(backup_path, target_path)?;
+        self.extract_backup_file(backup_path, target_path)?;
 
         // Verify the content was restored correctly
         let mut restored_content = Vec::new();
         let mut file = File::open(target_path)?;
         file.read_to_end(&mut restored_content)?;
 
-        let mut original_content = Vec::new();
-        let mut orig_file = File::open(backup_path)?;
-        orig_file.read_to_end(&mut original_content)?;
+        let original_content = read_backup_image(backup_path)?;
 
         if restored_content != original_content {
             log_error!(
@@ -205,6 +538,81 @@ impl BackupManager {
         Ok(())
     }
 
+    /// Restore the newest backup to `target_path`, returning the backup restored
+    pub fn restore_latest(&self, target_path: &Path) -> io::Result<(PathBuf, BackupMetadata)> {
+        let (backup_path, metadata) = self.latest_backup()?;
+        self.restore_backup(&backup_path, target_path)?;
+        Ok((backup_path, metadata))
+    }
+
+    /// Get the newest backup
+    pub fn latest_backup(&self) -> io::Result<(PathBuf, BackupMetadata)> {
+        self.list_backups()?.into_iter().next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No backups found in {}", self.backup_dir.display()),
+            )
+        })
+    }
+
+    /// Verify a backup against the checksum recorded in its metadata
+    ///
+    /// Returns `false` if the file no longer matches its checksum. Backups made
+    /// before checksums were recorded cannot be verified and are reported as valid.
+    pub fn verify_backup(&self, backup_path: &Path) -> io::Result<bool> {
+        let filename = backup_path
+            .file_name()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid backup path: {}", backup_path.display()),
+                )
+            })?
+            .to_string_lossy()
+            .to_string();
+
+        let metadata = self.read_metadata(&filename)?;
+        let expected = match metadata.sha256 {
+            Some(expected) => expected,
+            None => {
+                log_warning!(
+                    "backup",
+                    &format!(
+                        "Backup {} has no checksum and cannot be verified",
+                        backup_path.display()
+                    )
+                );
+                return Ok(true);
+            }
+        };
+
+        let actual = sha256_file(backup_path)?;
+        if actual != expected {
+            log_error!(
+                "backup",
+                &format!(
+                    "Checksum mismatch for backup {}: expected {}, found {}",
+                    backup_path.display(),
+                    expected,
+                    actual
+                )
+            );
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Read the metadata of a backup in the backup directory
+    pub fn backup_metadata(&self, backup_filename: &str) -> io::Result<BackupMetadata> {
+        self.read_metadata(backup_filename)
+    }
+
+    /// Get the path of a backup in the backup directory
+    pub fn backup_path(&self, backup_filename: &str) -> PathBuf {
+        self.backup_dir.join(backup_filename)
+    }
+
     /// List available backups
     pub fn list_backups(&self) -> io::Result<Vec<(PathBuf, BackupMetadata)>> {
         let mut backups = Vec::new();
@@ -215,9 +623,8 @@ impl BackupManager {
             let path = entry.path();
 
             // Check if it's a backup file
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "db") {
-                let filename = path.file_name().unwrap().to_string_lossy().to_string();
-
+            let filename = entry.file_name().to_string_lossy().to_string();
+            if path.is_file() && backup_id(&filename).is_some() {
                 // Try to read metadata
                 match self.read_metadata(&filename) {
                     Ok(metadata) => {
@@ -234,7 +641,7 @@ impl BackupManager {
         }
 
         // Sort backups by timestamp (newest first)
-        backups.sort_by(|(_, a), (_, b)| b.timestamp.cmp(&a.timestamp));
+        backups.sort_by_key(|(_, metadata)| std::cmp::Reverse(metadata.created_at()));
 
         Ok(backups)
     }
@@ -272,12 +679,45 @@ impl BackupManager {
     }
 
     /// Rotate old backups
+    /// Pin or unpin a backup; pinned backups and the backups they build on survive rotation
+    pub fn set_pinned(&self, backup_path: &Path, pinned: bool) -> io::Result<()> {
+        let filename = backup_filename(backup_path)?;
+        let mut metadata = self.read_metadata(&filename)?;
+        metadata.pinned = pinned;
+        self.save_metadata(&filename, &metadata)
+    }
+
     fn rotate_backups(&self) -> io::Result<()> {
         // List all backups
         let mut backups = self.list_backups()?;
 
+        // Pinned backups, and the bases their restore depends on, are never rotated out
+        let bases: std::collections::HashMap<String, Option<String>> = backups
+            .iter()
+            .filter_map(|(path, metadata)| {
+                let filename = backup_filename(path).ok()?;
+                Some((filename, metadata.base_backup.clone()))
+            })
+            .collect();
+        let mut protected = std::collections::HashSet::new();
+        for (path, metadata) in &backups {
+            if !metadata.pinned {
+                continue;
+            }
+            let mut next = backup_filename(path).ok();
+            while let Some(filename) = next {
+                if !protected.insert(filename.clone()) {
+                    break;
+                }
+                next = bases.get(&filename).cloned().flatten();
+            }
+        }
+        backups.retain(|(path, _)| {
+            backup_filename(path).is_ok_and(|filename| !protected.contains(&filename))
+        });
+
         // Sort backups by timestamp (newest first)
-        backups.sort_by(|(_, a), (_, b)| b.timestamp.cmp(&a.timestamp));
+        backups.sort_by_key(|(_, metadata)| std::cmp::Reverse(metadata.created_at()));
 
         log_info!(
             "backup",
@@ -292,16 +732,32 @@ impl BackupManager {
         if backups.len() > self.max_backups {
             let to_delete = backups.len() - self.max_backups;
             log_info!("backup", &format!("Deleting {} old backups", to_delete));
-            for (path, _) in backups.iter().rev().take(to_delete) {
+
+            // Oldest first, so an incremental whose base was just deleted is removed too
+            let mut deleted = std::collections::HashSet::new();
+            for (index, (path, metadata)) in backups.iter().rev().enumerate() {
+                let orphaned = metadata
+                    .base_backup
+                    .as_ref()
+                    .is_some_and(|base| deleted.contains(base));
+                if index >= to_delete && !orphaned {
+                    continue;
+                }
+
                 log_info!(
                     "backup",
                     &format!("Deleting old backup: {}", path.display())
                 );
-                if let Err(e) = self.delete_backup(path) {
-                    log_warning!(
-                        "backup",
-                        &format!("Failed to delete old backup {}: {}", path.display(), e)
-                    );
+                match self.delete_backup(path) {
+                    Ok(()) => {
+                        deleted.insert(backup_filename(path)?);
+                    }
+                    Err(e) => {
+                        log_warning!(
+                            "backup",
+                            &format!("Failed to delete old backup {}: {}", path.display(), e)
+                        );
+                    }
                 }
             }
         }
@@ -309,18 +765,213 @@ impl BackupManager {
         Ok(())
     }
 
-    /// Copy a file
-    fn copy_file(&self, source: &Path, destination: &Path) -> io::Result<()> {
-        // Open source file
-        let mut source_file = File::open(source)?;
+    /// Find the full backup with the given ID in any compression
+    pub fn find_backup(&self, backup_id: &str) -> Option<PathBuf> {
+        [
+            CompressionMode::None,
+            CompressionMode::Gzip,
+            CompressionMode::Zstd,
+        ]
+        .iter()
+        .map(|mode| {
+            self.backup_dir
+                .join(format!("backup_{}.{}", backup_id, mode.extension()))
+        })
+        .find(|path| path.exists())
+    }
+
+    /// Upload a backup and its metadata to `s3://{bucket}/{key_prefix}/{filename}`, returning that URL
+    ///
+    /// Unless a client was set, credentials and region come from the standard
+    /// AWS environment variables and config files.
+    pub async fn export_to_s3(
+        &self,
+        backup_path: &Path,
+        bucket: &str,
+        key_prefix: &str,
+    ) -> io::Result<String> {
+        let filename = backup_filename(backup_path)?;
+        let key = s3_key(key_prefix, &filename);
+        let metadata_path = backup_path.with_file_name(format!("{}.meta", filename));
+        let client = self.s3_client().await;
+
+        let upload = |key: String, path: PathBuf| {
+            let client = client.clone();
+            async move {
+                let body = ByteStream::from_path(&path)
+                    .await
+                    .map_err(io::Error::other)?;
+                client
+                    .put_object()
+                    .bucket(bucket)
+                    .key(&key)
+                    .body(body)
+                    .send()
+                    .await
+                    .map_err(|e| s3_error(&format!("upload s3://{}/{}", bucket, key), e))?;
+                Ok::<_, io::Error>(())
+            }
+        };
+        upload(key.clone(), backup_path.to_path_buf()).await?;
+        // Backups made without metadata are uploaded alone, as they are listed locally
+        if metadata_path.exists() {
+            upload(format!("{}.meta", key), metadata_path).await?;
+        }
+
+        let url = format!("s3://{}/{}", bucket, key);
+        log_info!(
+            "backup",
+            &format!("Exported backup {} to {}", backup_path.display(), url)
+        );
+        Ok(url)
+    }
+
+    /// Download the backup at `s3://{bucket}/{key}` to `target`, with its metadata if it has any
+    ///
+    /// A backup whose metadata records a checksum it does not match is removed
+    /// again and reported as invalid data.
+    pub async fn import_from_s3(&self, bucket: &str, key: &str, target: &Path) -> io::Result<()> {
+        let client = self.s3_client().await;
+        let metadata_key = format!("{}.meta", key);
+        let metadata = match client
+            .get_object()
+            .bucket(bucket)
+            .key(&metadata_key)
+            .send()
+            .await
+        {
+            Ok(output) => {
+                let bytes = output.body.collect().await.map_err(|e| {
+                    s3_error(&format!("download s3://{}/{}", bucket, metadata_key), e)
+                })?;
+                let metadata: BackupMetadata = serde_json::from_slice(&bytes.into_bytes())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                Some(metadata)
+            }
+            Err(e) if e.raw_response().is_some_and(|r| r.status().as_u16() == 404) => None,
+            Err(e) => {
+                return Err(s3_error(
+                    &format!("download s3://{}/{}", bucket, metadata_key),
+                    e,
+                ))
+            }
+        };
+
+        let action = format!("download s3://{}/{}", bucket, key);
+        let mut body = client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| s3_error(&action, e))?
+            .body;
+        let mut file = tokio::fs::File::create(target).await?;
+        while let Some(chunk) = body.try_next().await.map_err(|e| s3_error(&action, e))? {
+            tokio::io::AsyncWriteExt::write_all(&mut file, &chunk).await?;
+        }
+        tokio::io::AsyncWriteExt::flush(&mut file).await?;
+        drop(file);
+
+        if let Some(metadata) = metadata {
+            if let Some(expected) = &metadata.sha256 {
+                let actual = sha256_file(target)?;
+                if &actual != expected {
+                    fs::remove_file(target)?;
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Checksum mismatch for s3://{}/{}: expected {}, found {}",
+                            bucket, key, expected, actual
+                        ),
+                    ));
+                }
+            }
+            let metadata_json =
+                serde_json::to_string_pretty(&metadata).map_err(io::Error::other)?;
+            fs::write(
+                target.with_file_name(format!("{}.meta", backup_filename(target)?)),
+                metadata_json,
+            )?;
+        }
+
+        log_info!(
+            "backup",
+            &format!(
+                "Imported backup s3://{}/{} to {}",
+                bucket,
+                key,
+                target.display()
+            )
+        );
+        Ok(())
+    }
+
+    /// Get the client for S3 exports and imports
+    async fn s3_client(&self) -> aws_sdk_s3::Client {
+        match &self.s3_client {
+            Some(client) => client.clone(),
+            None => {
+                let config = aws_config::from_env()
+                    .http_client(s3_http_client())
+                    .load()
+                    .await;
+                aws_sdk_s3::Client::new(&config)
+            }
+        }
+    }
 
-        // Create destination file
-        let mut dest_file = File::create(destination)?;
+    /// Write a consistent copy of a database into a backup file, compressing it as configured
+    ///
+    /// A live database in WAL mode keeps committed writes in its `-wal` file
+    /// until a checkpoint, so databases are copied with the online backup API
+    /// first; any other file is copied as is.
+    fn write_backup_file(&self, source: &Path, destination: &Path) -> io::Result<()> {
+        if !is_sqlite_database(source)? {
+            return self.compress_file(source, destination);
+        }
+
+        let snapshot_path =
+            destination.with_file_name(format!("{}.snapshot", backup_filename(destination)?));
+        let result = copy_database(source, &snapshot_path)
+            .and_then(|()| self.compress_file(&snapshot_path, destination));
+        let _ = fs::remove_file(&snapshot_path);
+        result
+    }
 
-        // Copy data
-        let mut buffer = Vec::new();
-        source_file.read_to_end(&mut buffer)?;
-        dest_file.write_all(&buffer)?;
+    /// Stream a file into a backup file, compressing it as configured
+    fn compress_file(&self, source: &Path, destination: &Path) -> io::Result<()> {
+        let mut reader = BufReader::new(File::open(source)?);
+        let writer = BufWriter::new(File::create(destination)?);
+
+        match self.compression {
+            CompressionMode::None => {
+                let mut writer = writer;
+                io::copy(&mut reader, &mut writer)?;
+                writer.flush()?;
+            }
+            CompressionMode::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                io::copy(&mut reader, &mut encoder)?;
+                encoder.finish()?.flush()?;
+            }
+            CompressionMode::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(writer, 0)?;
+                io::copy(&mut reader, &mut encoder)?;
+                encoder.finish()?.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stream a backup file to a database, decompressing it according to its extension
+    fn extract_backup_file(&self, backup_path: &Path, destination: &Path) -> io::Result<()> {
+        let mut reader = open_backup_reader(backup_path)?;
+        let mut writer = BufWriter::new(File::create(destination)?);
+        io::copy(&mut reader, &mut writer)?;
+        writer.flush()?;
 
         Ok(())
     }
@@ -343,9 +994,8 @@ impl BackupManager {
 
         // If metadata file doesn't exist, try to extract information from the filename
         if !metadata_path.exists() {
-            if let Some(timestamp_str) = backup_filename
-                .strip_prefix("backup_")
-                .and_then(|s| s.strip_suffix(".db"))
+            if let Some(timestamp_str) =
+                backup_id(backup_filename).and_then(|s| s.split('_').next())
             {
                 if let Ok(timestamp) = timestamp_str.parse::<u64>() {
                     let backup_path = self.backup_dir.join(backup_filename);
@@ -361,6 +1011,12 @@ impl BackupManager {
                         size,
                         version: "unknown".to_string(),
                         backup_type: "unknown".to_string(),
+                        sha256: None,
+                        base_backup: None,
+                        compression: CompressionMode::from_path(Path::new(backup_filename))
+                            .as_str()
+                            .to_string(),
+                        pinned: false,
                     });
                 }
             }
@@ -384,13 +1040,714 @@ impl BackupManager {
     }
 }
 
+/// Periodically creates automatic backups of a database
+pub struct BackupScheduler {
+    /// Manager that creates and rotates the backups
+    backup_manager: Arc<BackupManager>,
+    /// Database to back up
+    db_path: PathBuf,
+    /// Time between backups
+    interval: Duration,
+    /// Database size in bytes above which the database is compacted after a backup
+    auto_compact_threshold: Option<u64>,
+    /// Bucket and key prefix each backup is copied to after it is made
+    s3_export: Option<(String, String)>,
+    /// Stops the scheduler once any in-progress backup has finished
+    cancellation_token: CancellationToken,
+}
+
+impl BackupScheduler {
+    /// Create a scheduler backing up `db_path` every `interval`
+    pub fn new(backup_manager: BackupManager, db_path: &Path, interval: Duration) -> Self {
+        Self {
+            backup_manager: Arc::new(backup_manager),
+            db_path: db_path.to_path_buf(),
+            interval,
+            auto_compact_threshold: None,
+            s3_export: None,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// Compact the database after each backup once it grows beyond `threshold_bytes`
+    ///
+    /// A threshold of 0 never compacts.
+    pub fn with_auto_compact_threshold(mut self, threshold_bytes: u64) -> Self {
+        self.auto_compact_threshold = Some(threshold_bytes).filter(|&bytes| bytes > 0);
+        self
+    }
+
+    /// Copy each backup to `s3://{bucket}/{key_prefix}/` after it is made
+    pub fn with_s3_export(mut self, bucket: &str, key_prefix: &str) -> Self {
+        self.s3_export = Some((bucket.to_string(), key_prefix.to_string()));
+        self
+    }
+
+    /// Copy each backup to the bucket named by `S3_BACKUP_BUCKET`, if it is set
+    ///
+    /// Backups are stored under `S3_BACKUP_PREFIX`, by default `smart-memory/backups`.
+    pub fn with_s3_export_from_env(self) -> Self {
+        match std::env::var(S3_BACKUP_BUCKET_VAR) {
+            Ok(bucket) if !bucket.is_empty() => {
+                let key_prefix = std::env::var(S3_BACKUP_PREFIX_VAR)
+                    .unwrap_or_else(|_| DEFAULT_S3_BACKUP_PREFIX.to_string());
+                self.with_s3_export(&bucket, &key_prefix)
+            }
+            _ => self,
+        }
+    }
+
+    /// Get a token that stops the scheduler when cancelled
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation_token.clone()
+    }
+
+    /// Start creating backups in a background task
+    pub fn start(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = self.cancellation_token.cancelled() => break,
+                    _ = tokio::time::sleep(self.interval) => {}
+                }
+
+                if !self.db_path.exists() {
+                    continue;
+                }
+
+                // Cancellation is only observed between backups, never during one
+                let backup_manager = self.backup_manager.clone();
+                let db_path = self.db_path.clone();
+                let auto_compact_threshold = self.auto_compact_threshold;
+                let result = tokio::task::spawn_blocking(move || {
+                    let backup = backup_manager.create_auto_backup(&db_path)?;
+                    // Only compact once the current contents are safely backed up
+                    if let Some(threshold) = auto_compact_threshold {
+                        compact_if_larger(&db_path, threshold);
+                    }
+                    vacuum_free_pages(&db_path, AUTO_VACUUM_PAGES);
+                    Ok::<_, io::Error>(backup)
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(backup)) => {
+                        if let Some((bucket, key_prefix)) = &self.s3_export {
+                            // The local backup stands even if the copy fails
+                            if let Err(e) = self
+                                .backup_manager
+                                .export_to_s3(&backup, bucket, key_prefix)
+                                .await
+                            {
+                                log_warning!(
+                                    "backup",
+                                    &format!("Scheduled S3 export failed: {}", e)
+                                );
+                            }
+                        }
+                    }
+                    Ok(Err(e)) => {
+                        log_warning!("backup", &format!("Scheduled backup failed: {}", e));
+                    }
+                    Err(e) => {
+                        log_error!("backup", &format!("Scheduled backup task failed: {}", e));
+                    }
+                }
+            }
+
+            log_info!("backup", "Backup scheduler stopped");
+        })
+    }
+}
+
+/// Compact the database at `db_path` if it is larger than `threshold` bytes
+fn compact_if_larger(db_path: &Path, threshold: u64) {
+    let size = database_size(db_path);
+    if size <= threshold {
+        return;
+    }
+
+    let result = rusqlite::Connection::open(db_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|connection| {
+            connection.busy_timeout(Duration::from_secs(5))?;
+            compact_database(&connection, db_path)
+        });
+    match result {
+        Ok(bytes_saved) => log_info!(
+            "backup",
+            &format!(
+                "Compacted {} bytes from {} after it grew to {} bytes",
+                bytes_saved,
+                db_path.display(),
+                size
+            )
+        ),
+        Err(e) => log_warning!("backup", &format!("Scheduled compaction failed: {:#}", e)),
+    }
+}
+
+/// Return up to `pages` free pages of the database at `db_path` to the file system
+fn vacuum_free_pages(db_path: &Path, pages: u32) {
+    let result = rusqlite::Connection::open(db_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|connection| {
+            connection.busy_timeout(Duration::from_secs(5))?;
+            incremental_vacuum(&connection, pages)
+        });
+    match result {
+        Ok(0) => {}
+        Ok(pages_freed) => log_info!(
+            "backup",
+            &format!("Freed {} pages from {}", pages_freed, db_path.display())
+        ),
+        Err(e) => log_warning!("backup", &format!("Scheduled vacuum failed: {:#}", e)),
+    }
+}
+
+/// Join a key prefix and a file name into an S3 object key
+fn s3_key(key_prefix: &str, filename: &str) -> String {
+    let key_prefix = key_prefix.trim_matches('/');
+    if key_prefix.is_empty() {
+        filename.to_string()
+    } else {
+        format!("{}/{}", key_prefix, filename)
+    }
+}
+
+/// Convert a failed S3 request into an I/O error naming what was being done
+fn s3_error(action: &str, error: impl std::error::Error) -> io::Error {
+    io::Error::other(format!(
+        "Failed to {}: {}",
+        action,
+        DisplayErrorContext(error)
+    ))
+}
+
+/// Get the ID of a backup from its file name, or `None` if it is not a backup file
+pub fn backup_id(backup_filename: &str) -> Option<&str> {
+    let stem = backup_filename.strip_prefix("backup_")?;
+    BACKUP_EXTENSIONS
+        .iter()
+        .find_map(|extension| stem.strip_suffix(extension))
+}
+
+/// Open a backup file for reading, decompressing it according to its extension
+fn open_backup_reader(backup_path: &Path) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(backup_path)?);
+    Ok(match CompressionMode::from_path(backup_path) {
+        CompressionMode::None => Box::new(file),
+        CompressionMode::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+        CompressionMode::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+    })
+}
+
+/// Read the database image stored in a full backup file
+fn read_backup_image(backup_path: &Path) -> io::Result<Vec<u8>> {
+    let mut image = Vec::new();
+    open_backup_reader(backup_path)?.read_to_end(&mut image)?;
+    Ok(image)
+}
+
+/// Get the file name of a backup path
+fn backup_filename(path: &Path) -> io::Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid backup path: {}", path.display()),
+            )
+        })
+}
+
+/// HTTPS client for S3, using rustls with ring like the rest of the server
+fn s3_http_client() -> aws_sdk_s3::config::SharedHttpClient {
+    aws_smithy_http_client::Builder::new()
+        .tls_provider(Provider::Rustls(CryptoMode::Ring))
+        .build_https()
+}
+
+/// Check whether a file holds a SQLite database
+fn is_sqlite_database(path: &Path) -> io::Result<bool> {
+    let mut header = [0; SQLITE_HEADER.len()];
+    match File::open(path)?.read_exact(&mut header) {
+        Ok(()) => Ok(&header == SQLITE_HEADER),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Copy a database, including writes still in its WAL file, with SQLite's online backup API
+fn copy_database(source_path: &Path, destination: &Path) -> io::Result<()> {
+    let source = rusqlite::Connection::open(source_path).map_err(io::Error::other)?;
+    let mut copy = rusqlite::Connection::open(destination).map_err(io::Error::other)?;
+    let backup = rusqlite::backup::Backup::new(&source, &mut copy).map_err(io::Error::other)?;
+    backup
+        .run_to_completion(SNAPSHOT_PAGES_PER_STEP, Duration::ZERO, None)
+        .map_err(io::Error::other)
+}
+
+/// Read the page size from a SQLite database header
+fn sqlite_page_size(image: &[u8]) -> io::Result<usize> {
+    if image.len() < 100 || !image.starts_with(SQLITE_HEADER) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not a SQLite database",
+        ));
+    }
+
+    // A stored value of 1 means 65536
+    match u16::from_be_bytes([image[16], image[17]]) {
+        1 => Ok(65536),
+        size => Ok(size as usize),
+    }
+}
+
+/// Encode the pages of `current` that differ from `base`
+///
+/// Layout: magic, page size and page count as big-endian `u32`s, then one
+/// big-endian `u32` page number followed by the page contents per changed page.
+fn encode_incremental(base: &[u8], current: &[u8]) -> io::Result<Vec<u8>> {
+    let page_size = sqlite_page_size(current)?;
+    let page_count = current.len() / page_size;
+
+    let mut encoded = Vec::with_capacity(INCREMENTAL_MAGIC.len() + 8);
+    encoded.extend_from_slice(INCREMENTAL_MAGIC);
+    encoded.extend_from_slice(&(page_size as u32).to_be_bytes());
+    encoded.extend_from_slice(&(page_count as u32).to_be_bytes());
+
+    for (page_number, page) in current.chunks(page_size).enumerate() {
+        let start = page_number * page_size;
+        if base.get(start..start + page_size) != Some(page) {
+            encoded.extend_from_slice(&(page_number as u32).to_be_bytes());
+            encoded.extend_from_slice(page);
+        }
+    }
+
+    Ok(encoded)
+}
+
+/// Apply an incremental backup to a database image in place
+fn apply_incremental(image: &mut Vec<u8>, incremental: &[u8]) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+    let header_len = INCREMENTAL_MAGIC.len() + 8;
+    if incremental.len() < header_len || !incremental.starts_with(INCREMENTAL_MAGIC) {
+        return Err(invalid("Not an incremental backup"));
+    }
+    let read_u32 = |offset: usize| {
+        u32::from_be_bytes(incremental[offset..offset + 4].try_into().unwrap()) as usize
+    };
+    let page_size = read_u32(INCREMENTAL_MAGIC.len());
+    let page_count = read_u32(INCREMENTAL_MAGIC.len() + 4);
+    if page_size == 0 {
+        return Err(invalid("Invalid page size in incremental backup"));
+    }
+
+    image.resize(page_size * page_count, 0);
+    for entry in incremental[header_len..].chunks(4 + page_size) {
+        if entry.len() != 4 + page_size {
+            return Err(invalid("Truncated incremental backup"));
+        }
+        let page_number = u32::from_be_bytes(entry[..4].try_into().unwrap()) as usize;
+        if page_number >= page_count {
+            return Err(invalid("Page number out of range in incremental backup"));
+        }
+        let start = page_number * page_size;
+        image[start..start + page_size].copy_from_slice(&entry[4..]);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
     use std::fs;
     use std::io::Write;
     use tempfile::tempdir;
 
+    proptest! {
+        #[test]
+        fn test_verify_backup_detects_corruption(
+            content in proptest::collection::vec(any::<u8>(), 1..4096),
+            position in any::<prop::sample::Index>(),
+            flip in 1u8..=255,
+        ) {
+            let temp_dir = tempdir()?;
+            let db_path = temp_dir.path().join("test.db");
+            fs::write(&db_path, &content)?;
+
+            let backup_manager = BackupManager::new(&temp_dir.path().join("backups"))?;
+            let backup_path = backup_manager.create_backup(&db_path, "Checksummed backup")?;
+            prop_assert!(backup_manager.verify_backup(&backup_path)?);
+
+            // Flip bits in one byte of the backup
+            let mut corrupted = fs::read(&backup_path)?;
+            let index = position.index(corrupted.len());
+            corrupted[index] ^= flip;
+            fs::write(&backup_path, &corrupted)?;
+
+            prop_assert!(!backup_manager.verify_backup(&backup_path)?);
+        }
+    }
+
+    #[test]
+    fn test_incremental_backup_chain() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("memories.db");
+        let backup_manager = BackupManager::new(&temp_dir.path().join("backups"))?;
+        let to_io = |e: rusqlite::Error| io::Error::other(e);
+
+        let connection = rusqlite::Connection::open(&db_path).map_err(to_io)?;
+        let insert_rows = |range: std::ops::Range<i64>| -> io::Result<()> {
+            for i in range {
+                connection
+                    .execute(
+                        "INSERT INTO notes (id, body) VALUES (?, ?)",
+                        rusqlite::params![i, "x".repeat(200)],
+                    )
+                    .map_err(to_io)?;
+            }
+            Ok(())
+        };
+        connection
+            .execute("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)", [])
+            .map_err(to_io)?;
+        insert_rows(0..200)?;
+
+        let full = backup_manager.create_backup(&db_path, "Base")?;
+        insert_rows(200..210)?;
+        let first = backup_manager.create_incremental_backup(&db_path, &full)?;
+        insert_rows(210..220)?;
+        let second = backup_manager.create_incremental_backup(&db_path, &first)?;
+
+        // Only changed pages are stored
+        assert!(fs::metadata(&first)?.len() < fs::metadata(&full)?.len() / 2);
+
+        let backups = backup_manager.list_backups()?;
+        assert_eq!(
+            backups.iter().filter(|(_, m)| m.is_incremental()).count(),
+            2
+        );
+
+        let restored = temp_dir.path().join("restored.db");
+        backup_manager.restore_incremental(&full, &[&first, &second], &restored)?;
+        let count: i64 = rusqlite::Connection::open(&restored)
+            .and_then(|c| c.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0)))
+            .map_err(to_io)?;
+        assert_eq!(count, 220);
+
+        // Links must be applied in chain order
+        assert!(backup_manager
+            .restore_incremental(&full, &[&second], &restored)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_latest_picks_newest_backup() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("memories.db");
+        let backup_manager = BackupManager::new(&temp_dir.path().join("backups"))?;
+
+        for version in ["first", "second", "third"] {
+            fs::write(&db_path, format!("{} version", version))?;
+            backup_manager.create_backup(&db_path, version)?;
+            // Keep the backup timestamps apart
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        fs::write(&db_path, b"current version")?;
+
+        let (backup_path, metadata) = backup_manager.restore_latest(&db_path)?;
+        assert_eq!(metadata.description, "third");
+        assert_eq!(
+            backup_manager
+                .backup_metadata(&backup_filename(&backup_path)?)?
+                .timestamp,
+            metadata.timestamp
+        );
+        assert_eq!(fs::read(&db_path)?, b"third version");
+
+        // The replaced contents were backed up first, so they are now the newest
+        let (_, newest) = backup_manager.latest_backup()?;
+        assert!(newest.description.starts_with("Pre-restore backup"));
+
+        let empty = BackupManager::new(&temp_dir.path().join("empty"))?;
+        assert_eq!(
+            empty.restore_latest(&db_path).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_scheduler() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("memories.db");
+        fs::write(&db_path, b"Scheduled content")?;
+
+        let backup_dir = temp_dir.path().join("backups");
+        let scheduler = BackupScheduler::new(
+            BackupManager::new(&backup_dir)?,
+            &db_path,
+            Duration::from_millis(100),
+        );
+        let cancellation_token = scheduler.cancellation_token();
+        let handle = scheduler.start();
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        cancellation_token.cancel();
+        handle.await.unwrap();
+
+        assert!(BackupManager::new(&backup_dir)?.list_backups()?.len() >= 3);
+        Ok(())
+    }
+
+    /// Client of a mock S3 server, addressing buckets by path
+    fn mock_s3_client(server: &mockito::Server) -> aws_sdk_s3::Client {
+        use aws_sdk_s3::config::{
+            BehaviorVersion, Credentials, Region, RequestChecksumCalculation,
+        };
+
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("key", "secret", None, None, "test"))
+            .http_client(s3_http_client())
+            .endpoint_url(server.url())
+            .force_path_style(true)
+            // Send bodies as they are so that the mock can match them
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .build();
+        aws_sdk_s3::Client::from_conf(config)
+    }
+
+    /// Serve an object of the `backups` bucket from a mock S3 server
+    async fn mock_object(
+        server: &mut mockito::Server,
+        key: &str,
+        body: &'static [u8],
+    ) -> mockito::Mock {
+        server
+            .mock("GET", format!("/backups/{}", key).as_str())
+            .match_query(mockito::Matcher::Any)
+            .with_body(body)
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_export_to_s3() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("memories.db");
+        fs::write(&db_path, b"Exported content")?;
+        let mut backup_manager = BackupManager::with_compression(
+            &temp_dir.path().join("backups"),
+            CompressionMode::None,
+        )?;
+        let backup_path = backup_manager.create_backup(&db_path, "Exported")?;
+        let filename = backup_filename(&backup_path)?;
+
+        let mut server = mockito::Server::new_async().await;
+        backup_manager.set_s3_client(mock_s3_client(&server));
+        let backup = server
+            .mock("PUT", format!("/backups/nightly/{}", filename).as_str())
+            .match_query(mockito::Matcher::Any)
+            .match_body("Exported content")
+            .expect(1)
+            .create_async()
+            .await;
+        let metadata = server
+            .mock(
+                "PUT",
+                format!("/backups/nightly/{}.meta", filename).as_str(),
+            )
+            .match_query(mockito::Matcher::Any)
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"description": "Exported"}"#.to_string(),
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let url = backup_manager
+            .export_to_s3(&backup_path, "backups", "/nightly/")
+            .await?;
+        assert_eq!(url, format!("s3://backups/nightly/{}", filename));
+        backup.assert_async().await;
+        metadata.assert_async().await;
+
+        // Failed uploads are reported
+        let failing = server
+            .mock("PUT", mockito::Matcher::Any)
+            .with_status(403)
+            .create_async()
+            .await;
+        assert!(backup_manager
+            .export_to_s3(&backup_path, "forbidden", "")
+            .await
+            .is_err());
+        failing.assert_async().await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_from_s3() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("memories.db");
+        fs::write(&db_path, b"Imported content")?;
+        let source = BackupManager::with_compression(
+            &temp_dir.path().join("source"),
+            CompressionMode::None,
+        )?;
+        let backup_path = source.create_backup(&db_path, "Imported")?;
+        let filename = backup_filename(&backup_path)?;
+        let metadata_json = fs::read_to_string(source.backup_path(&format!("{}.meta", filename)))?;
+
+        let mut server = mockito::Server::new_async().await;
+        let mut backup_manager = BackupManager::new(&temp_dir.path().join("backups"))?;
+        backup_manager.set_s3_client(mock_s3_client(&server));
+        let metadata = server
+            .mock("GET", format!("/backups/{}.meta", filename).as_str())
+            .match_query(mockito::Matcher::Any)
+            .with_body(&metadata_json)
+            .create_async()
+            .await;
+        let backup = mock_object(&mut server, &filename, b"Imported content").await;
+        let target = backup_manager.backup_path(&filename);
+        backup_manager
+            .import_from_s3("backups", &filename, &target)
+            .await?;
+        assert_eq!(fs::read(&target)?, b"Imported content");
+        assert_eq!(
+            backup_manager.backup_metadata(&filename)?.description,
+            "Imported"
+        );
+        assert!(backup_manager.verify_backup(&target)?);
+        backup.remove_async().await;
+
+        // A download that does not match its checksum is discarded
+        let tampered = mock_object(&mut server, &filename, b"Tampered content").await;
+        let tampered_target = temp_dir.path().join(&filename);
+        let error = backup_manager
+            .import_from_s3("backups", &filename, &tampered_target)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(!tampered_target.exists());
+        tampered.remove_async().await;
+        metadata.remove_async().await;
+
+        // Backups without metadata are imported as they are
+        let _missing = server
+            .mock("GET", mockito::Matcher::Regex(r"\.meta$".to_string()))
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .create_async()
+            .await;
+        let _bare = mock_object(&mut server, "bare.db", b"Bare content").await;
+        let bare_target = temp_dir.path().join("bare.db");
+        backup_manager
+            .import_from_s3("backups", "bare.db", &bare_target)
+            .await?;
+        assert_eq!(fs::read(&bare_target)?, b"Bare content");
+        assert!(!temp_dir.path().join("bare.db.meta").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_scheduler_exports_to_s3() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("memories.db");
+        fs::write(&db_path, b"Scheduled content")?;
+
+        let mut server = mockito::Server::new_async().await;
+        let uploads = server
+            .mock(
+                "PUT",
+                mockito::Matcher::Regex(r"^/offsite/scheduled/backup_[^/]+$".to_string()),
+            )
+            .match_query(mockito::Matcher::Any)
+            .expect_at_least(2)
+            .create_async()
+            .await;
+
+        let mut backup_manager = BackupManager::new(&temp_dir.path().join("backups"))?;
+        backup_manager.set_s3_client(mock_s3_client(&server));
+        let scheduler = BackupScheduler::new(backup_manager, &db_path, Duration::from_millis(100))
+            .with_s3_export("offsite", "scheduled");
+        let cancellation_token = scheduler.cancellation_token();
+        let handle = scheduler.start();
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        cancellation_token.cancel();
+        handle.await.unwrap();
+
+        // Each backup is uploaded together with its metadata
+        uploads.assert_async().await;
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_backups() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let backup_dir = temp_dir.path().join("backups");
+        let db_path = temp_dir.path().join("memories.db");
+        let content = b"Compressible content ".repeat(100);
+        fs::write(&db_path, &content)?;
+
+        // Backups made before and after compression is enabled live side by side
+        let mut backups = Vec::new();
+        for mode in [
+            CompressionMode::None,
+            CompressionMode::Gzip,
+            CompressionMode::Zstd,
+        ] {
+            let backup_manager = BackupManager::with_compression(&backup_dir, mode)?;
+            let backup_path = backup_manager.create_backup(&db_path, mode.as_str())?;
+            assert!(backup_path
+                .to_string_lossy()
+                .ends_with(&format!(".{}", mode.extension())));
+            backups.push(backup_path);
+        }
+
+        let backup_manager = BackupManager::with_compression(&backup_dir, CompressionMode::None)?;
+        let listed = backup_manager.list_backups()?;
+        assert_eq!(listed.len(), 3);
+        assert!(listed
+            .iter()
+            .any(|(_, metadata)| metadata.compression == "zstd"));
+
+        for backup_path in &backups {
+            assert!(backup_manager.verify_backup(backup_path)?);
+            fs::write(&db_path, b"Modified content")?;
+            backup_manager.restore_backup(backup_path, &db_path)?;
+            assert_eq!(fs::read(&db_path)?, content);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_backup_without_checksum_is_unverified() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let backup_manager = BackupManager::new(temp_dir.path())?;
+
+        // Backups from before checksums have no metadata file to read a checksum from
+        let backup_path = temp_dir.path().join("backup_1700000000.db");
+        fs::write(&backup_path, b"legacy backup")?;
+
+        assert!(backup_manager.verify_backup(&backup_path)?);
+        Ok(())
+    }
+
     #[test]
     fn test_create_and_restore_backup() -> io::Result<()> {
         // Create temporary directories
@@ -433,6 +1790,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_backup_includes_writes_in_wal() -> io::Result<()> {
+        use crate::storage::{MemoryId, MemoryStore, Tokenizer};
+        use std::collections::HashMap;
+
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("memories.db");
+        let backup_manager = BackupManager::with_compression(
+            &temp_dir.path().join("backups"),
+            CompressionMode::Zstd,
+        )?;
+
+        // The open store keeps its committed writes in the WAL file
+        let store =
+            MemoryStore::new_sqlite(&db_path, Tokenizer::default()).map_err(io::Error::other)?;
+        let memory = store
+            .store(
+                "written through the pool".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+            .map_err(io::Error::other)?;
+        let backup_path = backup_manager.create_backup(&db_path, "Live backup")?;
+        let auto_backup_path = backup_manager.create_auto_backup(&db_path)?;
+
+        for (index, backup_path) in [backup_path, auto_backup_path].iter().enumerate() {
+            let restored_path = temp_dir.path().join(format!("restored_{}.db", index));
+            backup_manager.restore_backup(backup_path, &restored_path)?;
+            let restored = MemoryStore::new_sqlite(&restored_path, Tokenizer::default())
+                .map_err(io::Error::other)?;
+            let retrieved = restored
+                .retrieve(&MemoryId::from(memory.id.as_str()))
+                .map_err(io::Error::other)?;
+            assert_eq!(retrieved.unwrap().content, "written through the pool");
+        }
+        drop(store);
+
+        Ok(())
+    }
+
     #[test]
     fn test_list_and_rotate_backups() -> io::Result<()> {
         // Create temporary directories
@@ -473,4 +1872,30 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_rotation_keeps_pinned_backups() -> io::Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("test.db");
+        fs::write(&db_path, b"Test content")?;
+
+        let mut backup_manager = BackupManager::new(&temp_dir.path().join("backups"))?;
+        backup_manager.set_max_backups(2);
+
+        let pinned = backup_manager.create_backup(&db_path, "Keep me")?;
+        backup_manager.set_pinned(&pinned, true)?;
+        for i in 1..=3 {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            backup_manager.create_backup(&db_path, &format!("Backup {}", i))?;
+        }
+
+        // The pinned backup survives and does not count toward the limit
+        let backups = backup_manager.list_backups()?;
+        assert_eq!(backups.len(), 3);
+        assert!(backups
+            .iter()
+            .any(|(path, metadata)| path == &pinned && metadata.pinned));
+
+        Ok(())
+    }
 }
@@ -3,5 +3,11 @@
 mod optimizer;
 pub mod relevance;
 
-pub use optimizer::{ContextOptimizer, TokenBudgetOptimizer};
-pub use relevance::{RelevanceScore, RelevanceScorer, TfIdfScorer};
+pub use optimizer::{
+    CategoryAwareOptimizer, ContextOptimizer, ExclusionReason, SummarizingOptimizer,
+    TokenBudgetOptimizer,
+};
+pub use relevance::{
+    EmbeddingScorer, HybridScorer, RelevanceScore, RelevanceScorer, TfIdfScorer,
+    DEFAULT_HYBRID_ALPHA,
+};
@@ -1,9 +1,62 @@
 //! Context optimization for memory retrieval
 
 use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use super::relevance::{RelevanceScore, ScoredMemory};
-use crate::storage::TokenCount;
+use crate::storage::{MemoryBankConfig, MemoryId, TokenCount, Tokenizer};
+
+/// Why an optimizer included a memory in the context or left it out
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExclusionReason {
+    /// The memory's relevance score, which is below the threshold
+    BelowThreshold(f64),
+    /// The memory needs more tokens than the budget had left
+    ExceedsTokenBudget { available: usize, needed: usize },
+    /// The named category had used up its token budget
+    CategoryBudgetExhausted(String),
+    /// The memory is part of the context
+    Included,
+}
+
+impl std::fmt::Display for ExclusionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BelowThreshold(score) => {
+                write!(f, "relevance {:.3} is below the threshold", score)
+            }
+            Self::ExceedsTokenBudget { available, needed } => write!(
+                f,
+                "needs {} tokens but only {} were left in the budget",
+                needed, available
+            ),
+            Self::CategoryBudgetExhausted(category) => {
+                write!(f, "category {} used up its token budget", category)
+            }
+            Self::Included => write!(f, "included"),
+        }
+    }
+}
+
+/// The decision an optimizer made about one memory
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimizationExplanation {
+    pub memory_id: MemoryId,
+    pub included: bool,
+    pub reason: ExclusionReason,
+}
+
+impl OptimizationExplanation {
+    /// Explain the decision `reason` about `scored`
+    fn new(scored: &ScoredMemory, reason: ExclusionReason) -> Self {
+        Self {
+            memory_id: scored.memory.id.clone(),
+            included: reason == ExclusionReason::Included,
+            reason,
+        }
+    }
+}
 
 /// Trait for optimizing context based on token budget
 pub trait ContextOptimizer: Send + Sync {
@@ -14,6 +67,54 @@ pub trait ContextOptimizer: Send + Sync {
         max_tokens: TokenCount,
         relevance_threshold: RelevanceScore,
     ) -> Result<Vec<ScoredMemory>>;
+
+    /// Explain why `optimize` includes or leaves out each memory, in input order
+    ///
+    /// By default memories left out above the threshold are put down to the
+    /// tokens left once the included memories are counted.
+    fn explain(
+        &self,
+        scored_memories: &[ScoredMemory],
+        max_tokens: TokenCount,
+        relevance_threshold: RelevanceScore,
+    ) -> Result<Vec<OptimizationExplanation>> {
+        let optimized = self.optimize(scored_memories, max_tokens, relevance_threshold)?;
+        let included: HashSet<&MemoryId> = optimized.iter().map(|s| &s.memory.id).collect();
+        let used: usize = optimized
+            .iter()
+            .map(|scored| scored.memory.token_count.as_usize())
+            .sum();
+        let available = max_tokens.as_usize().saturating_sub(used);
+
+        Ok(scored_memories
+            .iter()
+            .map(|scored| {
+                let reason = if included.contains(&scored.memory.id) {
+                    ExclusionReason::Included
+                } else if scored.score.as_f64() < relevance_threshold.as_f64() {
+                    ExclusionReason::BelowThreshold(scored.score.as_f64())
+                } else {
+                    ExclusionReason::ExceedsTokenBudget {
+                        available,
+                        needed: scored.memory.token_count.as_usize(),
+                    }
+                };
+                OptimizationExplanation::new(scored, reason)
+            })
+            .collect())
+    }
+}
+
+/// Pair each memory with the decision made about it
+fn explanations(
+    scored_memories: &[ScoredMemory],
+    reasons: Vec<ExclusionReason>,
+) -> Vec<OptimizationExplanation> {
+    scored_memories
+        .iter()
+        .zip(reasons)
+        .map(|(scored, reason)| OptimizationExplanation::new(scored, reason))
+        .collect()
 }
 
 /// Token budget based context optimizer
@@ -24,6 +125,48 @@ impl TokenBudgetOptimizer {
     pub fn new() -> Self {
         Self
     }
+
+    /// Decide about each memory in turn, in input order
+    fn decide(
+        scored_memories: &[ScoredMemory],
+        max_tokens: TokenCount,
+        relevance_threshold: RelevanceScore,
+    ) -> Vec<ExclusionReason> {
+        let mut reasons = Vec::with_capacity(scored_memories.len());
+        let mut total_tokens = TokenCount::from(0);
+        let mut added = false;
+        let mut stopped = false;
+
+        // Add memories until we reach the token budget or run out of memories
+        for memory in scored_memories {
+            // Skip memories below the relevance threshold
+            if memory.score.as_f64() < relevance_threshold.as_f64() {
+                reasons.push(ExclusionReason::BelowThreshold(memory.score.as_f64()));
+                continue;
+            }
+
+            // Once a memory overflows the budget after others were added, stop adding.
+            // The first memory is added even when it is too large on its own.
+            let new_total = total_tokens + memory.memory.token_count;
+            if stopped || (added && new_total.as_usize() > max_tokens.as_usize()) {
+                stopped = true;
+                reasons.push(ExclusionReason::ExceedsTokenBudget {
+                    available: max_tokens
+                        .as_usize()
+                        .saturating_sub(total_tokens.as_usize()),
+                    needed: memory.memory.token_count.as_usize(),
+                });
+                continue;
+            }
+
+            // Add the memory and update the total tokens
+            reasons.push(ExclusionReason::Included);
+            total_tokens = new_total;
+            added = true;
+        }
+
+        reasons
+    }
 }
 
 impl Default for TokenBudgetOptimizer {
@@ -39,35 +182,505 @@ impl ContextOptimizer for TokenBudgetOptimizer {
         max_tokens: TokenCount,
         relevance_threshold: RelevanceScore,
     ) -> Result<Vec<ScoredMemory>> {
-        let mut optimized_memories = Vec::new();
-        let mut total_tokens = TokenCount::from(0);
+        let reasons = Self::decide(scored_memories, max_tokens, relevance_threshold);
+        Ok(scored_memories
+            .iter()
+            .zip(reasons)
+            .filter(|(_, reason)| *reason == ExclusionReason::Included)
+            .map(|(memory, _)| memory.clone())
+            .collect())
+    }
 
-        // Add memories until we reach the token budget or run out of memories
-        for memory in scored_memories {
-            // Skip memories below the relevance threshold
-            if memory.score.as_f64() < relevance_threshold.as_f64() {
+    fn explain(
+        &self,
+        scored_memories: &[ScoredMemory],
+        max_tokens: TokenCount,
+        relevance_threshold: RelevanceScore,
+    ) -> Result<Vec<OptimizationExplanation>> {
+        let reasons = Self::decide(scored_memories, max_tokens, relevance_threshold);
+        Ok(explanations(scored_memories, reasons))
+    }
+}
+
+/// Context optimizer enforcing the per-category token budgets of a memory bank config
+///
+/// Categories are filled in priority order, each up to its own `max_tokens`,
+/// while the overall budget still applies. Memories without a configured
+/// category are only limited by the overall budget and are filled last.
+pub struct CategoryAwareOptimizer {
+    /// Config providing each category's budget and priority
+    config: MemoryBankConfig,
+}
+
+impl CategoryAwareOptimizer {
+    /// Create an optimizer for the categories in `config`
+    pub fn new(config: MemoryBankConfig) -> Self {
+        Self { config }
+    }
+
+    /// Decide about each memory, returning the decisions in input order
+    fn decide(
+        &self,
+        scored_memories: &[ScoredMemory],
+        max_tokens: TokenCount,
+        relevance_threshold: RelevanceScore,
+    ) -> Vec<ExclusionReason> {
+        let mut reasons = vec![ExclusionReason::Included; scored_memories.len()];
+
+        // Partition by category, keeping each partition in score order
+        let mut partitions: HashMap<Option<&str>, Vec<(usize, &ScoredMemory)>> = HashMap::new();
+        for (index, scored) in scored_memories.iter().enumerate() {
+            if scored.score.as_f64() < relevance_threshold.as_f64() {
+                reasons[index] = ExclusionReason::BelowThreshold(scored.score.as_f64());
                 continue;
             }
+            let category = scored
+                .memory
+                .category
+                .as_deref()
+                .filter(|category| self.config.categories.contains_key(*category));
+            partitions
+                .entry(category)
+                .or_default()
+                .push((index, scored));
+        }
 
-            // Check if adding this memory would exceed the token budget
-            let new_total = total_tokens + memory.memory.token_count;
-            if new_total.as_usize() > max_tokens.as_usize() {
-                // If we've already added some memories, stop here
-                if !optimized_memories.is_empty() {
-                    break;
+        // Highest priority first; unconfigured memories last; names break ties
+        let mut partitions: Vec<_> = partitions.into_iter().collect();
+        partitions.sort_by_key(|(category, _)| {
+            let priority = category.map(|category| self.config.get_priority(category));
+            (std::cmp::Reverse(priority), *category)
+        });
+
+        let mut total_tokens = 0;
+        for (category, memories) in partitions {
+            let cap = category.map_or(usize::MAX, |category| {
+                self.config.get_max_tokens(category).as_usize()
+            });
+
+            let mut category_tokens = 0;
+            for (index, scored) in memories {
+                let tokens = scored.memory.token_count.as_usize();
+                if let Some(category) = category.filter(|_| category_tokens + tokens > cap) {
+                    reasons[index] = ExclusionReason::CategoryBudgetExhausted(category.to_string());
+                    continue;
                 }
+                if total_tokens + tokens > max_tokens.as_usize() {
+                    reasons[index] = ExclusionReason::ExceedsTokenBudget {
+                        available: max_tokens.as_usize() - total_tokens,
+                        needed: tokens,
+                    };
+                    continue;
+                }
+                category_tokens += tokens;
+                total_tokens += tokens;
+            }
+        }
+
+        reasons
+    }
+}
 
-                // If this is the first memory and it's too large, add it anyway
-                // but truncate it to fit the budget
-                // In a real implementation, we would truncate the content
-                // For now, we'll just add it as is
+impl ContextOptimizer for CategoryAwareOptimizer {
+    fn optimize(
+        &self,
+        scored_memories: &[ScoredMemory],
+        max_tokens: TokenCount,
+        relevance_threshold: RelevanceScore,
+    ) -> Result<Vec<ScoredMemory>> {
+        let reasons = self.decide(scored_memories, max_tokens, relevance_threshold);
+        let mut selected: Vec<(usize, &ScoredMemory)> = scored_memories
+            .iter()
+            .enumerate()
+            .zip(reasons)
+            .filter(|(_, reason)| *reason == ExclusionReason::Included)
+            .map(|(selected, _)| selected)
+            .collect();
+
+        // Merge back into relevance order, ties in their original order
+        selected.sort_by(|(a_index, a), (b_index, b)| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a_index.cmp(b_index))
+        });
+
+        Ok(selected
+            .into_iter()
+            .map(|(_, scored)| scored.clone())
+            .collect())
+    }
+
+    fn explain(
+        &self,
+        scored_memories: &[ScoredMemory],
+        max_tokens: TokenCount,
+        relevance_threshold: RelevanceScore,
+    ) -> Result<Vec<OptimizationExplanation>> {
+        let reasons = self.decide(scored_memories, max_tokens, relevance_threshold);
+        Ok(explanations(scored_memories, reasons))
+    }
+}
+
+/// Context optimizer that truncates memories too large for the remaining budget
+///
+/// The inner optimizer picks the memories. Any of them that overflow the budget
+/// are cut down to the tokens left, and if budget remains, the most relevant
+/// memory that did not fit is cut down to it and added. Truncated memories keep
+/// their leading words and carry `truncated = "true"` in their metadata.
+pub struct SummarizingOptimizer {
+    /// Optimizer choosing the memories
+    inner: Arc<dyn ContextOptimizer>,
+    /// Tokenizer measuring truncated content
+    tokenizer: Tokenizer,
+}
+
+impl SummarizingOptimizer {
+    /// Wrap `inner`, measuring truncated content with `tokenizer`
+    pub fn new(inner: Arc<dyn ContextOptimizer>, tokenizer: Tokenizer) -> Self {
+        Self { inner, tokenizer }
+    }
+
+    /// Cut a memory down to its leading words fitting in `budget` tokens
+    ///
+    /// Returns `None` if not even one word fits.
+    fn truncate(&self, scored: &ScoredMemory, budget: usize) -> Option<ScoredMemory> {
+        let content = self
+            .tokenizer
+            .truncate_to_limit(&scored.memory.content, TokenCount::from(budget))
+            .trim();
+        if content.is_empty() {
+            return None;
+        }
+
+        let mut truncated = scored.clone();
+        truncated.memory.content = content.to_string();
+        truncated.memory.token_count = self.tokenizer.count_tokens(&truncated.memory.content);
+        truncated
+            .memory
+            .metadata
+            .insert("truncated".to_string(), "true".to_string());
+        Some(truncated)
+    }
+}
+
+impl ContextOptimizer for SummarizingOptimizer {
+    fn optimize(
+        &self,
+        scored_memories: &[ScoredMemory],
+        max_tokens: TokenCount,
+        relevance_threshold: RelevanceScore,
+    ) -> Result<Vec<ScoredMemory>> {
+        let max_tokens = max_tokens.as_usize();
+        let selected = self.inner.optimize(
+            scored_memories,
+            TokenCount::from(max_tokens),
+            relevance_threshold,
+        )?;
+
+        // Cut down whatever the inner optimizer let overflow the budget
+        let mut optimized = Vec::with_capacity(selected.len());
+        let mut total_tokens = 0;
+        for scored in selected {
+            let remaining = max_tokens - total_tokens;
+            let tokens = scored.memory.token_count.as_usize();
+            let fitted = if tokens <= remaining {
+                Some(scored)
+            } else {
+                self.truncate(&scored, remaining)
+            };
+            if let Some(fitted) = fitted {
+                total_tokens += fitted.memory.token_count.as_usize();
+                optimized.push(fitted);
+            }
+        }
+
+        // Fill what is left with the most relevant memory that was too large
+        let remaining = max_tokens - total_tokens;
+        if remaining > 0 {
+            let chosen: HashSet<_> = optimized.iter().map(|s| s.memory.id.clone()).collect();
+            let oversized = scored_memories.iter().find(|scored| {
+                scored.score.as_f64() >= relevance_threshold.as_f64()
+                    && !chosen.contains(&scored.memory.id)
+                    && scored.memory.token_count.as_usize() > remaining
+            });
+            if let Some(truncated) = oversized.and_then(|s| self.truncate(s, remaining)) {
+                optimized.push(truncated);
             }
+        }
 
-            // Add the memory and update the total tokens
-            optimized_memories.push(memory.clone());
-            total_tokens = total_tokens + memory.memory.token_count;
+        Ok(optimized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{CategoryConfig, Memory, Priority, Tokenizer};
+
+    fn config_with_caps(caps: &[(&str, usize, Priority)]) -> MemoryBankConfig {
+        let categories = caps
+            .iter()
+            .map(|(name, max_tokens, priority)| {
+                let category = CategoryConfig::new(*max_tokens, *priority);
+                (name.to_string(), category)
+            })
+            .collect();
+        MemoryBankConfig {
+            categories,
+            ..Default::default()
         }
+    }
+
+    fn scored(content: &str, category: Option<&str>, score: f64) -> ScoredMemory {
+        ScoredMemory {
+            memory: Memory::new(
+                content.to_string(),
+                "text/plain".to_string(),
+                category.map(str::to_string),
+                None,
+                HashMap::new(),
+                &Tokenizer::default(),
+            ),
+            score: RelevanceScore::new(score),
+        }
+    }
+
+    fn total_tokens(memories: &[ScoredMemory]) -> usize {
+        memories
+            .iter()
+            .map(|scored| scored.memory.token_count.as_usize())
+            .sum()
+    }
+
+    #[test]
+    fn test_summarizing_optimizer_truncates_oversized_memory() -> Result<()> {
+        let optimizer =
+            SummarizingOptimizer::new(Arc::new(TokenBudgetOptimizer::new()), Tokenizer::default());
+        // A single memory ten times the budget
+        let words: Vec<String> = (0..100).map(|i| format!("word{}", i)).collect();
+        let huge = scored(&words.join(" "), None, 0.9);
+
+        let optimized = optimizer.optimize(
+            std::slice::from_ref(&huge),
+            TokenCount::from(10),
+            RelevanceScore::new(0.0),
+        )?;
+        assert_eq!(optimized.len(), 1);
+        assert_eq!(total_tokens(&optimized), 10);
+        assert_eq!(optimized[0].memory.content, words[..10].join(" "));
+        assert_eq!(
+            optimized[0].memory.metadata.get("truncated"),
+            Some(&"true".to_string())
+        );
+        assert_eq!(optimized[0].memory.id, huge.memory.id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_summarizing_optimizer_fills_remaining_budget() -> Result<()> {
+        let optimizer =
+            SummarizingOptimizer::new(Arc::new(TokenBudgetOptimizer::new()), Tokenizer::default());
+        let small = scored("one two three four", None, 0.9);
+        let huge = scored(&"filler ".repeat(100), None, 0.8);
+
+        for max_tokens in [1, 4, 10, 50] {
+            let optimized = optimizer.optimize(
+                &[small.clone(), huge.clone()],
+                TokenCount::from(max_tokens),
+                RelevanceScore::new(0.0),
+            )?;
+            assert!(total_tokens(&optimized) <= max_tokens);
+            assert_eq!(total_tokens(&optimized), max_tokens);
+        }
+
+        // Untruncated memories are left alone
+        let optimized = optimizer.optimize(
+            std::slice::from_ref(&small),
+            TokenCount::from(10),
+            RelevanceScore::new(0.0),
+        )?;
+        assert_eq!(optimized[0].memory.metadata.get("truncated"), None);
+        Ok(())
+    }
+
+    fn category_tokens(memories: &[ScoredMemory], category: &str) -> usize {
+        memories
+            .iter()
+            .filter(|scored| scored.memory.category.as_deref() == Some(category))
+            .map(|scored| scored.memory.token_count.as_usize())
+            .sum()
+    }
+
+    #[test]
+    fn test_category_caps_are_respected() {
+        let config = config_with_caps(&[
+            ("context", 100, Priority::High),
+            ("decision", 50, Priority::Medium),
+        ]);
+        let memories: Vec<_> = (0..20)
+            .map(|i| {
+                let category = if i % 2 == 0 { "context" } else { "decision" };
+                let content = format!("{} note {} ", category, i).repeat(5);
+                scored(&content, Some(category), 1.0 - i as f64 / 20.0)
+            })
+            .collect();
+
+        let optimized = CategoryAwareOptimizer::new(config)
+            .optimize(
+                &memories,
+                TokenCount::from(10_000),
+                RelevanceScore::new(0.0),
+            )
+            .unwrap();
+
+        assert!(category_tokens(&optimized, "context") <= 100);
+        assert!(category_tokens(&optimized, "decision") <= 50);
+        assert!(category_tokens(&optimized, "context") > 0);
+        assert!(category_tokens(&optimized, "decision") > 0);
+        assert!(optimized
+            .windows(2)
+            .all(|pair| pair[0].score.as_f64() >= pair[1].score.as_f64()));
+    }
+
+    #[test]
+    fn test_higher_priority_categories_fill_first() {
+        let config = config_with_caps(&[
+            ("context", 1_000, Priority::Low),
+            ("decision", 1_000, Priority::Critical),
+        ]);
+        let memories = vec![
+            scored(&"context ".repeat(20), Some("context"), 0.9),
+            scored(&"decision ".repeat(20), Some("decision"), 0.1),
+        ];
+        let budget = memories[1].memory.token_count;
+
+        let optimized = CategoryAwareOptimizer::new(config)
+            .optimize(&memories, budget, RelevanceScore::new(0.0))
+            .unwrap();
+
+        assert_eq!(optimized.len(), 1);
+        assert_eq!(optimized[0].memory.category.as_deref(), Some("decision"));
+    }
+
+    #[test]
+    fn test_unconfigured_categories_use_global_budget() {
+        let config = config_with_caps(&[("context", 0, Priority::High)]);
+        let memories = vec![
+            scored("capped", Some("context"), 0.9),
+            scored("free form", None, 0.5),
+            scored("other", Some("misc"), 0.4),
+        ];
+
+        let optimized = CategoryAwareOptimizer::new(config)
+            .optimize(
+                &memories,
+                TokenCount::from(10_000),
+                RelevanceScore::new(0.0),
+            )
+            .unwrap();
+
+        assert_eq!(optimized.len(), 2);
+        assert_eq!(category_tokens(&optimized, "context"), 0);
+    }
+
+    fn reasons(explanations: &[OptimizationExplanation]) -> Vec<&ExclusionReason> {
+        explanations.iter().map(|e| &e.reason).collect()
+    }
+
+    #[test]
+    fn test_explain_below_threshold_and_token_budget() -> Result<()> {
+        let fits = scored("one two three", None, 0.9);
+        let too_large = scored("four five six seven", None, 0.8);
+        let irrelevant = scored("eight", None, 0.1);
+        let memories = [fits.clone(), too_large.clone(), irrelevant.clone()];
+
+        let explanations = TokenBudgetOptimizer::new().explain(
+            &memories,
+            TokenCount::from(5),
+            RelevanceScore::new(0.5),
+        )?;
+
+        assert_eq!(
+            reasons(&explanations),
+            vec![
+                &ExclusionReason::Included,
+                &ExclusionReason::ExceedsTokenBudget {
+                    available: 2,
+                    needed: too_large.memory.token_count.as_usize(),
+                },
+                &ExclusionReason::BelowThreshold(0.1),
+            ]
+        );
+        assert_eq!(explanations[0].memory_id, fits.memory.id);
+        assert!(explanations[0].included);
+        assert!(!explanations[1].included);
+        assert_eq!(
+            explanations[2].reason.to_string(),
+            "relevance 0.100 is below the threshold"
+        );
+
+        // Explanations agree with what is optimized
+        let optimized = TokenBudgetOptimizer::new().optimize(
+            &memories,
+            TokenCount::from(5),
+            RelevanceScore::new(0.5),
+        )?;
+        assert_eq!(optimized.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_category_budget_exhausted() -> Result<()> {
+        let config = config_with_caps(&[("decision", 3, Priority::High)]);
+        let first = scored("use grpc here", Some("decision"), 0.9);
+        let second = scored("and sqlite", Some("decision"), 0.8);
+        let free = scored("free form note", None, 0.7);
+
+        let explanations = CategoryAwareOptimizer::new(config).explain(
+            &[first, second, free.clone()],
+            TokenCount::from(5),
+            RelevanceScore::new(0.0),
+        )?;
+
+        assert_eq!(
+            reasons(&explanations),
+            vec![
+                &ExclusionReason::Included,
+                &ExclusionReason::CategoryBudgetExhausted("decision".to_string()),
+                &ExclusionReason::ExceedsTokenBudget {
+                    available: 2,
+                    needed: free.memory.token_count.as_usize(),
+                },
+            ]
+        );
+        assert_eq!(
+            explanations[1].reason.to_string(),
+            "category decision used up its token budget"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_explain_follows_optimize() -> Result<()> {
+        let optimizer =
+            SummarizingOptimizer::new(Arc::new(TokenBudgetOptimizer::new()), Tokenizer::default());
+        let small = scored("one two", None, 0.9);
+        let irrelevant = scored("three", None, 0.2);
+
+        let explanations = optimizer.explain(
+            &[small, irrelevant],
+            TokenCount::from(10),
+            RelevanceScore::new(0.5),
+        )?;
 
-        Ok(optimized_memories)
+        assert_eq!(
+            reasons(&explanations),
+            vec![
+                &ExclusionReason::Included,
+                &ExclusionReason::BelowThreshold(0.2)
+            ]
+        );
+        Ok(())
     }
 }
@@ -1,7 +1,16 @@
 //! Relevance scoring for memories
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use ort::session::{Session, SessionInputValue};
+use ort::value::Tensor;
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
 use crate::storage::{Memory, MemoryId, TokenCount};
 
@@ -39,12 +48,46 @@ pub trait RelevanceScorer: Send + Sync {
         mode: &str,
         query: Option<&str>,
     ) -> Result<Vec<ScoredMemory>>;
+
+    /// Drop statistics cached from memories that have since been stored, updated or deleted
+    fn invalidate_cache(&self) {}
+
+    /// Cache what scoring `memories` needs ahead of the first request for them
+    fn warm_up(&self, _memories: &[Memory]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Share of a TF-IDF score taken from content, the rest coming from metadata
+const CONTENT_WEIGHT: f64 = 0.7;
+
+/// Share of a TF-IDF score taken from content when scoring against a query
+const QUERY_CONTENT_WEIGHT: f64 = 0.9;
+
+/// Document frequencies of the memories last scored together
+#[derive(Debug, Clone)]
+struct IdfCache {
+    /// Fingerprint of the IDs of the memories counted
+    corpus: u64,
+    /// Number of counted memories containing each term
+    document_frequencies: HashMap<String, usize>,
+    /// Number of memories counted
+    total_documents: usize,
 }
 
 /// TF-IDF based relevance scorer
+///
+/// Document frequencies are cached between calls and only rebuilt when the
+/// scored memories differ from the cached ones or the cache was invalidated.
 pub struct TfIdfScorer {
     /// Mode weights for different metadata fields
     mode_weights: HashMap<String, HashMap<String, f64>>,
+    /// Only memories accessed within this window count towards document frequencies
+    corpus_window: Option<Duration>,
+    /// Document frequencies shared by concurrent scoring calls
+    idf_cache: Arc<RwLock<Option<IdfCache>>>,
+    /// Whether a memory changed since the cache was built
+    dirty: AtomicBool,
 }
 
 impl TfIdfScorer {
@@ -76,7 +119,18 @@ impl TfIdfScorer {
         debug_weights.insert("project".to_string(), 0.5);
         mode_weights.insert("debug".to_string(), debug_weights);
 
-        Self { mode_weights }
+        Self {
+            mode_weights,
+            corpus_window: None,
+            idf_cache: Arc::new(RwLock::new(None)),
+            dirty: AtomicBool::new(false),
+        }
+    }
+
+    /// Restrict corpus statistics to memories accessed within the given window
+    pub fn with_corpus_window(mut self, corpus_window: Option<Duration>) -> Self {
+        self.corpus_window = corpus_window;
+        self
     }
 
     /// Calculate the TF-IDF score for a memory
@@ -125,7 +179,7 @@ impl TfIdfScorer {
                 let tf = *term_frequencies.get(*term).unwrap_or(&0) as f64
                     / content_terms.len().max(1) as f64;
                 let df = document_frequencies.get(*term).copied().unwrap_or(1) as f64;
-                let idf = (total_documents as f64 / df).ln();
+                let idf = (total_documents.max(1) as f64 / df).ln();
                 tf_idf_sum += tf * idf;
             }
 
@@ -133,27 +187,71 @@ impl TfIdfScorer {
             tf_idf_sum / query_terms.len().max(1) as f64
         } else {
             // If no query, use a simple recency score
-            let now = chrono::Utc::now();
-            let age = now
-                .signed_duration_since(memory.last_accessed)
-                .num_seconds() as f64;
-            let recency_score = 1.0 / (1.0 + age / (24.0 * 60.0 * 60.0)); // Decay over 24 hours
-            recency_score
+            recency_score(memory)
         };
 
-        // Combine the scores (70% content, 30% metadata)
-        let combined_score = 0.7 * content_score + 0.3 * metadata_score;
+        // A query makes content matches count for more than mode metadata
+        let content_weight = if query.is_some() {
+            QUERY_CONTENT_WEIGHT
+        } else {
+            CONTENT_WEIGHT
+        };
+        let combined_score =
+            content_weight * content_score + (1.0 - content_weight) * metadata_score;
 
         RelevanceScore::new(combined_score)
     }
 
-    /// Build document frequencies for all terms in the memories
-    fn build_document_frequencies(&self, memories: &[Memory]) -> HashMap<String, usize> {
+    /// Build and cache the document frequencies of `memories`
+    pub fn precompute(&self, memories: &[Memory]) {
+        self.dirty.store(false, Ordering::Release);
+        let cache = self.build_idf_cache(&self.corpus(memories));
+        *self.idf_cache.write().unwrap() = Some(cache);
+    }
+
+    /// Get the memories counted towards document frequencies
+    ///
+    /// Only recently accessed memories count when a corpus window is configured.
+    fn corpus<'a>(&self, memories: &'a [Memory]) -> Vec<&'a Memory> {
+        let cutoff = self
+            .corpus_window
+            .and_then(|window| chrono::Duration::from_std(window).ok())
+            .map(|window| chrono::Utc::now() - window);
+
+        memories
+            .iter()
+            .filter(|memory| cutoff.is_none_or(|cutoff| memory.last_accessed > cutoff))
+            .collect()
+    }
+
+    /// Run `f` with the document frequencies of `memories`, rebuilding the cache when stale
+    ///
+    /// Readers of a fresh cache share it without blocking each other.
+    fn with_idf_cache<T>(&self, memories: &[Memory], f: impl FnOnce(&IdfCache) -> T) -> T {
+        let corpus = self.corpus(memories);
+        if !self.dirty.load(Ordering::Acquire) {
+            let fingerprint = corpus_fingerprint(&corpus);
+            let cached = self.idf_cache.read().unwrap();
+            if let Some(cache) = cached.as_ref().filter(|cache| cache.corpus == fingerprint) {
+                return f(cache);
+            }
+        }
+
+        // Clear the flag first so a change made while rebuilding marks the new cache stale
+        self.dirty.store(false, Ordering::Release);
+        let cache = self.build_idf_cache(&corpus);
+        let result = f(&cache);
+        *self.idf_cache.write().unwrap() = Some(cache);
+        result
+    }
+
+    /// Count the documents of the corpus containing each term
+    fn build_idf_cache(&self, corpus: &[&Memory]) -> IdfCache {
         let mut document_frequencies = HashMap::new();
         let mut document_terms = Vec::new();
 
         // Collect unique terms for each document
-        for memory in memories {
+        for memory in corpus {
             let terms: HashSet<String> = memory
                 .content
                 .to_lowercase()
@@ -171,10 +269,26 @@ impl TfIdfScorer {
             }
         }
 
-        document_frequencies
+        IdfCache {
+            corpus: corpus_fingerprint(corpus),
+            document_frequencies,
+            total_documents: document_terms.len(),
+        }
     }
 }
 
+/// Fingerprint a corpus by its memory IDs, independent of their order
+fn corpus_fingerprint(corpus: &[&Memory]) -> u64 {
+    corpus
+        .iter()
+        .map(|memory| {
+            let mut hasher = DefaultHasher::new();
+            memory.id.hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(corpus.len() as u64, u64::wrapping_add)
+}
+
 impl Default for TfIdfScorer {
     fn default() -> Self {
         Self::new()
@@ -188,28 +302,289 @@ impl RelevanceScorer for TfIdfScorer {
         mode: &str,
         query: Option<&str>,
     ) -> Result<Vec<ScoredMemory>> {
-        // Build document frequencies
-        let document_frequencies = self.build_document_frequencies(memories);
-        let total_documents = memories.len();
-
         // Score each memory
-        let mut scored_memories = memories
+        let mut scored_memories = self.with_idf_cache(memories, |cache| {
+            memories
+                .iter()
+                .map(|memory| ScoredMemory {
+                    memory: memory.clone(),
+                    score: self.calculate_tf_idf(
+                        memory,
+                        mode,
+                        query,
+                        &cache.document_frequencies,
+                        cache.total_documents,
+                    ),
+                })
+                .collect::<Vec<_>>()
+        });
+
+        // Sort by score in descending order
+        scored_memories.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(scored_memories)
+    }
+
+    fn invalidate_cache(&self) {
+        self.dirty.store(true, Ordering::Release);
+    }
+
+    fn warm_up(&self, memories: &[Memory]) -> Result<()> {
+        self.precompute(memories);
+        Ok(())
+    }
+}
+
+/// Maximum number of tokens fed to the embedding model
+const EMBEDDING_MAX_TOKENS: usize = 512;
+
+/// Relevance scorer ranking memories by cosine similarity of sentence embeddings
+///
+/// Uses a sentence-transformer model exported to ONNX together with its
+/// `tokenizer.json`. Token embeddings are mean-pooled into one vector per text.
+pub struct EmbeddingScorer {
+    /// The ONNX model session; running it needs exclusive access
+    session: Mutex<Session>,
+    /// Tokenizer matching the model
+    tokenizer: tokenizers::Tokenizer,
+    /// Whether the model takes a `token_type_ids` input
+    uses_token_type_ids: bool,
+    /// Embeddings of memory content by memory ID
+    embeddings: RwLock<HashMap<MemoryId, Vec<f32>>>,
+}
+
+impl EmbeddingScorer {
+    /// Load the model at `EMBEDDING_MODEL_PATH`
+    ///
+    /// The tokenizer is read from `EMBEDDING_TOKENIZER_PATH`, defaulting to
+    /// `tokenizer.json` next to the model.
+    pub fn new() -> Result<Self> {
+        let model_path = std::env::var("EMBEDDING_MODEL_PATH")
+            .map(PathBuf::from)
+            .context("EMBEDDING_MODEL_PATH is not set")?;
+        let tokenizer_path = std::env::var("EMBEDDING_TOKENIZER_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| model_path.with_file_name("tokenizer.json"));
+
+        Self::from_files(&model_path, &tokenizer_path)
+    }
+
+    /// Load a model and its tokenizer from the given files
+    pub fn from_files(model_path: &Path, tokenizer_path: &Path) -> Result<Self> {
+        if !model_path.is_file() {
+            return Err(anyhow!(
+                "Embedding model not found: {}",
+                model_path.display()
+            ));
+        }
+
+        let tokenizer = tokenizers::Tokenizer::from_file(tokenizer_path)
+            .map_err(|e| anyhow!("Failed to load embedding tokenizer: {}", e))?;
+        let session = load_session(model_path)?;
+        let uses_token_type_ids = session
+            .inputs()
             .iter()
-            .map(|memory| {
-                let score = self.calculate_tf_idf(
-                    memory,
-                    mode,
-                    query,
-                    &document_frequencies,
-                    total_documents,
-                );
+            .any(|input| input.name() == "token_type_ids");
+
+        Ok(Self {
+            session: Mutex::new(session),
+            tokenizer,
+            uses_token_type_ids,
+            embeddings: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Embed every memory not already in the embedding cache
+    pub fn precompute_all(&self, memories: &[Memory]) -> Result<()> {
+        for memory in memories {
+            self.memory_embedding(memory)?;
+        }
+        Ok(())
+    }
+
+    /// Get a memory's embedding from the cache, computing it if missing
+    fn memory_embedding(&self, memory: &Memory) -> Result<Vec<f32>> {
+        if let Some(embedding) = self.embeddings.read().unwrap().get(&memory.id) {
+            return Ok(embedding.clone());
+        }
+
+        let embedding = self.embed(&memory.content)?;
+        self.embeddings
+            .write()
+            .unwrap()
+            .insert(memory.id.clone(), embedding.clone());
+        Ok(embedding)
+    }
+
+    /// Embed a text into a unit-length vector
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| anyhow!("Failed to tokenize text for embedding: {}", e))?;
+        let length = encoding.get_ids().len().min(EMBEDDING_MAX_TOKENS);
+        let to_i64 =
+            |values: &[u32]| -> Vec<i64> { values[..length].iter().map(|&v| v as i64).collect() };
+        let attention_mask = to_i64(encoding.get_attention_mask());
+        let shape = [1, length as i64];
+
+        let mut inputs: Vec<(Cow<str>, SessionInputValue)> = vec![
+            (
+                "input_ids".into(),
+                Tensor::from_array((shape, to_i64(encoding.get_ids())))?.into(),
+            ),
+            (
+                "attention_mask".into(),
+                Tensor::from_array((shape, attention_mask.clone()))?.into(),
+            ),
+        ];
+        if self.uses_token_type_ids {
+            inputs.push((
+                "token_type_ids".into(),
+                Tensor::from_array((shape, to_i64(encoding.get_type_ids())))?.into(),
+            ));
+        }
+
+        let mut session = self.session.lock().unwrap();
+        let outputs = session.run(inputs)?;
+        let (output_shape, values) = outputs[0].try_extract_tensor::<f32>()?;
+
+        // Models either return one pooled vector or one vector per token
+        let embedding = match **output_shape {
+            [1, dimensions] => values[..dimensions as usize].to_vec(),
+            [1, tokens, dimensions] => mean_pool(
+                values,
+                &attention_mask,
+                tokens as usize,
+                dimensions as usize,
+            ),
+            ref other => return Err(anyhow!("Unexpected embedding output shape: {:?}", other)),
+        };
+
+        Ok(normalize(embedding))
+    }
+}
+
+impl RelevanceScorer for EmbeddingScorer {
+    fn score_memories(
+        &self,
+        memories: &[Memory],
+        _mode: &str,
+        query: Option<&str>,
+    ) -> Result<Vec<ScoredMemory>> {
+        let query_embedding = query.map(|query| self.embed(query)).transpose()?;
+
+        let mut scored_memories = Vec::with_capacity(memories.len());
+        for memory in memories {
+            // Without a query, fall back to recency like the TF-IDF scorer
+            let score = match &query_embedding {
+                Some(query_embedding) => {
+                    cosine_similarity(query_embedding, &self.memory_embedding(memory)?)
+                }
+                None => recency_score(memory),
+            };
+
+            scored_memories.push(ScoredMemory {
+                memory: memory.clone(),
+                score: RelevanceScore::new(score),
+            });
+        }
+
+        // Sort by score in descending order
+        scored_memories.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        Ok(scored_memories)
+    }
+
+    fn warm_up(&self, memories: &[Memory]) -> Result<()> {
+        self.precompute_all(memories)
+    }
+}
 
+/// Default weight of the keyword score in a hybrid score
+pub const DEFAULT_HYBRID_ALPHA: f64 = 0.5;
+
+/// Relevance scorer blending a keyword scorer with a semantic scorer
+///
+/// Each scorer's scores are min-max scaled to [0, 1] before blending as
+/// `alpha * keyword + (1 - alpha) * semantic`.
+pub struct HybridScorer {
+    /// Keyword-based scorer, usually TF-IDF
+    keyword: Arc<dyn RelevanceScorer>,
+    /// Semantic scorer, usually embeddings
+    semantic: Arc<dyn RelevanceScorer>,
+    /// Weight of the keyword score, from 0.0 to 1.0
+    alpha: f64,
+}
+
+impl HybridScorer {
+    /// Blend TF-IDF with the embedding model configured by `EMBEDDING_MODEL_PATH`
+    pub fn new(alpha: f64) -> Result<Self> {
+        Self::with_scorers(
+            Arc::new(TfIdfScorer::new()),
+            Arc::new(EmbeddingScorer::new()?),
+            alpha,
+        )
+    }
+
+    /// Blend the given keyword and semantic scorers
+    pub fn with_scorers(
+        keyword: Arc<dyn RelevanceScorer>,
+        semantic: Arc<dyn RelevanceScorer>,
+        alpha: f64,
+    ) -> Result<Self> {
+        if !(0.0..=1.0).contains(&alpha) {
+            return Err(anyhow!(
+                "Hybrid alpha must be between 0 and 1, got {}",
+                alpha
+            ));
+        }
+
+        Ok(Self {
+            keyword,
+            semantic,
+            alpha,
+        })
+    }
+}
+
+impl RelevanceScorer for HybridScorer {
+    fn score_memories(
+        &self,
+        memories: &[Memory],
+        mode: &str,
+        query: Option<&str>,
+    ) -> Result<Vec<ScoredMemory>> {
+        let keyword = self.keyword.score_memories(memories, mode, query)?;
+        let semantic = self.semantic.score_memories(memories, mode, query)?;
+        let semantic_scores = min_max_scaled(&semantic);
+
+        // Follow the keyword ranking so equal blended scores keep its order
+        let keyword_scores = min_max_scaled(&keyword);
+        let mut scored_memories: Vec<ScoredMemory> = keyword
+            .into_iter()
+            .map(|scored| {
+                let keyword_score = keyword_scores[&scored.memory.id];
+                let semantic_score = semantic_scores
+                    .get(&scored.memory.id)
+                    .copied()
+                    .unwrap_or(0.0);
                 ScoredMemory {
-                    memory: memory.clone(),
-                    score,
+                    score: RelevanceScore::new(
+                        self.alpha * keyword_score + (1.0 - self.alpha) * semantic_score,
+                    ),
+                    memory: scored.memory,
                 }
             })
-            .collect::<Vec<_>>();
+            .collect();
 
         // Sort by score in descending order
         scored_memories.sort_by(|a, b| {
@@ -220,4 +595,314 @@ impl RelevanceScorer for TfIdfScorer {
 
         Ok(scored_memories)
     }
+
+    fn invalidate_cache(&self) {
+        self.keyword.invalidate_cache();
+        self.semantic.invalidate_cache();
+    }
+
+    fn warm_up(&self, memories: &[Memory]) -> Result<()> {
+        self.keyword.warm_up(memories)?;
+        self.semantic.warm_up(memories)
+    }
+}
+
+/// Scale scores to [0, 1] by memory ID; equal scores all scale to 1
+fn min_max_scaled(scored: &[ScoredMemory]) -> HashMap<MemoryId, f64> {
+    let scores = scored.iter().map(|s| s.score.as_f64());
+    let min = scores.clone().fold(f64::INFINITY, f64::min);
+    let max = scores.fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    scored
+        .iter()
+        .map(|s| {
+            let scaled = if range > 0.0 {
+                (s.score.as_f64() - min) / range
+            } else {
+                1.0
+            };
+            (s.memory.id.clone(), scaled)
+        })
+        .collect()
+}
+
+/// Load the ONNX Runtime library and open a model session
+///
+/// The runtime is loaded from `ORT_DYLIB_PATH` or the system library path.
+/// Loading it is attempted explicitly so that a missing runtime is reported as
+/// an error rather than a panic inside `ort`.
+fn load_session(model_path: &Path) -> Result<Session> {
+    let dylib_path = std::env::var("ORT_DYLIB_PATH").unwrap_or_else(|_| {
+        format!(
+            "{}onnxruntime{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        )
+    });
+
+    let environment = ort::init_from(&dylib_path)
+        .map_err(|e| anyhow!("Failed to load ONNX Runtime from {}: {}", dylib_path, e))?
+        .with_name("smart-memory")
+        .build()
+        .context("Failed to create ONNX Runtime environment")?;
+
+    Session::builder(&environment)
+        .context("Failed to create ONNX session builder")?
+        .commit_from_file(model_path)
+        .with_context(|| format!("Failed to load embedding model {}", model_path.display()))
+}
+
+/// Average token embeddings over the tokens included by the attention mask
+fn mean_pool(values: &[f32], attention_mask: &[i64], tokens: usize, dimensions: usize) -> Vec<f32> {
+    let mut pooled = vec![0.0; dimensions];
+    let mut counted = 0.0;
+
+    for (token, &mask) in attention_mask.iter().enumerate().take(tokens) {
+        if mask == 0 {
+            continue;
+        }
+        let row = &values[token * dimensions..(token + 1) * dimensions];
+        for (sum, value) in pooled.iter_mut().zip(row) {
+            *sum += value;
+        }
+        counted += 1.0;
+    }
+
+    if counted > 0.0 {
+        pooled.iter_mut().for_each(|value| *value /= counted);
+    }
+    pooled
+}
+
+/// Scale a vector to unit length
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+    vector
+}
+
+/// Cosine similarity of two unit-length vectors
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>() as f64
+}
+
+/// Score a memory by how recently it was accessed, decaying over 24 hours
+fn recency_score(memory: &Memory) -> f64 {
+    let age = chrono::Utc::now()
+        .signed_duration_since(memory.last_accessed)
+        .num_seconds() as f64;
+    1.0 / (1.0 + age / (24.0 * 60.0 * 60.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Tokenizer;
+
+    fn memory_accessed_hours_ago(content: &str, hours: i64) -> Memory {
+        let mut memory = Memory::new(
+            content.to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+            &Tokenizer::default(),
+        );
+        memory.last_accessed = chrono::Utc::now() - chrono::Duration::hours(hours);
+        memory
+    }
+
+    #[test]
+    fn test_embedding_scorer_requires_model() {
+        let missing = Path::new("/nonexistent/model.onnx");
+        assert!(
+            EmbeddingScorer::from_files(missing, &missing.with_file_name("tokenizer.json"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_mean_pool_ignores_padding() {
+        let values = [1.0, 2.0, 3.0, 4.0, 100.0, 100.0];
+        let pooled = mean_pool(&values, &[1, 1, 0], 3, 2);
+        assert_eq!(pooled, vec![2.0, 3.0]);
+
+        let a = normalize(vec![3.0, 4.0]);
+        assert!((cosine_similarity(&a, &a) - 1.0).abs() < 1e-6);
+    }
+
+    /// Scores memories in reverse order of their content length
+    struct ShortestFirstScorer;
+
+    impl RelevanceScorer for ShortestFirstScorer {
+        fn score_memories(
+            &self,
+            memories: &[Memory],
+            _mode: &str,
+            _query: Option<&str>,
+        ) -> Result<Vec<ScoredMemory>> {
+            Ok(memories
+                .iter()
+                .map(|memory| ScoredMemory {
+                    memory: memory.clone(),
+                    score: RelevanceScore::new(1.0 / memory.content.len() as f64),
+                })
+                .collect())
+        }
+    }
+
+    fn ranking(scored: &[ScoredMemory]) -> Vec<MemoryId> {
+        scored.iter().map(|s| s.memory.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_hybrid_scorer_blends_rankings() -> Result<()> {
+        let memories = vec![
+            memory_accessed_hours_ago("rust build cache tuning for the rust compiler", 1),
+            memory_accessed_hours_ago("rust", 1),
+            memory_accessed_hours_ago("deploy notes for the python service", 1),
+        ];
+        let query = Some("rust compiler build");
+        let tf_idf = TfIdfScorer::new().score_memories(&memories, "code", query)?;
+        let hybrid = |alpha| {
+            HybridScorer::with_scorers(
+                Arc::new(TfIdfScorer::new()),
+                Arc::new(ShortestFirstScorer),
+                alpha,
+            )
+        };
+
+        // alpha = 1.0 is keyword scoring alone
+        let keyword_only = hybrid(1.0)?.score_memories(&memories, "code", query)?;
+        assert_eq!(ranking(&keyword_only), ranking(&tf_idf));
+
+        // alpha = 0.0 is semantic scoring alone
+        let semantic_only = hybrid(0.0)?.score_memories(&memories, "code", query)?;
+        assert_eq!(semantic_only[0].memory.content, "rust");
+
+        assert!(hybrid(1.5).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_corpus_window_excludes_old_documents() {
+        let memories = vec![
+            memory_accessed_hours_ago("legacy build uses make", 24 * 30),
+            memory_accessed_hours_ago("legacy deploy uses ftp", 24 * 30),
+            memory_accessed_hours_ago("new build uses cargo", 1),
+        ];
+
+        let scorer = TfIdfScorer::new();
+        scorer.precompute(&memories);
+        let cache = cached(&scorer).unwrap();
+        assert_eq!(cache.total_documents, 3);
+        assert_eq!(cache.document_frequencies.get("legacy"), Some(&2));
+
+        let windowed = TfIdfScorer::new().with_corpus_window(Some(Duration::from_secs(24 * 3600)));
+        windowed.precompute(&memories);
+        let cache = cached(&windowed).unwrap();
+        assert_eq!(cache.total_documents, 1);
+        assert_eq!(cache.document_frequencies.get("legacy"), None);
+        assert_eq!(cache.document_frequencies.get("cargo"), Some(&1));
+    }
+
+    fn cached(scorer: &TfIdfScorer) -> Option<IdfCache> {
+        scorer.idf_cache.read().unwrap().clone()
+    }
+
+    #[test]
+    fn test_idf_cache_is_reused_until_invalidated() -> Result<()> {
+        let mut memories = vec![
+            memory_accessed_hours_ago("rust build cache", 1),
+            memory_accessed_hours_ago("python deploy notes", 1),
+        ];
+        let scorer = TfIdfScorer::new();
+        assert!(cached(&scorer).is_none());
+        scorer.score_memories(&memories, "code", Some("rust"))?;
+        assert_eq!(cached(&scorer).unwrap().document_frequencies["rust"], 1);
+
+        // An edit keeps the memory IDs, so the cache is reused until invalidated
+        memories[1].content = "rust deploy notes".to_string();
+        scorer.score_memories(&memories, "code", Some("rust"))?;
+        assert_eq!(cached(&scorer).unwrap().document_frequencies["rust"], 1);
+
+        scorer.invalidate_cache();
+        scorer.score_memories(&memories, "code", Some("rust"))?;
+        assert_eq!(cached(&scorer).unwrap().document_frequencies["rust"], 2);
+
+        // Scoring other memories rebuilds the cache for them
+        scorer.score_memories(&memories[..1], "code", Some("rust"))?;
+        assert_eq!(cached(&scorer).unwrap().total_documents, 1);
+
+        // The memory order does not matter
+        let forward: Vec<_> = memories.iter().collect();
+        let backward: Vec<_> = memories.iter().rev().collect();
+        assert_eq!(corpus_fingerprint(&forward), corpus_fingerprint(&backward));
+        Ok(())
+    }
+
+    /// Compares scoring with the IDF table rebuilt every round against the cached table
+    ///
+    /// Run with `cargo test --release bench_idf_cache -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_idf_cache_with_10k_memories() -> Result<()> {
+        let words = [
+            "rust",
+            "build",
+            "cache",
+            "deploy",
+            "python",
+            "service",
+            "notes",
+            "compiler",
+            "database",
+            "migration",
+            "schema",
+            "index",
+            "query",
+            "latency",
+            "memory",
+            "token",
+        ];
+        let memories: Vec<Memory> = (0..10_000)
+            .map(|i| {
+                let content = (0..40)
+                    .map(|j| format!("{}{}", words[(i * 7 + j * 3) % words.len()], j % 5))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                memory_accessed_hours_ago(&content, 1)
+            })
+            .collect();
+        let rounds = 10;
+        let scorer = TfIdfScorer::new();
+
+        let started = std::time::Instant::now();
+        for _ in 0..rounds {
+            scorer.invalidate_cache();
+            scorer.score_memories(&memories, "code", Some("rust0 cache1"))?;
+        }
+        let uncached = started.elapsed();
+
+        scorer.precompute(&memories);
+        let started = std::time::Instant::now();
+        for _ in 0..rounds {
+            scorer.score_memories(&memories, "code", Some("rust0 cache1"))?;
+        }
+        let cached = started.elapsed();
+
+        println!(
+            "{} rounds over {} memories: {:?} rebuilding, {:?} cached ({:.1}x faster)",
+            rounds,
+            memories.len(),
+            uncached,
+            cached,
+            uncached.as_secs_f64() / cached.as_secs_f64()
+        );
+        assert!(cached < uncached);
+        Ok(())
+    }
 }
@@ -0,0 +1,273 @@
+//! Async access to the blocking memory store
+//!
+//! `rusqlite` is synchronous, so a query run directly from an RPC handler
+//! holds one of Tokio's worker threads until SQLite returns. The repository
+//! here moves each call onto Tokio's blocking pool with `spawn_blocking`,
+//! leaving the workers free to serve other requests meanwhile. It wraps the
+//! whole [`MemoryStore`] rather than the SQLite repository inside it, so the
+//! store's cache, token totals and audit log stay in step with every call.
+
+use anyhow::{Context, Result};
+use std::sync::Arc;
+
+use crate::storage::memory::StoreItem;
+use crate::storage::{Memory, MemoryFilter, MemoryId, MemorySortField, MemoryStore, SortOrder};
+
+/// Memory store whose operations can be awaited without blocking the runtime
+#[tonic::async_trait]
+pub trait AsyncMemoryRepository: Send + Sync + std::fmt::Debug {
+    /// Retrieve a memory by ID, counting the access
+    async fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>>;
+
+    /// Delete a memory by ID, returning it if it existed
+    async fn delete(&self, id: &MemoryId) -> Result<Option<Memory>>;
+
+    /// Store a batch of memories in one transaction
+    async fn bulk_store(&self, items: Vec<StoreItem>) -> Result<Vec<Memory>>;
+
+    /// Get the best `limit` matches of a search, optionally within one mode,
+    /// and the number matching in total
+    async fn search(
+        &self,
+        query: &str,
+        mode: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<Memory>, u64)>;
+
+    /// Get one page of the memories matching `filter` and the number matching in total
+    async fn list(
+        &self,
+        filter: &MemoryFilter,
+        sort: MemorySortField,
+        order: SortOrder,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<Memory>, u64)>;
+}
+
+/// Runs a memory store, with its SQLite repository and any layers such as
+/// encryption in front of it, on Tokio's blocking pool
+#[derive(Debug, Clone)]
+pub struct AsyncSqliteMemoryRepository {
+    /// The blocking store each call is handed to
+    store: Arc<MemoryStore>,
+}
+
+impl AsyncSqliteMemoryRepository {
+    /// Wrap a blocking store
+    pub fn new(store: Arc<MemoryStore>) -> Self {
+        Self { store }
+    }
+
+    /// Run `operation` against the store on the blocking pool
+    ///
+    /// Calls that need several store operations, or work besides them, run
+    /// them together here. The operation runs to completion even if the
+    /// returned future is dropped.
+    pub async fn run<T, F>(&self, operation: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&MemoryStore) -> T + Send + 'static,
+    {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || operation(&store))
+            .await
+            .context("Store task failed")
+    }
+}
+
+#[tonic::async_trait]
+impl AsyncMemoryRepository for AsyncSqliteMemoryRepository {
+    async fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>> {
+        let id = id.clone();
+        self.run(move |store| store.retrieve(&id)).await?
+    }
+
+    async fn delete(&self, id: &MemoryId) -> Result<Option<Memory>> {
+        let id = id.clone();
+        self.run(move |store| store.delete(&id)).await?
+    }
+
+    async fn bulk_store(&self, items: Vec<StoreItem>) -> Result<Vec<Memory>> {
+        self.run(move |store| store.bulk_store(items)).await?
+    }
+
+    async fn search(
+        &self,
+        query: &str,
+        mode: Option<&str>,
+        limit: usize,
+    ) -> Result<(Vec<Memory>, u64)> {
+        let query = query.to_string();
+        let mode = mode.map(str::to_string);
+        self.run(move |store| {
+            let memories = store.search(&query, mode.as_deref(), limit)?;
+            let total = store.count_search_matches(&query, mode.as_deref())?;
+            Ok((memories, total))
+        })
+        .await?
+    }
+
+    async fn list(
+        &self,
+        filter: &MemoryFilter,
+        sort: MemorySortField,
+        order: SortOrder,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<Memory>, u64)> {
+        let filter = filter.clone();
+        self.run(move |store| store.list(&filter, sort, order, page, page_size))
+            .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::DEFAULT_MAX_CACHE_ENTRIES;
+    use crate::storage::{TokenCount, Tokenizer};
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_round_trip_through_the_blocking_pool() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let db_path = temp_dir.path().join("memories.db");
+        let memories = AsyncSqliteMemoryRepository::new(Arc::new(MemoryStore::new_sqlite(
+            &db_path,
+            Tokenizer::default(),
+        )?))
+        .bulk_store(vec![(
+            "retrieved without blocking the runtime".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+        )])
+        .await?;
+        let memory = &memories[0];
+
+        // A repository lookup from a store with a cold cache, then a cache hit,
+        // each counted as an access
+        let store = Arc::new(MemoryStore::new_sqlite(&db_path, Tokenizer::default())?);
+        let repository = AsyncSqliteMemoryRepository::new(store.clone());
+        assert_eq!(
+            repository.retrieve(&memory.id).await?.unwrap().access_count,
+            1
+        );
+        assert_eq!(
+            repository.retrieve(&memory.id).await?.unwrap().access_count,
+            2
+        );
+        assert_eq!(store.retrieve(&memory.id)?.unwrap().access_count, 3);
+
+        let (found, total) = repository.search("runtime", None, 10).await?;
+        assert_eq!((found.len(), total), (1, 1));
+        let (page, total) = repository
+            .list(
+                &MemoryFilter::default(),
+                MemorySortField::default(),
+                SortOrder::default(),
+                0,
+                10,
+            )
+            .await?;
+        assert_eq!((page.len(), total), (1, 1));
+
+        let deleted = repository.delete(&memory.id).await?.unwrap();
+        assert_eq!(deleted.id, memory.id);
+        assert!(repository.retrieve(&memory.id).await?.is_none());
+        assert!(repository.delete(&memory.id).await?.is_none());
+        assert_eq!(store.get_total_tokens()?, TokenCount::new(0));
+
+        Ok(())
+    }
+
+    /// Compares a heartbeat task's worst delay while concurrent lookups run
+    /// directly on the workers and through the blocking pool
+    ///
+    /// More memories are stored than the cache holds, so lookups also reach SQLite.
+    /// Run with `cargo test --release bench_concurrent_lookups -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_concurrent_lookups() -> Result<()> {
+        use std::time::{Duration, Instant};
+
+        const TASKS: usize = 64;
+        const LOOKUPS: usize = 200;
+
+        let temp_dir = tempdir()?;
+        let store = Arc::new(MemoryStore::new_sqlite(
+            &temp_dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?);
+        let repository = AsyncSqliteMemoryRepository::new(store.clone());
+        let mut ids = Vec::new();
+        for i in 0..2 * DEFAULT_MAX_CACHE_ENTRIES {
+            let memory = store.store(
+                format!("memory number {} about latency", i),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )?;
+            ids.push(memory.id);
+        }
+        let ids = Arc::new(ids);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()?;
+
+        for blocking in [true, false] {
+            let (elapsed, worst_tick) = runtime.block_on(async {
+                let heartbeat = tokio::spawn(async {
+                    let mut worst = Duration::ZERO;
+                    for _ in 0..100 {
+                        let started = Instant::now();
+                        tokio::time::sleep(Duration::from_millis(1)).await;
+                        worst = worst.max(started.elapsed());
+                    }
+                    worst
+                });
+
+                let started = Instant::now();
+                let tasks: Vec<_> = (0..TASKS)
+                    .map(|task| {
+                        let store = store.clone();
+                        let repository = repository.clone();
+                        let ids = ids.clone();
+                        tokio::spawn(async move {
+                            for i in 0..LOOKUPS {
+                                let id = &ids[(task * LOOKUPS + i) % ids.len()];
+                                if blocking {
+                                    store.retrieve(id).unwrap();
+                                } else {
+                                    repository.retrieve(id).await.unwrap();
+                                }
+                            }
+                        })
+                    })
+                    .collect();
+                for task in tasks {
+                    task.await.unwrap();
+                }
+                (started.elapsed(), heartbeat.await.unwrap())
+            });
+
+            println!(
+                "{}: {:.0} lookups/s, worst heartbeat delay {:?}",
+                if blocking {
+                    "on workers"
+                } else {
+                    "spawn_blocking"
+                },
+                (TASKS * LOOKUPS) as f64 / elapsed.as_secs_f64(),
+                worst_tick
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! Compaction of SQLite databases
+//!
+//! Deleted memories leave free pages behind, so the database file never
+//! shrinks on its own. `VACUUM` rewrites the database without them.
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{log_info, log_warning};
+
+/// Another compaction of the same database is already running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionInProgress;
+
+impl fmt::Display for CompactionInProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a compaction of this database is already in progress")
+    }
+}
+
+impl std::error::Error for CompactionInProgress {}
+
+/// Advisory lock file held for the duration of a compaction
+struct CompactionLock {
+    path: PathBuf,
+}
+
+impl CompactionLock {
+    /// Create the lock file next to `db_path`, failing if it already exists
+    fn acquire(db_path: &Path) -> Result<Self> {
+        let path = suffixed(db_path, "-compact.lock");
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => Ok(Self { path }),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(CompactionInProgress.into()),
+            Err(e) => Err(e)
+                .with_context(|| format!("Failed to create compaction lock: {}", path.display())),
+        }
+    }
+}
+
+impl Drop for CompactionLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log_warning!(
+                "storage",
+                &format!(
+                    "Failed to remove compaction lock {}: {}",
+                    self.path.display(),
+                    e
+                )
+            );
+        }
+    }
+}
+
+/// Rewrite the database at `db_path` without free pages, returning the bytes recovered
+///
+/// `VACUUM` holds an exclusive lock on the database while it runs, and the WAL
+/// is checkpointed afterwards so the main file shrinks straight away.
+pub fn compact_database(connection: &Connection, db_path: &Path) -> Result<u64> {
+    let _lock = CompactionLock::acquire(db_path)?;
+
+    let before = database_size(db_path);
+    log_info!(
+        "storage",
+        &format!("Compacting {} ({} bytes)", db_path.display(), before)
+    );
+
+    connection
+        .execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+        .context("Failed to compact database")?;
+
+    let after = database_size(db_path);
+    log_info!(
+        "storage",
+        &format!(
+            "Compacted {} from {} to {} bytes",
+            db_path.display(),
+            before,
+            after
+        )
+    );
+
+    Ok(before.saturating_sub(after))
+}
+
+/// Number of free pages in the database, waiting to be reclaimed
+pub fn free_list_count(connection: &Connection) -> Result<u64> {
+    connection
+        .query_row("PRAGMA freelist_count", [], |row| row.get(0))
+        .context("Failed to read free list size")
+}
+
+/// Return up to `pages` free pages to the file system, or all of them if `pages` is 0,
+/// returning the number freed
+///
+/// Only databases created with `auto_vacuum = INCREMENTAL` can shrink this way;
+/// older databases pick the setting up the next time they are compacted.
+pub fn incremental_vacuum(connection: &Connection, pages: u32) -> Result<u64> {
+    let before = free_list_count(connection)?;
+    // Each step of the pragma frees one page, so run it to completion
+    let mut statement = connection.prepare(&format!("PRAGMA incremental_vacuum({})", pages))?;
+    let mut rows = statement.query([])?;
+    while rows.next().context("Failed to vacuum database")?.is_some() {}
+    let after = free_list_count(connection)?;
+
+    Ok(before.saturating_sub(after))
+}
+
+/// Size of the database file and its write-ahead log, in bytes
+pub fn database_size(db_path: &Path) -> u64 {
+    [db_path.to_path_buf(), suffixed(db_path, "-wal")]
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Path of a file SQLite-style named after the database, e.g. `memories.db-wal`
+fn suffixed(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MemoryStore, Tokenizer};
+    use std::collections::HashMap;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_compact_reclaims_deleted_memories() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("memories.db");
+        let store = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?;
+
+        let ids = (0..200)
+            .map(|i| {
+                store
+                    .store(
+                        format!("memory {} {}", i, "padding ".repeat(200)),
+                        "text/plain".to_string(),
+                        None,
+                        None,
+                        HashMap::new(),
+                    )
+                    .map(|memory| memory.id)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for id in &ids {
+            store.delete(id)?;
+        }
+
+        let before = database_size(&db_path);
+        let bytes_saved = store.compact()?;
+        assert!(bytes_saved > 0);
+        assert_eq!(database_size(&db_path), before - bytes_saved);
+        assert!(!suffixed(&db_path, "-compact.lock").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_compaction_is_rejected() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("memories.db");
+        let store = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?;
+
+        let lock = CompactionLock::acquire(&db_path)?;
+        let error = store.compact().unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<CompactionInProgress>(),
+            Some(&CompactionInProgress)
+        );
+
+        drop(lock);
+        store.compact()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+//! Schema migrations for the SQLite memory database
+//!
+//! Migrations are applied in order on startup. Each one runs in its own
+//! transaction together with the `schema_version` row recording it, so a
+//! failed migration leaves the database at the previous version.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+
+/// Schema migrations; the migration at index `i` brings the schema to version `i + 1`
+const MIGRATIONS: &[&str] = &[
+    // 1: memories table
+    "CREATE TABLE IF NOT EXISTS memories (
+        id TEXT PRIMARY KEY,
+        content TEXT NOT NULL,
+        content_type TEXT NOT NULL,
+        category TEXT,
+        mode TEXT,
+        metadata_json TEXT NOT NULL,
+        token_count INTEGER NOT NULL,
+        created_at TEXT NOT NULL,
+        last_accessed TEXT NOT NULL
+    );",
+    // 2: memory time-to-live
+    "ALTER TABLE memories ADD COLUMN ttl_seconds INTEGER;",
+    // 3: idempotency keys for store requests
+    "CREATE TABLE idempotency_keys (
+        key TEXT PRIMARY KEY,
+        memory_id TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys (created_at);",
+    // 4: full-text index over memory content
+    //
+    // `INSERT OR REPLACE` does not fire delete triggers, so inserts clear any stale row first
+    "CREATE VIRTUAL TABLE memories_fts USING fts5(id UNINDEXED, content);
+    CREATE TRIGGER memories_fts_insert AFTER INSERT ON memories BEGIN
+        DELETE FROM memories_fts WHERE id = new.id;
+        INSERT INTO memories_fts (id, content) VALUES (new.id, new.content);
+    END;
+    CREATE TRIGGER memories_fts_update AFTER UPDATE OF content ON memories BEGIN
+        DELETE FROM memories_fts WHERE id = old.id;
+        INSERT INTO memories_fts (id, content) VALUES (new.id, new.content);
+    END;
+    CREATE TRIGGER memories_fts_delete AFTER DELETE ON memories BEGIN
+        DELETE FROM memories_fts WHERE id = old.id;
+    END;
+    INSERT INTO memories_fts (id, content) SELECT id, content FROM memories;",
+    // 5: per-operation service metrics
+    "CREATE TABLE metrics (
+        timestamp INTEGER NOT NULL,
+        operation TEXT NOT NULL,
+        mode TEXT NOT NULL,
+        token_count INTEGER NOT NULL,
+        latency_ms INTEGER NOT NULL
+    );
+    CREATE INDEX idx_metrics_operation_timestamp ON metrics (operation, timestamp);",
+    // 6: category and mode lookups, ordered like full listings
+    "CREATE INDEX idx_category ON memories (category, created_at, id);
+    CREATE INDEX idx_mode ON memories (mode, created_at, id);",
+    // 7: memories protected from expiration
+    "ALTER TABLE memories ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;",
+    // 8: multi-valued memory tags
+    "CREATE TABLE tags (
+        memory_id TEXT NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (memory_id, tag),
+        FOREIGN KEY (memory_id) REFERENCES memories (id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_tags_tag ON tags (tag, memory_id);",
+    // 9: typed links from one memory to another
+    "CREATE TABLE memory_links (
+        source_id TEXT NOT NULL,
+        target_id TEXT NOT NULL,
+        relation_type TEXT NOT NULL,
+        PRIMARY KEY (source_id, target_id, relation_type),
+        FOREIGN KEY (source_id) REFERENCES memories (id) ON DELETE CASCADE,
+        FOREIGN KEY (target_id) REFERENCES memories (id) ON DELETE CASCADE
+    );
+    CREATE INDEX idx_memory_links_target ON memory_links (target_id);",
+    // 10: how often each memory has been retrieved
+    "ALTER TABLE memories ADD COLUMN access_count INTEGER NOT NULL DEFAULT 0;
+    CREATE INDEX idx_access_count ON memories (access_count);",
+];
+
+/// Bring the database schema to the latest version, returning that version
+pub fn run_migrations(connection: &mut Connection) -> Result<u32> {
+    apply_migrations(connection, MIGRATIONS)
+}
+
+/// Apply any of `migrations` newer than the database's current version
+fn apply_migrations(connection: &mut Connection, migrations: &[&str]) -> Result<u32> {
+    let tracked: bool = connection
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'schema_version')",
+            [],
+            |row| row.get(0),
+        )
+        .context("Failed to check for schema_version table")?;
+
+    connection
+        .execute(
+            "CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                applied_at TEXT NOT NULL
+            )",
+            [],
+        )
+        .context("Failed to create schema_version table")?;
+
+    // Databases created before migrations were tracked are matched by their tables
+    if !tracked {
+        for version in 1..=detect_untracked_version(connection)? {
+            record_version(connection, version)?;
+        }
+    }
+
+    let mut version = current_version(connection)?;
+    for (index, migration) in migrations.iter().enumerate().skip(version as usize) {
+        let target = index as u32 + 1;
+
+        let transaction = connection.transaction()?;
+        transaction
+            .execute_batch(migration)
+            .with_context(|| format!("Failed to apply schema migration {}", target))?;
+        record_version(&transaction, target)?;
+        transaction
+            .commit()
+            .with_context(|| format!("Failed to commit schema migration {}", target))?;
+
+        version = target;
+    }
+
+    Ok(version)
+}
+
+/// Get the latest applied schema version, or 0 for an empty database
+fn current_version(connection: &Connection) -> Result<u32> {
+    connection
+        .query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )
+        .context("Failed to read schema version")
+}
+
+/// Record that a schema version has been applied
+fn record_version(connection: &Connection, version: u32) -> Result<()> {
+    connection
+        .execute(
+            "INSERT INTO schema_version (version, applied_at) VALUES (?, ?)",
+            params![version, chrono::Utc::now().to_rfc3339()],
+        )
+        .context("Failed to record schema version")?;
+    Ok(())
+}
+
+/// Infer the schema version of a database that predates the `schema_version` table
+fn detect_untracked_version(connection: &Connection) -> Result<u32> {
+    let exists = |sql: &str| -> Result<bool> {
+        connection
+            .query_row(sql, [], |row| row.get(0))
+            .context("Failed to inspect database schema")
+    };
+
+    let checks = [
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'memories')",
+        "SELECT EXISTS (SELECT 1 FROM pragma_table_info('memories') WHERE name = 'ttl_seconds')",
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'idempotency_keys')",
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'memories_fts')",
+    ];
+
+    let mut version = 0;
+    for check in checks {
+        if !exists(check)? {
+            break;
+        }
+        version += 1;
+    }
+
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_exists(connection: &Connection, name: &str) -> bool {
+        connection
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = ?)",
+                params![name],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_fresh_database_is_fully_migrated() -> Result<()> {
+        let mut connection = Connection::open_in_memory()?;
+
+        assert_eq!(run_migrations(&mut connection)?, MIGRATIONS.len() as u32);
+        assert!(table_exists(&connection, "memories_fts"));
+
+        // Running again is a no-op
+        assert_eq!(run_migrations(&mut connection)?, MIGRATIONS.len() as u32);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_v1_database_is_upgraded_without_data_loss() -> Result<()> {
+        let mut connection = Connection::open_in_memory()?;
+        apply_migrations(&mut connection, &MIGRATIONS[..1])?;
+        connection.execute(
+            "INSERT INTO memories VALUES ('mem_1', 'kept', 'text/plain', NULL, NULL, '{}', 1, ?1, ?1)",
+            params![chrono::Utc::now().to_rfc3339()],
+        )?;
+
+        assert_eq!(run_migrations(&mut connection)?, MIGRATIONS.len() as u32);
+
+        let (content, ttl): (String, Option<i64>) = connection.query_row(
+            "SELECT content, ttl_seconds FROM memories WHERE id = 'mem_1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        assert_eq!(content, "kept");
+        assert_eq!(ttl, None);
+
+        // Existing rows are added to the full-text index
+        let indexed: i64 = connection.query_row(
+            "SELECT COUNT(*) FROM memories_fts WHERE memories_fts MATCH 'kept'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(indexed, 1);
+
+        Ok(())
+    }
+
+    fn query_plan(connection: &Connection, column: &str) -> String {
+        let mut statement = connection
+            .prepare(&format!(
+                "EXPLAIN QUERY PLAN SELECT * FROM memories WHERE {} = ? \
+                 ORDER BY created_at, id LIMIT ? OFFSET ?",
+                column
+            ))
+            .unwrap();
+        let details = statement
+            .query_map(params!["code", 10, 0], |row| row.get::<_, String>(3))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+        details.join("\n")
+    }
+
+    #[test]
+    fn test_category_and_mode_lookups_use_indexes() -> Result<()> {
+        let mut connection = Connection::open_in_memory()?;
+        run_migrations(&mut connection)?;
+
+        let plan = query_plan(&connection, "category");
+        assert!(plan.contains("USING INDEX idx_category"), "{}", plan);
+        assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+
+        let plan = query_plan(&connection, "mode");
+        assert!(plan.contains("USING INDEX idx_mode"), "{}", plan);
+        assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() -> Result<()> {
+        let mut connection = Connection::open_in_memory()?;
+        let migrations = [
+            MIGRATIONS[0],
+            "CREATE TABLE partial (id INTEGER); NOT VALID SQL;",
+        ];
+
+        assert!(apply_migrations(&mut connection, &migrations).is_err());
+        assert!(!table_exists(&connection, "partial"));
+        assert_eq!(current_version(&connection)?, 1);
+
+        Ok(())
+    }
+}
@@ -1,6 +1,12 @@
 //! Database storage for memories
 
+mod async_repository;
+mod compaction;
+mod migrations;
 mod repository;
 mod schema;
 
+pub use async_repository::{AsyncMemoryRepository, AsyncSqliteMemoryRepository};
+pub use compaction::{compact_database, database_size, incremental_vacuum, CompactionInProgress};
+pub(crate) use migrations::run_migrations;
 pub use repository::{MemoryRepository, SqliteMemoryRepository};
@@ -1,74 +1,502 @@
 //! Repository for memory storage
 
-use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use r2d2::{Pool, PooledConnection};
+use r2d2_sqlite::SqliteConnectionManager;
+use regex::Regex;
+use rusqlite::backup::Backup;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, Row, TransactionBehavior};
 use serde_json;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 
+use super::compaction::{compact_database, free_list_count, incremental_vacuum};
+use super::migrations;
 use super::schema::{MemoryEntity, MemoryMetadata};
-use crate::storage::{Memory, MemoryId, TokenCount, Tokenizer};
+use crate::storage::{
+    CategoryStats, DuplicateMemoryId, Memory, MemoryFilter, MemoryId, MemoryQuery, MemorySortField,
+    MemoryStats, ModeStats, RegexSafetyCheck, SizeDistribution, SortOrder, TokenCount, Tokenizer,
+    HIDDEN_CATEGORIES, NO_MODE, UNCATEGORIZED,
+};
+
+/// Columns selected when loading a full memory row, with its tags as a JSON array
+const MEMORY_COLUMNS: &str =
+    "id, content, content_type, category, mode, metadata_json, token_count, \
+     created_at, last_accessed, ttl_seconds, pinned, access_count, \
+     (SELECT json_group_array(tag) FROM \
+         (SELECT tag FROM tags WHERE tags.memory_id = memories.id ORDER BY tag))";
+
+/// Pages copied per step when restoring a database over the live one
+const RESTORE_PAGES_PER_STEP: std::os::raw::c_int = 256;
+
+/// Default number of pooled database connections
+const DEFAULT_DB_POOL_SIZE: u32 = 4;
+
+/// How long a connection waits for a lock held by another connection
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Repository for memory storage
 pub trait MemoryRepository: Send + Sync + std::fmt::Debug {
     /// Store a memory
     fn store(&self, memory: &Memory) -> Result<()>;
 
+    /// Store a new memory, failing with [`DuplicateMemoryId`] if its ID is taken
+    fn insert(&self, memory: &Memory) -> Result<()>;
+
+    /// Store new memories atomically; if any insert fails, none are stored
+    fn store_batch(&self, memories: &[Memory]) -> Result<()>;
+
     /// Retrieve a memory by ID
     fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>>;
 
-    /// Update a memory's last accessed time
+    /// Record a retrieval of a memory, updating its last accessed time and access count
     fn touch(&self, id: &MemoryId) -> Result<()>;
 
-    /// Get all memory IDs
-    fn get_all_ids(&self) -> Result<Vec<MemoryId>>;
+    /// Delete a memory; deleting a missing ID is not an error
+    fn delete(&self, id: &MemoryId) -> Result<()>;
+
+    /// Protect a memory from expiration
+    fn pin(&self, id: &MemoryId) -> Result<()>;
+
+    /// Let a pinned memory expire again
+    fn unpin(&self, id: &MemoryId) -> Result<()>;
+
+    /// Tag a memory; tagging a missing memory or repeating a tag is not an error
+    fn add_tag(&self, id: &MemoryId, tag: &str) -> Result<()>;
+
+    /// Remove a tag from a memory
+    fn remove_tag(&self, id: &MemoryId, tag: &str) -> Result<()>;
+
+    /// Get one page of the memories with a tag, oldest memories first
+    fn get_by_tag(&self, tag: &str, page: usize, page_size: usize) -> Result<Vec<Memory>>;
+
+    /// Get the tags of a memory, sorted
+    fn get_tags_for_memory(&self, id: &MemoryId) -> Result<Vec<String>>;
+
+    /// Link one memory to another; repeating a link is not an error
+    fn link(&self, source: &MemoryId, target: &MemoryId, relation: &str) -> Result<()>;
+
+    /// Get the memories a memory links to, optionally only through one relation, oldest first
+    fn get_linked(&self, id: &MemoryId, relation: Option<&str>) -> Result<Vec<Memory>>;
+
+    /// Update a memory's content, metadata, token count and last accessed time
+    ///
+    /// All other fields, including `created_at`, are left unchanged.
+    fn update(&self, memory: &Memory) -> Result<()>;
+
+    /// Get one page of memory IDs, oldest memories first
+    fn get_all_ids(&self, page: usize, page_size: usize) -> Result<Vec<MemoryId>>;
+
+    /// Get one page of memories in the same order as `get_all_ids`
+    fn get_all(&self, page: usize, page_size: usize) -> Result<Vec<Memory>>;
+
+    /// Get one page of the memories in `category`, in the same order as `get_all`
+    fn get_by_category(&self, category: &str, page: usize, page_size: usize)
+        -> Result<Vec<Memory>>;
+
+    /// Get one page of the memories in `mode`, in the same order as `get_all`
+    fn get_by_mode(&self, mode: &str, page: usize, page_size: usize) -> Result<Vec<Memory>>;
+
+    /// Get one sorted page of the memories matching `filter`, with the total number matching
+    fn list(
+        &self,
+        filter: &MemoryFilter,
+        sort: MemorySortField,
+        order: SortOrder,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<Memory>, u64)>;
+
+    /// Get the number of pages of `page_size` memories
+    fn get_page_count(&self, page_size: usize) -> Result<usize>;
 
     /// Get the total number of tokens across all memories
     fn total_tokens(&self) -> Result<TokenCount>;
+
+    /// Count all memories
+    fn count(&self) -> Result<u64>;
+
+    /// Check that the underlying storage answers queries
+    fn check_connection(&self) -> Result<()>;
+
+    /// Search memory content with a regex, rejecting patterns that could backtrack excessively
+    fn search_content_regex_safe(&self, pattern: &str, limit: usize) -> Result<Vec<Memory>>;
+
+    /// Full-text search over memory content, best matches first, optionally restricted to a mode
+    fn search(&self, query: &str, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>>;
+
+    /// Count the memories [`MemoryRepository::search`] would find without a limit
+    fn count_search_matches(&self, query: &str, mode: Option<&str>) -> Result<u64>;
+
+    /// Delete all expired memories that are not pinned, returning the ID and token count of each
+    fn purge_expired(&self) -> Result<Vec<(MemoryId, TokenCount)>>;
+
+    /// Get the most recently accessed memories, newest first
+    fn get_recently_accessed(&self, limit: usize) -> Result<Vec<Memory>>;
+
+    /// Get the most often retrieved memories, optionally only those of one mode
+    ///
+    /// Memories are ordered by access count, ties going to the most recently
+    /// accessed; memories never retrieved are left out.
+    fn get_hot_memories(&self, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>>;
+
+    /// Get every memory whose metadata maps `key` to `value`, oldest first
+    fn get_by_metadata(&self, key: &str, value: &str) -> Result<Vec<Memory>>;
+
+    /// Get the distribution of memory sizes, optionally filtered by category and mode
+    fn get_memory_size_distribution(
+        &self,
+        category: Option<&str>,
+        mode: Option<&str>,
+    ) -> Result<SizeDistribution>;
+
+    /// Get memory counts and tokens, overall and per category and mode
+    fn get_stats(&self) -> Result<MemoryStats>;
+
+    /// Count the memories in each category, leaving out uncategorized memories
+    fn count_by_category(&self) -> Result<HashMap<String, u64>>;
+
+    /// Count the memories without a category
+    fn count_uncategorized(&self) -> Result<u64>;
+
+    /// Move every memory in the source category to the target category, returning the number moved
+    fn merge_categories(&self, source: &str, target: &str) -> Result<u64>;
+
+    /// Move one memory into `category`, returning whether it exists
+    fn set_category(&self, id: &MemoryId, category: &str) -> Result<bool>;
+
+    /// Reclaim the space left behind by deleted memories, returning the bytes recovered
+    fn compact(&self) -> Result<u64>;
+
+    /// Return up to `pages` free pages to the file system, or all of them if `pages` is 0,
+    /// returning the number freed
+    fn vacuum(&self, pages: u32) -> Result<u64>;
+
+    /// Number of free pages waiting to be reclaimed by [`Self::vacuum`]
+    fn free_list_size(&self) -> Result<u64>;
+
+    /// Path of the database file, or `None` if memories are not kept in a file
+    fn database_path(&self) -> Option<PathBuf>;
+
+    /// Replace every memory with the contents of the SQLite database at `source`
+    fn restore_from(&self, source: &Path) -> Result<()>;
+
+    /// Overwrite the token counts of the given memories in one transaction, returning the number updated
+    fn set_token_counts(&self, counts: &[(MemoryId, TokenCount)]) -> Result<u64>;
+
+    /// Open a savepoint named `name` that later writes can be rolled back to
+    fn savepoint(&self, name: &str) -> Result<()>;
+
+    /// Undo every write since the savepoint `name`, closing it and any savepoint opened after it
+    fn rollback_to_savepoint(&self, name: &str) -> Result<()>;
+
+    /// Keep every write since the savepoint `name`, closing it and any savepoint opened after it
+    fn release_savepoint(&self, name: &str) -> Result<()>;
+
+    /// Get the memory ID and creation time recorded for an idempotency key
+    fn get_idempotency_key(&self, key: &str) -> Result<Option<(MemoryId, DateTime<Utc>)>>;
+
+    /// Record the memory created for an idempotency key
+    fn store_idempotency_key(
+        &self,
+        key: &str,
+        memory_id: &MemoryId,
+        created_at: DateTime<Utc>,
+    ) -> Result<()>;
+
+    /// Remove idempotency keys created before the given time
+    fn prune_idempotency_keys(&self, before: DateTime<Utc>) -> Result<()>;
+
+    /// Copy a memory under a fresh ID, optionally into a different category or mode
+    ///
+    /// The copy records the original ID in its `copy_source_id` metadata entry.
+    fn copy_memory(
+        &self,
+        id: &MemoryId,
+        target_category: Option<String>,
+        target_mode: Option<String>,
+    ) -> Result<Memory> {
+        let source = self
+            .retrieve(id)?
+            .ok_or_else(|| anyhow!("Memory with ID {} not found", id.as_str()))?;
+
+        let mut metadata = source.metadata.clone();
+        metadata.insert("copy_source_id".to_string(), id.as_str().to_string());
+
+        let now = Utc::now();
+        let copy = Memory {
+            id: MemoryId::new(),
+            category: target_category.or(source.category),
+            mode: target_mode.or(source.mode),
+            metadata,
+            created_at: now,
+            last_accessed: now,
+            pinned: false,
+            access_count: 0,
+            ..source
+        };
+
+        self.store(&copy)?;
+        Ok(copy)
+    }
 }
 
 /// SQLite implementation of the memory repository
 #[derive(Debug)]
 pub struct SqliteMemoryRepository {
-    /// The database connection
-    connection: Arc<Mutex<Connection>>,
+    /// Pool of database connections
+    pool: Pool<SqliteConnectionManager>,
+    /// Path of the database file
+    db_path: PathBuf,
     /// The tokenizer used for counting tokens
     tokenizer: Tokenizer,
+    /// Connection holding the open savepoints, used for every call until they are closed
+    savepoint_connection: Mutex<Option<PooledConnection<SqliteConnectionManager>>>,
+}
+
+/// A pooled connection, or the connection holding the open savepoints
+enum RepositoryConnection<'a> {
+    Pooled(PooledConnection<SqliteConnectionManager>),
+    Savepoint(MutexGuard<'a, Option<PooledConnection<SqliteConnectionManager>>>),
+}
+
+impl Deref for RepositoryConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        match self {
+            Self::Pooled(connection) => connection,
+            Self::Savepoint(held) => held.as_ref().expect("savepoint connection is held"),
+        }
+    }
+}
+
+impl DerefMut for RepositoryConnection<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        match self {
+            Self::Pooled(connection) => connection,
+            Self::Savepoint(held) => held.as_mut().expect("savepoint connection is held"),
+        }
+    }
 }
 
 impl SqliteMemoryRepository {
-    /// Create a new SQLite memory repository
+    /// Create a new SQLite memory repository with [`Self::default_pool_size`] connections
     pub fn new(db_path: &Path, tokenizer: Tokenizer) -> Result<Self> {
+        Self::with_pool_size(db_path, tokenizer, Self::default_pool_size())
+    }
+
+    /// Number of pooled connections, from `DB_POOL_SIZE` or the default of 4
+    pub fn default_pool_size() -> u32 {
+        std::env::var("DB_POOL_SIZE")
+            .ok()
+            .and_then(|size| size.parse::<u32>().ok())
+            .filter(|&size| size > 0)
+            .unwrap_or(DEFAULT_DB_POOL_SIZE)
+    }
+
+    /// Create a new SQLite memory repository with a pool of `pool_size` connections
+    pub fn with_pool_size(db_path: &Path, tokenizer: Tokenizer, pool_size: u32) -> Result<Self> {
         // Create the database directory if it doesn't exist
         if let Some(parent) = db_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        // Open the database connection
-        let connection = Connection::open(db_path).context("Failed to open SQLite database")?;
+        // Every pooled connection uses WAL so readers do not block the writer
+        let manager = SqliteConnectionManager::file(db_path).with_init(|connection| {
+            // Only takes effect on a new database, before any table is created
+            connection.pragma_update(None, "auto_vacuum", "INCREMENTAL")?;
+            connection.pragma_update(None, "journal_mode", "WAL")?;
+            connection.pragma_update(None, "foreign_keys", true)?;
+            connection.busy_timeout(BUSY_TIMEOUT)?;
+
+            // Register the regexp function used by regex searches
+            Self::register_regexp_function(connection)
+        });
+
+        let pool = Pool::builder()
+            .max_size(pool_size.max(1))
+            .build(manager)
+            .context("Failed to open SQLite database")?;
+        // Bring the schema up to date before anything else touches the database
+        let mut connection = pool.get().context("Failed to get database connection")?;
+        migrations::run_migrations(&mut connection)?;
+        drop(connection);
+
+        Ok(Self {
+            pool,
+            db_path: db_path.to_path_buf(),
+            tokenizer,
+            savepoint_connection: Mutex::new(None),
+        })
+    }
+
+    /// Get a connection from the pool, or the one holding the open savepoints
+    ///
+    /// Savepoints belong to a single connection, so while any is open every
+    /// call goes through that connection, one at a time.
+    fn connection(&self) -> Result<RepositoryConnection<'_>> {
+        let held = self.savepoint_connection.lock().unwrap();
+        if held.is_some() {
+            return Ok(RepositoryConnection::Savepoint(held));
+        }
+
+        // Checked out under the lock so a savepoint cannot open in between
+        let connection = self
+            .pool
+            .get()
+            .context("Failed to get database connection")?;
+        drop(held);
+        Ok(RepositoryConnection::Pooled(connection))
+    }
 
-        // Create the memories table if it doesn't exist
+    /// Run `sql` on the connection holding the open savepoints, returning it to the pool once none are left
+    fn close_savepoint(&self, name: &str, sql: &str) -> Result<()> {
+        let mut held = self.savepoint_connection.lock().unwrap();
+        let connection = held
+            .as_ref()
+            .ok_or_else(|| anyhow!("No savepoint named {} is open", name))?;
+        connection
+            .execute_batch(sql)
+            .with_context(|| format!("Failed to close savepoint {}", name))?;
+
+        if connection.is_autocommit() {
+            *held = None;
+        }
+        Ok(())
+    }
+
+    fn set_pinned(&self, id: &MemoryId, pinned: bool) -> Result<()> {
+        let connection = self.connection()?;
         connection
             .execute(
-                "CREATE TABLE IF NOT EXISTS memories (
-                id TEXT PRIMARY KEY,
-                content TEXT NOT NULL,
-                content_type TEXT NOT NULL,
-                category TEXT,
-                mode TEXT,
-                metadata_json TEXT NOT NULL,
-                token_count INTEGER NOT NULL,
-                created_at TEXT NOT NULL,
-                last_accessed TEXT NOT NULL
-            )",
+                "UPDATE memories SET pinned = ? WHERE id = ?",
+                params![pinned, id.as_str()],
+            )
+            .context("Failed to update pinned flag")?;
+
+        Ok(())
+    }
+
+    fn pinned_count(&self) -> Result<usize> {
+        let connection = self.connection()?;
+        let count: i64 = connection
+            .query_row(
+                "SELECT COUNT(*) FROM memories WHERE pinned = 1",
                 [],
+                |row| row.get(0),
             )
-            .context("Failed to create memories table")?;
+            .context("Failed to count pinned memories")?;
 
-        Ok(Self {
-            connection: Arc::new(Mutex::new(connection)),
-            tokenizer,
+        Ok(count as usize)
+    }
+
+    /// Count the memories and sum their tokens per value of `column`, keying NULL as `missing`
+    fn group_stats(&self, column: &str, missing: &str) -> Result<HashMap<String, (usize, usize)>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT COALESCE({0}, ?), COUNT(*), SUM(token_count) FROM memories
+                 WHERE {1} GROUP BY 1",
+                column,
+                visible_condition()
+            ))
+            .with_context(|| format!("Failed to prepare stats by {} statement", column))?;
+
+        let groups = stmt
+            .query_map(params![missing], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    (
+                        row.get::<_, i64>(1)? as usize,
+                        row.get::<_, i64>(2)? as usize,
+                    ),
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()
+            .with_context(|| format!("Failed to compute stats by {}", column))?;
+
+        Ok(groups)
+    }
+
+    /// Get one page of the memories whose indexed `column` equals `value`
+    fn get_where(
+        &self,
+        column: &str,
+        value: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<Memory>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories WHERE {} = ? ORDER BY created_at, id LIMIT ? OFFSET ?",
+                MEMORY_COLUMNS, column
+            ))
+            .with_context(|| format!("Failed to prepare get by {} statement", column))?;
+
+        let (limit, offset) = page_bounds(page, page_size);
+        let mut rows = stmt.query(params![value, limit, offset])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    /// Register a `regexp(pattern, text)` SQL function backed by the regex crate
+    fn register_regexp_function(connection: &Connection) -> rusqlite::Result<()> {
+        connection.create_scalar_function(
+            "regexp",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let regex: Arc<Regex> = ctx.get_or_create_aux(
+                    0,
+                    |vr| -> std::result::Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                        Ok(Regex::new(vr.as_str()?)?)
+                    },
+                )?;
+                let text = ctx
+                    .get_raw(1)
+                    .as_str()
+                    .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))?;
+
+                Ok(regex.is_match(text))
+            },
+        )
+    }
+
+    /// Read a MemoryEntity from a row selected with `MEMORY_COLUMNS`
+    fn entity_from_row(row: &Row) -> Result<MemoryEntity> {
+        Ok(MemoryEntity {
+            id: row.get(0)?,
+            content: row.get(1)?,
+            content_type: row.get(2)?,
+            category: row.get(3)?,
+            mode: row.get(4)?,
+            metadata_json: row.get(5)?,
+            token_count: row.get(6)?,
+            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
+                .context("Failed to parse created_at")?
+                .with_timezone(&Utc),
+            last_accessed: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
+                .context("Failed to parse last_accessed")?
+                .with_timezone(&Utc),
+            ttl_seconds: row.get::<_, Option<i64>>(9)?.map(|ttl| ttl as u64),
+            pinned: row.get(10)?,
+            access_count: row.get::<_, i64>(11)? as u64,
+            tags: serde_json::from_str(&row.get::<_, String>(12)?)
+                .context("Failed to parse memory tags")?,
         })
     }
 
@@ -88,6 +516,10 @@ impl SqliteMemoryRepository {
             token_count: memory.token_count.as_usize(),
             created_at: memory.created_at,
             last_accessed: memory.last_accessed,
+            ttl_seconds: memory.ttl_seconds,
+            pinned: memory.pinned,
+            access_count: memory.access_count,
+            tags: memory.tags.clone(),
         })
     }
 
@@ -106,19 +538,31 @@ impl SqliteMemoryRepository {
             token_count: TokenCount::from(entity.token_count),
             created_at: entity.created_at,
             last_accessed: entity.last_accessed,
+            ttl_seconds: entity.ttl_seconds,
+            pinned: entity.pinned,
+            tags: entity.tags,
+            access_count: entity.access_count,
         })
     }
-}
 
-impl MemoryRepository for SqliteMemoryRepository {
-    fn store(&self, memory: &Memory) -> Result<()> {
+    /// Write a memory and its tags, resolving an existing ID with `on_conflict`
+    ///
+    /// Without a conflict clause a memory whose ID exists fails with [`DuplicateMemoryId`].
+    fn write_memory(&self, memory: &Memory, on_conflict: &str) -> Result<()> {
         let entity = Self::memory_to_entity(memory)?;
 
-        let connection = self.connection.lock().unwrap();
-        connection.execute(
-            "INSERT OR REPLACE INTO memories (
-                id, content, content_type, category, mode, metadata_json, token_count, created_at, last_accessed
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        // A savepoint nests inside open savepoints and begins a transaction otherwise
+        let mut connection = self.connection()?;
+        let transaction = connection
+            .savepoint()
+            .context("Failed to begin store transaction")?;
+        let inserted = transaction.execute(
+            &format!(
+                "INSERT INTO memories (
+                    id, content, content_type, category, mode, metadata_json, token_count, created_at, last_accessed, ttl_seconds, pinned, access_count
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) {}",
+                on_conflict
+            ),
             params![
                 entity.id,
                 entity.content,
@@ -129,39 +573,95 @@ impl MemoryRepository for SqliteMemoryRepository {
                 entity.token_count,
                 entity.created_at.to_rfc3339(),
                 entity.last_accessed.to_rfc3339(),
+                entity
+                .ttl_seconds
+                .map(|ttl| i64::try_from(ttl).unwrap_or(i64::MAX)),
+                entity.pinned,
+                entity.access_count as i64,
             ],
-        ).context("Failed to store memory")?;
+        );
+        match inserted {
+            Err(rusqlite::Error::SqliteFailure(error, _))
+                if error.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY =>
+            {
+                return Err(DuplicateMemoryId(memory.id.clone()).into());
+            }
+            inserted => inserted.context("Failed to store memory")?,
+        };
+
+        // A replaced memory takes the stored memory's tags
+        transaction
+            .execute("DELETE FROM tags WHERE memory_id = ?", params![entity.id])
+            .context("Failed to clear memory tags")?;
+        insert_tags(&transaction, &entity.id, &entity.tags)?;
+        transaction.commit().context("Failed to commit memory")?;
 
         Ok(())
     }
+}
+
+/// Conflict clause updating a stored memory in place
+///
+/// `INSERT OR REPLACE` would delete the old row first, and with it every link
+/// and other row referencing the memory through `ON DELETE CASCADE`.
+const UPDATE_ON_CONFLICT: &str = "ON CONFLICT (id) DO UPDATE SET
+    content = excluded.content,
+    content_type = excluded.content_type,
+    category = excluded.category,
+    mode = excluded.mode,
+    metadata_json = excluded.metadata_json,
+    token_count = excluded.token_count,
+    created_at = excluded.created_at,
+    last_accessed = excluded.last_accessed,
+    ttl_seconds = excluded.ttl_seconds,
+    pinned = excluded.pinned,
+    access_count = excluded.access_count";
+
+impl MemoryRepository for SqliteMemoryRepository {
+    fn store(&self, memory: &Memory) -> Result<()> {
+        self.write_memory(memory, UPDATE_ON_CONFLICT)
+    }
+
+    fn insert(&self, memory: &Memory) -> Result<()> {
+        self.write_memory(memory, "")
+    }
+
+    fn store_batch(&self, memories: &[Memory]) -> Result<()> {
+        let mut connection = self.connection()?;
+
+        // Store the batch in one transaction so it costs a single commit. BEGIN
+        // IMMEDIATE takes the write lock before the first insert; within open
+        // savepoints, where no transaction can begin, the batch nests as another one.
+        // Dropping the transaction on an error rolls back every insert.
+        if connection.is_autocommit() {
+            let transaction = connection
+                .transaction_with_behavior(TransactionBehavior::Immediate)
+                .context("Failed to begin bulk store transaction")?;
+            insert_batch(&transaction, memories)?;
+            transaction.commit()
+        } else {
+            let savepoint = connection
+                .savepoint()
+                .context("Failed to begin bulk store savepoint")?;
+            insert_batch(&savepoint, memories)?;
+            savepoint.commit()
+        }
+        .context("Failed to commit bulk store")
+    }
 
     fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>> {
-        let connection = self.connection.lock().unwrap();
-        let mut stmt = connection.prepare(
-            "SELECT id, content, content_type, category, mode, metadata_json, token_count, created_at, last_accessed
-             FROM memories
-             WHERE id = ?"
-        ).context("Failed to prepare retrieve statement")?;
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories WHERE id = ?",
+                MEMORY_COLUMNS
+            ))
+            .context("Failed to prepare retrieve statement")?;
 
         let mut rows = stmt.query(params![id.as_str()])?;
 
         if let Some(row) = rows.next()? {
-            let entity = MemoryEntity {
-                id: row.get(0)?,
-                content: row.get(1)?,
-                content_type: row.get(2)?,
-                category: row.get(3)?,
-                mode: row.get(4)?,
-                metadata_json: row.get(5)?,
-                token_count: row.get(6)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
-                    .context("Failed to parse created_at")?
-                    .with_timezone(&Utc),
-                last_accessed: DateTime::parse_from_rfc3339(&row.get::<_, String>(8)?)
-                    .context("Failed to parse last_accessed")?
-                    .with_timezone(&Utc),
-            };
-
+            let entity = Self::entity_from_row(row)?;
             let memory = self.entity_to_memory(entity)?;
             Ok(Some(memory))
         } else {
@@ -172,10 +672,11 @@ impl MemoryRepository for SqliteMemoryRepository {
     fn touch(&self, id: &MemoryId) -> Result<()> {
         let now = Utc::now().to_rfc3339();
 
-        let connection = self.connection.lock().unwrap();
+        let connection = self.connection()?;
         connection
             .execute(
-                "UPDATE memories SET last_accessed = ? WHERE id = ?",
+                "UPDATE memories SET last_accessed = ?, access_count = access_count + 1
+                 WHERE id = ?",
                 params![now, id.as_str()],
             )
             .context("Failed to update last_accessed")?;
@@ -183,13 +684,151 @@ impl MemoryRepository for SqliteMemoryRepository {
         Ok(())
     }
 
-    fn get_all_ids(&self) -> Result<Vec<MemoryId>> {
-        let connection = self.connection.lock().unwrap();
+    fn delete(&self, id: &MemoryId) -> Result<()> {
+        let connection = self.connection()?;
+        connection
+            .execute("DELETE FROM memories WHERE id = ?", params![id.as_str()])
+            .context("Failed to delete memory")?;
+
+        Ok(())
+    }
+
+    fn pin(&self, id: &MemoryId) -> Result<()> {
+        self.set_pinned(id, true)
+    }
+
+    fn add_tag(&self, id: &MemoryId, tag: &str) -> Result<()> {
+        let connection = self.connection()?;
+        connection
+            .execute(
+                "INSERT OR IGNORE INTO tags (memory_id, tag) SELECT id, ? FROM memories WHERE id = ?",
+                params![tag, id.as_str()],
+            )
+            .context("Failed to add tag")?;
+
+        Ok(())
+    }
+
+    fn remove_tag(&self, id: &MemoryId, tag: &str) -> Result<()> {
+        let connection = self.connection()?;
+        connection
+            .execute(
+                "DELETE FROM tags WHERE memory_id = ? AND tag = ?",
+                params![id.as_str(), tag],
+            )
+            .context("Failed to remove tag")?;
+
+        Ok(())
+    }
+
+    fn get_tags_for_memory(&self, id: &MemoryId) -> Result<Vec<String>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare("SELECT tag FROM tags WHERE memory_id = ? ORDER BY tag")
+            .context("Failed to prepare get tags statement")?;
+
+        let tags = stmt
+            .query_map(params![id.as_str()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .context("Failed to get memory tags")?;
+
+        Ok(tags)
+    }
+
+    fn link(&self, source: &MemoryId, target: &MemoryId, relation: &str) -> Result<()> {
+        let connection = self.connection()?;
+        connection
+            .execute(
+                "INSERT OR IGNORE INTO memory_links (source_id, target_id, relation_type)
+                 VALUES (?, ?, ?)",
+                params![source.as_str(), target.as_str(), relation],
+            )
+            .context("Failed to link memories")?;
+
+        Ok(())
+    }
+
+    fn get_linked(&self, id: &MemoryId, relation: Option<&str>) -> Result<Vec<Memory>> {
+        let connection = self.connection()?;
         let mut stmt = connection
-            .prepare("SELECT id FROM memories")
+            .prepare(&format!(
+                "SELECT {} FROM memories
+                 WHERE id IN (
+                     SELECT target_id FROM memory_links
+                     WHERE source_id = ?1 AND (?2 IS NULL OR relation_type = ?2)
+                 )
+                 ORDER BY created_at, id",
+                MEMORY_COLUMNS
+            ))
+            .context("Failed to prepare get linked statement")?;
+
+        let mut rows = stmt.query(params![id.as_str(), relation])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    fn get_by_tag(&self, tag: &str, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories
+                 WHERE id IN (SELECT memory_id FROM tags WHERE tag = ?)
+                 ORDER BY created_at, id LIMIT ? OFFSET ?",
+                MEMORY_COLUMNS
+            ))
+            .context("Failed to prepare get by tag statement")?;
+
+        let (limit, offset) = page_bounds(page, page_size);
+        let mut rows = stmt.query(params![tag, limit, offset])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    fn unpin(&self, id: &MemoryId) -> Result<()> {
+        self.set_pinned(id, false)
+    }
+
+    fn update(&self, memory: &Memory) -> Result<()> {
+        let entity = Self::memory_to_entity(memory)?;
+
+        let connection = self.connection()?;
+        connection
+            .execute(
+                "UPDATE memories SET content = ?, metadata_json = ?, token_count = ?, last_accessed = ?
+                 WHERE id = ?",
+                params![
+                    entity.content,
+                    entity.metadata_json,
+                    entity.token_count,
+                    entity.last_accessed.to_rfc3339(),
+                    entity.id,
+                ],
+            )
+            .context("Failed to update memory")?;
+
+        Ok(())
+    }
+
+    fn get_all_ids(&self, page: usize, page_size: usize) -> Result<Vec<MemoryId>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare("SELECT id FROM memories ORDER BY created_at, id LIMIT ? OFFSET ?")
             .context("Failed to prepare get_all_ids statement")?;
 
-        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let (limit, offset) = page_bounds(page, page_size);
+        let rows = stmt.query_map(params![limit, offset], |row| row.get::<_, String>(0))?;
 
         let mut ids = Vec::new();
         for id_result in rows {
@@ -200,14 +839,773 @@ impl MemoryRepository for SqliteMemoryRepository {
         Ok(ids)
     }
 
+    fn get_all(&self, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories ORDER BY created_at, id LIMIT ? OFFSET ?",
+                MEMORY_COLUMNS
+            ))
+            .context("Failed to prepare get_all statement")?;
+
+        let (limit, offset) = page_bounds(page, page_size);
+        let mut rows = stmt.query(params![limit, offset])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    fn get_by_category(
+        &self,
+        category: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<Memory>> {
+        self.get_where("category", category, page, page_size)
+    }
+
+    fn get_by_mode(&self, mode: &str, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        self.get_where("mode", mode, page, page_size)
+    }
+
+    fn list(
+        &self,
+        filter: &MemoryFilter,
+        sort: MemorySortField,
+        order: SortOrder,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<Memory>, u64)> {
+        let (where_clause, mut values) = filter_clause(filter);
+        let connection = self.connection()?;
+
+        let total: i64 = connection
+            .query_row(
+                &format!("SELECT COUNT(*) FROM memories{}", where_clause),
+                params_from_iter(&values),
+                |row| row.get(0),
+            )
+            .context("Failed to count listed memories")?;
+
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories{} ORDER BY {} {3}, id {3} LIMIT ? OFFSET ?",
+                MEMORY_COLUMNS,
+                where_clause,
+                sort.column(),
+                order.as_sql()
+            ))
+            .context("Failed to prepare list statement")?;
+        let (limit, offset) = page_bounds(page, page_size);
+        values.extend([Value::Integer(limit), Value::Integer(offset)]);
+        let mut rows = stmt.query(params_from_iter(&values))?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok((memories, total as u64))
+    }
+
+    fn get_page_count(&self, page_size: usize) -> Result<usize> {
+        if page_size == 0 {
+            return Err(anyhow!("Page size must be greater than zero"));
+        }
+
+        let connection = self.connection()?;
+        let count: i64 = connection
+            .query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))
+            .context("Failed to count memories")?;
+
+        Ok((count as usize).div_ceil(page_size))
+    }
+
     fn total_tokens(&self) -> Result<TokenCount> {
-        let connection = self.connection.lock().unwrap();
+        let connection = self.connection()?;
         let mut stmt = connection
-            .prepare("SELECT SUM(token_count) FROM memories")
+            .prepare(&format!(
+                "SELECT SUM(token_count) FROM memories WHERE {}",
+                visible_condition()
+            ))
             .context("Failed to prepare total_tokens statement")?;
 
         let total: i64 = stmt.query_row([], |row| row.get(0)).unwrap_or(0);
 
         Ok(TokenCount::from(total as usize))
     }
+
+    fn count(&self) -> Result<u64> {
+        let connection = self.connection()?;
+        let count: i64 = connection
+            .query_row("SELECT COUNT(*) FROM memories", [], |row| row.get(0))
+            .context("Failed to count memories")?;
+
+        Ok(count as u64)
+    }
+
+    fn check_connection(&self) -> Result<()> {
+        let connection = self.connection()?;
+        connection
+            .query_row("SELECT 1", [], |row| row.get::<_, i64>(0))
+            .context("Database did not answer")?;
+
+        Ok(())
+    }
+
+    fn search_content_regex_safe(&self, pattern: &str, limit: usize) -> Result<Vec<Memory>> {
+        // Validate the pattern before handing it to SQLite
+        RegexSafetyCheck::new().check(pattern)?;
+
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories WHERE content REGEXP ? ORDER BY last_accessed DESC LIMIT ?",
+                MEMORY_COLUMNS
+            ))
+            .context("Failed to prepare regex search statement")?;
+
+        let mut rows = stmt.query(params![pattern, limit as i64])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    fn search(&self, query: &str, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>> {
+        let query = fts_query(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories
+                 JOIN (SELECT id AS match_id, rank FROM memories_fts WHERE memories_fts MATCH ?1)
+                   ON memories.id = match_id
+                 WHERE (?2 IS NULL OR mode = ?2) AND {}
+                 ORDER BY rank LIMIT ?3",
+                MEMORY_COLUMNS,
+                visible_condition()
+            ))
+            .context("Failed to prepare full-text search statement")?;
+
+        let mut rows = stmt.query(params![query, mode, limit as i64])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    fn count_search_matches(&self, query: &str, mode: Option<&str>) -> Result<u64> {
+        let query = fts_query(query);
+        if query.is_empty() {
+            return Ok(0);
+        }
+
+        let connection = self.connection()?;
+        let count: i64 = connection
+            .query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM memories
+                     JOIN (SELECT id AS match_id FROM memories_fts WHERE memories_fts MATCH ?1)
+                       ON memories.id = match_id
+                     WHERE (?2 IS NULL OR mode = ?2) AND {}",
+                    visible_condition()
+                ),
+                params![query, mode],
+                |row| row.get(0),
+            )
+            .context("Failed to count full-text search matches")?;
+
+        Ok(count as u64)
+    }
+
+    fn purge_expired(&self) -> Result<Vec<(MemoryId, TokenCount)>> {
+        let connection = self.connection()?;
+        let mut stmt = connection.prepare(
+            "DELETE FROM memories WHERE ttl_seconds IS NOT NULL AND pinned = 0
+             AND CAST(strftime('%s', created_at) AS INTEGER) + ttl_seconds
+                 <= CAST(strftime('%s', 'now') AS INTEGER)
+             RETURNING id, token_count",
+        )?;
+        let purged = stmt
+            .query_map([], |row| {
+                Ok((
+                    MemoryId::from(row.get::<_, String>(0)?),
+                    TokenCount::new(row.get::<_, i64>(1)? as usize),
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to purge expired memories")?;
+
+        Ok(purged)
+    }
+
+    fn get_recently_accessed(&self, limit: usize) -> Result<Vec<Memory>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories ORDER BY last_accessed DESC LIMIT ?",
+                MEMORY_COLUMNS
+            ))
+            .context("Failed to prepare recently accessed statement")?;
+
+        let mut rows = stmt.query(params![limit as i64])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    fn get_hot_memories(&self, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories
+                 WHERE access_count > 0 AND (?1 IS NULL OR mode = ?1)
+                 ORDER BY access_count DESC, last_accessed DESC LIMIT ?2",
+                MEMORY_COLUMNS
+            ))
+            .context("Failed to prepare hot memories statement")?;
+
+        let mut rows = stmt.query(params![mode, limit as i64])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    fn get_by_metadata(&self, key: &str, value: &str) -> Result<Vec<Memory>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM memories
+                 WHERE json_extract(metadata_json, '$.values.' || json_quote(?1)) = ?2
+                 ORDER BY created_at, id",
+                MEMORY_COLUMNS
+            ))
+            .context("Failed to prepare get by metadata statement")?;
+
+        let mut rows = stmt.query(params![key, value])?;
+
+        let mut memories = Vec::new();
+        while let Some(row) = rows.next()? {
+            let entity = Self::entity_from_row(row)?;
+            memories.push(self.entity_to_memory(entity)?);
+        }
+
+        Ok(memories)
+    }
+
+    fn get_memory_size_distribution(
+        &self,
+        category: Option<&str>,
+        mode: Option<&str>,
+    ) -> Result<SizeDistribution> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(
+                "SELECT token_count FROM memories
+                 WHERE (?1 IS NULL OR category = ?1) AND (?2 IS NULL OR mode = ?2)",
+            )
+            .context("Failed to prepare size distribution statement")?;
+
+        let counts = stmt
+            .query_map(params![category, mode], |row| row.get::<_, i64>(0))?
+            .map(|count| count.map(|count| count as usize))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(SizeDistribution::from_token_counts(counts))
+    }
+
+    fn get_stats(&self) -> Result<MemoryStats> {
+        let by_category = self.group_stats("category", UNCATEGORIZED)?;
+        let by_mode = self.group_stats("mode", NO_MODE)?;
+
+        Ok(MemoryStats {
+            total_count: by_category.values().map(|(count, _)| count).sum(),
+            total_tokens: by_category.values().map(|(_, tokens)| tokens).sum(),
+            by_category: by_category
+                .into_iter()
+                .map(|(category, (memory_count, token_count))| {
+                    (
+                        category,
+                        CategoryStats {
+                            memory_count,
+                            token_count,
+                        },
+                    )
+                })
+                .collect(),
+            by_mode: by_mode
+                .into_iter()
+                .map(|(mode, (memory_count, token_count))| {
+                    (
+                        mode,
+                        ModeStats {
+                            memory_count,
+                            token_count,
+                        },
+                    )
+                })
+                .collect(),
+            pinned_count: self.pinned_count()?,
+        })
+    }
+
+    fn count_by_category(&self) -> Result<HashMap<String, u64>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare(
+                "SELECT category, COUNT(*) FROM memories
+                 WHERE category IS NOT NULL GROUP BY category",
+            )
+            .context("Failed to prepare count by category statement")?;
+
+        let counts = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            })?
+            .collect::<rusqlite::Result<_>>()
+            .context("Failed to count memories by category")?;
+
+        Ok(counts)
+    }
+
+    fn count_uncategorized(&self) -> Result<u64> {
+        let connection = self.connection()?;
+        let count: i64 = connection
+            .query_row(
+                "SELECT COUNT(*) FROM memories WHERE category IS NULL",
+                [],
+                |row| row.get(0),
+            )
+            .context("Failed to count uncategorized memories")?;
+
+        Ok(count as u64)
+    }
+
+    fn merge_categories(&self, source: &str, target: &str) -> Result<u64> {
+        let connection = self.connection()?;
+        let updated = connection
+            .execute(
+                "UPDATE memories SET category = ? WHERE category = ?",
+                params![target, source],
+            )
+            .context("Failed to merge categories")?;
+
+        Ok(updated as u64)
+    }
+
+    fn set_category(&self, id: &MemoryId, category: &str) -> Result<bool> {
+        let connection = self.connection()?;
+        let updated = connection
+            .execute(
+                "UPDATE memories SET category = ? WHERE id = ?",
+                params![category, id.as_str()],
+            )
+            .context("Failed to set memory category")?;
+
+        Ok(updated > 0)
+    }
+
+    fn compact(&self) -> Result<u64> {
+        let connection = self.connection()?;
+        compact_database(&connection, &self.db_path)
+    }
+
+    fn vacuum(&self, pages: u32) -> Result<u64> {
+        let connection = self.connection()?;
+        incremental_vacuum(&connection, pages)
+    }
+
+    fn free_list_size(&self) -> Result<u64> {
+        let connection = self.connection()?;
+        free_list_count(&connection)
+    }
+
+    fn database_path(&self) -> Option<PathBuf> {
+        Some(self.db_path.clone())
+    }
+
+    fn restore_from(&self, source: &Path) -> Result<()> {
+        let source = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Failed to open {}", source.display()))?;
+
+        // Copied through the live connection, so every pooled connection sees the restored pages
+        let mut connection = self.connection()?;
+        Backup::new(&source, &mut connection)
+            .and_then(|backup| {
+                backup.run_to_completion(RESTORE_PAGES_PER_STEP, Duration::ZERO, None)
+            })
+            .context("Failed to copy database")?;
+
+        // The source may predate the latest schema
+        migrations::run_migrations(&mut connection)?;
+        Ok(())
+    }
+
+    fn set_token_counts(&self, counts: &[(MemoryId, TokenCount)]) -> Result<u64> {
+        let mut connection = self.connection()?;
+
+        let transaction = connection
+            .savepoint()
+            .context("Failed to begin token count transaction")?;
+        let mut updated = 0;
+        {
+            let mut stmt = transaction
+                .prepare("UPDATE memories SET token_count = ? WHERE id = ?")
+                .context("Failed to prepare token count update")?;
+            for (id, token_count) in counts {
+                updated += stmt
+                    .execute(params![token_count.as_usize() as i64, id.as_str()])
+                    .with_context(|| {
+                        format!("Failed to update token count of memory {}", id.as_str())
+                    })?;
+            }
+        }
+        transaction
+            .commit()
+            .context("Failed to commit token count update")?;
+
+        Ok(updated as u64)
+    }
+
+    fn savepoint(&self, name: &str) -> Result<()> {
+        let mut held = self.savepoint_connection.lock().unwrap();
+        if held.is_none() {
+            *held = Some(
+                self.pool
+                    .get()
+                    .context("Failed to get database connection")?,
+            );
+        }
+
+        let connection = held.as_ref().expect("savepoint connection is held");
+        let result = connection
+            .execute_batch(&format!("SAVEPOINT {}", name))
+            .with_context(|| format!("Failed to open savepoint {}", name));
+        if connection.is_autocommit() {
+            *held = None;
+        }
+        result
+    }
+
+    fn rollback_to_savepoint(&self, name: &str) -> Result<()> {
+        // Rolling back leaves the savepoint open, so release it as well
+        self.close_savepoint(
+            name,
+            &format!("ROLLBACK TO SAVEPOINT {0}; RELEASE SAVEPOINT {0};", name),
+        )
+    }
+
+    fn release_savepoint(&self, name: &str) -> Result<()> {
+        self.close_savepoint(name, &format!("RELEASE SAVEPOINT {}", name))
+    }
+
+    fn get_idempotency_key(&self, key: &str) -> Result<Option<(MemoryId, DateTime<Utc>)>> {
+        let connection = self.connection()?;
+        let mut stmt = connection
+            .prepare("SELECT memory_id, created_at FROM idempotency_keys WHERE key = ?")
+            .context("Failed to prepare idempotency key statement")?;
+
+        let mut rows = stmt.query(params![key])?;
+
+        if let Some(row) = rows.next()? {
+            let memory_id: String = row.get(0)?;
+            let created_at: String = row.get(1)?;
+            let created_at = DateTime::parse_from_rfc3339(&created_at)
+                .context("Invalid idempotency key timestamp")?
+                .with_timezone(&Utc);
+            Ok(Some((MemoryId::from(memory_id), created_at)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn store_idempotency_key(
+        &self,
+        key: &str,
+        memory_id: &MemoryId,
+        created_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let connection = self.connection()?;
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO idempotency_keys (key, memory_id, created_at)
+                 VALUES (?, ?, ?)",
+                params![key, memory_id.as_str(), idempotency_timestamp(created_at)],
+            )
+            .context("Failed to store idempotency key")?;
+
+        Ok(())
+    }
+
+    fn prune_idempotency_keys(&self, before: DateTime<Utc>) -> Result<()> {
+        let connection = self.connection()?;
+        connection
+            .execute(
+                "DELETE FROM idempotency_keys WHERE created_at < ?",
+                params![idempotency_timestamp(before)],
+            )
+            .context("Failed to prune idempotency keys")?;
+
+        Ok(())
+    }
+}
+
+/// Tag a newly stored memory within the transaction storing it
+fn insert_tags(connection: &Connection, memory_id: &str, tags: &[String]) -> Result<()> {
+    let mut stmt = connection
+        .prepare_cached("INSERT OR IGNORE INTO tags (memory_id, tag) VALUES (?, ?)")
+        .context("Failed to prepare tag statement")?;
+    for tag in tags {
+        stmt.execute(params![memory_id, tag])
+            .with_context(|| format!("Failed to tag memory {}", memory_id))?;
+    }
+    Ok(())
+}
+
+/// Insert a batch of new memories and their tags within the transaction storing them
+fn insert_batch(connection: &Connection, memories: &[Memory]) -> Result<()> {
+    let mut stmt = connection
+        .prepare(
+            "INSERT INTO memories (
+                id, content, content_type, category, mode, metadata_json, token_count, created_at, last_accessed, ttl_seconds, pinned, access_count
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .context("Failed to prepare bulk store statement")?;
+
+    for memory in memories {
+        let entity = SqliteMemoryRepository::memory_to_entity(memory)?;
+        stmt.execute(params![
+            entity.id,
+            entity.content,
+            entity.content_type,
+            entity.category,
+            entity.mode,
+            entity.metadata_json,
+            entity.token_count,
+            entity.created_at.to_rfc3339(),
+            entity.last_accessed.to_rfc3339(),
+            entity
+                .ttl_seconds
+                .map(|ttl| i64::try_from(ttl).unwrap_or(i64::MAX)),
+            entity.pinned,
+            entity.access_count as i64,
+        ])
+        .with_context(|| format!("Failed to store memory {}", memory.id.as_str()))?;
+        insert_tags(connection, &entity.id, &entity.tags)?;
+    }
+    Ok(())
+}
+
+/// Build the `WHERE` clause selecting the memories matching `filter`, with its parameters
+fn filter_clause(filter: &MemoryFilter) -> (String, Vec<Value>) {
+    let mut conditions = vec![visible_condition()];
+    let mut values = Vec::new();
+    let mut bind = |condition: &'static str, value: String| {
+        conditions.push(condition.to_string());
+        values.push(Value::Text(value));
+    };
+
+    if let Some(category) = &filter.category {
+        bind("category = ?", category.clone());
+    }
+    if let Some(mode) = &filter.mode {
+        bind("mode = ?", mode.clone());
+    }
+    // Timestamps are stored as RFC 3339 text, which sorts chronologically
+    if let Some(from_date) = filter.from_date {
+        bind("created_at >= ?", from_date.to_rfc3339());
+    }
+    if let Some(to_date) = filter.to_date {
+        bind("created_at <= ?", to_date.to_rfc3339());
+    }
+    if let Some(text) = &filter.content_contains {
+        bind("instr(content, ?) > 0", text.clone());
+    }
+    if let Some(query) = &filter.query {
+        conditions.push(query_condition(query, &mut values));
+    }
+
+    (format!(" WHERE {}", conditions.join(" AND ")), values)
+}
+
+/// Build the condition leaving out the bookkeeping memories in [`HIDDEN_CATEGORIES`]
+fn visible_condition() -> String {
+    let categories: Vec<String> = HIDDEN_CATEGORIES
+        .iter()
+        .map(|category| format!("'{}'", category))
+        .collect();
+    format!(
+        "(category IS NULL OR category NOT IN ({}))",
+        categories.join(", ")
+    )
+}
+
+/// Build the SQL condition of a query, appending its parameters to `values`
+///
+/// `IS` compares nullable columns so that `NOT category:x` matches uncategorized memories.
+fn query_condition(query: &MemoryQuery, values: &mut Vec<Value>) -> String {
+    let mut bind = |condition: &str, value: Value| {
+        values.push(value);
+        condition.to_string()
+    };
+    match query {
+        MemoryQuery::Category(category) => bind("category IS ?", Value::Text(category.clone())),
+        MemoryQuery::Mode(mode) => bind("mode IS ?", Value::Text(mode.clone())),
+        MemoryQuery::Tag(tag) => bind(
+            "EXISTS (SELECT 1 FROM tags WHERE tags.memory_id = memories.id AND tags.tag = ?)",
+            Value::Text(tag.clone()),
+        ),
+        MemoryQuery::TokensAbove(tokens) => bind(
+            "token_count > ?",
+            Value::Integer((*tokens).min(i64::MAX as usize) as i64),
+        ),
+        MemoryQuery::TokensBelow(tokens) => bind(
+            "token_count < ?",
+            Value::Integer((*tokens).min(i64::MAX as usize) as i64),
+        ),
+        MemoryQuery::CreatedAfter(from) => bind("created_at >= ?", Value::Text(from.to_rfc3339())),
+        MemoryQuery::ContentContains(text) => {
+            bind("instr(content, ?) > 0", Value::Text(text.clone()))
+        }
+        MemoryQuery::And(left, right) => format!(
+            "({} AND {})",
+            query_condition(left, values),
+            query_condition(right, values)
+        ),
+        MemoryQuery::Or(left, right) => format!(
+            "({} OR {})",
+            query_condition(left, values),
+            query_condition(right, values)
+        ),
+        MemoryQuery::Not(query) => format!("NOT ({})", query_condition(query, values)),
+    }
+}
+
+/// Convert a page number and size into SQLite `LIMIT` and `OFFSET` values
+fn page_bounds(page: usize, page_size: usize) -> (i64, i64) {
+    let limit = page_size.min(i64::MAX as usize);
+    let offset = page.saturating_mul(page_size).min(i64::MAX as usize);
+    (limit as i64, offset as i64)
+}
+
+/// Turn free text into an FTS5 query matching every word
+///
+/// Each word is quoted so that punctuation and FTS5 operators in user input
+/// are matched literally instead of being parsed as query syntax.
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Format an idempotency key timestamp so that stored values sort chronologically
+fn idempotency_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_count_by_category_uses_index() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repository =
+            SqliteMemoryRepository::new(&dir.path().join("memories.db"), Tokenizer::default())?;
+
+        // Seed in one statement; the full-text trigger is not under test and slows inserts
+        let connection = repository.connection()?;
+        connection.execute_batch(
+            "DROP TRIGGER memories_fts_insert;
+             WITH RECURSIVE seq(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM seq WHERE i < 9999)
+             INSERT INTO memories (id, content, content_type, category, mode, metadata_json,
+                                   token_count, created_at, last_accessed)
+             SELECT 'mem_' || i, 'memory ' || i, 'text/plain',
+                    CASE i % 5 WHEN 0 THEN NULL ELSE 'category_' || (i % 5) END,
+                    NULL, '{\"values\":{}}', 2, '2024-01-01T00:00:00+00:00',
+                    '2024-01-01T00:00:00+00:00'
+             FROM seq;",
+        )?;
+
+        let plan: String = connection.query_row(
+            "EXPLAIN QUERY PLAN SELECT category, COUNT(*) FROM memories
+             WHERE category IS NOT NULL GROUP BY category",
+            [],
+            |row| row.get(3),
+        )?;
+        assert!(plan.contains("idx_category"), "{}", plan);
+
+        let started = Instant::now();
+        let counts = repository.count_by_category()?;
+        let elapsed = started.elapsed();
+
+        assert_eq!(counts.len(), 4);
+        assert!(counts.values().all(|&count| count == 2_000));
+        assert_eq!(repository.count_uncategorized()?, 2_000);
+        assert!(elapsed < Duration::from_millis(10), "took {:?}", elapsed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vacuum_shrinks_free_list() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repository =
+            SqliteMemoryRepository::new(&dir.path().join("memories.db"), Tokenizer::default())?;
+
+        let connection = repository.connection()?;
+        connection.execute_batch(
+            "WITH RECURSIVE seq(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM seq WHERE i < 999)
+             INSERT INTO memories (id, content, content_type, metadata_json, token_count,
+                                   created_at, last_accessed)
+             SELECT 'mem_' || i, 'memory ' || i || ' ' || hex(randomblob(512)), 'text/plain',
+                    '{\"values\":{}}', 2, '2024-01-01T00:00:00+00:00',
+                    '2024-01-01T00:00:00+00:00'
+             FROM seq;
+             DELETE FROM memories;",
+        )?;
+        drop(connection);
+
+        let before = repository.free_list_size()?;
+        assert!(before > 100, "only {} free pages", before);
+
+        assert_eq!(repository.vacuum(100)?, 100);
+        assert_eq!(repository.free_list_size()?, before - 100);
+
+        assert_eq!(repository.vacuum(0)?, before - 100);
+        assert_eq!(repository.free_list_size()?, 0);
+
+        Ok(())
+    }
 }
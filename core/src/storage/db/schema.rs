@@ -24,6 +24,14 @@ pub struct MemoryEntity {
     pub created_at: DateTime<Utc>,
     /// When the memory was last accessed
     pub last_accessed: DateTime<Utc>,
+    /// Seconds after creation when the memory expires
+    pub ttl_seconds: Option<u64>,
+    /// Whether the memory is protected from expiration
+    pub pinned: bool,
+    /// Number of times the memory has been retrieved
+    pub access_count: u64,
+    /// Tags of the memory, sorted, from the `tags` table
+    pub tags: Vec<String>,
 }
 
 /// Memory metadata for database storage
@@ -0,0 +1,294 @@
+//! Detection of near-duplicate memories
+//!
+//! Memories are compared by the Jaccard similarity of their whitespace-separated
+//! terms. To avoid comparing every pair, each memory gets a MinHash signature
+//! and only memories whose signatures agree on a whole band are compared.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use super::memory::{Memory, MemoryId};
+
+/// Number of hash functions in a MinHash signature
+const SIGNATURE_LENGTH: usize = 128;
+
+/// Number of signature values in each locality-sensitive hashing band
+///
+/// Memories sharing any band are compared. With 32 bands of 4 values a pair
+/// of similarity 0.5 becomes a candidate with probability 0.87 and a pair of
+/// 0.8 almost surely, while 4 bands of 32 values would miss most pairs below 0.95.
+const ROWS_PER_BAND: usize = 4;
+
+/// Metadata key counting the duplicates merged into a memory
+pub(super) const DUPLICATE_COUNT_KEY: &str = "duplicate_count";
+
+/// Memories whose terms largely overlap, keyed by the one to keep
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    /// Longest memory of the group
+    pub primary_id: MemoryId,
+    /// Memories similar to the primary, most similar first
+    pub duplicate_ids: Vec<MemoryId>,
+    /// Lowest similarity between the primary and one of its duplicates
+    pub similarity: f64,
+}
+
+/// Split content into its set of whitespace-separated terms
+pub fn term_set(content: &str) -> HashSet<&str> {
+    content.split_whitespace().collect()
+}
+
+/// Compute the Jaccard similarity of two term sets
+pub fn jaccard_similarity(a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// Get the number of duplicates already merged into a memory
+pub(super) fn duplicate_count(memory: &Memory) -> u32 {
+    memory
+        .metadata
+        .get(DUPLICATE_COUNT_KEY)
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Group memories whose similarity to a longer memory is at least `threshold`
+///
+/// Each memory is in at most one group. Longer memories become primaries first,
+/// so a group's primary is its longest memory; groups are ordered by primary.
+pub fn find_duplicate_groups(memories: &[Memory], threshold: f64) -> Vec<DuplicateGroup> {
+    let terms: Vec<_> = memories.iter().map(|m| term_set(&m.content)).collect();
+
+    // Every candidate pair is compared exactly, once
+    let mut similar: HashMap<usize, Vec<(usize, f64)>> = HashMap::new();
+    for (i, j) in candidate_pairs(&terms) {
+        let similarity = jaccard_similarity(&terms[i], &terms[j]);
+        if similarity >= threshold {
+            similar.entry(i).or_default().push((j, similarity));
+            similar.entry(j).or_default().push((i, similarity));
+        }
+    }
+
+    let mut order: Vec<usize> = similar.keys().copied().collect();
+    order.sort_by(|&a, &b| {
+        memories[b]
+            .content
+            .len()
+            .cmp(&memories[a].content.len())
+            .then_with(|| memories[a].id.as_str().cmp(memories[b].id.as_str()))
+    });
+
+    let mut grouped = vec![false; memories.len()];
+    let mut groups = Vec::new();
+    for primary in order {
+        if grouped[primary] {
+            continue;
+        }
+        let mut duplicates: Vec<(usize, f64)> = similar[&primary]
+            .iter()
+            .copied()
+            .filter(|&(other, _)| !grouped[other])
+            .collect();
+        if duplicates.is_empty() {
+            continue;
+        }
+        duplicates.sort_by(|a, b| {
+            b.1.total_cmp(&a.1)
+                .then_with(|| memories[a.0].id.as_str().cmp(memories[b.0].id.as_str()))
+        });
+
+        grouped[primary] = true;
+        for &(other, _) in &duplicates {
+            grouped[other] = true;
+        }
+        groups.push(DuplicateGroup {
+            primary_id: memories[primary].id.clone(),
+            similarity: duplicates
+                .iter()
+                .map(|&(_, similarity)| similarity)
+                .fold(1.0, f64::min),
+            duplicate_ids: duplicates
+                .into_iter()
+                .map(|(other, _)| memories[other].id.clone())
+                .collect(),
+        });
+    }
+
+    groups
+}
+
+/// Find the pairs of term sets whose MinHash signatures agree on at least one band
+fn candidate_pairs(terms: &[HashSet<&str>]) -> HashSet<(usize, usize)> {
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (index, terms) in terms.iter().enumerate() {
+        let signature = minhash_signature(terms);
+        for (band, rows) in signature.chunks(ROWS_PER_BAND).enumerate() {
+            let mut hasher = DefaultHasher::new();
+            rows.hash(&mut hasher);
+            buckets
+                .entry((band, hasher.finish()))
+                .or_default()
+                .push(index);
+        }
+    }
+
+    let mut pairs = HashSet::new();
+    for members in buckets.values() {
+        for (position, &i) in members.iter().enumerate() {
+            for &j in &members[position + 1..] {
+                pairs.insert((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+/// Compute the MinHash signature of a term set
+fn minhash_signature(terms: &HashSet<&str>) -> [u64; SIGNATURE_LENGTH] {
+    let mut signature = [u64::MAX; SIGNATURE_LENGTH];
+    for term in terms {
+        let mut hasher = DefaultHasher::new();
+        term.hash(&mut hasher);
+        let term_hash = hasher.finish();
+        for (seed, min) in signature.iter_mut().enumerate() {
+            *min = (*min).min(mix(
+                term_hash ^ (seed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ));
+        }
+    }
+    signature
+}
+
+/// Scramble a 64-bit value (the SplitMix64 finalizer), giving one hash function per seed
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tokenizer::Tokenizer;
+
+    fn memories(contents: &[String]) -> Vec<Memory> {
+        let tokenizer = Tokenizer::default();
+        contents
+            .iter()
+            .map(|content| {
+                Memory::new(
+                    content.clone(),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    Default::default(),
+                    &tokenizer,
+                )
+            })
+            .collect()
+    }
+
+    /// Sentence of `length` numbered words about `topic`
+    fn sentence(topic: &str, length: usize) -> String {
+        (0..length)
+            .map(|i| format!("{}{}", topic, i))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn test_jaccard_similarity() {
+        let a = term_set("the quick brown fox");
+        let b = term_set("the quick red fox jumps");
+        assert_eq!(jaccard_similarity(&a, &b), 3.0 / 6.0);
+        assert_eq!(jaccard_similarity(&a, &a), 1.0);
+        assert_eq!(jaccard_similarity(&term_set(""), &term_set("  ")), 1.0);
+    }
+
+    #[test]
+    fn test_signatures_estimate_similarity() {
+        let base = sentence("word", 200);
+        let a = term_set(&base);
+        // Replace 40 of the 200 terms: similarity 160 / 240
+        let edited = format!("{} {}", sentence("word", 160), sentence("other", 40));
+        let b = term_set(&edited);
+
+        let (sa, sb) = (minhash_signature(&a), minhash_signature(&b));
+        let agreeing = sa.iter().zip(&sb).filter(|(x, y)| x == y).count();
+        let estimate = agreeing as f64 / SIGNATURE_LENGTH as f64;
+        assert!(
+            (estimate - jaccard_similarity(&a, &b)).abs() < 0.15,
+            "estimate {}",
+            estimate
+        );
+        assert_eq!(minhash_signature(&a), sa);
+    }
+
+    #[test]
+    fn test_finds_near_duplicates() {
+        let base = sentence("alpha", 50);
+        let contents = vec![
+            // 0: original
+            base.clone(),
+            // 1: two words dropped, similarity 48 / 50
+            sentence("alpha", 48),
+            // 2: five words added and the original, similarity 50 / 55
+            format!("{} {}", base, sentence("extra", 5)),
+            // 3 and 4: a second, unrelated pair
+            sentence("beta", 30),
+            format!("{} beta-tail", sentence("beta", 30)),
+            // 5: unrelated
+            sentence("gamma", 40),
+            // 6: shares half of the alpha terms
+            format!("{} {}", sentence("alpha", 25), sentence("delta", 25)),
+        ];
+        let memories = memories(&contents);
+        let id = |index: usize| memories[index].id.clone();
+
+        let groups = find_duplicate_groups(&memories, 0.8);
+        assert_eq!(groups.len(), 2);
+
+        // The longest memory is the primary, its most similar duplicate first
+        assert_eq!(groups[0].primary_id, id(2));
+        assert_eq!(groups[0].duplicate_ids, vec![id(0), id(1)]);
+        assert_eq!(groups[0].similarity, 48.0 / 55.0);
+
+        assert_eq!(groups[1].primary_id, id(4));
+        assert_eq!(groups[1].duplicate_ids, vec![id(3)]);
+        assert_eq!(groups[1].similarity, 30.0 / 31.0);
+
+        // A higher threshold splits off the less similar duplicates
+        let groups = find_duplicate_groups(&memories, 0.95);
+        let pairs: Vec<_> = groups
+            .iter()
+            .map(|group| (group.primary_id.clone(), group.duplicate_ids.clone()))
+            .collect();
+        assert_eq!(pairs, vec![(id(0), vec![id(1)]), (id(4), vec![id(3)])]);
+    }
+
+    #[test]
+    fn test_many_copies_form_one_group() {
+        let contents: Vec<String> = (0..300)
+            .map(|i| {
+                format!(
+                    "{} variant{}",
+                    sentence(&format!("topic{}-", i % 100), 20),
+                    i
+                )
+            })
+            .collect();
+        let groups = find_duplicate_groups(&memories(&contents), 0.8);
+
+        // Each of the 100 topics appears three times, with one differing term
+        assert_eq!(groups.len(), 100);
+        assert!(groups.iter().all(|group| group.duplicate_ids.len() == 2));
+        assert!(groups
+            .iter()
+            .all(|group| (group.similarity - 20.0 / 22.0).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,126 @@
+//! Line-level differences between two memories
+//!
+//! Used to see how a memory such as a progress note changed between versions.
+
+use similar::{ChangeTag, TextDiff};
+use std::collections::{BTreeSet, HashMap};
+
+use super::memory::Memory;
+
+/// Changes between an older and a newer memory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryDiff {
+    /// Lines only in the newer content
+    pub added_lines: Vec<String>,
+    /// Lines only in the older content
+    pub removed_lines: Vec<String>,
+    /// Lines in both contents
+    pub unchanged_lines: Vec<String>,
+    /// Tokens of the newer memory minus those of the older one
+    pub added_tokens: i64,
+    /// Metadata keys whose values differ, mapped to their old and new values
+    pub metadata_changes: HashMap<String, (Option<String>, Option<String>)>,
+}
+
+impl MemoryDiff {
+    /// Compare `old` with `new` line by line
+    pub fn diff(old: &Memory, new: &Memory) -> Self {
+        let mut diff = Self {
+            added_tokens: new.token_count.as_usize() as i64 - old.token_count.as_usize() as i64,
+            ..Self::default()
+        };
+
+        for change in TextDiff::from_lines(&old.content, &new.content).iter_all_changes() {
+            let line = change.value().trim_end_matches(['\r', '\n']).to_string();
+            match change.tag() {
+                ChangeTag::Insert => diff.added_lines.push(line),
+                ChangeTag::Delete => diff.removed_lines.push(line),
+                ChangeTag::Equal => diff.unchanged_lines.push(line),
+            }
+        }
+
+        let keys: BTreeSet<&String> = old.metadata.keys().chain(new.metadata.keys()).collect();
+        for key in keys {
+            let (old_value, new_value) = (old.metadata.get(key), new.metadata.get(key));
+            if old_value != new_value {
+                diff.metadata_changes
+                    .insert(key.clone(), (old_value.cloned(), new_value.cloned()));
+            }
+        }
+
+        diff
+    }
+
+    /// Check whether the memories have the same content and metadata
+    pub fn is_empty(&self) -> bool {
+        self.added_lines.is_empty()
+            && self.removed_lines.is_empty()
+            && self.metadata_changes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Tokenizer;
+
+    fn memory(content: &str, metadata: &[(&str, &str)]) -> Memory {
+        Memory::new(
+            content.to_string(),
+            "text/plain".to_string(),
+            Some("progress".to_string()),
+            None,
+            metadata
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            &Tokenizer::default(),
+        )
+    }
+
+    #[test]
+    fn test_identical_content_has_empty_diff() {
+        let old = memory("Wrote the parser\nAdded tests\n", &[("status", "done")]);
+        let new = memory("Wrote the parser\nAdded tests\n", &[("status", "done")]);
+
+        let diff = MemoryDiff::diff(&old, &new);
+        assert!(diff.is_empty());
+        assert_eq!(diff.unchanged_lines, ["Wrote the parser", "Added tests"]);
+        assert_eq!(diff.added_tokens, 0);
+    }
+
+    #[test]
+    fn test_completely_different_content() {
+        let old = memory("first draft\nof the plan", &[("status", "draft")]);
+        let new = memory("shipped", &[("reviewer", "sam")]);
+
+        let diff = MemoryDiff::diff(&old, &new);
+        assert_eq!(diff.removed_lines, ["first draft", "of the plan"]);
+        assert_eq!(diff.added_lines, ["shipped"]);
+        assert!(diff.unchanged_lines.is_empty());
+        assert_eq!(diff.added_tokens, -4);
+        assert_eq!(
+            diff.metadata_changes,
+            HashMap::from([
+                ("status".to_string(), (Some("draft".to_string()), None)),
+                ("reviewer".to_string(), (None, Some("sam".to_string()))),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_partial_overlap() {
+        let old = memory("Wrote the parser\nFixing the lexer\nNext: docs\n", &[]);
+        let new = memory(
+            "Wrote the parser\nFixed the lexer\nNext: docs\nThen release\n",
+            &[],
+        );
+
+        let diff = MemoryDiff::diff(&old, &new);
+        assert_eq!(diff.removed_lines, ["Fixing the lexer"]);
+        assert_eq!(diff.added_lines, ["Fixed the lexer", "Then release"]);
+        assert_eq!(diff.unchanged_lines, ["Wrote the parser", "Next: docs"]);
+        assert_eq!(diff.added_tokens, 2);
+        assert!(diff.metadata_changes.is_empty());
+    }
+}
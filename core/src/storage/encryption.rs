@@ -0,0 +1,477 @@
+//! Encryption of memory content at rest
+//!
+//! When `ENCRYPTION_KEY` holds a 32-byte hex key, memory content is encrypted
+//! with AES-256-GCM before it reaches the repository. Each value is stored as
+//! `enc:v1:` followed by the base64 of a random nonce and the ciphertext. IDs,
+//! content types, categories, modes and metadata stay in plaintext so they can
+//! still be indexed, and token counts are taken from the plaintext.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::db::MemoryRepository;
+use super::filter::{MemoryFilter, MemorySortField, SortOrder};
+use super::memory::{Memory, MemoryId};
+use super::query::MemoryQuery;
+use super::regex_safety::RegexSafetyCheck;
+use super::stats::{MemoryStats, SizeDistribution};
+use super::tokenizer::TokenCount;
+
+/// Environment variable holding the hex-encoded content encryption key
+pub const ENCRYPTION_KEY_VAR: &str = "ENCRYPTION_KEY";
+
+/// Prefix marking encrypted content
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// Length of an AES-256 key in bytes
+const KEY_LEN: usize = 32;
+
+/// Length of an AES-GCM nonce in bytes
+const NONCE_LEN: usize = 12;
+
+/// Encrypts and decrypts memory content with AES-256-GCM
+#[derive(Clone)]
+pub struct ContentCipher {
+    cipher: Aes256Gcm,
+}
+
+impl std::fmt::Debug for ContentCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContentCipher").finish_non_exhaustive()
+    }
+}
+
+impl ContentCipher {
+    /// Create a cipher from a 32-byte key
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.len() != KEY_LEN {
+            bail!(
+                "Encryption key must be {} bytes, got {}",
+                KEY_LEN,
+                key.len()
+            );
+        }
+        Ok(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        })
+    }
+
+    /// Create a cipher from a hex-encoded 32-byte key
+    pub fn from_hex(key: &str) -> Result<Self> {
+        let key = hex::decode(key.trim()).context("Encryption key is not valid hex")?;
+        Self::new(&key)
+    }
+
+    /// Create the cipher configured by `ENCRYPTION_KEY`, if it is set
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var(ENCRYPTION_KEY_VAR) {
+            Ok(key) if !key.is_empty() => Self::from_hex(&key)
+                .with_context(|| format!("Invalid {}", ENCRYPTION_KEY_VAR))
+                .map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Check whether stored content was encrypted
+    pub fn is_encrypted(content: &str) -> bool {
+        content.starts_with(ENCRYPTED_PREFIX)
+    }
+
+    /// Encrypt content under a fresh random nonce
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt memory content"))?;
+
+        let mut payload = nonce.to_vec();
+        payload.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(payload)))
+    }
+
+    /// Decrypt stored content; content stored before encryption was enabled is returned as is
+    pub fn decrypt(&self, content: &str) -> Result<String> {
+        let Some(encoded) = content.strip_prefix(ENCRYPTED_PREFIX) else {
+            return Ok(content.to_string());
+        };
+
+        let payload = BASE64
+            .decode(encoded)
+            .context("Encrypted content is not valid base64")?;
+        if payload.len() < NONCE_LEN {
+            bail!("Encrypted content is too short");
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Failed to decrypt memory content, is the key correct?"))?;
+        String::from_utf8(plaintext).context("Decrypted content is not valid UTF-8")
+    }
+}
+
+/// Repository that encrypts memory content before handing it to another repository
+///
+/// Content searches cannot run on ciphertext, so they scan the decrypted memories.
+#[derive(Debug)]
+pub struct EncryptedRepository {
+    inner: Arc<dyn MemoryRepository>,
+    cipher: ContentCipher,
+}
+
+impl EncryptedRepository {
+    /// Encrypt the content stored in `inner` with `cipher`
+    pub fn new(inner: Arc<dyn MemoryRepository>, cipher: ContentCipher) -> Self {
+        Self { inner, cipher }
+    }
+
+    fn encrypt(&self, memory: &Memory) -> Result<Memory> {
+        Ok(Memory {
+            content: self.cipher.encrypt(&memory.content)?,
+            ..memory.clone()
+        })
+    }
+
+    fn decrypt(&self, mut memory: Memory) -> Result<Memory> {
+        memory.content = self
+            .cipher
+            .decrypt(&memory.content)
+            .with_context(|| format!("Memory {}", memory.id.as_str()))?;
+        Ok(memory)
+    }
+
+    fn decrypt_all(&self, memories: Vec<Memory>) -> Result<Vec<Memory>> {
+        memories
+            .into_iter()
+            .map(|memory| self.decrypt(memory))
+            .collect()
+    }
+
+    /// Decrypt every memory matching `filter`, most recently accessed first
+    fn scan(&self, filter: impl Fn(&Memory) -> bool, limit: usize) -> Result<Vec<Memory>> {
+        let mut matches: Vec<Memory> = self
+            .decrypt_all(self.inner.get_all(0, usize::MAX)?)?
+            .into_iter()
+            .filter(|memory| filter(memory))
+            .collect();
+        matches.sort_by_key(|memory| std::cmp::Reverse(memory.last_accessed));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+}
+
+impl MemoryRepository for EncryptedRepository {
+    fn store(&self, memory: &Memory) -> Result<()> {
+        self.inner.store(&self.encrypt(memory)?)
+    }
+
+    fn insert(&self, memory: &Memory) -> Result<()> {
+        self.inner.insert(&self.encrypt(memory)?)
+    }
+
+    fn store_batch(&self, memories: &[Memory]) -> Result<()> {
+        let encrypted = memories
+            .iter()
+            .map(|memory| self.encrypt(memory))
+            .collect::<Result<Vec<_>>>()?;
+        self.inner.store_batch(&encrypted)
+    }
+
+    fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>> {
+        self.inner
+            .retrieve(id)?
+            .map(|memory| self.decrypt(memory))
+            .transpose()
+    }
+
+    fn touch(&self, id: &MemoryId) -> Result<()> {
+        self.inner.touch(id)
+    }
+
+    fn delete(&self, id: &MemoryId) -> Result<()> {
+        self.inner.delete(id)
+    }
+
+    fn pin(&self, id: &MemoryId) -> Result<()> {
+        self.inner.pin(id)
+    }
+
+    fn unpin(&self, id: &MemoryId) -> Result<()> {
+        self.inner.unpin(id)
+    }
+
+    fn add_tag(&self, id: &MemoryId, tag: &str) -> Result<()> {
+        self.inner.add_tag(id, tag)
+    }
+
+    fn remove_tag(&self, id: &MemoryId, tag: &str) -> Result<()> {
+        self.inner.remove_tag(id, tag)
+    }
+
+    fn get_by_tag(&self, tag: &str, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        self.decrypt_all(self.inner.get_by_tag(tag, page, page_size)?)
+    }
+
+    fn get_tags_for_memory(&self, id: &MemoryId) -> Result<Vec<String>> {
+        self.inner.get_tags_for_memory(id)
+    }
+
+    fn link(&self, source: &MemoryId, target: &MemoryId, relation: &str) -> Result<()> {
+        self.inner.link(source, target, relation)
+    }
+
+    fn get_linked(&self, id: &MemoryId, relation: Option<&str>) -> Result<Vec<Memory>> {
+        self.decrypt_all(self.inner.get_linked(id, relation)?)
+    }
+
+    fn update(&self, memory: &Memory) -> Result<()> {
+        self.inner.update(&self.encrypt(memory)?)
+    }
+
+    fn get_all_ids(&self, page: usize, page_size: usize) -> Result<Vec<MemoryId>> {
+        self.inner.get_all_ids(page, page_size)
+    }
+
+    fn get_all(&self, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        self.decrypt_all(self.inner.get_all(page, page_size)?)
+    }
+
+    fn get_by_category(
+        &self,
+        category: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<Memory>> {
+        self.decrypt_all(self.inner.get_by_category(category, page, page_size)?)
+    }
+
+    fn list(
+        &self,
+        filter: &MemoryFilter,
+        sort: MemorySortField,
+        order: SortOrder,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<Memory>, u64)> {
+        let tests_content = filter.content_contains.is_some()
+            || filter
+                .query
+                .as_ref()
+                .is_some_and(MemoryQuery::tests_content);
+        if !tests_content {
+            let (memories, total) = self.inner.list(filter, sort, order, page, page_size)?;
+            return Ok((self.decrypt_all(memories)?, total));
+        }
+
+        // Stored content is encrypted, so match it after decrypting every other match
+        let others = MemoryFilter {
+            content_contains: None,
+            query: None,
+            ..filter.clone()
+        };
+        let (memories, _) = self.inner.list(&others, sort, order, 0, usize::MAX)?;
+        let matches: Vec<Memory> = self
+            .decrypt_all(memories)?
+            .into_iter()
+            .filter(|memory| filter.matches(memory))
+            .collect();
+        let total = matches.len() as u64;
+        let page = matches
+            .into_iter()
+            .skip(page.saturating_mul(page_size))
+            .take(page_size)
+            .collect();
+        Ok((page, total))
+    }
+
+    fn get_by_mode(&self, mode: &str, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        self.decrypt_all(self.inner.get_by_mode(mode, page, page_size)?)
+    }
+
+    fn get_page_count(&self, page_size: usize) -> Result<usize> {
+        self.inner.get_page_count(page_size)
+    }
+
+    fn count(&self) -> Result<u64> {
+        self.inner.count()
+    }
+
+    fn check_connection(&self) -> Result<()> {
+        self.inner.check_connection()
+    }
+
+    fn total_tokens(&self) -> Result<TokenCount> {
+        self.inner.total_tokens()
+    }
+
+    fn search_content_regex_safe(&self, pattern: &str, limit: usize) -> Result<Vec<Memory>> {
+        let regex = RegexSafetyCheck::new().check(pattern)?;
+        self.scan(|memory| regex.is_match(&memory.content), limit)
+    }
+
+    fn search(&self, query: &str, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>> {
+        let terms: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        self.scan(
+            |memory| {
+                let content = memory.content.to_lowercase();
+                !memory.is_hidden()
+                    && mode.is_none_or(|mode| memory.mode.as_deref() == Some(mode))
+                    && terms.iter().all(|term| content.contains(term.as_str()))
+            },
+            limit,
+        )
+    }
+
+    fn count_search_matches(&self, query: &str, mode: Option<&str>) -> Result<u64> {
+        Ok(self.search(query, mode, usize::MAX)?.len() as u64)
+    }
+
+    fn purge_expired(&self) -> Result<Vec<(MemoryId, TokenCount)>> {
+        self.inner.purge_expired()
+    }
+
+    fn get_recently_accessed(&self, limit: usize) -> Result<Vec<Memory>> {
+        self.decrypt_all(self.inner.get_recently_accessed(limit)?)
+    }
+
+    fn get_hot_memories(&self, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>> {
+        self.decrypt_all(self.inner.get_hot_memories(mode, limit)?)
+    }
+
+    fn get_by_metadata(&self, key: &str, value: &str) -> Result<Vec<Memory>> {
+        self.decrypt_all(self.inner.get_by_metadata(key, value)?)
+    }
+
+    fn get_memory_size_distribution(
+        &self,
+        category: Option<&str>,
+        mode: Option<&str>,
+    ) -> Result<SizeDistribution> {
+        self.inner.get_memory_size_distribution(category, mode)
+    }
+
+    fn get_stats(&self) -> Result<MemoryStats> {
+        self.inner.get_stats()
+    }
+
+    fn count_by_category(&self) -> Result<HashMap<String, u64>> {
+        self.inner.count_by_category()
+    }
+
+    fn count_uncategorized(&self) -> Result<u64> {
+        self.inner.count_uncategorized()
+    }
+
+    fn merge_categories(&self, source: &str, target: &str) -> Result<u64> {
+        self.inner.merge_categories(source, target)
+    }
+
+    fn set_category(&self, id: &MemoryId, category: &str) -> Result<bool> {
+        self.inner.set_category(id, category)
+    }
+
+    fn compact(&self) -> Result<u64> {
+        self.inner.compact()
+    }
+
+    fn vacuum(&self, pages: u32) -> Result<u64> {
+        self.inner.vacuum(pages)
+    }
+
+    fn free_list_size(&self) -> Result<u64> {
+        self.inner.free_list_size()
+    }
+
+    fn database_path(&self) -> Option<PathBuf> {
+        self.inner.database_path()
+    }
+
+    fn restore_from(&self, source: &Path) -> Result<()> {
+        self.inner.restore_from(source)
+    }
+
+    fn set_token_counts(&self, counts: &[(MemoryId, TokenCount)]) -> Result<u64> {
+        self.inner.set_token_counts(counts)
+    }
+
+    fn savepoint(&self, name: &str) -> Result<()> {
+        self.inner.savepoint(name)
+    }
+
+    fn rollback_to_savepoint(&self, name: &str) -> Result<()> {
+        self.inner.rollback_to_savepoint(name)
+    }
+
+    fn release_savepoint(&self, name: &str) -> Result<()> {
+        self.inner.release_savepoint(name)
+    }
+
+    fn get_idempotency_key(&self, key: &str) -> Result<Option<(MemoryId, DateTime<Utc>)>> {
+        self.inner.get_idempotency_key(key)
+    }
+
+    fn store_idempotency_key(
+        &self,
+        key: &str,
+        memory_id: &MemoryId,
+        created_at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.inner.store_idempotency_key(key, memory_id, created_at)
+    }
+
+    fn prune_idempotency_keys(&self, before: DateTime<Utc>) -> Result<()> {
+        self.inner.prune_idempotency_keys(before)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn test_round_trip_uses_fresh_nonces() {
+        let cipher = ContentCipher::from_hex(KEY).unwrap();
+        let first = cipher.encrypt("secret").unwrap();
+        let second = cipher.encrypt("secret").unwrap();
+
+        assert!(ContentCipher::is_encrypted(&first));
+        assert_ne!(first, second);
+        assert_eq!(cipher.decrypt(&first).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_plaintext_passes_through() {
+        let cipher = ContentCipher::from_hex(KEY).unwrap();
+        assert_eq!(cipher.decrypt("stored earlier").unwrap(), "stored earlier");
+    }
+
+    #[test]
+    fn test_wrong_key_fails_to_decrypt() {
+        let encrypted = ContentCipher::from_hex(KEY)
+            .unwrap()
+            .encrypt("secret")
+            .unwrap();
+        let other = ContentCipher::new(&[7; KEY_LEN]).unwrap();
+        assert!(other.decrypt(&encrypted).is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed_keys() {
+        assert!(ContentCipher::from_hex("not hex").is_err());
+        assert!(ContentCipher::from_hex("0011").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Filtering and sorting of memory listings
+
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+
+use super::memory::Memory;
+use super::query::MemoryQuery;
+
+/// Conditions a listed memory must meet; unset fields match every memory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryFilter {
+    pub category: Option<String>,
+    pub mode: Option<String>,
+    /// Earliest creation time, inclusive
+    pub from_date: Option<DateTime<Utc>>,
+    /// Latest creation time, inclusive
+    pub to_date: Option<DateTime<Utc>>,
+    /// Text the content must contain, matched case-sensitively
+    pub content_contains: Option<String>,
+    /// Expression the memory must also meet
+    pub query: Option<MemoryQuery>,
+}
+
+impl MemoryFilter {
+    /// Check whether a memory meets every condition; hidden memories never do
+    pub fn matches(&self, memory: &Memory) -> bool {
+        !memory.is_hidden()
+            && self
+                .category
+                .as_ref()
+                .is_none_or(|category| memory.category.as_ref() == Some(category))
+            && self
+                .mode
+                .as_ref()
+                .is_none_or(|mode| memory.mode.as_ref() == Some(mode))
+            && self.from_date.is_none_or(|from| memory.created_at >= from)
+            && self.to_date.is_none_or(|to| memory.created_at <= to)
+            && self
+                .content_contains
+                .as_ref()
+                .is_none_or(|text| memory.content.contains(text.as_str()))
+            && self
+                .query
+                .as_ref()
+                .is_none_or(|query| query.matches(memory))
+    }
+}
+
+/// Field memory listings are sorted by; ties are broken by memory ID
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemorySortField {
+    #[default]
+    CreatedAt,
+    LastAccessed,
+    TokenCount,
+}
+
+impl MemorySortField {
+    /// Parse `created_at`, `last_accessed` or `token_count`
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "created_at" => Some(Self::CreatedAt),
+            "last_accessed" => Some(Self::LastAccessed),
+            "token_count" => Some(Self::TokenCount),
+            _ => None,
+        }
+    }
+
+    /// Column of the `memories` table holding the field
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::CreatedAt => "created_at",
+            Self::LastAccessed => "last_accessed",
+            Self::TokenCount => "token_count",
+        }
+    }
+
+    /// Compare two memories by the field, then by ID
+    pub fn compare(&self, a: &Memory, b: &Memory) -> Ordering {
+        let by_field = match self {
+            Self::CreatedAt => a.created_at.cmp(&b.created_at),
+            Self::LastAccessed => a.last_accessed.cmp(&b.last_accessed),
+            Self::TokenCount => a.token_count.as_usize().cmp(&b.token_count.as_usize()),
+        };
+        by_field.then_with(|| a.id.as_str().cmp(b.id.as_str()))
+    }
+}
+
+/// Direction of a sorted listing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    /// Parse `asc` or `desc`
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "asc" => Some(Self::Ascending),
+            "desc" => Some(Self::Descending),
+            _ => None,
+        }
+    }
+
+    /// SQL keyword for the direction
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Ascending => "ASC",
+            Self::Descending => "DESC",
+        }
+    }
+
+    /// Apply the direction to an ascending comparison
+    pub fn apply(&self, ordering: Ordering) -> Ordering {
+        match self {
+            Self::Ascending => ordering,
+            Self::Descending => ordering.reverse(),
+        }
+    }
+}
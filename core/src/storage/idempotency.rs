@@ -0,0 +1,137 @@
+//! Idempotency keys for at-least-once store requests
+//!
+//! A client retrying a store with the same idempotency key gets back the memory
+//! created by the first attempt instead of a duplicate.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+use super::memory::MemoryId;
+
+/// Default number of idempotency keys kept in memory
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 10_000;
+
+/// Default lifetime of an idempotency key in hours
+pub const DEFAULT_IDEMPOTENCY_TTL_HOURS: i64 = 24;
+
+/// A remembered idempotency key
+#[derive(Debug, Clone)]
+struct IdempotencyEntry {
+    /// The memory created for the key
+    memory_id: MemoryId,
+    /// When the key was first used
+    created_at: DateTime<Utc>,
+    /// Position in the recency order
+    tick: u64,
+}
+
+/// Bounded LRU map from idempotency key to memory ID with a time-to-live
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    /// Entries by key
+    entries: HashMap<String, IdempotencyEntry>,
+    /// Keys ordered from least to most recently used
+    recency: BTreeMap<u64, String>,
+    /// Next recency tick
+    next_tick: u64,
+    /// Maximum number of entries
+    capacity: usize,
+    /// How long a key remains valid
+    ttl: Duration,
+}
+
+impl IdempotencyCache {
+    /// Create a cache holding at most `capacity` keys for `ttl`
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            next_tick: 0,
+            capacity,
+            ttl,
+        }
+    }
+
+    /// Get the lifetime of a key
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Check whether a key created at `created_at` is still valid
+    pub fn is_live(&self, created_at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        now - created_at < self.ttl
+    }
+
+    /// Look up the memory ID for a key, dropping it if it has expired
+    pub fn get(&mut self, key: &str, now: DateTime<Utc>) -> Option<MemoryId> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.tick);
+
+        if !self.is_live(entry.created_at, now) {
+            return None;
+        }
+
+        let memory_id = entry.memory_id.clone();
+        self.insert(key.to_string(), entry.memory_id, entry.created_at);
+        Some(memory_id)
+    }
+
+    /// Remember the memory ID for a key, evicting the least recently used key if full
+    pub fn insert(&mut self, key: String, memory_id: MemoryId, created_at: DateTime<Utc>) {
+        if let Some(previous) = self.entries.remove(&key) {
+            self.recency.remove(&previous.tick);
+        }
+
+        while self.entries.len() >= self.capacity {
+            match self.recency.pop_first() {
+                Some((_, oldest)) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            IdempotencyEntry {
+                memory_id,
+                created_at,
+                tick,
+            },
+        );
+    }
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_IDEMPOTENCY_CAPACITY,
+            Duration::hours(DEFAULT_IDEMPOTENCY_TTL_HOURS),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idempotency_cache_evicts_and_expires() {
+        let mut cache = IdempotencyCache::new(2, Duration::hours(1));
+        let now = Utc::now();
+
+        cache.insert("a".to_string(), MemoryId::from("mem_a"), now);
+        cache.insert("b".to_string(), MemoryId::from("mem_b"), now);
+
+        // Using "a" makes "b" the least recently used key
+        assert_eq!(cache.get("a", now), Some(MemoryId::from("mem_a")));
+        cache.insert("c".to_string(), MemoryId::from("mem_c"), now);
+        assert_eq!(cache.get("b", now), None);
+        assert_eq!(cache.get("c", now), Some(MemoryId::from("mem_c")));
+
+        assert_eq!(cache.get("a", now + Duration::hours(2)), None);
+    }
+}
@@ -0,0 +1,60 @@
+//! Typed links between memories
+//!
+//! A link points from one memory to another with a relation such as
+//! `references`, e.g. from a progress entry to the decision it implements.
+//! Links of one relation type never form a cycle.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use std::fmt;
+
+use super::db::MemoryRepository;
+use super::memory::MemoryId;
+
+/// A link that would make a memory lead back to itself through one relation type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircularLink {
+    pub source: MemoryId,
+    pub target: MemoryId,
+    pub relation: String,
+}
+
+impl fmt::Display for CircularLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "linking {} to {} would create a '{}' cycle",
+            self.source.as_str(),
+            self.target.as_str(),
+            self.relation
+        )
+    }
+}
+
+impl std::error::Error for CircularLink {}
+
+/// Check whether `target` already leads to `source` through `relation` links
+pub fn would_create_cycle(
+    repository: &dyn MemoryRepository,
+    source: &MemoryId,
+    target: &MemoryId,
+    relation: &str,
+) -> Result<bool> {
+    let mut visited = HashSet::new();
+    let mut pending = vec![target.clone()];
+    while let Some(id) = pending.pop() {
+        if &id == source {
+            return Ok(true);
+        }
+        if !visited.insert(id.clone()) {
+            continue;
+        }
+        pending.extend(
+            repository
+                .get_linked(&id, Some(relation))?
+                .into_iter()
+                .map(|memory| memory.id),
+        );
+    }
+    Ok(false)
+}
@@ -1,16 +1,133 @@
 //! Memory storage implementation
 
-use anyhow::{Context, Result};
-use std::collections::HashMap;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use anyhow::{bail, Context, Result};
+use lru::LruCache;
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+use super::audit::{read_audit_log, AuditLog, AuditOperation};
+use super::context::relevance::{RelevanceScorer, ScoredMemory};
 use super::db::{MemoryRepository, SqliteMemoryRepository};
+use super::dedup::{duplicate_count, find_duplicate_groups, DuplicateGroup, DUPLICATE_COUNT_KEY};
+use super::encryption::{ContentCipher, EncryptedRepository};
+use super::filter::{MemoryFilter, MemorySortField, SortOrder};
+use super::idempotency::IdempotencyCache;
+use super::links::{would_create_cycle, CircularLink};
+use super::memory_bank_config::AuditLogConfig;
+use super::processors::PostStoreProcessor;
+use super::regex_safety::RegexSafetyCheck;
+use super::stats::{MemoryStats, SizeDistribution};
+use super::sync::{ConflictResolution, ImportConflict, ImportResult, ImportStats};
 use super::tokenizer::{TokenCount, Tokenizer, TokenizerType};
+use super::watch::{StoreEvent, StoreEventKind, StoreEvents};
+
+/// Sentinel marking the cached token total as not yet loaded
+const TOKEN_TOTAL_UNLOADED: u64 = u64::MAX;
+
+/// Number of writes after which the cached token total is reconciled with the repository
+const TOKEN_RECONCILE_INTERVAL: u64 = 100;
+
+/// How long a snapshot may stay open before it is rolled back as abandoned
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Default number of memories held in the cache
+pub const DEFAULT_MAX_CACHE_ENTRIES: usize = 1000;
+
+/// Longest slug kept in a readable memory ID
+const MAX_SLUG_LEN: usize = 32;
+
+/// Number of memories read from the repository at a time while exporting JSON
+const JSON_EXPORT_PAGE_SIZE: usize = 256;
+
+/// Category of the memories holding the content an update replaced
+pub const HISTORY_CATEGORY: &str = "history";
+
+/// Category of the memories recording which memories a context retrieval returned
+pub const ACCESS_LOG_CATEGORY: &str = "access_log";
+
+/// Categories of bookkeeping memories, left out of listings, searches, exports and token totals
+pub const HIDDEN_CATEGORIES: &[&str] = &[HISTORY_CATEGORY, ACCESS_LOG_CATEGORY];
+
+/// Metadata key of a history memory naming the memory it is a version of
+const HISTORY_PARENT_KEY: &str = "parent_id";
+
+/// Metadata key of a history memory holding its version, counted from 1
+const HISTORY_VERSION_KEY: &str = "version";
+
+/// Memory cache, evicting the least recently used memory when full
+type MemoryCache = LruCache<MemoryId, Memory>;
+
+/// Close a snapshot and every snapshot taken after it, returning its savepoint name
+fn close_snapshots_from(snapshots: &mut Vec<OpenSnapshot>, id: &SnapshotId) -> Result<String> {
+    let index = snapshots
+        .iter()
+        .position(|snapshot| &snapshot.id == id)
+        .ok_or_else(|| anyhow::Error::new(UnknownSnapshot(id.clone())))?;
+    Ok(snapshots.split_off(index).swap_remove(0).savepoint)
+}
+
+/// Version number of a history memory
+fn history_version(memory: &Memory) -> Option<u32> {
+    memory
+        .metadata
+        .get(HISTORY_VERSION_KEY)
+        .and_then(|version| version.parse().ok())
+}
+
+/// Create an empty cache sized by `CACHE_CAPACITY`
+fn new_memory_cache() -> MemoryCache {
+    let capacity = std::env::var("CACHE_CAPACITY")
+        .ok()
+        .and_then(|capacity| capacity.parse::<usize>().ok())
+        .and_then(NonZeroUsize::new)
+        .unwrap_or(NonZeroUsize::new(DEFAULT_MAX_CACHE_ENTRIES).unwrap());
+    LruCache::new(capacity)
+}
+
+/// Content, content type, category, mode and metadata of a memory to store
+pub type StoreItem = (
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    HashMap<String, String>,
+);
+
+/// Optional settings of a memory to store
+#[derive(Debug, Clone, Default)]
+pub struct StoreOptions {
+    /// Seconds after creation when the memory expires, or `None` to keep it indefinitely
+    pub ttl_seconds: Option<u64>,
+    /// Tags to store with the memory
+    pub tags: Vec<String>,
+    /// Slug to use as the memory's ID; when taken, a slug ID with a random suffix is used
+    pub preferred_id: Option<String>,
+}
+
+/// Snapshot of the memory cache's size and effectiveness
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    /// Memories currently cached
+    pub entries: usize,
+    /// Maximum number of cached memories
+    pub capacity: usize,
+    /// Lookups served from the cache
+    pub hits: u64,
+    /// Lookups that fell through to the repository
+    pub misses: u64,
+}
 
 /// Unique identifier for a memory
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct MemoryId(String);
 
 impl MemoryId {
@@ -22,12 +139,56 @@ impl MemoryId {
         ))
     }
 
+    /// Create a readable memory ID from a slug and a random suffix, like `mem_architecture-a1b2`
+    ///
+    /// The slug is lowercased and reduced to ASCII letters, digits and single
+    /// hyphens, at most 32 characters. A slug with nothing left gets a random ID.
+    pub fn new_with_slug(slug: &str) -> Self {
+        let slug = sanitize_slug(slug);
+        if slug.is_empty() {
+            return Self::new();
+        }
+        Self(format!(
+            "mem_{}-{}",
+            slug,
+            &Uuid::new_v4().simple().to_string()[..4]
+        ))
+    }
+
+    /// Check whether `s` is a slug `new_with_slug` would keep unchanged
+    pub fn is_valid_slug(s: &str) -> bool {
+        !s.is_empty()
+            && s.len() <= MAX_SLUG_LEN
+            && !s.starts_with('-')
+            && !s.ends_with('-')
+            && !s.contains("--")
+            && s.bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    }
+
     /// Get the string representation of the memory ID
     pub fn as_str(&self) -> &str {
         &self.0
     }
 }
 
+/// Lowercase a slug, replacing each run of other characters than ASCII letters and digits
+/// with a hyphen and capping its length
+fn sanitize_slug(slug: &str) -> String {
+    let mut sanitized = String::new();
+    for c in slug.chars().flat_map(char::to_lowercase) {
+        if sanitized.len() == MAX_SLUG_LEN {
+            break;
+        }
+        if c.is_ascii_alphanumeric() {
+            sanitized.push(c);
+        } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+    sanitized.trim_end_matches('-').to_string()
+}
+
 impl From<String> for MemoryId {
     fn from(s: String) -> Self {
         Self(s)
@@ -40,8 +201,86 @@ impl From<&str> for MemoryId {
     }
 }
 
+/// Identifier of an open memory store snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SnapshotId(String);
+
+/// An open snapshot and the repository savepoint behind it
+#[derive(Debug)]
+struct OpenSnapshot {
+    id: SnapshotId,
+    savepoint: String,
+    taken_at: Instant,
+}
+
+impl SnapshotId {
+    /// Create a new random snapshot ID
+    pub fn new() -> Self {
+        Self(Uuid::new_v4().simple().to_string())
+    }
+
+    /// Get the string representation of the snapshot ID
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SnapshotId {
+    fn from(s: String) -> Self {
+        Self(s)
+    }
+}
+
+/// A snapshot that was never taken or has already been rolled back or committed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownSnapshot(pub SnapshotId);
+
+impl std::fmt::Display for UnknownSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no open snapshot with ID {}", self.0.as_str())
+    }
+}
+
+impl std::error::Error for UnknownSnapshot {}
+
+/// A new memory was given the ID of a memory that already exists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateMemoryId(pub MemoryId);
+
+impl std::fmt::Display for DuplicateMemoryId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "a memory with ID {} already exists", self.0.as_str())
+    }
+}
+
+impl std::error::Error for DuplicateMemoryId {}
+
+/// A memory version that was never recorded, or a memory that does not exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownVersion(pub MemoryId, pub u32);
+
+impl std::fmt::Display for UnknownVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "memory {} has no version {}", self.0.as_str(), self.1)
+    }
+}
+
+impl std::error::Error for UnknownVersion {}
+
+/// A memory that does not exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownMemory(pub MemoryId);
+
+impl std::fmt::Display for UnknownMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no memory with ID {}", self.0.as_str())
+    }
+}
+
+impl std::error::Error for UnknownMemory {}
+
 /// A memory entry with content and metadata
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Memory {
     /// Unique identifier for the memory
     pub id: MemoryId,
@@ -61,6 +300,18 @@ pub struct Memory {
     pub created_at: chrono::DateTime<chrono::Utc>,
     /// When the memory was last accessed
     pub last_accessed: chrono::DateTime<chrono::Utc>,
+    /// Seconds after creation when the memory expires, or `None` to keep it indefinitely
+    #[serde(default)]
+    pub ttl_seconds: Option<u64>,
+    /// Whether the memory is protected from expiration
+    #[serde(default)]
+    pub pinned: bool,
+    /// Labels of the memory, sorted and without duplicates
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Number of times the memory has been retrieved
+    #[serde(default)]
+    pub access_count: u64,
 }
 
 impl Memory {
@@ -87,6 +338,10 @@ impl Memory {
             token_count,
             created_at: now,
             last_accessed: now,
+            ttl_seconds: None,
+            pinned: false,
+            tags: Vec::new(),
+            access_count: 0,
         }
     }
 
@@ -94,6 +349,33 @@ impl Memory {
     pub fn touch(&mut self) {
         self.last_accessed = chrono::Utc::now();
     }
+
+    /// Check whether the memory's time-to-live has elapsed; pinned memories never expire
+    pub fn is_expired(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        !self.pinned
+            && self.ttl_seconds.is_some_and(|ttl| {
+                // A TTL too long to measure in seconds never elapses
+                i64::try_from(ttl).is_ok_and(|ttl| {
+                    now.signed_duration_since(self.created_at).num_seconds() >= ttl
+                })
+            })
+    }
+
+    /// Check whether the memory is bookkeeping in one of the [`HIDDEN_CATEGORIES`]
+    pub fn is_hidden(&self) -> bool {
+        self.category
+            .as_deref()
+            .is_some_and(|category| HIDDEN_CATEGORIES.contains(&category))
+    }
+
+    /// Get the tokens the memory adds to the store's total; hidden memories add none
+    fn counted_tokens(&self) -> i64 {
+        if self.is_hidden() {
+            0
+        } else {
+            self.token_count.as_usize() as i64
+        }
+    }
 }
 
 /// Storage for memories
@@ -101,36 +383,129 @@ impl Memory {
 pub struct MemoryStore {
     /// The memory repository
     repository: Arc<dyn MemoryRepository>,
+    /// The repository beneath any encryption, holding content as stored
+    storage: Arc<dyn MemoryRepository>,
     /// The tokenizer used for counting tokens
     tokenizer: Tokenizer,
-    /// In-memory cache of memories
-    cache: Arc<Mutex<HashMap<MemoryId, Memory>>>,
+    /// In-memory cache of recently used memories; evicted memories stay in the repository
+    cache: Arc<Mutex<MemoryCache>>,
+    /// Processors applied to each memory after it is stored
+    post_store_processors: Arc<RwLock<Vec<Arc<dyn PostStoreProcessor>>>>,
+    /// Cached total token count, loaded from the repository on first access
+    token_total: Arc<AtomicU64>,
+    /// Writes since the cached token total was last reconciled
+    writes_since_reconcile: Arc<AtomicU64>,
+    /// Cache lookups served from the cache
+    cache_hits: Arc<AtomicU64>,
+    /// Cache lookups that fell through to the repository
+    cache_misses: Arc<AtomicU64>,
+    /// Hits and misses when the hit rate was last taken
+    hit_rate_baseline: Arc<Mutex<(u64, u64)>>,
+    /// Recently used idempotency keys, backed by the repository
+    idempotency_cache: Arc<Mutex<IdempotencyCache>>,
+    /// Open snapshots, oldest first
+    snapshots: Arc<Mutex<Vec<OpenSnapshot>>>,
+    /// Notifies watchers of each write
+    events: Arc<StoreEvents>,
+    /// Audit log that stores, updates and deletes are appended to, when enabled
+    audit_log: Arc<RwLock<Option<Arc<AuditLog>>>>,
+    /// Whether memory content is encrypted at rest
+    encrypted: bool,
 }
 
 impl MemoryStore {
     /// Create a new memory store with in-memory storage
     pub fn new_in_memory(tokenizer: Tokenizer) -> Self {
-        // Create an in-memory repository
         let repository = Arc::new(InMemoryRepository::new(tokenizer.clone()));
+        Self::with_repository(repository, tokenizer)
+    }
+
+    /// Create a new memory store with SQLite storage
+    pub fn new_sqlite(db_path: &Path, tokenizer: Tokenizer) -> Result<Self> {
+        Self::new_sqlite_with_pool_size(
+            db_path,
+            tokenizer,
+            SqliteMemoryRepository::default_pool_size(),
+        )
+    }
 
+    /// Create a new memory store with SQLite storage reached through `pool_size` connections
+    pub fn new_sqlite_with_pool_size(
+        db_path: &Path,
+        tokenizer: Tokenizer,
+        pool_size: u32,
+    ) -> Result<Self> {
+        let repository =
+            SqliteMemoryRepository::with_pool_size(db_path, tokenizer.clone(), pool_size)
+                .context("Failed to create SQLite repository")?;
+        Ok(Self::with_repository(Arc::new(repository), tokenizer))
+    }
+
+    /// Create a memory store with an empty cache in front of `repository`
+    fn with_repository(repository: Arc<dyn MemoryRepository>, tokenizer: Tokenizer) -> Self {
         Self {
+            storage: repository.clone(),
             repository,
             tokenizer,
-            cache: Arc::new(Mutex::new(HashMap::new())),
+            cache: Arc::new(Mutex::new(new_memory_cache())),
+            post_store_processors: Arc::new(RwLock::new(Vec::new())),
+            token_total: Arc::new(AtomicU64::new(TOKEN_TOTAL_UNLOADED)),
+            writes_since_reconcile: Arc::new(AtomicU64::new(0)),
+            cache_hits: Arc::new(AtomicU64::new(0)),
+            cache_misses: Arc::new(AtomicU64::new(0)),
+            hit_rate_baseline: Arc::new(Mutex::new((0, 0))),
+            idempotency_cache: Arc::new(Mutex::new(IdempotencyCache::default())),
+            snapshots: Arc::new(Mutex::new(Vec::new())),
+            events: Arc::new(StoreEvents::from_env()),
+            audit_log: Arc::new(RwLock::new(None)),
+            encrypted: false,
         }
     }
 
-    /// Create a new memory store with SQLite storage
-    pub fn new_sqlite(db_path: &Path, tokenizer: Tokenizer) -> Result<Self> {
-        // Create a SQLite repository
-        let repository = SqliteMemoryRepository::new(db_path, tokenizer.clone())
-            .context("Failed to create SQLite repository")?;
+    /// Get the tokenizer counting the tokens of stored memories
+    pub fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
 
-        Ok(Self {
-            repository: Arc::new(repository),
-            tokenizer,
-            cache: Arc::new(Mutex::new(HashMap::new())),
-        })
+    /// Encrypt memory content with `cipher` before it reaches the repository
+    pub fn with_encryption(mut self, cipher: ContentCipher) -> Self {
+        self.repository = Arc::new(EncryptedRepository::new(self.storage.clone(), cipher));
+        self.encrypted = true;
+        self
+    }
+
+    /// Re-encrypt every stored memory after a key change, returning how many were rewritten
+    ///
+    /// Content stored before encryption was enabled is encrypted with the new key too,
+    /// and memories already under the new key are left alone so an interrupted run can
+    /// be repeated. Reopen the store with the new key once this returns.
+    pub fn reencrypt_all(&self, old_key: &[u8], new_key: &[u8]) -> Result<u64> {
+        let old_cipher = ContentCipher::new(old_key)?;
+        let new_cipher = ContentCipher::new(new_key)?;
+
+        let mut rewritten = 0;
+        for mut memory in self.storage.get_all(0, usize::MAX)? {
+            // Memories already under the new key were rewritten by an earlier, interrupted run
+            if ContentCipher::is_encrypted(&memory.content)
+                && new_cipher.decrypt(&memory.content).is_ok()
+            {
+                continue;
+            }
+
+            let plaintext = old_cipher
+                .decrypt(&memory.content)
+                .with_context(|| format!("Failed to decrypt memory {}", memory.id.as_str()))?;
+            memory.content = new_cipher.encrypt(&plaintext)?;
+            self.storage.update(&memory)?;
+            rewritten += 1;
+        }
+
+        Ok(rewritten)
+    }
+
+    /// Register a processor to run after each memory is stored
+    pub fn add_post_store_processor(&self, processor: Arc<dyn PostStoreProcessor>) {
+        self.post_store_processors.write().unwrap().push(processor);
     }
 
     /// Store a new memory and return its ID
@@ -142,7 +517,43 @@ impl MemoryStore {
         mode: Option<String>,
         metadata: HashMap<String, String>,
     ) -> Result<Memory> {
-        let memory = Memory::new(
+        self.store_with_ttl(content, content_type, category, mode, metadata, None)
+    }
+
+    /// Store a new memory that expires `ttl_seconds` after creation
+    pub fn store_with_ttl(
+        &self,
+        content: String,
+        content_type: String,
+        category: Option<String>,
+        mode: Option<String>,
+        metadata: HashMap<String, String>,
+        ttl_seconds: Option<u64>,
+    ) -> Result<Memory> {
+        self.store_with_options(
+            content,
+            content_type,
+            category,
+            mode,
+            metadata,
+            StoreOptions {
+                ttl_seconds,
+                ..StoreOptions::default()
+            },
+        )
+    }
+
+    /// Store a new memory with a TTL and tags, writing the tags together with the memory
+    pub fn store_with_options(
+        &self,
+        content: String,
+        content_type: String,
+        category: Option<String>,
+        mode: Option<String>,
+        metadata: HashMap<String, String>,
+        options: StoreOptions,
+    ) -> Result<Memory> {
+        let mut memory = Memory::new(
             content,
             content_type,
             category,
@@ -150,112 +561,4503 @@ impl MemoryStore {
             metadata,
             &self.tokenizer,
         );
+        memory.ttl_seconds = options.ttl_seconds;
+        memory.tags = normalize_tags(options.tags);
 
         // Store the memory in the repository
-        self.repository.store(&memory)?;
+        let replaced_tokens = match options.preferred_id {
+            Some(preferred_id) => {
+                self.insert_with_preferred_id(&mut memory, &preferred_id)?;
+                0
+            }
+            None => self.overwrite(&memory)?,
+        };
+        self.run_post_store_processors(&mut memory)?;
+        self.audit(AuditOperation::Store, &memory)?;
+        self.adjust_token_total(memory.counted_tokens() - replaced_tokens)?;
+        self.events.publish(
+            StoreEventKind::Stored,
+            &memory.id,
+            memory.token_count.as_usize() as i64,
+        );
 
         // Update the cache
         let mut cache = self.cache.lock().unwrap();
-        cache.insert(memory.id.clone(), memory.clone());
+        cache.put(memory.id.clone(), memory.clone());
 
         Ok(memory)
     }
 
-    /// Retrieve a memory by ID
-    pub fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>> {
-        // Check the cache first
-        {
-            let mut cache = self.cache.lock().unwrap();
-            if let Some(memory) = cache.get_mut(id) {
-                // Update the last accessed time
-                memory.touch();
+    /// Store `memory` over any memory with its ID, returning the tokens the replaced memory counted
+    fn overwrite(&self, memory: &Memory) -> Result<i64> {
+        let replaced = self.repository.retrieve(&memory.id)?;
+        self.repository.store(memory)?;
+        Ok(replaced.as_ref().map_or(0, Memory::counted_tokens))
+    }
 
-                // Update the repository
-                self.repository.touch(id)?;
+    /// Insert a new memory under `preferred_id`, or a slug ID built from it when that is taken
+    ///
+    /// Fails with [`DuplicateMemoryId`] if the slug ID is taken as well.
+    fn insert_with_preferred_id(&self, memory: &mut Memory, preferred_id: &str) -> Result<()> {
+        if !MemoryId::is_valid_slug(preferred_id) {
+            bail!("Invalid preferred memory ID: {:?}", preferred_id);
+        }
 
-                return Ok(Some(memory.clone()));
+        memory.id = MemoryId::from(preferred_id);
+        match self.repository.insert(memory) {
+            Err(e) if e.is::<DuplicateMemoryId>() => {
+                memory.id = MemoryId::new_with_slug(preferred_id);
+                self.repository.insert(memory)
             }
+            inserted => inserted,
         }
+    }
 
-        // If not in cache, retrieve from the repository
-        match self.repository.retrieve(id)? {
-            Some(memory) => {
-                // Update the cache
-                let mut cache = self.cache.lock().unwrap();
-                cache.insert(memory.id.clone(), memory.clone());
+    /// Store several new memories in one transaction
+    ///
+    /// Either every memory is stored or, if any insert fails, none are.
+    pub fn bulk_store(&self, items: Vec<StoreItem>) -> Result<Vec<Memory>> {
+        self.bulk_store_with_tags(items.into_iter().map(|item| (item, Vec::new())).collect())
+    }
 
-                Ok(Some(memory))
-            }
-            None => Ok(None),
+    /// Store several new memories and their tags in one transaction
+    ///
+    /// Either every memory is stored or, if any insert fails, none are.
+    pub fn bulk_store_with_tags(
+        &self,
+        items: Vec<(StoreItem, Vec<String>)>,
+    ) -> Result<Vec<Memory>> {
+        let mut memories: Vec<Memory> = items
+            .into_iter()
+            .map(
+                |((content, content_type, category, mode, metadata), tags)| {
+                    let mut memory = Memory::new(
+                        content,
+                        content_type,
+                        category,
+                        mode,
+                        metadata,
+                        &self.tokenizer,
+                    );
+                    memory.tags = normalize_tags(tags);
+                    memory
+                },
+            )
+            .collect();
+
+        self.repository.store_batch(&memories)?;
+        for memory in &mut memories {
+            self.run_post_store_processors(memory)?;
+            self.audit(AuditOperation::Store, memory)?;
+        }
+        self.adjust_token_total(memories.iter().map(|memory| memory.counted_tokens()).sum())?;
+
+        for memory in &memories {
+            self.events.publish(
+                StoreEventKind::Stored,
+                &memory.id,
+                memory.token_count.as_usize() as i64,
+            );
+        }
+
+        // Update the cache
+        let mut cache = self.cache.lock().unwrap();
+        for memory in &memories {
+            cache.put(memory.id.clone(), memory.clone());
         }
+
+        Ok(memories)
     }
 
-    /// Get all memory IDs
-    pub fn get_all_ids(&self) -> Result<Vec<MemoryId>> {
-        self.repository.get_all_ids()
+    /// Run `store` unless the idempotency key was already used
+    ///
+    /// A repeated key returns the memory stored by the first request. Keys are
+    /// persisted so retries are recognised across restarts until they expire.
+    pub fn store_idempotent(
+        &self,
+        idempotency_key: &str,
+        store: impl FnOnce() -> Result<Memory>,
+    ) -> Result<Memory> {
+        // Held across the store so concurrent retries cannot both miss
+        let mut keys = self.idempotency_cache.lock().unwrap();
+        let now = chrono::Utc::now();
+
+        let existing_id = match keys.get(idempotency_key, now) {
+            Some(id) => Some(id),
+            None => match self.repository.get_idempotency_key(idempotency_key)? {
+                Some((id, created_at)) if keys.is_live(created_at, now) => {
+                    keys.insert(idempotency_key.to_string(), id.clone(), created_at);
+                    Some(id)
+                }
+                _ => None,
+            },
+        };
+
+        // A key whose memory no longer exists is treated as unused
+        if let Some(id) = existing_id {
+            if let Some(memory) = self.retrieve(&id)? {
+                return Ok(memory);
+            }
+        }
+
+        let memory = store()?;
+        self.repository
+            .store_idempotency_key(idempotency_key, &memory.id, now)?;
+        self.repository.prune_idempotency_keys(now - keys.ttl())?;
+        keys.insert(idempotency_key.to_string(), memory.id.clone(), now);
+
+        Ok(memory)
     }
 
-    /// Get the total number of tokens across all memories
-    pub fn get_total_tokens(&self) -> Result<TokenCount> {
-        self.repository.total_tokens()
+    /// Combine memories into one new memory and delete them, all in one transaction
+    ///
+    /// Contents are joined with `separator` in the order the IDs are given and
+    /// the merged memory's tokens are counted afresh. Metadata maps and tags are
+    /// united, later memories winning on shared metadata keys; the content type,
+    /// category and mode are the first memory's. Fails without changing anything
+    /// if fewer than two distinct IDs are given or, with [`UnknownMemory`], if a
+    /// memory does not exist.
+    pub fn merge_memories(&self, ids: &[MemoryId], separator: &str) -> Result<Memory> {
+        self.merge_memories_into(ids, separator, None)
     }
 
-    /// Check if the connection to the repository is working
-    pub fn check_connection(&self) -> Result<bool> {
-        // For now, just check if we can get all IDs
-        match self.get_all_ids() {
-            Ok(_) => Ok(true),
-            Err(_) => Ok(false),
+    /// Merge memories as [`MemoryStore::merge_memories`] does, into `category` when given
+    pub fn merge_memories_into(
+        &self,
+        ids: &[MemoryId],
+        separator: &str,
+        category: Option<String>,
+    ) -> Result<Memory> {
+        if ids.len() < 2 {
+            bail!("At least two memories are needed for a merge");
+        }
+        let mut seen = HashSet::new();
+        let mut originals = Vec::with_capacity(ids.len());
+        for id in ids {
+            if !seen.insert(id) {
+                bail!("Memory {} is listed more than once", id.as_str());
+            }
+            let memory = self
+                .repository
+                .retrieve(id)?
+                .ok_or_else(|| anyhow::Error::new(UnknownMemory(id.clone())))?;
+            originals.push(memory);
         }
+
+        let content = originals
+            .iter()
+            .map(|memory| memory.content.as_str())
+            .collect::<Vec<_>>()
+            .join(separator);
+        let mut metadata = HashMap::new();
+        let mut tags = Vec::new();
+        for memory in &originals {
+            metadata.extend(memory.metadata.clone());
+            tags.extend(memory.tags.iter().cloned());
+        }
+        let first = &originals[0];
+        let mut merged = Memory::new(
+            content,
+            first.content_type.clone(),
+            category.or_else(|| first.category.clone()),
+            first.mode.clone(),
+            metadata,
+            &self.tokenizer,
+        );
+        merged.tags = normalize_tags(tags);
+
+        // Store the merged memory and delete the originals together or not at all
+        let savepoint = format!("merge_{}", Uuid::new_v4().simple());
+        self.repository.savepoint(&savepoint)?;
+        let written = self.repository.store(&merged).and_then(|_| {
+            originals
+                .iter()
+                .try_for_each(|memory| self.repository.delete(&memory.id))
+        });
+        match written {
+            Ok(()) => self.repository.release_savepoint(&savepoint)?,
+            Err(e) => {
+                self.repository.rollback_to_savepoint(&savepoint)?;
+                return Err(e);
+            }
+        }
+
+        self.run_post_store_processors(&mut merged)?;
+        self.audit(AuditOperation::Store, &merged)?;
+        let merged_tokens = merged.token_count.as_usize() as i64;
+        self.events
+            .publish(StoreEventKind::Stored, &merged.id, merged_tokens);
+        let mut token_delta = merged.counted_tokens();
+        for memory in &originals {
+            self.audit(AuditOperation::Delete, memory)?;
+            let tokens = memory.token_count.as_usize() as i64;
+            self.events
+                .publish(StoreEventKind::Deleted, &memory.id, -tokens);
+            token_delta -= memory.counted_tokens();
+        }
+        self.adjust_token_total(token_delta)?;
+
+        // Replace the originals in the cache with the merged memory
+        let mut cache = self.cache.lock().unwrap();
+        for memory in &originals {
+            cache.pop(&memory.id);
+        }
+        cache.put(merged.id.clone(), merged.clone());
+
+        Ok(merged)
     }
-}
 
-/// In-memory implementation of the memory repository
-#[derive(Debug)]
-struct InMemoryRepository {
-    /// The memories stored by ID
-    memories: Arc<Mutex<HashMap<MemoryId, Memory>>>,
-    /// The tokenizer used for counting tokens
-    tokenizer: Tokenizer,
-}
+    /// Merge a group of near-duplicates into its primary, returning the updated
+    /// primary and the duplicates deleted
+    ///
+    /// Content a duplicate adds is appended to the primary's after `separator`,
+    /// its tags are added to the primary's, and the primary's `duplicate_count`
+    /// metadata counts every memory merged into it so far. Either the primary is
+    /// updated and every duplicate deleted, or nothing changes.
+    pub fn merge_duplicates(
+        &self,
+        group: &DuplicateGroup,
+        separator: &str,
+    ) -> Result<(Memory, Vec<Memory>)> {
+        let retrieve = |id: &MemoryId| {
+            self.repository
+                .retrieve(id)?
+                .ok_or_else(|| anyhow::Error::new(UnknownMemory(id.clone())))
+        };
+        let primary = retrieve(&group.primary_id)?;
+        let duplicates = group
+            .duplicate_ids
+            .iter()
+            .map(retrieve)
+            .collect::<Result<Vec<_>>>()?;
 
-impl InMemoryRepository {
-    /// Create a new in-memory repository
-    fn new(tokenizer: Tokenizer) -> Self {
-        Self {
-            memories: Arc::new(Mutex::new(HashMap::new())),
-            tokenizer,
+        let mut merged = primary.clone();
+        let mut merged_count = duplicate_count(&primary);
+        let mut tags = primary.tags.clone();
+        for duplicate in &duplicates {
+            if !merged.content.contains(&duplicate.content) {
+                merged.content.push_str(separator);
+                merged.content.push_str(&duplicate.content);
+            }
+            merged_count += 1 + duplicate_count(duplicate);
+            tags.extend(duplicate.tags.iter().cloned());
         }
+        merged.token_count = self.tokenizer.count_tokens(&merged.content);
+        merged
+            .metadata
+            .insert(DUPLICATE_COUNT_KEY.to_string(), merged_count.to_string());
+        merged.tags = normalize_tags(tags);
+        merged.touch();
+
+        // Update the primary and delete the duplicates together or not at all
+        let savepoint = format!("dedup_{}", Uuid::new_v4().simple());
+        self.repository.savepoint(&savepoint)?;
+        let written = self
+            .repository
+            .update(&merged)
+            .and_then(|_| {
+                merged
+                    .tags
+                    .iter()
+                    .filter(|tag| !primary.tags.contains(tag))
+                    .try_for_each(|tag| self.repository.add_tag(&merged.id, tag))
+            })
+            .and_then(|_| {
+                duplicates
+                    .iter()
+                    .try_for_each(|memory| self.repository.delete(&memory.id))
+            });
+        match written {
+            Ok(()) => self.repository.release_savepoint(&savepoint)?,
+            Err(e) => {
+                self.repository.rollback_to_savepoint(&savepoint)?;
+                return Err(e);
+            }
+        }
+
+        self.run_post_store_processors(&mut merged)?;
+        self.audit(AuditOperation::Update, &merged)?;
+        let token_delta =
+            merged.token_count.as_usize() as i64 - primary.token_count.as_usize() as i64;
+        self.events
+            .publish(StoreEventKind::Updated, &merged.id, token_delta);
+        let mut counted_delta = merged.counted_tokens() - primary.counted_tokens();
+        for memory in &duplicates {
+            self.audit(AuditOperation::Delete, memory)?;
+            let tokens = memory.token_count.as_usize() as i64;
+            self.events
+                .publish(StoreEventKind::Deleted, &memory.id, -tokens);
+            counted_delta -= memory.counted_tokens();
+        }
+        self.adjust_token_total(counted_delta)?;
+
+        // Replace the duplicates in the cache with the merged primary
+        let mut cache = self.cache.lock().unwrap();
+        for memory in &duplicates {
+            cache.pop(&memory.id);
+        }
+        cache.put(merged.id.clone(), merged.clone());
+
+        Ok((merged, duplicates))
     }
-}
 
-impl MemoryRepository for InMemoryRepository {
-    fn store(&self, memory: &Memory) -> Result<()> {
-        let mut memories = self.memories.lock().unwrap();
-        memories.insert(memory.id.clone(), memory.clone());
-        Ok(())
+    /// Find groups of near-duplicate memories without changing anything
+    ///
+    /// Memories are compared by the Jaccard similarity of their whitespace-separated
+    /// terms; a memory joins a group when its similarity to the group's primary,
+    /// the group's longest memory, is at least `similarity_threshold`. History
+    /// versions are left out. Pass each group to [`MemoryStore::merge_duplicates`]
+    /// to combine it.
+    pub fn find_duplicates(&self, similarity_threshold: f64) -> Result<Vec<DuplicateGroup>> {
+        self.find_duplicates_where(similarity_threshold, |_| true)
     }
 
-    fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>> {
-        let memories = self.memories.lock().unwrap();
-        Ok(memories.get(id).cloned())
+    /// Find duplicates as [`MemoryStore::find_duplicates`] does, among the memories `include` accepts
+    pub fn find_duplicates_where(
+        &self,
+        similarity_threshold: f64,
+        include: impl Fn(&Memory) -> bool,
+    ) -> Result<Vec<DuplicateGroup>> {
+        let mut memories = self.repository.get_all(0, usize::MAX)?;
+        memories.retain(|memory| {
+            memory.category.as_deref() != Some(HISTORY_CATEGORY) && include(memory)
+        });
+
+        Ok(find_duplicate_groups(&memories, similarity_threshold))
     }
 
-    fn touch(&self, id: &MemoryId) -> Result<()> {
-        let mut memories = self.memories.lock().unwrap();
-        if let Some(memory) = memories.get_mut(id) {
-            memory.touch();
+    /// Copy a memory under a fresh ID, returning `None` if the source does not exist
+    ///
+    /// When `copy_metadata` is false only the `copy_source_id` entry is kept.
+    pub fn copy_memory(
+        &self,
+        id: &MemoryId,
+        target_category: Option<String>,
+        target_mode: Option<String>,
+        copy_metadata: bool,
+    ) -> Result<Option<Memory>> {
+        if self.retrieve(id)?.is_none() {
+            return Ok(None);
         }
-        Ok(())
+
+        let mut copy = self
+            .repository
+            .copy_memory(id, target_category, target_mode)?;
+
+        if !copy_metadata {
+            copy.metadata.retain(|key, _| key == "copy_source_id");
+            self.repository.store(&copy)?;
+        }
+        self.run_post_store_processors(&mut copy)?;
+        self.audit(AuditOperation::Store, &copy)?;
+        self.adjust_token_total(copy.token_count.as_usize() as i64)?;
+        self.events.publish(
+            StoreEventKind::Stored,
+            &copy.id,
+            copy.token_count.as_usize() as i64,
+        );
+
+        // Update the cache
+        let mut cache = self.cache.lock().unwrap();
+        cache.put(copy.id.clone(), copy.clone());
+
+        Ok(Some(copy))
     }
 
-    fn get_all_ids(&self) -> Result<Vec<MemoryId>> {
-        let memories = self.memories.lock().unwrap();
-        Ok(memories.keys().cloned().collect())
+    /// Run the post-store pipeline and persist any changes it made
+    fn run_post_store_processors(&self, memory: &mut Memory) -> Result<()> {
+        let mut modified = false;
+        for processor in self.post_store_processors.read().unwrap().iter() {
+            modified |= processor.process(memory);
+        }
+        if modified {
+            self.repository.store(memory)?;
+        }
+        Ok(())
     }
 
-    fn total_tokens(&self) -> Result<TokenCount> {
-        let memories = self.memories.lock().unwrap();
-        Ok(memories.values().map(|m| m.token_count).sum())
+    /// Retrieve a memory by ID
+    pub fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>> {
+        let now = chrono::Utc::now();
+
+        // Check the cache first
+        if let Some(memory) = self.retrieve_cached(id, now) {
+            self.repository.touch(id)?;
+            return Ok(Some(memory));
+        }
+
+        // If not in cache, retrieve from the repository
+        match self.repository.retrieve(id)? {
+            Some(memory) if memory.is_expired(now) => {
+                self.remove(id, StoreEventKind::Expired)?;
+                Ok(None)
+            }
+            Some(memory) => {
+                self.repository.touch(id)?;
+                Ok(Some(self.cache_retrieved(memory)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Receive an event for every write to the store from now on
+    ///
+    /// Pass the receiver to [`MemoryStore::next_event`] so a watcher that falls
+    /// too far behind is counted when it is dropped.
+    pub fn watch(&self) -> tokio::sync::broadcast::Receiver<StoreEvent> {
+        self.events.watch()
+    }
+
+    /// Wait for a watcher's next event, or `None` once it must be dropped
+    ///
+    /// A watcher more than `EVENT_CHANNEL_CAPACITY` events behind has missed
+    /// events and is dropped; watch again to resume from the current state.
+    pub async fn next_event(
+        &self,
+        receiver: &mut tokio::sync::broadcast::Receiver<StoreEvent>,
+    ) -> Option<StoreEvent> {
+        self.events.next(receiver).await
+    }
+
+    /// Number of watchers dropped for falling too far behind
+    pub fn lagged_watchers(&self) -> u64 {
+        self.events.lagged()
+    }
+
+    /// Append every later store, update and delete to the audit log described by `config`
+    ///
+    /// Does nothing unless `config.enabled` is set. Fails if `config.include_content`
+    /// is set on an encrypted store.
+    pub fn enable_audit_log(&self, config: &AuditLogConfig) -> Result<()> {
+        // The log would hold in plaintext what the database holds encrypted
+        if config.enabled && config.include_content && self.encrypted {
+            bail!("The audit log cannot include memory content while ENCRYPTION_KEY is set");
+        }
+        if config.enabled {
+            let log = AuditLog::open(&config.path, config.include_content)?;
+            *self.audit_log.write().unwrap() = Some(Arc::new(log));
+        }
+        Ok(())
+    }
+
+    /// Record `operation` on `memory` in the audit log, if one is enabled
+    fn audit(&self, operation: AuditOperation, memory: &Memory) -> Result<()> {
+        let log = self.audit_log.read().unwrap().clone();
+        match log {
+            Some(log) => log.record(operation, memory),
+            None => Ok(()),
+        }
+    }
+
+    /// Rebuild the memories recorded in the audit log at `path`, returning how many were imported
+    ///
+    /// Only lines written with `include_content` carry enough to restore a memory;
+    /// hashed lines are skipped. Stores and updates are applied in order, deleted
+    /// memories are left out, and the rest are imported under their original IDs,
+    /// replacing any local memory with the same ID. The replay itself is not audited.
+    pub fn replay_audit_log(&self, path: &Path) -> Result<u64> {
+        let mut memories: HashMap<String, Memory> = HashMap::new();
+        let mut order = Vec::new();
+
+        for record in read_audit_log(path)? {
+            match record.operation {
+                AuditOperation::Delete => {
+                    memories.remove(&record.memory_id);
+                }
+                AuditOperation::Store | AuditOperation::Update => {
+                    let Some(content) = record.content else {
+                        continue;
+                    };
+                    let content_type = record
+                        .content_type
+                        .unwrap_or_else(|| "text/plain".to_string());
+                    let metadata = record.metadata.unwrap_or_default();
+                    match memories.get_mut(&record.memory_id) {
+                        Some(memory) => {
+                            memory.token_count = self.tokenizer.count_tokens(&content);
+                            memory.content = content;
+                            memory.content_type = content_type;
+                            memory.category = record.category;
+                            memory.mode = record.mode;
+                            memory.metadata = metadata;
+                            memory.last_accessed = record.timestamp;
+                        }
+                        None => {
+                            let mut memory = Memory::new(
+                                content,
+                                content_type,
+                                record.category,
+                                record.mode,
+                                metadata,
+                                &self.tokenizer,
+                            );
+                            memory.id = MemoryId::from(record.memory_id.as_str());
+                            memory.created_at = record.timestamp;
+                            memory.last_accessed = record.timestamp;
+                            order.push(record.memory_id.clone());
+                            memories.insert(record.memory_id, memory);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Keep the order the memories were first stored in
+        let memories = order.iter().filter_map(|id| memories.remove(id)).collect();
+        let stats = self.import(memories, ConflictResolution::PreferIncoming, false)?;
+        Ok(stats.imported as u64)
+    }
+
+    /// Take a live memory from the cache and record the access, counting the lookup
+    ///
+    /// Expired memories are evicted and left for the caller to delete from the repository.
+    fn retrieve_cached(&self, id: &MemoryId, now: chrono::DateTime<chrono::Utc>) -> Option<Memory> {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.peek(id).is_some_and(|memory| memory.is_expired(now)) {
+            cache.pop(id);
+        }
+
+        match cache.get_mut(id) {
+            Some(memory) => {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                memory.touch();
+                memory.access_count += 1;
+                Some(memory.clone())
+            }
+            None => {
+                self.cache_misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Record an access to a memory loaded from the repository and cache it
+    fn cache_retrieved(&self, mut memory: Memory) -> Memory {
+        memory.touch();
+        memory.access_count += 1;
+        self.cache
+            .lock()
+            .unwrap()
+            .put(memory.id.clone(), memory.clone());
+        memory
+    }
+
+    /// Replace a memory's content and optionally its metadata, keeping its ID and `created_at`
+    ///
+    /// The replaced content is kept as the memory's next version in a
+    /// [`HISTORY_CATEGORY`] memory. Returns the updated memory, or `None` if it
+    /// does not exist.
+    pub fn update(
+        &self,
+        id: &MemoryId,
+        new_content: String,
+        new_metadata: Option<HashMap<String, String>>,
+    ) -> Result<Option<Memory>> {
+        let existing = match self.repository.retrieve(id)? {
+            Some(memory) => memory,
+            None => return Ok(None),
+        };
+
+        self.store_version(&existing)?;
+
+        let mut memory = existing.clone();
+        memory.token_count = self.tokenizer.count_tokens(&new_content);
+        memory.content = new_content;
+        if let Some(metadata) = new_metadata {
+            memory.metadata = metadata;
+        }
+        memory.touch();
+
+        self.repository.update(&memory)?;
+        self.run_post_store_processors(&mut memory)?;
+        self.audit(AuditOperation::Update, &memory)?;
+        let token_delta =
+            memory.token_count.as_usize() as i64 - existing.token_count.as_usize() as i64;
+        self.adjust_token_total(memory.counted_tokens() - existing.counted_tokens())?;
+        self.events
+            .publish(StoreEventKind::Updated, &memory.id, token_delta);
+
+        // Replace the cache entry
+        let mut cache = self.cache.lock().unwrap();
+        cache.put(memory.id.clone(), memory.clone());
+
+        Ok(Some(memory))
+    }
+
+    /// Keep the content of `memory` as its next version before it is replaced
+    fn store_version(&self, memory: &Memory) -> Result<Memory> {
+        let version = self.get_history(&memory.id)?.len() + 1;
+        let metadata = HashMap::from([
+            (
+                HISTORY_PARENT_KEY.to_string(),
+                memory.id.as_str().to_string(),
+            ),
+            (HISTORY_VERSION_KEY.to_string(), version.to_string()),
+        ]);
+        self.store(
+            memory.content.clone(),
+            memory.content_type.clone(),
+            Some(HISTORY_CATEGORY.to_string()),
+            memory.mode.clone(),
+            metadata,
+        )
+    }
+
+    /// Get the earlier versions of a memory, oldest first
+    ///
+    /// Version 1 is the content the memory was stored with; each update adds the
+    /// content it replaced as the next version.
+    pub fn get_history(&self, id: &MemoryId) -> Result<Vec<Memory>> {
+        let mut history = self
+            .repository
+            .get_by_metadata(HISTORY_PARENT_KEY, id.as_str())?;
+        history.retain(|memory| memory.category.as_deref() == Some(HISTORY_CATEGORY));
+        // Versions stored within the same instant are told apart by number
+        history.sort_by_key(|memory| (memory.created_at, history_version(memory)));
+        Ok(history)
+    }
+
+    /// Restore the content a memory had at `version`, returning the updated memory
+    ///
+    /// The content replaced by the rollback is kept as a new version, so a
+    /// rollback can itself be undone. Fails with [`UnknownVersion`] if the memory
+    /// or the version does not exist.
+    pub fn rollback_to_version(&self, id: &MemoryId, version: u32) -> Result<Memory> {
+        let unknown = || anyhow::Error::new(UnknownVersion(id.clone(), version));
+        let entry = self
+            .get_history(id)?
+            .into_iter()
+            .find(|memory| history_version(memory) == Some(version))
+            .ok_or_else(unknown)?;
+
+        self.update(id, entry.content, None)?.ok_or_else(unknown)
+    }
+
+    /// Delete a memory, returning the deleted memory or `None` if it does not exist
+    pub fn delete(&self, id: &MemoryId) -> Result<Option<Memory>> {
+        self.remove(id, StoreEventKind::Deleted)
+    }
+
+    /// Delete a memory and its history versions, telling watchers it was deleted as `kind`
+    fn remove(&self, id: &MemoryId, kind: StoreEventKind) -> Result<Option<Memory>> {
+        let memory = match self.repository.retrieve(id)? {
+            Some(memory) => memory,
+            None => return Ok(None),
+        };
+        let history = if memory.category.as_deref() == Some(HISTORY_CATEGORY) {
+            Vec::new()
+        } else {
+            self.get_history(id)?
+        };
+
+        if history.is_empty() {
+            self.repository.delete(id)?;
+        } else {
+            // Delete the versions and the memory together or not at all
+            let savepoint = format!("delete_{}", Uuid::new_v4().simple());
+            self.repository.savepoint(&savepoint)?;
+            let deleted = history
+                .iter()
+                .try_for_each(|version| self.repository.delete(&version.id))
+                .and_then(|_| self.repository.delete(id));
+            match deleted {
+                Ok(()) => self.repository.release_savepoint(&savepoint)?,
+                Err(e) => {
+                    self.repository.rollback_to_savepoint(&savepoint)?;
+                    return Err(e);
+                }
+            }
+        }
+
+        for version in &history {
+            self.audit(AuditOperation::Delete, version)?;
+            let tokens = version.token_count.as_usize() as i64;
+            self.events
+                .publish(StoreEventKind::Deleted, &version.id, -tokens);
+        }
+        self.audit(AuditOperation::Delete, &memory)?;
+        let tokens = memory.token_count.as_usize() as i64;
+        self.events.publish(kind, id, -tokens);
+        self.adjust_token_total(-memory.counted_tokens())?;
+
+        // Evict the cache entries
+        let mut cache = self.cache.lock().unwrap();
+        for version in &history {
+            cache.pop(&version.id);
+        }
+        cache.pop(id);
+
+        Ok(Some(memory))
+    }
+
+    /// Find the `limit` memories most relevant to a memory, using its content as the query
+    ///
+    /// Memories are scored for the target memory's mode, most relevant first, and
+    /// the target memory itself is never included.
+    pub fn get_related(
+        &self,
+        id: &MemoryId,
+        scorer: &dyn RelevanceScorer,
+        limit: usize,
+    ) -> Result<Vec<ScoredMemory>> {
+        let target = self
+            .retrieve(id)?
+            .ok_or_else(|| anyhow::anyhow!("Memory with ID {} not found", id.as_str()))?;
+
+        let mut others = self.get_memories_page(0, usize::MAX)?;
+        others.retain(|memory| memory.id != target.id);
+
+        let mut related = scorer.score_memories(
+            &others,
+            target.mode.as_deref().unwrap_or_default(),
+            Some(&target.content),
+        )?;
+        related.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        related.truncate(limit);
+
+        Ok(related)
+    }
+
+    /// Tag a memory, returning it or `None` if it does not exist
+    pub fn add_tag(&self, id: &MemoryId, tag: &str) -> Result<Option<Memory>> {
+        self.change_tags(id, tag, true)
+    }
+
+    /// Remove a tag from a memory, returning it or `None` if it does not exist
+    pub fn remove_tag(&self, id: &MemoryId, tag: &str) -> Result<Option<Memory>> {
+        self.change_tags(id, tag, false)
+    }
+
+    fn change_tags(&self, id: &MemoryId, tag: &str, add: bool) -> Result<Option<Memory>> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(anyhow::anyhow!("Tags must not be empty"));
+        }
+        if self.repository.retrieve(id)?.is_none() {
+            return Ok(None);
+        }
+
+        if add {
+            self.repository.add_tag(id, tag)?;
+        } else {
+            self.repository.remove_tag(id, tag)?;
+        }
+
+        // Reload the memory so the cached copy carries the new tags
+        let memory = self.repository.retrieve(id)?;
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(memory) = &memory {
+            if let Some(cached) = cache.peek_mut(id) {
+                cached.tags = memory.tags.clone();
+            }
+        }
+
+        Ok(memory)
+    }
+
+    /// Get the tags of a memory, sorted
+    pub fn get_tags_for_memory(&self, id: &MemoryId) -> Result<Vec<String>> {
+        self.repository.get_tags_for_memory(id)
+    }
+
+    /// Link `source` to `target` through `relation`, returning false if either does not exist
+    ///
+    /// Fails with [`CircularLink`] if `target` already leads back to `source`
+    /// through `relation` links, including when both are the same memory.
+    pub fn link(&self, source: &MemoryId, target: &MemoryId, relation: &str) -> Result<bool> {
+        let relation = relation.trim();
+        if relation.is_empty() {
+            return Err(anyhow::anyhow!("Relation types must not be empty"));
+        }
+        if self.repository.retrieve(source)?.is_none()
+            || self.repository.retrieve(target)?.is_none()
+        {
+            return Ok(false);
+        }
+
+        if would_create_cycle(self.repository.as_ref(), source, target, relation)? {
+            return Err(CircularLink {
+                source: source.clone(),
+                target: target.clone(),
+                relation: relation.to_string(),
+            }
+            .into());
+        }
+
+        self.repository.link(source, target, relation)?;
+        Ok(true)
+    }
+
+    /// Get the unexpired memories `id` links to, optionally only through `relation`, oldest first
+    pub fn get_linked(&self, id: &MemoryId, relation: Option<&str>) -> Result<Vec<Memory>> {
+        let now = chrono::Utc::now();
+        let mut memories = self.repository.get_linked(id, relation)?;
+        memories.retain(|memory| !memory.is_expired(now));
+        Ok(memories)
+    }
+
+    /// Get one sorted page of the memories matching `filter`, with the total number matching
+    ///
+    /// Expired memories that have not been purged yet still count towards the total.
+    pub fn list(
+        &self,
+        filter: &MemoryFilter,
+        sort: MemorySortField,
+        order: SortOrder,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<Memory>, u64)> {
+        let now = chrono::Utc::now();
+        let (mut memories, total) = self.repository.list(filter, sort, order, page, page_size)?;
+        memories.retain(|memory| !memory.is_expired(now));
+        Ok((memories, total))
+    }
+
+    /// Get one page of the unexpired memories tagged `tag`, oldest first
+    pub fn get_by_tag(&self, tag: &str, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        let now = chrono::Utc::now();
+        let mut memories = self.repository.get_by_tag(tag, page, page_size)?;
+        memories.retain(|memory| !memory.is_expired(now));
+        Ok(memories)
+    }
+
+    /// Get the unexpired memories tagged with all of `tags`, or with any of them, oldest first
+    pub fn filter_by_tags(&self, tags: &[String], match_all: bool) -> Result<Vec<Memory>> {
+        let tags = normalize_tags(tags.to_vec());
+        let Some((first, rest)) = tags.split_first() else {
+            return Ok(Vec::new());
+        };
+
+        let mut memories = self.get_by_tag(first, 0, usize::MAX)?;
+        if match_all {
+            memories.retain(|memory| rest.iter().all(|tag| memory.tags.contains(tag)));
+        } else {
+            let mut seen: HashSet<MemoryId> = memories.iter().map(|m| m.id.clone()).collect();
+            for tag in rest {
+                for memory in self.get_by_tag(tag, 0, usize::MAX)? {
+                    if seen.insert(memory.id.clone()) {
+                        memories.push(memory);
+                    }
+                }
+            }
+            memories
+                .sort_by(|a, b| (a.created_at, a.id.as_str()).cmp(&(b.created_at, b.id.as_str())));
+        }
+
+        Ok(memories)
+    }
+
+    /// Protect a memory from expiration, returning it or `None` if it does not exist
+    pub fn pin(&self, id: &MemoryId) -> Result<Option<Memory>> {
+        self.set_pinned(id, true)
+    }
+
+    /// Let a pinned memory expire again, returning it or `None` if it does not exist
+    pub fn unpin(&self, id: &MemoryId) -> Result<Option<Memory>> {
+        self.set_pinned(id, false)
+    }
+
+    fn set_pinned(&self, id: &MemoryId, pinned: bool) -> Result<Option<Memory>> {
+        let mut memory = match self.repository.retrieve(id)? {
+            Some(memory) => memory,
+            None => return Ok(None),
+        };
+
+        if pinned {
+            self.repository.pin(id)?;
+        } else {
+            self.repository.unpin(id)?;
+        }
+        memory.pinned = pinned;
+
+        // Keep a cached copy in step so it is not purged from the cache
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(cached) = cache.peek_mut(id) {
+            cached.pinned = pinned;
+        }
+
+        Ok(Some(memory))
+    }
+
+    /// Delete all expired memories that are not pinned, returning the number deleted
+    pub fn purge_expired(&self) -> Result<u64> {
+        let purged = self.repository.purge_expired()?;
+        if purged.is_empty() {
+            return Ok(0);
+        }
+
+        let now = chrono::Utc::now();
+        let mut cache = self.cache.lock().unwrap();
+        let expired: Vec<_> = cache
+            .iter()
+            .filter(|(_, memory)| memory.is_expired(now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in expired {
+            cache.pop(&id);
+        }
+
+        drop(cache);
+
+        for (id, token_count) in &purged {
+            let token_delta = -(token_count.as_usize() as i64);
+            self.events
+                .publish(StoreEventKind::Expired, id, token_delta);
+        }
+        // Hidden memories expire too, so reload the total rather than subtract their tokens
+        self.token_total
+            .store(TOKEN_TOTAL_UNLOADED, Ordering::Release);
+
+        Ok(purged.len() as u64)
+    }
+
+    /// Get the most often retrieved memories, optionally only those of one mode
+    pub fn get_hot_memories(&self, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>> {
+        let now = chrono::Utc::now();
+        let mut memories = self.repository.get_hot_memories(mode, limit)?;
+        memories.retain(|memory| !memory.is_expired(now));
+        Ok(memories)
+    }
+
+    /// Load up to `limit` of the most often retrieved memories into the cache, returning them
+    pub fn preload_hot_memories(&self, limit: usize) -> Result<Vec<Memory>> {
+        let memories = self.get_hot_memories(None, limit)?;
+
+        // Least accessed first, so the most accessed memory is evicted last
+        let mut cache = self.cache.lock().unwrap();
+        for memory in memories.iter().rev() {
+            cache.put(memory.id.clone(), memory.clone());
+        }
+
+        Ok(memories)
+    }
+
+    /// Path of the database file, or `None` for in-memory storage
+    pub fn database_path(&self) -> Option<PathBuf> {
+        self.repository.database_path()
+    }
+
+    /// Replace every memory with the contents of the SQLite database at `source`
+    ///
+    /// Only works on SQLite storage; the cache and token total are reloaded afterwards.
+    pub fn restore_from(&self, source: &Path) -> Result<()> {
+        self.repository.restore_from(source)?;
+        self.forget_cached_writes();
+        Ok(())
+    }
+
+    /// Drop cached state that may describe writes the repository no longer has
+    fn forget_cached_writes(&self) {
+        self.cache.lock().unwrap().clear();
+        *self.idempotency_cache.lock().unwrap() = IdempotencyCache::default();
+        self.token_total
+            .store(TOKEN_TOTAL_UNLOADED, Ordering::Release);
+    }
+
+    /// Replace the cache with the most recently accessed memories from the repository
+    ///
+    /// Returns the number of memories loaded into the cache.
+    pub fn rebuild_cache_from_db(&self) -> Result<usize> {
+        let mut cache = self.cache.lock().unwrap();
+        cache.clear();
+
+        let memories = self.repository.get_recently_accessed(cache.cap().get())?;
+        let loaded = memories.len();
+
+        // Least recent first, so the most recently accessed memory is evicted last
+        for memory in memories.into_iter().rev() {
+            cache.put(memory.id.clone(), memory);
+        }
+
+        Ok(loaded)
+    }
+
+    /// Get the cache hit rate since the last call, or `None` if there were no lookups
+    pub fn take_cache_hit_rate(&self) -> Option<f64> {
+        let hits = self.cache_hits.load(Ordering::Relaxed);
+        let misses = self.cache_misses.load(Ordering::Relaxed);
+
+        let mut baseline = self.hit_rate_baseline.lock().unwrap();
+        let (previous_hits, previous_misses) = std::mem::replace(&mut *baseline, (hits, misses));
+        let window_hits = hits - previous_hits;
+        let lookups = window_hits + misses - previous_misses;
+
+        if lookups == 0 {
+            None
+        } else {
+            Some(window_hits as f64 / lookups as f64)
+        }
+    }
+
+    /// Get the fraction of all cache lookups served from the cache, or 0 if there were none
+    pub fn cache_hit_rate(&self) -> f64 {
+        let stats = self.cache_stats();
+        let lookups = stats.hits + stats.misses;
+
+        if lookups == 0 {
+            0.0
+        } else {
+            stats.hits as f64 / lookups as f64
+        }
+    }
+
+    /// Get the cache's size and lifetime hit and miss counts
+    pub fn cache_stats(&self) -> CacheStats {
+        let cache = self.cache.lock().unwrap();
+        CacheStats {
+            entries: cache.len(),
+            capacity: cache.cap().get(),
+            hits: self.cache_hits.load(Ordering::Relaxed),
+            misses: self.cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get all memory IDs
+    #[deprecated(
+        note = "load memories a page at a time with `get_ids_page` or `get_memories_page`"
+    )]
+    pub fn get_all_ids(&self) -> Result<Vec<MemoryId>> {
+        self.get_ids_page(0, usize::MAX)
+    }
+
+    /// Get one page of memory IDs, oldest memories first
+    pub fn get_ids_page(&self, page: usize, page_size: usize) -> Result<Vec<MemoryId>> {
+        self.repository.get_all_ids(page, page_size)
+    }
+
+    /// Get one page of unexpired memories in the same order as `get_ids_page`
+    ///
+    /// Memories are read straight from the repository without updating their
+    /// last accessed time or the cache.
+    pub fn get_memories_page(&self, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        let now = chrono::Utc::now();
+        let mut memories = self.repository.get_all(page, page_size)?;
+        memories.retain(|memory| !memory.is_expired(now));
+        Ok(memories)
+    }
+
+    /// Get one page of the unexpired memories in `category`, oldest first
+    pub fn get_by_category(
+        &self,
+        category: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<Memory>> {
+        let now = chrono::Utc::now();
+        let mut memories = self.repository.get_by_category(category, page, page_size)?;
+        memories.retain(|memory| !memory.is_expired(now));
+        Ok(memories)
+    }
+
+    /// Get one page of the unexpired memories in `mode`, oldest first
+    pub fn get_by_mode(&self, mode: &str, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        let now = chrono::Utc::now();
+        let mut memories = self.repository.get_by_mode(mode, page, page_size)?;
+        memories.retain(|memory| !memory.is_expired(now));
+        Ok(memories)
+    }
+
+    /// Count all memories, including expired ones that have not been purged yet
+    pub fn count(&self) -> Result<u64> {
+        self.repository.count()
+    }
+
+    /// Get the number of pages of `page_size` memories
+    pub fn get_page_count(&self, page_size: usize) -> Result<usize> {
+        self.repository.get_page_count(page_size)
+    }
+
+    /// Get the total number of tokens across all memories
+    ///
+    /// The total is read from the repository once and then maintained in memory.
+    pub fn get_total_tokens(&self) -> Result<TokenCount> {
+        let current = self.token_total.load(Ordering::Acquire);
+        if current != TOKEN_TOTAL_UNLOADED {
+            return Ok(TokenCount::new(current as usize));
+        }
+
+        // Only the first loader wins; a concurrent writer may already have set it
+        let total = self.repository.total_tokens()?.as_usize() as u64;
+        match self.token_total.compare_exchange(
+            TOKEN_TOTAL_UNLOADED,
+            total,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => Ok(TokenCount::new(total as usize)),
+            Err(existing) => Ok(TokenCount::new(existing as usize)),
+        }
+    }
+
+    /// Apply a change to the cached token total, reconciling it periodically
+    fn adjust_token_total(&self, delta: i64) -> Result<()> {
+        // An unloaded total stays unloaded; the first read picks up this write
+        let _ = self
+            .token_total
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                if current == TOKEN_TOTAL_UNLOADED {
+                    None
+                } else {
+                    Some(current.saturating_add_signed(delta))
+                }
+            });
+
+        let writes = self.writes_since_reconcile.fetch_add(1, Ordering::AcqRel) + 1;
+        if writes >= TOKEN_RECONCILE_INTERVAL {
+            self.writes_since_reconcile.store(0, Ordering::Release);
+            let total = self.repository.total_tokens()?.as_usize() as u64;
+            self.token_total.store(total, Ordering::Release);
+        }
+
+        Ok(())
+    }
+
+    /// Search memory content with a regex pattern that has passed the safety check
+    pub fn search_content_regex_safe(&self, pattern: &str, limit: usize) -> Result<Vec<Memory>> {
+        self.repository.search_content_regex_safe(pattern, limit)
+    }
+
+    /// Full-text search over memory content, optionally restricted to a mode
+    pub fn search(&self, query: &str, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>> {
+        self.repository.search(query, mode, limit)
+    }
+
+    /// Count every memory a search finds, however many its limit would return
+    pub fn count_search_matches(&self, query: &str, mode: Option<&str>) -> Result<u64> {
+        self.repository.count_search_matches(query, mode)
+    }
+
+    /// Get the distribution of memory sizes, optionally filtered by category and mode
+    pub fn get_memory_size_distribution(
+        &self,
+        category: Option<&str>,
+        mode: Option<&str>,
+    ) -> Result<SizeDistribution> {
+        self.repository.get_memory_size_distribution(category, mode)
+    }
+
+    /// Get memory counts and tokens, overall and per category and mode
+    pub fn get_stats(&self) -> Result<MemoryStats> {
+        self.repository.get_stats()
+    }
+
+    /// Count the memories in each category, leaving out uncategorized memories
+    pub fn count_by_category(&self) -> Result<HashMap<String, u64>> {
+        self.repository.count_by_category()
+    }
+
+    /// Count the memories without a category
+    pub fn count_uncategorized(&self) -> Result<u64> {
+        self.repository.count_uncategorized()
+    }
+
+    /// Move every memory in the source category to the target category
+    ///
+    /// Returns the number of memories moved.
+    pub fn merge_categories(&self, source: &str, target: &str) -> Result<u64> {
+        if source.is_empty() || target.is_empty() {
+            return Err(anyhow::anyhow!("Category names must not be empty"));
+        }
+        if source == target {
+            return Ok(0);
+        }
+
+        let updated = self.repository.merge_categories(source, target)?;
+
+        let mut cache = self.cache.lock().unwrap();
+        for (_, memory) in cache
+            .iter_mut()
+            .filter(|(_, m)| m.category.as_deref() == Some(source))
+        {
+            memory.category = Some(target.to_string());
+        }
+
+        Ok(updated)
+    }
+
+    /// Move one memory into `category`, returning it or `None` if it does not exist
+    pub fn set_category(&self, id: &MemoryId, category: &str) -> Result<Option<Memory>> {
+        if category.is_empty() {
+            return Err(anyhow::anyhow!("Category name must not be empty"));
+        }
+
+        let mut memory = match self.repository.retrieve(id)? {
+            Some(memory) => memory,
+            None => return Ok(None),
+        };
+        if !self.repository.set_category(id, category)? {
+            return Ok(None);
+        }
+        memory.category = Some(category.to_string());
+
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(cached) = cache.peek_mut(id) {
+            cached.category = Some(category.to_string());
+        }
+
+        Ok(Some(memory))
+    }
+
+    /// Rename a category, returning the number of memories moved
+    pub fn rename_category(&self, old_name: &str, new_name: &str) -> Result<u64> {
+        self.merge_categories(old_name, new_name)
+    }
+
+    /// Reclaim the space left behind by deleted memories, returning the bytes recovered
+    ///
+    /// Fails with [`CompactionInProgress`](super::CompactionInProgress) while another
+    /// compaction of the same database is running.
+    pub fn compact(&self) -> Result<u64> {
+        self.repository.compact()
+    }
+
+    /// Return up to `pages` free pages to the file system, or all of them if `pages` is 0,
+    /// returning the number freed
+    ///
+    /// Unlike [`Self::compact`] this never rewrites the database, so it is cheap
+    /// enough to run routinely.
+    pub fn vacuum(&self, pages: u32) -> Result<u64> {
+        self.repository.vacuum(pages)
+    }
+
+    /// Recount the tokens of every memory with the current tokenizer, returning how many changed
+    ///
+    /// Run this after switching tokenizers; counts that are already correct
+    /// are left alone and the rest are updated in one transaction.
+    pub fn recalculate_tokens(&self) -> Result<u64> {
+        let stale: Vec<(MemoryId, TokenCount)> = self
+            .repository
+            .get_all(0, usize::MAX)?
+            .into_iter()
+            .filter_map(|memory| {
+                let token_count = self.tokenizer.count_tokens(&memory.content);
+                (token_count != memory.token_count).then_some((memory.id, token_count))
+            })
+            .collect();
+        if stale.is_empty() {
+            return Ok(0);
+        }
+
+        let updated = self.repository.set_token_counts(&stale)?;
+
+        let mut cache = self.cache.lock().unwrap();
+        for (id, token_count) in &stale {
+            if let Some(cached) = cache.peek_mut(id) {
+                cached.token_count = *token_count;
+            }
+        }
+        self.token_total
+            .store(TOKEN_TOTAL_UNLOADED, Ordering::Release);
+        Ok(updated)
+    }
+
+    /// Checkpoint the stored memories so later writes can be rolled back
+    ///
+    /// Snapshots nest: rolling back or committing one also closes every
+    /// snapshot taken after it. A snapshot still open after [`SNAPSHOT_TIMEOUT`]
+    /// is rolled back by [`MemoryStore::expire_snapshots`].
+    pub fn snapshot(&self) -> Result<SnapshotId> {
+        self.expire_snapshots()?;
+
+        let id = SnapshotId::new();
+        let savepoint = format!("snap_{}", id.as_str());
+        let mut snapshots = self.snapshots.lock().unwrap();
+        self.repository.savepoint(&savepoint)?;
+        snapshots.push(OpenSnapshot {
+            id: id.clone(),
+            savepoint,
+            taken_at: Instant::now(),
+        });
+        Ok(id)
+    }
+
+    /// Undo every write made since the snapshot was taken
+    pub fn rollback_snapshot(&self, id: &SnapshotId) -> Result<()> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let savepoint = close_snapshots_from(&mut snapshots, id)?;
+        self.repository.rollback_to_savepoint(&savepoint)?;
+        drop(snapshots);
+
+        self.forget_cached_writes();
+        Ok(())
+    }
+
+    /// Keep every write made since the snapshot was taken and close it
+    pub fn commit_snapshot(&self, id: &SnapshotId) -> Result<()> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let savepoint = close_snapshots_from(&mut snapshots, id)?;
+        self.repository.release_savepoint(&savepoint)
+    }
+
+    /// Roll back snapshots open longer than [`SNAPSHOT_TIMEOUT`], returning how many were closed
+    pub fn expire_snapshots(&self) -> Result<usize> {
+        self.expire_snapshots_at(Instant::now())
+    }
+
+    /// Roll back the snapshots that have timed out by `now`
+    fn expire_snapshots_at(&self, now: Instant) -> Result<usize> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let Some(oldest) = snapshots
+            .iter()
+            .find(|snapshot| now.duration_since(snapshot.taken_at) >= SNAPSHOT_TIMEOUT)
+        else {
+            return Ok(0);
+        };
+        let id = oldest.id.clone();
+        let open = snapshots.len();
+        let savepoint = close_snapshots_from(&mut snapshots, &id)?;
+        self.repository.rollback_to_savepoint(&savepoint)?;
+        let expired = open - snapshots.len();
+        drop(snapshots);
+
+        self.forget_cached_writes();
+        Ok(expired)
+    }
+
+    /// Export all memories, optionally restricted to the given categories
+    ///
+    /// Memories are read straight from the repository so exporting does not
+    /// update their last accessed time.
+    pub fn export_memories(&self, categories: &[String]) -> Result<Vec<Memory>> {
+        let categories: HashSet<&str> = categories.iter().map(String::as_str).collect();
+
+        let mut memories = self.repository.get_all(0, usize::MAX)?;
+        memories.retain(|memory| {
+            !memory.is_hidden()
+                && (categories.is_empty()
+                    || memory
+                        .category
+                        .as_deref()
+                        .is_some_and(|c| categories.contains(c)))
+        });
+
+        Ok(memories)
+    }
+
+    /// Import memories under their original IDs, resolving conflicts with existing ones
+    ///
+    /// A memory conflicts when one with the same ID exists locally with different
+    /// content or attributes; identical memories are skipped.
+    pub fn import_memories(
+        &self,
+        memories: Vec<Memory>,
+        resolution: ConflictResolution,
+    ) -> Result<ImportStats> {
+        self.import(memories, resolution, true)
+    }
+
+    /// Import memories as [`MemoryStore::import_memories`] does, auditing them if `audited`
+    fn import(
+        &self,
+        memories: Vec<Memory>,
+        resolution: ConflictResolution,
+        audited: bool,
+    ) -> Result<ImportStats> {
+        let mut stats = ImportStats::default();
+
+        if resolution == ConflictResolution::Fail {
+            for memory in &memories {
+                if let Some(existing) = self.repository.retrieve(&memory.id)? {
+                    if !same_contents(&existing, memory) {
+                        return Err(ImportConflict(memory.id.clone()).into());
+                    }
+                }
+            }
+        }
+
+        for memory in memories {
+            let existing = self.repository.retrieve(&memory.id)?;
+            if let Some(existing) = &existing {
+                if same_contents(existing, &memory) {
+                    continue;
+                }
+                stats.conflicts_resolved += 1;
+                if !resolution.prefers_incoming(existing, &memory) {
+                    continue;
+                }
+            }
+
+            self.repository.store(&memory)?;
+            if audited {
+                let operation = match existing {
+                    Some(_) => AuditOperation::Update,
+                    None => AuditOperation::Store,
+                };
+                self.audit(operation, &memory)?;
+            }
+            let previous_tokens = existing.as_ref().map_or(0, |m| m.token_count.as_usize());
+            let token_delta = memory.token_count.as_usize() as i64 - previous_tokens as i64;
+            self.adjust_token_total(
+                memory.counted_tokens() - existing.as_ref().map_or(0, Memory::counted_tokens),
+            )?;
+            let kind = match existing {
+                Some(_) => StoreEventKind::Updated,
+                None => StoreEventKind::Stored,
+            };
+            self.events.publish(kind, &memory.id, token_delta);
+
+            // Update the cache
+            let mut cache = self.cache.lock().unwrap();
+            cache.put(memory.id.clone(), memory);
+            stats.imported += 1;
+        }
+
+        Ok(stats)
+    }
+
+    /// Write the memories matching `filter` to `writer` as a JSON array, returning how many
+    ///
+    /// Memories are serialized one page at a time, so the export is never held in memory
+    /// as a whole. Expired memories are left out.
+    pub fn export_json(
+        &self,
+        writer: &mut impl Write,
+        filter: Option<&MemoryFilter>,
+    ) -> Result<u64> {
+        let now = chrono::Utc::now();
+        let mut serializer = serde_json::Serializer::pretty(writer);
+        let mut array = serializer
+            .serialize_seq(None)
+            .context("Failed to start JSON export")?;
+
+        let mut count = 0;
+        for page in 0.. {
+            let memories = self.repository.get_all(page, JSON_EXPORT_PAGE_SIZE)?;
+            for memory in &memories {
+                if memory.is_expired(now) || filter.is_some_and(|filter| !filter.matches(memory)) {
+                    continue;
+                }
+                array
+                    .serialize_element(memory)
+                    .with_context(|| format!("Failed to export memory {}", memory.id.as_str()))?;
+                count += 1;
+            }
+            if memories.len() < JSON_EXPORT_PAGE_SIZE {
+                break;
+            }
+        }
+
+        array.end().context("Failed to finish JSON export")?;
+        Ok(count)
+    }
+
+    /// Import memories from a JSON array written by `export_json`
+    ///
+    /// Entries that are not valid memories are reported in the result's errors
+    /// instead of failing the import.
+    pub fn import_json(
+        &self,
+        reader: &mut impl Read,
+        resolution: ConflictResolution,
+    ) -> Result<ImportResult> {
+        let entries: Vec<serde_json::Value> =
+            serde_json::from_reader(reader).context("Failed to parse JSON export")?;
+
+        let mut result = ImportResult::default();
+        let mut memories = Vec::with_capacity(entries.len());
+        for (index, entry) in entries.into_iter().enumerate() {
+            match serde_json::from_value::<Memory>(entry) {
+                Ok(memory) => memories.push(memory),
+                Err(e) => result.errors.push(format!("Entry {}: {}", index, e)),
+            }
+        }
+
+        let valid = memories.len();
+        let stats = self.import_memories(memories, resolution)?;
+        result.imported = stats.imported as u64;
+        result.skipped = (valid - stats.imported) as u64;
+        Ok(result)
+    }
+
+    /// Check if the connection to the repository is working
+    pub fn check_connection(&self) -> Result<bool> {
+        Ok(self.repository.check_connection().is_ok())
+    }
+}
+
+/// Check whether two versions of a memory hold the same data
+fn same_contents(a: &Memory, b: &Memory) -> bool {
+    a.content == b.content
+        && a.content_type == b.content_type
+        && a.category == b.category
+        && a.mode == b.mode
+        && a.metadata == b.metadata
+        && a.tags == b.tags
+}
+
+/// Trim tags, dropping empty and repeated ones, and sort them
+fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .into_iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Memory ID and creation time recorded for an idempotency key
+type IdempotencyRecord = (MemoryId, chrono::DateTime<chrono::Utc>);
+
+/// In-memory implementation of the memory repository
+#[derive(Debug)]
+struct InMemoryRepository {
+    /// The memories stored by ID
+    memories: Arc<Mutex<HashMap<MemoryId, Memory>>>,
+    /// Idempotency keys with their memory ID and creation time
+    idempotency_keys: Arc<Mutex<HashMap<String, IdempotencyRecord>>>,
+    /// Links between memories as (source, target, relation type)
+    links: Arc<Mutex<HashSet<(MemoryId, MemoryId, String)>>>,
+    /// Open savepoints, oldest first
+    savepoints: Arc<Mutex<Vec<InMemorySavepoint>>>,
+    /// The tokenizer used for counting tokens
+    tokenizer: Tokenizer,
+}
+
+/// Copy of an in-memory repository's contents taken when a savepoint was opened
+#[derive(Debug)]
+struct InMemorySavepoint {
+    name: String,
+    memories: HashMap<MemoryId, Memory>,
+    idempotency_keys: HashMap<String, IdempotencyRecord>,
+    links: HashSet<(MemoryId, MemoryId, String)>,
+}
+
+impl InMemoryRepository {
+    /// Create a new in-memory repository
+    fn new(tokenizer: Tokenizer) -> Self {
+        Self {
+            memories: Arc::new(Mutex::new(HashMap::new())),
+            idempotency_keys: Arc::new(Mutex::new(HashMap::new())),
+            links: Arc::new(Mutex::new(HashSet::new())),
+            savepoints: Arc::new(Mutex::new(Vec::new())),
+            tokenizer,
+        }
+    }
+
+    /// Close the savepoint `name` and every savepoint opened after it, returning it
+    fn close_savepoint(&self, name: &str) -> Result<InMemorySavepoint> {
+        let mut savepoints = self.savepoints.lock().unwrap();
+        let position = savepoints
+            .iter()
+            .position(|savepoint| savepoint.name == name)
+            .ok_or_else(|| anyhow::anyhow!("No savepoint named {} is open", name))?;
+        let savepoint = savepoints.drain(position..).next().unwrap();
+        Ok(savepoint)
+    }
+
+    /// Get one page of the memories matching `filter`, oldest first
+    fn page_where(
+        &self,
+        filter: impl Fn(&Memory) -> bool,
+        page: usize,
+        page_size: usize,
+    ) -> Vec<Memory> {
+        let memories = self.memories.lock().unwrap();
+        let mut ordered: Vec<&Memory> = memories.values().filter(|m| filter(m)).collect();
+        ordered.sort_by(|a, b| (a.created_at, a.id.as_str()).cmp(&(b.created_at, b.id.as_str())));
+
+        ordered
+            .into_iter()
+            .skip(page.saturating_mul(page_size))
+            .take(page_size)
+            .cloned()
+            .collect()
+    }
+}
+
+impl MemoryRepository for InMemoryRepository {
+    fn store(&self, memory: &Memory) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+        memories.insert(memory.id.clone(), memory.clone());
+        Ok(())
+    }
+
+    fn insert(&self, memory: &Memory) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+        if memories.contains_key(&memory.id) {
+            return Err(DuplicateMemoryId(memory.id.clone()).into());
+        }
+        memories.insert(memory.id.clone(), memory.clone());
+        Ok(())
+    }
+
+    fn store_batch(&self, batch: &[Memory]) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+
+        // Check every ID before inserting anything, matching the SQLite rollback
+        let mut ids = HashSet::new();
+        for memory in batch {
+            if memories.contains_key(&memory.id) || !ids.insert(&memory.id) {
+                return Err(anyhow::anyhow!(
+                    "Memory with ID {} already exists",
+                    memory.id.as_str()
+                ));
+            }
+        }
+
+        for memory in batch {
+            memories.insert(memory.id.clone(), memory.clone());
+        }
+        Ok(())
+    }
+
+    fn retrieve(&self, id: &MemoryId) -> Result<Option<Memory>> {
+        let memories = self.memories.lock().unwrap();
+        Ok(memories.get(id).cloned())
+    }
+
+    fn touch(&self, id: &MemoryId) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+        if let Some(memory) = memories.get_mut(id) {
+            memory.touch();
+            memory.access_count += 1;
+        }
+        Ok(())
+    }
+
+    fn delete(&self, id: &MemoryId) -> Result<()> {
+        self.memories.lock().unwrap().remove(id);
+
+        // Links of a deleted memory go with it, as with the SQLite foreign keys
+        let mut links = self.links.lock().unwrap();
+        links.retain(|(source, target, _)| source != id && target != id);
+        Ok(())
+    }
+
+    fn pin(&self, id: &MemoryId) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+        if let Some(memory) = memories.get_mut(id) {
+            memory.pinned = true;
+        }
+        Ok(())
+    }
+
+    fn unpin(&self, id: &MemoryId) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+        if let Some(memory) = memories.get_mut(id) {
+            memory.pinned = false;
+        }
+        Ok(())
+    }
+
+    fn add_tag(&self, id: &MemoryId, tag: &str) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+        if let Some(memory) = memories.get_mut(id) {
+            if let Err(index) = memory.tags.binary_search_by(|t| t.as_str().cmp(tag)) {
+                memory.tags.insert(index, tag.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_tag(&self, id: &MemoryId, tag: &str) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+        if let Some(memory) = memories.get_mut(id) {
+            memory.tags.retain(|t| t != tag);
+        }
+        Ok(())
+    }
+
+    fn get_by_tag(&self, tag: &str, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        Ok(self.page_where(
+            |memory| memory.tags.iter().any(|t| t == tag),
+            page,
+            page_size,
+        ))
+    }
+
+    fn get_tags_for_memory(&self, id: &MemoryId) -> Result<Vec<String>> {
+        let memories = self.memories.lock().unwrap();
+        Ok(memories
+            .get(id)
+            .map(|memory| memory.tags.clone())
+            .unwrap_or_default())
+    }
+
+    fn link(&self, source: &MemoryId, target: &MemoryId, relation: &str) -> Result<()> {
+        let memories = self.memories.lock().unwrap();
+        if memories.contains_key(source) && memories.contains_key(target) {
+            let mut links = self.links.lock().unwrap();
+            links.insert((source.clone(), target.clone(), relation.to_string()));
+        }
+        Ok(())
+    }
+
+    fn get_linked(&self, id: &MemoryId, relation: Option<&str>) -> Result<Vec<Memory>> {
+        let targets: HashSet<MemoryId> = self
+            .links
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(source, _, rel)| source == id && relation.is_none_or(|r| r == rel))
+            .map(|(_, target, _)| target.clone())
+            .collect();
+
+        Ok(self.page_where(|memory| targets.contains(&memory.id), 0, usize::MAX))
+    }
+
+    fn update(&self, memory: &Memory) -> Result<()> {
+        let mut memories = self.memories.lock().unwrap();
+        if let Some(existing) = memories.get_mut(&memory.id) {
+            existing.content = memory.content.clone();
+            existing.metadata = memory.metadata.clone();
+            existing.token_count = memory.token_count;
+            existing.last_accessed = memory.last_accessed;
+        }
+        Ok(())
+    }
+
+    fn get_all_ids(&self, page: usize, page_size: usize) -> Result<Vec<MemoryId>> {
+        Ok(self
+            .get_all(page, page_size)?
+            .into_iter()
+            .map(|memory| memory.id)
+            .collect())
+    }
+
+    fn get_all(&self, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        Ok(self.page_where(|_| true, page, page_size))
+    }
+
+    fn get_by_category(
+        &self,
+        category: &str,
+        page: usize,
+        page_size: usize,
+    ) -> Result<Vec<Memory>> {
+        Ok(self.page_where(
+            |memory| memory.category.as_deref() == Some(category),
+            page,
+            page_size,
+        ))
+    }
+
+    fn get_by_mode(&self, mode: &str, page: usize, page_size: usize) -> Result<Vec<Memory>> {
+        Ok(self.page_where(
+            |memory| memory.mode.as_deref() == Some(mode),
+            page,
+            page_size,
+        ))
+    }
+
+    fn list(
+        &self,
+        filter: &MemoryFilter,
+        sort: MemorySortField,
+        order: SortOrder,
+        page: usize,
+        page_size: usize,
+    ) -> Result<(Vec<Memory>, u64)> {
+        let memories = self.memories.lock().unwrap();
+        let mut matches: Vec<&Memory> = memories.values().filter(|m| filter.matches(m)).collect();
+        matches.sort_by(|a, b| order.apply(sort.compare(a, b)));
+
+        let total = matches.len() as u64;
+        let page = matches
+            .into_iter()
+            .skip(page.saturating_mul(page_size))
+            .take(page_size)
+            .cloned()
+            .collect();
+        Ok((page, total))
+    }
+
+    fn get_page_count(&self, page_size: usize) -> Result<usize> {
+        if page_size == 0 {
+            return Err(anyhow::anyhow!("Page size must be greater than zero"));
+        }
+        Ok(self.memories.lock().unwrap().len().div_ceil(page_size))
+    }
+
+    fn total_tokens(&self) -> Result<TokenCount> {
+        let memories = self.memories.lock().unwrap();
+        Ok(memories
+            .values()
+            .filter(|m| !m.is_hidden())
+            .map(|m| m.token_count)
+            .sum())
+    }
+
+    fn count(&self) -> Result<u64> {
+        Ok(self.memories.lock().unwrap().len() as u64)
+    }
+
+    fn check_connection(&self) -> Result<()> {
+        // Nothing can be disconnected
+        Ok(())
+    }
+
+    fn search_content_regex_safe(&self, pattern: &str, limit: usize) -> Result<Vec<Memory>> {
+        let regex = RegexSafetyCheck::new().check(pattern)?;
+
+        let memories = self.memories.lock().unwrap();
+        let mut matches: Vec<Memory> = memories
+            .values()
+            .filter(|m| regex.is_match(&m.content))
+            .cloned()
+            .collect();
+
+        // Most recently accessed first, matching the SQLite ordering
+        matches.sort_by_key(|m| std::cmp::Reverse(m.last_accessed));
+        matches.truncate(limit);
+
+        Ok(matches)
+    }
+
+    fn search(&self, query: &str, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let memories = self.memories.lock().unwrap();
+        let mut matches: Vec<Memory> = memories
+            .values()
+            .filter(|m| !m.is_hidden() && mode.is_none_or(|md| m.mode.as_deref() == Some(md)))
+            .filter(|m| m.content.to_lowercase().contains(&query))
+            .cloned()
+            .collect();
+
+        matches.sort_by_key(|m| std::cmp::Reverse(m.last_accessed));
+        matches.truncate(limit);
+
+        Ok(matches)
+    }
+
+    fn count_search_matches(&self, query: &str, mode: Option<&str>) -> Result<u64> {
+        Ok(self.search(query, mode, usize::MAX)?.len() as u64)
+    }
+
+    fn purge_expired(&self) -> Result<Vec<(MemoryId, TokenCount)>> {
+        let now = chrono::Utc::now();
+        let mut memories = self.memories.lock().unwrap();
+        let mut purged = Vec::new();
+        memories.retain(|id, memory| {
+            let expired = memory.is_expired(now);
+            if expired {
+                purged.push((id.clone(), memory.token_count));
+            }
+            !expired
+        });
+        Ok(purged)
+    }
+
+    fn get_recently_accessed(&self, limit: usize) -> Result<Vec<Memory>> {
+        let memories = self.memories.lock().unwrap();
+        let mut recent: Vec<Memory> = memories.values().cloned().collect();
+
+        recent.sort_by_key(|m| std::cmp::Reverse(m.last_accessed));
+        recent.truncate(limit);
+
+        Ok(recent)
+    }
+
+    fn get_hot_memories(&self, mode: Option<&str>, limit: usize) -> Result<Vec<Memory>> {
+        let memories = self.memories.lock().unwrap();
+        let mut hot: Vec<Memory> = memories
+            .values()
+            .filter(|m| m.access_count > 0)
+            .filter(|m| mode.is_none_or(|md| m.mode.as_deref() == Some(md)))
+            .cloned()
+            .collect();
+
+        hot.sort_by_key(|m| std::cmp::Reverse((m.access_count, m.last_accessed)));
+        hot.truncate(limit);
+
+        Ok(hot)
+    }
+
+    fn get_by_metadata(&self, key: &str, value: &str) -> Result<Vec<Memory>> {
+        Ok(self.page_where(
+            |memory| memory.metadata.get(key).map(String::as_str) == Some(value),
+            0,
+            usize::MAX,
+        ))
+    }
+
+    fn get_memory_size_distribution(
+        &self,
+        category: Option<&str>,
+        mode: Option<&str>,
+    ) -> Result<SizeDistribution> {
+        let memories = self.memories.lock().unwrap();
+        let counts = memories
+            .values()
+            .filter(|m| category.is_none_or(|c| m.category.as_deref() == Some(c)))
+            .filter(|m| mode.is_none_or(|md| m.mode.as_deref() == Some(md)))
+            .map(|m| m.token_count.as_usize())
+            .collect();
+
+        Ok(SizeDistribution::from_token_counts(counts))
+    }
+
+    fn get_stats(&self) -> Result<MemoryStats> {
+        let memories = self.memories.lock().unwrap();
+        let mut stats = MemoryStats::default();
+        for memory in memories.values().filter(|m| !m.is_hidden()) {
+            stats.add(
+                memory.category.as_deref(),
+                memory.mode.as_deref(),
+                memory.token_count.as_usize(),
+            );
+            if memory.pinned {
+                stats.pinned_count += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    fn count_by_category(&self) -> Result<HashMap<String, u64>> {
+        let memories = self.memories.lock().unwrap();
+        let mut counts = HashMap::new();
+        for category in memories.values().filter_map(|m| m.category.as_ref()) {
+            *counts.entry(category.clone()).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    fn count_uncategorized(&self) -> Result<u64> {
+        let memories = self.memories.lock().unwrap();
+        Ok(memories.values().filter(|m| m.category.is_none()).count() as u64)
+    }
+
+    fn merge_categories(&self, source: &str, target: &str) -> Result<u64> {
+        let mut memories = self.memories.lock().unwrap();
+        let mut updated = 0;
+        for memory in memories
+            .values_mut()
+            .filter(|m| m.category.as_deref() == Some(source))
+        {
+            memory.category = Some(target.to_string());
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    fn set_category(&self, id: &MemoryId, category: &str) -> Result<bool> {
+        let mut memories = self.memories.lock().unwrap();
+        match memories.get_mut(id) {
+            Some(memory) => {
+                memory.category = Some(category.to_string());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    fn compact(&self) -> Result<u64> {
+        // Nothing is left behind on disk
+        Ok(0)
+    }
+
+    fn vacuum(&self, _pages: u32) -> Result<u64> {
+        Ok(0)
+    }
+
+    fn free_list_size(&self) -> Result<u64> {
+        Ok(0)
+    }
+
+    fn database_path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn restore_from(&self, _source: &Path) -> Result<()> {
+        bail!("Restoring a database file needs SQLite storage")
+    }
+
+    fn set_token_counts(&self, counts: &[(MemoryId, TokenCount)]) -> Result<u64> {
+        let mut memories = self.memories.lock().unwrap();
+        let mut updated = 0;
+        for (id, token_count) in counts {
+            if let Some(memory) = memories.get_mut(id) {
+                memory.token_count = *token_count;
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    }
+
+    fn savepoint(&self, name: &str) -> Result<()> {
+        let savepoint = InMemorySavepoint {
+            name: name.to_string(),
+            memories: self.memories.lock().unwrap().clone(),
+            idempotency_keys: self.idempotency_keys.lock().unwrap().clone(),
+            links: self.links.lock().unwrap().clone(),
+        };
+        self.savepoints.lock().unwrap().push(savepoint);
+        Ok(())
+    }
+
+    fn rollback_to_savepoint(&self, name: &str) -> Result<()> {
+        let savepoint = self.close_savepoint(name)?;
+        *self.memories.lock().unwrap() = savepoint.memories;
+        *self.idempotency_keys.lock().unwrap() = savepoint.idempotency_keys;
+        *self.links.lock().unwrap() = savepoint.links;
+        Ok(())
+    }
+
+    fn release_savepoint(&self, name: &str) -> Result<()> {
+        self.close_savepoint(name).map(drop)
+    }
+
+    fn get_idempotency_key(&self, key: &str) -> Result<Option<IdempotencyRecord>> {
+        let keys = self.idempotency_keys.lock().unwrap();
+        Ok(keys.get(key).cloned())
+    }
+
+    fn store_idempotency_key(
+        &self,
+        key: &str,
+        memory_id: &MemoryId,
+        created_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<()> {
+        let mut keys = self.idempotency_keys.lock().unwrap();
+        keys.insert(key.to_string(), (memory_id.clone(), created_at));
+        Ok(())
+    }
+
+    fn prune_idempotency_keys(&self, before: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        let mut keys = self.idempotency_keys.lock().unwrap();
+        keys.retain(|_, (_, created_at)| *created_at >= before);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{
+        decode_memories, encode_memories, ContentCipher, LanguageTagger, MemoryQuery,
+        RegexSafetyError, NO_MODE, UNCATEGORIZED,
+    };
+    use proptest::prelude::*;
+    use tempfile::tempdir;
+
+    fn store_samples(store: &MemoryStore) -> Result<()> {
+        for content in [
+            "fn main() { println!(\"hello\"); }",
+            "def main(): print('hello')",
+            "Decision: use gRPC for transport",
+        ] {
+            store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_search_in_memory() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        store_samples(&store)?;
+
+        let results = store.search_content_regex_safe("^(fn|def) main", 10)?;
+        assert_eq!(results.len(), 2);
+
+        let results = store.search_content_regex_safe("gRPC", 10)?;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].content.starts_with("Decision"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_search_sqlite() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let store =
+            MemoryStore::new_sqlite(&temp_dir.path().join("test.db"), Tokenizer::default())?;
+        store_samples(&store)?;
+
+        let results = store.search_content_regex_safe("hello", 10)?;
+        assert_eq!(results.len(), 2);
+
+        let results = store.search_content_regex_safe("hello", 1)?;
+        assert_eq!(results.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_text_search_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        store_samples(&store)?;
+        let decision = store.store(
+            "Decision: cache gRPC channels per peer".to_string(),
+            "text/plain".to_string(),
+            None,
+            Some("architect".to_string()),
+            HashMap::new(),
+        )?;
+
+        assert_eq!(store.search("gRPC", None, 10)?.len(), 2);
+        assert_eq!(store.search("gRPC", None, 1)?.len(), 1);
+        assert_eq!(store.count_search_matches("gRPC", None)?, 2);
+        assert_eq!(store.count_search_matches("grpc", Some("architect"))?, 1);
+        let results = store.search("grpc", Some("architect"), 10)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, decision.id);
+
+        // Updates and deletes keep the index in sync; the version keeping the old content is hidden
+        store.update(&decision.id, "Decision: pool connections".to_string(), None)?;
+        let results = store.search("gRPC", None, 10)?;
+        assert_eq!(results.len(), 1);
+        assert!(!results.iter().any(|memory| memory.id == decision.id));
+        store.delete(&decision.id)?;
+        assert!(store.search("pool", None, 10)?.is_empty());
+
+        // Operators and punctuation are matched literally
+        assert!(store.search("main() OR \"", None, 10).is_ok());
+
+        Ok(())
+    }
+
+    /// Read a memory's content column straight from the database
+    fn raw_content(db_path: &Path, id: &MemoryId) -> Result<String> {
+        let connection = rusqlite::Connection::open(db_path)?;
+        Ok(connection.query_row(
+            "SELECT content FROM memories WHERE id = ?",
+            [id.as_str()],
+            |row| row.get(0),
+        )?)
+    }
+
+    #[test]
+    fn test_encrypted_content_is_unreadable_in_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("memories.db");
+        let store = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?
+            .with_encryption(ContentCipher::new(&[1; 32])?);
+        store_samples(&store)?;
+        let secret = store.store(
+            "API key: sk-live-1234".to_string(),
+            "text/plain".to_string(),
+            Some("context".to_string()),
+            None,
+            HashMap::new(),
+        )?;
+
+        let stored = raw_content(&db_path, &secret.id)?;
+        assert!(ContentCipher::is_encrypted(&stored));
+        assert!(!stored.contains("sk-live"));
+
+        // Token counts come from the plaintext, and reads see it again
+        let tokenizer = Tokenizer::default();
+        assert_eq!(
+            secret.token_count,
+            tokenizer.count_tokens("API key: sk-live-1234")
+        );
+        let reopened = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?
+            .with_encryption(ContentCipher::new(&[1; 32])?);
+        assert_eq!(
+            reopened.retrieve(&secret.id)?.unwrap().content,
+            "API key: sk-live-1234"
+        );
+        assert_eq!(
+            reopened.get_by_category("context", 0, 10)?[0].content,
+            "API key: sk-live-1234"
+        );
+
+        // Content searches still match the plaintext
+        assert_eq!(store.search("sk-live", None, 10)?.len(), 1);
+        assert_eq!(
+            store.search_content_regex_safe("^(fn|def) main", 10)?.len(),
+            2
+        );
+
+        // Without the key the content cannot be read back
+        let other = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?
+            .with_encryption(ContentCipher::new(&[2; 32])?);
+        assert!(other.retrieve(&secret.id).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reencrypt_all_switches_keys() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("memories.db");
+        let (old_key, new_key) = ([1; 32], [2; 32]);
+
+        // One memory stored before encryption was enabled
+        let plain = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?;
+        let earlier = plain.store(
+            "stored in plaintext".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+        )?;
+        let store = plain.with_encryption(ContentCipher::new(&old_key)?);
+        store_samples(&store)?;
+
+        assert_eq!(store.reencrypt_all(&old_key, &new_key)?, 4);
+        assert!(ContentCipher::is_encrypted(&raw_content(
+            &db_path,
+            &earlier.id
+        )?));
+        // Repeating the migration finds nothing left to do
+        assert_eq!(store.reencrypt_all(&old_key, &new_key)?, 0);
+
+        let reopened = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?
+            .with_encryption(ContentCipher::new(&new_key)?);
+        assert_eq!(
+            reopened.retrieve(&earlier.id)?.unwrap().content,
+            "stored in plaintext"
+        );
+        assert_eq!(reopened.search_content_regex_safe("hello", 10)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_distribution_sqlite() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let store =
+            MemoryStore::new_sqlite(&temp_dir.path().join("test.db"), Tokenizer::default())?;
+        store_samples(&store)?;
+        store.store(
+            "one two three".to_string(),
+            "text/plain".to_string(),
+            Some("decision".to_string()),
+            None,
+            HashMap::new(),
+        )?;
+
+        let all = store.get_memory_size_distribution(None, None)?;
+        assert_eq!(all.total_count, 4);
+
+        let decisions = store.get_memory_size_distribution(Some("decision"), None)?;
+        assert_eq!(decisions.total_count, 1);
+        assert_eq!(decisions.min_tokens, decisions.max_tokens);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebuild_cache_from_db() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let store =
+            MemoryStore::new_sqlite(&temp_dir.path().join("test.db"), Tokenizer::default())?;
+        store
+            .cache
+            .lock()
+            .unwrap()
+            .resize(NonZeroUsize::new(2).unwrap());
+        store_samples(&store)?;
+
+        // Simulate a stale cache
+        store.cache.lock().unwrap().clear();
+        assert_eq!(store.rebuild_cache_from_db()?, 2);
+        assert_eq!(store.cache.lock().unwrap().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hot_memories_ranked_by_access_count() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let stores = [
+            MemoryStore::new_in_memory(Tokenizer::default()),
+            MemoryStore::new_sqlite(&temp_dir.path().join("test.db"), Tokenizer::default())?,
+        ];
+        for store in stores {
+            let store_one = |content: &str, mode: &str| {
+                store.store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    Some(mode.to_string()),
+                    HashMap::new(),
+                )
+            };
+            let once = store_one("retrieved once", "code")?;
+            let often = store_one("retrieved often", "code")?;
+            let never = store_one("never retrieved", "code")?;
+            let other_mode = store_one("retrieved in another mode", "architect")?;
+
+            store.retrieve(&once.id)?;
+            for _ in 0..5 {
+                store.retrieve(&often.id)?;
+            }
+            for _ in 0..3 {
+                store.retrieve(&other_mode.id)?;
+            }
+            // Counts survive the cache, which was filled by the first retrieval
+            store.cache.lock().unwrap().clear();
+
+            let hot = store.get_hot_memories(Some("code"), 10)?;
+            let ranked: Vec<(&MemoryId, u64)> = hot
+                .iter()
+                .map(|memory| (&memory.id, memory.access_count))
+                .collect();
+            assert_eq!(ranked, vec![(&often.id, 5), (&once.id, 1)]);
+            assert!(!hot.iter().any(|memory| memory.id == never.id));
+
+            assert_eq!(store.get_hot_memories(None, 2)?[1].id, other_mode.id);
+            assert_eq!(store.retrieve(&often.id)?.unwrap().access_count, 6);
+
+            store.cache.lock().unwrap().clear();
+            assert_eq!(store.preload_hot_memories(10)?.len(), 3);
+            assert_eq!(store.cache_stats().entries, 3);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_from_replaces_memories() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let store_in = |name: &str, content: &str| -> Result<(MemoryStore, Memory)> {
+            let store = MemoryStore::new_sqlite(&temp_dir.path().join(name), Tokenizer::default())?;
+            let memory = store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )?;
+            Ok((store, memory))
+        };
+        let (store, replaced) = store_in("live.db", "replaced by the restore")?;
+        let (source, restored) = store_in("source.db", "restored from the source database")?;
+        drop(source);
+        assert_eq!(store.get_total_tokens()?, replaced.token_count);
+
+        store.restore_from(&temp_dir.path().join("source.db"))?;
+
+        assert!(store.retrieve(&replaced.id)?.is_none());
+        assert_eq!(
+            store.retrieve(&restored.id)?.unwrap().content,
+            restored.content
+        );
+        assert_eq!(store.get_total_tokens()?, restored.token_count);
+
+        let in_memory = MemoryStore::new_in_memory(Tokenizer::default());
+        assert!(in_memory
+            .restore_from(&temp_dir.path().join("source.db"))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replay_audit_log_rebuilds_memories() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let audit_path = temp_dir.path().join("audit.jsonl");
+        let config = AuditLogConfig {
+            enabled: true,
+            path: audit_path.clone(),
+            include_content: true,
+        };
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        store.enable_audit_log(&config)?;
+        let store_one = |content: &str| {
+            store.store(
+                content.to_string(),
+                "text/markdown".to_string(),
+                Some("decision".to_string()),
+                Some("code".to_string()),
+                HashMap::new(),
+            )
+        };
+
+        let kept = store_one("kept as stored")?;
+        let updated = store_one("replaced by an update")?;
+        let deleted = store_one("deleted before the loss")?;
+        store.update(&updated.id, "content after the update".to_string(), None)?;
+        store.delete(&deleted.id)?;
+        // The update also stores the replaced content as a history version
+        let lines_before = std::fs::read_to_string(&audit_path)?.lines().count();
+        assert_eq!(lines_before, 6);
+
+        let rebuilt = MemoryStore::new_in_memory(Tokenizer::default());
+        rebuilt.enable_audit_log(&config)?;
+        assert_eq!(rebuilt.replay_audit_log(&audit_path)?, 3);
+
+        let restored = rebuilt.retrieve(&kept.id)?.unwrap();
+        assert_eq!(restored.content, kept.content);
+        assert_eq!(restored.content_type, "text/markdown");
+        assert_eq!(restored.category.as_deref(), Some("decision"));
+        assert_eq!(restored.mode.as_deref(), Some("code"));
+        assert_eq!(
+            rebuilt.retrieve(&updated.id)?.unwrap().content,
+            "content after the update"
+        );
+        assert!(rebuilt.retrieve(&deleted.id)?.is_none());
+        assert_eq!(
+            rebuilt.get_history(&updated.id)?[0].content,
+            updated.content
+        );
+        assert_eq!(rebuilt.count()?, 3);
+        // The replay is not written back to the log it was read from
+        assert_eq!(
+            std::fs::read_to_string(&audit_path)?.lines().count(),
+            lines_before
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encrypted_store_refuses_audited_content() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let mut config = AuditLogConfig {
+            enabled: true,
+            path: temp_dir.path().join("audit.jsonl"),
+            include_content: true,
+        };
+        let store = MemoryStore::new_in_memory(Tokenizer::default())
+            .with_encryption(ContentCipher::new(&[1; 32])?);
+
+        assert!(store.enable_audit_log(&config).is_err());
+        assert!(!config.path.exists());
+
+        // Hashes give nothing away, so they are still recorded
+        config.include_content = false;
+        store.enable_audit_log(&config)?;
+        store.store(
+            "secret".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+        )?;
+        assert!(!std::fs::read_to_string(&config.path)?.contains("secret"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watchers_receive_events_from_concurrent_stores() -> Result<()> {
+        let store = Arc::new(MemoryStore::new_in_memory(Tokenizer::default()));
+        let mut watcher = store.watch();
+
+        let stores: Vec<_> = (0..3)
+            .map(|i| {
+                let store = store.clone();
+                tokio::task::spawn_blocking(move || {
+                    store.store(
+                        format!("Concurrent memory number {}", i),
+                        "text/plain".to_string(),
+                        None,
+                        None,
+                        HashMap::new(),
+                    )
+                })
+            })
+            .collect();
+        let mut expected = HashMap::new();
+        for stored in stores {
+            let memory = stored.await??;
+            expected.insert(memory.id, memory.token_count.as_usize() as i64);
+        }
+
+        let mut received = HashMap::new();
+        for _ in 0..3 {
+            let event = store.next_event(&mut watcher).await.unwrap();
+            assert_eq!(event.kind, StoreEventKind::Stored);
+            received.insert(event.memory_id, event.token_delta);
+        }
+        assert_eq!(received, expected);
+        assert_eq!(store.lagged_watchers(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watchers_see_updates_deletes_and_expiry() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        let memory = store.store(
+            "short".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+        )?;
+        let expiring = store.store_with_ttl(
+            "gone soon".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+            Some(0),
+        )?;
+        let mut watcher = store.watch();
+
+        let updated = store
+            .update(&memory.id, "a somewhat longer text".to_string(), None)?
+            .unwrap();
+        store.delete(&memory.id)?;
+        assert!(store.retrieve(&expiring.id)?.is_none());
+
+        // The update first stores the replaced content as a history version
+        let version = store.next_event(&mut watcher).await.unwrap();
+        assert_eq!(version.kind, StoreEventKind::Stored);
+        assert_eq!(version.token_delta, memory.token_count.as_usize() as i64);
+
+        let events = [
+            store.next_event(&mut watcher).await.unwrap(),
+            store.next_event(&mut watcher).await.unwrap(),
+            store.next_event(&mut watcher).await.unwrap(),
+            store.next_event(&mut watcher).await.unwrap(),
+        ];
+        assert_eq!(events[0].kind, StoreEventKind::Updated);
+        assert_eq!(
+            events[0].token_delta,
+            updated.token_count.as_usize() as i64 - memory.token_count.as_usize() as i64
+        );
+        // Deleting the memory deletes its history version first
+        assert_eq!(events[1].kind, StoreEventKind::Deleted);
+        assert_eq!(events[1].memory_id, version.memory_id);
+        assert_eq!(events[2].kind, StoreEventKind::Deleted);
+        assert_eq!(events[2].memory_id, memory.id);
+        assert_eq!(
+            events[2].token_delta,
+            -(updated.token_count.as_usize() as i64)
+        );
+        assert_eq!(events[3].kind, StoreEventKind::Expired);
+        assert_eq!(events[3].memory_id, expiring.id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_hit_rate() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        assert_eq!(store.take_cache_hit_rate(), None);
+
+        store_samples(&store)?;
+        let id = store.get_ids_page(0, usize::MAX)?.remove(0);
+        store.retrieve(&id)?;
+        store.cache.lock().unwrap().clear();
+        store.retrieve(&id)?;
+
+        assert_eq!(store.take_cache_hit_rate(), Some(0.5));
+        assert_eq!(store.take_cache_hit_rate(), None);
+
+        // The lifetime rate is not reset by taking the windowed rate
+        store.retrieve(&id)?;
+        assert_eq!(store.take_cache_hit_rate(), Some(1.0));
+        assert!((store.cache_hit_rate() - 2.0 / 3.0).abs() < f64::EPSILON);
+
+        Ok(())
+    }
+
+    fn bulk_items(contents: &[&str]) -> Vec<StoreItem> {
+        contents
+            .iter()
+            .map(|content| {
+                (
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    Some("progress".to_string()),
+                    None,
+                    HashMap::new(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_paginated_listing_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        let stored = store.bulk_store(bulk_items(&["one", "two", "three", "four", "five"]))?;
+
+        assert_eq!(store.get_page_count(2)?, 3);
+        assert!(store.get_page_count(0).is_err());
+
+        let mut paged = Vec::new();
+        for page in 0..store.get_page_count(2)? {
+            let ids = store.get_ids_page(page, 2)?;
+            let memories = store.get_memories_page(page, 2)?;
+            assert_eq!(
+                ids,
+                memories.iter().map(|m| m.id.clone()).collect::<Vec<_>>()
+            );
+            paged.extend(ids);
+        }
+        assert!(store.get_ids_page(3, 2)?.is_empty());
+
+        // Every memory appears exactly once across the pages
+        let mut expected: Vec<_> = stored.into_iter().map(|m| m.id).collect();
+        expected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        paged.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        assert_eq!(paged, expected);
+
+        Ok(())
+    }
+
+    fn check_category_and_mode_lookups(store: &MemoryStore) -> Result<()> {
+        for (content, category, mode) in [
+            ("first decision", "decision", "architect"),
+            ("a bug", "issue", "debug"),
+            ("second decision", "decision", "code"),
+            ("third decision", "decision", "architect"),
+        ] {
+            store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                Some(category.to_string()),
+                Some(mode.to_string()),
+                HashMap::new(),
+            )?;
+        }
+
+        let contents = |memories: Vec<Memory>| -> Vec<String> {
+            memories.into_iter().map(|m| m.content).collect()
+        };
+        assert_eq!(
+            contents(store.get_by_category("decision", 0, 10)?),
+            ["first decision", "second decision", "third decision"]
+        );
+        assert_eq!(
+            contents(store.get_by_category("decision", 1, 2)?),
+            ["third decision"]
+        );
+        assert!(store.get_by_category("missing", 0, 10)?.is_empty());
+        assert_eq!(
+            contents(store.get_by_mode("architect", 0, 10)?),
+            ["first decision", "third decision"]
+        );
+        assert_eq!(contents(store.get_by_mode("debug", 0, 1)?), ["a bug"]);
+        Ok(())
+    }
+
+    fn check_stats(store: &MemoryStore) -> Result<()> {
+        for (content, category, mode) in [
+            ("alpha beta", Some("decision"), Some("code")),
+            ("gamma", Some("decision"), None),
+            ("delta epsilon zeta", None, Some("code")),
+            ("eta", Some(UNCATEGORIZED), Some("debug")),
+        ] {
+            store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                category.map(str::to_string),
+                mode.map(str::to_string),
+                HashMap::new(),
+            )?;
+        }
+
+        let stats = store.get_stats()?;
+        assert_eq!(stats.total_count, 4);
+        assert_eq!(stats.total_tokens, store.get_total_tokens()?.as_usize());
+        assert_eq!(stats.by_category.len(), 2);
+        assert_eq!(stats.by_category["decision"].memory_count, 2);
+        // Explicitly uncategorized memories share the key of memories without a category
+        assert_eq!(stats.by_category[UNCATEGORIZED].memory_count, 2);
+        assert_eq!(stats.by_mode["code"].memory_count, 2);
+        assert_eq!(stats.by_mode[NO_MODE].memory_count, 1);
+        assert_eq!(
+            stats.by_mode.values().map(|m| m.token_count).sum::<usize>(),
+            stats.total_tokens
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats_in_memory() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        assert_eq!(store.get_stats()?, MemoryStats::default());
+        check_stats(&store)
+    }
+
+    #[test]
+    fn test_stats_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        assert_eq!(store.get_stats()?, MemoryStats::default());
+        check_stats(&store)
+    }
+
+    #[test]
+    fn test_category_and_mode_lookups_in_memory() -> Result<()> {
+        check_category_and_mode_lookups(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_category_and_mode_lookups_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        check_category_and_mode_lookups(&store)
+    }
+
+    #[test]
+    fn test_bulk_store_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+
+        let memories = store.bulk_store(bulk_items(&["first entry", "second entry"]))?;
+        assert_eq!(memories.len(), 2);
+        assert_eq!(store.get_ids_page(0, usize::MAX)?.len(), 2);
+        assert_eq!(
+            store.get_total_tokens()?.as_usize(),
+            memories
+                .iter()
+                .map(|memory| memory.token_count.as_usize())
+                .sum::<usize>()
+        );
+
+        store.cache.lock().unwrap().clear();
+        let retrieved = store.retrieve(&memories[1].id)?.unwrap();
+        assert_eq!(retrieved.content, "second entry");
+        assert_eq!(retrieved.category.as_deref(), Some("progress"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_store_with_tags_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+
+        let items = bulk_items(&["tagged entry", "untagged entry"])
+            .into_iter()
+            .zip([vec!["rust".to_string(), "async".to_string()], Vec::new()])
+            .collect();
+        let memories = store.bulk_store_with_tags(items)?;
+        assert_eq!(memories[0].tags, vec!["async", "rust"]);
+
+        store.cache.lock().unwrap().clear();
+        assert_eq!(
+            store.retrieve(&memories[0].id)?.unwrap().tags,
+            vec!["async", "rust"]
+        );
+        assert!(store.retrieve(&memories[1].id)?.unwrap().tags.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_store_rolls_back_on_failure() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+
+        // The repeated ID makes the second insert fail
+        let memory = Memory::new(
+            "duplicate".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+            &store.tokenizer,
+        );
+        let other = Memory::new(
+            "other".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+            &store.tokenizer,
+        );
+        assert!(store
+            .repository
+            .store_batch(&[other.clone(), memory.clone(), memory])
+            .is_err());
+
+        assert!(store.get_ids_page(0, usize::MAX)?.is_empty());
+        assert!(store.retrieve(&other.id)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bulk_store_nests_in_open_snapshot() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+
+        // No transaction can begin inside the snapshot's savepoint
+        let snapshot = store.snapshot()?;
+        store.bulk_store(bulk_items(&["first entry", "second entry"]))?;
+        assert_eq!(store.get_ids_page(0, usize::MAX)?.len(), 2);
+
+        store.rollback_snapshot(&snapshot)?;
+        assert!(store.get_ids_page(0, usize::MAX)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        store
+            .cache
+            .lock()
+            .unwrap()
+            .resize(NonZeroUsize::new(2).unwrap());
+
+        let store_memory = |content: &str| {
+            store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+        };
+        let first = store_memory("first")?;
+        let second = store_memory("second")?;
+
+        // Using the first memory makes the second the least recently used
+        store.retrieve(&first.id)?;
+        store_memory("third")?;
+
+        let stats = store.cache_stats();
+        assert_eq!((stats.entries, stats.capacity), (2, 2));
+        assert!(store.cache.lock().unwrap().contains(&first.id));
+        assert!(!store.cache.lock().unwrap().contains(&second.id));
+
+        // Evicted memories are still in the repository
+        assert_eq!(store.retrieve(&second.id)?.unwrap().content, "second");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_post_store_processor_tags_language() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        store.add_post_store_processor(Arc::new(LanguageTagger));
+
+        let memory = store.store(
+            "def handler(event):\n    return event".to_string(),
+            "text/x-python".to_string(),
+            None,
+            Some("code".to_string()),
+            HashMap::new(),
+        )?;
+        assert_eq!(memory.metadata.get("language"), Some(&"python".to_string()));
+
+        // The tag is persisted, not just returned
+        store.cache.lock().unwrap().clear();
+        let retrieved = store.retrieve(&memory.id)?.unwrap();
+        assert_eq!(
+            retrieved.metadata.get("language"),
+            Some(&"python".to_string())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_memory() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        let mut metadata = HashMap::new();
+        metadata.insert("author".to_string(), "alice".to_string());
+        let source = store.store(
+            "Use SQLite for persistence".to_string(),
+            "text/plain".to_string(),
+            Some("decision".to_string()),
+            Some("architect".to_string()),
+            metadata,
+        )?;
+
+        let copy = store
+            .copy_memory(&source.id, Some("context".to_string()), None, true)?
+            .unwrap();
+        assert_ne!(copy.id, source.id);
+        assert_eq!(copy.content, source.content);
+        assert_eq!(copy.category.as_deref(), Some("context"));
+        assert_eq!(copy.mode.as_deref(), Some("architect"));
+        assert_eq!(copy.metadata.get("author"), Some(&"alice".to_string()));
+        assert_eq!(
+            copy.metadata.get("copy_source_id"),
+            Some(&source.id.as_str().to_string())
+        );
+
+        // The original is left untouched
+        let original = store.retrieve(&source.id)?.unwrap();
+        assert_eq!(original.category.as_deref(), Some("decision"));
+
+        let bare = store.copy_memory(&source.id, None, None, false)?.unwrap();
+        assert_eq!(bare.metadata.len(), 1);
+
+        assert!(store
+            .copy_memory(&MemoryId::from("mem_missing"), None, None, true)?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_total_tokens_cached_and_reconciled() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        store_samples(&store)?;
+
+        let expected = store.repository.total_tokens()?;
+        assert_eq!(store.get_total_tokens()?, expected);
+
+        // Writes after the first read update the cached total
+        let memory = store.store(
+            "one more memory".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+        )?;
+        assert_eq!(store.get_total_tokens()?, expected + memory.token_count);
+
+        // Drift is corrected on the next reconciliation
+        store.token_total.store(0, Ordering::Release);
+        for _ in 0..TOKEN_RECONCILE_INTERVAL {
+            store.store(
+                "x".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )?;
+        }
+        assert_eq!(store.get_total_tokens()?, store.repository.total_tokens()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_overwrite_replaces_the_old_tokens() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        let memory = store.store(
+            "short".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+        )?;
+        assert_eq!(store.get_total_tokens()?, memory.token_count);
+
+        let mut replacement = memory.clone();
+        replacement.content = "a considerably longer replacement".to_string();
+        replacement.token_count = Tokenizer::default().count_tokens(&replacement.content);
+        let replaced_tokens = store.overwrite(&replacement)?;
+        assert_eq!(replaced_tokens, memory.token_count.as_usize() as i64);
+        store.adjust_token_total(replacement.counted_tokens() - replaced_tokens)?;
+
+        assert_eq!(store.get_total_tokens()?, replacement.token_count);
+        assert_eq!(store.repository.total_tokens()?, replacement.token_count);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_between_stores() -> Result<()> {
+        let local = MemoryStore::new_in_memory(Tokenizer::default());
+        let remote = MemoryStore::new_in_memory(Tokenizer::default());
+        store_samples(&local)?;
+
+        let payload = encode_memories(&local.export_memories(&[])?)?;
+        let stats =
+            remote.import_memories(decode_memories(&payload)?, ConflictResolution::NewerWins)?;
+        assert_eq!(stats.imported, 3);
+        assert_eq!(remote.get_total_tokens()?, local.get_total_tokens()?);
+
+        // Identical memories are not conflicts
+        let stats =
+            remote.import_memories(local.export_memories(&[])?, ConflictResolution::NewerWins)?;
+        assert_eq!(stats, ImportStats::default());
+
+        // An older edit loses under newer_wins but wins when preferred
+        let mut stale = local.export_memories(&[])?.remove(0);
+        stale.content = "stale edit".to_string();
+        stale.last_accessed -= chrono::Duration::hours(1);
+        let stats = remote.import_memories(vec![stale.clone()], ConflictResolution::NewerWins)?;
+        assert_eq!((stats.imported, stats.conflicts_resolved), (0, 1));
+
+        let stats =
+            remote.import_memories(vec![stale.clone()], ConflictResolution::PreferIncoming)?;
+        assert_eq!((stats.imported, stats.conflicts_resolved), (1, 1));
+        assert_eq!(remote.retrieve(&stale.id)?.unwrap().content, "stale edit");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_export_round_trip() -> Result<()> {
+        let dir = tempdir()?;
+        let local = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        store_samples(&local)?;
+
+        let mut json = Vec::new();
+        assert_eq!(local.export_json(&mut json, None)?, 3);
+        let entries: Vec<serde_json::Value> = serde_json::from_slice(&json)?;
+        assert_eq!(entries.len(), 3);
+        for field in ["created_at", "last_accessed"] {
+            let timestamp = entries[0][field].as_str().unwrap();
+            assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+        }
+
+        let remote = MemoryStore::new_in_memory(Tokenizer::default());
+        let result = remote.import_json(&mut json.as_slice(), ConflictResolution::Fail)?;
+        assert_eq!(
+            result,
+            ImportResult {
+                imported: 3,
+                ..Default::default()
+            }
+        );
+        for memory in local.export_memories(&[])? {
+            let imported = remote.retrieve(&memory.id)?.unwrap();
+            assert_eq!(imported.content, memory.content);
+            assert_eq!(imported.created_at, memory.created_at);
+        }
+        assert_eq!(remote.get_total_tokens()?, local.get_total_tokens()?);
+
+        let filter = MemoryFilter {
+            content_contains: Some("main".to_string()),
+            ..Default::default()
+        };
+        let mut json = Vec::new();
+        assert_eq!(local.export_json(&mut json, Some(&filter))?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_import_conflict_resolution() -> Result<()> {
+        let local = MemoryStore::new_in_memory(Tokenizer::default());
+        store_samples(&local)?;
+        let mut original = Vec::new();
+        local.export_json(&mut original, None)?;
+
+        // Edit one memory and add an entry that is not a memory
+        let mut entries: Vec<serde_json::Value> = serde_json::from_slice(&original)?;
+        entries[0]["content"] = "edited".into();
+        let edited_id = MemoryId::from(entries[0]["id"].as_str().unwrap().to_string());
+        entries.push(serde_json::json!({ "content": 1 }));
+        let edited = serde_json::to_vec(&entries)?;
+
+        let remote = MemoryStore::new_in_memory(Tokenizer::default());
+        remote.import_json(&mut original.as_slice(), ConflictResolution::Fail)?;
+
+        let result =
+            remote.import_json(&mut edited.as_slice(), ConflictResolution::KeepExisting)?;
+        assert_eq!((result.imported, result.skipped), (0, 3));
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].starts_with("Entry 3"));
+        assert_ne!(remote.retrieve(&edited_id)?.unwrap().content, "edited");
+
+        let result =
+            remote.import_json(&mut edited.as_slice(), ConflictResolution::PreferIncoming)?;
+        assert_eq!((result.imported, result.skipped), (1, 2));
+        assert_eq!(remote.retrieve(&edited_id)?.unwrap().content, "edited");
+
+        // A conflict aborts the import before anything is written
+        let error = remote
+            .import_json(&mut original.as_slice(), ConflictResolution::Fail)
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ImportConflict>(),
+            Some(&ImportConflict(edited_id.clone()))
+        );
+        assert_eq!(remote.retrieve(&edited_id)?.unwrap().content, "edited");
+
+        assert!(remote
+            .import_json(&mut "not json".as_bytes(), ConflictResolution::Fail)
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_idempotent_survives_restart() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("memories.db");
+        let store_with_key = |store: &MemoryStore| {
+            store.store_idempotent("request-1", || {
+                store.store(
+                    "Decision: use gRPC for transport".to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    HashMap::new(),
+                )
+            })
+        };
+
+        let store = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?;
+        let first = store_with_key(&store)?;
+        assert_eq!(store_with_key(&store)?.id, first.id);
+        drop(store);
+
+        // The key is persisted, so a retry after a restart is still deduplicated
+        let store = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?;
+        assert_eq!(store_with_key(&store)?.id, first.id);
+        assert_eq!(store.get_ids_page(0, usize::MAX)?.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        let original = store.store(
+            "short".to_string(),
+            "text/plain".to_string(),
+            Some("decision".to_string()),
+            None,
+            HashMap::from([("author".to_string(), "sam".to_string())]),
+        )?;
+        let total = store.get_total_tokens()?.as_usize();
+
+        let content = "a considerably longer replacement for the original content".to_string();
+        let updated = store.update(&original.id, content.clone(), None)?.unwrap();
+
+        // Re-read from the repository rather than the cache
+        store.rebuild_cache_from_db()?;
+        let reloaded = store.retrieve(&original.id)?.unwrap();
+        assert_eq!(reloaded.content, content);
+        assert_eq!(reloaded.metadata, original.metadata);
+        assert_eq!(reloaded.category, original.category);
+        assert_eq!(reloaded.created_at, original.created_at);
+        assert_eq!(reloaded.token_count, updated.token_count);
+        // The history version keeping the original content does not count towards the total
+        assert_eq!(
+            store.get_total_tokens()?.as_usize(),
+            total - original.token_count.as_usize() + updated.token_count.as_usize()
+        );
+
+        let updated = store
+            .update(&original.id, content, Some(HashMap::new()))?
+            .unwrap();
+        assert!(updated.metadata.is_empty());
+
+        assert!(store
+            .update(&MemoryId::from("mem_missing"), String::new(), None)?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_updates_keep_history_for_rollback() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        let memory = store.store(
+            "first draft".to_string(),
+            "text/markdown".to_string(),
+            Some("decision".to_string()),
+            Some("architect".to_string()),
+            HashMap::new(),
+        )?;
+        for content in ["second draft", "third draft", "fourth draft"] {
+            store.update(&memory.id, content.to_string(), None)?;
+        }
+
+        let history = store.get_history(&memory.id)?;
+        let versions: Vec<(&str, &str)> = history
+            .iter()
+            .map(|version| {
+                (
+                    version.content.as_str(),
+                    version.metadata["version"].as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            versions,
+            vec![
+                ("first draft", "1"),
+                ("second draft", "2"),
+                ("third draft", "3")
+            ]
+        );
+        for version in &history {
+            assert_eq!(version.category.as_deref(), Some(HISTORY_CATEGORY));
+            assert_eq!(version.metadata["parent_id"], memory.id.as_str());
+            assert_eq!(version.mode.as_deref(), Some("architect"));
+            assert_eq!(version.content_type, "text/markdown");
+        }
+
+        let rolled_back = store.rollback_to_version(&memory.id, 1)?;
+        assert_eq!(rolled_back.content, "first draft");
+        assert_eq!(rolled_back.id, memory.id);
+        assert_eq!(store.retrieve(&memory.id)?.unwrap().content, "first draft");
+        // The rollback can be undone from the version it added
+        let history = store.get_history(&memory.id)?;
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[3].content, "fourth draft");
+
+        let error = store.rollback_to_version(&memory.id, 9).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<UnknownVersion>(),
+            Some(&UnknownVersion(memory.id.clone(), 9))
+        );
+        assert!(store
+            .rollback_to_version(&MemoryId::from("mem_missing"), 1)
+            .unwrap_err()
+            .is::<UnknownVersion>());
+
+        Ok(())
+    }
+
+    fn check_delete_removes_history(store: &MemoryStore) -> Result<()> {
+        let memory = store.store(
+            "first draft".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+        )?;
+        let other = store.store(
+            "unrelated note".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+        )?;
+        for content in ["second draft", "third draft"] {
+            store.update(&memory.id, content.to_string(), None)?;
+        }
+        assert_eq!(store.get_history(&memory.id)?.len(), 2);
+
+        store.delete(&memory.id)?.unwrap();
+        assert!(store.get_history(&memory.id)?.is_empty());
+        assert_eq!(store.count()?, 1);
+        assert_eq!(store.get_total_tokens()?, other.token_count);
+
+        // Deleting a single version leaves the rest of the history alone
+        store.update(&other.id, "revised note".to_string(), None)?;
+        store.update(&other.id, "final note".to_string(), None)?;
+        let history = store.get_history(&other.id)?;
+        store.delete(&history[0].id)?.unwrap();
+        assert_eq!(store.get_history(&other.id)?.len(), 1);
+        assert!(store.retrieve(&other.id)?.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_removes_history() -> Result<()> {
+        check_delete_removes_history(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_delete_removes_history_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_delete_removes_history(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    fn check_history_is_hidden(store: &MemoryStore) -> Result<()> {
+        let memory = store.store(
+            "first draft".to_string(),
+            "text/plain".to_string(),
+            Some("decision".to_string()),
+            None,
+            HashMap::new(),
+        )?;
+        let updated = store
+            .update(&memory.id, "second draft".to_string(), None)?
+            .unwrap();
+        assert_eq!(store.get_history(&memory.id)?.len(), 1);
+
+        // Only the current content counts, both cached and as read back from the repository
+        assert_eq!(store.get_total_tokens()?, updated.token_count);
+        assert_eq!(store.repository.total_tokens()?, updated.token_count);
+        let stats = store.get_stats()?;
+        assert_eq!(stats.total_count, 1);
+        assert_eq!(stats.total_tokens, updated.token_count.as_usize());
+        assert!(!stats.by_category.contains_key(HISTORY_CATEGORY));
+
+        let (listed, total) = store.list(
+            &MemoryFilter::default(),
+            MemorySortField::default(),
+            SortOrder::default(),
+            0,
+            10,
+        )?;
+        assert_eq!(total, 1);
+        assert_eq!(listed[0].id, memory.id);
+        let history_only = MemoryFilter {
+            category: Some(HISTORY_CATEGORY.to_string()),
+            ..MemoryFilter::default()
+        };
+        assert_eq!(
+            store
+                .list(
+                    &history_only,
+                    MemorySortField::default(),
+                    SortOrder::default(),
+                    0,
+                    10
+                )?
+                .1,
+            0
+        );
+
+        assert!(store
+            .search("draft", None, 10)?
+            .iter()
+            .all(|m| m.id == memory.id));
+        let exported = store.export_memories(&[])?;
+        assert_eq!(exported.len(), 1);
+        assert!(store
+            .export_memories(&[HISTORY_CATEGORY.to_string()])?
+            .is_empty());
+
+        store.delete(&memory.id)?.unwrap();
+        assert_eq!(store.get_total_tokens()?.as_usize(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_history_is_hidden() -> Result<()> {
+        check_history_is_hidden(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_history_is_hidden_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_history_is_hidden(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    /// Store `contents` as notes in the code mode, each with one metadata entry
+    fn store_notes(store: &MemoryStore, contents: &[&str]) -> Result<Vec<Memory>> {
+        contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                store.store_with_options(
+                    content.to_string(),
+                    "text/markdown".to_string(),
+                    Some("progress".to_string()),
+                    Some("code".to_string()),
+                    HashMap::from([
+                        (format!("note_{}", i), "yes".to_string()),
+                        ("author".to_string(), format!("author {}", i)),
+                    ]),
+                    StoreOptions {
+                        tags: vec![format!("tag{}", i % 2)],
+                        ..StoreOptions::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_two_memories() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        let notes = store_notes(&store, &["first note", "second note"])?;
+        let ids: Vec<MemoryId> = notes.iter().map(|note| note.id.clone()).collect();
+
+        let merged = store.merge_memories(&ids, "\n---\n")?;
+
+        assert_eq!(merged.content, "first note\n---\nsecond note");
+        assert_eq!(
+            merged.token_count,
+            Tokenizer::default().count_tokens(&merged.content)
+        );
+        assert_eq!(merged.content_type, "text/markdown");
+        assert_eq!(merged.category.as_deref(), Some("progress"));
+        assert_eq!(merged.mode.as_deref(), Some("code"));
+        assert_eq!(merged.metadata["note_0"], "yes");
+        assert_eq!(merged.metadata["note_1"], "yes");
+        assert_eq!(merged.metadata["author"], "author 1");
+        assert_eq!(merged.tags, vec!["tag0", "tag1"]);
+
+        // Read back from the database, not the cache
+        store.cache.lock().unwrap().clear();
+        assert_eq!(store.retrieve(&merged.id)?.unwrap().content, merged.content);
+        for id in &ids {
+            assert!(store.retrieve(id)?.is_none());
+        }
+        assert_eq!(store.count()?, 1);
+        assert_eq!(store.get_total_tokens()?, merged.token_count);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_ten_memories_in_order() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        let contents: Vec<String> = (0..10).map(|i| format!("note number {}", i)).collect();
+        let contents: Vec<&str> = contents.iter().map(String::as_str).collect();
+        let notes = store_notes(&store, &contents)?;
+        // Merge newest first to show the given order is kept
+        let ids: Vec<MemoryId> = notes.iter().rev().map(|note| note.id.clone()).collect();
+
+        let merged = store.merge_memories_into(&ids, " | ", Some("summary".to_string()))?;
+
+        let expected: Vec<&str> = contents.iter().rev().copied().collect();
+        assert_eq!(merged.content, expected.join(" | "));
+        assert_eq!(merged.category.as_deref(), Some("summary"));
+        assert_eq!(merged.metadata.len(), 11);
+        assert_eq!(merged.metadata["author"], "author 0");
+        assert_eq!(store.count()?, 1);
+        assert_eq!(store.get_total_tokens()?, merged.token_count);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_with_missing_memory_changes_nothing() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        let notes = store_notes(&store, &["kept one", "kept two"])?;
+        let tokens = store.get_total_tokens()?;
+        let missing = MemoryId::from("mem_missing");
+        let ids = vec![notes[0].id.clone(), missing.clone(), notes[1].id.clone()];
+
+        let error = store.merge_memories(&ids, "\n").unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<UnknownMemory>(),
+            Some(&UnknownMemory(missing))
+        );
+        assert_eq!(store.count()?, 2);
+        assert_eq!(store.get_total_tokens()?, tokens);
+        for note in &notes {
+            assert_eq!(store.retrieve(&note.id)?.unwrap().content, note.content);
+        }
+
+        // Too few or repeated IDs are refused as well
+        assert!(store.merge_memories(&ids[..1], "\n").is_err());
+        assert!(store
+            .merge_memories(&[notes[0].id.clone(), notes[0].id.clone()], "\n")
+            .is_err());
+        assert_eq!(store.count()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_and_merge_duplicates() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        let base = "the service retries failed uploads three times with exponential backoff \
+                    before it gives up and reports the error to the caller";
+        let notes = store_notes(
+            &store,
+            &[
+                base,
+                &format!("{} immediately", base),
+                "the cache is invalidated whenever a memory is updated or deleted",
+                "the cache is invalidated whenever a memory is updated or deleted or renamed",
+                "tokens are counted with the configured tokenizer",
+            ],
+        )?;
+        // The old version kept in history is not reported as a duplicate
+        store.update(&notes[4].id, format!("{} again", notes[4].content), None)?;
+
+        let groups = store.find_duplicates(0.8)?;
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].primary_id, notes[1].id);
+        assert_eq!(groups[0].duplicate_ids, vec![notes[0].id.clone()]);
+        assert_eq!(groups[0].similarity, 19.0 / 20.0);
+        assert_eq!(groups[1].primary_id, notes[3].id);
+        assert_eq!(groups[1].duplicate_ids, vec![notes[2].id.clone()]);
+        assert_eq!(store.find_duplicates(0.95)?.len(), 1);
+        assert!(store
+            .find_duplicates_where(0.8, |memory| memory.id != notes[0].id)?
+            .iter()
+            .all(|group| group.primary_id != notes[1].id));
+
+        let tokens = store.get_total_tokens()?.as_usize();
+        let (primary, duplicates) = store.merge_duplicates(&groups[0], "\n\n")?;
+        assert_eq!(
+            store.get_total_tokens()?.as_usize(),
+            tokens + primary.token_count.as_usize()
+                - notes[0].token_count.as_usize()
+                - notes[1].token_count.as_usize()
+        );
+        assert_eq!(primary.id, notes[1].id);
+        // The primary already holds its duplicate's content, so keeps it once
+        assert_eq!(primary.content, notes[1].content);
+        assert_eq!(primary.metadata.get(DUPLICATE_COUNT_KEY).unwrap(), "1");
+        assert_eq!(primary.tags, vec!["tag0".to_string(), "tag1".to_string()]);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].id, notes[0].id);
+        assert!(store.retrieve(&notes[0].id)?.is_none());
+        assert_eq!(
+            store.get_tags_for_memory(&primary.id)?,
+            vec!["tag0".to_string(), "tag1".to_string()]
+        );
+
+        // Content the primary lacks is appended after the separator
+        let (primary, _) = store.merge_duplicates(
+            &DuplicateGroup {
+                primary_id: notes[2].id.clone(),
+                duplicate_ids: vec![notes[3].id.clone()],
+                similarity: groups[1].similarity,
+            },
+            "\n\n",
+        )?;
+        assert_eq!(
+            primary.content,
+            format!("{}\n\n{}", notes[2].content, notes[3].content)
+        );
+
+        // The count includes what each duplicate had absorbed before
+        let (primary, _) = store.merge_duplicates(
+            &DuplicateGroup {
+                primary_id: notes[1].id.clone(),
+                duplicate_ids: vec![notes[2].id.clone()],
+                similarity: 0.0,
+            },
+            "\n\n",
+        )?;
+        assert_eq!(primary.metadata.get(DUPLICATE_COUNT_KEY).unwrap(), "3");
+
+        // The merged memory, the updated note and its history version
+        assert_eq!(store.count()?, 3);
+
+        // A group naming a memory that is gone changes nothing
+        let stale = DuplicateGroup {
+            primary_id: notes[1].id.clone(),
+            duplicate_ids: vec![notes[0].id.clone()],
+            similarity: 1.0,
+        };
+        let error = store.merge_duplicates(&stale, "\n\n").unwrap_err();
+        assert!(error.downcast_ref::<UnknownMemory>().is_some());
+        assert_eq!(store.count()?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_memories_are_not_returned() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        let store_note = |ttl_seconds| {
+            store.store_with_ttl(
+                "temporary note".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+                ttl_seconds,
+            )
+        };
+        let expiring = store_note(Some(1))?;
+        let lasting = store_note(None)?;
+        assert!(store.retrieve(&expiring.id)?.is_some());
+
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+
+        // The cached copy is invalidated on access
+        assert!(store.retrieve(&expiring.id)?.is_none());
+        assert!(store.retrieve(&lasting.id)?.is_some());
+        assert_eq!(store.get_total_tokens()?, lasting.token_count);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_related() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        let ids: Vec<MemoryId> = [
+            "the sqlite connection pool is exhausted under load",
+            "raise the sqlite connection pool size for load tests",
+            "sqlite connection pool timeouts during load",
+            "write release notes for the next version",
+            "plan the team offsite agenda",
+        ]
+        .iter()
+        .map(|content| {
+            store
+                .store(
+                    content.to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    HashMap::new(),
+                )
+                .map(|memory| memory.id)
+        })
+        .collect::<Result<_>>()?;
+        let scorer = crate::storage::TfIdfScorer::new();
+
+        let related: HashSet<MemoryId> = store
+            .get_related(&ids[0], &scorer, 2)?
+            .into_iter()
+            .map(|scored| scored.memory.id)
+            .collect();
+        assert_eq!(related, HashSet::from([ids[1].clone(), ids[2].clone()]));
+
+        // The target is never related to itself, and unrelated memories rank last
+        let all = store.get_related(&ids[0], &scorer, 10)?;
+        assert_eq!(all.len(), 4);
+        assert!(all.iter().all(|scored| scored.memory.id != ids[0]));
+        assert!(all[2].score < all[1].score);
+
+        assert!(store
+            .get_related(&MemoryId::from("mem_missing"), &scorer, 2)
+            .is_err());
+
+        Ok(())
+    }
+
+    /// Store memories tagged as given, returning their IDs
+    fn store_tagged(store: &MemoryStore, tags: &[&[&str]]) -> Result<Vec<MemoryId>> {
+        tags.iter()
+            .map(|tags| {
+                let options = StoreOptions {
+                    tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                    ..StoreOptions::default()
+                };
+                let memory = store.store_with_options(
+                    "tagged note".to_string(),
+                    "text/plain".to_string(),
+                    None,
+                    None,
+                    HashMap::new(),
+                    options,
+                )?;
+                Ok(memory.id)
+            })
+            .collect()
+    }
+
+    fn check_tags(store: &MemoryStore) -> Result<()> {
+        let ids = store_tagged(
+            store,
+            &[&["rust", " grpc ", "rust"], &["rust"], &["python"]],
+        )?;
+        assert_eq!(store.get_tags_for_memory(&ids[0])?, vec!["grpc", "rust"]);
+        assert_eq!(store.retrieve(&ids[0])?.unwrap().tags, vec!["grpc", "rust"]);
+
+        let ids_of = |memories: Vec<Memory>| -> Vec<MemoryId> {
+            memories.into_iter().map(|memory| memory.id).collect()
+        };
+        let tags = |tags: &[&str]| -> Vec<String> { tags.iter().map(|t| t.to_string()).collect() };
+        assert_eq!(ids_of(store.get_by_tag("rust", 0, 10)?), ids[..2]);
+        assert_eq!(
+            ids_of(store.filter_by_tags(&tags(&["rust", "grpc"]), true)?),
+            ids[..1]
+        );
+        assert_eq!(
+            ids_of(store.filter_by_tags(&tags(&["grpc", "python"]), false)?),
+            [ids[0].clone(), ids[2].clone()]
+        );
+        assert!(store
+            .filter_by_tags(&tags(&["grpc", "python"]), true)?
+            .is_empty());
+        assert!(store.filter_by_tags(&[], false)?.is_empty());
+
+        // Tag changes reach both the repository and the cached copy
+        assert_eq!(
+            store.add_tag(&ids[1], "grpc")?.unwrap().tags,
+            vec!["grpc", "rust"]
+        );
+        assert_eq!(store.retrieve(&ids[1])?.unwrap().tags, vec!["grpc", "rust"]);
+        assert_eq!(
+            store.remove_tag(&ids[0], "rust")?.unwrap().tags,
+            vec!["grpc"]
+        );
+        assert_eq!(ids_of(store.get_by_tag("rust", 0, 10)?), ids[1..2]);
+        assert!(store
+            .add_tag(&MemoryId::from("mem_missing"), "rust")?
+            .is_none());
+        assert!(store.add_tag(&ids[0], " ").is_err());
+
+        // Deleting a memory deletes its tags
+        store.delete(&ids[1])?;
+        assert!(store.get_tags_for_memory(&ids[1])?.is_empty());
+        assert!(store.get_by_tag("rust", 0, 10)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tags() -> Result<()> {
+        check_tags(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_tags_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_tags(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    /// Merge a category into another and check that the moved memories are otherwise unchanged
+    fn check_merge_categories(store: &MemoryStore) -> Result<()> {
+        let store_in = |category: &str| {
+            store.store_with_options(
+                format!("{} note", category),
+                "text/plain".to_string(),
+                Some(category.to_string()),
+                Some("code".to_string()),
+                HashMap::from([("source".to_string(), "test".to_string())]),
+                StoreOptions {
+                    tags: vec!["merge".to_string()],
+                    ..StoreOptions::default()
+                },
+            )
+        };
+        let moved = [store_in("ctx")?, store_in("ctx")?];
+        store_in("context")?;
+        store_in("decision")?;
+
+        // Load one moved memory into the cache first
+        store.retrieve(&moved[0].id)?;
+        assert_eq!(store.merge_categories("ctx", "context")?, 2);
+
+        for memory in &moved {
+            let merged = store.retrieve(&memory.id)?.unwrap();
+            assert_eq!(merged.category.as_deref(), Some("context"));
+            assert_eq!(merged.content, memory.content);
+            assert_eq!(merged.mode, memory.mode);
+            assert_eq!(merged.metadata, memory.metadata);
+            assert_eq!(merged.tags, memory.tags);
+            assert_eq!(merged.token_count, memory.token_count);
+        }
+
+        let counts = store.count_by_category()?;
+        assert_eq!(counts.get("context"), Some(&3));
+        assert_eq!(counts.get("decision"), Some(&1));
+        assert!(!counts.contains_key("ctx"));
+        assert!(store.get_by_category("ctx", 0, 10)?.is_empty());
+
+        assert_eq!(store.rename_category("decision", "decisions")?, 1);
+        assert_eq!(store.rename_category("missing", "decisions")?, 0);
+        assert!(store.merge_categories("", "context").is_err());
+
+        let archived = store.set_category(&moved[1].id, "archived")?.unwrap();
+        assert_eq!(archived.category.as_deref(), Some("archived"));
+        assert_eq!(
+            store.retrieve(&moved[1].id)?.unwrap().category.as_deref(),
+            Some("archived")
+        );
+        assert!(store
+            .set_category(&MemoryId::from("mem_missing".to_string()), "archived")?
+            .is_none());
+        assert!(store.set_category(&moved[0].id, "").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_categories() -> Result<()> {
+        check_merge_categories(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_merge_categories_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_merge_categories(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    fn check_preferred_ids(store: &MemoryStore) -> Result<()> {
+        let store_as = |preferred_id: &str| {
+            store.store_with_options(
+                "Use hexagonal architecture".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+                StoreOptions {
+                    preferred_id: Some(preferred_id.to_string()),
+                    ..StoreOptions::default()
+                },
+            )
+        };
+
+        let first = store_as("architecture")?;
+        assert_eq!(first.id.as_str(), "architecture");
+
+        // A taken ID falls back to the slug with a random suffix
+        let second = store_as("architecture")?;
+        let suffix = second
+            .id
+            .as_str()
+            .strip_prefix("mem_architecture-")
+            .unwrap();
+        assert_eq!(suffix.len(), 4);
+        assert!(store.retrieve(&first.id)?.is_some());
+        assert!(store.retrieve(&second.id)?.is_some());
+
+        let error = store.repository.insert(&second).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<DuplicateMemoryId>(),
+            Some(&DuplicateMemoryId(second.id.clone()))
+        );
+
+        assert!(store_as("Not A Slug").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_preferred_ids() -> Result<()> {
+        check_preferred_ids(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_preferred_ids_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_preferred_ids(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    #[test]
+    fn test_new_with_slug() {
+        let id = MemoryId::new_with_slug("Architecture Decisions!");
+        assert!(id.as_str().starts_with("mem_architecture-decisions-"));
+        assert_eq!(sanitize_slug("  Über__café  v2 "), "ber-caf-v2");
+        assert_eq!(sanitize_slug(&"x".repeat(40)).len(), 32);
+
+        // Nothing left to use as a slug
+        assert!(!MemoryId::new_with_slug("日本語").as_str().contains('-'));
+        assert!(MemoryId::is_valid_slug("release-notes-2"));
+        assert!(!MemoryId::is_valid_slug("release--notes"));
+        assert!(!MemoryId::is_valid_slug(""));
+    }
+
+    proptest! {
+        #[test]
+        fn fuzz_sanitize_slug(slug in "\\PC{0,64}") {
+            let sanitized = sanitize_slug(&slug);
+            prop_assert!(sanitized.is_empty() || MemoryId::is_valid_slug(&sanitized));
+            prop_assert_eq!(sanitize_slug(&sanitized), sanitized.clone());
+
+            let id = MemoryId::new_with_slug(&slug);
+            if !sanitized.is_empty() {
+                let rest = id.as_str().strip_prefix("mem_").unwrap();
+                prop_assert_eq!(rest.len(), sanitized.len() + 5);
+                prop_assert!(rest.starts_with(sanitized.as_str()));
+            }
+        }
+
+        #[test]
+        fn fuzz_valid_slugs_survive_sanitizing(slug in "[a-z0-9]{1,8}(-[a-z0-9]{1,8}){0,2}") {
+            prop_assert!(MemoryId::is_valid_slug(&slug));
+            prop_assert_eq!(sanitize_slug(&slug), slug);
+        }
+    }
+
+    #[test]
+    fn test_recalculate_tokens_after_tokenizer_change() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("memories.db");
+        let contents = [
+            "Internationalization requirements",
+            "Serialization benchmarks finished",
+            "Authentication middleware rewritten",
+        ];
+
+        let store = MemoryStore::new_sqlite(&db_path, Tokenizer::default())?;
+        let ids = contents
+            .iter()
+            .map(|content| {
+                store
+                    .store(
+                        content.to_string(),
+                        "text/plain".to_string(),
+                        None,
+                        None,
+                        HashMap::new(),
+                    )
+                    .map(|memory| memory.id)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let simple_counts = ids
+            .iter()
+            .map(|id| Ok(store.retrieve(id)?.unwrap().token_count))
+            .collect::<Result<Vec<_>>>()?;
+        drop(store);
+
+        let cl100k = Tokenizer::approximate(TokenizerType::Cl100k);
+        let store = MemoryStore::new_sqlite(&db_path, cl100k.clone())?;
+        assert_eq!(store.recalculate_tokens()?, 3);
+
+        for ((id, content), simple_count) in ids.iter().zip(contents).zip(simple_counts) {
+            let token_count = store.retrieve(id)?.unwrap().token_count;
+            assert_eq!(token_count, cl100k.count_tokens(content));
+            assert_ne!(token_count, simple_count);
+        }
+        assert_eq!(
+            store.get_total_tokens()?.as_usize(),
+            contents
+                .iter()
+                .map(|content| cl100k.count_tokens(content).as_usize())
+                .sum::<usize>()
+        );
+
+        // Counts that already match the tokenizer are left alone
+        assert_eq!(store.recalculate_tokens()?, 0);
+
+        Ok(())
+    }
+
+    fn check_list(store: &MemoryStore) -> Result<()> {
+        let store_in = |content: &str, category: &str, mode: &str| {
+            store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                Some(category.to_string()),
+                Some(mode.to_string()),
+                HashMap::new(),
+            )
+        };
+        let memories = [
+            store_in("rust grpc server", "context", "code")?,
+            store_in("python notes", "context", "architect")?,
+            store_in("rust decision", "decision", "code")?,
+            store_in(
+                "rust progress log that runs on for a good many more words",
+                "progress",
+                "code",
+            )?,
+        ];
+        let ids = |listed: Vec<Memory>| -> Vec<MemoryId> {
+            listed.into_iter().map(|memory| memory.id).collect()
+        };
+        let ids_at = |indices: &[usize]| -> Vec<MemoryId> {
+            indices.iter().map(|&i| memories[i].id.clone()).collect()
+        };
+        let list = |filter: &MemoryFilter, sort, order, page, page_size| {
+            store.list(filter, sort, order, page, page_size)
+        };
+
+        let by_category = MemoryFilter {
+            category: Some("context".to_string()),
+            ..MemoryFilter::default()
+        };
+        let (listed, total) = list(
+            &by_category,
+            MemorySortField::CreatedAt,
+            SortOrder::Ascending,
+            0,
+            10,
+        )?;
+        assert_eq!((ids(listed), total), (ids_at(&[0, 1]), 2));
+
+        // Filters combine, and the decrypted content is searched
+        let rust_code = MemoryFilter {
+            mode: Some("code".to_string()),
+            content_contains: Some("rust".to_string()),
+            ..MemoryFilter::default()
+        };
+        let (listed, total) = list(
+            &rust_code,
+            MemorySortField::TokenCount,
+            SortOrder::Descending,
+            0,
+            10,
+        )?;
+        assert_eq!(total, 3);
+        assert_eq!(listed[0].id, memories[3].id);
+        assert!(listed
+            .windows(2)
+            .all(|pair| pair[0].token_count >= pair[1].token_count));
+
+        let (listed, total) = list(
+            &MemoryFilter::default(),
+            MemorySortField::CreatedAt,
+            SortOrder::Descending,
+            0,
+            10,
+        )?;
+        assert_eq!((ids(listed), total), (ids_at(&[3, 2, 1, 0]), 4));
+
+        // The total covers every page
+        let (listed, total) = list(
+            &MemoryFilter::default(),
+            MemorySortField::CreatedAt,
+            SortOrder::Ascending,
+            1,
+            3,
+        )?;
+        assert_eq!((ids(listed), total), (ids_at(&[3]), 4));
+
+        let one_day = MemoryFilter {
+            from_date: Some(memories[1].created_at),
+            to_date: Some(memories[2].created_at),
+            ..MemoryFilter::default()
+        };
+        let (listed, total) = list(
+            &one_day,
+            MemorySortField::CreatedAt,
+            SortOrder::Ascending,
+            0,
+            10,
+        )?;
+        assert_eq!((ids(listed), total), (ids_at(&[1, 2]), 2));
+
+        // Queries combine with the other filters and may test tags, tokens and decrypted content
+        store.add_tag(&memories[1].id, "notes")?;
+        let query = |expr: &str, category: Option<&str>| -> Result<Vec<MemoryId>> {
+            let filter = MemoryFilter {
+                category: category.map(str::to_string),
+                ..MemoryQuery::parse(expr)?
+            };
+            let (listed, total) = list(
+                &filter,
+                MemorySortField::CreatedAt,
+                SortOrder::Ascending,
+                0,
+                10,
+            )?;
+            assert_eq!(listed.len() as u64, total);
+            Ok(ids(listed))
+        };
+        assert_eq!(
+            query("mode:architect OR category:decision", None)?,
+            ids_at(&[1, 2])
+        );
+        assert_eq!(query("NOT mode:code", None)?, ids_at(&[1]));
+        assert_eq!(
+            query(r#"content_contains:"grpc" OR tag:notes"#, None)?,
+            ids_at(&[0, 1])
+        );
+        let shorter = memories[..3]
+            .iter()
+            .map(|memory| memory.token_count.as_usize())
+            .max()
+            .unwrap();
+        assert_eq!(query(&format!("tokens>{}", shorter), None)?, ids_at(&[3]));
+        assert_eq!(
+            query(&format!("tokens<{} AND tag:notes", shorter + 1), None)?,
+            ids_at(&[1])
+        );
+        assert_eq!(
+            query("mode:code AND created_after:2000-01-01", Some("context"))?,
+            ids_at(&[0])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list() -> Result<()> {
+        check_list(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_list_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_list(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    #[test]
+    fn test_list_encrypted() -> Result<()> {
+        let dir = tempdir()?;
+        let cipher = ContentCipher::new(&[7; 32])?;
+        check_list(
+            &MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?
+                .with_encryption(cipher),
+        )
+    }
+
+    fn check_count_and_connection(store: &MemoryStore) -> Result<()> {
+        assert!(store.check_connection()?);
+        assert_eq!(store.count()?, 0);
+        assert_eq!(store.get_total_tokens()?.as_usize(), 0);
+
+        let store_text = |content: &str| {
+            store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+        };
+        let first = store_text("first memory")?;
+        let second = store_text("second memory with more words")?;
+        assert_eq!(store.count()?, 2);
+        assert_eq!(
+            store.get_total_tokens()?,
+            first.token_count + second.token_count
+        );
+
+        store.delete(&first.id)?;
+        assert_eq!(store.count()?, 1);
+        assert_eq!(store.get_total_tokens()?, second.token_count);
+        assert!(store.check_connection()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_and_connection() -> Result<()> {
+        check_count_and_connection(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_count_and_connection_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_count_and_connection(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    fn check_snapshots(store: &MemoryStore) -> Result<()> {
+        let store_numbered = |i: usize| {
+            store.store(
+                format!("memory number {}", i),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+        };
+        for i in 0..5 {
+            store_numbered(i)?;
+        }
+        let tokens = store.get_total_tokens()?;
+
+        let snapshot = store.snapshot()?;
+        let speculative: Vec<Memory> = (5..8).map(store_numbered).collect::<Result<_>>()?;
+        assert_eq!(store.count()?, 8);
+
+        store.rollback_snapshot(&snapshot)?;
+        assert_eq!(store.count()?, 5);
+        assert_eq!(store.get_total_tokens()?, tokens);
+        assert!(store.retrieve(&speculative[0].id)?.is_none());
+        let error = store.rollback_snapshot(&snapshot).unwrap_err();
+        assert!(error.downcast_ref::<UnknownSnapshot>().is_some());
+
+        // Committing an outer snapshot keeps its writes and closes the inner one
+        let outer = store.snapshot()?;
+        store_numbered(8)?;
+        let inner = store.snapshot()?;
+        store_numbered(9)?;
+        store.commit_snapshot(&outer)?;
+        assert_eq!(store.count()?, 7);
+        assert!(store.rollback_snapshot(&inner).is_err());
+        assert_eq!(store.count()?, 7);
+
+        // Rolling back an outer snapshot forgets the inner one too
+        let outer = store.snapshot()?;
+        let inner = store.snapshot()?;
+        store.rollback_snapshot(&outer)?;
+        assert!(store.snapshots.lock().unwrap().is_empty());
+        let error = store.commit_snapshot(&inner).unwrap_err();
+        assert!(error.downcast_ref::<UnknownSnapshot>().is_some());
+
+        // An abandoned snapshot is rolled back once it times out
+        let abandoned = store.snapshot()?;
+        store_numbered(10)?;
+        assert_eq!(store.expire_snapshots()?, 0);
+        assert_eq!(
+            store.expire_snapshots_at(Instant::now() + SNAPSHOT_TIMEOUT)?,
+            1
+        );
+        assert_eq!(store.count()?, 7);
+        assert!(store.commit_snapshot(&abandoned).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_snapshots_in_memory() -> Result<()> {
+        check_snapshots(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_snapshots_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_snapshots(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    fn check_links(store: &MemoryStore) -> Result<()> {
+        let store_note = |content: &str| {
+            store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+        };
+        let context = store_note("context")?;
+        let decision = store_note("decision")?;
+        let progress = store_note("progress")?;
+        let missing = MemoryId::from("mem_missing");
+
+        assert!(store.link(&decision.id, &context.id, "references")?);
+        assert!(store.link(&progress.id, &decision.id, "references")?);
+        assert!(store.link(&progress.id, &context.id, "mentions")?);
+        assert!(store.link(&progress.id, &decision.id, "references")?);
+        assert!(!store.link(&progress.id, &missing, "references")?);
+        assert!(store.link(&progress.id, &decision.id, " ").is_err());
+
+        let ids = |memories: Vec<Memory>| -> Vec<MemoryId> {
+            memories.into_iter().map(|memory| memory.id).collect()
+        };
+        assert_eq!(
+            ids(store.get_linked(&progress.id, None)?),
+            vec![context.id.clone(), decision.id.clone()]
+        );
+        assert_eq!(
+            ids(store.get_linked(&progress.id, Some("references"))?),
+            vec![decision.id.clone()]
+        );
+        assert!(store.get_linked(&context.id, None)?.is_empty());
+
+        // Deleting a memory removes its links
+        store.delete(&decision.id)?;
+        assert_eq!(
+            ids(store.get_linked(&progress.id, None)?),
+            vec![context.id.clone()]
+        );
+
+        Ok(())
+    }
+
+    fn check_import_over_linked_memory_keeps_links(store: &MemoryStore) -> Result<()> {
+        let note = |content: &str| {
+            store.store(
+                content.to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+            )
+        };
+        let decision = note("Decision: use SQLite")?;
+        let progress = note("Progress: schema written")?;
+        assert!(store.link(&progress.id, &decision.id, "implements")?);
+
+        let mut incoming = decision.clone();
+        incoming.content = "Decision: use SQLite with WAL".to_string();
+        let stats = store.import_memories(vec![incoming], ConflictResolution::PreferIncoming)?;
+        assert_eq!(stats.imported, 1);
+
+        assert_eq!(
+            store.retrieve(&decision.id)?.unwrap().content,
+            "Decision: use SQLite with WAL"
+        );
+        let linked = store.get_linked(&progress.id, Some("implements"))?;
+        assert_eq!(linked.len(), 1);
+        assert_eq!(linked[0].id, decision.id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_over_linked_memory_keeps_links() -> Result<()> {
+        check_import_over_linked_memory_keeps_links(&MemoryStore::new_in_memory(
+            Tokenizer::default(),
+        ))
+    }
+
+    #[test]
+    fn test_import_over_linked_memory_keeps_links_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_import_over_linked_memory_keeps_links(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    #[test]
+    fn test_links() -> Result<()> {
+        check_links(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_links_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_links(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    fn check_circular_links_are_rejected(store: &MemoryStore) -> Result<()> {
+        let ids = (0..3)
+            .map(|i| {
+                store
+                    .store(
+                        format!("memory {}", i),
+                        "text/plain".to_string(),
+                        None,
+                        None,
+                        HashMap::new(),
+                    )
+                    .map(|memory| memory.id)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let circular = |source: usize, target: usize, relation: &str| {
+            store
+                .link(&ids[source], &ids[target], relation)
+                .unwrap_err()
+                .downcast::<CircularLink>()
+                .unwrap()
+        };
+
+        // A memory cannot link to itself
+        assert_eq!(
+            circular(0, 0, "references"),
+            CircularLink {
+                source: ids[0].clone(),
+                target: ids[0].clone(),
+                relation: "references".to_string(),
+            }
+        );
+
+        // Nor close a chain of links back to its start, directly or indirectly
+        assert!(store.link(&ids[0], &ids[1], "references")?);
+        assert!(store.link(&ids[1], &ids[2], "references")?);
+        circular(1, 0, "references");
+        circular(2, 0, "references");
+        circular(2, 1, "references");
+
+        // Other relation types form their own graph
+        assert!(store.link(&ids[2], &ids[0], "follows")?);
+        circular(0, 2, "follows");
+
+        assert_eq!(store.get_linked(&ids[2], Some("references"))?.len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_circular_links_are_rejected() -> Result<()> {
+        check_circular_links_are_rejected(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_circular_links_are_rejected_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        check_circular_links_are_rejected(&MemoryStore::new_sqlite(
+            &dir.path().join("memories.db"),
+            Tokenizer::default(),
+        )?)
+    }
+
+    /// Pin a memory that expires immediately and check that it outlives its TTL
+    fn check_pinned_memory_survives_expiry(store: &MemoryStore) -> Result<()> {
+        let store_note = || {
+            store.store_with_ttl(
+                "temporary note".to_string(),
+                "text/plain".to_string(),
+                None,
+                None,
+                HashMap::new(),
+                Some(0),
+            )
+        };
+        let pinned = store_note()?;
+        let expiring = store_note()?;
+        assert!(store.pin(&pinned.id)?.unwrap().pinned);
+        assert_eq!(store.get_stats()?.pinned_count, 1);
+
+        assert_eq!(store.purge_expired()?, 1);
+        assert!(store.retrieve(&expiring.id)?.is_none());
+        assert!(store.retrieve(&pinned.id)?.unwrap().pinned);
+
+        // Once unpinned the memory expires as usual
+        assert!(!store.unpin(&pinned.id)?.unwrap().pinned);
+        assert_eq!(store.purge_expired()?, 1);
+        assert!(store.retrieve(&pinned.id)?.is_none());
+        assert!(store.pin(&pinned.id)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_memory_survives_expiry() -> Result<()> {
+        check_pinned_memory_survives_expiry(&MemoryStore::new_in_memory(Tokenizer::default()))
+    }
+
+    #[test]
+    fn test_pinned_memory_survives_expiry_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let db_path = dir.path().join("memories.db");
+        check_pinned_memory_survives_expiry(&MemoryStore::new_sqlite(
+            &db_path,
+            Tokenizer::default(),
+        )?)
+    }
+
+    #[test]
+    fn test_purge_expired_sqlite() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        store_samples(&store)?;
+        store.store_with_ttl(
+            "temporary note".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+            Some(1),
+        )?;
+        // A TTL beyond what fits in an i64 never elapses rather than wrapping into the past
+        let lasting = store.store_with_ttl(
+            "lasting note".to_string(),
+            "text/plain".to_string(),
+            None,
+            None,
+            HashMap::new(),
+            Some(u64::MAX),
+        )?;
+        assert_eq!(store.purge_expired()?, 0);
+
+        std::thread::sleep(std::time::Duration::from_millis(2100));
+
+        assert_eq!(store.purge_expired()?, 1);
+        assert_eq!(store.get_ids_page(0, usize::MAX)?.len(), 4);
+        store.cache.lock().unwrap().clear();
+        assert!(store.retrieve(&lasting.id)?.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete() -> Result<()> {
+        let dir = tempdir()?;
+        let store = MemoryStore::new_sqlite(&dir.path().join("memories.db"), Tokenizer::default())?;
+        store_samples(&store)?;
+        let ids = store.get_ids_page(0, usize::MAX)?;
+        let total = store.get_total_tokens()?;
+
+        // Load the memory into the cache before deleting it
+        let memory = store.retrieve(&ids[0])?.unwrap();
+        assert_eq!(store.delete(&ids[0])?.map(|m| m.id), Some(memory.id));
+
+        assert!(store.retrieve(&ids[0])?.is_none());
+        assert_eq!(store.get_ids_page(0, usize::MAX)?.len(), 2);
+        assert_eq!(
+            store.get_total_tokens()?.as_usize(),
+            total.as_usize() - memory.token_count.as_usize()
+        );
+
+        assert!(store.delete(&MemoryId::from("mem_missing"))?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_regex_search_rejects_catastrophic_pattern() -> Result<()> {
+        let store = MemoryStore::new_in_memory(Tokenizer::default());
+        store_samples(&store)?;
+
+        let err = store.search_content_regex_safe("(a+)+$", 10).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<RegexSafetyError>(),
+            Some(&RegexSafetyError::NestedQuantifier)
+        );
+
+        Ok(())
     }
 }
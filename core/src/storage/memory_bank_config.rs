@@ -3,17 +3,27 @@
 //! This module provides functionality for configuring the memory bank categories,
 //! token budgets, and other settings.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use super::TokenCount;
 
-/// Priority level for memory bank categories
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Version of the configuration schema reported to clients
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
+/// Config keys that must never be exposed to clients
+const SENSITIVE_KEYS: &[&str] = &["api_key", "encryption_key"];
+
+/// Number of categories above which the config is likely a mistake
+const MAX_CATEGORIES: usize = 20;
+
+/// Priority level for memory bank categories, ordered from lowest to highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Priority {
     /// Low priority
@@ -26,17 +36,59 @@ pub enum Priority {
     Critical,
 }
 
+impl Priority {
+    /// Get the lowercase name of the priority
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Critical => "critical",
+        }
+    }
+}
+
+/// Which modes' memories a context may draw from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IsolationMode {
+    /// Only memories stored in the requested mode or in no mode
+    #[default]
+    Strict,
+    /// Memories of every mode, scored by how well they fit the requested one
+    Shared,
+}
+
 /// Configuration for a memory bank category
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Fields left unset are inherited from the template named by `extends`, and
+/// otherwise from the config's `default_category`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct CategoryConfig {
     /// Maximum number of tokens for this category
-    pub max_tokens: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
     /// Priority level for this category
-    pub priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    /// Template this category inherits unset fields from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+}
+
+impl CategoryConfig {
+    /// Create a category config setting every field
+    pub fn new(max_tokens: usize, priority: Priority) -> Self {
+        Self {
+            max_tokens: Some(max_tokens),
+            priority: Some(priority),
+            extends: None,
+        }
+    }
 }
 
 /// Configuration for memory bank update triggers
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UpdateTriggersConfig {
     /// Whether to automatically update the memory bank
     pub auto_update: bool,
@@ -45,7 +97,7 @@ pub struct UpdateTriggersConfig {
 }
 
 /// Configuration for memory bank token budget
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TokenBudgetConfig {
     /// Total token budget across all categories
     pub total: usize,
@@ -54,16 +106,87 @@ pub struct TokenBudgetConfig {
 }
 
 /// Configuration for memory bank relevance scoring
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RelevanceConfig {
     /// Minimum relevance threshold for including memories
     pub threshold: f64,
     /// Whether to boost the relevance of recent memories
     pub boost_recent: bool,
+    /// Only memories accessed within this many hours count towards IDF statistics
+    #[serde(default)]
+    pub corpus_window_hours: Option<u64>,
+    /// Weights of the dimensions combined into a mode's effectiveness score
+    #[serde(default)]
+    pub effectiveness_weights: EffectivenessWeights,
+}
+
+/// Weights of the dimensions reported by `AnalyzeMode`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectivenessWeights {
+    /// Weight of the average relevance of the mode's memories
+    pub relevance: f64,
+    /// Weight of the share of the mode's memories accessed recently
+    pub activity: f64,
+    /// Weight of how compact the mode's memories are
+    pub tokens: f64,
+}
+
+impl Default for EffectivenessWeights {
+    fn default() -> Self {
+        Self {
+            relevance: 0.5,
+            activity: 0.3,
+            tokens: 0.2,
+        }
+    }
+}
+
+impl RelevanceConfig {
+    /// Get the corpus window used for IDF statistics, if any
+    pub fn corpus_window(&self) -> Option<Duration> {
+        self.corpus_window_hours
+            .map(|hours| Duration::from_secs(hours * 60 * 60))
+    }
+}
+
+/// Configuration of the append-only audit log of memory mutations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditLogConfig {
+    /// Whether stores, updates and deletes are recorded
+    #[serde(default)]
+    pub enabled: bool,
+    /// File the audit lines are appended to
+    #[serde(default = "default_audit_log_path")]
+    pub path: PathBuf,
+    /// Whether lines carry the full content rather than its SHA-256 hash
+    #[serde(default)]
+    pub include_content: bool,
+}
+
+impl Default for AuditLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: default_audit_log_path(),
+            include_content: false,
+        }
+    }
+}
+
+/// Serde default for the audit log: `audit.jsonl` in the data directory
+fn default_audit_log_path() -> PathBuf {
+    std::env::var("DATA_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(".smart-memory")
+        })
+        .join("audit.jsonl")
 }
 
 /// Memory Bank configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MemoryBankConfig {
     /// Configuration for each category
     pub categories: HashMap<String, CategoryConfig>,
@@ -73,6 +196,72 @@ pub struct MemoryBankConfig {
     pub token_budget: TokenBudgetConfig,
     /// Configuration for relevance scoring
     pub relevance: RelevanceConfig,
+    /// Whether post-store processors may add metadata tags automatically
+    #[serde(default = "default_true")]
+    pub auto_tag: bool,
+    /// Whether to detect and tag the `language` of stored memories
+    #[serde(default = "default_true")]
+    pub language_detection_enabled: bool,
+    /// Whether the server rejects writes
+    #[serde(default)]
+    pub read_only: bool,
+    /// Modes defined by the user in addition to the built-in ones
+    #[serde(default)]
+    pub custom_modes: Vec<String>,
+    /// Cache hit rate percentage below which the cache is rebuilt from the database
+    #[serde(default = "default_cache_rebuild_threshold")]
+    pub cache_rebuild_threshold: u32,
+    /// Jaccard similarity above which `OptimizeMemory` merges two memories
+    #[serde(default = "default_dedup_threshold")]
+    pub dedup_threshold: f64,
+    /// Most memories that may be pinned at once
+    #[serde(default = "default_max_pinned")]
+    pub max_pinned: usize,
+    /// Database size in megabytes above which scheduled backups also compact it, 0 to never compact
+    #[serde(default = "default_auto_compact_threshold_mb")]
+    pub auto_compact_threshold_mb: u64,
+    /// Isolation of context requests that do not choose one
+    #[serde(default)]
+    pub default_isolation: IsolationMode,
+    /// Fallback for categories that are not listed or leave fields unset
+    #[serde(default = "default_category")]
+    pub default_category: CategoryConfig,
+    /// Named category configs that categories can extend
+    #[serde(default)]
+    pub templates: HashMap<String, CategoryConfig>,
+    /// Audit trail of memory mutations
+    #[serde(default)]
+    pub audit_log: AuditLogConfig,
+}
+
+/// Serde default for flags that are enabled unless configured otherwise
+fn default_true() -> bool {
+    true
+}
+
+/// Serde default for `cache_rebuild_threshold`
+fn default_cache_rebuild_threshold() -> u32 {
+    50
+}
+
+/// Serde default for `dedup_threshold`
+fn default_dedup_threshold() -> f64 {
+    0.85
+}
+
+/// Serde default for `max_pinned`
+fn default_max_pinned() -> usize {
+    100
+}
+
+/// Serde default for `auto_compact_threshold_mb`
+fn default_auto_compact_threshold_mb() -> u64 {
+    100
+}
+
+/// Serde default for `default_category`
+fn default_category() -> CategoryConfig {
+    CategoryConfig::new(1000, Priority::Medium)
 }
 
 impl Default for MemoryBankConfig {
@@ -82,42 +271,27 @@ impl Default for MemoryBankConfig {
         // Add default categories
         categories.insert(
             "context".to_string(),
-            CategoryConfig {
-                max_tokens: 10000,
-                priority: Priority::High,
-            },
+            CategoryConfig::new(10000, Priority::High),
         );
 
         categories.insert(
             "decision".to_string(),
-            CategoryConfig {
-                max_tokens: 5000,
-                priority: Priority::Medium,
-            },
+            CategoryConfig::new(5000, Priority::Medium),
         );
 
         categories.insert(
             "progress".to_string(),
-            CategoryConfig {
-                max_tokens: 8000,
-                priority: Priority::High,
-            },
+            CategoryConfig::new(8000, Priority::High),
         );
 
         categories.insert(
             "product".to_string(),
-            CategoryConfig {
-                max_tokens: 10000,
-                priority: Priority::Medium,
-            },
+            CategoryConfig::new(10000, Priority::Medium),
         );
 
         categories.insert(
             "pattern".to_string(),
-            CategoryConfig {
-                max_tokens: 5000,
-                priority: Priority::Low,
-            },
+            CategoryConfig::new(5000, Priority::Low),
         );
 
         Self {
@@ -133,7 +307,21 @@ impl Default for MemoryBankConfig {
             relevance: RelevanceConfig {
                 threshold: 0.7,
                 boost_recent: true,
+                corpus_window_hours: None,
+                effectiveness_weights: EffectivenessWeights::default(),
             },
+            auto_tag: true,
+            language_detection_enabled: true,
+            read_only: false,
+            custom_modes: Vec::new(),
+            cache_rebuild_threshold: default_cache_rebuild_threshold(),
+            dedup_threshold: default_dedup_threshold(),
+            max_pinned: default_max_pinned(),
+            auto_compact_threshold_mb: default_auto_compact_threshold_mb(),
+            default_isolation: IsolationMode::default(),
+            default_category: default_category(),
+            templates: HashMap::new(),
+            audit_log: AuditLogConfig::default(),
         }
     }
 }
@@ -164,22 +352,759 @@ impl MemoryBankConfig {
         Ok(())
     }
 
+    /// Load configuration from a TOML file
+    pub fn from_toml_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+        Self::from_toml_str(&contents)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+
+    /// Save configuration to a TOML file
+    pub fn to_toml_file(&self, path: &Path) -> Result<()> {
+        let contents = self.to_toml_string()?;
+
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Parse configuration from a TOML string
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        toml::from_str(s).context("Failed to parse TOML config")
+    }
+
+    /// Serialize the configuration to a TOML string
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize config")
+    }
+
+    /// Load configuration from a YAML file
+    pub fn from_yaml_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+        Self::from_yaml_str(&contents)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+
+    /// Save configuration to a YAML file
+    pub fn to_yaml_file(&self, path: &Path) -> Result<()> {
+        let contents = self.to_yaml_str()?;
+
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Parse configuration from a YAML string
+    pub fn from_yaml_str(s: &str) -> Result<Self> {
+        serde_yaml::from_str(s).context("Failed to parse YAML config")
+    }
+
+    /// Serialize the configuration to a YAML string
+    pub fn to_yaml_str(&self) -> Result<String> {
+        serde_yaml::to_string(self).context("Failed to serialize config")
+    }
+
+    /// Load configuration from a `.json`, `.toml`, `.yaml` or `.yml` file, chosen by extension
+    pub fn load(path: &Path) -> Result<Self> {
+        match ConfigFormat::from_path(path)? {
+            ConfigFormat::Json => Self::from_file(path),
+            ConfigFormat::Toml => Self::from_toml_file(path),
+            ConfigFormat::Yaml => Self::from_yaml_file(path),
+        }
+    }
+
+    /// Save configuration to a `.json`, `.toml`, `.yaml` or `.yml` file, chosen by extension
+    pub fn save(&self, path: &Path) -> Result<()> {
+        match ConfigFormat::from_path(path)? {
+            ConfigFormat::Json => self.to_file(path),
+            ConfigFormat::Toml => self.to_toml_file(path),
+            ConfigFormat::Yaml => self.to_yaml_file(path),
+        }
+    }
+
+    /// Serialize the configuration to JSON with sensitive fields removed
+    pub fn to_public_json(&self) -> Result<String> {
+        let mut value = serde_json::to_value(self).context("Failed to serialize config")?;
+        strip_sensitive_keys(&mut value);
+
+        serde_json::to_string_pretty(&value).context("Failed to serialize config")
+    }
+
     /// Get the maximum tokens for a category
     pub fn get_max_tokens(&self, category: &str) -> TokenCount {
-        let max_tokens = self
-            .categories
-            .get(category)
-            .map(|c| c.max_tokens)
-            .unwrap_or(1000);
+        let max_tokens = self.inherited(category, |c| c.max_tokens).unwrap_or(1000);
 
         TokenCount::from(max_tokens)
     }
 
     /// Get the priority for a category
     pub fn get_priority(&self, category: &str) -> Priority {
-        self.categories
-            .get(category)
-            .map(|c| c.priority)
+        self.inherited(category, |c| c.priority)
             .unwrap_or(Priority::Medium)
     }
+
+    /// Get a category field, following `extends` and then `default_category` while it is unset
+    fn inherited<T>(
+        &self,
+        category: &str,
+        field: impl Fn(&CategoryConfig) -> Option<T>,
+    ) -> Option<T> {
+        let mut current = self.categories.get(category);
+        // Circular templates are reported by `validate`; stop once every template was visited
+        for _ in 0..=self.templates.len() {
+            let Some(config) = current else {
+                break;
+            };
+            if let Some(value) = field(config) {
+                return Some(value);
+            }
+            current = config
+                .extends
+                .as_deref()
+                .and_then(|template| self.templates.get(template));
+        }
+
+        field(&self.default_category)
+    }
+
+    /// Find the templates extending each other in a cycle, each cycle listed from its first name
+    fn inheritance_cycles(&self) -> Vec<Vec<String>> {
+        let mut names: Vec<_> = self.templates.keys().collect();
+        names.sort();
+
+        let mut cycles = Vec::new();
+        for start in names {
+            let mut chain = vec![start.clone()];
+            let mut seen = HashSet::from([start.as_str()]);
+            let mut next = self.templates[start].extends.as_deref();
+            while let Some(name) = next.filter(|name| self.templates.contains_key(*name)) {
+                if name == start {
+                    // Report each cycle once, from its alphabetically first template
+                    if chain.iter().all(|member| member >= start) {
+                        chain.push(start.clone());
+                        cycles.push(chain);
+                    }
+                    break;
+                }
+                if !seen.insert(name) {
+                    break;
+                }
+                chain.push(name.to_string());
+                next = self.templates[name].extends.as_deref();
+            }
+        }
+
+        cycles
+    }
+
+    /// Check the config for values that would make the server misbehave
+    ///
+    /// Returns the warnings worth logging when the config is usable, or every
+    /// error found when it is not.
+    pub fn validate(&self) -> Result<Vec<ValidationWarning>, Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let mut warnings = Vec::new();
+
+        if !(0.0..=1.0).contains(&self.relevance.threshold) {
+            errors.push(ValidationError::ThresholdOutOfRange(
+                self.relevance.threshold,
+            ));
+        }
+
+        let mut extended: Vec<_> = self
+            .categories
+            .iter()
+            .chain(&self.templates)
+            .filter_map(|(name, config)| Some((name, config.extends.as_ref()?)))
+            .filter(|(_, template)| !self.templates.contains_key(*template))
+            .collect();
+        extended.sort();
+        for (name, template) in extended {
+            errors.push(ValidationError::UnknownTemplate {
+                name: name.clone(),
+                template: template.clone(),
+            });
+        }
+        for cycle in self.inheritance_cycles() {
+            errors.push(ValidationError::CircularInheritance(cycle));
+        }
+
+        let mut categories: Vec<_> = self
+            .categories
+            .keys()
+            .map(|name| (name, self.get_max_tokens(name).as_usize()))
+            .collect();
+        categories.sort();
+        for (name, max_tokens) in &categories {
+            if *max_tokens == 0 {
+                errors.push(ValidationError::ZeroMaxTokens(name.to_string()));
+            }
+            if name.chars().any(char::is_whitespace) {
+                warnings.push(ValidationWarning::WhitespaceInCategoryName(
+                    name.to_string(),
+                ));
+            }
+        }
+
+        if let Some((name, max_tokens)) =
+            categories.iter().max_by_key(|(_, max_tokens)| *max_tokens)
+        {
+            if self.token_budget.total < *max_tokens {
+                errors.push(ValidationError::TotalBelowCategoryBudget {
+                    total: self.token_budget.total,
+                    category: name.to_string(),
+                    max_tokens: *max_tokens,
+                });
+            }
+        }
+
+        if self.categories.len() > MAX_CATEGORIES {
+            warnings.push(ValidationWarning::TooManyCategories(self.categories.len()));
+        }
+
+        if errors.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Fold the source category's config into the target category's
+    ///
+    /// A source config without a target config is renamed. When both exist, the target
+    /// keeps its priority and gets both budgets, capped at the total token budget.
+    pub fn merge_categories(&mut self, source: &str, target: &str) {
+        if source == target {
+            return;
+        }
+        // Budgets may be inherited, so resolve them before the source is removed
+        let source_max_tokens = self.get_max_tokens(source).as_usize();
+        let target_max_tokens = self.get_max_tokens(target).as_usize();
+        let Some(source_config) = self.categories.remove(source) else {
+            return;
+        };
+
+        match self.categories.get_mut(target) {
+            Some(target_config) => {
+                target_config.max_tokens =
+                    Some((target_max_tokens + source_max_tokens).min(self.token_budget.total));
+            }
+            None => {
+                self.categories.insert(target.to_string(), source_config);
+            }
+        }
+    }
+}
+
+/// A config value that blocks startup
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// `relevance.threshold` is outside [0, 1]
+    ThresholdOutOfRange(f64),
+    /// A category can never hold any tokens
+    ZeroMaxTokens(String),
+    /// `token_budget.total` is smaller than a single category's budget
+    TotalBelowCategoryBudget {
+        total: usize,
+        category: String,
+        max_tokens: usize,
+    },
+    /// A category or template extends a template that does not exist
+    UnknownTemplate { name: String, template: String },
+    /// Templates extend each other in a cycle, listed from and back to its first template
+    CircularInheritance(Vec<String>),
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ThresholdOutOfRange(threshold) => {
+                write!(f, "relevance.threshold {} is outside [0, 1]", threshold)
+            }
+            Self::ZeroMaxTokens(category) => {
+                write!(f, "category {:?} has max_tokens 0", category)
+            }
+            Self::TotalBelowCategoryBudget {
+                total,
+                category,
+                max_tokens,
+            } => write!(
+                f,
+                "token_budget.total {} is less than the {} max_tokens of category {:?}",
+                total, max_tokens, category
+            ),
+            Self::UnknownTemplate { name, template } => {
+                write!(f, "{:?} extends unknown template {:?}", name, template)
+            }
+            Self::CircularInheritance(cycle) => {
+                write!(f, "templates inherit in a cycle: {}", cycle.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A suspicious config value that still lets the server run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// More categories than [`MAX_CATEGORIES`] are defined
+    TooManyCategories(usize),
+    /// A category name contains whitespace, which is awkward to pass around
+    WhitespaceInCategoryName(String),
+}
+
+impl std::fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooManyCategories(count) => write!(
+                f,
+                "{} categories are defined, more than the recommended {}",
+                count, MAX_CATEGORIES
+            ),
+            Self::WhitespaceInCategoryName(category) => {
+                write!(f, "category name {:?} contains whitespace", category)
+            }
+        }
+    }
+}
+
+/// File formats a configuration can be stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detect the format from the extension of `path`
+    fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(Self::Json),
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Ok(Self::Toml),
+            Some(ext) if ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml") => {
+                Ok(Self::Yaml)
+            }
+            _ => bail!(
+                "Unsupported config file extension (expected .json, .toml, .yaml or .yml): {}",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Recursively remove sensitive keys from a JSON value
+fn strip_sensitive_keys(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|key, _| !SENSITIVE_KEYS.contains(&key.as_str()));
+            map.values_mut().for_each(strip_sensitive_keys);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strip_sensitive_keys),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_categories() {
+        let mut config = MemoryBankConfig::default();
+        let context = config.categories["context"].clone();
+        config.merge_categories("context", "ctx");
+        assert!(!config.categories.contains_key("context"));
+        assert_eq!(config.categories["ctx"], context);
+
+        let decision = config.get_max_tokens("decision").as_usize();
+        let progress = config.get_max_tokens("progress").as_usize();
+        let priority = config.get_priority("progress");
+        config.merge_categories("decision", "progress");
+        assert!(!config.categories.contains_key("decision"));
+        assert_eq!(config.get_priority("progress"), priority);
+        assert_eq!(
+            config.get_max_tokens("progress").as_usize(),
+            (progress + decision).min(config.token_budget.total)
+        );
+
+        config.token_budget.total = 12000;
+        config.merge_categories("ctx", "progress");
+        assert_eq!(config.categories["progress"].max_tokens, Some(12000));
+    }
+
+    #[test]
+    fn test_merge_categories_resolves_inherited_budgets() {
+        let mut config = MemoryBankConfig::default();
+        config.templates.insert(
+            "notes".to_string(),
+            CategoryConfig::new(2000, Priority::Low),
+        );
+        config.categories.insert(
+            "meeting".to_string(),
+            CategoryConfig {
+                extends: Some("notes".to_string()),
+                ..Default::default()
+            },
+        );
+
+        config.merge_categories("meeting", "pattern");
+        assert_eq!(config.get_max_tokens("pattern").as_usize(), 7000);
+    }
+
+    #[test]
+    fn test_public_json_strips_sensitive_keys() {
+        let mut value = serde_json::json!({
+            "api_key": "secret",
+            "storage": { "encryption_key": "secret", "path": "memories.db" },
+        });
+        strip_sensitive_keys(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({ "storage": { "path": "memories.db" } })
+        );
+
+        let json = MemoryBankConfig::default().to_public_json().unwrap();
+        assert!(json.contains("\"categories\""));
+    }
+
+    /// A config with every field, including optional and nested ones, set
+    fn full_config() -> MemoryBankConfig {
+        let mut config = MemoryBankConfig::default();
+        config.categories.insert(
+            "incident".to_string(),
+            CategoryConfig::new(1234, Priority::Critical),
+        );
+        config.categories.insert(
+            "postmortem".to_string(),
+            CategoryConfig {
+                max_tokens: Some(4321),
+                extends: Some("report".to_string()),
+                ..Default::default()
+            },
+        );
+        config.templates.insert(
+            "report".to_string(),
+            CategoryConfig {
+                priority: Some(Priority::High),
+                ..Default::default()
+            },
+        );
+        MemoryBankConfig {
+            update_triggers: UpdateTriggersConfig {
+                auto_update: false,
+                umb_command: true,
+            },
+            token_budget: TokenBudgetConfig {
+                total: 42000,
+                per_category: false,
+            },
+            relevance: RelevanceConfig {
+                threshold: 0.55,
+                boost_recent: false,
+                corpus_window_hours: Some(72),
+                effectiveness_weights: EffectivenessWeights {
+                    relevance: 0.6,
+                    activity: 0.25,
+                    tokens: 0.15,
+                },
+            },
+            auto_tag: false,
+            language_detection_enabled: false,
+            read_only: true,
+            custom_modes: vec!["research".to_string(), "triage".to_string()],
+            cache_rebuild_threshold: 35,
+            dedup_threshold: 0.9,
+            default_category: CategoryConfig::new(800, Priority::Low),
+            ..config
+        }
+    }
+
+    fn as_value(config: &MemoryBankConfig) -> serde_json::Value {
+        serde_json::to_value(config).unwrap()
+    }
+
+    #[test]
+    fn test_toml_string_round_trip() -> Result<()> {
+        let config = full_config();
+        let toml = config.to_toml_string()?;
+        assert!(toml.contains("[categories.incident]"));
+        assert!(toml.contains("[templates.report]"));
+
+        let parsed = MemoryBankConfig::from_toml_str(&toml)?;
+        assert_eq!(as_value(&parsed), as_value(&config));
+        Ok(())
+    }
+
+    #[test]
+    fn test_yaml_string_round_trip() -> Result<()> {
+        let config = full_config();
+        let yaml = config.to_yaml_str()?;
+        assert!(yaml.contains("incident:"));
+
+        let parsed = MemoryBankConfig::from_yaml_str(&yaml)?;
+        assert_eq!(as_value(&parsed), as_value(&config));
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_json_to_yaml_to_toml() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = full_config();
+        let json = dir.path().join("config.json");
+        let yaml = dir.path().join("config.yml");
+        let toml = dir.path().join("config.toml");
+
+        config.save(&json)?;
+        MemoryBankConfig::load(&json)?.save(&yaml)?;
+        MemoryBankConfig::load(&yaml)?.save(&toml)?;
+
+        assert_eq!(MemoryBankConfig::load(&toml)?, config);
+        Ok(())
+    }
+
+    #[test]
+    fn test_toml_defaults_missing_optional_fields() -> Result<()> {
+        let config = MemoryBankConfig::from_toml_str(
+            r#"
+            # Only the required sections
+            [categories.context]
+            max_tokens = 100
+            priority = "high"
+
+            [update_triggers]
+            auto_update = true
+            umb_command = false
+
+            [token_budget]
+            total = 1000
+            per_category = true
+
+            [relevance]
+            threshold = 0.5
+            boost_recent = true
+            "#,
+        )?;
+
+        assert_eq!(config.categories["context"].priority, Some(Priority::High));
+        assert_eq!(config.default_category, default_category());
+        assert!(config.templates.is_empty());
+        assert_eq!(config.relevance.corpus_window_hours, None);
+        assert!(config.auto_tag);
+        assert_eq!(config.cache_rebuild_threshold, 50);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_round_trip_by_extension() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let config = full_config();
+
+        for name in [
+            "config.json",
+            "config.toml",
+            "CONFIG.TOML",
+            "config.yaml",
+            "config.yml",
+        ] {
+            let path = dir.path().join(name);
+            config.save(&path)?;
+            assert_eq!(as_value(&MemoryBankConfig::load(&path)?), as_value(&config));
+        }
+
+        // Each file holds its own format
+        let json = std::fs::read_to_string(dir.path().join("config.json"))?;
+        assert!(serde_json::from_str::<serde_json::Value>(&json).is_ok());
+        let toml = std::fs::read_to_string(dir.path().join("config.toml"))?;
+        assert!(MemoryBankConfig::from_toml_str(&toml).is_ok());
+        assert!(serde_json::from_str::<serde_json::Value>(&toml).is_err());
+        let yaml = std::fs::read_to_string(dir.path().join("config.yaml"))?;
+        assert!(MemoryBankConfig::from_yaml_str(&yaml).is_ok());
+        assert!(serde_json::from_str::<serde_json::Value>(&yaml).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_extension_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = MemoryBankConfig::default();
+
+        for name in ["config.ini", "config"] {
+            let path = dir.path().join(name);
+            assert!(config.save(&path).is_err());
+            assert!(!path.exists());
+            assert!(MemoryBankConfig::load(&path).is_err());
+        }
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert_eq!(MemoryBankConfig::default().validate(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_validate_reports_errors() {
+        let mut config = MemoryBankConfig::default();
+        config.relevance.threshold = -0.1;
+        assert_eq!(
+            config.validate(),
+            Err(vec![ValidationError::ThresholdOutOfRange(-0.1)])
+        );
+
+        config.relevance.threshold = f64::NAN;
+        assert!(config.validate().is_err());
+
+        let mut config = MemoryBankConfig::default();
+        config.categories.get_mut("pattern").unwrap().max_tokens = Some(0);
+        assert_eq!(
+            config.validate(),
+            Err(vec![ValidationError::ZeroMaxTokens("pattern".to_string())])
+        );
+
+        let mut config = MemoryBankConfig::default();
+        config.token_budget.total = 9000;
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            &errors[0],
+            ValidationError::TotalBelowCategoryBudget {
+                total: 9000,
+                max_tokens: 10000,
+                ..
+            }
+        ));
+
+        // Every error is reported at once
+        config.relevance.threshold = 2.0;
+        config.categories.get_mut("decision").unwrap().max_tokens = Some(0);
+        assert_eq!(config.validate().unwrap_err().len(), 3);
+    }
+
+    #[test]
+    fn test_validate_reports_warnings() {
+        let mut config = MemoryBankConfig::default();
+        config.categories.insert(
+            "open questions".to_string(),
+            CategoryConfig::new(1000, Priority::Low),
+        );
+        assert_eq!(
+            config.validate(),
+            Ok(vec![ValidationWarning::WhitespaceInCategoryName(
+                "open questions".to_string()
+            )])
+        );
+
+        let mut config = MemoryBankConfig::default();
+        for index in config.categories.len()..=MAX_CATEGORIES {
+            config.categories.insert(
+                format!("category-{}", index),
+                CategoryConfig::new(1000, Priority::Low),
+            );
+        }
+        assert_eq!(
+            config.validate(),
+            Ok(vec![ValidationWarning::TooManyCategories(
+                MAX_CATEGORIES + 1
+            )])
+        );
+    }
+
+    #[test]
+    fn test_missing_category_uses_default() {
+        let mut config = MemoryBankConfig {
+            default_category: CategoryConfig::new(2500, Priority::High),
+            ..Default::default()
+        };
+        assert_eq!(config.get_max_tokens("research").as_usize(), 2500);
+        assert_eq!(config.get_priority("research"), Priority::High);
+
+        // Listed categories fall back to the default for the fields they leave unset
+        config.categories.insert(
+            "research".to_string(),
+            CategoryConfig {
+                max_tokens: Some(300),
+                ..Default::default()
+            },
+        );
+        assert_eq!(config.get_max_tokens("research").as_usize(), 300);
+        assert_eq!(config.get_priority("research"), Priority::High);
+    }
+
+    #[test]
+    fn test_category_extends_template() {
+        let config = MemoryBankConfig::from_toml_str(
+            r#"
+            [categories.incident]
+            extends = "log"
+            priority = "critical"
+
+            [templates.log]
+            max_tokens = 3000
+            priority = "low"
+
+            [update_triggers]
+            auto_update = true
+            umb_command = false
+
+            [token_budget]
+            total = 10000
+            per_category = true
+
+            [relevance]
+            threshold = 0.5
+            boost_recent = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.get_max_tokens("incident").as_usize(), 3000);
+        assert_eq!(config.get_priority("incident"), Priority::Critical);
+        assert_eq!(config.validate(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_validate_reports_circular_inheritance() {
+        let mut config = MemoryBankConfig::default();
+        for (name, extends) in [("a", "b"), ("b", "c"), ("c", "a"), ("d", "d")] {
+            config.templates.insert(
+                name.to_string(),
+                CategoryConfig {
+                    extends: Some(extends.to_string()),
+                    ..Default::default()
+                },
+            );
+        }
+        config.categories.get_mut("context").unwrap().extends = Some("a".to_string());
+        config.categories.get_mut("pattern").unwrap().extends = Some("missing".to_string());
+
+        let cycle = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        assert_eq!(
+            config.validate(),
+            Err(vec![
+                ValidationError::UnknownTemplate {
+                    name: "pattern".to_string(),
+                    template: "missing".to_string(),
+                },
+                ValidationError::CircularInheritance(cycle(&["a", "b", "c", "a"])),
+                ValidationError::CircularInheritance(cycle(&["d", "d"])),
+            ])
+        );
+
+        // Lookups still terminate, falling back to the default
+        config.categories.insert(
+            "loop".to_string(),
+            CategoryConfig {
+                extends: Some("a".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(config.get_max_tokens("loop").as_usize(), 1000);
+    }
 }
@@ -0,0 +1,274 @@
+//! Per-operation metrics recorded by the service
+//!
+//! Every RPC records a row in the `metrics` table with its operation name,
+//! mode, token count and latency. Rows older than [`METRICS_RETENTION`] are
+//! purged when the store is opened and periodically while recording.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::db::run_migrations;
+use super::stats::percentile;
+
+/// How long metrics rows are kept
+pub const METRICS_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Number of recorded rows between purges of expired rows
+const PURGE_INTERVAL: u64 = 1000;
+
+/// How long to wait for a locked database before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Latency percentiles over a set of recorded calls, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyPercentiles {
+    /// Median latency
+    pub p50_ms: u64,
+    /// 95th percentile latency
+    pub p95_ms: u64,
+    /// 99th percentile latency
+    pub p99_ms: u64,
+}
+
+/// Store of per-operation metrics backed by the `metrics` table
+#[derive(Debug)]
+pub struct MetricsStore {
+    /// Connection to the database holding the metrics table
+    connection: Mutex<Connection>,
+    /// Unix time at which the store was opened
+    opened_at: i64,
+    /// Rows recorded since the last purge
+    writes_since_purge: AtomicU64,
+}
+
+impl MetricsStore {
+    /// Open the metrics table in the SQLite database at `db_path`
+    pub fn open(db_path: &Path) -> Result<Self> {
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(db_path).context("Failed to open metrics database")?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        Self::with_connection(connection)
+    }
+
+    /// Create a metrics store that lives only in memory
+    pub fn new_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut connection: Connection) -> Result<Self> {
+        run_migrations(&mut connection)?;
+        let store = Self {
+            connection: Mutex::new(connection),
+            opened_at: chrono::Utc::now().timestamp(),
+            writes_since_purge: AtomicU64::new(0),
+        };
+        store.purge_expired()?;
+        Ok(store)
+    }
+
+    /// Record a call to `operation`
+    pub fn record(
+        &self,
+        operation: &str,
+        mode: &str,
+        token_count: usize,
+        latency: Duration,
+    ) -> Result<()> {
+        self.record_at(
+            chrono::Utc::now().timestamp(),
+            operation,
+            mode,
+            token_count,
+            latency,
+        )
+    }
+
+    fn record_at(
+        &self,
+        timestamp: i64,
+        operation: &str,
+        mode: &str,
+        token_count: usize,
+        latency: Duration,
+    ) -> Result<()> {
+        self.connection
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO metrics (timestamp, operation, mode, token_count, latency_ms)
+                 VALUES (?, ?, ?, ?, ?)",
+                params![
+                    timestamp,
+                    operation,
+                    mode,
+                    token_count as i64,
+                    latency.as_millis() as i64
+                ],
+            )
+            .context("Failed to record metrics")?;
+
+        if self.writes_since_purge.fetch_add(1, Ordering::Relaxed) + 1 >= PURGE_INTERVAL {
+            self.writes_since_purge.store(0, Ordering::Relaxed);
+            self.purge_expired()?;
+        }
+        Ok(())
+    }
+
+    /// Delete rows older than the retention period, returning how many were removed
+    pub fn purge_expired(&self) -> Result<usize> {
+        let cutoff = chrono::Utc::now().timestamp() - METRICS_RETENTION.as_secs() as i64;
+        self.connection
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM metrics WHERE timestamp < ?", params![cutoff])
+            .context("Failed to purge expired metrics")
+    }
+
+    /// Count the calls to each operation since `since` (Unix time)
+    pub fn calls_per_operation(&self, since: i64) -> Result<BTreeMap<String, u64>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT operation, COUNT(*) FROM metrics WHERE timestamp >= ? GROUP BY operation",
+        )?;
+        let rows = statement.query_map(params![since], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+        })?;
+        rows.collect::<rusqlite::Result<_>>()
+            .context("Failed to count calls per operation")
+    }
+
+    /// Compute latency percentiles over the calls since `since` (Unix time)
+    pub fn latency_percentiles(&self, since: i64) -> Result<LatencyPercentiles> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection
+            .prepare("SELECT latency_ms FROM metrics WHERE timestamp >= ? ORDER BY latency_ms")?;
+        let latencies = statement
+            .query_map(params![since], |row| row.get::<_, i64>(0))?
+            .map(|latency| latency.map(|ms| ms as usize))
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("Failed to read latencies")?;
+
+        if latencies.is_empty() {
+            return Ok(LatencyPercentiles::default());
+        }
+        Ok(LatencyPercentiles {
+            p50_ms: percentile(&latencies, 50) as u64,
+            p95_ms: percentile(&latencies, 95) as u64,
+            p99_ms: percentile(&latencies, 99) as u64,
+        })
+    }
+
+    /// Sum the tokens of each mode per UTC day since `since` (Unix time)
+    ///
+    /// The result maps each mode to `(day start, tokens)` pairs in day order;
+    /// days without calls are omitted.
+    pub fn tokens_per_mode_per_day(&self, since: i64) -> Result<BTreeMap<String, Vec<(i64, u64)>>> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(
+            "SELECT mode, timestamp / 86400 * 86400 AS day, SUM(token_count)
+             FROM metrics WHERE timestamp >= ? AND mode != ''
+             GROUP BY mode, day ORDER BY mode, day",
+        )?;
+        let rows = statement.query_map(params![since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)? as u64,
+            ))
+        })?;
+
+        let mut tokens: BTreeMap<String, Vec<(i64, u64)>> = BTreeMap::new();
+        for row in rows {
+            let (mode, day, total) = row.context("Failed to read tokens per mode")?;
+            tokens.entry(mode).or_default().push((day, total));
+        }
+        Ok(tokens)
+    }
+
+    /// Sum the tokens recorded since `since` (Unix time)
+    pub fn total_tokens(&self, since: i64) -> Result<u64> {
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT COALESCE(SUM(token_count), 0) FROM metrics WHERE timestamp >= ?",
+                params![since],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|total| total as u64)
+            .context("Failed to sum tokens")
+    }
+
+    /// Sum the tokens recorded since this store was opened
+    pub fn session_tokens(&self) -> Result<u64> {
+        self.total_tokens(self.opened_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregates() -> Result<()> {
+        let store = MetricsStore::new_in_memory()?;
+        for latency in 1..=100 {
+            store.record(
+                "retrieve_memory",
+                "code",
+                10,
+                Duration::from_millis(latency),
+            )?;
+        }
+        store.record("store_memory", "debug", 25, Duration::from_millis(3))?;
+
+        let calls = store.calls_per_operation(0)?;
+        assert_eq!(calls["retrieve_memory"], 100);
+        assert_eq!(calls["store_memory"], 1);
+
+        let latency = store.latency_percentiles(0)?;
+        assert_eq!(latency.p50_ms, 50);
+        assert_eq!(latency.p95_ms, 95);
+        assert_eq!(latency.p99_ms, 99);
+
+        let tokens = store.tokens_per_mode_per_day(0)?;
+        assert_eq!(tokens["code"].len(), 1);
+        assert_eq!(tokens["code"][0].1, 1000);
+        assert_eq!(tokens["debug"][0].1, 25);
+        assert_eq!(store.session_tokens()?, 1025);
+        Ok(())
+    }
+
+    #[test]
+    fn test_purges_rows_past_retention() -> Result<()> {
+        let store = MetricsStore::new_in_memory()?;
+        let now = chrono::Utc::now().timestamp();
+        let expired = now - METRICS_RETENTION.as_secs() as i64 - 60;
+        store.record_at(expired, "get_context", "code", 5, Duration::ZERO)?;
+        store.record_at(now, "get_context", "code", 7, Duration::ZERO)?;
+
+        assert_eq!(store.purge_expired()?, 1);
+        assert_eq!(store.total_tokens(0)?, 7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_metrics_persist_in_database_file() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db_path = dir.path().join("memories.db");
+
+        MetricsStore::open(&db_path)?.record("store_memory", "code", 3, Duration::ZERO)?;
+
+        let reopened = MetricsStore::open(&db_path)?;
+        assert_eq!(reopened.calls_per_operation(0)?["store_memory"], 1);
+        Ok(())
+    }
+}
@@ -3,21 +3,54 @@
 //! This module provides functionality for storing and retrieving memory content,
 //! along with tokenization and optimization capabilities.
 
+mod audit;
 mod backup;
 mod context;
 mod db;
+mod dedup;
+mod diff;
+mod encryption;
+mod filter;
+mod idempotency;
+mod links;
 mod memory;
 mod memory_bank_config;
+mod metrics;
+mod processors;
+mod query;
+mod regex_safety;
+mod stats;
+mod sync;
 mod tokenizer;
+mod watch;
 
-pub use backup::{BackupManager, BackupMetadata};
+pub use backup::{backup_id, default_backup_dir, BackupManager, BackupMetadata, BackupScheduler};
 pub use context::{
-    relevance::RelevanceScore, ContextOptimizer, RelevanceScorer, TfIdfScorer, TokenBudgetOptimizer,
+    relevance::{RelevanceScore, ScoredMemory},
+    CategoryAwareOptimizer, ContextOptimizer, EmbeddingScorer, ExclusionReason, HybridScorer,
+    RelevanceScorer, SummarizingOptimizer, TfIdfScorer, TokenBudgetOptimizer, DEFAULT_HYBRID_ALPHA,
+};
+pub use db::{
+    AsyncMemoryRepository, AsyncSqliteMemoryRepository, CompactionInProgress, MemoryRepository,
+    SqliteMemoryRepository,
+};
+pub use dedup::{find_duplicate_groups, DuplicateGroup};
+pub use diff::MemoryDiff;
+pub use encryption::ContentCipher;
+pub use filter::{MemoryFilter, MemorySortField, SortOrder};
+pub use links::CircularLink;
+pub use memory::{
+    DuplicateMemoryId, Memory, MemoryId, MemoryStore, SnapshotId, StoreOptions, UnknownMemory,
+    UnknownSnapshot, UnknownVersion, ACCESS_LOG_CATEGORY, HIDDEN_CATEGORIES, HISTORY_CATEGORY,
 };
-pub use db::{MemoryRepository, SqliteMemoryRepository};
-pub use memory::{Memory, MemoryId, MemoryStore};
 pub use memory_bank_config::{
-    CategoryConfig, MemoryBankConfig, Priority, RelevanceConfig, TokenBudgetConfig,
-    UpdateTriggersConfig,
+    CategoryConfig, IsolationMode, MemoryBankConfig, Priority, RelevanceConfig, TokenBudgetConfig,
+    UpdateTriggersConfig, CONFIG_SCHEMA_VERSION,
 };
-pub use tokenizer::{TokenCount, Tokenizer, TokenizerType};
+pub use metrics::MetricsStore;
+pub use processors::LanguageTagger;
+pub use query::MemoryQuery;
+pub use regex_safety::{RegexSafetyCheck, RegexSafetyError};
+pub use stats::{CategoryStats, MemoryStats, ModeStats, SizeDistribution, NO_MODE, UNCATEGORIZED};
+pub use sync::{decode_memories, encode_memories, ConflictResolution, ImportConflict};
+pub use tokenizer::{TokenCount, TokenPricing, Tokenizer, TokenizerType};
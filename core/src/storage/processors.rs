@@ -0,0 +1,107 @@
+//! Post-store processing for memories
+//!
+//! Processors run after a memory has been persisted and may enrich it (for
+//! example by adding metadata tags). A processor that changes the memory
+//! causes it to be written back to the repository.
+
+use super::memory::Memory;
+
+/// Code markers checked at the start of each line, in priority order
+const CODE_MARKERS: &[(&str, &str)] = &[
+    ("fn ", "rust"),
+    ("pub fn ", "rust"),
+    ("async fn ", "rust"),
+    ("def ", "python"),
+    ("async def ", "python"),
+    ("function ", "javascript"),
+    ("async function ", "javascript"),
+    ("export function ", "javascript"),
+];
+
+/// A processing step applied to every newly stored memory
+pub trait PostStoreProcessor: Send + Sync + std::fmt::Debug {
+    /// Process the memory, returning `true` if it was modified
+    fn process(&self, memory: &mut Memory) -> bool;
+}
+
+/// Detect the language of a piece of content
+///
+/// Code is recognised by simple keyword heuristics; anything else is passed to
+/// natural language detection and tagged with its ISO 639-3 code (e.g. `eng`).
+pub fn detect_language(content: &str) -> Option<String> {
+    for line in content.lines() {
+        let line = line.trim_start();
+        if let Some((_, language)) = CODE_MARKERS
+            .iter()
+            .find(|(marker, _)| line.starts_with(marker))
+        {
+            return Some(language.to_string());
+        }
+    }
+
+    whatlang::detect(content)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code().to_string())
+}
+
+/// Tags memories with a `language` metadata entry
+#[derive(Debug, Clone, Default)]
+pub struct LanguageTagger;
+
+impl PostStoreProcessor for LanguageTagger {
+    fn process(&self, memory: &mut Memory) -> bool {
+        // Never override a language provided by the client
+        if memory.metadata.contains_key("language") {
+            return false;
+        }
+
+        match detect_language(&memory.content) {
+            Some(language) => {
+                memory.metadata.insert("language".to_string(), language);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_code_languages() {
+        assert_eq!(
+            detect_language("fn main() {\n    println!(\"hi\");\n}"),
+            Some("rust".to_string())
+        );
+        assert_eq!(
+            detect_language("import os\n\ndef main():\n    pass"),
+            Some("python".to_string())
+        );
+        assert_eq!(
+            detect_language("function add(a, b) {\n  return a + b;\n}"),
+            Some("javascript".to_string())
+        );
+    }
+
+    #[test]
+    fn test_detect_natural_language() {
+        assert_eq!(
+            detect_language(
+                "We decided to use gRPC for the transport layer because it gives us \
+                 strongly typed messages and efficient streaming between processes."
+            ),
+            Some("eng".to_string())
+        );
+        assert_eq!(detect_language(""), None);
+    }
+
+    #[test]
+    fn test_prose_mentioning_function_is_not_code() {
+        assert_ne!(
+            detect_language("This function returns the total number of stored memories."),
+            Some("javascript".to_string())
+        );
+    }
+}
@@ -0,0 +1,651 @@
+//! Structured filter expressions for memory listings
+//!
+//! A query combines conditions such as `category:context`, `tokens>500` or
+//! `content_contains:"two words"` with `AND`, `OR`, `NOT` and parentheses.
+//! `NOT` binds tighter than `AND`, which binds tighter than `OR`, so
+//! `a OR b AND NOT c` reads as `a OR (b AND (NOT c))`.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use std::fmt;
+
+use super::filter::MemoryFilter;
+use super::memory::Memory;
+
+/// Deepest nesting of parentheses and `NOT`s a query may use
+const MAX_DEPTH: usize = 64;
+
+/// Fields a condition can test, as listed in parse errors
+const FIELDS: &str = "category, mode, tag, tokens, created_after or content_contains";
+
+/// Parsed filter expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryQuery {
+    /// `category:X`
+    Category(String),
+    /// `mode:X`
+    Mode(String),
+    /// `tag:X`
+    Tag(String),
+    /// `tokens>N`
+    TokensAbove(usize),
+    /// `tokens<N`
+    TokensBelow(usize),
+    /// `created_after:YYYY-MM-DD`, from the start of that day in UTC
+    CreatedAfter(DateTime<Utc>),
+    /// `content_contains:"phrase"`, matched case-sensitively
+    ContentContains(String),
+    And(Box<MemoryQuery>, Box<MemoryQuery>),
+    Or(Box<MemoryQuery>, Box<MemoryQuery>),
+    Not(Box<MemoryQuery>),
+}
+
+impl MemoryQuery {
+    /// Parse a query into a filter holding it
+    pub fn parse(expr: &str) -> Result<MemoryFilter, ParseError> {
+        let tokens = tokenize(expr)?;
+        let mut parser = Parser {
+            tokens,
+            next: 0,
+            end: expr.len(),
+        };
+        let query = parser.parse_or(0)?;
+        if let Some(token) = parser.peek() {
+            return Err(ParseError::new(
+                token.position,
+                format!("expected AND or OR, found {}", token.kind),
+            ));
+        }
+        Ok(MemoryFilter {
+            query: Some(query),
+            ..MemoryFilter::default()
+        })
+    }
+
+    /// Check whether a memory meets the query
+    pub fn matches(&self, memory: &Memory) -> bool {
+        match self {
+            Self::Category(category) => memory.category.as_ref() == Some(category),
+            Self::Mode(mode) => memory.mode.as_ref() == Some(mode),
+            Self::Tag(tag) => memory.tags.contains(tag),
+            Self::TokensAbove(tokens) => memory.token_count.as_usize() > *tokens,
+            Self::TokensBelow(tokens) => memory.token_count.as_usize() < *tokens,
+            Self::CreatedAfter(from) => memory.created_at >= *from,
+            Self::ContentContains(text) => memory.content.contains(text.as_str()),
+            Self::And(left, right) => left.matches(memory) && right.matches(memory),
+            Self::Or(left, right) => left.matches(memory) || right.matches(memory),
+            Self::Not(query) => !query.matches(memory),
+        }
+    }
+
+    /// Check whether any condition of the query tests memory content
+    pub fn tests_content(&self) -> bool {
+        match self {
+            Self::ContentContains(_) => true,
+            Self::And(left, right) | Self::Or(left, right) => {
+                left.tests_content() || right.tests_content()
+            }
+            Self::Not(query) => query.tests_content(),
+            _ => false,
+        }
+    }
+}
+
+/// Reason a query could not be parsed, with the byte offset it was found at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Kind of a lexical token
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    /// Bare word, e.g. a field name, keyword or unquoted value
+    Word(String),
+    /// Double-quoted string, with escapes resolved
+    Quoted(String),
+    Colon,
+    Greater,
+    Less,
+    Open,
+    Close,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Word(word) => write!(f, "'{}'", word),
+            Self::Quoted(text) => write!(f, "\"{}\"", text),
+            Self::Colon => write!(f, "':'"),
+            Self::Greater => write!(f, "'>'"),
+            Self::Less => write!(f, "'<'"),
+            Self::Open => write!(f, "'('"),
+            Self::Close => write!(f, "')'"),
+        }
+    }
+}
+
+/// Lexical token and the byte offset it starts at
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+/// Split a query into tokens
+fn tokenize(expr: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        let kind = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            ':' => TokenKind::Colon,
+            '>' => TokenKind::Greater,
+            '<' => TokenKind::Less,
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped @ ('"' | '\\'))) => text.push(escaped),
+                            Some((at, other)) => {
+                                return Err(ParseError::new(
+                                    at,
+                                    format!("unknown escape '\\{}' in string", other),
+                                ))
+                            }
+                            None => {
+                                return Err(ParseError::new(
+                                    position,
+                                    "string is missing its closing quote",
+                                ))
+                            }
+                        },
+                        Some((_, other)) => text.push(other),
+                        None => {
+                            return Err(ParseError::new(
+                                position,
+                                "string is missing its closing quote",
+                            ))
+                        }
+                    }
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Quoted(text),
+                    position,
+                });
+                continue;
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, ':' | '>' | '<' | '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Word(word),
+                    position,
+                });
+                continue;
+            }
+        };
+        chars.next();
+        tokens.push(Token { kind, position });
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive-descent parser over the tokens of a query
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+    /// Length of the query, reported as the position of its end
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// Consume the next token if it is the given keyword, in any case
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(
+            self.peek(),
+            Some(Token { kind: TokenKind::Word(word), .. }) if word.eq_ignore_ascii_case(keyword)
+        );
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    /// `or := and (OR and)*`
+    fn parse_or(&mut self, depth: usize) -> Result<MemoryQuery, ParseError> {
+        let mut query = self.parse_and(depth)?;
+        while self.eat_keyword("OR") {
+            let right = self.parse_and(depth)?;
+            query = MemoryQuery::Or(Box::new(query), Box::new(right));
+        }
+        Ok(query)
+    }
+
+    /// `and := unary (AND unary)*`
+    fn parse_and(&mut self, depth: usize) -> Result<MemoryQuery, ParseError> {
+        let mut query = self.parse_unary(depth)?;
+        while self.eat_keyword("AND") {
+            let right = self.parse_unary(depth)?;
+            query = MemoryQuery::And(Box::new(query), Box::new(right));
+        }
+        Ok(query)
+    }
+
+    /// `unary := NOT unary | '(' or ')' | condition`
+    fn parse_unary(&mut self, depth: usize) -> Result<MemoryQuery, ParseError> {
+        let position = self.peek().map_or(self.end, |token| token.position);
+        if depth >= MAX_DEPTH {
+            return Err(ParseError::new(
+                position,
+                format!("query nests deeper than {} levels", MAX_DEPTH),
+            ));
+        }
+
+        if self.eat_keyword("NOT") {
+            return Ok(MemoryQuery::Not(Box::new(self.parse_unary(depth + 1)?)));
+        }
+        match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::Open) => {
+                self.next += 1;
+                let query = self.parse_or(depth + 1)?;
+                match self.advance() {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => Ok(query),
+                    Some(token) => Err(ParseError::new(
+                        token.position,
+                        format!(
+                            "expected ')' to close the '(' at position {}, found {}",
+                            position, token.kind
+                        ),
+                    )),
+                    None => Err(ParseError::new(
+                        self.end,
+                        format!("'(' at position {} is never closed", position),
+                    )),
+                }
+            }
+            _ => self.parse_condition(),
+        }
+    }
+
+    /// `condition := field (':' | '>' | '<') value`
+    fn parse_condition(&mut self) -> Result<MemoryQuery, ParseError> {
+        let (field, position) = match self.advance() {
+            Some(Token {
+                kind: TokenKind::Word(word),
+                position,
+            }) if !is_keyword(&word) => (word, position),
+            Some(token) => {
+                return Err(ParseError::new(
+                    token.position,
+                    format!("expected a condition, found {}", token.kind),
+                ))
+            }
+            None => {
+                return Err(ParseError::new(
+                    self.end,
+                    "expected a condition, found the end of the query",
+                ))
+            }
+        };
+
+        let operator = match self.advance().map(|token| (token.kind, token.position)) {
+            Some((TokenKind::Colon, _)) => ":",
+            Some((TokenKind::Greater, _)) => ">",
+            Some((TokenKind::Less, _)) => "<",
+            Some((kind, position)) => {
+                return Err(ParseError::new(
+                    position,
+                    format!("expected ':', '>' or '<' after '{}', found {}", field, kind),
+                ))
+            }
+            None => {
+                return Err(ParseError::new(
+                    self.end,
+                    format!(
+                        "expected ':', '>' or '<' after '{}', found the end of the query",
+                        field
+                    ),
+                ))
+            }
+        };
+
+        let (value, value_position) = match self.advance() {
+            Some(Token {
+                kind: TokenKind::Word(value) | TokenKind::Quoted(value),
+                position,
+            }) => (value, position),
+            Some(token) => {
+                return Err(ParseError::new(
+                    token.position,
+                    format!(
+                        "expected a value after '{}{}', found {}",
+                        field, operator, token.kind
+                    ),
+                ))
+            }
+            None => {
+                return Err(ParseError::new(
+                    self.end,
+                    format!(
+                        "expected a value after '{}{}', found the end of the query",
+                        field, operator
+                    ),
+                ))
+            }
+        };
+
+        let wrong_operator = |expected: &str| {
+            ParseError::new(
+                position,
+                format!("'{}' takes {}, not '{}'", field, expected, operator),
+            )
+        };
+        match (field.as_str(), operator) {
+            ("category", ":") => Ok(MemoryQuery::Category(value)),
+            ("mode", ":") => Ok(MemoryQuery::Mode(value)),
+            ("tag", ":") => Ok(MemoryQuery::Tag(value)),
+            ("content_contains", ":") => Ok(MemoryQuery::ContentContains(value)),
+            ("created_after", ":") => {
+                let date = NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(|_| {
+                    ParseError::new(
+                        value_position,
+                        format!("'{}' is not a date in the form YYYY-MM-DD", value),
+                    )
+                })?;
+                Ok(MemoryQuery::CreatedAfter(
+                    date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+                ))
+            }
+            ("tokens", ">" | "<") => {
+                let tokens = value.parse().map_err(|_| {
+                    ParseError::new(
+                        value_position,
+                        format!("'{}' is not a whole number of tokens", value),
+                    )
+                })?;
+                Ok(if operator == ">" {
+                    MemoryQuery::TokensAbove(tokens)
+                } else {
+                    MemoryQuery::TokensBelow(tokens)
+                })
+            }
+            ("category" | "mode" | "tag" | "content_contains" | "created_after", _) => {
+                Err(wrong_operator("':'"))
+            }
+            ("tokens", _) => Err(wrong_operator("'>' or '<'")),
+            _ => Err(ParseError::new(
+                position,
+                format!("unknown field '{}'; expected {}", field, FIELDS),
+            )),
+        }
+    }
+}
+
+/// Check whether a word is one of the query keywords
+fn is_keyword(word: &str) -> bool {
+    ["AND", "OR", "NOT"]
+        .iter()
+        .any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tokenizer::Tokenizer;
+    use std::collections::HashMap;
+
+    fn parse(expr: &str) -> MemoryQuery {
+        MemoryQuery::parse(expr)
+            .unwrap_or_else(|e| panic!("{:?} failed to parse: {}", expr, e))
+            .query
+            .unwrap()
+    }
+
+    fn parse_error(expr: &str) -> ParseError {
+        MemoryQuery::parse(expr).unwrap_err()
+    }
+
+    fn category(category: &str) -> MemoryQuery {
+        MemoryQuery::Category(category.to_string())
+    }
+
+    fn mode(mode: &str) -> MemoryQuery {
+        MemoryQuery::Mode(mode.to_string())
+    }
+
+    fn and(left: MemoryQuery, right: MemoryQuery) -> MemoryQuery {
+        MemoryQuery::And(Box::new(left), Box::new(right))
+    }
+
+    fn or(left: MemoryQuery, right: MemoryQuery) -> MemoryQuery {
+        MemoryQuery::Or(Box::new(left), Box::new(right))
+    }
+
+    fn not(query: MemoryQuery) -> MemoryQuery {
+        MemoryQuery::Not(Box::new(query))
+    }
+
+    fn day(date: &str) -> DateTime<Utc> {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn test_parse_conditions() {
+        assert_eq!(parse("category:context"), category("context"));
+        assert_eq!(parse("mode:code"), mode("code"));
+        assert_eq!(parse("tag:rust"), MemoryQuery::Tag("rust".to_string()));
+        assert_eq!(parse("tokens>500"), MemoryQuery::TokensAbove(500));
+        assert_eq!(parse("tokens < 20"), MemoryQuery::TokensBelow(20));
+        assert_eq!(
+            parse("created_after:2024-01-01"),
+            MemoryQuery::CreatedAfter(day("2024-01-01"))
+        );
+        assert_eq!(
+            parse(r#"content_contains:"two words""#),
+            MemoryQuery::ContentContains("two words".to_string())
+        );
+        assert_eq!(
+            parse(r#"content_contains:"say \"hi\" \\ bye""#),
+            MemoryQuery::ContentContains(r#"say "hi" \ bye"#.to_string())
+        );
+        assert_eq!(
+            parse("content_contains:main"),
+            MemoryQuery::ContentContains("main".to_string())
+        );
+        assert_eq!(parse(r#"category:"and""#), category("and"));
+    }
+
+    #[test]
+    fn test_parse_operators_and_precedence() {
+        assert_eq!(
+            parse("category:context AND mode:code AND tokens>500 AND created_after:2024-01-01"),
+            and(
+                and(
+                    and(category("context"), mode("code")),
+                    MemoryQuery::TokensAbove(500)
+                ),
+                MemoryQuery::CreatedAfter(day("2024-01-01"))
+            )
+        );
+        assert_eq!(
+            parse("category:a OR category:b AND NOT mode:c"),
+            or(category("a"), and(category("b"), not(mode("c"))))
+        );
+        assert_eq!(
+            parse("(category:a OR category:b) AND mode:c"),
+            and(or(category("a"), category("b")), mode("c"))
+        );
+        assert_eq!(parse("NOT NOT mode:code"), not(not(mode("code"))));
+        assert_eq!(
+            parse("NOT (mode:code OR mode:debug)"),
+            not(or(mode("code"), mode("debug")))
+        );
+        assert_eq!(parse("((mode:code))"), mode("code"));
+        assert_eq!(
+            parse("mode:code and not category:history or tag:x"),
+            or(
+                and(mode("code"), not(category("history"))),
+                MemoryQuery::Tag("x".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("", 0, "expected a condition, found the end of the query"),
+            ("   ", 3, "expected a condition, found the end of the query"),
+            (
+                "colour:red",
+                0,
+                "unknown field 'colour'; expected category, mode, tag, tokens, created_after or content_contains",
+            ),
+            ("category>5", 0, "'category' takes ':', not '>'"),
+            ("tokens:500", 0, "'tokens' takes '>' or '<', not ':'"),
+            ("tokens>many", 7, "'many' is not a whole number of tokens"),
+            ("tokens>-1", 7, "'-1' is not a whole number of tokens"),
+            (
+                "created_after:yesterday",
+                14,
+                "'yesterday' is not a date in the form YYYY-MM-DD",
+            ),
+            (
+                "created_after:2024-13-01",
+                14,
+                "'2024-13-01' is not a date in the form YYYY-MM-DD",
+            ),
+            (
+                "category:",
+                9,
+                "expected a value after 'category:', found the end of the query",
+            ),
+            (
+                "mode",
+                4,
+                "expected ':', '>' or '<' after 'mode', found the end of the query",
+            ),
+            ("mode code", 5, "expected ':', '>' or '<' after 'mode', found 'code'"),
+            (r#"content_contains:"open"#, 17, "string is missing its closing quote"),
+            (r#"content_contains:"\n""#, 19, "unknown escape '\\n' in string"),
+            ("mode:code AND", 13, "expected a condition, found the end of the query"),
+            ("AND mode:code", 0, "expected a condition, found 'AND'"),
+            ("mode:code mode:debug", 10, "expected AND or OR, found 'mode'"),
+            ("(mode:code", 10, "'(' at position 0 is never closed"),
+            (
+                "(mode:code category:x)",
+                11,
+                "expected ')' to close the '(' at position 0, found 'category'",
+            ),
+            ("mode:code)", 9, "expected AND or OR, found ')'"),
+            ("()", 1, "expected a condition, found ')'"),
+            ("NOT", 3, "expected a condition, found the end of the query"),
+        ];
+        for (expr, position, message) in cases {
+            assert_eq!(
+                parse_error(expr),
+                ParseError::new(position, message),
+                "parsing {:?}",
+                expr
+            );
+        }
+
+        let deep = format!("{}mode:code{}", "(".repeat(100), ")".repeat(100));
+        assert_eq!(
+            parse_error(&deep),
+            ParseError::new(64, "query nests deeper than 64 levels")
+        );
+        assert_eq!(
+            parse_error("tokens>x").to_string(),
+            "'x' is not a whole number of tokens at position 7"
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let tokenizer = Tokenizer::default();
+        let mut memory = Memory::new(
+            "the grpc server listens on port 50051".to_string(),
+            "text/plain".to_string(),
+            Some("context".to_string()),
+            Some("code".to_string()),
+            HashMap::new(),
+            &tokenizer,
+        );
+        memory.tags = vec!["rust".to_string()];
+        let tokens = memory.token_count.as_usize();
+        let matches = |expr: &str| parse(expr).matches(&memory);
+
+        assert!(matches("category:context AND mode:code"));
+        assert!(!matches("category:context AND mode:debug"));
+        assert!(matches("mode:debug OR tag:rust"));
+        assert!(matches("NOT tag:python"));
+        assert!(matches(&format!(
+            "tokens>{} AND tokens<{}",
+            tokens - 1,
+            tokens + 1
+        )));
+        assert!(!matches(&format!("tokens>{}", tokens)));
+        assert!(matches(r#"content_contains:"grpc server""#));
+        assert!(!matches(r#"content_contains:"GRPC""#));
+        assert!(matches("created_after:2024-01-01"));
+        assert!(!matches("created_after:2999-01-01"));
+        assert!(!matches("NOT (category:context OR category:decision)"));
+    }
+
+    #[test]
+    fn test_tests_content() {
+        assert!(parse(r#"NOT (mode:code OR content_contains:"x")"#).tests_content());
+        assert!(!parse("mode:code AND tokens>5").tests_content());
+    }
+}
@@ -0,0 +1,202 @@
+//! Safety checks for user-supplied regular expressions
+//!
+//! Regex searches run inside SQLite through the `regexp` function, so a
+//! pathological pattern would stall the database connection. Patterns are
+//! validated here before they ever reach a query.
+
+use regex::Regex;
+use std::fmt;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Constructs known to cause catastrophic backtracking in backtracking engines
+const DENIED_CONSTRUCTS: &[&str] = &["(.*)+", "(.+)+", "(.*)*", "(.+)*", "(.*){", "(.+){"];
+
+/// Length of the probe string used for the timing check
+const PROBE_LENGTH: usize = 1000;
+
+/// Reason a regex pattern was rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegexSafetyError {
+    /// The pattern is not a valid regular expression
+    InvalidPattern(String),
+    /// The pattern contains a construct from the deny-list
+    DeniedConstruct(String),
+    /// The pattern contains a quantified group that itself contains a quantifier
+    NestedQuantifier,
+    /// Matching the probe string exceeded the time limit
+    Timeout,
+}
+
+impl fmt::Display for RegexSafetyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegexSafetyError::InvalidPattern(e) => write!(f, "invalid regex: {}", e),
+            RegexSafetyError::DeniedConstruct(construct) => write!(
+                f,
+                "regex would cause excessive backtracking: `{}` repeats a group that can \
+                 already match any text; remove the outer quantifier",
+                construct
+            ),
+            RegexSafetyError::NestedQuantifier => write!(
+                f,
+                "regex would cause excessive backtracking: a quantified group contains \
+                 another quantifier (e.g. `(a+)+`); flatten it to a single quantifier"
+            ),
+            RegexSafetyError::Timeout => write!(
+                f,
+                "regex would cause excessive backtracking: matching a {}-character probe \
+                 string took too long; simplify the pattern",
+                PROBE_LENGTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RegexSafetyError {}
+
+/// Validator that rejects regex patterns prone to catastrophic backtracking
+#[derive(Debug, Clone)]
+pub struct RegexSafetyCheck {
+    /// Maximum time allowed to match the probe string
+    timeout: Duration,
+}
+
+impl RegexSafetyCheck {
+    /// Create a new safety check with the default 10ms timeout
+    pub fn new() -> Self {
+        Self {
+            timeout: Duration::from_millis(10),
+        }
+    }
+
+    /// Validate a pattern and return the compiled regex if it is safe
+    pub fn check(&self, pattern: &str) -> Result<Regex, RegexSafetyError> {
+        // Parse the pattern first so syntax errors are reported as such
+        let regex =
+            Regex::new(pattern).map_err(|e| RegexSafetyError::InvalidPattern(e.to_string()))?;
+
+        // Reject known catastrophic constructs
+        let compact: String = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+        if let Some(construct) = DENIED_CONSTRUCTS.iter().find(|c| compact.contains(*c)) {
+            return Err(RegexSafetyError::DeniedConstruct(construct.to_string()));
+        }
+
+        if Self::has_nested_quantifier(&compact) {
+            return Err(RegexSafetyError::NestedQuantifier);
+        }
+
+        // Time a match against the probe string on a separate thread. The thread
+        // signals once it is ready so that spawn latency is not counted.
+        let probe_regex = regex.clone();
+        let (ready_sender, ready_receiver) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let probe = "a".repeat(PROBE_LENGTH);
+            let _ = ready_sender.send(());
+            let _ = sender.send(probe_regex.is_match(&probe));
+        });
+
+        if ready_receiver.recv().is_err() {
+            return Err(RegexSafetyError::Timeout);
+        }
+
+        match receiver.recv_timeout(self.timeout) {
+            Ok(_) => Ok(regex),
+            Err(_) => Err(RegexSafetyError::Timeout),
+        }
+    }
+
+    /// Check whether a quantified group contains an inner quantifier, e.g. `(a+)+`
+    fn has_nested_quantifier(pattern: &str) -> bool {
+        let chars: Vec<char> = pattern.chars().collect();
+        // Each open group records whether it contains a quantifier so far
+        let mut groups: Vec<bool> = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                '\\' => i += 1,
+                '(' => groups.push(false),
+                '+' | '*' | '{' => {
+                    if let Some(inner) = groups.last_mut() {
+                        *inner = true;
+                    }
+                }
+                ')' => {
+                    let inner_quantified = groups.pop().unwrap_or(false);
+                    let outer_quantified = matches!(chars.get(i + 1), Some('+' | '*' | '{'));
+                    if inner_quantified && outer_quantified {
+                        return true;
+                    }
+                    // A quantifier inside a nested group also counts for the parent
+                    if inner_quantified {
+                        if let Some(parent) = groups.last_mut() {
+                            *parent = true;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        false
+    }
+}
+
+impl Default for RegexSafetyCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_simple_patterns() {
+        let check = RegexSafetyCheck::new();
+        assert!(check.check("fn \\w+\\(").is_ok());
+        assert!(check.check("error: .*").is_ok());
+        assert!(check.check("(foo|bar)+").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_denied_constructs() {
+        let check = RegexSafetyCheck::new();
+        assert_eq!(
+            check.check("(.*)+x").unwrap_err(),
+            RegexSafetyError::DeniedConstruct("(.*)+".to_string())
+        );
+        assert!(matches!(
+            check.check("(.+)*"),
+            Err(RegexSafetyError::DeniedConstruct(_))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_nested_quantifiers() {
+        let check = RegexSafetyCheck::new();
+        assert_eq!(
+            check.check("(a+)+").unwrap_err(),
+            RegexSafetyError::NestedQuantifier
+        );
+        assert_eq!(
+            check.check("((ab)*c)+").unwrap_err(),
+            RegexSafetyError::NestedQuantifier
+        );
+        assert!(check.check("\\(a+\\)+").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_invalid_patterns() {
+        let check = RegexSafetyCheck::new();
+        assert!(matches!(
+            check.check("(unclosed"),
+            Err(RegexSafetyError::InvalidPattern(_))
+        ));
+    }
+}
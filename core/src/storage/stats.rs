@@ -0,0 +1,171 @@
+//! Statistics over stored memories
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Key under which memories without a category are counted
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+/// Key under which memories without a mode are counted
+pub const NO_MODE: &str = "none";
+
+/// Number and tokens of the memories in one category
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategoryStats {
+    /// Number of memories in the category
+    pub memory_count: usize,
+    /// Total tokens of the memories in the category
+    pub token_count: usize,
+}
+
+/// Number and tokens of the memories in one mode
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModeStats {
+    /// Number of memories in the mode
+    pub memory_count: usize,
+    /// Total tokens of the memories in the mode
+    pub token_count: usize,
+}
+
+/// Memory counts and tokens, overall and per category and mode
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryStats {
+    /// Number of memories
+    pub total_count: usize,
+    /// Total tokens of all memories
+    pub total_tokens: usize,
+    /// Stats per category, with uncategorized memories under [`UNCATEGORIZED`]
+    pub by_category: HashMap<String, CategoryStats>,
+    /// Stats per mode, with memories without a mode under [`NO_MODE`]
+    pub by_mode: HashMap<String, ModeStats>,
+    /// Number of pinned memories
+    #[serde(default)]
+    pub pinned_count: usize,
+}
+
+impl MemoryStats {
+    /// Count one memory
+    pub fn add(&mut self, category: Option<&str>, mode: Option<&str>, tokens: usize) {
+        self.total_count += 1;
+        self.total_tokens += tokens;
+
+        let category = self
+            .by_category
+            .entry(category.unwrap_or(UNCATEGORIZED).to_string())
+            .or_default();
+        category.memory_count += 1;
+        category.token_count += tokens;
+
+        let mode = self
+            .by_mode
+            .entry(mode.unwrap_or(NO_MODE).to_string())
+            .or_default();
+        mode.memory_count += 1;
+        mode.token_count += tokens;
+    }
+}
+
+/// Distribution of memory sizes in tokens
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SizeDistribution {
+    /// 25th percentile token count
+    pub p25_tokens: usize,
+    /// Median token count
+    pub p50_tokens: usize,
+    /// 75th percentile token count
+    pub p75_tokens: usize,
+    /// 90th percentile token count
+    pub p90_tokens: usize,
+    /// 99th percentile token count
+    pub p99_tokens: usize,
+    /// Mean token count
+    pub mean_tokens: f64,
+    /// Population standard deviation of the token count
+    pub std_dev_tokens: f64,
+    /// Largest token count
+    pub max_tokens: usize,
+    /// Smallest token count
+    pub min_tokens: usize,
+    /// Number of memories measured
+    pub total_count: usize,
+}
+
+impl SizeDistribution {
+    /// Compute the distribution of a set of token counts
+    pub fn from_token_counts(mut counts: Vec<usize>) -> Self {
+        if counts.is_empty() {
+            return Self::default();
+        }
+
+        counts.sort_unstable();
+        let total_count = counts.len();
+        let mean_tokens = counts.iter().sum::<usize>() as f64 / total_count as f64;
+        let variance = counts
+            .iter()
+            .map(|&count| (count as f64 - mean_tokens).powi(2))
+            .sum::<f64>()
+            / total_count as f64;
+
+        Self {
+            p25_tokens: percentile(&counts, 25),
+            p50_tokens: percentile(&counts, 50),
+            p75_tokens: percentile(&counts, 75),
+            p90_tokens: percentile(&counts, 90),
+            p99_tokens: percentile(&counts, 99),
+            mean_tokens,
+            std_dev_tokens: variance.sqrt(),
+            max_tokens: counts[total_count - 1],
+            min_tokens: counts[0],
+            total_count,
+        }
+    }
+}
+
+/// Nearest-rank percentile of a sorted, non-empty slice
+pub(super) fn percentile(sorted: &[usize], percent: usize) -> usize {
+    let rank = (percent * sorted.len()).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_distribution() {
+        let distribution = SizeDistribution::from_token_counts((1..=100).rev().collect());
+
+        assert_eq!(distribution.total_count, 100);
+        assert_eq!(distribution.min_tokens, 1);
+        assert_eq!(distribution.max_tokens, 100);
+        assert_eq!(distribution.p25_tokens, 25);
+        assert_eq!(distribution.p50_tokens, 50);
+        assert_eq!(distribution.p90_tokens, 90);
+        assert_eq!(distribution.p99_tokens, 99);
+        assert!((distribution.mean_tokens - 50.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_memory_stats_add() {
+        let mut stats = MemoryStats::default();
+        stats.add(Some("decision"), Some("code"), 10);
+        stats.add(Some("decision"), None, 5);
+        stats.add(None, Some("code"), 1);
+
+        assert_eq!(stats.total_count, 3);
+        assert_eq!(stats.total_tokens, 16);
+        assert_eq!(stats.by_category["decision"].memory_count, 2);
+        assert_eq!(stats.by_category["decision"].token_count, 15);
+        assert_eq!(stats.by_category[UNCATEGORIZED].token_count, 1);
+        assert_eq!(stats.by_mode["code"].token_count, 11);
+        assert_eq!(stats.by_mode[NO_MODE].memory_count, 1);
+    }
+
+    #[test]
+    fn test_empty_size_distribution() {
+        assert_eq!(
+            SizeDistribution::from_token_counts(Vec::new()),
+            SizeDistribution::default()
+        );
+    }
+}
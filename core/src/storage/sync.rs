@@ -0,0 +1,141 @@
+//! Memory transfer between server instances
+//!
+//! Memories are exchanged as zstd-compressed JSON so a sync between two
+//! servers ships a single compact payload per direction.
+
+use anyhow::{bail, Context, Result};
+use std::io::Read;
+
+use super::memory::{Memory, MemoryId};
+
+/// zstd compression level used for sync payloads
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Largest size a received payload may decompress to
+const MAX_DECODED_PAYLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+/// How an imported memory is reconciled with an existing memory of the same ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Keep whichever version was accessed most recently
+    NewerWins,
+    /// Keep the memory already stored on the importing side
+    KeepExisting,
+    /// Replace the stored memory with the incoming one
+    PreferIncoming,
+    /// Import nothing if any incoming memory conflicts with a stored one
+    Fail,
+}
+
+impl ConflictResolution {
+    /// Parse a resolution name as used on the wire
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "" | "newer_wins" => Some(Self::NewerWins),
+            "keep_existing" => Some(Self::KeepExisting),
+            "prefer_incoming" => Some(Self::PreferIncoming),
+            "fail" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+
+    /// Get the wire name of the resolution
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NewerWins => "newer_wins",
+            Self::KeepExisting => "keep_existing",
+            Self::PreferIncoming => "prefer_incoming",
+            Self::Fail => "fail",
+        }
+    }
+
+    /// Decide whether the incoming memory replaces the existing one
+    pub fn prefers_incoming(&self, existing: &Memory, incoming: &Memory) -> bool {
+        match self {
+            Self::NewerWins => incoming.last_accessed > existing.last_accessed,
+            Self::KeepExisting | Self::Fail => false,
+            Self::PreferIncoming => true,
+        }
+    }
+}
+
+/// An import under [`ConflictResolution::Fail`] met a memory that conflicts with a stored one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportConflict(pub MemoryId);
+
+impl std::fmt::Display for ImportConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "memory {} already exists with different contents",
+            self.0.as_str()
+        )
+    }
+}
+
+impl std::error::Error for ImportConflict {}
+
+/// Outcome of importing a batch of memories
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    /// Memories written to the store
+    pub imported: usize,
+    /// Conflicting memories that were resolved, whichever side won
+    pub conflicts_resolved: usize,
+}
+
+/// Outcome of importing a JSON export
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportResult {
+    /// Memories written to the store
+    pub imported: u64,
+    /// Valid entries that were not written, being identical to or losing against a stored memory
+    pub skipped: u64,
+    /// Entries that could not be read as memories
+    pub errors: Vec<String>,
+}
+
+/// Serialize and compress memories for transfer
+pub fn encode_memories(memories: &[Memory]) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(memories).context("Failed to serialize memories")?;
+    zstd::encode_all(json.as_slice(), COMPRESSION_LEVEL).context("Failed to compress memories")
+}
+
+/// Decompress and deserialize memories received from a peer
+///
+/// Payloads decompressing to more than `MAX_DECODED_PAYLOAD_BYTES` are rejected
+/// without decompressing the rest.
+pub fn decode_memories(payload: &[u8]) -> Result<Vec<Memory>> {
+    let mut json = Vec::new();
+    zstd::stream::read::Decoder::new(payload)
+        .context("Failed to decompress memories")?
+        .take(MAX_DECODED_PAYLOAD_BYTES + 1)
+        .read_to_end(&mut json)
+        .context("Failed to decompress memories")?;
+    if json.len() as u64 > MAX_DECODED_PAYLOAD_BYTES {
+        bail!(
+            "Sync payload decompresses to more than {} bytes",
+            MAX_DECODED_PAYLOAD_BYTES
+        );
+    }
+    serde_json::from_slice(&json).context("Failed to deserialize memories")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_rejects_oversized_payloads() {
+        // Zeros compress to a tiny payload that would expand past the cap
+        let zeros = std::io::repeat(0).take(MAX_DECODED_PAYLOAD_BYTES + 1);
+        let payload = zstd::stream::encode_all(zeros, COMPRESSION_LEVEL).unwrap();
+        assert!(payload.len() < 1024 * 1024);
+
+        let error = decode_memories(&payload).unwrap_err();
+        assert!(error.to_string().contains("decompresses to more than"));
+
+        let payload = encode_memories(&[]).unwrap();
+        assert!(decode_memories(&payload).unwrap().is_empty());
+    }
+}
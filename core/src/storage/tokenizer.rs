@@ -1,6 +1,7 @@
 //! Tokenization utilities for memory content
 
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, AddAssign};
 use std::path::Path;
 use std::sync::Arc;
@@ -8,7 +9,8 @@ use tokenizers::models::bpe::BPE;
 use tokenizers::Tokenizer as HfTokenizer;
 
 /// Count of tokens in a piece of content
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct TokenCount(pub usize);
 
 impl TokenCount {
@@ -55,6 +57,52 @@ impl std::iter::Sum for TokenCount {
     }
 }
 
+/// Price of the tokens sent to and generated by a model, in US dollars
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenPricing {
+    /// Cost of 1000 prompt tokens
+    pub input_cost_per_1k: f64,
+    /// Cost of 1000 generated tokens
+    pub output_cost_per_1k: f64,
+}
+
+/// List prices of well-known models, keyed by model name prefix
+const MODEL_PRICING: &[(&str, TokenPricing)] = &[
+    ("gpt-4", TokenPricing::new(0.03, 0.06)),
+    ("gpt-4-turbo", TokenPricing::new(0.01, 0.03)),
+    ("gpt-4o", TokenPricing::new(0.005, 0.015)),
+    ("gpt-4o-mini", TokenPricing::new(0.00015, 0.0006)),
+    ("gpt-3.5-turbo", TokenPricing::new(0.0005, 0.0015)),
+    ("claude-3-opus", TokenPricing::new(0.015, 0.075)),
+    ("claude-3-sonnet", TokenPricing::new(0.003, 0.015)),
+    ("claude-3-haiku", TokenPricing::new(0.00025, 0.00125)),
+    ("claude-3-5-sonnet", TokenPricing::new(0.003, 0.015)),
+    ("claude-3-5-haiku", TokenPricing::new(0.0008, 0.004)),
+];
+
+impl TokenPricing {
+    /// Create a pricing from the costs of 1000 input and output tokens
+    pub const fn new(input_cost_per_1k: f64, output_cost_per_1k: f64) -> Self {
+        Self {
+            input_cost_per_1k,
+            output_cost_per_1k,
+        }
+    }
+
+    /// Look up the built-in pricing of `model`
+    ///
+    /// Dated or suffixed names such as `claude-3-opus-20240229` match the
+    /// longest known prefix.
+    pub fn for_model(model: &str) -> Option<Self> {
+        let model = model.trim().to_ascii_lowercase();
+        MODEL_PRICING
+            .iter()
+            .filter(|(name, _)| model.starts_with(name))
+            .max_by_key(|(name, _)| name.len())
+            .map(|(_, pricing)| *pricing)
+    }
+}
+
 /// Type of tokenizer to use
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TokenizerType {
@@ -98,6 +146,15 @@ impl Tokenizer {
         })
     }
 
+    /// Create a tokenizer that approximates `tokenizer_type` without loading a model
+    #[cfg(test)]
+    pub(crate) fn approximate(tokenizer_type: TokenizerType) -> Self {
+        Self {
+            tokenizer_type,
+            hf_tokenizer: None,
+        }
+    }
+
     /// Load the GPT-2 tokenizer
     fn load_gpt2_tokenizer() -> Result<HfTokenizer> {
         // Check if the tokenizer files exist in the models directory
@@ -183,6 +240,131 @@ impl Tokenizer {
             }
         }
     }
+
+    /// Count the tokens in a string, stopping once the count exceeds `max_count`
+    ///
+    /// Returns the count and whether it exceeds `max_count`; a count that
+    /// exceeds it is reported as `max_count + 1`. Neural tokenizers encode the
+    /// whole string before the count is capped.
+    pub fn count_tokens_partial(&self, text: &str, max_count: usize) -> (TokenCount, bool) {
+        let count = match self.tokenizer_type {
+            TokenizerType::Simple => text.split_whitespace().take(max_count + 1).count(),
+            TokenizerType::Gpt2 | TokenizerType::Cl100k => self.count_tokens(text).as_usize(),
+        };
+        if count > max_count {
+            (TokenCount(max_count + 1), true)
+        } else {
+            (TokenCount(count), false)
+        }
+    }
+
+    /// Estimate the cost in US dollars of sending `token_count` tokens to `model`
+    ///
+    /// The tokens are priced as input; counts from a tokenizer other than the
+    /// model's own are only approximate, and so is the cost.
+    pub fn estimate_cost(
+        &self,
+        token_count: TokenCount,
+        model: &str,
+        pricing: &TokenPricing,
+    ) -> f64 {
+        let cost = token_count.as_usize() as f64 / 1000.0 * pricing.input_cost_per_1k;
+        crate::log_debug!(
+            "tokenizer",
+            &format!(
+                "Estimated ${:.6} for {} {:?} tokens sent to {}",
+                cost,
+                token_count.as_usize(),
+                self.tokenizer_type,
+                model
+            )
+        );
+        cost
+    }
+
+    /// Get the longest prefix of `text` with at most `max_tokens` tokens
+    ///
+    /// The prefix always ends on a UTF-8 character boundary. The simple tokenizer
+    /// cuts after a word; neural tokenizers cut where their decoded tokens end.
+    pub fn truncate_to_limit<'a>(&self, text: &'a str, max_tokens: TokenCount) -> &'a str {
+        let max_tokens = max_tokens.as_usize();
+        let fits = |end: usize| !self.count_tokens_partial(&text[..end], max_tokens).1;
+        if fits(text.len()) {
+            return text;
+        }
+        if max_tokens == 0 {
+            return "";
+        }
+
+        let cut_points: Vec<usize> = match self.tokenizer_type {
+            TokenizerType::Simple => word_ends(text),
+            TokenizerType::Gpt2 | TokenizerType::Cl100k => {
+                let boundaries = char_boundaries(text);
+                // Start the search where the first `max_tokens` tokens decode to
+                let decoded_end = self
+                    .decoded_prefix_len(text, max_tokens)
+                    .map(|len| boundaries.partition_point(|&end| end <= len))
+                    .filter(|&count| count > 0 && fits(boundaries[count - 1]));
+                match decoded_end {
+                    Some(count) => boundaries[count - 1..].to_vec(),
+                    None => boundaries,
+                }
+            }
+        };
+
+        // Largest cut point that fits; cut points are sorted by offset
+        let (mut low, mut high) = (0, cut_points.len());
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if fits(cut_points[mid - 1]) {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        match low {
+            0 => "",
+            count => &text[..cut_points[count - 1]],
+        }
+    }
+
+    /// Get the byte length of the first `max_tokens` tokens of `text` once decoded
+    fn decoded_prefix_len(&self, text: &str, max_tokens: usize) -> Option<usize> {
+        let tokenizer = self.hf_tokenizer.as_ref()?;
+        let encoding = tokenizer.encode(text, false).ok()?;
+        let ids = encoding.get_ids();
+        let decoded = tokenizer
+            .decode(&ids[..max_tokens.min(ids.len())], false)
+            .ok()?;
+        Some(decoded.len())
+    }
+}
+
+/// Get the byte offset at the end of each whitespace-separated word
+fn word_ends(text: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut in_word = false;
+    for (offset, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if in_word {
+                ends.push(offset);
+            }
+            in_word = false;
+        } else {
+            in_word = true;
+        }
+    }
+    if in_word {
+        ends.push(text.len());
+    }
+    ends
+}
+
+/// Get the byte offset of every non-empty prefix ending on a character boundary
+fn char_boundaries(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(offset, c)| offset + c.len_utf8())
+        .collect()
 }
 
 impl Default for Tokenizer {
@@ -195,3 +377,132 @@ impl Default for Tokenizer {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_pricing_for_model() {
+        assert_eq!(
+            TokenPricing::for_model("gpt-4"),
+            Some(TokenPricing::new(0.03, 0.06))
+        );
+        assert_eq!(
+            TokenPricing::for_model("gpt-4-turbo-2024-04-09"),
+            Some(TokenPricing::new(0.01, 0.03))
+        );
+        assert_eq!(
+            TokenPricing::for_model("Claude-3-Opus-20240229"),
+            Some(TokenPricing::new(0.015, 0.075))
+        );
+        assert_eq!(
+            TokenPricing::for_model("gpt-3.5-turbo"),
+            Some(TokenPricing::new(0.0005, 0.0015))
+        );
+        assert_eq!(TokenPricing::for_model("llama-3"), None);
+        assert_eq!(TokenPricing::for_model(""), None);
+    }
+
+    #[test]
+    fn test_estimate_cost() {
+        let tokenizer = Tokenizer::default();
+        let cost = |tokens: usize, model: &str| {
+            let pricing = TokenPricing::for_model(model).unwrap();
+            tokenizer.estimate_cost(TokenCount(tokens), model, &pricing)
+        };
+
+        assert!((cost(1000, "gpt-4") - 0.03).abs() < 1e-12);
+        assert!((cost(2500, "claude-3-opus") - 0.0375).abs() < 1e-12);
+        assert!((cost(4000, "gpt-3.5-turbo") - 0.002).abs() < 1e-12);
+        assert_eq!(cost(0, "gpt-4"), 0.0);
+
+        let custom = TokenPricing::new(1.0, 2.0);
+        assert_eq!(
+            tokenizer.estimate_cost(TokenCount(500), "in-house", &custom),
+            0.5
+        );
+    }
+
+    #[test]
+    fn test_count_tokens_partial() {
+        let tokenizer = Tokenizer::default();
+        assert_eq!(
+            tokenizer.count_tokens_partial("one two three", 5),
+            (TokenCount(3), false)
+        );
+        assert_eq!(
+            tokenizer.count_tokens_partial("one two three", 3),
+            (TokenCount(3), false)
+        );
+        assert_eq!(
+            tokenizer.count_tokens_partial("one two three", 1),
+            (TokenCount(2), true)
+        );
+        assert_eq!(
+            tokenizer.count_tokens_partial("", 0),
+            (TokenCount(0), false)
+        );
+    }
+
+    #[test]
+    fn test_truncate_to_limit_cuts_after_a_word() {
+        let tokenizer = Tokenizer::default();
+        let text = "  one two\tthree  four ";
+        assert_eq!(
+            tokenizer.truncate_to_limit(text, TokenCount(2)),
+            "  one two"
+        );
+        assert_eq!(tokenizer.truncate_to_limit(text, TokenCount(4)), text);
+        assert_eq!(tokenizer.truncate_to_limit(text, TokenCount(0)), "");
+        assert_eq!(
+            tokenizer.truncate_to_limit("héllo wörld", TokenCount(1)),
+            "héllo"
+        );
+    }
+
+    #[test]
+    fn test_truncate_to_limit_without_a_neural_model() {
+        // Without a loaded model tokens are approximated from the byte length
+        let tokenizer = Tokenizer {
+            tokenizer_type: TokenizerType::Cl100k,
+            hf_tokenizer: None,
+        };
+        let text = "ünïcödé ".repeat(20);
+        let truncated = tokenizer.truncate_to_limit(&text, TokenCount(10));
+        assert!(text.starts_with(truncated));
+        assert_eq!(tokenizer.count_tokens(truncated), TokenCount(10));
+        // One more character no longer fits
+        let next = text[truncated.len()..].chars().next().unwrap();
+        let longer = &text[..truncated.len() + next.len_utf8()];
+        assert!(tokenizer.count_tokens(longer) > TokenCount(10));
+    }
+
+    proptest! {
+        #[test]
+        fn test_truncate_to_limit_never_exceeds_limit(
+            text in "\\PC{0,200}",
+            max_tokens in 0usize..40,
+            neural in any::<bool>(),
+        ) {
+            let tokenizer = if neural {
+                Tokenizer {
+                    tokenizer_type: TokenizerType::Gpt2,
+                    hf_tokenizer: None,
+                }
+            } else {
+                Tokenizer::default()
+            };
+            let truncated = tokenizer.truncate_to_limit(&text, TokenCount(max_tokens));
+
+            prop_assert!(text.starts_with(truncated));
+            prop_assert!(
+                truncated.is_empty() || tokenizer.count_tokens(truncated).as_usize() <= max_tokens
+            );
+            if tokenizer.count_tokens(&text).as_usize() <= max_tokens {
+                prop_assert_eq!(truncated, text.as_str());
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Change notifications from the memory store to internal consumers
+//!
+//! Caches and metrics kept beside the store can follow its writes through
+//! [`MemoryStore::watch`](super::MemoryStore::watch) instead of polling. A
+//! watcher falling more than `EVENT_CHANNEL_CAPACITY` events behind is
+//! dropped and counted, since it can no longer tell what it missed.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::memory::MemoryId;
+
+/// Environment variable holding the number of events a watcher may fall behind
+const EVENT_CHANNEL_CAPACITY_VAR: &str = "EVENT_CHANNEL_CAPACITY";
+
+/// Default number of events a watcher may fall behind
+const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Kind of write to a memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreEventKind {
+    Stored,
+    Updated,
+    Deleted,
+    /// Deleted because its TTL ran out
+    Expired,
+}
+
+/// One write to the memory store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreEvent {
+    pub kind: StoreEventKind,
+    pub memory_id: MemoryId,
+    /// Change in the store's total token count
+    pub token_delta: i64,
+}
+
+/// Sends store events to every watcher and counts the watchers dropped for lagging
+#[derive(Debug)]
+pub(crate) struct StoreEvents {
+    sender: broadcast::Sender<StoreEvent>,
+    /// Watchers dropped for falling too far behind
+    lagged: AtomicU64,
+}
+
+impl StoreEvents {
+    /// Create a channel whose watchers may fall `capacity` events behind
+    pub(crate) fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            sender,
+            lagged: AtomicU64::new(0),
+        }
+    }
+
+    /// Create a channel sized by `EVENT_CHANNEL_CAPACITY`
+    pub(crate) fn from_env() -> Self {
+        let capacity = std::env::var(EVENT_CHANNEL_CAPACITY_VAR)
+            .ok()
+            .and_then(|capacity| capacity.parse().ok())
+            .unwrap_or(DEFAULT_EVENT_CHANNEL_CAPACITY);
+        Self::new(capacity)
+    }
+
+    /// Send an event to the current watchers, if any
+    pub(crate) fn publish(&self, kind: StoreEventKind, memory_id: &MemoryId, token_delta: i64) {
+        // Sending only fails when nobody is watching
+        let _ = self.sender.send(StoreEvent {
+            kind,
+            memory_id: memory_id.clone(),
+            token_delta,
+        });
+    }
+
+    /// Receive every event sent from now on
+    pub(crate) fn watch(&self) -> broadcast::Receiver<StoreEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Wait for a watcher's next event
+    ///
+    /// Returns `None` once the watcher has fallen behind, counting it as dropped,
+    /// or once the store is gone; the receiver should be dropped either way.
+    pub(crate) async fn next(
+        &self,
+        receiver: &mut broadcast::Receiver<StoreEvent>,
+    ) -> Option<StoreEvent> {
+        match receiver.recv().await {
+            Ok(event) => Some(event),
+            Err(RecvError::Lagged(_)) => {
+                self.lagged.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            Err(RecvError::Closed) => None,
+        }
+    }
+
+    /// Number of watchers dropped for falling too far behind
+    pub(crate) fn lagged(&self) -> u64 {
+        self.lagged.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lagging_watchers_are_dropped_and_counted() {
+        let events = StoreEvents::new(2);
+        let mut watcher = events.watch();
+        let id = MemoryId::from("mem_watched");
+
+        events.publish(StoreEventKind::Stored, &id, 10);
+        assert_eq!(
+            events.next(&mut watcher).await,
+            Some(StoreEvent {
+                kind: StoreEventKind::Stored,
+                memory_id: id.clone(),
+                token_delta: 10,
+            })
+        );
+        assert_eq!(events.lagged(), 0);
+
+        for _ in 0..3 {
+            events.publish(StoreEventKind::Updated, &id, 1);
+        }
+        assert_eq!(events.next(&mut watcher).await, None);
+        assert_eq!(events.lagged(), 1);
+    }
+}
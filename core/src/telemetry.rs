@@ -0,0 +1,116 @@
+//! Distributed tracing of gRPC calls
+//!
+//! Spans are exported over OTLP to `OTEL_EXPORTER_OTLP_ENDPOINT` under the
+//! service name in `OTEL_SERVICE_NAME`. Without an endpoint a no-op tracer is
+//! used, so tracing costs nothing unless it is configured.
+
+use opentelemetry::global::BoxedTracer;
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::noop::NoopTracer;
+use opentelemetry::{Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace as sdktrace, Resource};
+use std::io;
+use tonic::metadata::{KeyRef, MetadataMap};
+
+/// Environment variable holding the OTLP collector endpoint
+pub const OTEL_ENDPOINT_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Environment variable holding the service name reported with every span
+pub const OTEL_SERVICE_NAME_VAR: &str = "OTEL_SERVICE_NAME";
+
+/// Service name used when `OTEL_SERVICE_NAME` is not set
+const DEFAULT_SERVICE_NAME: &str = "smart-memory-mcp";
+
+/// Create the tracer configured by the environment
+///
+/// Must be called within a Tokio runtime, which exports the spans in batches.
+pub fn init_tracer() -> io::Result<BoxedTracer> {
+    let endpoint = match std::env::var(OTEL_ENDPOINT_VAR) {
+        Ok(endpoint) if !endpoint.is_empty() => endpoint,
+        _ => return Ok(noop_tracer()),
+    };
+    let service_name =
+        std::env::var(OTEL_SERVICE_NAME_VAR).unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            sdktrace::config()
+                .with_resource(Resource::new([KeyValue::new("service.name", service_name)])),
+        )
+        .install_batch(runtime::Tokio)
+        .map_err(io::Error::other)?;
+    Ok(BoxedTracer::new(Box::new(tracer)))
+}
+
+/// A tracer that records nothing
+pub fn noop_tracer() -> BoxedTracer {
+    BoxedTracer::new(Box::new(NoopTracer::new()))
+}
+
+/// Flush and stop the exporter started by [`init_tracer`]
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Reads W3C trace context headers from gRPC metadata
+struct MetadataExtractor<'a>(&'a MetadataMap);
+
+impl Extractor for MetadataExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .map(|key| match key {
+                KeyRef::Ascii(key) => key.as_str(),
+                KeyRef::Binary(key) => key.as_str(),
+            })
+            .collect()
+    }
+}
+
+/// Get the caller's trace context from the `traceparent` header of a request
+pub fn extract_context(metadata: &MetadataMap) -> Context {
+    TraceContextPropagator::new().extract(&MetadataExtractor(metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn test_extracts_traceparent() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+
+        let context = extract_context(&metadata);
+        let span_context = context.span().span_context().clone();
+        assert!(span_context.is_remote());
+        assert_eq!(
+            span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+    }
+
+    #[test]
+    fn test_missing_traceparent_has_no_parent() {
+        let context = extract_context(&MetadataMap::new());
+        assert!(!context.span().span_context().is_valid());
+    }
+}
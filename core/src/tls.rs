@@ -0,0 +1,393 @@
+//! Optional TLS and mutual TLS for the gRPC server and the REST bridge
+//!
+//! TLS is enabled when `TLS_CERT_PATH` is set, and requires `TLS_KEY_PATH`.
+//! Setting `TLS_CLIENT_CA_PATH` as well makes the server require client
+//! certificates signed by that CA.
+
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Arc;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{self, WebPkiSupportedAlgorithms};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, ServerConfig,
+    SignatureScheme,
+};
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+/// Environment variable holding the PEM server certificate path
+pub const TLS_CERT_PATH_VAR: &str = "TLS_CERT_PATH";
+
+/// Environment variable holding the PEM server private key path
+pub const TLS_KEY_PATH_VAR: &str = "TLS_KEY_PATH";
+
+/// Environment variable holding the PEM CA used to verify client certificates
+pub const TLS_CLIENT_CA_PATH_VAR: &str = "TLS_CLIENT_CA_PATH";
+
+/// Paths of the certificates and key the server uses for TLS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsPaths {
+    /// PEM certificate chain presented to clients
+    pub cert_path: PathBuf,
+    /// PEM private key of the certificate
+    pub key_path: PathBuf,
+    /// PEM CA certificate verifying client certificates, enabling mutual TLS
+    pub client_ca_path: Option<PathBuf>,
+}
+
+impl TlsPaths {
+    /// Read the TLS paths from the environment, or `None` when TLS is not configured
+    pub fn from_env() -> Result<Option<Self>> {
+        let cert_path = match env::var_os(TLS_CERT_PATH_VAR) {
+            Some(path) => PathBuf::from(path),
+            None => return Ok(None),
+        };
+        let key_path = match env::var_os(TLS_KEY_PATH_VAR) {
+            Some(path) => PathBuf::from(path),
+            None => bail!(
+                "{} is set but {} is not",
+                TLS_CERT_PATH_VAR,
+                TLS_KEY_PATH_VAR
+            ),
+        };
+
+        Ok(Some(Self {
+            cert_path,
+            key_path,
+            client_ca_path: env::var_os(TLS_CLIENT_CA_PATH_VAR).map(PathBuf::from),
+        }))
+    }
+
+    /// Pass these paths to a spawned server process
+    pub fn apply_to(&self, command: &mut Command) {
+        command
+            .env(TLS_CERT_PATH_VAR, &self.cert_path)
+            .env(TLS_KEY_PATH_VAR, &self.key_path);
+        match &self.client_ca_path {
+            Some(path) => command.env(TLS_CLIENT_CA_PATH_VAR, path),
+            None => command.env_remove(TLS_CLIENT_CA_PATH_VAR),
+        };
+    }
+
+    /// Read the PEM certificate, key and client CA
+    async fn read(&self) -> Result<(Vec<u8>, Vec<u8>, Option<Vec<u8>>)> {
+        let cert = tokio::fs::read(&self.cert_path).await.with_context(|| {
+            format!(
+                "Failed to read TLS certificate {}",
+                self.cert_path.display()
+            )
+        })?;
+        let key = tokio::fs::read(&self.key_path)
+            .await
+            .with_context(|| format!("Failed to read TLS key {}", self.key_path.display()))?;
+        let client_ca = match &self.client_ca_path {
+            Some(client_ca_path) => {
+                Some(tokio::fs::read(client_ca_path).await.with_context(|| {
+                    format!("Failed to read TLS client CA {}", client_ca_path.display())
+                })?)
+            }
+            None => None,
+        };
+
+        Ok((cert, key, client_ca))
+    }
+
+    /// Load the certificates and build the server TLS configuration
+    pub async fn load(&self) -> Result<ServerTlsConfig> {
+        let (cert, key, client_ca) = self.read().await?;
+
+        let mut config = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+        if let Some(client_ca) = client_ca {
+            config = config.client_ca_root(Certificate::from_pem(client_ca));
+        }
+
+        Ok(config)
+    }
+
+    /// Load the certificates and build the TLS configuration of the REST bridge
+    pub async fn load_rustls(&self) -> Result<Arc<ServerConfig>> {
+        let (cert, key, client_ca) = self.read().await?;
+        let (certs, key) = parse_identity(&cert, &key)?;
+
+        let builder = ServerConfig::builder();
+        let builder = match client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                for cert in rustls_pemfile::certs(&mut client_ca.as_slice()) {
+                    roots
+                        .add(cert.context("Invalid TLS client CA")?)
+                        .context("Invalid TLS client CA")?;
+                }
+                builder.with_client_cert_verifier(
+                    WebPkiClientVerifier::builder(Arc::new(roots))
+                        .build()
+                        .context("Invalid TLS client CA")?,
+                )
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder
+            .with_single_cert(certs, key)
+            .context("TLS key does not match the certificate")?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(Arc::new(config))
+    }
+
+    /// Load the certificates and build a client configuration trusting only this server
+    ///
+    /// Lets the server manager probe the servers it starts whatever host name
+    /// their certificate names. With mutual TLS the probe presents the
+    /// server's own certificate, so the client CA must have issued it too.
+    pub async fn load_probe_config(&self) -> Result<Arc<ClientConfig>> {
+        let (cert, key, _) = self.read().await?;
+        let (certs, key) = parse_identity(&cert, &key)?;
+        let server_cert = certs
+            .first()
+            .context("TLS certificate file holds no certificate")?
+            .clone();
+
+        let builder = ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinnedCertificate {
+                certificate: server_cert,
+                algorithms: crypto::ring::default_provider().signature_verification_algorithms,
+            }));
+        let mut config = if self.is_mutual() {
+            builder
+                .with_client_auth_cert(certs, key)
+                .context("TLS key does not match the certificate")?
+        } else {
+            builder.with_no_client_auth()
+        };
+        config.alpn_protocols = vec![b"h2".to_vec()];
+
+        Ok(Arc::new(config))
+    }
+
+    /// Whether clients must present a certificate
+    pub fn is_mutual(&self) -> bool {
+        self.client_ca_path.is_some()
+    }
+}
+
+/// Parse a PEM certificate chain and its private key
+fn parse_identity(
+    cert: &[u8],
+    key: &[u8],
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+    let certs = rustls_pemfile::certs(&mut &cert[..])
+        .collect::<Result<Vec<_>, _>>()
+        .context("Invalid TLS certificate")?;
+    let key = rustls_pemfile::private_key(&mut &key[..])
+        .context("Invalid TLS key")?
+        .context("TLS key file holds no private key")?;
+    Ok((certs, key))
+}
+
+/// Server certificate verifier accepting exactly one certificate
+#[derive(Debug)]
+struct PinnedCertificate {
+    certificate: CertificateDer<'static>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for PinnedCertificate {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        if end_entity.as_ref() == self.certificate.as_ref() {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(tokio_rustls::rustls::Error::InvalidCertificate(
+                CertificateError::UnknownIssuer,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::health_check_client::HealthCheckClient;
+    use crate::proto::HealthCheckRequest;
+    use crate::service::create_health_service;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use std::path::Path;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::transport::{Channel, ClientTlsConfig, Server};
+
+    /// A self-signed CA with a `localhost` server certificate and a client certificate
+    struct TestPki {
+        dir: tempfile::TempDir,
+        ca_pem: String,
+        client_identity: Identity,
+    }
+
+    impl TestPki {
+        fn generate() -> Self {
+            let ca_key = KeyPair::generate().unwrap();
+            let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = ca_params.self_signed(&ca_key).unwrap();
+
+            let sign = |names: Vec<String>| {
+                let key = KeyPair::generate().unwrap();
+                let cert = CertificateParams::new(names)
+                    .unwrap()
+                    .signed_by(&key, &ca, &ca_key)
+                    .unwrap();
+                (cert.pem(), key.serialize_pem())
+            };
+            let (server_cert, server_key) = sign(vec!["localhost".to_string()]);
+            let (client_cert, client_key) = sign(vec!["client".to_string()]);
+
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::write(dir.path().join("server.pem"), server_cert).unwrap();
+            std::fs::write(dir.path().join("server.key"), server_key).unwrap();
+            std::fs::write(dir.path().join("ca.pem"), ca.pem()).unwrap();
+
+            Self {
+                dir,
+                ca_pem: ca.pem(),
+                client_identity: Identity::from_pem(client_cert, client_key),
+            }
+        }
+
+        fn paths(&self, mutual: bool) -> TlsPaths {
+            let path = |name: &str| self.dir.path().join(name);
+            TlsPaths {
+                cert_path: path("server.pem"),
+                key_path: path("server.key"),
+                client_ca_path: mutual.then(|| path("ca.pem")),
+            }
+        }
+    }
+
+    /// Serve the health service with `paths` on a free port, returning the port
+    async fn serve(paths: &TlsPaths) -> u16 {
+        let tls_config = paths.load().await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = Server::builder()
+            .tls_config(tls_config)
+            .unwrap()
+            .add_service(create_health_service(None));
+        tokio::spawn(server.serve_with_incoming(TcpListenerStream::new(listener)));
+        port
+    }
+
+    /// Call the health check over TLS, optionally presenting the client certificate
+    async fn check(pki: &TestPki, port: u16, identity: Option<&Identity>) -> Result<()> {
+        let mut tls = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(&pki.ca_pem))
+            .domain_name("localhost");
+        if let Some(identity) = identity {
+            tls = tls.identity(identity.clone());
+        }
+        let channel = Channel::from_shared(format!("https://127.0.0.1:{}", port))?
+            .tls_config(tls)?
+            .connect()
+            .await?;
+        HealthCheckClient::new(channel)
+            .check(HealthCheckRequest::default())
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tls_server_accepts_trusting_clients() {
+        let pki = TestPki::generate();
+        let port = serve(&pki.paths(false)).await;
+
+        check(&pki, port, None).await.unwrap();
+
+        // Plain HTTP/2 clients cannot talk to a TLS server
+        let plain = Channel::from_shared(format!("http://127.0.0.1:{}", port))
+            .unwrap()
+            .connect()
+            .await;
+        if let Ok(channel) = plain {
+            assert!(HealthCheckClient::new(channel)
+                .check(HealthCheckRequest::default())
+                .await
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_requires_client_certificate() {
+        let pki = TestPki::generate();
+        let paths = pki.paths(true);
+        assert!(paths.is_mutual());
+        let port = serve(&paths).await;
+
+        check(&pki, port, Some(&pki.client_identity)).await.unwrap();
+        assert!(check(&pki, port, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_certificate_fails_to_load() {
+        let paths = TlsPaths {
+            cert_path: Path::new("/nonexistent/server.pem").to_path_buf(),
+            key_path: Path::new("/nonexistent/server.key").to_path_buf(),
+            client_ca_path: None,
+        };
+        assert!(paths.load().await.is_err());
+    }
+
+    #[test]
+    fn test_apply_to_sets_environment() {
+        let paths = TlsPaths {
+            cert_path: PathBuf::from("/certs/server.pem"),
+            key_path: PathBuf::from("/certs/server.key"),
+            client_ca_path: Some(PathBuf::from("/certs/ca.pem")),
+        };
+        let mut command = Command::new("true");
+        paths.apply_to(&mut command);
+
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&(
+            TLS_CERT_PATH_VAR.as_ref(),
+            Some(Path::new("/certs/server.pem").as_os_str())
+        )));
+        assert!(envs.contains(&(
+            TLS_CLIENT_CA_PATH_VAR.as_ref(),
+            Some(Path::new("/certs/ca.pem").as_os_str())
+        )));
+    }
+}
@@ -1,10 +1,37 @@
 use crate::logging::LogLevel;
 use crate::{log_error, log_info, log_warning};
 use serde::{Deserialize, Serialize};
+use std::env;
 use std::fmt;
 use std::fs::File;
 use std::io::{self, Read};
 use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Environment variable overriding the release endpoint checked for updates
+pub const VERSION_CHECK_URL_VAR: &str = "VERSION_CHECK_URL";
+
+/// Release endpoint checked for updates by default
+pub const DEFAULT_VERSION_CHECK_URL: &str =
+    "https://api.github.com/repos/shipdocs/smart-memory-mcp-v2/releases/latest";
+
+/// How long to wait for the release endpoint
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the result of an update check is reused
+const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Release returned by the release endpoint
+#[derive(Debug, Deserialize)]
+struct Release {
+    /// Tag of the release, e.g. "v1.2.3"
+    tag_name: String,
+}
+
+/// Release endpoint from [`VERSION_CHECK_URL_VAR`], or the default one
+fn version_check_url() -> String {
+    env::var(VERSION_CHECK_URL_VAR).unwrap_or_else(|_| DEFAULT_VERSION_CHECK_URL.to_string())
+}
 
 /// Version information
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -139,11 +166,52 @@ impl Version {
             .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid version format"))
     }
 
-    /// Check for updates
-    pub fn check_for_updates(&self) -> io::Result<Option<Self>> {
-        // In a real implementation, this would check a remote server for updates
-        // For now, we'll just return None to indicate no updates are available
-        Ok(None)
+    /// Check the release endpoint for the latest version
+    ///
+    /// Failures are logged and reported as no version being available.
+    pub async fn check_for_updates(&self) -> io::Result<Option<Self>> {
+        Ok(Self::fetch_latest(&version_check_url()).await)
+    }
+
+    /// Fetch the latest version from a release endpoint, logging failures
+    async fn fetch_latest(url: &str) -> Option<Self> {
+        match Self::request_latest(url).await {
+            Ok(version) => Some(version),
+            Err(e) => {
+                log_warning!(
+                    "version",
+                    &format!("Failed to check {} for updates: {}", url, e)
+                );
+                None
+            }
+        }
+    }
+
+    /// Request the latest release from a release endpoint and parse its tag
+    async fn request_latest(url: &str) -> io::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(UPDATE_CHECK_TIMEOUT)
+            .user_agent(concat!("smart-memory-mcp/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(io::Error::other)?;
+
+        let release = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(io::Error::other)?
+            .json::<Release>()
+            .await
+            .map_err(io::Error::other)?;
+
+        let tag = release.tag_name.trim();
+        Self::parse(tag.strip_prefix('v').unwrap_or(tag)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid release tag: {}", release.tag_name),
+            )
+        })
     }
 }
 
@@ -171,6 +239,10 @@ pub struct VersionManager {
     latest_version: Option<Version>,
     /// Update available
     update_available: bool,
+    /// Release endpoint checked for updates
+    update_url: String,
+    /// When updates were last checked
+    last_checked: Option<Instant>,
 }
 
 impl VersionManager {
@@ -182,11 +254,27 @@ impl VersionManager {
             current_version,
             latest_version: None,
             update_available: false,
+            update_url: version_check_url(),
+            last_checked: None,
         }
     }
 
-    /// Check for updates
-    pub fn check_for_updates(&mut self) -> io::Result<bool> {
+    /// Use a different release endpoint for update checks
+    pub fn with_update_url(mut self, update_url: impl Into<String>) -> Self {
+        self.update_url = update_url.into();
+        self
+    }
+
+    /// Check the release endpoint for updates
+    ///
+    /// The result is reused for an hour after a check.
+    pub async fn check_for_updates_async(&mut self) -> io::Result<bool> {
+        if let Some(last_checked) = self.last_checked {
+            if last_checked.elapsed() < UPDATE_CHECK_INTERVAL {
+                return Ok(self.update_available);
+            }
+        }
+
         log_info!(
             "version",
             &format!(
@@ -195,7 +283,9 @@ impl VersionManager {
             )
         );
 
-        match self.current_version.check_for_updates()? {
+        self.last_checked = Some(Instant::now());
+
+        match Version::fetch_latest(&self.update_url).await {
             Some(latest_version) => {
                 self.latest_version = Some(latest_version.clone());
                 self.update_available = latest_version.is_greater_than(&self.current_version);
@@ -233,8 +323,52 @@ impl VersionManager {
 }
 
 /// Initialize the version manager
+///
+/// Updates are checked separately with [`VersionManager::check_for_updates_async`].
 pub fn init() -> io::Result<VersionManager> {
-    let mut manager = VersionManager::new();
-    manager.check_for_updates()?;
-    Ok(manager)
+    Ok(VersionManager::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_check_for_updates_finds_newer_release() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/releases/latest")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"tag_name": "v999.0.0"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut manager =
+            VersionManager::new().with_update_url(format!("{}/releases/latest", server.url()));
+
+        assert!(manager.check_for_updates_async().await.unwrap());
+        assert_eq!(manager.get_latest_version(), Some(&Version::new(999, 0, 0)));
+
+        // The second check within the hour is answered from the cache
+        assert!(manager.check_for_updates_async().await.unwrap());
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_check_for_updates_ignores_failures() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/releases/latest")
+            .with_status(500)
+            .create_async()
+            .await;
+
+        let mut manager =
+            VersionManager::new().with_update_url(format!("{}/releases/latest", server.url()));
+
+        assert!(!manager.check_for_updates_async().await.unwrap());
+        assert_eq!(manager.get_latest_version(), None);
+        mock.assert_async().await;
+    }
 }